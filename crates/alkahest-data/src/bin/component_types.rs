@@ -1,7 +1,7 @@
 use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use alkahest_data::entity::SEntity;
-use alkahest_pm::{package_manager, PACKAGE_MANAGER};
+use alkahest_pm::{package_manager, set_package_manager};
 use destiny_pkg::{GameVersion, PackageManager, TagHash};
 use lazy_static::lazy_static;
 use tiger_parse::PackageManagerExt;
@@ -72,7 +72,7 @@ fn get_type_name(tag: u32) -> Cow<'static, str> {
 
 fn main() -> anyhow::Result<()> {
     let packages_dir = std::env::args().nth(1).expect("missing packages dir");
-    *PACKAGE_MANAGER.write() = Some(Arc::new(PackageManager::new(
+    set_package_manager(Arc::new(PackageManager::new(
         packages_dir,
         GameVersion::Destiny2TheFinalShape,
         None,
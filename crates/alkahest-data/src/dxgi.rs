@@ -153,16 +153,16 @@ impl From<DxgiFormat> for u32 {
     }
 }
 
-// impl TryFrom<u32> for DxgiFormat {
-//     type Error = anyhow::Error;
+impl TryFrom<u32> for DxgiFormat {
+    type Error = anyhow::Error;
 
-//     fn try_from(value: u32) -> Result<Self, Self::Error> {
-//         Ok(match value {
-//             0..=115 | 130..=132 => unsafe { transmute(value) },
-//             e => return Err(anyhow::anyhow!("DXGI format is out of range ({e})")),
-//         })
-//     }
-// }
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0..=115 | 130..=132 => unsafe { transmute(value) },
+            e => return Err(anyhow::anyhow!("DXGI format is out of range ({e})")),
+        })
+    }
+}
 
 #[allow(unused)]
 impl DxgiFormat {
@@ -123,3 +123,35 @@ pub struct Unk80809d02 {
 pub struct Unk8080894d {
     pub name: Pointer<NullString>,
 }
+
+/// Bone hierarchy of an entity, stored in one of its entity resources
+#[derive(Debug, Clone)]
+#[tiger_tag(id = 0xffffffff)]
+pub struct SSkeleton {
+    pub nodes: Vec<SSkeletonNode>,
+    pub default_object_space_transforms: Vec<SSkeletonTransform>,
+    pub default_inverse_object_space_transforms: Vec<SSkeletonTransform>,
+}
+
+impl SSkeleton {
+    /// Resource type of entity resources containing a skeleton
+    pub const RESOURCE_TYPE: u32 = 0x808081DE;
+}
+
+#[derive(Debug, Clone)]
+#[tiger_tag(id = 0x808081E1)]
+pub struct SSkeletonNode {
+    pub name_hash: FnvHash,
+    /// -1 for root nodes
+    pub parent_index: i32,
+    pub first_child_index: i32,
+    pub next_sibling_index: i32,
+}
+
+#[derive(Debug, Clone)]
+#[tiger_tag(id = 0x808081E0)]
+pub struct SSkeletonTransform {
+    pub rotation: glam::Quat,
+    pub translation: glam::Vec3,
+    pub scale: f32,
+}
@@ -82,6 +82,21 @@ impl ELodCategory {
         }
     }
 
+    /// Detail level of the category, starting at 0 for the highest detail. Categories with the
+    /// same level are drawn together
+    pub fn level(&self) -> u8 {
+        match self {
+            ELodCategory::Lod_0_0
+            | ELodCategory::Lod_0_1
+            | ELodCategory::Lod_0_2
+            | ELodCategory::Lod_0_3
+            | ELodCategory::Lod_Detail => 0,
+            ELodCategory::Lod_1_0 => 1,
+            ELodCategory::Lod_2_0 | ELodCategory::Lod_2_1 => 2,
+            ELodCategory::Lod_3_0 => 3,
+        }
+    }
+
     pub fn is_highest_detail(&self) -> bool {
         matches!(
             self,
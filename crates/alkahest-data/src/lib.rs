@@ -18,5 +18,6 @@ pub mod text;
 pub mod texture;
 pub mod tfx;
 pub mod unknown;
+pub mod version;
 
 pub use tag::{Tag, WideHash, WideTag};
@@ -1,11 +1,14 @@
+use std::io::{Read, Seek};
+
 use destiny_pkg::TagHash;
 use glam::{Mat4, Quat, Vec4};
-use tiger_parse::{tiger_tag, Pointer, ResourcePointer};
+use tiger_parse::{tiger_tag, NullString, Pointer, ResourcePointer, TigerReadable};
 
 use crate::{
     common::ResourceHash,
     occlusion::{Aabb, SObjectOcclusionBounds, SOcclusionBounds},
     statics::SStaticMeshInstances,
+    version::{struct_layout, StructLayout},
     Tag, WideHash, WideTag,
 };
 
@@ -89,6 +92,17 @@ pub struct SUnk80809885 {
     pub unk80: [u32; 4],
 }
 
+impl SUnk80809885 {
+    /// Entity placed by this entry. Before Lightfall only the 32-bit hash was used
+    pub fn entity_hash(&self) -> TagHash {
+        if struct_layout() < StructLayout::Lightfall && self.entity_old.is_some() {
+            self.entity_old
+        } else {
+            self.entity.hash32()
+        }
+    }
+}
+
 #[derive(Debug)]
 #[tiger_tag(id = 0x80806A0D)]
 pub struct SUnk80806ef4 {
@@ -193,14 +207,66 @@ pub struct SCubemapVolume {
     pub unk190: Vec4,
     pub unk1a0: [u32; 3],
 
-    // TODO(cohae): Removed in TFS, apply versioning to this field
-    // pub cubemap_name: Pointer<NullString>,
     pub cubemap_texture: TagHash,      // 0x1ac
     pub _unk_cubemap_skymask: TagHash, // 0x1b0
     pub voxel_ibl_texture: TagHash,    // 0x1b4
     pub unk1c4: [u32; 3],
 }
 
+/// Cubemap volume resource from before The Final Shape, which still had a name
+#[derive(Clone, Debug)]
+#[tiger_tag(id = 0xffffffff)]
+pub struct SCubemapVolumePreTfs {
+    #[tag(offset = 0x20)]
+    pub cubemap_extents: Vec4,
+    pub cubemap_center: Vec4,
+
+    #[tag(offset = 0x1b0)]
+    pub cubemap_name: Pointer<NullString>,
+    pub cubemap_texture: TagHash,      // 0x1b8
+    pub _unk_cubemap_skymask: TagHash, // 0x1bc
+    pub voxel_ibl_texture: TagHash,    // 0x1c0
+}
+
+/// The parts of a cubemap volume that are shared between all layouts
+#[derive(Clone, Debug)]
+pub struct CubemapVolumeInfo {
+    pub extents: Vec4,
+    pub cubemap_texture: TagHash,
+    pub voxel_ibl_texture: TagHash,
+    /// Only available before The Final Shape
+    pub name: Option<String>,
+}
+
+impl CubemapVolumeInfo {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> tiger_parse::Result<Self> {
+        Self::read_with_layout(reader, struct_layout())
+    }
+
+    fn read_with_layout<R: Read + Seek>(
+        reader: &mut R,
+        layout: StructLayout,
+    ) -> tiger_parse::Result<Self> {
+        if layout < StructLayout::TheFinalShape {
+            let volume = SCubemapVolumePreTfs::read_ds(reader)?;
+            Ok(Self {
+                extents: volume.cubemap_extents,
+                cubemap_texture: volume.cubemap_texture,
+                voxel_ibl_texture: volume.voxel_ibl_texture,
+                name: Some(volume.cubemap_name.to_string()),
+            })
+        } else {
+            let volume = SCubemapVolume::read_ds(reader)?;
+            Ok(Self {
+                extents: volume.cubemap_extents,
+                cubemap_texture: volume.cubemap_texture,
+                voxel_ibl_texture: volume.voxel_ibl_texture,
+                name: None,
+            })
+        }
+    }
+}
+
 /// Decal collection resource
 #[derive(Clone, Debug)]
 #[tiger_tag(id = 0x8080695B)]
@@ -606,9 +672,10 @@ pub struct SUnk80808246 {
     pub unk0: [u32; 4],
     pub unk10: Tag<SUnk80808248>,
 }
+/// Resource 0x80806ac2. Marks the surfaces of a havok shape as slippery
 #[derive(Clone, Debug)]
 #[tiger_tag(id = 0xffffffff)]
-pub struct SUnk80806ac2 {
+pub struct SSlipSurfaceVolume {
     pub unk0: [u32; 4],
     pub unk10: Tag<SUnk80806ac4>,
     pub array_index: u32,
@@ -688,3 +755,37 @@ pub struct SAmbientOcclusionOffsetMapping {
     pub unkc: u32,
     pub unk10: [u32; 4],
 }
+
+#[test]
+fn test_cubemap_volume_layouts() {
+    fn write_hash(data: &mut [u8], offset: usize, hash: u32) {
+        data[offset..offset + 4].copy_from_slice(&hash.to_le_bytes());
+    }
+
+    let mut tfs = vec![0u8; 0x200];
+    write_hash(&mut tfs, 0x1ac, 0x80801111);
+    write_hash(&mut tfs, 0x1b4, 0x80802222);
+    let volume = CubemapVolumeInfo::read_with_layout(
+        &mut std::io::Cursor::new(&tfs),
+        StructLayout::TheFinalShape,
+    )
+    .unwrap();
+    assert_eq!(volume.cubemap_texture, TagHash(0x80801111));
+    assert_eq!(volume.voxel_ibl_texture, TagHash(0x80802222));
+    assert!(volume.name.is_none());
+
+    // The name pointer shifts the textures back by 12 bytes. It points into the zeroed padding
+    // after the struct, so the name is empty
+    let mut pre_tfs = vec![0u8; 0x200];
+    pre_tfs[0x1b0..0x1b8].copy_from_slice(&0x20u64.to_le_bytes());
+    write_hash(&mut pre_tfs, 0x1b8, 0x80801111);
+    write_hash(&mut pre_tfs, 0x1c0, 0x80802222);
+    let volume = CubemapVolumeInfo::read_with_layout(
+        &mut std::io::Cursor::new(&pre_tfs),
+        StructLayout::Lightfall,
+    )
+    .unwrap();
+    assert_eq!(volume.cubemap_texture, TagHash(0x80801111));
+    assert_eq!(volume.voxel_ibl_texture, TagHash(0x80802222));
+    assert_eq!(volume.name.as_deref(), Some(""));
+}
@@ -193,7 +193,7 @@ impl TigerReadable for TfxFeatureRenderer {
 
 // TODO(cohae): Duplicate struct, used in TFX bytecode in the renderer
 #[repr(u8)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TfxShaderStage {
     Pixel = 1,
     Vertex = 2,
@@ -0,0 +1,52 @@
+//! Struct layouts differ between seasons. Structs that changed have a definition per layout, the
+//! one matching the game version the package manager was created with is read.
+//!
+//! This is not a full compatibility layer. The only structs that are versioned so far are:
+//! - `SUnk80809885` (map data entries): the 32-bit entity hash used before Lightfall
+//! - `SCubemapVolume` (cubemap volumes): the name field that was removed in The Final Shape
+//!
+//! Everything else is read with the layout of The Final Shape. Which other structs differ in
+//! older seasons hasn't been worked out, so maps from older seasons can still fail to load.
+
+use alkahest_pm::package_manager_version;
+use destiny_pkg::GameVersion;
+
+/// Revisions of the struct layouts, in release order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StructLayout {
+    /// Shadowkeep up to and including The Witch Queen
+    PreLightfall,
+    Lightfall,
+    TheFinalShape,
+}
+
+impl From<GameVersion> for StructLayout {
+    fn from(version: GameVersion) -> Self {
+        match version {
+            GameVersion::Destiny2TheFinalShape => StructLayout::TheFinalShape,
+            GameVersion::Destiny2Lightfall => StructLayout::Lightfall,
+            _ => StructLayout::PreLightfall,
+        }
+    }
+}
+
+/// Layout of the loaded packages, the latest layout if no packages are loaded yet
+pub fn struct_layout() -> StructLayout {
+    package_manager_version().map_or(StructLayout::TheFinalShape, StructLayout::from)
+}
+
+#[test]
+fn test_struct_layout_of_version() {
+    assert_eq!(
+        StructLayout::from(GameVersion::Destiny2WitchQueen),
+        StructLayout::PreLightfall
+    );
+    assert_eq!(
+        StructLayout::from(GameVersion::Destiny2Lightfall),
+        StructLayout::Lightfall
+    );
+    assert_eq!(
+        StructLayout::from(GameVersion::Destiny2TheFinalShape),
+        StructLayout::TheFinalShape
+    );
+}
@@ -1,6 +1,8 @@
 // TODO(cohae): Remove this once PanicHookInfo becomes stable (and update MSRV)
 #![allow(deprecated)]
 
+mod report;
+
 use std::{
     backtrace::{Backtrace, BacktraceStatus},
     io::Write,
@@ -13,6 +15,7 @@ use std::{
 use breakpad_handler::BreakpadHandler;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
+pub use report::{add_report_file, add_report_log};
 
 lazy_static! {
     static ref PANIC_FILE: Arc<Mutex<Option<fs_err::File>>> = Arc::new(Mutex::new(None));
@@ -24,6 +27,8 @@ lazy_static! {
 }
 
 pub fn install_hook(header: Option<String>) {
+    report::SESSION_START.set(SystemTime::now()).ok();
+
     std::panic::set_hook(Box::new(|info| {
         let _guard = PANIC_LOCK.lock();
         let this_thread = std::thread::current();
@@ -48,17 +53,13 @@ pub fn install_hook(header: Option<String>) {
         if !cfg!(debug_assertions) {
             // Finally, show a dialog
             let panic_message_stripped = strip_ansi_codes(&format!("{info}"));
-            if let Err(e) = native_dialog::MessageDialog::new()
-                .set_type(native_dialog::MessageType::Error)
-                .set_title("Alkahest crashed!")
-                .set_text(&format!(
+            report::offer_report(
+                "Alkahest crashed!",
+                &format!(
                     "{}\n\nA full crash log has been written to panic.log",
                     panic_message_stripped
-                ))
-                .show_alert()
-            {
-                eprintln!("Failed to show error dialog: {e}")
-            }
+                ),
+            );
         }
 
         // Make sure the application exits
@@ -118,18 +119,14 @@ fn install_breakpad() {
         breakpad_handler::InstallOptions::BothHandlers,
         Box::new(|path: PathBuf| {
             eprintln!("Crash dump written to: {}", path.display());
-            if let Err(e) = native_dialog::MessageDialog::new()
-                .set_type(native_dialog::MessageType::Error)
-                .set_title("Alkahest crashed!")
-                .set_text(&format!(
+            report::offer_report(
+                "Alkahest crashed!",
+                &format!(
                     "Alkahest encountered an unrecoverable error and must close.\n\nA crash dump \
-                     has been written to:\n{}\nPlease report this issue to the developers.",
+                     has been written to:\n{}",
                     path.display()
-                ))
-                .show_alert()
-            {
-                eprintln!("Failed to show error dialog: {e}")
-            }
+                ),
+            );
         }),
     )
     .expect("Failed to install breakpad handler");
@@ -0,0 +1,280 @@
+//! Crash reports: the panic log, crash dumps, config and recent log of the session collected into
+//! a single zip, along with a pre-filled GitHub issue to attach it to
+
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+const ISSUE_URL: &str = "https://github.com/cohaereo/alkahest/issues/new";
+/// Only the end of log files is included, that's where the lead-up to the crash is
+const LOG_TAIL_BYTES: u64 = 4 * 1024 * 1024;
+/// Browsers and GitHub reject overly long URLs, the full message is in the report anyways
+const ISSUE_MESSAGE_MAX_LEN: usize = 1500;
+
+struct ReportFile {
+    path: PathBuf,
+    is_log: bool,
+}
+
+lazy_static! {
+    static ref REPORT_FILES: Mutex<Vec<ReportFile>> = Mutex::new(vec![]);
+    static ref SECRET_PATTERN: regex::Regex =
+        regex::Regex::new(r"(?im)^(\s*[\w-]*(?:api_key|token|password|secret)[\w-]*\s*:).*$")
+            .unwrap();
+}
+
+pub(crate) static SESSION_START: OnceLock<SystemTime> = OnceLock::new();
+
+/// Adds a file to crash reports, such as the config. Values of keys that look like secrets are
+/// redacted
+pub fn add_report_file(path: impl Into<PathBuf>) {
+    REPORT_FILES.lock().push(ReportFile {
+        path: path.into(),
+        is_log: false,
+    });
+}
+
+/// Adds a log file to crash reports, only the most recent part of it is included
+pub fn add_report_log(path: impl Into<PathBuf>) {
+    REPORT_FILES.lock().push(ReportFile {
+        path: path.into(),
+        is_log: true,
+    });
+}
+
+/// Asks the user whether to create a crash report. If they agree, the report is written to
+/// `crashes/`, revealed in the file explorer and a pre-filled issue is opened in the browser
+pub(crate) fn offer_report(title: &str, message: &str) {
+    let confirmed = native_dialog::MessageDialog::new()
+        .set_type(native_dialog::MessageType::Error)
+        .set_title(title)
+        .set_text(&format!(
+            "{message}\n\nWould you like to collect a crash report and open a GitHub issue for \
+             it?\nThe report contains the crash log, crash dumps, your config and the recent \
+             log. Nothing is uploaded until you attach it to the issue yourself."
+        ))
+        .show_confirm();
+
+    match confirmed {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            eprintln!("Failed to show error dialog: {e}");
+            return;
+        }
+    }
+
+    let report = match write_report(message) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Failed to write crash report: {e}");
+            native_dialog::MessageDialog::new()
+                .set_type(native_dialog::MessageType::Error)
+                .set_title(title)
+                .set_text(&format!("Failed to write the crash report: {e}"))
+                .show_alert()
+                .ok();
+            return;
+        }
+    };
+    eprintln!("Crash report written to {}", report.display());
+
+    reveal_in_explorer(&report);
+    open_url(&issue_url(message, &report));
+}
+
+/// Writes the report zip to `crashes/`, returning its path
+fn write_report(message: &str) -> std::io::Result<PathBuf> {
+    let session_start = SESSION_START
+        .get()
+        .copied()
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    std::fs::create_dir_all("crashes")?;
+    let path = PathBuf::from("crashes").join(format!("crash_report_{timestamp}.zip"));
+    let mut zip = zip::ZipWriter::new(fs_err::File::create(&path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut add = |name: &str, data: &[u8]| -> std::io::Result<()> {
+        zip.start_file(name, options)
+            .map_err(std::io::Error::other)?;
+        zip.write_all(data)
+    };
+
+    let mut summary = String::new();
+    if let Some(header) = crate::PANIC_HEADER.get() {
+        summary.push_str(header);
+    }
+    summary.push_str(&format!(
+        "Platform: {} {}\n\n{message}\n",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ));
+    add("summary.txt", summary.as_bytes())?;
+
+    let panic_log = Path::new("panic.log");
+    if is_newer(panic_log, session_start) {
+        add("panic.log", &std::fs::read(panic_log)?)?;
+    }
+
+    for dump in crash_dumps_since(session_start) {
+        let name = format!(
+            "crashes/{}",
+            dump.file_name().unwrap_or_default().to_string_lossy()
+        );
+        add(&name, &std::fs::read(&dump)?)?;
+    }
+
+    for file in REPORT_FILES.lock().iter() {
+        let Some(name) = file.path.file_name() else {
+            continue;
+        };
+
+        let data = if file.is_log {
+            read_tail(&file.path, LOG_TAIL_BYTES)
+        } else {
+            std::fs::read_to_string(&file.path)
+                .map(|text| {
+                    SECRET_PATTERN
+                        .replace_all(&text, "$1 <redacted>")
+                        .into_owned()
+                })
+                .map(String::into_bytes)
+        };
+
+        match data {
+            Ok(data) => add(&name.to_string_lossy(), &data)?,
+            Err(e) => eprintln!("Failed to add {} to crash report: {e}", file.path.display()),
+        }
+    }
+
+    zip.finish().map_err(std::io::Error::other)?;
+    Ok(path)
+}
+
+fn is_newer(path: &Path, time: SystemTime) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| t >= time)
+}
+
+fn crash_dumps_since(time: SystemTime) -> Vec<PathBuf> {
+    std::fs::read_dir("crashes")
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "dmp"))
+                .filter(|p| is_newer(p, time))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn read_tail(path: &Path, max_len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs_err::File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_len)))?;
+
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn issue_url(message: &str, report: &Path) -> String {
+    let first_line = message.lines().next().unwrap_or_default();
+    let title = format!("Crash: {}", truncate(first_line, 96));
+
+    let mut body = String::new();
+    if let Some(header) = crate::PANIC_HEADER.get() {
+        body.push_str(&format!("**Version**\n```\n{}```\n\n", header));
+    }
+    body.push_str(
+        "**What were you doing when Alkahest crashed?**\n<!-- Map, activity, the last few \
+         actions you took -->\n\n",
+    );
+    body.push_str(&format!(
+        "**Crash message**\n```\n{}\n```\n\n",
+        truncate(message, ISSUE_MESSAGE_MAX_LEN)
+    ));
+    body.push_str(&format!(
+        "**Crash report**\nPlease attach `{}` from the `crashes` folder to this issue\n",
+        report.file_name().unwrap_or_default().to_string_lossy()
+    ));
+
+    format!(
+        "{ISSUE_URL}?title={}&body={}",
+        percent_encode(&title),
+        percent_encode(&body)
+    )
+}
+
+fn truncate(s: &str, max_len: usize) -> &str {
+    match s.char_indices().nth(max_len) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
+
+fn open_url(url: &str) {
+    // Unlike `cmd /c start`, this doesn't trip over the ampersands in the URL
+    #[cfg(windows)]
+    let result = Command::new("rundll32")
+        .args(["url.dll,FileProtocolHandler", url])
+        .spawn();
+    #[cfg(not(windows))]
+    let result = Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(e) = result {
+        eprintln!("Failed to open the issue page: {e}");
+    }
+}
+
+fn reveal_in_explorer(path: &Path) {
+    // Explorer doesn't understand the verbatim paths returned by `canonicalize`
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    #[cfg(windows)]
+    let result = Command::new("explorer")
+        .arg(format!("/select,{}", path.display()))
+        .spawn();
+    #[cfg(not(windows))]
+    let result = Command::new("xdg-open")
+        .arg(path.parent().unwrap_or(&path))
+        .spawn();
+
+    if let Err(e) = result {
+        eprintln!("Failed to reveal the crash report: {e}");
+    }
+}
+
+#[test]
+fn test_percent_encode() {
+    assert_eq!(percent_encode("a b&c=d"), "a%20b%26c%3Dd");
+    assert_eq!(percent_encode("é"), "%C3%A9");
+    assert_eq!(truncate("abcdef", 3), "abc");
+}
@@ -1,11 +1,136 @@
-use std::sync::Arc;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
-use destiny_pkg::{PackageManager, TagHash};
+use anyhow::Context;
+use destiny_pkg::{GameVersion, PackageManager, TagHash};
 use lazy_static::lazy_static;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 lazy_static! {
-    pub static ref PACKAGE_MANAGER: RwLock<Option<Arc<PackageManager>>> = RwLock::new(None);
+    static ref PACKAGE_MANAGER: RwLock<Option<Arc<PackageManager>>> = RwLock::new(None);
+    /// Version of [`PACKAGE_MANAGER`], kept separately so it can be checked on every struct read
+    /// without going through the package manager
+    static ref PACKAGE_MANAGER_VERSION: RwLock<Option<GameVersion>> = RwLock::new(None);
+    static ref PACKAGE_SOURCES: RwLock<Vec<Arc<PackageSource>>> = RwLock::new(vec![]);
+}
+
+/// Index of the source [`PACKAGE_MANAGER`] was last set to
+static ACTIVE_PACKAGE_SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+/// A package directory that maps can be loaded from, such as the live install or an archived
+/// snapshot. The first source is the one the package manager was initialized with
+pub struct PackageSource {
+    pub name: String,
+    pub directory: PathBuf,
+    pub version: GameVersion,
+    /// Created the first time the source is used, reading the packages takes a while
+    package_manager: Mutex<Option<Arc<PackageManager>>>,
+}
+
+impl PackageSource {
+    /// Returns the package manager of the source, reading the packages if this is the first time
+    /// it's used. This can take several seconds
+    pub fn package_manager(&self) -> anyhow::Result<Arc<PackageManager>> {
+        let mut pm = self.package_manager.lock();
+        if let Some(pm) = pm.as_ref() {
+            return Ok(pm.clone());
+        }
+
+        let new_pm = Arc::new(
+            PackageManager::new(&self.directory, self.version, None).with_context(|| {
+                format!(
+                    "Failed to read the packages of source '{}' in {}",
+                    self.name,
+                    self.directory.display()
+                )
+            })?,
+        );
+        *pm = Some(new_pm.clone());
+        Ok(new_pm)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.package_manager.lock().is_some()
+    }
+}
+
+/// Registers the package manager the application was started with as the first package source,
+/// and makes it the global package manager
+pub fn set_primary_package_source(name: impl Into<String>, pm: Arc<PackageManager>) {
+    let source = Arc::new(PackageSource {
+        name: name.into(),
+        directory: pm.package_dir.clone(),
+        version: pm.version,
+        package_manager: Mutex::new(Some(pm.clone())),
+    });
+
+    let mut sources = PACKAGE_SOURCES.write();
+    sources.clear();
+    sources.push(source);
+    ACTIVE_PACKAGE_SOURCE.store(0, Ordering::Relaxed);
+    set_package_manager(pm);
+}
+
+/// Registers an additional package source, returning its index. Its packages aren't read until
+/// the source is activated
+pub fn add_package_source(
+    name: impl Into<String>,
+    directory: impl Into<PathBuf>,
+    version: GameVersion,
+) -> usize {
+    let mut sources = PACKAGE_SOURCES.write();
+    sources.push(Arc::new(PackageSource {
+        name: name.into(),
+        directory: directory.into(),
+        version,
+        package_manager: Mutex::new(None),
+    }));
+    sources.len() - 1
+}
+
+pub fn package_sources() -> Vec<Arc<PackageSource>> {
+    PACKAGE_SOURCES.read().clone()
+}
+
+pub fn package_source(index: usize) -> Option<Arc<PackageSource>> {
+    PACKAGE_SOURCES.read().get(index).cloned()
+}
+
+pub fn active_package_source() -> usize {
+    ACTIVE_PACKAGE_SOURCE.load(Ordering::Relaxed)
+}
+
+/// Replaces the global package manager with the one of the given source. The packages of the
+/// source have to be read first with [`PackageSource::package_manager`], which should be done off
+/// the UI thread. Anything read from the previous package manager (eg. loaded assets) has to be
+/// discarded by the caller, and nothing may be reading from it during the switch
+pub fn set_active_package_source(index: usize) -> anyhow::Result<()> {
+    let source = package_source(index).context("Package source does not exist")?;
+    let pm = source
+        .package_manager
+        .lock()
+        .clone()
+        .context("The packages of the source have not been read yet")?;
+
+    set_package_manager(pm);
+    ACTIVE_PACKAGE_SOURCE.store(index, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Replaces the global package manager, without registering it as a package source
+pub fn set_package_manager(pm: Arc<PackageManager>) {
+    *PACKAGE_MANAGER_VERSION.write() = Some(pm.version);
+    *PACKAGE_MANAGER.write() = Some(pm);
+}
+
+/// Game version the global package manager was created with
+pub fn package_manager_version() -> Option<GameVersion> {
+    *PACKAGE_MANAGER_VERSION.read()
 }
 
 pub fn package_manager_checked() -> anyhow::Result<Arc<PackageManager>> {
@@ -0,0 +1,77 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+#define MAX_CUBEMAP_VOLUMES 64
+
+cbuffer scope_alkahest_cubemap_influence : register(b0) {
+    float4x4 target_pixel_to_world;
+    uint volume_count;
+    float4x4 world_to_volume[MAX_CUBEMAP_VOLUMES];
+};
+
+Texture2D RtDepth : register(t0);
+
+float3 WorldPosFromDepth(float depth, float2 viewportPos) {
+    float4 clipSpacePos = float4(viewportPos, depth, 1.0);
+
+    float4 worldSpacePos = mul(target_pixel_to_world, clipSpacePos);
+    return worldSpacePos.xyz / worldSpacePos.w;
+}
+
+// Stable, reasonably distinct color for each volume index
+float3 VolumeColor(uint i) {
+    float hue = frac(i * 0.618034);
+    float3 rgb = saturate(abs(frac(hue + float3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0);
+    return lerp(0.15, 1.0, rgb);
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float depth = RtDepth.Load(int3(input.position.xy, 0)).x;
+    // Reverse-Z, sky
+    if (depth == 0.0) {
+        return float4(0, 0, 0, 1);
+    }
+
+    float3 worldPos = WorldPosFromDepth(depth, input.position.xy);
+
+    float3 color = 0.0;
+    float total_weight = 0.0;
+    float max_weight = 0.0;
+    uint overlap = 0;
+    for (uint i = 0; i < volume_count; i++) {
+        float4 p = mul(world_to_volume[i], float4(worldPos, 1.0));
+        float3 local = abs(p.xyz / p.w);
+        float edge = max(local.x, max(local.y, local.z));
+        if (edge > 1.0) {
+            continue;
+        }
+
+        // Influence falls off towards the edges of the volume
+        float weight = saturate((1.0 - edge) * 4.0) + 0.05;
+        color += VolumeColor(i) * weight;
+        total_weight += weight;
+        max_weight = max(max_weight, weight);
+        overlap++;
+    }
+
+    if (overlap == 0) {
+        // Not covered by any cubemap, falls back to the sky/global IBL
+        bool stripe = frac((input.position.x + input.position.y) / 16.0) < 0.5;
+        return float4(stripe ? float3(0.25, 0.0, 0.0) : float3(0.05, 0.05, 0.05), 1);
+    }
+
+    color = (color / total_weight) * max_weight;
+    // Darken pixels where multiple volumes overlap
+    if (overlap > 1) {
+        color *= lerp(1.0, 0.6, saturate((overlap - 1) / 3.0));
+    }
+
+    return float4(color, 1);
+}
+
+#endif
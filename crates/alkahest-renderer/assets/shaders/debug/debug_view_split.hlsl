@@ -0,0 +1,38 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_debug_view_split : register(b0) {
+    // Fraction of the screen width (from the left) that shows the debug view
+    float split;
+};
+
+Texture2D DebugView : register(t0);
+
+float3 ConvertColorSpace(float3 r0) {
+    r0 = log2(r0.xyz);
+    r0 = float3(0.454545468,0.454545468,0.454545468) * r0.xyz;
+    return exp2(r0.xyz);
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float split_x = split * target_width;
+    if (input.position.x > split_x + 1.0) {
+        // Leave the final image untouched
+        discard;
+    }
+
+    if (input.position.x > split_x - 1.0) {
+        return float4(1, 1, 1, 1);
+    }
+
+    // The final image is already gamma corrected, debug views are not
+    float3 debug_view = DebugView.Load(int3(input.position.xy, 0)).rgb;
+    return float4(ConvertColorSpace(max(debug_view, 0.0)), 1);
+}
+
+#endif
@@ -0,0 +1,33 @@
+// VSMain
+#include "screen_space.hlsli"
+#include "heatmap.hlsli"
+#include "light_clusters.hlsli"
+
+#ifdef STAGE_PS
+
+Texture2D<float> Depth : register(t0);
+StructuredBuffer<uint2> ClusterLights : register(t1);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float depth = Depth.Load(int3(input.position.xy, 0));
+    if (depth == 0.0) {
+        return float4(0, 0, 0, 1);
+    }
+
+    uint count = ClusterLights[ClusterIndex(input.position.xy, depth)].y;
+    if (count == 0) {
+        return float4(0.05, 0.05, 0.05, 1);
+    }
+
+    // Past the end of the scale
+    if (count > heatmap_max) {
+        return float4(1, 1, 1, 1);
+    }
+
+    float t = (count - 1) / max(float(heatmap_max) - 1.0, 1.0);
+    return float4(Heatmap(t), 1);
+}
+
+#endif
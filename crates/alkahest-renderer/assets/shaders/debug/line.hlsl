@@ -1,7 +1,7 @@
 #include "scopes/frame.hlsli"
 #include "scopes/view.hlsli"
 
-cbuffer scope_alk_debugline : register(b0) {
+struct DebugLineInstance {
     float4 lineStart;
     float4 lineEnd;
 
@@ -15,25 +15,36 @@ cbuffer scope_alk_debugline : register(b0) {
     float scrollSpeed;
 };
 
+cbuffer scope_alk_debugbatch : register(b0) {
+    uint instance_offset;
+};
+
+StructuredBuffer<DebugLineInstance> line_instances : register(t0);
+
 
 struct VSOutput {
     float4 position : SV_POSITION;
     float4 color : COLOR0;
     float normalizedPositionOnLine : TEXCOORD0;
     noperspective float2 aaUv : TEXCOORD1;
+    nointerpolation uint instance : INSTANCE;
 };
 
-VSOutput VSMain(uint vertex_id: SV_VertexID) {
+VSOutput VSMain(uint vertex_id: SV_VertexID, uint instance_id : SV_InstanceID) {
     VSOutput output;
 
+    uint instance = instance_offset + instance_id;
+    DebugLineInstance l = line_instances[instance];
+    output.instance = instance;
+
     if(vertex_id % 2 == 0){
-        output.position = mul(world_to_projective, lineStart);
-        output.color = colorStart;
+        output.position = mul(world_to_projective, l.lineStart);
+        output.color = l.colorStart;
         output.normalizedPositionOnLine = 0.0f;
         output.aaUv = float2(0, 0);
     } else{
-        output.position = mul(world_to_projective, lineEnd);
-        output.color = colorEnd;
+        output.position = mul(world_to_projective, l.lineEnd);
+        output.color = l.colorEnd;
         output.normalizedPositionOnLine = 1.0f;
         output.aaUv = float2(0, 0);
     }
@@ -69,7 +80,7 @@ void GSMain(line VSOutput input[2], inout TriangleStream<VSOutput> OutputStream
     float2 viewportLineVector = lineVector * target_resolution;
     float2 dir = normalize(float2( lineVector.x, lineVector.y * aspectRatio ));
 
-    float lineWidth = max( 1.0, width );
+    float lineWidth = max( 1.0, line_instances[p0.instance].width );
     float lineLength = length( viewportLineVector ) + 2.0;
 
     float2 normal = float2( -dir.y, dir.x );
@@ -77,6 +88,7 @@ void GSMain(line VSOutput input[2], inout TriangleStream<VSOutput> OutputStream
     float2 extensionNormal = float2( 0.0f, 0.0f );
 
     VSOutput output;
+    output.instance = p0.instance;
 
     output.position = float4((ndcA + normal - extensionNormal) * p0.position.w, p0.position.zw);
     output.normalizedPositionOnLine = p0.normalizedPositionOnLine;
@@ -111,15 +123,17 @@ void GSMain(line VSOutput input[2], inout TriangleStream<VSOutput> OutputStream
 // Pixel Shader
 float4 PSMain(VSOutput input) : SV_Target {
  	// float aa = exp2(-2.7 * input.aaUv.x * input.aaUv.x);
+    DebugLineInstance l = line_instances[input.instance];
+    float dotScale = l.dotScale;
 
     if(dotScale == 0.0f)
         return input.color;
 
-    float lineLength = length(lineEnd - lineStart);
+    float lineLength = length(l.lineEnd - l.lineStart);
     float progress = input.normalizedPositionOnLine * lineLength;
-    progress += dotScale * game_time * scrollSpeed;
+    progress += dotScale * game_time * l.scrollSpeed;
 
-    if((progress % (dotScale * LINE_LENGTH)) < (dotScale * LINE_LENGTH * lineRatio))
+    if((progress % (dotScale * LINE_LENGTH)) < (dotScale * LINE_LENGTH * l.lineRatio))
         return input.color;
     else
         discard;
@@ -0,0 +1,8 @@
+RWTexture2D<uint> OverdrawCount : register(u0);
+
+// Early depth testing keeps fragments that would have been rejected by the depth buffer from
+// being counted, like they would be in the actual pass
+[earlydepthstencil]
+void PSMain(float4 position : SV_Position) {
+    InterlockedAdd(OverdrawCount[uint2(position.xy)], 1);
+}
@@ -0,0 +1,33 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+#include "heatmap.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_overdraw : register(b0) {
+    // Fragment count shown as the hottest color
+    uint max_overdraw;
+};
+
+Texture2D<uint> OverdrawCount : register(t0);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    uint count = OverdrawCount.Load(int3(input.position.xy, 0));
+    // Nothing drawn
+    if (count == 0) {
+        return float4(0, 0, 0, 1);
+    }
+
+    // Past the end of the scale
+    if (count > max_overdraw) {
+        return float4(1, 1, 1, 1);
+    }
+
+    float t = (count - 1) / max(float(max_overdraw) - 1.0, 1.0);
+    return float4(Heatmap(t), 1);
+}
+
+#endif
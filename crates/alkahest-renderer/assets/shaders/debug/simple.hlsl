@@ -1,18 +1,27 @@
 #include "scopes/view.hlsli"
 
-cbuffer scope_alk_debugshape : register(b0) {
+struct DebugShapeInstance {
     float4x4 local_to_world;
     float4 color;
 };
 
+cbuffer scope_alk_debugbatch : register(b0) {
+    uint instance_offset;
+};
+
+StructuredBuffer<DebugShapeInstance> shape_instances : register(t0);
+
 struct VSOutput {
     float4 position : SV_POSITION;
+    nointerpolation float4 color : COLOR0;
 };
 
-VSOutput VSMain(float3 in_position : POSITION) {
+VSOutput VSMain(float3 in_position : POSITION, uint instance_id : SV_InstanceID) {
     VSOutput output;
 
-    output.position = mul(world_to_projective, mul(local_to_world, float4(in_position, 1.0)));
+    DebugShapeInstance instance = shape_instances[instance_offset + instance_id];
+    output.position = mul(world_to_projective, mul(instance.local_to_world, float4(in_position, 1.0)));
+    output.color = instance.color;
 
     return output;
 }
@@ -21,5 +30,5 @@ void PSMain(
     VSOutput input,
     out float4 rt0 : SV_Target0
 ) {
-    rt0 = color;
-}
\ No newline at end of file
+    rt0 = input.color;
+}
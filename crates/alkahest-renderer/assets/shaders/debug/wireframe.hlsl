@@ -0,0 +1,7 @@
+cbuffer cb_wireframe : register(b7) {
+    float4 wireframe_color;
+}
+
+float4 PSMain() : SV_Target0 {
+    return wireframe_color;
+}
@@ -3,12 +3,17 @@
 #include "dither.hlsli"
 
 cbuffer cb_outline : register(b0) {
+    float3 outline_color;
     float time_since_selection;
+    float outline_width;
+    // Pulses per second, 0 for a steady outline
+    float pulse_speed;
+    // Tint the whole silhouette instead of drawing an outline
+    uint fill_tint;
 };
 
-#define OUTLINE_COLOR float3(1.0, 0.6, 0.2) * 0.6
-#define OUTLINE_COLOR_BACK (OUTLINE_COLOR * 0.6)
-#define OUTLINE_WIDTH 2
+#define OUTLINE_COLOR_BACK (outline_color * 0.6)
+#define MAX_OUTLINE_WIDTH 8
 
 Texture2D DepthTargetOutline : register(t0);
 Texture2D DepthTargetScene : register(t1);
@@ -21,19 +26,42 @@ float2 QueryTexelSize(Texture2D t) {
 	return 1.0 / float2(width, height);
 }
 
+float PulseAlpha() {
+    if (pulse_speed <= 0.0)
+        return 1.0;
+
+    return lerp(0.45, 1.0, 0.5 + 0.5 * cos(time_since_selection * pulse_speed * 6.2831853));
+}
+
 // Pixel Shader
 float4 PSMain(VSOutput input) : SV_Target {
-    float depth = DepthTargetOutline.Sample(SampleType, input.uv).r;
+    float depth = DepthTargetOutline.SampleLevel(SampleType, input.uv, 0).r;
 
     // if the pixel isn't 0 (we are on the depth silhouette)
     if (depth != 0)
     {
         float timeNormMul = clamp(time_since_selection * 4.0, 0.0, 1.0);
+        float fillFlash = (1.0 - timeNormMul) * 0.20;
+        float pulse = PulseAlpha();
+        float depthScene = DepthTargetScene.SampleLevel(SampleType, input.uv, 0).r;
+        bool behindScene = depthScene > depth;
+
+        if (fill_tint != 0)
+        {
+            if (behindScene) {
+                dither_discard(input.screen_pos, 0.15);
+                return float4(OUTLINE_COLOR_BACK, (0.35 + fillFlash) * pulse);
+            }
+
+            return float4(outline_color, (0.45 + fillFlash) * pulse);
+        }
+
+        int width = clamp((int)outline_width, 1, MAX_OUTLINE_WIDTH);
         float2 size = QueryTexelSize(DepthTargetScene);
 
-        [unroll] for (int i = -OUTLINE_WIDTH; i <= +OUTLINE_WIDTH; i++)
+        [loop] for (int i = -width; i <= +width; i++)
         {
-            [unroll] for (int j = -OUTLINE_WIDTH; j <= +OUTLINE_WIDTH; j++)
+            [loop] for (int j = -width; j <= +width; j++)
             {
                 if (i == 0 && j == 0)
                 {
@@ -43,29 +71,24 @@ float4 PSMain(VSOutput input) : SV_Target {
                 float2 offset = float2(i, j) * size * (3 - timeNormMul * 2);
 
                 // and if one of the pixel-neighbor is black (we are on the border)
-                if (DepthTargetOutline.Sample(SampleType, input.uv + offset).r == 0)
+                if (DepthTargetOutline.SampleLevel(SampleType, input.uv + offset, 0).r == 0)
                 {
-                    float depthScene = DepthTargetScene.Sample(SampleType, input.uv).r;
-                    if(depthScene > depth) // Behind scene
-                        return float4(OUTLINE_COLOR, 0.65);
+                    if(behindScene)
+                        return float4(outline_color, 0.65 * pulse);
                     else // In front of scene
-                        return float4(OUTLINE_COLOR, 1);
+                        return float4(outline_color, pulse);
                 }
             }
         }
 
         // if we are on the silhouette but not on the border
-        float depthScene = DepthTargetScene.Sample(SampleType, input.uv).r;
-        float fillFlash = (1.0 - timeNormMul) * 0.20;
-        if(depthScene > depth) { // Behind scene
+        if(behindScene) {
             dither_discard(input.screen_pos, 0.15);
-//             return float4(OUTLINE_COLOR, 0.16 + fillFlash);
-            return float4(lerp(OUTLINE_COLOR, OUTLINE_COLOR_BACK, timeNormMul), 0.75 + fillFlash);
+            float3 color = lerp(outline_color, OUTLINE_COLOR_BACK, timeNormMul);
+            return float4(color, (0.75 + fillFlash) * pulse);
         }
-//         else // In front of scene
-//             return float4(OUTLINE_COLOR, 0.015 + fillFlash);
     }
 
     discard;
     return float4(0, 0, 0, 0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,14 @@
+// Blue -> cyan -> green -> yellow -> red
+float3 Heatmap(float t) {
+    const float3 colors[5] = {
+        float3(0.0, 0.0, 1.0),
+        float3(0.0, 1.0, 1.0),
+        float3(0.0, 1.0, 0.0),
+        float3(1.0, 1.0, 0.0),
+        float3(1.0, 0.0, 0.0)
+    };
+
+    float x = saturate(t) * 4.0;
+    uint i = min((uint)x, 3);
+    return lerp(colors[i], colors[i + 1], x - i);
+}
@@ -0,0 +1,29 @@
+// Froxel grid used for light culling, see renderer/light_occlusion.rs
+#define CLUSTERS_X 16
+#define CLUSTERS_Y 9
+#define CLUSTERS_Z 24
+#define CLUSTER_COUNT (CLUSTERS_X * CLUSTERS_Y * CLUSTERS_Z)
+
+cbuffer light_clusters : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4 camera_pos;
+    float4 camera_forward;
+    // x = target width, y = target height, z = depth slice scale, w = depth slice bias
+    float4 cluster_params;
+    // Light count shown as the hottest color in the debug view
+    uint heatmap_max;
+};
+
+// Index of the cluster containing the given pixel at the given (reverse-Z) depth
+uint ClusterIndex(float2 pixel, float depth) {
+    float4 world = mul(target_pixel_to_world, float4(pixel, depth, 1.0));
+    float view_depth = dot(world.xyz / world.w - camera_pos.xyz, camera_forward.xyz);
+
+    // Depth slices are distributed exponentially
+    float slice = floor(log(max(view_depth, 1e-4)) * cluster_params.z + cluster_params.w);
+    uint z = (uint)clamp(slice, 0.0, CLUSTERS_Z - 1);
+    uint x = min((uint)(pixel.x / cluster_params.x * CLUSTERS_X), CLUSTERS_X - 1);
+    uint y = min((uint)(pixel.y / cluster_params.y * CLUSTERS_Y), CLUSTERS_Y - 1);
+
+    return x + y * CLUSTERS_X + z * CLUSTERS_X * CLUSTERS_Y;
+}
@@ -0,0 +1,56 @@
+cbuffer scope_alkahest_tonemap : register(b0) {
+    // Linear scale applied to the scene before tonemapping (2^EV)
+    float exposure_scale;
+    uint tonemapper;
+    uint output_mode;
+    // Brightness of SDR white on HDR displays, in nits
+    float paper_white;
+    // Peak brightness of the display, in nits
+    float peak_luminance;
+    // Whether the source texture is linear, instead of gamma corrected
+    uint input_is_linear;
+};
+
+#define TONEMAPPER_FILM_CURVE 0
+#define TONEMAPPER_ACES 1
+#define TONEMAPPER_REINHARD 2
+#define TONEMAPPER_CLAMP 3
+
+#define OUTPUT_MODE_SDR 0
+#define OUTPUT_MODE_HDR10 1
+#define OUTPUT_MODE_SCRGB 2
+
+// Same 2.2 gamma as final_combine, extended to values above 1
+float3 GammaEncode(float3 c) {
+    return sign(c) * pow(abs(c), 1.0 / 2.2);
+}
+
+float3 GammaDecode(float3 c) {
+    return sign(c) * pow(abs(c), 2.2);
+}
+
+// Brightest value the tonemapper can output, relative to paper white
+float DisplayPeak() {
+    return output_mode == OUTPUT_MODE_SDR ? 1.0 : peak_luminance / paper_white;
+}
+
+float3 Rec709ToRec2020(float3 c) {
+    static const float3x3 m = {
+        0.627402, 0.329292, 0.043306,
+        0.069095, 0.919544, 0.011360,
+        0.016394, 0.088028, 0.895578
+    };
+    return mul(m, c);
+}
+
+// SMPTE ST 2084 inverse EOTF, for values normalized to 10000 nits
+float3 LinearToPQ(float3 c) {
+    const float m1 = 2610.0 / 16384.0;
+    const float m2 = 2523.0 / 4096.0 * 128.0;
+    const float c1 = 3424.0 / 4096.0;
+    const float c2 = 2413.0 / 4096.0 * 32.0;
+    const float c3 = 2392.0 / 4096.0 * 32.0;
+
+    float3 p = pow(saturate(c), m1);
+    return pow((c1 + c2 * p) / (1.0 + c3 * p), m2);
+}
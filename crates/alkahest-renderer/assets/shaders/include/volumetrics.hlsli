@@ -0,0 +1,20 @@
+#define MAX_SHAFT_LIGHTS 4
+
+cbuffer scope_alkahest_volumetrics : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4x4 light_world_to_projective[MAX_SHAFT_LIGHTS];
+    // World space position (xyz) and range (w) of every light
+    float4 light_position_range[MAX_SHAFT_LIGHTS];
+    float3 fog_color;
+    float density;
+    // Light shaft color, premultiplied by the intensity
+    float3 light_color;
+    // Henyey-Greenstein anisotropy, positive values scatter light forwards
+    float anisotropy;
+    float height_falloff;
+    float base_height;
+    float max_distance;
+    uint light_count;
+    uint step_count;
+    uint frame_index;
+};
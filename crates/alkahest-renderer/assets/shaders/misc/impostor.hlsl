@@ -0,0 +1,80 @@
+#include "scopes/view.hlsli"
+
+struct ImpostorInstance {
+    float3 center;
+    float radius;
+    // Rotation of the instance, applied to the captured normals
+    float4 rotation;
+    // Offset and size of the captured view in the atlas
+    float4 atlas_rect;
+};
+
+StructuredBuffer<ImpostorInstance> impostor_instances : register(t0);
+
+Texture2D AtlasRt0 : register(t0);
+Texture2D AtlasRt1 : register(t1);
+Texture2D AtlasRt2 : register(t2);
+Texture2D<float> AtlasDepth : register(t3);
+SamplerState PointSampler : register(s0);
+
+static const float2 QUAD_CORNERS[4] = {
+    float2(-1.0, -1.0),
+    float2(-1.0, 1.0),
+    float2(1.0, -1.0),
+    float2(1.0, 1.0)
+};
+
+struct VSOutput {
+    float4 position : SV_POSITION;
+    float2 texcoord : TEXCOORD0;
+    nointerpolation float4 rotation : TEXCOORD1;
+};
+
+VSOutput VSMain(uint vertex_id : SV_VertexID, uint instance_id : SV_InstanceID) {
+    VSOutput output;
+
+    ImpostorInstance instance = impostor_instances[instance_id];
+    float2 corner = QUAD_CORNERS[vertex_id];
+
+    // Billboards only turn around the up axis, like the views they were captured from
+    float3 to_camera = camera_position - instance.center;
+    float3 forward = normalize(float3(to_camera.xy, 0.0) + float3(1e-5, 0.0, 0.0));
+    float3 right = cross(float3(0.0, 0.0, 1.0), forward);
+
+    float3 offset = right * corner.x + float3(0.0, 0.0, corner.y);
+    float3 world_position = instance.center + offset * instance.radius;
+    output.position = mul(world_to_projective, float4(world_position, 1.0));
+
+    float2 cell_uv = corner * float2(0.5, -0.5) + 0.5;
+    output.texcoord = instance.atlas_rect.xy + cell_uv * instance.atlas_rect.zw;
+    output.rotation = instance.rotation;
+
+    return output;
+}
+
+float3 RotateVector(float3 v, float4 q) {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
+
+void PSMain(
+    VSOutput input,
+    out float4 rt0 : SV_Target0,
+    out float4 rt1 : SV_Target1,
+    out float4 rt2 : SV_Target2
+) {
+    // Texels the model didn't cover still have the cleared depth
+    if (AtlasDepth.SampleLevel(PointSampler, input.texcoord, 0) == 0.0)
+        discard;
+
+    rt0 = AtlasRt0.SampleLevel(PointSampler, input.texcoord, 0);
+
+    // The length of the normal holds the smoothness, keep it while rotating the normal
+    float4 packed_normal = AtlasRt1.SampleLevel(PointSampler, input.texcoord, 0);
+    float3 normal = packed_normal.xyz - 0.5;
+    float normal_length = max(length(normal), 1e-4);
+    normal = RotateVector(normal / normal_length, input.rotation);
+    rt1.xyz = saturate(normal * normal_length + 0.5);
+    rt1.w = packed_normal.w;
+
+    rt2 = AtlasRt2.SampleLevel(PointSampler, input.texcoord, 0);
+}
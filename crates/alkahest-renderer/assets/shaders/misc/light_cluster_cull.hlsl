@@ -0,0 +1,20 @@
+#include "light_clusters.hlsli"
+
+Buffer<uint> Occupancy : register(t0);
+// Offset into LightIndices and light count, per cluster
+StructuredBuffer<uint2> ClusterLights : register(t1);
+StructuredBuffer<uint> LightIndices : register(t2);
+// DrawIndexedInstancedIndirect arguments for every light, with an instance count of 0
+RWBuffer<uint> DrawArgs : register(u0);
+
+[numthreads(64, 1, 1)]
+void CSMain(uint3 id : SV_DispatchThreadID) {
+    if (id.x >= CLUSTER_COUNT || Occupancy[id.x] == 0)
+        return;
+
+    // Every light touching a cluster with visible geometry is drawn
+    uint2 list = ClusterLights[id.x];
+    for (uint i = 0; i < list.y; i++) {
+        DrawArgs[LightIndices[list.x + i] * 5 + 1] = 1;
+    }
+}
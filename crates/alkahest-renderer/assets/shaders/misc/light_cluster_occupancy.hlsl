@@ -0,0 +1,18 @@
+#include "light_clusters.hlsli"
+
+Texture2D<float> Depth : register(t0);
+// 1 for every cluster that contains visible geometry
+RWBuffer<uint> Occupancy : register(u0);
+
+[numthreads(8, 8, 1)]
+void CSMain(uint3 id : SV_DispatchThreadID) {
+    if (id.x >= (uint)cluster_params.x || id.y >= (uint)cluster_params.y)
+        return;
+
+    float depth = Depth.Load(int3(id.xy, 0));
+    // Nothing to light in the sky (reverse-Z)
+    if (depth == 0.0)
+        return;
+
+    Occupancy[ClusterIndex(float2(id.xy) + 0.5, depth)] = 1;
+}
@@ -0,0 +1,23 @@
+// VSMain
+#include "screen_space.hlsli"
+
+cbuffer lighting_only : register(b0) {
+    float4 albedo;
+};
+
+Texture2D<float> Depth : register(t0);
+
+void PSMain(
+    VSOutput input,
+    out float4 rt0 : SV_Target0,
+    out float4 rt2 : SV_Target1
+) {
+    // Leave the sky alone
+    if (Depth.Load(int3(input.position.xy, 0)) == 0.0)
+        discard;
+
+    rt0 = albedo;
+
+    // Only the emission channel is written, 0.5 being no emission
+    rt2 = float4(0.0, 0.5, 0.0, 0.0);
+}
@@ -0,0 +1,42 @@
+// VSMain
+#include "screen_space.hlsli"
+
+#define PI 3.14159265
+
+cbuffer sky_replace : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4 camera_pos;
+    float4 color;
+    // x = use the HDRI, y = exposure multiplier, z = rotation in radians, w = gamma correct
+    float4 params;
+};
+
+Texture2D Depth : register(t0);
+Texture2D Hdri : register(t1);
+SamplerState LinearSampler : register(s0);
+
+float3 LinearToSrgb(float3 c) {
+    return c <= 0.0031308 ? c * 12.92 : 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+}
+
+float4 PSMain(VSOutput input) : SV_Target0 {
+    // Only the background is replaced (reverse-Z)
+    if (Depth.Load(int3(input.position.xy, 0)).x != 0.0)
+        discard;
+
+    float3 result = color.rgb;
+    if (params.x != 0.0) {
+        float4 nearPos = mul(target_pixel_to_world, float4(input.position.xy, 1.0, 1.0));
+        float3 dir = normalize(nearPos.xyz / nearPos.w - camera_pos.xyz);
+
+        // Equirectangular, Z up
+        float u = frac(atan2(dir.y, dir.x) / (2.0 * PI) + 0.5 + params.z / (2.0 * PI));
+        float v = acos(clamp(dir.z, -1.0, 1.0)) / PI;
+        result = Hdri.SampleLevel(LinearSampler, float2(u, v), 0).rgb * params.y;
+    }
+
+    if (params.w != 0.0)
+        result = LinearToSrgb(saturate(result));
+
+    return float4(result, 1.0);
+}
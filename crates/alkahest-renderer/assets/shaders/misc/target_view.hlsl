@@ -0,0 +1,49 @@
+// VSMain
+#include "screen_space.hlsli"
+
+cbuffer target_view : register(b0) {
+    float4 channel_mask;
+    float range_min;
+    float range_max;
+    uint layer;
+    // 0 = color, 1 = depth array, 2 = integer IDs
+    uint mode;
+};
+
+Texture2D Source : register(t0);
+Texture2DArray SourceArray : register(t1);
+Texture2D<uint> SourceUint : register(t2);
+SamplerState PointSampler : register(s0);
+
+float3 IdColor(uint id) {
+    if (id == 0)
+        return float3(0.0, 0.0, 0.0);
+
+    uint h = id * 0x9E3779B9u;
+    h ^= h >> 16;
+    h *= 0x85EBCA6Bu;
+    h ^= h >> 13;
+    return float3(h & 0xFF, (h >> 8) & 0xFF, (h >> 16) & 0xFF) / 255.0;
+}
+
+float4 PSMain(VSOutput input) : SV_Target0 {
+    if (mode == 2) {
+        uint width, height;
+        SourceUint.GetDimensions(width, height);
+        return float4(IdColor(SourceUint.Load(int3(input.uv * float2(width, height), 0))), 1.0);
+    }
+
+    float4 value;
+    if (mode == 1)
+        value = SourceArray.SampleLevel(PointSampler, float3(input.uv, layer), 0);
+    else
+        value = Source.SampleLevel(PointSampler, input.uv, 0);
+
+    value = saturate((value - range_min) / max(range_max - range_min, 1e-6));
+
+    // A single isolated channel is shown in greyscale
+    if (dot(channel_mask, 1.0) == 1.0)
+        return float4(dot(value, channel_mask).xxx, 1.0);
+
+    return float4(value.rgb * channel_mask.rgb, 1.0);
+}
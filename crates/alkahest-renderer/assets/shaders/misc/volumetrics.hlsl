@@ -0,0 +1,108 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+#include "volumetrics.hlsli"
+
+// Offset against shadow acne on the shadow map lookups
+#define SHADOW_BIAS 0.0005
+
+Texture2D RtDepth : register(t0);
+Texture2DArray ShadowMaps[MAX_SHAFT_LIGHTS] : register(t1);
+
+float3 WorldPosFromDepth(float depth, float2 uv) {
+    float4 world = mul(target_pixel_to_world, float4(uv * target_resolution, depth, 1.0));
+    return world.xyz / world.w;
+}
+
+// Interleaved gradient noise (Jimenez 2014)
+float Noise(float2 p) {
+    return frac(52.9829189 * frac(dot(p, float2(0.06711056, 0.00583715))));
+}
+
+// Henyey-Greenstein phase function, scaled so an anisotropy of 0 scatters like the ambient fog
+float Phase(float cos_theta, float g) {
+    float g2 = g * g;
+    return (1.0 - g2) / pow(max(1.0 + g2 - 2.0 * g * cos_theta, 1e-4), 1.5);
+}
+
+// Fog density at the given position, thinning out with height above the base height
+float FogDensity(float3 p) {
+    return density * exp(-height_falloff * clamp(p.z - base_height, -64.0, 4096.0));
+}
+
+// Light of shaft light `i` arriving at `p`, 0 outside of the light's frustum or in its shadow
+float3 ShaftLight(uint i, float3 p, float3 ray_dir) {
+    float4 clip = mul(light_world_to_projective[i], float4(p, 1.0));
+    if (clip.w <= 0.0)
+        return 0;
+
+    float3 ndc = clip.xyz / clip.w;
+    float2 uv = ndc.xy * float2(0.5, -0.5) + 0.5;
+    if (any(uv < 0.0) || any(uv > 1.0) || ndc.z > 1.0)
+        return 0;
+
+    float shadow_depth = 1.0;
+    // Resources can only be indexed with a literal
+    [unroll] for (uint s = 0; s < MAX_SHAFT_LIGHTS; s++) {
+        if (s == i)
+            shadow_depth = ShadowMaps[s].SampleLevel(def_point_clamp, float3(uv, 0), 0).r;
+    }
+    if (ndc.z > shadow_depth + SHADOW_BIAS)
+        return 0;
+
+    float3 to_sample = p - light_position_range[i].xyz;
+    float distance = length(to_sample);
+    float attenuation = saturate(1.0 - distance / light_position_range[i].w);
+    // Soften the edges of the light's frustum
+    float2 edge = saturate(min(uv, 1.0 - uv) * 8.0);
+
+    float phase = Phase(dot(ray_dir, to_sample / max(distance, 1e-4)), anisotropy);
+    return light_color * phase * attenuation * attenuation * edge.x * edge.y;
+}
+
+// Half resolution in-scattered light (rgb) and transmittance (a) between the camera and the
+// depth buffer
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float depth = RtDepth.SampleLevel(def_point_clamp, input.uv, 0).r;
+    // The sky is at depth 0, march it up to the maximum distance
+    float3 target = WorldPosFromDepth(max(depth, 1e-7), input.uv);
+    float3 ray = target - camera_position;
+    float ray_length = min(length(ray), max_distance);
+    float3 ray_dir = normalize(ray);
+
+    float step_length = ray_length / step_count;
+    float2 pixel = input.position.xy + float(frame_index % 64) * float2(5.588238, 5.588238);
+    float jitter = Noise(pixel);
+
+    float3 scattered = 0;
+    float transmittance = 1.0;
+    for (uint s = 0; s < step_count; s++) {
+        float3 p = camera_position + ray_dir * ((s + jitter) * step_length);
+        float sample_density = FogDensity(p);
+        if (sample_density <= 0.0)
+            continue;
+
+        // Isotropic ambient fog, lit by the fog color
+        float3 in_scatter = fog_color;
+        for (uint i = 0; i < light_count; i++) {
+            in_scatter += ShaftLight(i, p, ray_dir);
+        }
+
+        float step_transmittance = exp(-sample_density * step_length);
+        // Energy conserving integration of the scattering over the step (Hillaire 2015)
+        scattered += transmittance * in_scatter * (1.0 - step_transmittance);
+        transmittance *= step_transmittance;
+
+        if (transmittance < 0.001)
+            break;
+    }
+
+    return float4(scattered * exposure_scale_for_shading, transmittance);
+}
+
+#endif
@@ -0,0 +1,21 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+Texture2D ShadingResult : register(t0);
+Texture2D Volumetrics   : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float3 color = ShadingResult.Load(int3(input.position.xy, 0)).rgb;
+    float4 volumetrics = Volumetrics.SampleLevel(linear_clamp, input.uv, 0);
+
+    return float4(color * volumetrics.a + volumetrics.rgb, 1.0);
+}
+
+#endif
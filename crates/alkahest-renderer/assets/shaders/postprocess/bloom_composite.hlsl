@@ -0,0 +1,28 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_bloom : register(b0) {
+    float threshold;
+    float knee;
+    // Already divided by the number of bloom levels
+    float intensity;
+};
+
+Texture2D Color : register(t0);
+Texture2D Bloom : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float3 color = Color.Load(int3(input.position.xy, 0)).rgb;
+    float3 bloom = Bloom.SampleLevel(linear_clamp, input.uv, 0).rgb;
+
+    return float4(color + bloom * intensity, 1.0);
+}
+
+#endif
@@ -0,0 +1,27 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+Texture2D Source : register(t0);
+
+SamplerState linear_clamp : register(s1);
+
+// 4x4 box filter, from four bilinear samples
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float2 size;
+    Source.GetDimensions(size.x, size.y);
+    float2 texel = 1.0 / size;
+
+    float3 color = Source.SampleLevel(linear_clamp, input.uv + texel * float2(-1, -1), 0).rgb;
+    color += Source.SampleLevel(linear_clamp, input.uv + texel * float2(1, -1), 0).rgb;
+    color += Source.SampleLevel(linear_clamp, input.uv + texel * float2(-1, 1), 0).rgb;
+    color += Source.SampleLevel(linear_clamp, input.uv + texel * float2(1, 1), 0).rgb;
+
+    return float4(color * 0.25, 1.0);
+}
+
+#endif
@@ -0,0 +1,34 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_bloom : register(b0) {
+    // Brightness at which pixels start to bloom
+    float threshold;
+    // Range below the threshold that is blended in smoothly
+    float knee;
+};
+
+Texture2D Source : register(t0);
+
+SamplerState linear_clamp : register(s1);
+
+// Downsamples the shading result to half resolution, keeping only the parts brighter than the
+// threshold
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    // Sampling in between four source pixels averages them
+    float3 color = Source.SampleLevel(linear_clamp, input.uv, 0).rgb;
+
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+
+    return float4(color * contribution, 1.0);
+}
+
+#endif
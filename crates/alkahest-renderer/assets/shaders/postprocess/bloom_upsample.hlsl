@@ -0,0 +1,33 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+// Downsampled level with the same resolution as the target
+Texture2D Current : register(t0);
+// Upsampled result of the level below
+Texture2D Lower : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+// Adds the lower level to the current one, using a 3x3 tent filter to upsample it
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float2 size;
+    Lower.GetDimensions(size.x, size.y);
+    float2 texel = 1.0 / size;
+
+    float3 lower = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            float weight = (2 - abs(x)) * (2 - abs(y)) / 16.0;
+            lower += Lower.SampleLevel(linear_clamp, input.uv + texel * float2(x, y), 0).rgb * weight;
+        }
+    }
+
+    return float4(Current.SampleLevel(linear_clamp, input.uv, 0).rgb + lower, 1.0);
+}
+
+#endif
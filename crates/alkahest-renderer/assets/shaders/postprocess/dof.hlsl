@@ -0,0 +1,75 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_dof : register(b0) {
+    float4x4 pixel_to_camera;
+    // Distance that is in focus, in meters. 0 focuses on the geometry in the center of the view
+    float focus_distance;
+    // Blur radius of geometry that is far out of focus, in pixels
+    float max_blur_radius;
+    uint sample_count;
+};
+
+Texture2D Color : register(t0);
+Texture2D RtDepth : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+static const float GOLDEN_ANGLE = 2.39996323;
+
+float DistanceAt(int2 p) {
+    p = clamp(p, int2(0, 0), int2(target_resolution) - 1);
+    float depth = RtDepth.Load(int3(p, 0)).r;
+    // Reversed depth, the sky is (close to) infinitely far away
+    if (depth <= 0.0) {
+        return 1e6;
+    }
+
+    float4 camera = mul(pixel_to_camera, float4(float2(p) + 0.5, depth, 1.0));
+    return length(camera.xyz / camera.w);
+}
+
+// Radius of the circle of confusion in pixels, following the thin lens model
+float BlurRadius(float distance, float focus) {
+    return max_blur_radius * saturate(abs(1.0 - focus / distance));
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    int2 p = int2(input.position.xy);
+    float focus = focus_distance > 0.0 ? focus_distance : DistanceAt(int2(target_resolution * 0.5));
+
+    float center_distance = DistanceAt(p);
+    float center_radius = BlurRadius(center_distance, focus);
+
+    float3 sum = Color.Load(int3(p, 0)).rgb;
+    float weight_sum = 1.0;
+
+    // Gather samples on a golden angle spiral, each sample contributing when its own blur
+    // radius reaches this pixel
+    for (uint i = 1; i < sample_count; i++) {
+        float radius = max_blur_radius * sqrt(float(i) / float(sample_count));
+        float theta = float(i) * GOLDEN_ANGLE;
+        float2 offset = float2(cos(theta), sin(theta)) * radius;
+
+        float sample_distance = DistanceAt(int2(input.position.xy + offset));
+        float sample_radius = BlurRadius(sample_distance, focus);
+        // Geometry behind this pixel can't blur over it, unless this pixel is out of focus itself
+        if (sample_distance > center_distance) {
+            sample_radius = min(sample_radius, center_radius);
+        }
+
+        float weight = smoothstep(radius - 1.0, radius + 1.0, sample_radius);
+        float2 uv = (input.position.xy + offset) * inverse_target_resolution;
+        sum += Color.SampleLevel(linear_clamp, uv, 0).rgb * weight;
+        weight_sum += weight;
+    }
+
+    return float4(sum / weight_sum, 1.0);
+}
+
+#endif
@@ -0,0 +1,17 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+#include "tonemap.hlsli"
+
+Texture2D Source : register(t0);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    return float4(Source.Load(int3(input.position.xy, 0)).rgb * exposure_scale, 1);
+}
+
+#endif
@@ -0,0 +1,51 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+// SMAA pass 3: neighborhood blending
+
+Texture2D Color : register(t0);
+Texture2D BlendWeights : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+float4 WeightsAt(int2 p) {
+    if (any(p >= int2(target_resolution))) {
+        return float4(0.0, 0.0, 0.0, 0.0);
+    }
+
+    return BlendWeights.Load(int3(p, 0));
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    int2 p = int2(input.position.xy);
+    float2 texel = inverse_target_resolution;
+    float2 uv = (float2(p) + 0.5) * texel;
+
+    float4 a;
+    a.x = WeightsAt(p + int2(1, 0)).a; // Right
+    a.y = WeightsAt(p + int2(0, 1)).g; // Bottom
+    a.wz = WeightsAt(p).xz;            // Top, left
+
+    if (dot(a, float4(1.0, 1.0, 1.0, 1.0)) < 1e-5) {
+        return Color.Load(int3(p, 0));
+    }
+
+    // Blend either horizontally or vertically, whichever has the largest weight
+    bool h = max(a.x, a.z) > max(a.y, a.w);
+    float4 blending_offset = h ? float4(a.x, 0.0, a.z, 0.0) : float4(0.0, a.y, 0.0, a.w);
+    float2 blending_weight = h ? a.xz : a.yw;
+    blending_weight /= dot(blending_weight, float2(1.0, 1.0));
+
+    float4 blending_coord = blending_offset * float4(texel, -texel) + uv.xyxy;
+    float4 color = blending_weight.x * Color.SampleLevel(linear_clamp, blending_coord.xy, 0);
+    color += blending_weight.y * Color.SampleLevel(linear_clamp, blending_coord.zw, 0);
+
+    return color;
+}
+
+#endif
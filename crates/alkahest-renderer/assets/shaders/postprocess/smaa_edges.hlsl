@@ -0,0 +1,55 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+// SMAA pass 1: luma edge detection with local contrast adaptation
+// Output: R = edge on the left side of the pixel, G = edge on the top side of the pixel
+
+#define SMAA_THRESHOLD 0.1
+#define SMAA_LOCAL_CONTRAST_ADAPTATION_FACTOR 2.0
+
+Texture2D Color : register(t0);
+
+float LumaAt(int2 p) {
+    p = clamp(p, int2(0, 0), int2(target_resolution) - 1);
+    // The source is linear, edges are detected on (approximately) gamma corrected luma
+    float3 c = pow(saturate(Color.Load(int3(p, 0)).rgb), 1.0 / 2.2);
+    return dot(c, float3(0.2126, 0.7152, 0.0722));
+}
+
+float2 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    int2 p = int2(input.position.xy);
+
+    float l = LumaAt(p);
+    float l_left = LumaAt(p + int2(-1, 0));
+    float l_top = LumaAt(p + int2(0, -1));
+
+    float4 delta;
+    delta.xy = abs(l - float2(l_left, l_top));
+    float2 edges = step(SMAA_THRESHOLD, delta.xy);
+
+    if (dot(edges, float2(1.0, 1.0)) == 0.0)
+        discard;
+
+    float l_right = LumaAt(p + int2(1, 0));
+    float l_bottom = LumaAt(p + int2(0, 1));
+    delta.zw = abs(l - float2(l_right, l_bottom));
+    float2 max_delta = max(delta.xy, delta.zw);
+
+    float l_left_left = LumaAt(p + int2(-2, 0));
+    float l_top_top = LumaAt(p + int2(0, -2));
+    delta.zw = abs(float2(l_left, l_top) - float2(l_left_left, l_top_top));
+    max_delta = max(max_delta, delta.zw);
+
+    // Suppress edges that are much weaker than the strongest edge around them
+    float final_delta = max(max_delta.x, max_delta.y);
+    edges *= step(final_delta, SMAA_LOCAL_CONTRAST_ADAPTATION_FACTOR * delta.xy);
+
+    return edges;
+}
+
+#endif
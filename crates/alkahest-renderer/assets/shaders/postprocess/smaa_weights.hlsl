@@ -0,0 +1,163 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+// SMAA pass 2: blending weight calculation
+// Instead of the precomputed area texture, the coverage of the reconstructed silhouette line is
+// calculated analytically for the orthogonal (L, U and Z shaped) patterns.
+// Output: RG = weights for the edge on top of the pixel, BA = weights for the edge on the left side
+
+#define SMAA_MAX_SEARCH_STEPS 32
+
+Texture2D Edges : register(t0);
+
+float2 EdgesAt(int2 p) {
+    if (any(p < int2(0, 0)) || any(p >= int2(target_resolution))) {
+        return float2(0.0, 0.0);
+    }
+
+    return Edges.Load(int3(p, 0)).rg;
+}
+
+// Height of the silhouette line at the end of a pattern, based on the crossing edges on the
+// positive (top/left) and negative (bottom/right) side of the edge
+float EndHeight(float positive, float negative) {
+    if (positive > 0.0 && negative == 0.0) {
+        return 0.5;
+    }
+    if (negative > 0.0 && positive == 0.0) {
+        return -0.5;
+    }
+    return 0.0;
+}
+
+// Signed height of the silhouette line at position x of a pattern with length d
+float LineHeight(float x, float d, float h1, float h2) {
+    // Z shape, a single line between both ends
+    if (h1 * h2 < 0.0) {
+        return lerp(h1, h2, x / d);
+    }
+
+    // L and U shapes, the line(s) meet the edge in the middle of the pattern
+    float half_d = d * 0.5;
+    if (x < half_d) {
+        return h1 * (1.0 - x / half_d);
+    }
+    return h2 * ((x - half_d) / half_d);
+}
+
+// Area between the edge and a straight line segment from (x1, y1) to (x2, y2)
+// Returns (area on the negative side, area on the positive side)
+float2 SegmentArea(float x1, float x2, float y1, float y2) {
+    if (x2 <= x1) {
+        return float2(0.0, 0.0);
+    }
+
+    if (y1 * y2 >= 0.0) {
+        float a = (y1 + y2) * 0.5 * (x2 - x1);
+        return a < 0.0 ? float2(-a, 0.0) : float2(0.0, a);
+    }
+
+    // The segment crosses the edge, which splits it into two triangles
+    float xr = x1 + (x2 - x1) * y1 / (y1 - y2);
+    float a1 = y1 * (xr - x1) * 0.5;
+    float a2 = y2 * (x2 - xr) * 0.5;
+    return float2(max(-a1, 0.0) + max(-a2, 0.0), max(a1, 0.0) + max(a2, 0.0));
+}
+
+// Area covered by the silhouette line for the pixel at `offset` in a pattern of length d
+float2 PatternArea(float offset, float d, float h1, float h2) {
+    float x1 = offset;
+    float x2 = offset + 1.0;
+
+    if (h1 * h2 < 0.0) {
+        return SegmentArea(x1, x2, LineHeight(x1, d, h1, h2), LineHeight(x2, d, h1, h2));
+    }
+
+    // Split at the middle of the pattern, where the line changes direction
+    float mid = d * 0.5;
+    float xm1 = min(x2, mid);
+    float xm2 = max(x1, mid);
+    return SegmentArea(x1, xm1, LineHeight(x1, d, h1, h2), LineHeight(xm1, d, h1, h2))
+        + SegmentArea(xm2, x2, LineHeight(xm2, d, h1, h2), LineHeight(x2, d, h1, h2));
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    int2 p = int2(input.position.xy);
+    float2 e = EdgesAt(p);
+    float4 weights = float4(0.0, 0.0, 0.0, 0.0);
+
+    [branch]
+    if (e.g > 0.0) {
+        // Edge on top, find both ends of the horizontal line
+        int left = 0;
+        [loop]
+        for (; left < SMAA_MAX_SEARCH_STEPS; left++) {
+            int2 q = p - int2(left, 0);
+            if (EdgesAt(q).r > 0.0 || EdgesAt(q + int2(0, -1)).r > 0.0 || EdgesAt(q + int2(-1, 0)).g == 0.0) {
+                break;
+            }
+        }
+
+        int right = 0;
+        [loop]
+        for (; right < SMAA_MAX_SEARCH_STEPS; right++) {
+            int2 n = p + int2(right + 1, 0);
+            if (EdgesAt(n).r > 0.0 || EdgesAt(n + int2(0, -1)).r > 0.0 || EdgesAt(n).g == 0.0) {
+                break;
+            }
+        }
+
+        int2 left_end = p - int2(left, 0);
+        int2 right_end = p + int2(right + 1, 0);
+        float h1 = left < SMAA_MAX_SEARCH_STEPS
+            ? EndHeight(EdgesAt(left_end + int2(0, -1)).r, EdgesAt(left_end).r)
+            : 0.0;
+        float h2 = right < SMAA_MAX_SEARCH_STEPS
+            ? EndHeight(EdgesAt(right_end + int2(0, -1)).r, EdgesAt(right_end).r)
+            : 0.0;
+
+        weights.rg = PatternArea(float(left), float(left + right + 1), h1, h2);
+    }
+
+    [branch]
+    if (e.r > 0.0) {
+        // Edge on the left, find both ends of the vertical line
+        int up = 0;
+        [loop]
+        for (; up < SMAA_MAX_SEARCH_STEPS; up++) {
+            int2 q = p - int2(0, up);
+            if (EdgesAt(q).g > 0.0 || EdgesAt(q + int2(-1, 0)).g > 0.0 || EdgesAt(q + int2(0, -1)).r == 0.0) {
+                break;
+            }
+        }
+
+        int down = 0;
+        [loop]
+        for (; down < SMAA_MAX_SEARCH_STEPS; down++) {
+            int2 n = p + int2(0, down + 1);
+            if (EdgesAt(n).g > 0.0 || EdgesAt(n + int2(-1, 0)).g > 0.0 || EdgesAt(n).r == 0.0) {
+                break;
+            }
+        }
+
+        int2 top_end = p - int2(0, up);
+        int2 bottom_end = p + int2(0, down + 1);
+        float h1 = up < SMAA_MAX_SEARCH_STEPS
+            ? EndHeight(EdgesAt(top_end + int2(-1, 0)).g, EdgesAt(top_end).g)
+            : 0.0;
+        float h2 = down < SMAA_MAX_SEARCH_STEPS
+            ? EndHeight(EdgesAt(bottom_end + int2(-1, 0)).g, EdgesAt(bottom_end).g)
+            : 0.0;
+
+        weights.ba = PatternArea(float(up), float(up + down + 1), h1, h2);
+    }
+
+    return weights;
+}
+
+#endif
@@ -0,0 +1,127 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_ssgi : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4x4 previous_world_to_projective;
+    // Maximum distance a ray travels, in meters
+    float radius;
+    float intensity;
+    // Weight of the history, 0 when there is no (valid) history
+    float history_weight;
+    uint frame_index;
+};
+
+Texture2D RtDepth       : register(t0);
+Texture2D RtNormal      : register(t1);
+Texture2D RtAlbedo      : register(t2);
+Texture2D LightDiffuse  : register(t3);
+Texture2D History       : register(t4);
+
+SamplerState linear_clamp : register(s1);
+
+#define RAY_COUNT 6
+#define RAY_STEPS 6
+#define PI 3.14159265
+
+float3 WorldPosFromDepth(float depth, float2 uv) {
+    float4 world = mul(target_pixel_to_world, float4(uv * target_resolution, depth, 1.0));
+    return world.xyz / world.w;
+}
+
+float3 DecodeNormal(float3 n) {
+    return normalize(n * 2.0 - 1.0);
+}
+
+// Interleaved gradient noise (Jimenez 2014)
+float Noise(float2 p) {
+    return frac(52.9829189 * frac(dot(p, float2(0.06711056, 0.00583715))));
+}
+
+float2 WorldToUv(float3 world, out float w) {
+    float4 clip = mul(world_to_projective, float4(world, 1.0));
+    w = clip.w;
+    return clip.xy / clip.w * float2(0.5, -0.5) + 0.5;
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float depth = RtDepth.SampleLevel(def_point_clamp, input.uv, 0).r;
+    // Nothing to light in the sky
+    if (depth == 0.0)
+        return float4(0, 0, 0, 1);
+
+    float3 position = WorldPosFromDepth(depth, input.uv);
+    float3 normal = DecodeNormal(RtNormal.SampleLevel(def_point_clamp, input.uv, 0).xyz);
+
+    float3 helper = abs(normal.z) < 0.999 ? float3(0, 0, 1) : float3(1, 0, 0);
+    float3 tangent = normalize(cross(helper, normal));
+    float3 bitangent = cross(normal, tangent);
+
+    float2 pixel = input.position.xy + float(frame_index % 64) * float2(5.588238, 5.588238);
+    float rotation = Noise(pixel) * 2.0 * PI;
+    float jitter = Noise(pixel.yx + 17.0);
+
+    // Offset the origin a bit to keep rays from hitting the surface they start on
+    float3 origin = position + normal * 0.05;
+    float3 bounce = 0;
+    for (int i = 0; i < RAY_COUNT; i++) {
+        // Cosine weighted hemisphere direction
+        float u = (i + jitter) / RAY_COUNT;
+        float phi = rotation + i * (2.0 * PI * 0.618034);
+        float sin_theta = sqrt(u);
+        float3 dir = tangent * (cos(phi) * sin_theta)
+            + bitangent * (sin(phi) * sin_theta)
+            + normal * sqrt(1.0 - u);
+
+        for (int s = 1; s <= RAY_STEPS; s++) {
+            // Steps get longer further away from the origin
+            float t = radius * pow((s - 1.0 + jitter) / RAY_STEPS, 2.0);
+            float3 sample_pos = origin + dir * max(t, 0.05);
+
+            float w;
+            float2 uv = WorldToUv(sample_pos, w);
+            if (w <= 0.0 || any(uv < 0.0) || any(uv > 1.0))
+                break;
+
+            float scene_depth = RtDepth.SampleLevel(def_point_clamp, uv, 0).r;
+            if (scene_depth == 0.0)
+                continue;
+
+            float3 scene_pos = WorldPosFromDepth(scene_depth, uv);
+            float sample_distance = length(sample_pos - camera_position);
+            float scene_distance = length(scene_pos - camera_position);
+
+            // The ray went behind the depth buffer, treat it as a hit if it isn't too far behind
+            if (scene_distance < sample_distance) {
+                if (sample_distance - scene_distance < radius * 0.25) {
+                    float3 hit_normal = DecodeNormal(RtNormal.SampleLevel(def_point_clamp, uv, 0).xyz);
+                    // Only surfaces facing the origin reflect light towards it
+                    float facing = saturate(dot(hit_normal, -dir));
+                    float3 albedo = RtAlbedo.SampleLevel(def_point_clamp, uv, 0).rgb;
+                    float3 light = LightDiffuse.SampleLevel(def_point_clamp, uv, 0).rgb;
+                    bounce += light * albedo * facing;
+                }
+                break;
+            }
+        }
+    }
+    bounce /= RAY_COUNT;
+
+    // Accumulate with the reprojected history
+    float4 previous_clip = mul(previous_world_to_projective, float4(position, 1.0));
+    float2 previous_uv = previous_clip.xy / previous_clip.w * float2(0.5, -0.5) + 0.5;
+    float weight = history_weight;
+    if (previous_clip.w <= 0.0 || any(previous_uv < 0.0) || any(previous_uv > 1.0)) {
+        weight = 0.0;
+    }
+
+    float3 history = History.SampleLevel(linear_clamp, previous_uv, 0).rgb;
+    return float4(lerp(bounce, history, weight), 1.0);
+}
+
+#endif
@@ -0,0 +1,33 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_ssgi : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4x4 previous_world_to_projective;
+    float radius;
+    float intensity;
+    float history_weight;
+    uint frame_index;
+};
+
+Texture2D RtDepth : register(t0);
+Texture2D Ssgi : register(t1);
+
+SamplerState linear_clamp : register(s1);
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    if (RtDepth.Load(int3(input.position.xy, 0)).r == 0.0)
+        return 0;
+
+    float3 bounce = Ssgi.SampleLevel(linear_clamp, input.uv, 0).rgb;
+
+    // Alpha of 0, so the blend only adds to the diffuse lighting
+    return float4(bounce * intensity, 0.0);
+}
+
+#endif
@@ -0,0 +1,157 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_ssr : register(b0) {
+    float4x4 target_pixel_to_world;
+    float4x4 previous_world_to_projective;
+    // Maximum distance a ray travels, in meters
+    float max_distance;
+    // Reflections fade out towards this roughness
+    float max_roughness;
+    uint step_count;
+    uint frame_index;
+};
+
+Texture2D RtDepth   : register(t0);
+Texture2D RtNormal  : register(t1);
+// Shading result of the previous frame
+Texture2D History   : register(t2);
+
+SamplerState linear_clamp : register(s1);
+
+#define REFINE_STEPS 5
+
+float3 WorldPosFromDepth(float depth, float2 uv) {
+    float4 world = mul(target_pixel_to_world, float4(uv * target_resolution, depth, 1.0));
+    return world.xyz / world.w;
+}
+
+float3 DecodeNormal(float3 n) {
+    return n * 2.0 - 1.0;
+}
+
+// Interleaved gradient noise (Jimenez 2014)
+float Noise(float2 p) {
+    return frac(52.9829189 * frac(dot(p, float2(0.06711056, 0.00583715))));
+}
+
+float2 WorldToUv(float4x4 to_projective, float3 world, out float w) {
+    float4 clip = mul(to_projective, float4(world, 1.0));
+    w = clip.w;
+    return clip.xy / clip.w * float2(0.5, -0.5) + 0.5;
+}
+
+// How far `world` is behind the depth buffer, in meters. Negative when it is in front of it
+float DepthDelta(float3 world, out float2 uv, out bool valid) {
+    float w;
+    uv = WorldToUv(world_to_projective, world, w);
+    valid = w > 0.0 && all(uv >= 0.0) && all(uv <= 1.0);
+
+    float scene_depth = RtDepth.SampleLevel(def_point_clamp, uv, 0).r;
+    // The sky is infinitely far away, nothing can be behind it
+    if (scene_depth == 0.0)
+        return -1.0;
+
+    float3 scene_pos = WorldPosFromDepth(scene_depth, uv);
+    return length(world - camera_position) - length(scene_pos - camera_position);
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float depth = RtDepth.SampleLevel(def_point_clamp, input.uv, 0).r;
+    // Keep the cubemaps (or the lack thereof) for the sky
+    if (depth == 0.0)
+        return 0;
+
+    float3 normal = DecodeNormal(RtNormal.SampleLevel(def_point_clamp, input.uv, 0).xyz);
+    float smoothness = saturate(length(normal) * 4 - 3);
+    float roughness = 1.0 - smoothness;
+    float roughness_fade = 1.0 - smoothstep(max_roughness * 0.5, max_roughness, roughness);
+    if (roughness_fade <= 0.0)
+        return 0;
+
+    normal = normalize(normal);
+    float3 position = WorldPosFromDepth(depth, input.uv);
+    float3 view_dir = normalize(position - camera_position);
+
+    // Rough surfaces scatter their rays around the mirror direction, the noise is resolved by
+    // the antialiasing
+    float2 pixel = input.position.xy + float(frame_index % 64) * float2(5.588238, 5.588238);
+    float jitter = Noise(pixel);
+    float3 scatter = float3(Noise(pixel + 31.0), Noise(pixel + 57.0), Noise(pixel + 83.0));
+    scatter = scatter * 2.0 - 1.0;
+    float3 dir = normalize(reflect(view_dir, normal) + scatter * roughness * roughness);
+    // Scattered rays that ended up going into the surface are mirrored back out
+    if (dot(dir, normal) < 0.0)
+        dir = reflect(dir, normal);
+
+    // Rays towards the camera mostly hit things that aren't on screen
+    float facing_fade = 1.0 - smoothstep(0.25, 0.75, dot(dir, -view_dir));
+    if (facing_fade <= 0.0)
+        return 0;
+
+    // Offset the origin a bit to keep rays from hitting the surface they start on
+    float3 origin = position + normal * 0.02;
+    // Rays hitting geometry that is thicker than this are considered to go behind it
+    float thickness = max(max_distance / step_count, 0.1);
+
+    float previous_t = 0.0;
+    float hit_t = -1.0;
+    for (uint s = 1; s <= step_count; s++) {
+        // Steps get longer further away from the origin
+        float t = max_distance * pow((s - 1.0 + jitter) / step_count, 2.0);
+        t = max(t, 0.02);
+
+        float2 uv;
+        bool valid;
+        float delta = DepthDelta(origin + dir * t, uv, valid);
+        if (!valid)
+            break;
+
+        if (delta > 0.0) {
+            if (delta < thickness + (t - previous_t))
+                hit_t = t;
+            break;
+        }
+        previous_t = t;
+    }
+
+    if (hit_t < 0.0)
+        return 0;
+
+    // Binary search between the last step in front of the depth buffer and the first behind it
+    float near_t = previous_t;
+    float far_t = hit_t;
+    for (int r = 0; r < REFINE_STEPS; r++) {
+        float mid_t = (near_t + far_t) * 0.5;
+        float2 uv;
+        bool valid;
+        if (DepthDelta(origin + dir * mid_t, uv, valid) > 0.0)
+            far_t = mid_t;
+        else
+            near_t = mid_t;
+    }
+
+    float3 hit = origin + dir * far_t;
+    float w;
+    float2 history_uv = WorldToUv(previous_world_to_projective, hit, w);
+    if (w <= 0.0 || any(history_uv < 0.0) || any(history_uv > 1.0))
+        return 0;
+
+    // Fade out near the edges of the screen and the end of the ray, instead of cutting off
+    float2 edge = saturate(min(history_uv, 1.0 - history_uv) * 10.0);
+    float edge_fade = edge.x * edge.y;
+    float distance_fade = 1.0 - smoothstep(0.75, 1.0, far_t / max_distance);
+
+    float confidence = roughness_fade * facing_fade * edge_fade * distance_fade;
+    float3 reflection = History.SampleLevel(linear_clamp, history_uv, 0).rgb;
+
+    // Premultiplied, the cubemaps fill in whatever the reflection doesn't cover
+    return float4(reflection * confidence, confidence);
+}
+
+#endif
@@ -0,0 +1,63 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+cbuffer scope_alkahest_taa : register(b0) {
+    // Jittered, for the current frame
+    float4x4 target_pixel_to_world_jittered;
+    // Without jitter, for the previous frame
+    float4x4 previous_world_to_projective;
+    // Weight of the history, 0 when there is no (valid) history
+    float history_weight;
+};
+
+Texture2D Color : register(t0);
+Texture2D History : register(t1);
+Texture2D RtDepth : register(t2);
+
+SamplerState linear_clamp : register(s1);
+
+float3 ColorAt(int2 p) {
+    p = clamp(p, int2(0, 0), int2(target_resolution) - 1);
+    return Color.Load(int3(p, 0)).rgb;
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    int2 p = int2(input.position.xy);
+    float4 source = Color.Load(int3(p, 0));
+    float3 current = source.rgb;
+
+    // Neighborhood bounds, used to reject history that doesn't match the current frame anymore
+    float3 color_min = current;
+    float3 color_max = current;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            float3 c = ColorAt(p + int2(x, y));
+            color_min = min(color_min, c);
+            color_max = max(color_max, c);
+        }
+    }
+
+    // Reproject using the depth buffer. Kept homogeneous so the sky (depth 0, infinitely far away)
+    // reprojects correctly as well
+    float depth = RtDepth.Load(int3(p, 0)).r;
+    float4 world = mul(target_pixel_to_world_jittered, float4(input.position.xy, depth, 1.0));
+    float4 previous_clip = mul(previous_world_to_projective, world);
+    float2 previous_uv = previous_clip.xy / previous_clip.w * float2(0.5, -0.5) + 0.5;
+
+    float weight = history_weight;
+    if (previous_clip.w <= 0.0 || any(previous_uv < 0.0) || any(previous_uv > 1.0)) {
+        weight = 0.0;
+    }
+
+    float3 history = History.SampleLevel(linear_clamp, previous_uv, 0).rgb;
+    history = clamp(history, color_min, color_max);
+
+    return float4(lerp(current, history, weight), source.a);
+}
+
+#endif
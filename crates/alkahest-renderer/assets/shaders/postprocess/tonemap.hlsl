@@ -0,0 +1,38 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+#include "tonemap.hlsli"
+
+Texture2D Source : register(t0);
+
+// Krzysztof Narkowicz's ACES filmic curve fit
+float3 AcesFitted(float3 x) {
+    return saturate((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14));
+}
+
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float3 color = max(Source.Load(int3(input.position.xy, 0)).rgb, 0.0);
+    float peak = DisplayPeak();
+
+    switch (tonemapper) {
+    case TONEMAPPER_ACES:
+        color = AcesFitted(color / peak) * peak;
+        break;
+    case TONEMAPPER_REINHARD:
+        color = color / (1.0 + color / peak);
+        break;
+    default:
+        color = min(color, peak);
+        break;
+    }
+
+    // Relative to paper white, gamma corrected like the output of final_combine
+    return float4(GammaEncode(color), 1);
+}
+
+#endif
@@ -0,0 +1,37 @@
+#include "scopes/frame.hlsli"
+// VSMain
+#include "screen_space.hlsli"
+
+#ifdef STAGE_PS
+
+#include "tonemap.hlsli"
+
+Texture2D Source : register(t0);
+
+// Converts a (premultiplied) gamma corrected image to the color space of the HDR swapchain
+float4 PSMain(
+    VSOutput input
+) : SV_Target0 {
+    float4 c = Source.Load(int3(input.position.xy, 0));
+    if (c.a <= 0.0) {
+        discard;
+    }
+
+    float3 color = c.rgb / c.a;
+    if (!input_is_linear) {
+        color = GammaDecode(color);
+    }
+
+    if (output_mode == OUTPUT_MODE_HDR10) {
+        color = LinearToPQ(Rec709ToRec2020(max(color, 0.0)) * paper_white / 10000.0);
+    } else if (output_mode == OUTPUT_MODE_SCRGB) {
+        // scRGB maps 1.0 to 80 nits
+        color = color * paper_white / 80.0;
+    } else {
+        color = GammaEncode(color);
+    }
+
+    return float4(color * c.a, c.a);
+}
+
+#endif
@@ -0,0 +1,278 @@
+//! CPU-side copy of the terrain and static geometry of a map, for the walk camera to stand on.
+//!
+//! Geometry is read from the packages on a background thread the first time walk mode is used in
+//! a scene. Entities that are moved or spawned afterwards are not picked up.
+
+use std::sync::Arc;
+
+use alkahest_data::{map::STerrain, occlusion::Aabb, statics::SStaticMesh};
+use alkahest_pm::package_manager;
+use bevy_ecs::prelude::Resource;
+use crossbeam::channel::Receiver;
+use destiny_pkg::TagHash;
+use glam::{IVec2, Mat4, Vec3};
+use itertools::Itertools;
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use tiger_parse::PackageManagerExt;
+
+use crate::ecs::{
+    hierarchy::Children,
+    render::{
+        havok::segment_triangle_intersection,
+        mesh_info::{static_mesh_info, terrain_mesh_info, MeshInfo},
+        static_geometry::{StaticInstances, StaticModelSingle},
+        terrain::TerrainPatches,
+    },
+    transform::Transform,
+    Scene,
+};
+
+/// Size of the cells triangles are bucketed into, in local space units
+const CHUNK_SIZE: f32 = 16.0;
+
+/// Triangles of a mesh in local space, bucketed into chunks so a segment only tests the triangles
+/// close to it
+pub struct CollisionMesh {
+    bounds: Aabb,
+    chunks: Vec<(Aabb, Vec<[Vec3; 3]>)>,
+}
+
+impl CollisionMesh {
+    /// Returns `None` if there are no triangles
+    pub fn new(triangles: impl IntoIterator<Item = [Vec3; 3]>) -> Option<Self> {
+        let mut cells: FxHashMap<IVec2, Vec<[Vec3; 3]>> = FxHashMap::default();
+        for t in triangles {
+            let center = (t[0] + t[1] + t[2]) / 3.0;
+            let cell = (center.truncate() / CHUNK_SIZE).floor().as_ivec2();
+            cells.entry(cell).or_default().push(t);
+        }
+
+        if cells.is_empty() {
+            return None;
+        }
+
+        let chunks = cells
+            .into_values()
+            .map(|triangles| {
+                let bounds = Aabb::from_points(triangles.iter().flatten().copied());
+                (bounds, triangles)
+            })
+            .collect_vec();
+        let bounds = Aabb::from_points(chunks.iter().flat_map(|(b, _)| [b.min, b.max]));
+
+        Some(Self { bounds, chunks })
+    }
+
+    fn from_mesh_infos(meshes: &[MeshInfo], hash: TagHash) -> Option<Self> {
+        let triangles = meshes
+            .iter()
+            .filter_map(|mesh| match mesh.read_geometry() {
+                Ok(geometry) => Some(
+                    geometry
+                        .triangles
+                        .into_iter()
+                        .map(move |t| t.map(|i| geometry.positions[i as usize]))
+                        .collect_vec(),
+                ),
+                Err(e) => {
+                    warn!("Failed to read collision geometry of {hash}: {e:?}");
+                    None
+                }
+            })
+            .flatten();
+
+        Self::new(triangles)
+    }
+
+    /// Returns the fraction of `end - start` at which the segment first crosses the mesh
+    pub fn intersect_segment(&self, start: Vec3, end: Vec3) -> Option<f32> {
+        if !segment_intersects_aabb(&self.bounds, start, end) {
+            return None;
+        }
+
+        let direction = end - start;
+        self.chunks
+            .iter()
+            .filter(|(bounds, _)| segment_intersects_aabb(bounds, start, end))
+            .flat_map(|(_, triangles)| triangles)
+            .filter_map(|t| segment_triangle_intersection(start, direction, t[0], t[1], t[2]))
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+struct CollisionBody {
+    mesh: Arc<CollisionMesh>,
+    world_to_local: Mat4,
+    /// World space bounds
+    bounds: Aabb,
+}
+
+#[derive(Default)]
+pub struct CollisionWorld {
+    bodies: Vec<CollisionBody>,
+}
+
+impl CollisionWorld {
+    fn add(&mut self, mesh: Arc<CollisionMesh>, local_to_world: Mat4) {
+        self.bodies.push(CollisionBody {
+            bounds: Aabb::from_obbs([(local_to_world, mesh.bounds)]),
+            world_to_local: local_to_world.inverse(),
+            mesh,
+        });
+    }
+
+    /// Returns the point closest to `start` where the segment from `start` to `end` crosses any
+    /// of the geometry
+    pub fn raycast(&self, start: Vec3, end: Vec3) -> Option<Vec3> {
+        self.bodies
+            .iter()
+            .filter(|body| segment_intersects_aabb(&body.bounds, start, end))
+            .filter_map(|body| {
+                // Affine transforms keep the fraction along the segment intact
+                body.mesh.intersect_segment(
+                    body.world_to_local.transform_point3(start),
+                    body.world_to_local.transform_point3(end),
+                )
+            })
+            .min_by(|a, b| a.total_cmp(b))
+            .map(|t| start + (end - start) * t)
+    }
+}
+
+/// Collision geometry of the scene it's inserted into
+#[derive(Resource)]
+pub enum WalkCollision {
+    Loading(Receiver<CollisionWorld>),
+    Loaded(CollisionWorld),
+}
+
+impl WalkCollision {
+    /// Collects the terrain and statics of the scene and reads their geometry on a separate thread
+    pub fn load(scene: &mut Scene) -> Self {
+        let terrain = scene
+            .query::<&TerrainPatches>()
+            .iter(scene)
+            .map(|t| t.hash)
+            .collect_vec();
+
+        let mut statics: FxHashMap<TagHash, Vec<Mat4>> = FxHashMap::default();
+        for (instances, children) in scene.query::<(&StaticInstances, &Children)>().iter(scene) {
+            let transforms = statics.entry(instances.model.hash).or_default();
+            transforms.extend(
+                children
+                    .iter()
+                    .filter_map(|e| scene.get::<Transform>(*e))
+                    .map(Transform::local_to_world),
+            );
+        }
+        for (model, transform) in scene
+            .query::<(&StaticModelSingle, &Transform)>()
+            .iter(scene)
+        {
+            statics
+                .entry(model.model.hash)
+                .or_default()
+                .push(transform.local_to_world());
+        }
+
+        let (tx, rx) = crossbeam::channel::bounded(1);
+        std::thread::spawn(move || {
+            profiling::scope!("WalkCollision::load");
+            let terrain = terrain
+                .par_iter()
+                .filter_map(|&hash| {
+                    let terrain: STerrain = package_manager()
+                        .read_tag_struct(hash)
+                        .map_err(|e| warn!("Failed to read terrain {hash}: {e:?}"))
+                        .ok()?;
+                    CollisionMesh::from_mesh_infos(&[terrain_mesh_info(&terrain)], hash)
+                })
+                .collect::<Vec<_>>();
+
+            let statics = statics
+                .into_par_iter()
+                .filter_map(|(hash, transforms)| {
+                    let model: SStaticMesh = package_manager()
+                        .read_tag_struct(hash)
+                        .map_err(|e| warn!("Failed to read static {hash}: {e:?}"))
+                        .ok()?;
+                    let mesh = CollisionMesh::from_mesh_infos(&static_mesh_info(&model), hash)?;
+                    Some((Arc::new(mesh), transforms))
+                })
+                .collect::<Vec<_>>();
+
+            let mut world = CollisionWorld::default();
+            for mesh in terrain {
+                world.add(Arc::new(mesh), Mat4::IDENTITY);
+            }
+            for (mesh, transforms) in statics {
+                for transform in transforms {
+                    world.add(mesh.clone(), transform);
+                }
+            }
+
+            info!("Loaded walk collision with {} bodies", world.bodies.len());
+            tx.send(world).ok();
+        });
+
+        Self::Loading(rx)
+    }
+
+    /// Returns the collision geometry once it has finished loading
+    pub fn world(&mut self) -> Option<&CollisionWorld> {
+        if let Self::Loading(rx) = self {
+            *self = Self::Loaded(rx.try_recv().ok()?);
+        }
+
+        match self {
+            Self::Loaded(world) => Some(world),
+            Self::Loading(_) => None,
+        }
+    }
+}
+
+/// Slab test between a segment and an AABB
+fn segment_intersects_aabb(aabb: &Aabb, start: Vec3, end: Vec3) -> bool {
+    let direction = end - start;
+    let inv = direction.recip();
+    let t0 = (aabb.min - start) * inv;
+    let t1 = (aabb.max - start) * inv;
+    // Axes the segment is parallel to produce NaN/inf, which min/max handle by ignoring the axis
+    // when the start is inside the slab
+    let t_min = t0.min(t1).max_element().max(0.0);
+    let t_max = t0.max(t1).min_element().min(1.0);
+
+    t_min <= t_max
+}
+
+#[test]
+fn test_collision_raycast() {
+    let floor = CollisionMesh::new([
+        [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ],
+        [
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ],
+    ])
+    .unwrap();
+
+    let mut world = CollisionWorld::default();
+    world.add(
+        Arc::new(floor),
+        Mat4::from_translation(Vec3::new(10.0, 0.0, 5.0)),
+    );
+
+    let hit = world.raycast(Vec3::new(10.5, 0.0, 10.0), Vec3::new(10.5, 0.0, 0.0));
+    assert_eq!(hit, Some(Vec3::new(10.5, 0.0, 5.0)));
+    // Misses the bounds of the floor entirely
+    assert_eq!(
+        world.raycast(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0)),
+        None
+    );
+}
@@ -13,16 +13,27 @@ pub struct FpsCamera {
     target_position: Vec3,
 }
 
+/// Forward, right and up vectors for a pitch/yaw orientation in degrees
+pub(super) fn look_vectors(orientation: Vec2) -> (Vec3, Vec3, Vec3) {
+    let mut front = Vec3::ZERO;
+    front.x = orientation.x.to_radians().cos() * orientation.y.to_radians().sin();
+    front.y = orientation.x.to_radians().cos() * orientation.y.to_radians().cos();
+    front.z = -orientation.x.to_radians().sin();
+
+    let forward = front.normalize();
+    let right = forward.cross(Vec3::Z).normalize();
+    let up = right.cross(forward).normalize();
+    (forward, right, up)
+}
+
+pub(super) fn look_rotation(orientation: Vec2) -> Quat {
+    Quat::from_rotation_z(-orientation.y.to_radians() + std::f32::consts::FRAC_PI_2)
+        * Quat::from_rotation_y(orientation.x.to_radians())
+}
+
 impl FpsCamera {
     fn update_vectors(&mut self) {
-        let mut front = Vec3::ZERO;
-        front.x = self.orientation.x.to_radians().cos() * self.orientation.y.to_radians().sin();
-        front.y = self.orientation.x.to_radians().cos() * self.orientation.y.to_radians().cos();
-        front.z = -self.orientation.x.to_radians().sin();
-
-        self.forward = front.normalize();
-        self.right = self.forward.cross(Vec3::Z).normalize();
-        self.up = self.right.cross(self.forward).normalize();
+        (self.forward, self.right, self.up) = look_vectors(self.orientation);
     }
 }
 
@@ -156,9 +167,7 @@ impl CameraController for FpsCamera {
 
         self.update_vectors();
 
-        self.rotation =
-            Quat::from_rotation_z(-self.orientation.y.to_radians() + std::f32::consts::FRAC_PI_2)
-                * Quat::from_rotation_y(self.orientation.x.to_radians());
+        self.rotation = look_rotation(self.orientation);
     }
 
     fn update_mouse(&mut self, delta: Vec2, _scroll_y: f32) {
@@ -1,15 +1,23 @@
 pub mod projection;
 use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 pub use projection::CameraProjection;
+use strum::EnumIter;
 
+pub mod collision;
 pub mod fps;
 pub mod orbit;
 pub mod tween;
+pub mod walk;
 
 pub mod viewport;
 pub use viewport::Viewport;
 
-use self::{fps::FpsCamera, tween::Tween};
+use self::{
+    fps::FpsCamera,
+    orbit::OrbitCamera,
+    tween::Tween,
+    walk::{WalkCamera, WalkSettings},
+};
 use crate::{
     ecs::culling::Frustum,
     input::InputState,
@@ -29,6 +37,19 @@ pub trait CameraController {
     fn update_mouse(&mut self, delta: Vec2, scroll: f32);
     fn update_gamepad(&mut self, movement: Vec2, look: Vec2, speed: f32, delta_time: f32);
 
+    /// Resolves collisions for controllers that move over the ground. `raycast` returns the hit
+    /// closest to the start of the segment between two points
+    fn update_ground(
+        &mut self,
+        _settings: &WalkSettings,
+        _delta_time: f32,
+        _raycast: &dyn Fn(Vec3, Vec3) -> Option<Vec3>,
+    ) {
+    }
+
+    /// Moves the point orbit controllers circle around, along with their distance to it
+    fn set_orbit_pivot(&mut self, _pivot: Vec3, _distance: f32) {}
+
     // TODO(cohae): These might be a bit confusing
     /// Returns the position of the camera
     /// Orbit camera will return the target position instead
@@ -72,8 +93,19 @@ pub fn get_look_angle(start_angle: Vec2, pos1: Vec3, pos2: Vec3) -> Vec2 {
         )
     }
 }
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, strum::Display)]
+pub enum CameraMode {
+    /// Free movement along the view direction
+    Fly,
+    /// Walks over collision geometry at eye height
+    Walk,
+    /// Circles around a pivot point, always looking at it
+    Orbit,
+}
+
 pub struct Camera {
     controller: Box<dyn CameraController>,
+    mode: CameraMode,
     viewport: Viewport,
 
     pub projection: CameraProjection,
@@ -96,6 +128,7 @@ pub struct Camera {
     pub speed_mul: f32,
     pub smooth_movement: f32,
     pub smooth_look: f32,
+    pub walk: WalkSettings,
 }
 
 impl Camera {
@@ -117,6 +150,7 @@ impl Camera {
     ) -> Self {
         let mut camera = Self {
             controller,
+            mode: CameraMode::Fly,
             viewport,
 
             projection,
@@ -135,6 +169,7 @@ impl Camera {
             speed_mul: 1.0,
             smooth_movement: 1.0,
             smooth_look: 0.0,
+            walk: WalkSettings::default(),
         };
 
         camera.update_matrices();
@@ -161,7 +196,10 @@ impl Camera {
         {
             self.tween = None;
         }
-        self.speed_mul = (self.speed_mul + scroll * 0.05).clamp(0.05, 25.0);
+        // Orbit controllers zoom with the scroll wheel instead
+        if self.mode != CameraMode::Orbit {
+            self.speed_mul = (self.speed_mul + scroll * 0.05).clamp(0.05, 25.0);
+        }
         self.controller.update_mouse(delta, scroll);
         self.update_matrices();
     }
@@ -178,6 +216,44 @@ impl Camera {
         self.update_matrices();
     }
 
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Swaps out the controller, keeping the current position and orientation
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+
+        let position = self.controller.position();
+        let orientation = self.controller.orientation();
+        self.controller = match mode {
+            CameraMode::Fly => Box::<FpsCamera>::default(),
+            CameraMode::Walk => Box::<WalkCamera>::default(),
+            CameraMode::Orbit => Box::<OrbitCamera>::default(),
+        };
+        self.controller.set_position(position);
+        self.controller.set_orientation(orientation);
+        self.mode = mode;
+        self.tween = None;
+        self.update_matrices();
+    }
+
+    /// Switches to the orbit controller and smoothly moves its pivot to `pivot`, keeping the
+    /// camera `distance` away from it
+    pub fn orbit_around(&mut self, pivot: Vec3, distance: f32) {
+        self.set_mode(CameraMode::Orbit);
+        self.tween = None;
+        self.controller.set_orbit_pivot(pivot, distance);
+    }
+
+    pub fn update_ground(&mut self, delta_time: f32, raycast: &dyn Fn(Vec3, Vec3) -> Option<Vec3>) {
+        self.controller
+            .update_ground(&self.walk, delta_time, raycast);
+        self.update_matrices();
+    }
+
     pub fn update_gamepad(&mut self, movement: Vec2, look: Vec2, speed: f32, delta_time: f32) {
         if movement.length() > 0.0 || look.length() > 0.0 {
             self.tween = None;
@@ -203,6 +279,18 @@ impl Camera {
         self.frustum = Frustum::from_matrix(self.world_to_projective);
     }
 
+    /// Replaces the view matrix from the controller, until the next call to [`Self::update_matrices`].
+    /// Used for views the controller can't represent, such as looking straight up or down
+    pub fn set_view_matrix(&mut self, world_to_camera: Mat4) {
+        self.world_to_camera = world_to_camera;
+        self.camera_to_world = world_to_camera.inverse();
+
+        self.world_to_projective = self.camera_to_projective * self.world_to_camera;
+        self.projective_to_world = self.world_to_projective.inverse();
+
+        self.frustum = Frustum::from_matrix(self.world_to_projective);
+    }
+
     pub fn is_point_visible(&self, point: Vec3) -> bool {
         let point_transformed = self.world_to_projective.project_point3(point);
 
@@ -305,6 +393,10 @@ impl View for Camera {
         self.frustum
     }
 
+    fn position(&self) -> Vec3 {
+        self.position()
+    }
+
     fn update_extern(&self, x: &mut crate::tfx::externs::View) {
         x.world_to_camera = self.world_to_camera;
         x.camera_to_projective = self.camera_to_projective;
@@ -1 +1,212 @@
+use glam::{Mat4, Quat, Vec2, Vec2Swizzles, Vec3};
 
+use super::{
+    fps::{look_rotation, look_vectors},
+    tween::Tween,
+    CameraController,
+};
+use crate::input::{InputState, Key};
+
+/// How quickly the pivot and distance move towards their targets, higher is faster
+const ORBIT_SMOOTHING: f32 = 10.0;
+const MIN_DISTANCE: f32 = 0.1;
+const MAX_DISTANCE: f32 = 10000.0;
+
+/// Camera that orbits around a pivot point, always looking at it
+pub struct OrbitCamera {
+    pub orientation: Vec2,
+    pub rotation: Quat,
+    pub forward: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    /// Point the camera orbits around
+    pub pivot: Vec3,
+    /// Distance between the camera and the pivot
+    pub distance: f32,
+
+    target_pivot: Vec3,
+    target_distance: f32,
+}
+
+impl OrbitCamera {
+    fn update_vectors(&mut self) {
+        (self.forward, self.right, self.up) = look_vectors(self.orientation);
+    }
+
+    fn view_position(&self) -> Vec3 {
+        self.pivot - self.forward * self.distance
+    }
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            rotation: Quat::IDENTITY,
+            forward: Vec3::Y,
+            right: -Vec3::X,
+            up: Vec3::Z,
+            orientation: Vec2::ZERO,
+            pivot: Vec3::Y * 10.0,
+            distance: 10.0,
+            target_pivot: Vec3::Y * 10.0,
+            target_distance: 10.0,
+        }
+    }
+}
+
+impl CameraController for OrbitCamera {
+    fn update(
+        &mut self,
+        tween: &mut Option<Tween>,
+        input: &InputState,
+        delta_time: f32,
+        speed_mul: f32,
+        _smooth_movement: f32,
+        _smooth_look: f32,
+    ) {
+        let mut speed = delta_time * speed_mul;
+        if input.shift() {
+            speed *= 3.0;
+        }
+        if input.ctrl() {
+            speed *= 0.1;
+        }
+
+        // A/D and Q/E circle around the pivot, W/S move towards and away from it
+        let mut rotation = Vec2::ZERO;
+        if input.is_key_down(Key::KeyA) {
+            rotation.y += 1.0;
+        }
+        if input.is_key_down(Key::KeyD) {
+            rotation.y -= 1.0;
+        }
+        if input.is_key_down(Key::KeyQ) {
+            rotation.x -= 1.0;
+        }
+        if input.is_key_down(Key::KeyE) {
+            rotation.x += 1.0;
+        }
+        self.orientation += rotation * speed * 90.0;
+
+        if input.is_key_down(Key::KeyW) {
+            self.target_distance *= 1.0 - (speed * 2.0).min(0.5);
+        }
+        if input.is_key_down(Key::KeyS) {
+            self.target_distance *= 1.0 + speed * 2.0;
+        }
+        self.target_distance = self.target_distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+        if rotation.length() > 0.0 {
+            if let Some(t) = tween {
+                t.abort();
+            }
+        }
+
+        // Tweens move the camera itself, the pivot follows along in front of it
+        if let Some(t) = tween {
+            if !t.is_aborted() {
+                self.orientation = t.update_angle().unwrap_or(self.orientation);
+                self.update_vectors();
+                if let Some(position) = t.update_pos() {
+                    self.pivot = position + self.forward * self.distance;
+                    self.target_pivot = self.pivot;
+                }
+            }
+        }
+
+        if tween.as_ref().is_some_and(Tween::is_finished) {
+            *tween = None;
+        }
+
+        let t = (delta_time * ORBIT_SMOOTHING).min(1.0);
+        self.pivot = self.pivot.lerp(self.target_pivot, t);
+        self.distance += (self.target_distance - self.distance) * t;
+
+        self.orientation.x = self.orientation.x.clamp(-89.9, 89.9);
+        self.orientation.y %= 360.0;
+
+        self.update_vectors();
+
+        self.rotation = look_rotation(self.orientation);
+    }
+
+    fn update_mouse(&mut self, delta: Vec2, scroll: f32) {
+        self.orientation += Vec2::new(delta.y * 0.8, delta.x) * 0.15;
+        self.orientation.x = self.orientation.x.clamp(-89.9, 89.9);
+        self.target_distance =
+            (self.target_distance * (1.0 - scroll * 0.1)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+        self.update_vectors();
+    }
+
+    fn update_gamepad(&mut self, movement: Vec2, look: Vec2, speed_mul: f32, delta_time: f32) {
+        self.target_distance = (self.target_distance
+            * (1.0 - movement.y * delta_time * speed_mul * 2.0))
+            .clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+        self.orientation += (look.yx() * Vec2::new(-1., 1.)) * 1.5;
+        self.orientation.y -= movement.x * delta_time * speed_mul * 90.0;
+
+        self.update_vectors();
+    }
+
+    fn set_orbit_pivot(&mut self, pivot: Vec3, distance: f32) {
+        self.target_pivot = pivot;
+        self.target_distance = distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    fn position_target(&self) -> Vec3 {
+        self.pivot
+    }
+
+    fn position(&self) -> Vec3 {
+        self.view_position()
+    }
+
+    fn orientation(&self) -> Vec2 {
+        self.orientation
+    }
+
+    fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    fn forward(&self) -> Vec3 {
+        self.forward
+    }
+
+    fn right(&self) -> Vec3 {
+        self.right
+    }
+
+    fn up(&self) -> Vec3 {
+        self.up
+    }
+
+    fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.view_position(), self.pivot, Vec3::Z)
+    }
+
+    fn view_angle(&self) -> Vec2 {
+        self.orientation
+    }
+
+    fn get_look_angle(&self, pos: Vec3) -> Vec2 {
+        super::get_look_angle(self.orientation, self.view_position(), pos)
+    }
+
+    /// Moves the camera without turning it, the pivot stays at the same distance in front of it
+    fn set_position(&mut self, position: Vec3) {
+        self.pivot = position + self.forward * self.distance;
+        self.target_pivot = self.pivot;
+    }
+
+    /// Turns the camera in place, moving the pivot along with the view direction
+    fn set_orientation(&mut self, orientation: Vec2) {
+        let position = self.view_position();
+        self.orientation = orientation;
+        self.update_vectors();
+        self.rotation = look_rotation(self.orientation);
+        self.set_position(position);
+    }
+}
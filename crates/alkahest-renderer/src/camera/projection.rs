@@ -1,4 +1,4 @@
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum CameraProjection {
     Perspective {
         /// Field of view in degrees
@@ -39,8 +39,9 @@ impl CameraProjection {
             Self::PerspectiveBounded { fov, near, far } => {
                 glam::Mat4::perspective_rh(fov.to_radians(), aspect, *near, *far)
             }
+            // Near and far are swapped for reverse Z, like the perspective projections
             Self::Orthographic { extents } => glam::Mat4::orthographic_rh(
-                -extents.x, extents.x, -extents.y, extents.y, -extents.z, extents.z,
+                -extents.x, extents.x, -extents.y, extents.y, extents.z, -extents.z,
             ),
         }
     }
@@ -0,0 +1,305 @@
+use glam::{Mat4, Quat, Vec2, Vec2Swizzles, Vec3};
+
+use super::{
+    collision::WalkCollision,
+    fps::{look_rotation, look_vectors},
+    tween::Tween,
+    Camera, CameraController, CameraMode,
+};
+use crate::{
+    ecs::Scene,
+    input::{InputState, Key},
+    renderer::Renderer,
+    resources::AppResources,
+    util::Vec3Ext,
+};
+
+/// Running speed in meters per second, close to a guardian's base movement speed
+const WALK_SPEED: f32 = 7.0;
+const GRAVITY: f32 = 20.0;
+const JUMP_VELOCITY: f32 = 7.0;
+/// Highest ledge that can be walked onto without jumping
+const STEP_HEIGHT: f32 = 0.5;
+/// Distance kept between the camera and walls
+const WALL_DISTANCE: f32 = 0.4;
+/// How far below the feet to look for ground
+const GROUND_PROBE_DISTANCE: f32 = 1000.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct WalkSettings {
+    /// Height of the camera above the ground
+    pub eye_height: f32,
+    pub gravity: bool,
+    pub jump: bool,
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self {
+            eye_height: 1.75,
+            gravity: true,
+            jump: true,
+        }
+    }
+}
+
+/// Camera that walks over collision geometry at player scale
+pub struct WalkCamera {
+    pub orientation: Vec2,
+    pub rotation: Quat,
+    pub forward: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    pub position: Vec3,
+
+    vertical_velocity: f32,
+    grounded: bool,
+    jump_requested: bool,
+    /// Position after the last ground update, walls are tested between this and the new position
+    last_position: Vec3,
+}
+
+impl WalkCamera {
+    fn update_vectors(&mut self) {
+        (self.forward, self.right, self.up) = look_vectors(self.orientation);
+    }
+}
+
+impl Default for WalkCamera {
+    fn default() -> Self {
+        Self {
+            rotation: Quat::IDENTITY,
+            forward: Vec3::Y,
+            right: -Vec3::X,
+            up: Vec3::Z,
+            position: Vec3::ZERO,
+            orientation: Vec2::ZERO,
+            vertical_velocity: 0.0,
+            grounded: false,
+            jump_requested: false,
+            last_position: Vec3::ZERO,
+        }
+    }
+}
+
+impl CameraController for WalkCamera {
+    fn update(
+        &mut self,
+        tween: &mut Option<Tween>,
+        input: &InputState,
+        delta_time: f32,
+        speed_mul: f32,
+        _smooth_movement: f32,
+        _smooth_look: f32,
+    ) {
+        let mut speed = delta_time * WALK_SPEED * speed_mul;
+        if input.shift() {
+            speed *= 1.5;
+        }
+        if input.ctrl() {
+            speed *= 0.3;
+        }
+
+        // Movement always happens on the ground plane, regardless of where the camera is looking
+        let forward = self.forward.flatten_xy(Vec3::X);
+        let right = self.right.flatten_xy(Vec3::Y);
+        let mut direction = Vec3::ZERO;
+        if input.is_key_down(Key::KeyW) {
+            direction += forward;
+        }
+        if input.is_key_down(Key::KeyS) {
+            direction -= forward;
+        }
+        if input.is_key_down(Key::KeyA) {
+            direction -= right;
+        }
+        if input.is_key_down(Key::KeyD) {
+            direction += right;
+        }
+        let direction = direction.normalize_or_zero();
+
+        if input.is_key_down(Key::Space) {
+            self.jump_requested = true;
+        }
+
+        if direction.length() > 0.0 {
+            if let Some(t) = tween {
+                t.abort();
+            }
+        }
+
+        match tween {
+            Some(t) if !t.is_aborted() => {
+                self.position = t.update_pos().unwrap_or(self.position);
+                self.orientation = t.update_angle().unwrap_or(self.orientation);
+                // Don't test walls against wherever the tween started
+                self.last_position = self.position;
+                self.vertical_velocity = 0.0;
+            }
+            _ => self.position += direction * speed,
+        }
+
+        if tween.as_ref().is_some_and(Tween::is_finished) {
+            *tween = None;
+        }
+
+        self.orientation.x = self.orientation.x.clamp(-89.9, 89.9);
+        self.orientation.y %= 360.0;
+
+        self.update_vectors();
+        self.rotation = look_rotation(self.orientation);
+    }
+
+    fn update_mouse(&mut self, delta: Vec2, _scroll_y: f32) {
+        self.orientation += Vec2::new(delta.y * 0.8, delta.x) * 0.15;
+
+        self.update_vectors();
+    }
+
+    fn update_gamepad(&mut self, movement: Vec2, look: Vec2, speed_mul: f32, delta_time: f32) {
+        let direction = self.forward.flatten_xy(Vec3::X) * movement.y
+            + self.right.flatten_xy(Vec3::Y) * movement.x;
+
+        self.position += direction * delta_time * WALK_SPEED * speed_mul;
+        self.orientation += (look.yx() * Vec2::new(-1., 1.)) * 1.5;
+
+        self.update_vectors();
+    }
+
+    fn update_ground(
+        &mut self,
+        settings: &WalkSettings,
+        delta_time: f32,
+        raycast: &dyn Fn(Vec3, Vec3) -> Option<Vec3>,
+    ) {
+        let jump = std::mem::take(&mut self.jump_requested);
+
+        // Walls are tested at knee height, anything lower is a step we can walk onto
+        let knee = Vec3::Z * (STEP_HEIGHT - settings.eye_height);
+        let movement = (self.position - self.last_position).with_z(0.0);
+        if let Some(direction) = movement.try_normalize() {
+            let start = self.last_position + knee;
+            let end = self.position.with_z(self.last_position.z) + direction * WALL_DISTANCE + knee;
+            if raycast(start, end).is_some() {
+                self.position.x = self.last_position.x;
+                self.position.y = self.last_position.y;
+            }
+        }
+
+        let feet = self.position - Vec3::Z * settings.eye_height;
+        let ground = raycast(
+            feet + Vec3::Z * STEP_HEIGHT,
+            feet - Vec3::Z * GROUND_PROBE_DISTANCE,
+        )
+        .map(|hit| hit.z);
+
+        match ground {
+            Some(ground) if settings.gravity => {
+                if self.grounded && jump && settings.jump {
+                    self.vertical_velocity = JUMP_VELOCITY;
+                    self.grounded = false;
+                } else if self.grounded && feet.z - ground > STEP_HEIGHT {
+                    // Walked off a ledge
+                    self.grounded = false;
+                }
+
+                if self.grounded {
+                    self.position.z = ground + settings.eye_height;
+                } else {
+                    self.vertical_velocity -= GRAVITY * delta_time;
+                    self.position.z += self.vertical_velocity * delta_time;
+                    if self.position.z - settings.eye_height <= ground {
+                        self.position.z = ground + settings.eye_height;
+                        self.vertical_velocity = 0.0;
+                        self.grounded = true;
+                    }
+                }
+            }
+            Some(ground) => {
+                self.position.z = ground + settings.eye_height;
+                self.vertical_velocity = 0.0;
+                self.grounded = true;
+            }
+            // Nothing to land on, stay at the current height instead of falling forever
+            None => {
+                self.vertical_velocity = 0.0;
+                self.grounded = false;
+            }
+        }
+
+        self.last_position = self.position;
+    }
+
+    fn position_target(&self) -> Vec3 {
+        self.position
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn orientation(&self) -> Vec2 {
+        self.orientation
+    }
+
+    fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    fn forward(&self) -> Vec3 {
+        self.forward
+    }
+
+    fn right(&self) -> Vec3 {
+        self.right
+    }
+
+    fn up(&self) -> Vec3 {
+        self.up
+    }
+
+    fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.position + self.forward, Vec3::Z)
+    }
+
+    fn view_angle(&self) -> Vec2 {
+        self.orientation
+    }
+
+    fn get_look_angle(&self, pos: Vec3) -> Vec2 {
+        super::get_look_angle(self.orientation, self.position, pos)
+    }
+
+    fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+        self.last_position = position;
+        self.vertical_velocity = 0.0;
+        self.grounded = false;
+    }
+
+    fn set_orientation(&mut self, orientation: Vec2) {
+        self.orientation = orientation;
+        self.update_vectors();
+    }
+}
+
+/// Keeps the camera on the ground while walking. Collides with the terrain and statics of the
+/// map, which are loaded the first time walk mode is used
+pub fn walk_camera_step(renderer: &Renderer, scene: &mut Scene, resources: &AppResources) {
+    let mut camera = resources.get_mut::<Camera>();
+    if camera.mode() != CameraMode::Walk {
+        return;
+    }
+
+    if !scene.contains_resource::<WalkCollision>() {
+        let collision = WalkCollision::load(scene);
+        scene.insert_resource(collision);
+    }
+
+    let mut collision = scene.resource_mut::<WalkCollision>();
+    // Nothing is hit while the geometry is loading, which keeps the camera at its current height
+    let world = collision.world();
+    camera.update_ground(renderer.delta_time.load() as f32, &|start, end| {
+        world.and_then(|w| w.raycast(start, end))
+    });
+}
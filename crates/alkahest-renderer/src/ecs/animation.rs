@@ -0,0 +1,217 @@
+//! Keyframed transform animations, used to preview moving platforms and other movers between
+//! their rest and end positions.
+//!
+//! Keyframes are only recorded by hand from the transform of the entity. Nothing is read from
+//! the game's mover resources: the only mover-like entity component we know of
+//! (`AscendantPlatform`, 0x80803058) has no known layout, so there are no paths to parse yet
+
+use bevy_ecs::{
+    component::Component,
+    system::{Query, Res},
+};
+use glam::{Quat, Vec3};
+use strum::EnumIter;
+
+use crate::{ecs::transform::Transform, renderer::RendererShared};
+
+/// Keyframes closer together than this are considered to be at the same time
+const KEYFRAME_TIME_EPSILON: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformKeyframe {
+    /// Time of the keyframe in seconds, relative to the start of the animation
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl TransformKeyframe {
+    pub fn from_transform(time: f32, transform: &Transform) -> Self {
+        Self {
+            time,
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumIter, strum::Display)]
+pub enum AnimationLoopMode {
+    /// Stops at the last keyframe
+    Once,
+    /// Jumps back to the first keyframe after the last one
+    #[default]
+    Loop,
+    /// Plays back and forth between the first and last keyframes
+    PingPong,
+}
+
+#[derive(Component, Clone)]
+pub struct TransformAnimation {
+    /// Sorted by time, the first keyframe is the rest position
+    keyframes: Vec<TransformKeyframe>,
+    pub playing: bool,
+    /// Playback time in seconds. Keeps counting past the end of the animation when looping
+    pub time: f32,
+    pub speed: f32,
+    pub loop_mode: AnimationLoopMode,
+}
+
+impl TransformAnimation {
+    /// Creates an animation with the given transform as the rest position
+    pub fn new(rest: &Transform) -> Self {
+        Self {
+            keyframes: vec![TransformKeyframe::from_transform(0.0, rest)],
+            playing: false,
+            time: 0.0,
+            speed: 1.0,
+            loop_mode: AnimationLoopMode::default(),
+        }
+    }
+
+    pub fn keyframes(&self) -> &[TransformKeyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Adds a keyframe with the given transform, replacing any keyframe at the same time
+    pub fn record(&mut self, time: f32, transform: &Transform) {
+        let keyframe = TransformKeyframe::from_transform(time.max(0.0), transform);
+        if let Some(existing) = self
+            .keyframes
+            .iter_mut()
+            .find(|k| (k.time - keyframe.time).abs() < KEYFRAME_TIME_EPSILON)
+        {
+            *existing = keyframe;
+        } else {
+            self.keyframes.push(keyframe);
+            self.sort_keyframes();
+        }
+    }
+
+    pub fn remove_keyframe(&mut self, index: usize) {
+        if index < self.keyframes.len() {
+            self.keyframes.remove(index);
+        }
+    }
+
+    pub fn set_keyframe_time(&mut self, index: usize, time: f32) {
+        if let Some(keyframe) = self.keyframes.get_mut(index) {
+            keyframe.time = time.max(0.0);
+            self.sort_keyframes();
+        }
+    }
+
+    fn sort_keyframes(&mut self) {
+        self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    /// Time within the keyframes that the playback time corresponds to
+    pub fn local_time(&self) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+
+        match self.loop_mode {
+            AnimationLoopMode::Once => self.time.clamp(0.0, duration),
+            AnimationLoopMode::Loop => self.time.rem_euclid(duration),
+            AnimationLoopMode::PingPong => {
+                let t = self.time.rem_euclid(duration * 2.0);
+                if t > duration {
+                    duration * 2.0 - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+
+    /// Moves the playback time forward, stopping at the end when the animation doesn't loop
+    pub fn advance(&mut self, delta: f32) {
+        self.time += delta * self.speed;
+        if self.loop_mode == AnimationLoopMode::Once && self.time >= self.duration() {
+            self.time = self.duration();
+            self.playing = false;
+        }
+    }
+
+    /// Interpolates the keyframes at the given time within the animation
+    pub fn sample(&self, time: f32) -> Option<TransformKeyframe> {
+        let first = self.keyframes.first()?;
+        let next_index = self.keyframes.partition_point(|k| k.time <= time);
+        if next_index == 0 {
+            return Some(*first);
+        }
+        if next_index == self.keyframes.len() {
+            return self.keyframes.last().copied();
+        }
+
+        let a = &self.keyframes[next_index - 1];
+        let b = &self.keyframes[next_index];
+        let t = ((time - a.time) / (b.time - a.time)).clamp(0.0, 1.0);
+        Some(TransformKeyframe {
+            time,
+            translation: a.translation.lerp(b.translation, t),
+            rotation: a.rotation.slerp(b.rotation, t),
+            scale: a.scale.lerp(b.scale, t),
+        })
+    }
+
+    /// Moves the transform to where the animation is at the current playback time
+    pub fn apply(&self, transform: &mut Transform) {
+        if let Some(keyframe) = self.sample(self.local_time()) {
+            transform.translation = keyframe.translation;
+            transform.rotation = keyframe.rotation;
+            transform.scale = keyframe.scale;
+        }
+    }
+}
+
+/// Advances the transform animations that are playing. Follows the scene time, so pausing or
+/// slowing down the scene does the same to the animations
+pub fn animate_transforms_system(
+    renderer: Res<RendererShared>,
+    mut q_animations: Query<(&mut TransformAnimation, &mut Transform)>,
+) {
+    let delta = renderer.delta_time.load() as f32 * renderer.time.load().speed();
+    for (mut animation, mut transform) in q_animations.iter_mut() {
+        if !animation.playing {
+            continue;
+        }
+
+        animation.advance(delta);
+        animation.apply(&mut transform);
+    }
+}
+
+#[test]
+fn test_transform_animation_sampling() {
+    let mut animation = TransformAnimation::new(&Transform::from_translation(Vec3::ZERO));
+    animation.record(2.0, &Transform::from_translation(Vec3::new(4.0, 0.0, 0.0)));
+    assert_eq!(animation.duration(), 2.0);
+    assert_eq!(
+        animation.sample(1.0).unwrap().translation,
+        Vec3::new(2.0, 0.0, 0.0)
+    );
+    assert_eq!(
+        animation.sample(3.0).unwrap().translation,
+        Vec3::new(4.0, 0.0, 0.0)
+    );
+
+    animation.time = 3.0;
+    animation.loop_mode = AnimationLoopMode::Loop;
+    assert_eq!(animation.local_time(), 1.0);
+    animation.loop_mode = AnimationLoopMode::PingPong;
+    assert_eq!(animation.local_time(), 1.0);
+    animation.time = 3.5;
+    assert_eq!(animation.local_time(), 0.5);
+    animation.loop_mode = AnimationLoopMode::Once;
+    assert_eq!(animation.local_time(), 2.0);
+}
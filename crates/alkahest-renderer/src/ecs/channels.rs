@@ -2,7 +2,7 @@ use bevy_ecs::{
     component::Component,
     entity::Entity,
     query::Without,
-    system::{In, Query},
+    system::{Query, Res},
 };
 use glam::Vec4;
 use rustc_hash::FxHashMap;
@@ -19,7 +19,7 @@ pub struct ObjectChannels {
 // Discover channels used by dynamic objects by going over every object with a DynamicModelComponent that doesn't already have a ObjectChannels component
 // If the dynamic model's techniques haven't been loaded by the asset system yet, this system will skip it and try again next time
 pub fn object_channels_discovery_system(
-    In(renderer): In<RendererShared>,
+    renderer: Res<RendererShared>,
     mut commands: bevy_ecs::system::Commands,
     q_dynamic_model: Query<(Entity, &DynamicModelComponent), Without<ObjectChannels>>,
 ) {
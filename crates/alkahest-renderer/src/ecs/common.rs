@@ -10,7 +10,7 @@ use super::visibility::VisibilityBundle;
 #[derive(Component, Copy, Clone)]
 pub struct EntityWorldId(pub u64);
 
-#[derive(Component, strum::Display, Copy, Clone, PartialEq, Eq)]
+#[derive(Component, strum::Display, strum::EnumIter, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ResourceOrigin {
     Map,
 
@@ -19,8 +19,31 @@ pub enum ResourceOrigin {
     Ambient,
 }
 
+impl ResourceOrigin {
+    /// Abbreviation shown next to node icons
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ResourceOrigin::Map => "M",
+            ResourceOrigin::Activity => "A",
+            ResourceOrigin::ActivityBruteforce => "Ab",
+            ResourceOrigin::Ambient => "AM",
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        match self {
+            ResourceOrigin::Map => Color32::LIGHT_RED,
+            ResourceOrigin::Activity => Color32::GREEN,
+            ResourceOrigin::ActivityBruteforce => Color32::RED,
+            ResourceOrigin::Ambient => Color32::from_rgb(0, 255, 255),
+        }
+    }
+}
+
 // pub struct HavokShape(pub TagHash, pub Option<CustomDebugShape>);
 
+/// Activity phase (by name hash) an entity was loaded from
+#[derive(Component, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct ActivityGroup(pub u32);
 
 #[derive(Component, Clone)]
@@ -59,6 +82,8 @@ pub struct Label {
     pub label: String,
     pub default: bool,
     pub offset: Vec3,
+    /// Label made from raw hashes, if `label` has been resolved to a readable name
+    pub raw: Option<String>,
 }
 
 impl Display for Label {
@@ -74,12 +99,26 @@ impl Label {
             label: s.to_string(),
             default: true,
             offset: Vec3::new(0.0, 0.0, 0.0),
+            raw: None,
         }
     }
     pub fn with_offset(mut self, x: f32, y: f32, z: f32) -> Self {
         self.offset = Vec3::new(x, y, z);
         self
     }
+
+    pub fn with_raw(mut self, raw: impl Into<String>) -> Self {
+        self.raw = Some(raw.into());
+        self
+    }
+
+    /// The hash-based label when `raw` is set, falling back to the readable one
+    pub fn text(&self, raw: bool) -> &str {
+        match &self.raw {
+            Some(raw_label) if raw => raw_label,
+            _ => &self.label,
+        }
+    }
 }
 
 impl From<&str> for Label {
@@ -88,6 +127,7 @@ impl From<&str> for Label {
             label: s.to_string(),
             default: false,
             offset: Vec3::new(0.0, 0.0, 0.0),
+            raw: None,
         }
     }
 }
@@ -98,6 +138,7 @@ impl From<String> for Label {
             label: s,
             default: false,
             offset: Vec3::new(0.0, 0.0, 0.0),
+            raw: None,
         }
     }
 }
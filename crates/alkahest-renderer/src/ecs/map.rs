@@ -7,6 +7,7 @@ use destiny_pkg::TagHash;
 use glam::Vec3;
 
 use crate::{
+    ecs::render::mesh_info::read_color_stream,
     gpu::{texture::Texture, GpuContext},
     handle::Handle,
     loaders::{
@@ -18,11 +19,11 @@ use crate::{
 
 #[derive(Resource)]
 pub struct MapAtmosphere {
-    _data: SMapAtmosphere,
+    data: SMapAtmosphere,
     lookup_0: Option<Texture>,
-    _lookup_1: Option<Texture>,
+    lookup_1: Option<Texture>,
     lookup_2: Option<Texture>,
-    _lookup_3: Option<Texture>,
+    lookup_3: Option<Texture>,
     unkd0: Option<Texture>,
 }
 
@@ -55,15 +56,50 @@ impl MapAtmosphere {
             .transpose()?;
 
         Ok(MapAtmosphere {
-            _data: data,
+            data,
             lookup_0,
-            _lookup_1: lookup_1,
+            lookup_1,
             lookup_2,
-            _lookup_3: lookup_3,
+            lookup_3,
             unkd0,
         })
     }
 
+    pub fn data(&self) -> &SMapAtmosphere {
+        &self.data
+    }
+
+    /// Every texture referenced by the atmosphere, with the name of the field it's in. Fields
+    /// without a texture are left out
+    pub fn textures(&self) -> Vec<(&'static str, TagHash, &Texture)> {
+        [
+            (
+                "lookup_texture_0",
+                self.data.lookup_texture_0,
+                &self.lookup_0,
+            ),
+            (
+                "lookup_texture_1",
+                self.data.lookup_texture_1,
+                &self.lookup_1,
+            ),
+            (
+                "lookup_texture_2",
+                self.data.lookup_texture_2,
+                &self.lookup_2,
+            ),
+            (
+                "lookup_texture_3",
+                self.data.lookup_texture_3,
+                &self.lookup_3,
+            ),
+            ("unkd0", self.data.unkd0, &self.unkd0),
+        ]
+        .into_iter()
+        .filter_map(|(name, hash, texture)| Some((name, hash.hash32(), texture.as_ref()?)))
+        .collect()
+    }
+
     pub fn update_extern(&self, x: &mut externs::Atmosphere, far: bool) {
         let lf_unk30: TextureView = self
             .lookup_0
@@ -134,6 +170,7 @@ pub struct NodeMetadata {
 #[derive(Resource)]
 pub struct MapStaticAO {
     pub ao_buffer: VertexBuffer,
+    pub ao_buffer_hash: TagHash,
     pub offset_map: HashMap<u64, u32>,
 }
 
@@ -150,7 +187,17 @@ impl MapStaticAO {
 
         Ok(MapStaticAO {
             ao_buffer,
+            ao_buffer_hash: tag.ao0.buffer,
             offset_map,
         })
     }
+
+    /// Reads the AO values of every vertex from the package, the GPU buffer can't be read back
+    pub fn read_ao(&self) -> anyhow::Result<Vec<f32>> {
+        Ok(read_color_stream(self.ao_buffer_hash)
+            .context("Failed to read AO buffer")?
+            .into_iter()
+            .map(|v| v.x)
+            .collect())
+    }
 }
@@ -2,6 +2,7 @@ use bevy_ecs::system::Resource;
 use destiny_pkg::TagHash;
 use resources::SelectedEntity;
 
+pub mod animation;
 pub mod audio;
 pub mod channels;
 pub mod common;
@@ -10,7 +11,10 @@ pub mod hierarchy;
 pub mod map;
 pub mod render;
 pub mod resources;
+pub mod respawn;
 pub mod route;
+pub mod schedule;
+pub mod skeleton;
 pub mod tags;
 pub mod transform;
 pub mod utility;
@@ -14,7 +14,7 @@ use crate::{
     gpu::{buffer::ConstantBuffer, global_state::RenderStates},
     gpu_event,
     loaders::vertex_buffer::{load_vertex_buffer, VertexBuffer},
-    renderer::Renderer,
+    renderer::{Renderer, StatsCategory},
     tfx::externs,
 };
 
@@ -173,6 +173,12 @@ impl DecoratorRenderer {
                         0,
                         instance_start,
                     );
+                    renderer.stats.record_draw(
+                        StatsCategory::Decorators,
+                        part.index_count,
+                        part.primitive_type,
+                        instance_count,
+                    );
                 },
             )?;
         }
@@ -18,7 +18,14 @@ use tiger_parse::PackageManagerExt;
 use crate::{
     ecs::{
         channels::ObjectChannels,
-        render::{decorators::DecoratorRenderer, static_geometry::ModelBuffers},
+        common::{ActivityGroup, ResourceOrigin},
+        render::{
+            decorators::DecoratorRenderer,
+            lod::LodLevels,
+            stages::{RenderStageOverride, RenderStageOverrideHelper},
+            static_geometry::ModelBuffers,
+            TechniqueOverride,
+        },
         transform::Transform,
         visibility::{ViewVisibility, VisibilityHelper},
         Scene,
@@ -27,7 +34,7 @@ use crate::{
     gpu_event,
     handle::Handle,
     loaders::AssetManager,
-    renderer::Renderer,
+    renderer::{Renderer, StatsCategory, StatsSource},
     tfx::{externs, scope::ScopeSkinning, technique::Technique, view::RenderStageSubscriptions},
     util::packages::TagHashExt,
 };
@@ -42,10 +49,18 @@ pub struct DynamicModel {
     pub mesh_stages: Vec<RenderStageSubscriptions>,
     pub subscribed_stages: RenderStageSubscriptions,
     part_techniques: Vec<Vec<Handle<Technique>>>,
+    pub technique_override: Option<TechniqueOverride>,
 
     pub selected_mesh: usize,
     pub selected_variant: usize,
     variant_count: usize,
+    /// LOD levels of all meshes combined
+    pub lod_levels: LodLevels,
+    /// LOD level that gets drawn, picked by [`select_lod_system`](super::lod::select_lod_system)
+    pub selected_lod: u8,
+    /// Screen coverage the LOD level was picked from, used to stream in fewer mips for distant
+    /// models. Infinite when unknown
+    pub screen_coverage: f32,
 
     identifier_count: usize,
 
@@ -54,6 +69,14 @@ pub struct DynamicModel {
 }
 
 impl DynamicModel {
+    /// Every technique the model can draw with, including part techniques
+    pub fn techniques(&self) -> Vec<Handle<Technique>> {
+        let mut techniques = self.techniques.clone();
+        techniques.extend(self.part_techniques.iter().flatten().cloned());
+
+        FxHashSet::from_iter(techniques).into_iter().collect()
+    }
+
     pub fn load(
         am: &mut AssetManager,
         hash: TagHash,
@@ -116,11 +139,21 @@ impl DynamicModel {
             .unwrap_or(0) as usize
             + 1;
 
+        let lod_levels = LodLevels::from_categories(
+            model
+                .meshes
+                .iter()
+                .flat_map(|m| m.parts.iter().map(|p| p.lod_category)),
+        );
+
         Ok(Self {
             selected_variant: 0,
             variant_count,
             selected_mesh: 0,
             identifier_count,
+            selected_lod: lod_levels.resolve(0),
+            screen_coverage: f32::INFINITY,
+            lod_levels,
             mesh_buffers,
             technique_map,
             techniques,
@@ -130,6 +163,7 @@ impl DynamicModel {
                 .fold(RenderStageSubscriptions::empty(), |acc, &x| acc | x),
             mesh_stages,
             part_techniques,
+            technique_override: None,
             hash,
             feature_type,
         })
@@ -175,11 +209,19 @@ impl DynamicModel {
             render_stage,
             identifier,
             object_channels,
-            |_, renderer, _mesh, part| unsafe {
-                renderer
-                    .gpu
-                    .lock_context()
-                    .DrawIndexed(part.index_count, part.index_start, 0);
+            |model, renderer, _mesh, part| {
+                unsafe {
+                    renderer
+                        .gpu
+                        .lock_context()
+                        .DrawIndexed(part.index_count, part.index_start, 0);
+                }
+                renderer.stats.record_draw(
+                    StatsCategory::from_feature(model.feature_type),
+                    part.index_count,
+                    part.primitive_type,
+                    1,
+                );
             },
         )
     }
@@ -195,20 +237,6 @@ impl DynamicModel {
     where
         F: Fn(&Self, &Renderer, &SDynamicMesh, &SDynamicMeshPart),
     {
-        if !renderer.settings.stage_transparent && render_stage == TfxRenderStage::Transparents {
-            return Ok(());
-        }
-
-        if !renderer.settings.stage_decals && render_stage == TfxRenderStage::Decals {
-            return Ok(());
-        }
-
-        if !renderer.settings.stage_decals_additive
-            && render_stage == TfxRenderStage::DecalsAdditive
-        {
-            return Ok(());
-        }
-
         gpu_event!(
             renderer.gpu,
             self.feature_type.short(),
@@ -216,12 +244,14 @@ impl DynamicModel {
         );
 
         profiling::scope!("DynamicModel::draw", format!("mesh={}", self.selected_mesh));
+        let _coverage = renderer.draw_coverage_scope(self.screen_coverage);
         // ensure!(self.selected_mesh < self.mesh_count(), "Invalid mesh index");
         // ensure!(
         //     self.selected_variant < self.variant_count() || self.variant_count() == 0,
         //     "Material variant out of range"
         // );
 
+        let stats_category = StatsCategory::from_feature(self.feature_type);
         let mesh = &self.model.meshes[self.selected_mesh];
         let stages = &self.mesh_stages[self.selected_mesh];
         if !stages.is_subscribed(render_stage) {
@@ -238,32 +268,48 @@ impl DynamicModel {
                 continue;
             }
 
-            if !part.lod_category.is_highest_detail() {
+            if part.lod_category.level() != self.selected_lod {
                 continue;
             }
 
-            let variant_material =
-                self.get_variant_technique(part.variant_shader_index, self.selected_variant);
-
             let mut all_scopes = TfxScopeBits::empty();
-            if let Some(technique) =
-                renderer.get_technique_shared(&self.part_techniques[self.selected_mesh][part_index])
+            if let Some(technique_override) = self
+                .technique_override
+                .as_ref()
+                .and_then(|o| o.technique_for_stage(render_stage))
             {
-                technique
-                    .bind_with_channels(renderer, object_channels)
-                    .expect("Failed to bind technique");
-                all_scopes |= technique.used_scopes;
-                // } else {
-                //     continue;
-            }
+                if let Some(technique) = renderer.get_technique_shared(technique_override) {
+                    technique
+                        .bind_with_channels(renderer, object_channels)
+                        .expect("Failed to bind override technique");
+                    renderer.stats.record_technique(stats_category, &technique);
+                    all_scopes |= technique.used_scopes;
+                }
+            } else {
+                let variant_material =
+                    self.get_variant_technique(part.variant_shader_index, self.selected_variant);
+
+                if let Some(technique) = renderer
+                    .get_technique_shared(&self.part_techniques[self.selected_mesh][part_index])
+                {
+                    technique
+                        .bind_with_channels(renderer, object_channels)
+                        .expect("Failed to bind technique");
+                    renderer.stats.record_technique(stats_category, &technique);
+                    all_scopes |= technique.used_scopes;
+                    // } else {
+                    //     continue;
+                }
 
-            if let Some(technique) = variant_material
-                .and_then(|t| renderer.data.lock().asset_manager.techniques.get_shared(&t))
-            {
-                technique
-                    .bind_with_channels(renderer, object_channels)
-                    .expect("Failed to bind variant technique");
-                all_scopes |= technique.used_scopes;
+                if let Some(technique) = variant_material
+                    .and_then(|t| renderer.data.lock().asset_manager.techniques.get_shared(&t))
+                {
+                    technique
+                        .bind_with_channels(renderer, object_channels)
+                        .expect("Failed to bind variant technique");
+                    renderer.stats.record_technique(stats_category, &technique);
+                    all_scopes |= technique.used_scopes;
+                }
             }
 
             if stages.contains(RenderStageSubscriptions::COMPUTE_SKINNING)
@@ -357,15 +403,19 @@ impl DynamicModelComponent {
     pub(self) fn update_cbuffer(&mut self, transform: &Transform) {
         let ext = self.create_extern(transform);
 
-        self.cbuffer.write(&ext).unwrap();
+        // Writes only fail when the device has been lost, which is recovered from next frame
+        if let Err(e) = self.cbuffer.write(&ext) {
+            error!("Failed to write dynamic model constants: {e:?}");
+        }
         if let Some(cbuffer_skinning) = &mut self.cbuffer_skinning {
-            cbuffer_skinning
-                .write(&ScopeSkinning {
-                    offset_scale: transform.translation.extend(transform.scale.x),
-                    texcoord0_scale_offset: ext.texcoord0_scale_offset,
-                    ..Default::default()
-                })
-                .unwrap();
+            let result = cbuffer_skinning.write(&ScopeSkinning {
+                offset_scale: transform.translation.extend(transform.scale.x),
+                texcoord0_scale_offset: ext.texcoord0_scale_offset,
+                ..Default::default()
+            });
+            if let Err(e) = result {
+                error!("Failed to write dynamic model skinning constants: {e:?}");
+            }
         }
 
         self.ext = ext;
@@ -396,10 +446,7 @@ impl DynamicModelComponent {
     }
 
     pub fn techniques(&self) -> Vec<Handle<Technique>> {
-        let mut techniques = self.model.techniques.clone();
-        techniques.extend(self.model.part_techniques.iter().flatten().cloned());
-
-        FxHashSet::from_iter(techniques).into_iter().collect()
+        self.model.techniques()
     }
 }
 
@@ -413,14 +460,27 @@ pub fn draw_dynamic_model_system(
         &format!("render_stage={render_stage:?}")
     );
 
+    let settings = renderer.settings();
     let mut entities = Vec::new();
-    for (e, dynamic, vis) in scene
-        .query::<(Entity, &DynamicModelComponent, Option<&ViewVisibility>)>()
+    for (e, dynamic, vis, stage_override) in scene
+        .query::<(
+            Entity,
+            &DynamicModelComponent,
+            Option<&ViewVisibility>,
+            Option<&RenderStageOverride>,
+        )>()
         .iter(scene)
     {
         // Sky objects are rendered by a separate system, so we filter them out here
-        if vis.is_visible(renderer.active_view)
-            && renderer.should_render(Some(render_stage), Some(dynamic.model.feature_type))
+        if vis.is_visible(renderer.active_view())
+            && stage_override.should_render(
+                render_stage,
+                renderer.should_render_with(
+                    &settings,
+                    Some(render_stage),
+                    Some(dynamic.model.feature_type),
+                ),
+            )
             && dynamic.model.feature_type != TfxFeatureRenderer::SkyTransparent
         {
             entities.push((e, dynamic.model.feature_type));
@@ -436,24 +496,49 @@ pub fn draw_dynamic_model_system(
     for (e, _feature_type) in entities {
         let dynamic = scene.get::<DynamicModelComponent>(e).unwrap();
         let object_channels = scene.get::<ObjectChannels>(e);
+        let source = renderer
+            .stats
+            .is_enabled()
+            .then(|| {
+                StatsSource::new(
+                    scene.get::<ResourceOrigin>(e),
+                    scene.get::<ActivityGroup>(e),
+                )
+            })
+            .unwrap_or_default();
 
         renderer.pickbuffer.with_entity(e, || {
-            dynamic
-                .draw(renderer, render_stage, object_channels)
-                .unwrap();
+            renderer.stats.with_source(source, || {
+                dynamic
+                    .draw(renderer, render_stage, object_channels)
+                    .unwrap();
+            });
         });
     }
 
-    if renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::SpeedtreeTrees)) {
-        for (e, decorator, vis) in scene
-            .query::<(Entity, &DecoratorRenderer, Option<&ViewVisibility>)>()
-            .iter(scene)
+    let decorators_enabled =
+        renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::SpeedtreeTrees));
+    for (e, decorator, vis, origin, group, stage_override) in scene
+        .query::<(
+            Entity,
+            &DecoratorRenderer,
+            Option<&ViewVisibility>,
+            Option<&ResourceOrigin>,
+            Option<&ActivityGroup>,
+            Option<&RenderStageOverride>,
+        )>()
+        .iter(scene)
+    {
+        if vis.is_visible(renderer.active_view())
+            && stage_override.should_render(render_stage, decorators_enabled)
         {
-            if vis.is_visible(renderer.active_view) {
-                renderer.pickbuffer.with_entity(e, || {
-                    decorator.draw(renderer, render_stage).unwrap();
-                });
-            }
+            renderer.pickbuffer.with_entity(e, || {
+                renderer
+                    .stats
+                    .with_source(StatsSource::new(origin, group), || {
+                        decorator.draw(renderer, render_stage).unwrap();
+                    });
+            });
         }
     }
 }
@@ -463,9 +548,8 @@ pub fn draw_sky_objects_system(
     scene: &mut Scene,
     render_stage: TfxRenderStage,
 ) {
-    if !renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::SkyTransparent)) {
-        return;
-    }
+    let sky_enabled =
+        renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::SkyTransparent));
     profiling::scope!(
         "draw_sky_object_system",
         &format!("render_stage={render_stage:?}")
@@ -488,13 +572,15 @@ pub fn draw_sky_objects_system(
             &Transform,
             &DynamicModelComponent,
             Option<&ViewVisibility>,
+            Option<&RenderStageOverride>,
         )>()
         .iter(scene)
-        .filter(|(_e, _transform, dynamic, view_vis)| {
-            view_vis.is_visible(renderer.active_view)
+        .filter(|(_e, _transform, dynamic, view_vis, stage_override)| {
+            view_vis.is_visible(renderer.active_view())
                 && dynamic.model.feature_type == TfxFeatureRenderer::SkyTransparent
+                && stage_override.should_render(render_stage, sky_enabled)
         })
-        .map(|(e, transform, _, _)| (e, transform.translation.distance_squared(view_position)))
+        .map(|(e, transform, _, _, _)| (e, transform.translation.distance_squared(view_position)))
         .collect_vec();
 
     entities_visible.sort_by(|(_, a), (_, b)| b.total_cmp(a));
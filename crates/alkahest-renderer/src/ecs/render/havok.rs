@@ -3,7 +3,7 @@ use anyhow::Context;
 use bevy_ecs::{
     entity::Entity,
     prelude::Component,
-    system::{In, Query, Res},
+    system::{Query, Res},
 };
 use destiny_havok::shape_collection;
 use glam::{Vec3, Vec4Swizzles};
@@ -39,6 +39,9 @@ pub struct HavokShapeRenderer {
     index_count: u32,
 
     cb_debug_shape: ConstantBuffer<HavokShapeScope>,
+
+    /// Local space triangles of the shape, for CPU-side intersection tests
+    triangles: Vec<[Vec3; 3]>,
 }
 
 impl HavokShapeRenderer {
@@ -69,9 +72,46 @@ impl HavokShapeRenderer {
             outline_index_count: indices_outline.len() as _,
             index_count: indices.len() as _,
             cb_debug_shape: ConstantBuffer::create(gpu.clone(), None)?,
+            triangles: shape
+                .indices
+                .chunks_exact(3)
+                .map(|i| {
+                    [
+                        shape.vertices[i[0] as usize],
+                        shape.vertices[i[1] as usize],
+                        shape.vertices[i[2] as usize],
+                    ]
+                })
+                .collect(),
         })
     }
 
+    /// Local space triangles of the shape
+    pub fn triangles(&self) -> &[[Vec3; 3]] {
+        &self.triangles
+    }
+
+    /// Returns the point closest to `start` where the segment from `start` to `end` crosses the
+    /// shape, if it does
+    pub fn intersect_segment(&self, transform: &Transform, start: Vec3, end: Vec3) -> Option<Vec3> {
+        let local_to_world = transform.local_to_world();
+        let direction = end - start;
+
+        self.triangles
+            .iter()
+            .filter_map(|t| {
+                segment_triangle_intersection(
+                    start,
+                    direction,
+                    local_to_world.transform_point3(t[0]),
+                    local_to_world.transform_point3(t[1]),
+                    local_to_world.transform_point3(t[2]),
+                )
+            })
+            .min_by(|a, b| a.total_cmp(b))
+            .map(|t| start + direction * t)
+    }
+
     pub fn draw(&self, gpu: &GpuContext, transform: &Transform, color: Color) {
         gpu_event!(gpu, "havok_shape");
         self.vb.bind_single(gpu, 0);
@@ -103,6 +143,39 @@ impl HavokShapeRenderer {
     }
 }
 
+/// Möller–Trumbore, returns the intersection as a fraction of `direction`
+pub(crate) fn segment_triangle_intersection(
+    origin: Vec3,
+    direction: Vec3,
+    v0: Vec3,
+    v1: Vec3,
+    v2: Vec3,
+) -> Option<f32> {
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let p = direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = det.recip();
+    let s = origin - v0;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+    (0.0..=1.0).contains(&t).then_some(t)
+}
+
 pub fn remove_diagonals_linegulate(vertices: &[(Vec3, Vec3)], indices: &[u16]) -> Vec<u16> {
     let mut indices_outline = vec![];
     for i in indices.chunks_exact(3) {
@@ -169,7 +242,7 @@ pub fn calculate_mesh_normals_flat(
 
 #[allow(clippy::type_complexity)]
 pub fn draw_debugshapes_system(
-    In(renderer): In<RendererShared>,
+    renderer: Res<RendererShared>,
     selected: Res<SelectedEntity>,
     q_ruler: Query<(
         Entity,
@@ -180,11 +253,11 @@ pub fn draw_debugshapes_system(
     )>,
 ) {
     for (e, transform, shape, filter, vis) in q_ruler.iter() {
-        if !vis.is_visible(renderer.active_view) {
+        if !vis.is_visible(renderer.active_view()) {
             continue;
         }
         let color = if let Some(filter) = filter {
-            if !renderer.lastfilters.contains(filter) {
+            if !renderer.lastfilters.read().contains(filter) {
                 continue;
             }
 
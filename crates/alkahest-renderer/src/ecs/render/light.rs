@@ -9,13 +9,14 @@ use genmesh::{
     generators::{IndexedPolygon, SharedVertex},
     Triangulate,
 };
-use glam::{Mat4, UVec2, Vec4, Vec4Swizzles};
+use glam::{Mat4, UVec2, Vec3, Vec4, Vec4Swizzles};
 use windows::Win32::Graphics::{
     Direct3D11::{
-        ID3D11Buffer, ID3D11DepthStencilState, D3D11_BIND_INDEX_BUFFER, D3D11_BIND_VERTEX_BUFFER,
-        D3D11_BUFFER_DESC, D3D11_CLEAR_DEPTH, D3D11_CLEAR_STENCIL, D3D11_COMPARISON_ALWAYS,
-        D3D11_DEPTH_STENCILOP_DESC, D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ZERO,
-        D3D11_STENCIL_OP_DECR, D3D11_STENCIL_OP_INCR, D3D11_STENCIL_OP_KEEP,
+        ID3D11Buffer, ID3D11DepthStencilState, ID3D11RasterizerState, D3D11_BIND_INDEX_BUFFER,
+        D3D11_BIND_VERTEX_BUFFER, D3D11_BUFFER_DESC, D3D11_CLEAR_DEPTH, D3D11_CLEAR_STENCIL,
+        D3D11_COMPARISON_ALWAYS, D3D11_CULL_NONE, D3D11_DEPTH_STENCILOP_DESC,
+        D3D11_DEPTH_STENCIL_DESC, D3D11_DEPTH_WRITE_MASK_ZERO, D3D11_FILL_SOLID,
+        D3D11_RASTERIZER_DESC, D3D11_STENCIL_OP_DECR, D3D11_STENCIL_OP_INCR, D3D11_STENCIL_OP_KEEP,
         D3D11_SUBRESOURCE_DATA, D3D11_USAGE_IMMUTABLE,
     },
     Dxgi::Common::DXGI_FORMAT_R16_UINT,
@@ -34,7 +35,7 @@ use crate::{
     handle::Handle,
     icons::{ICON_LIGHTBULB_FLUORESCENT_TUBE, ICON_LIGHTBULB_ON, ICON_SPOTLIGHT_BEAM},
     loaders::AssetManager,
-    renderer::{gbuffer::ShadowDepthMap, Renderer, ShadowQuality},
+    renderer::{gbuffer::ShadowDepthMap, RenderDebugView, Renderer},
     tfx::{
         externs::{self, TextureView},
         technique::Technique,
@@ -212,7 +213,17 @@ impl LightRenderer {
         })
     }
 
-    fn draw(&self, renderer: &Renderer, draw_shadows: bool) {
+    pub(crate) fn index_count(&self) -> u32 {
+        self.cube_index_count
+    }
+
+    /// Draws the light volume, with the instance count taken from `indirect_args` if set
+    fn draw(
+        &self,
+        renderer: &Renderer,
+        draw_shadows: bool,
+        indirect_args: Option<(&ID3D11Buffer, u32)>,
+    ) {
         gpu_event!(renderer.gpu, &self.debug_label);
         unsafe {
             renderer
@@ -243,31 +254,98 @@ impl LightRenderer {
                 return;
             }
 
-            renderer
-                .gpu
-                .lock_context()
-                .IASetIndexBuffer(Some(&self.ib_cube), DXGI_FORMAT_R16_UINT, 0);
+            renderer.gpu.lock_context().IASetIndexBuffer(
+                Some(&self.ib_cube),
+                DXGI_FORMAT_R16_UINT,
+                0,
+            );
 
             renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
 
-            renderer
-                .gpu
-                .lock_context()
-                .DrawIndexed(self.cube_index_count, 0, 0);
+            if let Some((args, offset)) = indirect_args {
+                renderer
+                    .gpu
+                    .lock_context()
+                    .DrawIndexedInstancedIndirect(args, offset);
+            } else {
+                renderer
+                    .gpu
+                    .lock_context()
+                    .DrawIndexed(self.cube_index_count, 0, 0);
+            }
         }
     }
 }
 
+/// Volumes of all lights, in the order they are drawn by [`draw_light_system`]
+fn light_volumes(renderer: &Renderer, scene: &mut Scene) -> Vec<Option<(Mat4, u32)>> {
+    let mut volumes = vec![];
+    for (transform, light_renderer, light, vis) in scene
+        .query::<(&Transform, &LightRenderer, &SLight, Option<&ViewVisibility>)>()
+        .iter(scene)
+    {
+        volumes.push(vis.is_visible(renderer.active_view()).then(|| {
+            (
+                transform.local_to_world() * light.light_to_world,
+                light_renderer.index_count(),
+            )
+        }));
+    }
+
+    for (transform, light_renderer, light, vis) in scene
+        .query::<(
+            &Transform,
+            &LightRenderer,
+            &SShadowingLight,
+            Option<&ViewVisibility>,
+        )>()
+        .iter(scene)
+    {
+        volumes.push(vis.is_visible(renderer.active_view()).then(|| {
+            (
+                transform.local_to_world() * light.light_to_world,
+                light_renderer.index_count(),
+            )
+        }));
+    }
+
+    volumes
+}
+
 pub fn draw_light_system(renderer: &Renderer, scene: &mut Scene) {
     profiling::scope!("draw_light_system");
+    let settings = renderer.settings();
+    // The clusters are also built for the light volume count view when culling is disabled
+    let clusters = (settings.light_occlusion_culling
+        || settings.effective_debug_view() == RenderDebugView::LightVolumeCounts)
+        .then(|| {
+            let volumes = light_volumes(renderer, scene);
+            renderer.cull_light_volumes(&volumes)
+        })
+        .flatten();
+    let clusters = clusters
+        .as_ref()
+        .filter(|_| settings.light_occlusion_culling);
+
+    let mut light_index = 0;
     for (transform, light_renderer, light, vis) in scene
         .query::<(&Transform, &LightRenderer, &SLight, Option<&ViewVisibility>)>()
         .iter(scene)
     {
-        if !vis.is_visible(renderer.active_view) {
+        let index = light_index;
+        light_index += 1;
+        if !vis.is_visible(renderer.active_view()) {
             continue;
         }
 
+        let indirect_args = match clusters {
+            Some(clusters) => match clusters.draw_args(index) {
+                Some(args) => Some(args),
+                None => continue,
+            },
+            None => None,
+        };
+
         {
             let externs = &mut renderer.data.lock().externs;
             let Some(view) = &externs.view else {
@@ -298,7 +376,7 @@ pub fn draw_light_system(renderer: &Renderer, scene: &mut Scene) {
             });
         }
 
-        light_renderer.draw(renderer, false);
+        light_renderer.draw(renderer, false, indirect_args);
     }
 
     for (transform, light_renderer, light, shadowmap, vis) in scene
@@ -311,10 +389,20 @@ pub fn draw_light_system(renderer: &Renderer, scene: &mut Scene) {
         )>()
         .iter(scene)
     {
-        if !vis.is_visible(renderer.active_view) {
+        let index = light_index;
+        light_index += 1;
+        if !vis.is_visible(renderer.active_view()) {
             continue;
         }
 
+        let indirect_args = match clusters {
+            Some(clusters) => match clusters.draw_args(index) {
+                Some(args) => Some(args),
+                None => continue,
+            },
+            None => None,
+        };
+
         {
             let externs = &mut renderer.data.lock().externs;
             let Some(view) = &externs.view else {
@@ -364,7 +452,7 @@ pub fn draw_light_system(renderer: &Renderer, scene: &mut Scene) {
                     resolution_width: shadowmap.resolution() as f32,
                     resolution_height: shadowmap.resolution() as f32,
                     unkc0: shadowmap.camera_to_projective * transform_relative.view_matrix(),
-                    unk180: renderer.settings.shadow_quality.pcf_samples() as u8 as f32,
+                    unk180: settings.shadow_quality.pcf_samples() as u8 as f32,
                     ..existing_shadowmap
                 })
             }
@@ -372,7 +460,8 @@ pub fn draw_light_system(renderer: &Renderer, scene: &mut Scene) {
 
         light_renderer.draw(
             renderer,
-            shadowmap.is_some() && renderer.settings.shadow_quality != ShadowQuality::Off,
+            shadowmap.is_some() && settings.shadows_enabled(),
+            indirect_args,
         );
     }
 }
@@ -387,14 +476,21 @@ pub struct ShadowMapRenderer {
     depth: ShadowDepthMap,
     viewport: Viewport,
     projection: CameraProjection,
+    /// Projection derived from the light data, used when resetting a manually adjusted projection
+    auto_projection: CameraProjection,
     transform: Transform,
 
     world_to_camera: Mat4,
     camera_to_projective: Mat4,
+
+    /// Resolution set for this light specifically, instead of the one from the shadow quality
+    resolution_override: Option<u32>,
+    /// Depth bias set for this light specifically, replacing the ones of the shadow casters
+    depth_bias_override: Option<(i32, ID3D11RasterizerState)>,
 }
 
 /// What geometry to render shadows for
-#[derive(PartialEq)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum ShadowGenerationMode {
     /// Only render stationary static geometry. Will clear the stationary depth buffer
     StationaryOnly,
@@ -429,11 +525,14 @@ impl ShadowMapRenderer {
             resolution,
             depth_stationary,
             depth,
+            auto_projection: projection.clone(),
             projection,
             transform,
             viewport,
             world_to_camera,
             camera_to_projective,
+            resolution_override: None,
+            depth_bias_override: None,
         })
     }
 
@@ -441,8 +540,139 @@ impl ShadowMapRenderer {
         self.resolution
     }
 
+    /// Recreates the depth maps at the given resolution, unless the resolution is overridden for
+    /// this light
     pub fn resize(&mut self, gpu: &GpuContext, resolution: u32) {
-        *self = Self::new(gpu, self.transform, self.projection.clone(), resolution).unwrap();
+        let resolution = self.resolution_override.unwrap_or(resolution);
+        let mut resized =
+            Self::new(gpu, self.transform, self.projection.clone(), resolution).unwrap();
+        resized.auto_projection = self.auto_projection.clone();
+        resized.resolution_override = self.resolution_override;
+        resized.depth_bias_override = self.depth_bias_override.take();
+        *self = resized;
+    }
+
+    pub fn resolution_override(&self) -> Option<u32> {
+        self.resolution_override
+    }
+
+    /// Overrides the resolution of this light's shadow map, or goes back to `default_resolution`
+    /// (the one from the shadow quality) when `None`
+    pub fn set_resolution_override(
+        &mut self,
+        gpu: &GpuContext,
+        resolution: Option<u32>,
+        default_resolution: u32,
+    ) {
+        self.resolution_override = resolution;
+        if resolution.unwrap_or(default_resolution) != self.resolution {
+            self.resize(gpu, default_resolution);
+        }
+    }
+
+    pub fn depth_bias_override(&self) -> Option<i32> {
+        self.depth_bias_override.as_ref().map(|(bias, _)| *bias)
+    }
+
+    /// Overrides the depth bias used when rendering the shadow casters of this light
+    pub fn set_depth_bias_override(
+        &mut self,
+        gpu: &GpuContext,
+        depth_bias: Option<i32>,
+    ) -> anyhow::Result<()> {
+        if depth_bias == self.depth_bias_override() {
+            return Ok(());
+        }
+
+        let Some(depth_bias) = depth_bias else {
+            self.depth_bias_override = None;
+            self.stationary_needs_update = true;
+            return Ok(());
+        };
+
+        // Same slope scale ratio as the game's depth bias states
+        let mut state = None;
+        unsafe {
+            gpu.device.CreateRasterizerState(
+                &D3D11_RASTERIZER_DESC {
+                    FillMode: D3D11_FILL_SOLID,
+                    CullMode: D3D11_CULL_NONE,
+                    FrontCounterClockwise: true.into(),
+                    DepthBias: depth_bias,
+                    DepthBiasClamp: 0.0,
+                    SlopeScaledDepthBias: depth_bias as f32 * 0.4,
+                    DepthClipEnable: true.into(),
+                    ScissorEnable: false.into(),
+                    MultisampleEnable: false.into(),
+                    AntialiasedLineEnable: false.into(),
+                },
+                Some(&mut state),
+            )?;
+        }
+
+        self.depth_bias_override = Some((
+            depth_bias,
+            state.context("Failed to create shadow bias rasterizer state")?,
+        ));
+        self.stationary_needs_update = true;
+        Ok(())
+    }
+
+    /// Rasterizer state to render the shadow casters with, if the depth bias is overridden
+    pub fn depth_bias_rasterizer_state(&self) -> Option<&ID3D11RasterizerState> {
+        self.depth_bias_override.as_ref().map(|(_, state)| state)
+    }
+
+    /// GPU memory used by the depth maps, in bytes
+    pub fn memory_usage(&self) -> usize {
+        // Stationary and final depth map, both 32-bit
+        2 * self.resolution as usize * self.resolution as usize * 4
+    }
+
+    pub fn projection(&self) -> &CameraProjection {
+        &self.projection
+    }
+
+    /// Overrides the shadow projection, eg. to tighten the shadow frustum around an area of interest
+    pub fn set_projection(&mut self, projection: CameraProjection) {
+        self.camera_to_projective = projection.matrix(self.viewport.aspect_ratio());
+        self.projection = projection;
+        self.stationary_needs_update = true;
+    }
+
+    /// Resets the shadow projection to the one derived from the light data
+    pub fn reset_projection(&mut self) {
+        self.set_projection(self.auto_projection.clone());
+    }
+
+    pub fn is_projection_overridden(&self) -> bool {
+        self.projection != self.auto_projection
+    }
+
+    /// World space corners of the shadow frustum (near plane first), for the given light transform
+    pub fn frustum_corners(&self, transform: &Transform) -> [Vec3; 8] {
+        let projective_to_world = (self.camera_to_projective * transform.view_matrix()).inverse();
+        let mut corners = [Vec3::ZERO; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let ndc = Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { 0.0 } else { 1.0 },
+            );
+            *corner = projective_to_world.project_point3(ndc);
+        }
+
+        corners
+    }
+
+    /// The shadow depth map used for the last frame, including moving geometry
+    pub fn depth_map(&self) -> &ShadowDepthMap {
+        &self.depth
+    }
+
+    /// Transforms world space positions into the shadow map
+    pub fn world_to_projective(&self) -> Mat4 {
+        self.camera_to_projective * self.world_to_camera
     }
 
     /// Binds the shadowmap
@@ -507,6 +737,10 @@ impl View for ShadowMapRenderer {
     fn frustum(&self) -> crate::ecs::culling::Frustum {
         Frustum::from_matrix(self.camera_to_projective * self.world_to_camera)
     }
+
+    fn position(&self) -> Vec3 {
+        self.world_to_camera.inverse().w_axis.truncate()
+    }
 }
 
 pub enum LightShape {
@@ -0,0 +1,234 @@
+//! Level of detail selection, based on how much of the screen the bounds of a model cover
+
+use alkahest_data::{geometry::ELodCategory, occlusion::Aabb};
+use bevy_ecs::{
+    query::With,
+    system::{Query, Res, Resource},
+    world::EntityRef,
+};
+use glam::{Vec3, Vec4};
+
+use crate::ecs::{
+    culling::Sphere,
+    hierarchy::Children,
+    render::{
+        dynamic_geometry::DynamicModelComponent,
+        static_geometry::{StaticInstance, StaticInstances, StaticModelSingle},
+    },
+    transform::Transform,
+};
+
+pub const LOD_LEVEL_COUNT: usize = 4;
+
+/// Minimum screen coverage for LOD levels 0 to 2, anything smaller uses level 3. Coverage is the
+/// bounding sphere radius relative to half of the screen height
+const LOD_COVERAGE_THRESHOLDS: [f32; LOD_LEVEL_COUNT - 1] = [0.25, 0.1, 0.04];
+
+/// Most mips skipped when streaming in textures of models that barely cover the screen
+pub const MAX_COVERAGE_MIP_SKIP: usize = 4;
+
+/// Colors of the LOD debug overlay, from the highest to the lowest detail
+pub const LOD_DEBUG_COLORS: [Vec4; LOD_LEVEL_COUNT] = [
+    Vec4::new(0.2, 0.9, 0.2, 1.0),
+    Vec4::new(0.9, 0.9, 0.2, 1.0),
+    Vec4::new(0.95, 0.5, 0.1, 1.0),
+    Vec4::new(0.9, 0.15, 0.15, 1.0),
+];
+
+/// The LOD levels a model has geometry for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LodLevels(u8);
+
+impl LodLevels {
+    pub fn from_categories(categories: impl IntoIterator<Item = ELodCategory>) -> Self {
+        Self(
+            categories
+                .into_iter()
+                .fold(0, |acc, category| acc | (1 << category.level())),
+        )
+    }
+
+    pub fn contains(&self, level: u8) -> bool {
+        self.0 & (1 << level) != 0
+    }
+
+    /// Returns the level closest to `requested` that has geometry, preferring more detail
+    pub fn resolve(&self, requested: u8) -> u8 {
+        (0..=requested)
+            .rev()
+            .chain(requested + 1..LOD_LEVEL_COUNT as u8)
+            .find(|&level| self.contains(level))
+            .unwrap_or(0)
+    }
+}
+
+/// LOD level for a model covering `coverage` of the screen. Positive biases switch to lower
+/// detail levels sooner, every step halving the coverage at which a level is picked
+pub fn lod_for_coverage(coverage: f32, bias: f32) -> u8 {
+    let coverage = coverage * 2f32.powf(-bias);
+    LOD_COVERAGE_THRESHOLDS
+        .iter()
+        .position(|&threshold| coverage >= threshold)
+        .unwrap_or(LOD_LEVEL_COUNT - 1) as u8
+}
+
+/// Number of mips that can be skipped for textures of a model covering `coverage` of the screen.
+/// Models at the highest detail level keep every mip, below that every halving of the coverage
+/// skips one more
+pub fn mip_skip_for_coverage(coverage: f32) -> usize {
+    let full_detail = LOD_COVERAGE_THRESHOLDS[0];
+    if coverage >= full_detail {
+        return 0;
+    }
+
+    let halvings = (full_detail / coverage.max(f32::MIN_POSITIVE)).log2().ceil();
+    (halvings as usize).min(MAX_COVERAGE_MIP_SKIP)
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LodSelectionParams {
+    pub view_position: Vec3,
+    /// Vertical scale of the projection matrix (1 / tan(fov_y / 2))
+    pub projection_scale: f32,
+    pub bias: f32,
+}
+
+impl LodSelectionParams {
+    /// Screen coverage of an entity with local bounds `bb`
+    fn coverage_for_bounds(&self, bb: &Aabb, transform: Option<&Transform>) -> f32 {
+        let mut sphere = Sphere {
+            center: bb.center(),
+            radius: bb.radius(),
+        };
+        if let Some(transform) = transform {
+            sphere = sphere.transform(transform.local_to_world());
+        }
+
+        self.screen_coverage(sphere)
+    }
+
+    fn screen_coverage(&self, sphere: Sphere) -> f32 {
+        let distance = sphere.center.distance(self.view_position);
+        if distance <= sphere.radius {
+            return f32::INFINITY;
+        }
+
+        sphere.radius * self.projection_scale / distance
+    }
+}
+
+/// Picks the LOD level of every static and dynamic model, and stores the screen coverage it was
+/// picked from for texture streaming. Models without bounds, and all models when `params` is
+/// `None` (auto LOD disabled, or capturing), use their highest detail.
+///
+/// Static instances are drawn in a single call, so the whole group uses the level and coverage of
+/// the instance closest to the camera
+pub fn select_lod_system(
+    params: Option<Res<LodSelectionParams>>,
+    mut q_static_single: Query<(&mut StaticModelSingle, Option<&Aabb>, Option<&Transform>)>,
+    mut q_static_instances: Query<(&mut StaticInstances, Option<&Children>)>,
+    q_instance_bounds: Query<(&Aabb, &Transform), With<StaticInstance>>,
+    mut q_dynamic: Query<(
+        &mut DynamicModelComponent,
+        Option<&Aabb>,
+        Option<&Transform>,
+    )>,
+) {
+    profiling::scope!("select_lod_system");
+    let params = params.as_deref().copied();
+
+    let bias = params.map_or(0.0, |params| params.bias);
+    let coverage_for_bounds = |bb: Option<&Aabb>, transform: Option<&Transform>| {
+        params.zip(bb).map_or(f32::INFINITY, |(params, bb)| {
+            params.coverage_for_bounds(bb, transform)
+        })
+    };
+
+    for (mut single, bb, transform) in q_static_single.iter_mut() {
+        let coverage = coverage_for_bounds(bb, transform);
+        let lod = single
+            .model
+            .lod_levels
+            .resolve(lod_for_coverage(coverage, bias));
+        if single.model.selected_lod != lod {
+            single.model.selected_lod = lod;
+        }
+        // Coverage changes whenever the camera moves, which shouldn't count as a model change
+        single.bypass_change_detection().model.screen_coverage = coverage;
+    }
+
+    for (mut instances, children) in q_static_instances.iter_mut() {
+        let coverage = children.map_or(f32::INFINITY, |children| {
+            q_instance_bounds
+                .iter_many(children.iter())
+                .map(|(bb, transform)| coverage_for_bounds(Some(bb), Some(transform)))
+                .reduce(f32::max)
+                .unwrap_or(f32::INFINITY)
+        });
+
+        let lod = instances
+            .model
+            .lod_levels
+            .resolve(lod_for_coverage(coverage, bias));
+        if instances.model.selected_lod != lod {
+            instances.model.selected_lod = lod;
+        }
+        instances.bypass_change_detection().model.screen_coverage = coverage;
+    }
+
+    for (mut dynamic, bb, transform) in q_dynamic.iter_mut() {
+        let coverage = coverage_for_bounds(bb, transform);
+        let lod = dynamic
+            .model
+            .lod_levels
+            .resolve(lod_for_coverage(coverage, bias));
+        if dynamic.model.selected_lod != lod {
+            dynamic.model.selected_lod = lod;
+        }
+        dynamic.bypass_change_detection().model.screen_coverage = coverage;
+    }
+}
+
+/// The LOD level an entity's model is currently drawn with, if it has a model with LODs
+pub fn selected_lod(entity: &EntityRef) -> Option<u8> {
+    if let Some(instances) = entity.get::<StaticInstances>() {
+        Some(instances.model.selected_lod)
+    } else if let Some(single) = entity.get::<StaticModelSingle>() {
+        Some(single.model.selected_lod)
+    } else {
+        entity
+            .get::<DynamicModelComponent>()
+            .map(|d| d.model.selected_lod)
+    }
+}
+
+#[test]
+fn test_lod_selection() {
+    assert_eq!(lod_for_coverage(f32::INFINITY, 0.0), 0);
+    assert_eq!(lod_for_coverage(0.3, 0.0), 0);
+    assert_eq!(lod_for_coverage(0.2, 0.0), 1);
+    assert_eq!(lod_for_coverage(0.05, 0.0), 2);
+    assert_eq!(lod_for_coverage(0.01, 0.0), 3);
+    // A positive bias halves the coverage, a negative one doubles it
+    assert_eq!(lod_for_coverage(0.3, 1.0), 1);
+    assert_eq!(lod_for_coverage(0.2, -1.0), 0);
+
+    assert_eq!(mip_skip_for_coverage(f32::INFINITY), 0);
+    assert_eq!(mip_skip_for_coverage(0.25), 0);
+    assert_eq!(mip_skip_for_coverage(0.2), 1);
+    assert_eq!(mip_skip_for_coverage(0.1), 2);
+    assert_eq!(mip_skip_for_coverage(0.0), MAX_COVERAGE_MIP_SKIP);
+
+    let levels = LodLevels::from_categories([
+        ELodCategory::Lod_0_0,
+        ELodCategory::Lod_Detail,
+        ELodCategory::Lod_2_0,
+    ]);
+    assert_eq!(levels.resolve(0), 0);
+    assert_eq!(levels.resolve(1), 0);
+    assert_eq!(levels.resolve(3), 2);
+
+    let lowest_only = LodLevels::from_categories([ELodCategory::Lod_3_0]);
+    assert_eq!(lowest_only.resolve(0), 3);
+    assert_eq!(LodLevels::default().resolve(2), 0);
+}
@@ -0,0 +1,640 @@
+//! CPU-side inspection of model geometry: vertex streams, layouts, LOD levels and texture
+//! coordinates. Reads the buffers from the packages, so it's independent of what's loaded on the GPU
+
+use alkahest_data::{
+    buffers::{IndexBufferHeader, VertexBufferHeader},
+    dxgi::DxgiFormat,
+    entity::SDynamicModel,
+    geometry::{ELodCategory, EPrimitiveType},
+    map::STerrain,
+    statics::SStaticMesh,
+    technique::STechnique,
+    tfx::TfxRenderStage,
+};
+use alkahest_pm::package_manager;
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Vec2, Vec3, Vec4};
+use tiger_parse::PackageManagerExt;
+
+use crate::{
+    gpu::global_state::{InputElementInfo, RenderStates},
+    util::dxbc::{parse_input_signature, read_shader_bytecode, SignatureElement},
+};
+
+pub struct VertexStream {
+    pub hash: TagHash,
+    /// Vertex buffer slot the stream is bound to
+    pub slot: u32,
+    pub stride: u32,
+    pub vertex_count: u32,
+}
+
+pub struct MeshLod {
+    pub category: ELodCategory,
+    pub part_count: usize,
+    pub index_count: u32,
+}
+
+struct MeshPart {
+    index_start: u32,
+    index_count: u32,
+    primitive_type: EPrimitiveType,
+    lod_category: ELodCategory,
+}
+
+/// A single set of buffers of a model
+pub struct MeshInfo {
+    pub vertex_streams: Vec<VertexStream>,
+    pub index_buffer: TagHash,
+    pub index_count: u32,
+    /// Parts drawn in the gbuffer pass, grouped by LOD category (highest detail first)
+    pub lods: Vec<MeshLod>,
+    /// Input layout used by the gbuffer pass
+    pub input_layout: Option<usize>,
+    /// Technique used by the first gbuffer part, its vertex shader determines which vertex
+    /// elements are actually read
+    pub technique: Option<TagHash>,
+    /// Vertex color stream, bound as a shader resource instead of a vertex buffer
+    pub color_buffer: Option<TagHash>,
+
+    parts: Vec<MeshPart>,
+    texcoord_scale: Vec2,
+    texcoord_offset: Vec2,
+    position_scale: Vec3,
+    position_offset: Vec3,
+}
+
+impl MeshInfo {
+    fn new(
+        buffers: [TagHash; 4],
+        parts: Vec<MeshPart>,
+        input_layout: Option<usize>,
+        technique: Option<TagHash>,
+        (texcoord_scale, texcoord_offset): (Vec2, Vec2),
+        (position_scale, position_offset): (Vec3, Vec3),
+    ) -> Self {
+        let [index_buffer, vertex_buffers @ .., color_buffer] = buffers;
+        let vertex_streams = vertex_buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| hash.is_some())
+            .filter_map(|(slot, &hash)| {
+                let header: VertexBufferHeader = package_manager().read_tag_struct(hash).ok()?;
+                Some(VertexStream {
+                    hash,
+                    slot: slot as u32,
+                    stride: header.stride as u32,
+                    vertex_count: header.data_size / (header.stride as u32).max(1),
+                })
+            })
+            .collect();
+
+        let index_count = package_manager()
+            .read_tag_struct::<IndexBufferHeader>(index_buffer)
+            .map_or(0, |h| (h.data_size / if h.is_32bit { 4 } else { 2 }) as u32);
+
+        let mut lods: Vec<MeshLod> = vec![];
+        for part in &parts {
+            if let Some(lod) = lods.iter_mut().find(|l| l.category == part.lod_category) {
+                lod.part_count += 1;
+                lod.index_count += part.index_count;
+            } else {
+                lods.push(MeshLod {
+                    category: part.lod_category,
+                    part_count: 1,
+                    index_count: part.index_count,
+                });
+            }
+        }
+        lods.sort_by(|a, b| b.category.partial_cmp(&a.category).unwrap());
+
+        Self {
+            vertex_streams,
+            index_buffer,
+            index_count,
+            lods,
+            input_layout,
+            technique,
+            color_buffer: Some(color_buffer).filter(|h| h.is_some()),
+            parts,
+            texcoord_scale,
+            texcoord_offset,
+            position_scale,
+            position_offset,
+        }
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_streams
+            .iter()
+            .find(|s| s.slot == 0)
+            .map_or(0, |s| s.vertex_count)
+    }
+
+    /// Elements of the input layout, with their offsets within the vertex streams
+    pub fn layout_elements(&self) -> Vec<InputElementInfo> {
+        self.input_layout
+            .map(RenderStates::input_layout_elements)
+            .unwrap_or_default()
+    }
+
+    /// Reads the input signature of the vertex shader of the mesh's technique
+    pub fn vertex_shader_signature(&self) -> anyhow::Result<Vec<SignatureElement>> {
+        let technique = self.technique.context("Mesh has no technique")?;
+        let technique: STechnique = package_manager()
+            .read_tag_struct(technique)
+            .context("Failed to read technique")?;
+        anyhow::ensure!(
+            technique.shader_vertex.shader.is_some(),
+            "Technique has no vertex shader"
+        );
+
+        parse_input_signature(&read_shader_bytecode(technique.shader_vertex.shader)?)
+    }
+
+    /// Reads the first set of texture coordinates and the triangles of the highest detail LOD
+    pub fn read_uv_layout(&self) -> anyhow::Result<UvLayout> {
+        let texcoords = self
+            .read_element("TEXCOORD", |v, format| {
+                decode_texcoord(v, format).map(|uv| uv * self.texcoord_scale + self.texcoord_offset)
+            })?
+            .context("Mesh has no texture coordinates")?;
+        let triangles = self.read_triangles(texcoords.len())?;
+
+        Ok(UvLayout {
+            texcoords,
+            triangles,
+        })
+    }
+
+    /// Reads the vertices and triangles of the highest detail LOD, for exporting the mesh
+    pub fn read_geometry(&self) -> anyhow::Result<MeshGeometry> {
+        let positions = self
+            .read_element("POSITION", |v, format| {
+                let p = decode_vec4(v, format)?;
+                // Integer positions are normalized like their SNORM counterparts
+                let p = if format == DxgiFormat::R16G16B16A16_SINT {
+                    p / 32767.0
+                } else {
+                    p
+                };
+                Some(p.truncate() * self.position_scale + self.position_offset)
+            })?
+            .context("Mesh has no positions")?;
+        let normals = self.read_element("NORMAL", |v, format| {
+            decode_vec4(v, format).map(|n| n.truncate().normalize_or_zero())
+        })?;
+        let texcoords = self.read_element("TEXCOORD", |v, format| {
+            decode_texcoord(v, format).map(|uv| uv * self.texcoord_scale + self.texcoord_offset)
+        })?;
+
+        let blend_indices = self.read_element("BLENDINDICES", decode_vec4)?;
+        let blend_weights = self.read_element("BLENDWEIGHT", decode_vec4)?;
+        let skin_weights = match (blend_indices, blend_weights) {
+            (Some(indices), Some(weights)) => Some(
+                indices
+                    .iter()
+                    .zip(&weights)
+                    .map(|(i, w)| {
+                        (0..4)
+                            .filter(|&c| w[c] > 0.0)
+                            .map(|c| (i[c] as u32, w[c]))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            // Rigidly skinned vertices store their bone index in the W component of the position
+            _ => self
+                .read_element("POSITION", |v, format| match format {
+                    DxgiFormat::R16G16B16A16_SINT | DxgiFormat::R16G16B16A16_SNORM => {
+                        let bone = i16::from_le_bytes(v.get(6..8)?.try_into().ok()?);
+                        Some(if bone >= 0 {
+                            vec![(bone as u32, 1.0)]
+                        } else {
+                            vec![]
+                        })
+                    }
+                    _ => None,
+                })
+                .ok()
+                .flatten(),
+        };
+
+        // Color streams can be shorter than the vertex buffers, the last color is used for the
+        // remaining vertices
+        let colors = match self.color_buffer {
+            Some(hash) => {
+                let colors = read_color_stream(hash)?;
+                (!colors.is_empty()).then(|| {
+                    (0..positions.len())
+                        .map(|i| colors[i.min(colors.len() - 1)])
+                        .collect()
+                })
+            }
+            None => None,
+        };
+
+        let triangles = self.read_triangles(positions.len())?;
+
+        Ok(MeshGeometry {
+            positions,
+            normals,
+            texcoords,
+            colors,
+            skin_weights,
+            triangles,
+        })
+    }
+
+    /// Decodes the first element with the given semantic from every vertex. Returns `None` if the
+    /// layout doesn't have the element
+    fn read_element<T>(
+        &self,
+        semantic_name: &str,
+        decode: impl Fn(&[u8], DxgiFormat) -> Option<T>,
+    ) -> anyhow::Result<Option<Vec<T>>> {
+        let Some(element) = self.layout_elements().into_iter().find(|e| {
+            e.semantic_name == semantic_name && e.semantic_index == 0 && !e.is_instance_data
+        }) else {
+            return Ok(None);
+        };
+        let stream = self
+            .vertex_streams
+            .iter()
+            .find(|s| s.slot == element.buffer_index)
+            .with_context(|| format!("{semantic_name} stream is missing"))?;
+
+        anyhow::ensure!(stream.stride != 0, "{semantic_name} stream has no stride");
+
+        let vertex_data = read_buffer_data(stream.hash)?;
+        vertex_data
+            .chunks_exact(stream.stride as usize)
+            .map(|v| {
+                v.get(element.offset as usize..)
+                    .and_then(|v| decode(v, element.format))
+            })
+            .collect::<Option<Vec<_>>>()
+            .with_context(|| format!("Unsupported {semantic_name} format {:?}", element.format))
+            .map(Some)
+    }
+
+    /// Reads the triangles of the highest detail LOD
+    fn read_triangles(&self, vertex_count: usize) -> anyhow::Result<Vec<[u32; 3]>> {
+        let index_header: IndexBufferHeader = package_manager()
+            .read_tag_struct(self.index_buffer)
+            .context("Failed to read index buffer header")?;
+        let index_data = read_buffer_data(self.index_buffer)?;
+        let indices: Vec<u32> = if index_header.is_32bit {
+            index_data
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .collect()
+        } else {
+            index_data
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes(b.try_into().unwrap()) as u32)
+                .collect()
+        };
+
+        let highest_detail = self.lods.first().map(|l| l.category);
+        let mut triangles = vec![];
+        for part in self
+            .parts
+            .iter()
+            .filter(|p| Some(p.lod_category) == highest_detail)
+        {
+            let start = part.index_start as usize;
+            let Some(part_indices) = indices.get(start..start + part.index_count as usize) else {
+                continue;
+            };
+            collect_triangles(part_indices, part.primitive_type, &mut triangles);
+        }
+
+        // Drop triangles referencing vertices that don't exist, instead of failing on them later
+        triangles.retain(|t| t.iter().all(|&i| (i as usize) < vertex_count));
+
+        Ok(triangles)
+    }
+}
+
+pub struct UvLayout {
+    pub texcoords: Vec<Vec2>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+#[derive(Clone)]
+pub struct MeshGeometry {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub texcoords: Option<Vec<Vec2>>,
+    pub colors: Option<Vec<Vec4>>,
+    /// Bone indices and weights of every vertex, if the mesh is skinned
+    pub skin_weights: Option<Vec<Vec<(u32, f32)>>>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+pub fn static_mesh_info(model: &SStaticMesh) -> Vec<MeshInfo> {
+    let data = &model.opaque_meshes;
+    let texcoord_transform = (
+        Vec2::splat(data.texture_coordinate_scale),
+        data.texture_coordinate_offset,
+    );
+    let position_transform = (Vec3::splat(data.mesh_scale), data.mesh_offset);
+
+    data.buffers
+        .iter()
+        .enumerate()
+        .map(|(buffer_index, &(index_buffer, vertex0, vertex1, color))| {
+            let groups = data
+                .mesh_groups
+                .iter()
+                .enumerate()
+                .filter(|(_, g)| {
+                    g.render_stage == TfxRenderStage::GenerateGbuffer
+                        && data
+                            .parts
+                            .get(g.part_index as usize)
+                            .map(|p| p.buffer_index as usize)
+                            == Some(buffer_index)
+                })
+                .collect::<Vec<_>>();
+
+            let parts = groups
+                .iter()
+                .filter_map(|(_, g)| data.parts.get(g.part_index as usize))
+                .map(|p| MeshPart {
+                    index_start: p.index_start,
+                    index_count: p.index_count,
+                    primitive_type: p.primitive_type,
+                    lod_category: p.lod_category,
+                })
+                .collect();
+
+            MeshInfo::new(
+                [index_buffer, vertex0, vertex1, color],
+                parts,
+                groups.first().map(|(_, g)| g.input_layout_index as usize),
+                groups
+                    .first()
+                    .and_then(|&(i, _)| model.techniques.get(i).copied()),
+                texcoord_transform,
+                position_transform,
+            )
+        })
+        .collect()
+}
+
+pub fn dynamic_mesh_info(model: &SDynamicModel) -> Vec<MeshInfo> {
+    let texcoord_transform = (model.texcoord_scale, model.texcoord_offset);
+    let position_transform = (model.model_scale.truncate(), model.model_offset.truncate());
+
+    model
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let parts = mesh
+                .parts
+                .get(mesh.get_range_for_stage(TfxRenderStage::GenerateGbuffer))
+                .unwrap_or_default()
+                .iter()
+                .collect::<Vec<_>>();
+
+            MeshInfo::new(
+                [
+                    mesh.index_buffer,
+                    mesh.vertex0_buffer,
+                    mesh.vertex1_buffer,
+                    mesh.color_buffer,
+                ],
+                parts
+                    .iter()
+                    .map(|p| MeshPart {
+                        index_start: p.index_start,
+                        index_count: p.index_count,
+                        primitive_type: p.primitive_type,
+                        lod_category: p.lod_category,
+                    })
+                    .collect(),
+                Some(
+                    mesh.input_layout_per_render_stage[TfxRenderStage::GenerateGbuffer as usize]
+                        as usize,
+                ),
+                parts.iter().map(|p| p.technique).find(|t| t.is_some()),
+                texcoord_transform,
+                position_transform,
+            )
+        })
+        .collect()
+}
+
+/// Terrain patches are drawn as triangle strips with a fixed input layout. Only the highest
+/// detail parts are included
+pub fn terrain_mesh_info(terrain: &STerrain) -> MeshInfo {
+    let parts = terrain
+        .mesh_parts
+        .iter()
+        .filter(|p| p.detail_level == 0)
+        .map(|p| MeshPart {
+            index_start: p.index_start,
+            index_count: p.index_count as u32,
+            primitive_type: EPrimitiveType::TriangleStrip,
+            lod_category: ELodCategory::Lod_0_0,
+        })
+        .collect();
+
+    MeshInfo::new(
+        [
+            terrain.index_buffer,
+            terrain.vertex0_buffer,
+            terrain.vertex1_buffer,
+            TagHash::NONE,
+        ],
+        parts,
+        Some(22),
+        terrain.mesh_parts.first().map(|p| p.technique),
+        (Vec2::ONE, Vec2::ZERO),
+        // Same offset and scale the vertex shader gets through cb11
+        (Vec3::splat(terrain.unk30.w), terrain.unk30.truncate()),
+    )
+}
+
+fn read_buffer_data(hash: TagHash) -> anyhow::Result<Vec<u8>> {
+    let entry = package_manager()
+        .get_entry(hash)
+        .context("Entry not found")?;
+    package_manager()
+        .read_tag(entry.reference)
+        .context("Failed to read buffer data")
+}
+
+/// Reads a color or AO stream. These are bound as R8G8B8A8 (or R8 for single channel streams)
+/// shader resources, single channel values are splatted to grey
+pub(crate) fn read_color_stream(hash: TagHash) -> anyhow::Result<Vec<Vec4>> {
+    let header: VertexBufferHeader = package_manager()
+        .read_tag_struct(hash)
+        .context("Failed to read color buffer header")?;
+    let data = read_buffer_data(hash)?;
+
+    match header.stride {
+        1 => Ok(data
+            .iter()
+            .map(|&v| Vec4::splat(v as f32 / 255.0))
+            .collect()),
+        4 => Ok(data
+            .chunks_exact(4)
+            .map(|c| Vec4::new(c[0] as f32, c[1] as f32, c[2] as f32, c[3] as f32) / 255.0)
+            .collect()),
+        stride => anyhow::bail!("Unsupported color buffer stride {stride}"),
+    }
+}
+
+fn collect_triangles(indices: &[u32], primitive_type: EPrimitiveType, out: &mut Vec<[u32; 3]>) {
+    match primitive_type {
+        EPrimitiveType::Triangles => {
+            out.extend(indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]));
+        }
+        EPrimitiveType::TriangleStrip => {
+            // Strips are split by restart indices
+            for strip in indices.split(|&i| i == 0xFFFF || i == 0xFFFFFFFF) {
+                for (i, t) in strip.windows(3).enumerate() {
+                    if t[0] == t[1] || t[1] == t[2] || t[0] == t[2] {
+                        continue;
+                    }
+
+                    out.push(if i % 2 == 0 {
+                        [t[0], t[1], t[2]]
+                    } else {
+                        [t[1], t[0], t[2]]
+                    });
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn decode_texcoord(data: &[u8], format: DxgiFormat) -> Option<Vec2> {
+    let read_u16 = |i: usize| {
+        Some(u16::from_le_bytes(
+            data.get(i * 2..i * 2 + 2)?.try_into().ok()?,
+        ))
+    };
+    let read_f32 = |i: usize| {
+        Some(f32::from_le_bytes(
+            data.get(i * 4..i * 4 + 4)?.try_into().ok()?,
+        ))
+    };
+
+    match format {
+        DxgiFormat::R16G16_SNORM | DxgiFormat::R16G16B16A16_SNORM => Some(Vec2::new(
+            (read_u16(0)? as i16 as f32 / 32767.0).max(-1.0),
+            (read_u16(1)? as i16 as f32 / 32767.0).max(-1.0),
+        )),
+        DxgiFormat::R16G16_FLOAT | DxgiFormat::R16G16B16A16_FLOAT => Some(Vec2::new(
+            f16_to_f32(read_u16(0)?),
+            f16_to_f32(read_u16(1)?),
+        )),
+        DxgiFormat::R32G32_FLOAT | DxgiFormat::R32G32B32_FLOAT | DxgiFormat::R32G32B32A32_FLOAT => {
+            Some(Vec2::new(read_f32(0)?, read_f32(1)?))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes a vertex element the way the input assembler would, missing components are filled in
+/// with 0 (1 for W)
+fn decode_vec4(data: &[u8], format: DxgiFormat) -> Option<Vec4> {
+    let read_u16 = |i: usize| {
+        Some(u16::from_le_bytes(
+            data.get(i * 2..i * 2 + 2)?.try_into().ok()?,
+        ))
+    };
+    let read_f32 = |i: usize| {
+        Some(f32::from_le_bytes(
+            data.get(i * 4..i * 4 + 4)?.try_into().ok()?,
+        ))
+    };
+    let read_u8 = |i: usize| data.get(i).map(|&v| v as f32);
+
+    match format {
+        DxgiFormat::R16G16B16A16_SNORM => Some(Vec4::from_array(
+            [read_u16(0)?, read_u16(1)?, read_u16(2)?, read_u16(3)?]
+                .map(|v| (v as i16 as f32 / 32767.0).max(-1.0)),
+        )),
+        DxgiFormat::R16G16B16A16_SINT => Some(Vec4::from_array(
+            [read_u16(0)?, read_u16(1)?, read_u16(2)?, read_u16(3)?].map(|v| v as i16 as f32),
+        )),
+        DxgiFormat::R16G16B16A16_FLOAT => Some(Vec4::from_array(
+            [read_u16(0)?, read_u16(1)?, read_u16(2)?, read_u16(3)?].map(f16_to_f32),
+        )),
+        DxgiFormat::R8G8B8A8_UNORM => {
+            Some(Vec4::new(read_u8(0)?, read_u8(1)?, read_u8(2)?, read_u8(3)?) / 255.0)
+        }
+        DxgiFormat::R8G8B8A8_UINT => Some(Vec4::new(
+            read_u8(0)?,
+            read_u8(1)?,
+            read_u8(2)?,
+            read_u8(3)?,
+        )),
+        DxgiFormat::R32G32B32A32_FLOAT => Some(Vec4::new(
+            read_f32(0)?,
+            read_f32(1)?,
+            read_f32(2)?,
+            read_f32(3)?,
+        )),
+        DxgiFormat::R32G32B32_FLOAT => {
+            Some(Vec4::new(read_f32(0)?, read_f32(1)?, read_f32(2)?, 1.0))
+        }
+        _ => None,
+    }
+}
+
+fn f16_to_f32(value: u16) -> f32 {
+    let sign = if value & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((value >> 10) & 0x1f) as i32;
+    let mantissa = (value & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        e => (1.0 + mantissa / 1024.0) * 2f32.powi(e - 15),
+    }
+}
+
+#[test]
+fn test_decode_texcoords() {
+    assert_eq!(f16_to_f32(0x3c00), 1.0);
+    assert_eq!(f16_to_f32(0xc000), -2.0);
+    assert_eq!(f16_to_f32(0x3800), 0.5);
+    assert_eq!(f16_to_f32(0), 0.0);
+
+    let snorm = [0xff, 0x7f, 0x01, 0x80];
+    assert_eq!(
+        decode_texcoord(&snorm, DxgiFormat::R16G16_SNORM),
+        Some(Vec2::new(1.0, -1.0))
+    );
+    assert_eq!(decode_texcoord(&snorm, DxgiFormat::R8G8B8A8_UNORM), None);
+    assert_eq!(decode_texcoord(&snorm[..2], DxgiFormat::R16G16_SNORM), None);
+
+    assert_eq!(
+        decode_vec4(
+            &[0xff, 0x7f, 0x01, 0x80, 0, 0, 3, 0],
+            DxgiFormat::R16G16B16A16_SINT
+        ),
+        Some(Vec4::new(32767.0, -32767.0, 0.0, 3.0))
+    );
+    assert_eq!(
+        decode_vec4(&[0, 255, 0, 255], DxgiFormat::R8G8B8A8_UNORM),
+        Some(Vec4::new(0.0, 1.0, 0.0, 1.0))
+    );
+
+    let mut triangles = vec![];
+    collect_triangles(
+        &[0, 1, 2, 3, 0xFFFF, 4, 5, 6],
+        EPrimitiveType::TriangleStrip,
+        &mut triangles,
+    );
+    assert_eq!(triangles, vec![[0, 1, 2], [2, 1, 3], [4, 5, 6]]);
+}
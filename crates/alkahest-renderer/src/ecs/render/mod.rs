@@ -1,5 +1,5 @@
 use alkahest_data::tfx::{TfxRenderStage, TfxShaderStage};
-use bevy_ecs::entity::Entity;
+use bevy_ecs::{component::Component, entity::Entity, world::EntityRef};
 
 use crate::{
     ecs::{
@@ -8,7 +8,8 @@ use crate::{
             decorators::DecoratorRenderer,
             dynamic_geometry::DynamicModelComponent,
             static_geometry::{
-                create_instances_scope, StaticInstance, StaticInstances, StaticModelSingle,
+                create_instances_scope, StaticInstance, StaticInstanceParameter, StaticInstances,
+                StaticModelSingle,
             },
             terrain::TerrainPatches,
         },
@@ -16,8 +17,10 @@ use crate::{
         Scene,
     },
     gpu::buffer::ConstantBuffer,
+    handle::Handle,
     renderer::Renderer,
-    shader::shader_ball::ShaderBallComponent,
+    shader::shader_ball::{LightProbeSphere, ShaderBallComponent},
+    tfx::technique::Technique,
 };
 
 use super::channels::ObjectChannels;
@@ -26,8 +29,79 @@ pub mod decorators;
 pub mod dynamic_geometry;
 pub mod havok;
 pub mod light;
+pub mod lod;
+pub mod mesh_info;
+pub mod stages;
 pub mod static_geometry;
 pub mod terrain;
+pub mod volumes;
+
+/// A technique that is drawn instead of a model's own techniques in one render stage. The model
+/// keeps its original techniques, so removing the override restores its materials.
+#[derive(Clone)]
+pub struct TechniqueOverride {
+    pub technique: Handle<Technique>,
+    pub stage: TfxRenderStage,
+}
+
+impl TechniqueOverride {
+    pub fn technique_for_stage(&self, stage: TfxRenderStage) -> Option<&Handle<Technique>> {
+        (self.stage == stage).then_some(&self.technique)
+    }
+}
+
+/// Marker component to draw the wireframe of an entity on top of it
+#[derive(Component)]
+pub struct ShowWireframe;
+
+/// Whether [`draw_entity`] can draw this entity
+pub fn has_geometry(e: EntityRef) -> bool {
+    e.contains::<StaticInstances>()
+        || e.contains::<StaticModelSingle>()
+        || e.contains::<TerrainPatches>()
+        || e.contains::<DecoratorRenderer>()
+        || e.contains::<DynamicModelComponent>()
+        || e.contains::<ShaderBallComponent>()
+        || e.contains::<LightProbeSphere>()
+        || e.contains::<StaticInstance>()
+}
+
+/// Techniques the geometry of an entity is drawn with, including technique overrides
+pub fn entity_techniques(e: EntityRef) -> Vec<Handle<Technique>> {
+    let mut techniques = vec![];
+    let static_model = e
+        .get::<StaticInstances>()
+        .map(|s| &s.model)
+        .or(e.get::<StaticModelSingle>().map(|s| &s.model));
+    if let Some(model) = static_model {
+        techniques.extend(model.materials.iter().cloned());
+        techniques.extend(model.technique_override.iter().map(|o| o.technique.clone()));
+    }
+
+    if let Some(dynamic) = e.get::<DynamicModelComponent>() {
+        techniques.extend(dynamic.techniques());
+        techniques.extend(
+            dynamic
+                .model
+                .technique_override
+                .iter()
+                .map(|o| o.technique.clone()),
+        );
+    }
+
+    if let Some(terrain) = e.get::<TerrainPatches>() {
+        techniques.extend(terrain.techniques().iter().cloned());
+    }
+
+    if let Some(decorator) = e.get::<DecoratorRenderer>() {
+        for (model, _, _) in &decorator.models {
+            techniques.extend(model.techniques());
+        }
+    }
+
+    techniques.retain(|t| !t.is_none());
+    techniques
+}
 
 /// Draw a specific entity. Only works for entities with geometry, but not screen-space decals, lights, etc
 /// Ignores the renderer's feature visibility settings
@@ -60,8 +134,18 @@ pub fn draw_entity(
         .get(scene, entity)
     {
         shaderball.draw(renderer, transform, render_stage);
-    } else if let Ok((transform, _instance, parent)) = scene
-        .query::<(&Transform, &StaticInstance, &Parent)>()
+    } else if let Ok((probe, transform)) = scene
+        .query::<(&LightProbeSphere, &Transform)>()
+        .get(scene, entity)
+    {
+        probe.draw(renderer, transform, render_stage);
+    } else if let Ok((transform, parameter, _instance, parent)) = scene
+        .query::<(
+            &Transform,
+            Option<&StaticInstanceParameter>,
+            &StaticInstance,
+            &Parent,
+        )>()
         .get(scene, entity)
     {
         if let Some(model) = scene.get::<StaticInstances>(parent.0) {
@@ -76,6 +160,7 @@ pub fn draw_entity(
                             create_instances_scope(
                                 &model.model.model.opaque_meshes,
                                 std::slice::from_ref(transform),
+                                &[parameter.copied().unwrap_or_default()],
                             )
                             .write()
                             .as_slice(),
@@ -0,0 +1,81 @@
+use alkahest_data::tfx::{TfxFeatureRenderer, TfxRenderStage};
+use bevy_ecs::{component::Component, world::EntityRef};
+
+use crate::{
+    ecs::render::{
+        decorators::DecoratorRenderer,
+        dynamic_geometry::DynamicModelComponent,
+        static_geometry::{StaticInstances, StaticModelSingle},
+        terrain::TerrainPatches,
+    },
+    tfx::view::RenderStageSubscriptions,
+};
+
+/// Debug override of the render stages an entity is drawn in
+#[derive(Component, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderStageOverride {
+    /// Stages the entity is drawn in, even if the stage or its feature renderer is disabled in
+    /// the render settings
+    pub forced: RenderStageSubscriptions,
+    /// Stages the entity is never drawn in
+    pub excluded: RenderStageSubscriptions,
+}
+
+impl RenderStageOverride {
+    pub fn is_empty(&self) -> bool {
+        self.forced.is_empty() && self.excluded.is_empty()
+    }
+}
+
+pub trait RenderStageOverrideHelper {
+    /// Applies the override to `default`, the result of
+    /// [`Renderer::should_render`](crate::renderer::Renderer::should_render) for the entity
+    fn should_render(&self, stage: TfxRenderStage, default: bool) -> bool;
+}
+
+impl RenderStageOverrideHelper for Option<&RenderStageOverride> {
+    fn should_render(&self, stage: TfxRenderStage, default: bool) -> bool {
+        match self {
+            Some(o) if o.excluded.is_subscribed(stage) => false,
+            Some(o) if o.forced.is_subscribed(stage) => true,
+            _ => default,
+        }
+    }
+}
+
+/// The feature renderer that draws an entity, and the render stages its geometry is subscribed to
+pub fn entity_render_stages(
+    e: EntityRef,
+) -> Option<(TfxFeatureRenderer, RenderStageSubscriptions)> {
+    if let Some(instances) = e.get::<StaticInstances>() {
+        Some((
+            TfxFeatureRenderer::StaticObjects,
+            instances.model.subscribed_stages,
+        ))
+    } else if let Some(single) = e.get::<StaticModelSingle>() {
+        Some((
+            TfxFeatureRenderer::StaticObjects,
+            single.model.subscribed_stages,
+        ))
+    } else if e.contains::<TerrainPatches>() {
+        Some((
+            TfxFeatureRenderer::TerrainPatch,
+            RenderStageSubscriptions::GENERATE_GBUFFER
+                | RenderStageSubscriptions::SHADOW_GENERATE
+                | RenderStageSubscriptions::DEPTH_PREPASS,
+        ))
+    } else if let Some(decorator) = e.get::<DecoratorRenderer>() {
+        Some((
+            TfxFeatureRenderer::SpeedtreeTrees,
+            decorator
+                .models
+                .iter()
+                .fold(RenderStageSubscriptions::empty(), |acc, (model, _, _)| {
+                    acc | model.subscribed_stages
+                }),
+        ))
+    } else {
+        e.get::<DynamicModelComponent>()
+            .map(|dynamic| (dynamic.model.feature_type, dynamic.model.subscribed_stages))
+    }
+}
@@ -20,8 +20,14 @@ use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
 
 use crate::{
     ecs::{
+        common::{ActivityGroup, ResourceOrigin},
         hierarchy::{Children, Parent},
-        render::light::ShadowGenerationMode,
+        render::{
+            light::ShadowGenerationMode,
+            lod::LodLevels,
+            stages::{RenderStageOverride, RenderStageOverrideHelper},
+            TechniqueOverride,
+        },
         transform::Transform,
         visibility::{ViewVisibility, VisibilityHelper},
         Scene,
@@ -30,7 +36,7 @@ use crate::{
     gpu_event,
     handle::Handle,
     loaders::{index_buffer::IndexBuffer, vertex_buffer::VertexBuffer, AssetManager},
-    renderer::Renderer,
+    renderer::{Renderer, StatsCategory, StatsSource},
     tfx::{scope::ScopeInstances, technique::Technique, view::RenderStageSubscriptions},
     util::packages::TagHashExt,
 };
@@ -94,8 +100,15 @@ struct SpecialMesh {
 pub struct StaticModel {
     pub model: SStaticMesh,
     pub materials: Vec<Handle<Technique>>,
+    pub technique_override: Option<TechniqueOverride>,
     pub hash: TagHash,
     pub subscribed_stages: RenderStageSubscriptions,
+    pub lod_levels: LodLevels,
+    /// LOD level that gets drawn, picked by [`select_lod_system`](super::lod::select_lod_system)
+    pub selected_lod: u8,
+    /// Screen coverage the LOD level was picked from, used to stream in fewer mips for distant
+    /// models. Infinite when unknown
+    pub screen_coverage: f32,
 
     buffers: Vec<ModelBuffers>,
     special_meshes: Vec<SpecialMesh>,
@@ -150,33 +163,45 @@ impl StaticModel {
             })
             .collect();
 
+        let lod_levels = LodLevels::from_categories(
+            model
+                .opaque_meshes
+                .parts
+                .iter()
+                .map(|p| p.lod_category)
+                .chain(model.special_meshes.iter().map(|m| m.lod)),
+        );
+
         Ok(Self {
             hash,
             model,
             materials,
+            technique_override: None,
             buffers,
             special_meshes,
             subscribed_stages,
+            selected_lod: lod_levels.resolve(0),
+            screen_coverage: f32::INFINITY,
+            lod_levels,
         })
     }
 
     /// ⚠ Expects the `instances` scope to be bound
     pub fn draw(&self, renderer: &Renderer, render_stage: TfxRenderStage, instances_count: u32) {
-        if !self.subscribed_stages.is_subscribed(render_stage) {
-            return;
-        }
-
-        if !renderer.settings.stage_transparent && render_stage == TfxRenderStage::Transparents {
-            return;
-        }
-
-        if !renderer.settings.stage_decals && render_stage == TfxRenderStage::Decals {
-            return;
-        }
+        self.draw_lod(renderer, render_stage, instances_count, self.selected_lod);
+    }
 
-        if !renderer.settings.stage_decals_additive
-            && render_stage == TfxRenderStage::DecalsAdditive
-        {
+    /// Draws the given LOD level instead of the selected one
+    ///
+    /// ⚠ Expects the `instances` scope to be bound
+    pub fn draw_lod(
+        &self,
+        renderer: &Renderer,
+        render_stage: TfxRenderStage,
+        instances_count: u32,
+        lod: u8,
+    ) {
+        if !self.subscribed_stages.is_subscribed(render_stage) {
             return;
         }
 
@@ -187,6 +212,7 @@ impl StaticModel {
         );
 
         profiling::scope!("StaticModel::draw");
+        let _coverage = renderer.draw_coverage_scope(self.screen_coverage);
         for (i, group) in self
             .model
             .opaque_meshes
@@ -197,9 +223,10 @@ impl StaticModel {
         {
             if group.render_stage == TfxRenderStage::ShadowGenerate
                 && ((group.unk6 == 2
-                    && renderer.active_shadow_generation_mode != ShadowGenerationMode::MovingOnly)
+                    && renderer.active_shadow_generation_mode.load()
+                        != ShadowGenerationMode::MovingOnly)
                     || (group.unk6 == 1
-                        && renderer.active_shadow_generation_mode
+                        && renderer.active_shadow_generation_mode.load()
                             != ShadowGenerationMode::StationaryOnly))
             {
                 continue;
@@ -207,7 +234,7 @@ impl StaticModel {
 
             profiling::scope!("StaticModel::draw::group", format!("group_{}", i));
             let part = &self.model.opaque_meshes.parts[group.part_index as usize];
-            if !part.lod_category.is_highest_detail() {
+            if part.lod_category.level() != lod {
                 continue;
             }
 
@@ -216,8 +243,14 @@ impl StaticModel {
                 continue;
             }
 
-            if let Some(technique) = renderer.get_technique_shared(&self.materials[i]) {
+            let material = self
+                .technique_override_for_stage(render_stage)
+                .unwrap_or(&self.materials[i]);
+            if let Some(technique) = renderer.get_technique_shared(material) {
                 technique.bind(renderer).expect("Failed to bind technique");
+                renderer
+                    .stats
+                    .record_technique(StatsCategory::Statics, &technique);
             } else {
                 continue;
             }
@@ -236,9 +269,21 @@ impl StaticModel {
                     0,
                 );
             }
+            renderer.stats.record_draw(
+                StatsCategory::Statics,
+                part.index_count,
+                part.primitive_type,
+                instances_count,
+            );
         }
 
-        self.draw_special_meshes(renderer, render_stage, instances_count);
+        self.draw_special_meshes(renderer, render_stage, instances_count, lod);
+    }
+
+    fn technique_override_for_stage(&self, stage: TfxRenderStage) -> Option<&Handle<Technique>> {
+        self.technique_override
+            .as_ref()
+            .and_then(|o| o.technique_for_stage(stage))
     }
 
     fn draw_special_meshes(
@@ -246,19 +291,26 @@ impl StaticModel {
         renderer: &Renderer,
         render_stage: TfxRenderStage,
         instances_count: u32,
+        lod: u8,
     ) {
         profiling::scope!("StaticModel::draw_special_meshes");
         for mesh in self
             .special_meshes
             .iter()
-            .filter(|m| m.mesh.render_stage == render_stage && m.mesh.lod.is_highest_detail())
+            .filter(|m| m.mesh.render_stage == render_stage && m.mesh.lod.level() == lod)
         {
             if mesh.buffers.bind(renderer).is_none() {
                 continue;
             }
 
-            if let Some(technique) = renderer.get_technique_shared(&mesh.technique) {
+            let material = self
+                .technique_override_for_stage(render_stage)
+                .unwrap_or(&mesh.technique);
+            if let Some(technique) = renderer.get_technique_shared(material) {
                 technique.bind(renderer).expect("Failed to bind technique");
+                renderer
+                    .stats
+                    .record_technique(StatsCategory::Statics, &technique);
                 // } else {
                 //     continue;
             }
@@ -277,6 +329,12 @@ impl StaticModel {
                     0,
                 );
             }
+            renderer.stats.record_draw(
+                StatsCategory::Statics,
+                mesh.mesh.index_count,
+                mesh.mesh.primitive_type,
+                instances_count,
+            );
         }
     }
 }
@@ -303,18 +361,15 @@ impl StaticModelSingle {
         Self::new(gctx, StaticModel::load(am, tag)?)
     }
 
-    pub fn update_cbuffer(&self, transform: &Transform) {
+    pub fn update_cbuffer(&self, transform: &Transform, parameter: StaticInstanceParameter) {
         profiling::scope!("StaticInstances::update_cbuffer");
 
-        unsafe {
-            let mesh_data = &self.model.model.opaque_meshes;
-            self.cbuffer
-                .write_array(
-                    create_instances_scope(mesh_data, std::slice::from_ref(transform))
-                        .write()
-                        .as_slice(),
-                )
-                .unwrap()
+        let mesh_data = &self.model.model.opaque_meshes;
+        let scope =
+            create_instances_scope(mesh_data, std::slice::from_ref(transform), &[parameter])
+                .write();
+        if let Err(e) = unsafe { self.cbuffer.write_array(scope.as_slice()) } {
+            error!("Failed to write static model constants: {e:?}");
         }
     }
 
@@ -333,6 +388,9 @@ pub struct StaticInstances {
     pub model: StaticModel,
     pub instance_count: usize,
     pub cbuffer: ConstantBuffer<u8>,
+    /// Drawn as billboard impostors in the opaque pass instead of the model, picked every frame
+    /// while impostors are enabled
+    pub impostor: bool,
 }
 
 impl StaticInstances {
@@ -343,21 +401,17 @@ impl StaticInstances {
             model,
             instance_count: instances,
             cbuffer,
+            impostor: false,
         })
     }
 
-    pub fn update_cbuffer(&self, transforms: &[Transform]) {
+    pub fn update_cbuffer(&self, transforms: &[Transform], parameters: &[StaticInstanceParameter]) {
         profiling::scope!("StaticInstances::update_cbuffer");
 
-        unsafe {
-            let mesh_data = &self.model.model.opaque_meshes;
-            self.cbuffer
-                .write_array(
-                    create_instances_scope(mesh_data, transforms)
-                        .write()
-                        .as_slice(),
-                )
-                .unwrap();
+        let mesh_data = &self.model.model.opaque_meshes;
+        let scope = create_instances_scope(mesh_data, transforms, parameters).write();
+        if let Err(e) = unsafe { self.cbuffer.write_array(scope.as_slice()) } {
+            error!("Failed to write static instance constants: {e:?}");
         }
     }
 
@@ -371,7 +425,24 @@ impl StaticInstances {
     }
 }
 
-pub fn create_instances_scope(mesh: &SStaticMeshData, transforms: &[Transform]) -> ScopeInstances {
+/// Per-instance constants of a static instance, passed to the shaders in the last column of the
+/// instance's transform in the instance scope. The values in the map data aren't read yet, every
+/// instance starts out with the default. What the components control isn't known
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct StaticInstanceParameter(pub Vec4);
+
+impl Default for StaticInstanceParameter {
+    fn default() -> Self {
+        Self(Vec4::new(1.0, 1.0, 1.0, f32::from_bits(0x02000000)))
+    }
+}
+
+/// Instances without a parameter in `parameters` use [`StaticInstanceParameter::default`]
+pub fn create_instances_scope(
+    mesh: &SStaticMeshData,
+    transforms: &[Transform],
+    parameters: &[StaticInstanceParameter],
+) -> ScopeInstances {
     ScopeInstances {
         mesh_offset: mesh.mesh_offset,
         mesh_scale: mesh.mesh_scale,
@@ -380,19 +451,26 @@ pub fn create_instances_scope(mesh: &SStaticMeshData, transforms: &[Transform])
         max_color_index: mesh.max_color_index,
         transforms: transforms
             .iter()
-            .map(|t| {
+            .enumerate()
+            .map(|(i, t)| {
                 let mat = t.local_to_world().transpose();
-                Mat4::from_cols(
-                    mat.x_axis,
-                    mat.y_axis,
-                    mat.z_axis,
-                    Vec4::new(1.0, 1.0, 1.0, f32::from_bits(0x02000000)),
-                )
+                let parameter = parameters.get(i).copied().unwrap_or_default();
+                Mat4::from_cols(mat.x_axis, mat.y_axis, mat.z_axis, parameter.0)
             })
             .collect(),
     }
 }
 
+/// Position of a static model within the instance groups of its placement. Every group of a
+/// placement shares the same [`NodeMetadata`](crate::ecs::map::NodeMetadata), so this is what
+/// tells them apart, eg. to find the group's values in the map's static AO
+#[derive(Component, Clone)]
+pub struct StaticInstanceGroup {
+    pub index: usize,
+    /// Model and instance count of every group of the placement, in order
+    pub groups: Arc<[(TagHash, usize)]>,
+}
+
 /// A single instance of a static model, can be manipulated individually
 /// Rendered by [`StaticInstances`]
 #[derive(Component)]
@@ -403,32 +481,61 @@ pub fn draw_static_instances_system(
     scene: &mut Scene,
     render_stage: TfxRenderStage,
 ) {
-    if !renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::StaticObjects)) {
-        return;
-    }
+    let statics_enabled =
+        renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::StaticObjects));
 
     profiling::scope!(
         "draw_static_instances_system",
         &format!("render_stage={render_stage:?}")
     );
-    for (e, instances, vis) in scene
-        .query::<(Entity, &StaticInstances, Option<&ViewVisibility>)>()
+    for (e, instances, vis, origin, group, stage_override) in scene
+        .query::<(
+            Entity,
+            &StaticInstances,
+            Option<&ViewVisibility>,
+            Option<&ResourceOrigin>,
+            Option<&ActivityGroup>,
+            Option<&RenderStageOverride>,
+        )>()
         .iter(scene)
     {
-        if vis.is_visible(renderer.active_view) {
+        if instances.impostor && render_stage == TfxRenderStage::GenerateGbuffer {
+            continue;
+        }
+
+        if vis.is_visible(renderer.active_view())
+            && stage_override.should_render(render_stage, statics_enabled)
+        {
             renderer.pickbuffer.with_entity(e, || {
-                instances.draw(renderer, render_stage);
+                renderer
+                    .stats
+                    .with_source(StatsSource::new(origin, group), || {
+                        instances.draw(renderer, render_stage);
+                    });
             });
         }
     }
 
-    for (e, instances, vis) in scene
-        .query::<(Entity, &StaticModelSingle, Option<&ViewVisibility>)>()
+    for (e, instances, vis, origin, group, stage_override) in scene
+        .query::<(
+            Entity,
+            &StaticModelSingle,
+            Option<&ViewVisibility>,
+            Option<&ResourceOrigin>,
+            Option<&ActivityGroup>,
+            Option<&RenderStageOverride>,
+        )>()
         .iter(scene)
     {
-        if vis.is_visible(renderer.active_view) {
+        if vis.is_visible(renderer.active_view())
+            && stage_override.should_render(render_stage, statics_enabled)
+        {
             renderer.pickbuffer.with_entity(e, || {
-                instances.draw(renderer, render_stage);
+                renderer
+                    .stats
+                    .with_source(StatsSource::new(origin, group), || {
+                        instances.draw(renderer, render_stage);
+                    });
             });
         }
     }
@@ -453,17 +560,18 @@ pub fn draw_static_instances_individual_system(
         renderer.render_globals.scopes.chunk_model.vertex_slot() as u32,
         TfxShaderStage::Vertex,
     );
-    for (e, transform, _instance, parent, vis) in scene
+    for (e, transform, parameter, _instance, parent, vis) in scene
         .query::<(
             Entity,
             &Transform,
+            Option<&StaticInstanceParameter>,
             &StaticInstance,
             &Parent,
             Option<&ViewVisibility>,
         )>()
         .iter(scene)
     {
-        if !vis.is_visible(renderer.active_view) {
+        if !vis.is_visible(renderer.active_view()) {
             continue;
         }
 
@@ -474,14 +582,27 @@ pub fn draw_static_instances_individual_system(
                         create_instances_scope(
                             &model.model.model.opaque_meshes,
                             std::slice::from_ref(transform),
+                            &[parameter.copied().unwrap_or_default()],
                         )
                         .write()
                         .as_slice(),
                     )
                     .unwrap();
             }
+            let source = renderer
+                .stats
+                .is_enabled()
+                .then(|| {
+                    StatsSource::new(
+                        scene.get::<ResourceOrigin>(parent.0),
+                        scene.get::<ActivityGroup>(parent.0),
+                    )
+                })
+                .unwrap_or_default();
             renderer.pickbuffer.with_entity(e, || {
-                model.model.draw(renderer, render_stage, 1);
+                renderer.stats.with_source(source, || {
+                    model.model.draw(renderer, render_stage, 1);
+                });
             });
         }
     }
@@ -489,40 +610,53 @@ pub fn draw_static_instances_individual_system(
 
 pub fn update_static_instances_system(
     mut q_static_instances: Query<(Entity, &mut StaticInstances, &Children)>,
-    q_static_model_single: Query<(Ref<Transform>, &StaticModelSingle)>,
-    q_instance_transform: Query<(Ref<Transform>, Option<&Aabb>)>,
+    q_static_model_single: Query<(
+        Ref<Transform>,
+        Option<Ref<StaticInstanceParameter>>,
+        &StaticModelSingle,
+    )>,
+    q_instance_transform: Query<(
+        Ref<Transform>,
+        Option<&Aabb>,
+        Option<Ref<StaticInstanceParameter>>,
+    )>,
     mut commands: Commands,
 ) {
     profiling::scope!("update_static_instances_system");
 
     for (entity, mut instances, children) in q_static_instances.iter_mut() {
         let mut transforms = Vec::with_capacity(children.len());
+        let mut parameters = Vec::with_capacity(children.len());
         let mut obbs = Vec::with_capacity(children.len());
         let mut changed = false;
         for e in children.iter() {
-            if let Ok((transform, bounds)) = q_instance_transform.get(*e) {
+            if let Ok((transform, bounds, parameter)) = q_instance_transform.get(*e) {
                 transforms.push(*transform);
+                parameters.push(parameter.as_deref().copied().unwrap_or_default());
                 obbs.push((
                     transform.local_to_world(),
                     bounds.cloned().unwrap_or(Aabb::ZERO),
                 ));
-                if transform.is_changed() {
+                if transform.is_changed() || parameter.is_some_and(|p| p.is_changed()) {
                     changed = true;
                 }
             }
         }
 
         if changed {
-            instances.update_cbuffer(&transforms);
+            instances.update_cbuffer(&transforms, &parameters);
             instances.instance_count = children.len();
 
             commands.entity(entity).insert((Aabb::from_obbs(obbs),));
         }
     }
 
-    for (transform, model) in q_static_model_single.iter() {
-        if transform.is_changed() {
-            model.update_cbuffer(&transform);
+    for (transform, parameter, model) in q_static_model_single.iter() {
+        if transform.is_changed() || parameter.as_ref().is_some_and(|p| p.is_changed()) {
+            model.update_cbuffer(
+                &transform,
+                parameter.as_deref().copied().unwrap_or_default(),
+            );
         }
     }
 }
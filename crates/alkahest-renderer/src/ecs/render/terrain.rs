@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use alkahest_data::{
     geometry::EPrimitiveType,
-    map::STerrain,
+    map::{STerrain, SUnk80807152},
     tfx::{TfxFeatureRenderer, TfxRenderStage, TfxShaderStage},
 };
 use alkahest_pm::package_manager;
@@ -14,7 +14,9 @@ use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT;
 
 use crate::{
     ecs::{
+        common::{ActivityGroup, ResourceOrigin},
         map::MapStaticAO,
+        render::stages::{RenderStageOverride, RenderStageOverrideHelper},
         visibility::{ViewVisibility, VisibilityHelper},
         Scene,
     },
@@ -22,8 +24,9 @@ use crate::{
     gpu_event,
     handle::Handle,
     loaders::{index_buffer::IndexBuffer, vertex_buffer::VertexBuffer},
-    renderer::Renderer,
+    renderer::{Renderer, StatsCategory, StatsSource},
     tfx::technique::Technique,
+    util::color::{Color, ColorExt, Hsv},
 };
 
 #[derive(Component)]
@@ -149,7 +152,30 @@ impl TerrainPatches {
         }
     }
 
+    pub fn techniques(&self) -> &[Handle<Technique>] {
+        &self.techniques
+    }
+
+    /// Parts drawn for the highest detail level, along with their index
+    pub fn highest_detail_parts(&self) -> impl Iterator<Item = (usize, &SUnk80807152)> {
+        self.terrain
+            .mesh_parts
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| u.detail_level == 0)
+    }
+
     pub fn draw(&self, renderer: &Renderer, render_stage: TfxRenderStage) {
+        self.draw_with(renderer, render_stage, |_| {});
+    }
+
+    /// Draws the patches, calling `before_part` after the technique of every part is bound
+    pub fn draw_with(
+        &self,
+        renderer: &Renderer,
+        render_stage: TfxRenderStage,
+        mut before_part: impl FnMut(&SUnk80807152),
+    ) {
         if !matches!(
             render_stage,
             TfxRenderStage::GenerateGbuffer
@@ -205,21 +231,19 @@ impl TerrainPatches {
             return;
         }
 
-        for (i, part) in self
-            .terrain
-            .mesh_parts
-            .iter()
-            .enumerate()
-            .filter(|(_, u)| u.detail_level == 0)
-        {
+        for (i, part) in self.highest_detail_parts() {
             let cb11 = &self.group_cbuffers[part.group_index as usize];
 
             if let Some(technique) = renderer.get_technique_shared(&self.techniques[i]) {
                 technique.bind(renderer).expect("Failed to bind technique");
+                renderer
+                    .stats
+                    .record_technique(StatsCategory::Terrain, &technique);
             } else {
                 continue;
             }
 
+            before_part(part);
             cb11.bind(11, TfxShaderStage::Vertex);
             if let Some(dyemap) = renderer
                 .data
@@ -232,23 +256,37 @@ impl TerrainPatches {
             }
 
             unsafe {
-                renderer
-                    .gpu
-                    .lock_context()
-                    .DrawIndexed(part.index_count as _, part.index_start as _, 0);
+                renderer.gpu.lock_context().DrawIndexed(
+                    part.index_count as _,
+                    part.index_start as _,
+                    0,
+                );
             }
+            renderer.stats.record_draw(
+                StatsCategory::Terrain,
+                part.index_count as _,
+                EPrimitiveType::TriangleStrip,
+                1,
+            );
         }
     }
 }
 
+/// Color of a terrain material in the terrain material overlay. Parts drawn with the same technique
+/// share a color
+pub fn terrain_material_color(technique: TagHash) -> Vec4 {
+    // Spread consecutive hashes over the hue circle
+    let hue = technique.0.wrapping_mul(0x9e37_79b9) as f32 / u32::MAX as f32;
+    Color::from(*Hsv::new(hue, 0.7, 0.95)).to_vec4()
+}
+
 pub fn draw_terrain_patches_system(
     renderer: &Renderer,
     scene: &mut Scene,
     render_stage: TfxRenderStage,
 ) {
-    if !renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::TerrainPatch)) {
-        return;
-    }
+    let terrain_enabled =
+        renderer.should_render(Some(render_stage), Some(TfxFeatureRenderer::TerrainPatch));
 
     if let Some(map_ao) = scene.get_resource::<MapStaticAO>() {
         unsafe {
@@ -259,13 +297,26 @@ pub fn draw_terrain_patches_system(
         }
     }
 
-    for (e, terrain, vis) in scene
-        .query::<(Entity, &TerrainPatches, Option<&ViewVisibility>)>()
+    for (e, terrain, vis, origin, group, stage_override) in scene
+        .query::<(
+            Entity,
+            &TerrainPatches,
+            Option<&ViewVisibility>,
+            Option<&ResourceOrigin>,
+            Option<&ActivityGroup>,
+            Option<&RenderStageOverride>,
+        )>()
         .iter(scene)
     {
-        if vis.is_visible(renderer.active_view) {
+        if vis.is_visible(renderer.active_view())
+            && stage_override.should_render(render_stage, terrain_enabled)
+        {
             renderer.pickbuffer.with_entity(e, || {
-                terrain.draw(renderer, render_stage);
+                renderer
+                    .stats
+                    .with_source(StatsSource::new(origin, group), || {
+                        terrain.draw(renderer, render_stage);
+                    });
             });
         }
     }
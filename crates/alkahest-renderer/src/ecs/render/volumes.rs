@@ -0,0 +1,91 @@
+use bevy_ecs::system::{Query, Res};
+use glam::{Mat4, Vec3};
+
+use crate::{
+    ecs::{
+        map::CubemapVolume,
+        render::light::LightRenderer,
+        transform::Transform,
+        visibility::{ViewVisibility, VisibilityHelper},
+    },
+    renderer::RendererShared,
+    util::color::Color,
+};
+
+const CUBEMAP_COLOR: Color = Color::from_rgb(0.2, 1.0, 0.3);
+const LIGHT_COLOR: Color = Color::from_rgb(1.0, 0.85, 0.2);
+
+/// Box of a cubemap volume, mapping the unit cube to world space
+pub fn cubemap_volume_matrix(transform: &Transform, volume: &CubemapVolume) -> Mat4 {
+    Mat4::from_scale_rotation_translation(volume.extents, transform.rotation, transform.translation)
+}
+
+fn box_contains(volume_to_world: Mat4, point: Vec3) -> bool {
+    volume_to_world
+        .inverse()
+        .transform_point3(point)
+        .abs()
+        .max_element()
+        <= 1.0
+}
+
+fn draw_volume(renderer: &RendererShared, volume_to_world: Mat4, color: Color, active: bool) {
+    let with_alpha = |a| Color::from_rgba_premultiplied(color.r(), color.g(), color.b(), a);
+    let (tint, outline) = if active {
+        (with_alpha(0.15), color)
+    } else {
+        (with_alpha(0.03), with_alpha(0.35))
+    };
+
+    renderer.immediate.cube(volume_to_world, tint);
+    renderer.immediate.cube_outline(volume_to_world, outline);
+}
+
+/// Draws the bounds of every cubemap volume and light. The volumes the camera is in are
+/// highlighted, for cubemaps only the smallest one, as that's the one that's applied
+pub fn draw_volume_bounds_system(
+    renderer: Res<RendererShared>,
+    q_cubemaps: Query<(&Transform, &CubemapVolume, Option<&ViewVisibility>)>,
+    q_lights: Query<(&Transform, &LightRenderer, Option<&ViewVisibility>)>,
+) {
+    let Some(camera_position) = renderer
+        .data
+        .lock()
+        .externs
+        .view
+        .as_ref()
+        .map(|v| v.position.truncate())
+    else {
+        return;
+    };
+
+    let cubemaps = q_cubemaps
+        .iter()
+        .filter(|(_, _, vis)| vis.is_visible(renderer.active_view()))
+        .map(|(transform, volume, _)| (cubemap_volume_matrix(transform, volume), volume.volume()))
+        .collect::<Vec<_>>();
+    let active_cubemap = cubemaps
+        .iter()
+        .enumerate()
+        .filter(|(_, (matrix, _))| box_contains(*matrix, camera_position))
+        .min_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+        .map(|(i, _)| i);
+
+    for (i, (matrix, _)) in cubemaps.iter().enumerate() {
+        draw_volume(&renderer, *matrix, CUBEMAP_COLOR, Some(i) == active_cubemap);
+    }
+
+    for (transform, light, vis) in q_lights.iter() {
+        if !vis.is_visible(renderer.active_view()) {
+            continue;
+        }
+
+        let matrix = transform.local_to_world() * light.projection_matrix;
+        draw_volume(
+            &renderer,
+            matrix,
+            LIGHT_COLOR,
+            box_contains(matrix, camera_position),
+        );
+    }
+}
@@ -60,3 +60,41 @@ impl SelectedEntity {
         }
     }
 }
+
+/// Where the entities in [`SearchHighlight`] came from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchHighlightSource {
+    Outliner,
+    Query,
+}
+
+/// Entities matching the active search, highlighted in the viewport. Stored per scene, as the
+/// entities are only meaningful for the map they were found in
+#[derive(Resource, Default)]
+pub struct SearchHighlight {
+    source: Option<SearchHighlightSource>,
+    entities: Vec<Entity>,
+}
+
+impl SearchHighlight {
+    pub fn set(&mut self, source: SearchHighlightSource, entities: Vec<Entity>) {
+        self.source = Some(source);
+        self.entities = entities;
+    }
+
+    /// Clears the highlight, unless it was set by a different source
+    pub fn clear(&mut self, source: SearchHighlightSource) {
+        if self.source == Some(source) {
+            self.source = None;
+            self.entities.clear();
+        }
+    }
+
+    pub fn source(&self) -> Option<SearchHighlightSource> {
+        self.source
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
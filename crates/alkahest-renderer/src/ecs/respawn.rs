@@ -0,0 +1,104 @@
+use alkahest_data::map::SRespawnPoint;
+use bevy_ecs::{
+    entity::Entity,
+    query::With,
+    system::{Query, Res},
+};
+use glam::Vec3;
+
+use crate::{
+    ecs::{
+        map::NodeMetadata,
+        resources::SelectedEntity,
+        tags::NodeFilter,
+        transform::Transform,
+        visibility::{ViewVisibility, VisibilityHelper},
+    },
+    renderer::RendererShared,
+    util::{
+        color::{Color, Hsv},
+        math::Vec3Ext,
+    },
+};
+
+/// Height of the player marker, roughly the height of a guardian
+const MARKER_HEIGHT: f32 = 1.9;
+const MARKER_RADIUS: f32 = 0.4;
+const ARROW_LENGTH: f32 = 1.2;
+
+/// Respawn points are loaded in sets, one per resource in a map data table. The game doesn't
+/// tell us which team a set belongs to, but opposing teams never share a set
+pub fn respawn_set_key(metadata: Option<&NodeMetadata>) -> (u32, u64) {
+    metadata.map_or((0, 0), |m| {
+        (m.source_table.0, m.source_table_resource_offset)
+    })
+}
+
+/// Stable color for a respawn set
+pub fn respawn_set_color(key: (u32, u64)) -> Color {
+    let hash = key.0 as u64 ^ key.1.rotate_left(32);
+    // Golden ratio hue stepping keeps sets that hash closely together apart
+    let hue = ((hash % 1024) as f32 * 0.618_034) % 1.0;
+    Color::from(*Hsv::new(hue, 0.75, 1.0))
+}
+
+pub fn draw_respawn_points_system(
+    renderer: Res<RendererShared>,
+    selected: Res<SelectedEntity>,
+    q_respawn: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&NodeMetadata>,
+            Option<&ViewVisibility>,
+        ),
+        With<SRespawnPoint>,
+    >,
+) {
+    if !renderer
+        .lastfilters
+        .read()
+        .contains(&NodeFilter::RespawnPoint)
+    {
+        return;
+    }
+
+    let influence_radius = renderer.settings().respawn_influence_radius;
+    for (e, transform, metadata, vis) in q_respawn.iter() {
+        if !vis.is_visible(renderer.active_view()) {
+            continue;
+        }
+
+        let color =
+            selected.select_fade_color(respawn_set_color(respawn_set_key(metadata)), Some(e));
+        let base = transform.translation;
+        let top = base + Vec3::Z * MARKER_HEIGHT;
+
+        renderer
+            .immediate
+            .circle(base, Vec3::Z * MARKER_RADIUS, 16, color);
+        renderer.immediate.line(base, top, color, 2.0);
+        renderer.immediate.sphere(top, MARKER_RADIUS * 0.5, color);
+
+        // Facing direction, flattened so it stays readable on tilted spawns
+        let forward = (transform.rotation * Vec3::X).flatten_xy(Vec3::X);
+        let right = Vec3::Z.cross(forward);
+        let arrow_start = base + Vec3::Z * 0.05;
+        let arrow_end = arrow_start + forward * ARROW_LENGTH;
+        renderer.immediate.line(arrow_start, arrow_end, color, 3.0);
+        for side in [-1.0, 1.0] {
+            renderer.immediate.line(
+                arrow_end,
+                arrow_end - forward * 0.3 + right * (side * 0.2),
+                color,
+                3.0,
+            );
+        }
+
+        if influence_radius > 0.0 {
+            renderer
+                .immediate
+                .circle(base, Vec3::Z * influence_radius, 48, color);
+        }
+    }
+}
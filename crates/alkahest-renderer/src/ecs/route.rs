@@ -1,9 +1,10 @@
-use std::fmt::Write;
+use std::{collections::VecDeque, fmt::Write};
 
 use anyhow::Context;
 use bevy_ecs::{bundle::Bundle, entity::Entity, prelude::Component, system::Commands};
 use destiny_pkg::TagHash;
 use glam::Vec3;
+use itertools::Itertools;
 
 use super::{
     common::{Global, Icon, Label, Mutable, RenderCommonBundle},
@@ -15,9 +16,12 @@ use super::{
     SceneInfo,
 };
 use crate::{
-    ecs::{hierarchy::Children, transform::Transform, Scene},
+    camera::{fps::FpsCamera, Camera, CameraProjection},
+    ecs::{hierarchy::Children, render::havok::HavokShapeRenderer, transform::Transform, Scene},
     icons::{ICON_MAP_MARKER, ICON_MAP_MARKER_PATH},
-    util::color::Color,
+    renderer::Renderer,
+    resources::AppResources,
+    util::{color::Color, text::StringExt},
 };
 
 pub struct RouteNodeData {
@@ -117,6 +121,7 @@ pub struct Route {
     pub marker_interval: f32,
     pub show_all: bool,
     pub activity_hash: Option<TagHash>,
+    pub validation_settings: RouteValidationSettings,
 }
 
 impl Default for Route {
@@ -129,6 +134,7 @@ impl Default for Route {
             marker_interval: 0.0,
             show_all: false,
             activity_hash: None,
+            validation_settings: RouteValidationSettings::default(),
         }
     }
 }
@@ -223,3 +229,233 @@ impl Utility for RouteNode {
         Label::new_default("").with_offset(0.0, 0.0, 0.12)
     }
 }
+
+#[derive(Clone, Copy)]
+pub struct RouteValidationSettings {
+    /// Steepest slope a segment can climb, in degrees
+    pub max_slope: f32,
+    /// Highest a segment can climb when it's steeper than `max_slope`
+    pub max_jump_height: f32,
+    /// Height above the nodes at which segments are tested against the scene geometry
+    pub clearance: f32,
+}
+
+impl Default for RouteValidationSettings {
+    fn default() -> Self {
+        Self {
+            max_slope: 50.0,
+            max_jump_height: 5.0,
+            clearance: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RouteIssueKind {
+    /// Scene geometry is in the way
+    Obstructed,
+    /// The segment crosses a kill or turnback barrier
+    Barrier(NodeFilter),
+    /// The segment climbs higher than can be jumped, at an angle that's too steep to walk
+    TooSteep { angle: f32, height: f32 },
+}
+
+impl std::fmt::Display for RouteIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteIssueKind::Obstructed => write!(f, "Obstructed"),
+            RouteIssueKind::Barrier(filter) => {
+                write!(f, "Crosses {}", filter.to_string().split_pascalcase())
+            }
+            RouteIssueKind::TooSteep { angle, height } => {
+                write!(f, "Too steep ({angle:.0}\u{b0}, {height:.1}m climb)")
+            }
+        }
+    }
+}
+
+pub struct RouteIssue {
+    /// Index of the node the segment starts at
+    pub segment: usize,
+    pub kind: RouteIssueKind,
+    /// Where the issue occurs along the segment
+    pub position: Vec3,
+}
+
+/// Segment of a route that still has to be tested against the scene geometry
+struct RouteProbe {
+    segment: usize,
+    start: Vec3,
+    end: Vec3,
+}
+
+/// Results of validating a route against the collision and geometry of the current map.
+/// Inserting this component on a route starts the validation.
+#[derive(Component, Default)]
+pub struct RouteValidation {
+    pub issues: Vec<RouteIssue>,
+    /// Number of segments that have to be probed in total
+    pub probe_count: usize,
+    planned: bool,
+    probes: VecDeque<RouteProbe>,
+}
+
+impl RouteValidation {
+    pub fn is_finished(&self) -> bool {
+        self.planned && self.probes.is_empty()
+    }
+
+    /// Progress of the geometry probes, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.probe_count == 0 {
+            return if self.planned { 1.0 } else { 0.0 };
+        }
+
+        1.0 - self.probes.len() as f32 / self.probe_count as f32
+    }
+
+    pub fn segment_issues(&self, segment: usize) -> impl Iterator<Item = &RouteIssue> {
+        self.issues.iter().filter(move |i| i.segment == segment)
+    }
+}
+
+/// Maximum amount the probed distance can fall short of the segment length before the segment is
+/// considered obstructed
+const PROBE_TOLERANCE: f32 = 0.25;
+
+/// Advances the validation of routes in the scene by a single step.
+/// Geometry is tested by rendering a depth probe along each segment, only one probe is rendered
+/// per call to keep the application responsive.
+pub fn route_validation_step(renderer: &Renderer, scene: &mut Scene, resources: &AppResources) {
+    let mut q_validations = scene.query::<(Entity, &RouteValidation)>();
+    let Some((entity, planned)) = q_validations
+        .iter(scene)
+        .find(|(_, v)| !v.is_finished())
+        .map(|(e, v)| (e, v.planned))
+    else {
+        return;
+    };
+
+    if !planned {
+        plan_route_validation(scene, entity);
+        return;
+    }
+
+    let Some(probe) = scene
+        .get_mut::<RouteValidation>(entity)
+        .and_then(|mut v| v.probes.pop_front())
+    else {
+        return;
+    };
+
+    let length = probe.start.distance(probe.end);
+    let mut camera = Camera::new(
+        resources.get::<Camera>().viewport().clone(),
+        CameraProjection::Perspective {
+            fov: 10.0,
+            near: 0.01,
+        },
+        Box::<FpsCamera>::default(),
+    );
+    camera.set_position(probe.start);
+    camera.set_forward(probe.end - probe.start);
+    camera.update_matrices();
+
+    let (distance, position) = renderer.probe_depth(&camera, scene, resources);
+    if distance < length - PROBE_TOLERANCE {
+        if let Some(mut validation) = scene.get_mut::<RouteValidation>(entity) {
+            validation.issues.push(RouteIssue {
+                segment: probe.segment,
+                kind: RouteIssueKind::Obstructed,
+                position,
+            });
+        }
+    }
+}
+
+/// Runs the CPU-side checks of a route, and queues up the geometry probes for each segment
+fn plan_route_validation(scene: &mut Scene, entity: Entity) {
+    let current_hash = scene.get_map_hash();
+    let settings = scene
+        .get::<Route>(entity)
+        .map(|r| r.validation_settings)
+        .unwrap_or_default();
+
+    let nodes = scene
+        .get::<Children>(entity)
+        .map(|c| {
+            c.0.iter()
+                .filter_map(|e| Some((scene.get::<Transform>(*e)?, scene.get::<RouteNode>(*e)?)))
+                .map(|(t, n)| {
+                    (
+                        t.translation,
+                        n.is_teleport,
+                        n.map_hash.is_none() || n.map_hash == current_hash,
+                    )
+                })
+                .collect_vec()
+        })
+        .unwrap_or_default();
+
+    let mut q_barriers = scene.query::<(&HavokShapeRenderer, &Transform, &NodeFilter)>();
+    let barriers = q_barriers
+        .iter(scene)
+        .filter(|(_, _, f)| {
+            matches!(
+                f,
+                NodeFilter::InstakillBarrier | NodeFilter::TurnbackBarrier
+            )
+        })
+        .collect_vec();
+
+    let mut issues = vec![];
+    let mut probes = VecDeque::new();
+    for (segment, ((start, _, start_local), (end, end_is_teleport, end_local))) in
+        nodes.iter().tuple_windows().enumerate()
+    {
+        // Teleports don't have to be traversable, and segments on other maps can't be tested
+        if *end_is_teleport || !start_local || !end_local {
+            continue;
+        }
+
+        let delta = *end - *start;
+        let horizontal = delta.truncate().length();
+        let angle = delta.z.atan2(horizontal).to_degrees();
+        if delta.z > settings.max_jump_height && angle > settings.max_slope {
+            issues.push(RouteIssue {
+                segment,
+                kind: RouteIssueKind::TooSteep {
+                    angle,
+                    height: delta.z,
+                },
+                position: (*start + *end) / 2.0,
+            });
+        }
+
+        for (shape, transform, filter) in &barriers {
+            if let Some(position) = shape.intersect_segment(transform, *start, *end) {
+                issues.push(RouteIssue {
+                    segment,
+                    kind: RouteIssueKind::Barrier(**filter),
+                    position,
+                });
+            }
+        }
+
+        // Probe cameras can't look straight up or down
+        if horizontal > 0.01 {
+            probes.push_back(RouteProbe {
+                segment,
+                start: *start + Vec3::Z * settings.clearance,
+                end: *end + Vec3::Z * settings.clearance,
+            });
+        }
+    }
+
+    if let Some(mut validation) = scene.get_mut::<RouteValidation>(entity) {
+        validation.issues = issues;
+        validation.probe_count = probes.len();
+        validation.probes = probes;
+        validation.planned = true;
+    }
+}
@@ -0,0 +1,119 @@
+//! Schedules for the systems that run every frame. Every scene keeps its own copy of the
+//! schedules, they are created the first time one of them runs.
+
+use bevy_ecs::{
+    schedule::{
+        ExecutorKind, IntoSystemConfigs, IntoSystemSetConfigs, Schedule, ScheduleLabel, Schedules,
+        SystemSet,
+    },
+    system::Res,
+};
+
+use crate::{
+    ecs::{
+        animation::animate_transforms_system,
+        channels::object_channels_discovery_system,
+        render::{
+            dynamic_geometry::update_dynamic_model_system, havok::draw_debugshapes_system,
+            light::update_shadowrenderer_system, lod::select_lod_system,
+            static_geometry::update_static_instances_system, volumes::draw_volume_bounds_system,
+        },
+        respawn::draw_respawn_points_system,
+        utility::draw_utilities_system,
+        visibility::{calculate_view_visibility_system, propagate_entity_visibility_system},
+        Scene,
+    },
+    renderer::RendererShared,
+};
+
+/// Scene maintenance, ran once per frame before anything is drawn. Expects a [`RendererShared`]
+/// resource
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct PreUpdate;
+
+/// Visibility and LOD selection for the view that is about to be drawn. Expects the
+/// [`VisibilityView`](super::visibility::VisibilityView) resource, LODs are selected when the
+/// [`LodSelectionParams`](super::render::lod::LodSelectionParams) resource is present
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ViewUpdate;
+
+/// Debug shapes and utilities drawn over the view. Expects a [`RendererShared`] resource
+#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct DrawOverlays;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum PreUpdateStage {
+    /// Systems that upload to the GPU, these run one after another
+    Gpu,
+    /// Systems that only touch the scene
+    Scene,
+}
+
+fn create_pre_update() -> Schedule {
+    let mut schedule = Schedule::new(PreUpdate);
+    schedule
+        .set_executor_kind(ExecutorKind::MultiThreaded)
+        .configure_sets(PreUpdateStage::Gpu.before(PreUpdateStage::Scene))
+        // Models pick up their transforms in the GPU stage
+        .add_systems(animate_transforms_system.before(PreUpdateStage::Gpu))
+        .add_systems(
+            (
+                object_channels_discovery_system,
+                update_static_instances_system,
+                update_dynamic_model_system,
+            )
+                .chain()
+                .in_set(PreUpdateStage::Gpu),
+        )
+        .add_systems(
+            (
+                update_shadowrenderer_system,
+                propagate_entity_visibility_system,
+            )
+                .in_set(PreUpdateStage::Scene),
+        );
+
+    schedule
+}
+
+fn create_view_update() -> Schedule {
+    let mut schedule = Schedule::new(ViewUpdate);
+    schedule
+        .set_executor_kind(ExecutorKind::MultiThreaded)
+        .add_systems((calculate_view_visibility_system, select_lod_system));
+
+    schedule
+}
+
+fn create_draw_overlays() -> Schedule {
+    let mut schedule = Schedule::new(DrawOverlays);
+    // Draws go through the immediate context, so their order has to stay fixed
+    schedule
+        .set_executor_kind(ExecutorKind::SingleThreaded)
+        .add_systems(
+            (
+                draw_debugshapes_system,
+                draw_respawn_points_system,
+                draw_volume_bounds_system
+                    .run_if(|r: Res<RendererShared>| r.settings().draw_volume_bounds),
+                draw_utilities_system.run_if(|r: Res<RendererShared>| r.settings().draw_utilities),
+            )
+                .chain(),
+        );
+
+    schedule
+}
+
+/// Runs one of the frame schedules on the scene
+pub fn run_frame_schedule(scene: &mut Scene, label: impl ScheduleLabel) {
+    let has_schedules = scene
+        .get_resource::<Schedules>()
+        .is_some_and(|s| s.contains(PreUpdate));
+    if !has_schedules {
+        scene.add_schedule(create_pre_update());
+        scene.add_schedule(create_view_update());
+        scene.add_schedule(create_draw_overlays());
+    }
+
+    scene.run_schedule(label);
+}
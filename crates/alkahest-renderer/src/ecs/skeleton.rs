@@ -0,0 +1,53 @@
+use alkahest_data::entity::SSkeleton;
+use bevy_ecs::component::Component;
+use glam::Mat4;
+
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Bind pose transform, in model space
+    pub object_transform: Mat4,
+}
+
+/// Bone hierarchy of an entity's dynamic model
+#[derive(Component)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+}
+
+impl Skeleton {
+    pub fn from_data(data: &SSkeleton) -> Self {
+        let bones = data
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| Bone {
+                name: format!("bone_{i}_{:08X}", node.name_hash),
+                parent: usize::try_from(node.parent_index)
+                    .ok()
+                    .filter(|&p| p < data.nodes.len() && p != i),
+                object_transform: data.default_object_space_transforms.get(i).map_or(
+                    Mat4::IDENTITY,
+                    |t| {
+                        Mat4::from_scale_rotation_translation(
+                            glam::Vec3::splat(t.scale),
+                            t.rotation,
+                            t.translation,
+                        )
+                    },
+                ),
+            })
+            .collect();
+
+        Self { bones }
+    }
+
+    /// Bind pose transform of a bone relative to its parent
+    pub fn local_transform(&self, index: usize) -> Mat4 {
+        let bone = &self.bones[index];
+        match bone.parent {
+            Some(parent) => self.bones[parent].object_transform.inverse() * bone.object_transform,
+            None => bone.object_transform,
+        }
+    }
+}
@@ -8,8 +8,8 @@ use super::Scene;
 use crate::{
     icons::{
         ICON_ACCOUNT_CONVERT, ICON_CHESS_PAWN, ICON_CUBE, ICON_DROPBOX, ICON_HELP,
-        ICON_LIGHTBULB_ON, ICON_PINE_TREE, ICON_REPLY, ICON_SKULL, ICON_SPHERE, ICON_STICKER,
-        ICON_TAG, ICON_TOOLBOX, ICON_VOLUME_HIGH, ICON_WEATHER_PARTLY_CLOUDY,
+        ICON_LIGHTBULB_ON, ICON_PINE_TREE, ICON_REPLY, ICON_SKATE, ICON_SKULL, ICON_SPHERE,
+        ICON_STICKER, ICON_TAG, ICON_TOOLBOX, ICON_VOLUME_HIGH, ICON_WEATHER_PARTLY_CLOUDY,
     },
     util::{color::Color, scene::EntityWorldMutExt},
 };
@@ -33,6 +33,8 @@ pub enum NodeFilter {
     PlayerContainmentVolume,
     NamedArea,
     Unknown80809121,
+    SlipSurfaceVolume,
+    Unknown80808246,
 
     Utility,
 
@@ -56,6 +58,8 @@ impl NodeFilter {
             NodeFilter::PlayerContainmentVolume => ICON_DROPBOX,
             NodeFilter::NamedArea => ICON_TAG,
             NodeFilter::Unknown80809121 => ICON_HELP,
+            NodeFilter::SlipSurfaceVolume => ICON_SKATE,
+            NodeFilter::Unknown80808246 => ICON_HELP,
             NodeFilter::Utility => ICON_TOOLBOX,
             NodeFilter::Unknown => ICON_HELP,
         }
@@ -79,13 +83,15 @@ impl NodeFilter {
             }
             NodeFilter::NamedArea => Color::from_srgba_unmultiplied(0, 127, 0, 255),
             NodeFilter::Unknown80809121 => Color::from_srgba_unmultiplied(96, 96, 255, 255),
+            NodeFilter::SlipSurfaceVolume => Color::from_srgba_unmultiplied(120, 220, 255, 255),
+            NodeFilter::Unknown80808246 => Color::from_srgba_unmultiplied(255, 140, 220, 255),
             NodeFilter::Utility => Color::from_srgba_unmultiplied(119, 142, 160, 255),
             NodeFilter::Unknown => Color::from_srgba_unmultiplied(255, 255, 255, 255),
         }
     }
 }
 
-#[derive(strum::EnumIter, Hash, PartialEq, Eq)]
+#[derive(strum::EnumIter, Debug, Clone, Hash, PartialEq, Eq)]
 pub enum EntityTag {
     Activity,
     Ambient,
@@ -93,6 +99,9 @@ pub enum EntityTag {
     Havok,
     Utility,
     User,
+    /// Tag defined by the user
+    #[strum(disabled)]
+    Custom(String),
 }
 
 pub const FNV1_BASE: u32 = 0x811c9dc5;
@@ -129,6 +138,7 @@ impl Display for EntityTag {
             EntityTag::Havok => write!(f, "Havok"),
             EntityTag::Utility => write!(f, "Utility"),
             EntityTag::User => write!(f, "User"),
+            EntityTag::Custom(name) => write!(f, "{name}"),
         }
     }
 }
@@ -141,9 +151,13 @@ impl Tags {
         self.0.insert(tag);
     }
 
-    // pub fn remove(&mut self, tag: EntityTag) {
-    //     self.0.remove(&tag);
-    // }
+    pub fn remove(&mut self, tag: &EntityTag) {
+        self.0.remove(tag);
+    }
+
+    pub fn contains(&self, tag: &EntityTag) -> bool {
+        self.0.contains(tag)
+    }
 }
 
 impl FromIterator<EntityTag> for Tags {
@@ -166,7 +180,15 @@ pub fn insert_tag(scene: &mut Scene, ent: Entity, tag: EntityTag) {
 pub fn remove_tag(scene: &mut Scene, ent: Entity, tag: EntityTag) {
     if let Some(mut e) = scene.get_entity_mut(ent) {
         if let Some(mut tags) = e.get_mut::<Tags>() {
-            tags.0.remove(&tag);
+            tags.remove(&tag);
         }
     }
 }
+
+pub fn entities_with_tag(scene: &Scene, tag: &EntityTag) -> Vec<Entity> {
+    scene
+        .iter_entities()
+        .filter(|e| e.get::<Tags>().is_some_and(|t| t.contains(tag)))
+        .map(|e| e.id())
+        .collect()
+}
@@ -4,15 +4,15 @@ use bevy_ecs::{
     bundle::Bundle,
     entity::Entity,
     prelude::Component,
-    system::{In, Query, Res, ResMut},
+    system::{Query, Res, ResMut},
 };
 use destiny_pkg::TagHash;
 use ecolor::Rgba;
-use glam::Vec3;
+use glam::{Vec3, Vec4};
 
 use super::{
     common::{Icon, Label, Mutable, RenderCommonBundle},
-    route::{Route, RouteNode},
+    route::{Route, RouteNode, RouteValidation},
     tags::{NodeFilter, Tags},
     visibility::VisibilityHelper,
     MapInfo,
@@ -22,7 +22,7 @@ use crate::{
         hierarchy::Children, resources::SelectedEntity, transform::Transform,
         visibility::ViewVisibility,
     },
-    icons::{ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE},
+    icons::{ICON_BOX_CUTTER, ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE},
     renderer::{LabelAlign, Renderer, RendererShared},
     util::{
         color::{Color, ColorExt, Hsv},
@@ -148,41 +148,95 @@ impl Utility for Beacon {
     }
 }
 
+/// Cuts away all scene geometry behind the plane. The plane goes through the translation of the
+/// entity, facing along the Z axis of its rotation. Only the side the plane is facing is kept
+#[derive(Component)]
+pub struct ClipPlane {
+    pub color: Color,
+    /// Half the width of the outline drawn for the plane, doesn't affect clipping
+    pub size: f32,
+}
+
+impl Default for ClipPlane {
+    fn default() -> Self {
+        Self {
+            color: Color::from_rgba_premultiplied(1.0, 0.35, 0.2, 1.0),
+            size: 4.0,
+        }
+    }
+}
+
+impl Utility for ClipPlane {
+    fn default_label() -> Label {
+        Label::new_default("Clip Plane")
+    }
+
+    fn icon() -> Icon {
+        Icon::Unicode(ICON_BOX_CUTTER)
+    }
+}
+
+impl ClipPlane {
+    pub fn normal(transform: &Transform) -> Vec3 {
+        transform.rotation * Vec3::Z
+    }
+
+    /// Plane equation in world space, positive on the side that is kept
+    pub fn equation(transform: &Transform) -> Vec4 {
+        let normal = Self::normal(transform);
+        normal.extend(-normal.dot(transform.translation))
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn draw_utilities_system(
-    In(renderer): In<RendererShared>,
+    renderer: Res<RendererShared>,
     map_info: Option<Res<MapInfo>>,
     selected: ResMut<SelectedEntity>,
     q_ruler: Query<(Entity, &Ruler, Option<&ViewVisibility>)>,
     q_sphere: Query<(Entity, &Transform, &Sphere, Option<&ViewVisibility>)>,
     q_beacon: Query<(Entity, &Transform, &Beacon, Option<&ViewVisibility>)>,
-    q_route: Query<(Entity, &Route, &Children, Option<&ViewVisibility>)>,
+    q_clip_plane: Query<(Entity, &Transform, &ClipPlane, Option<&ViewVisibility>)>,
+    q_route: Query<(
+        Entity,
+        &Route,
+        &Children,
+        Option<&RouteValidation>,
+        Option<&ViewVisibility>,
+    )>,
     q_route_node: Query<(Entity, &Transform, &RouteNode)>,
 ) {
     for (e, ruler, vis) in q_ruler.iter() {
-        if vis.is_visible(renderer.active_view) {
+        if vis.is_visible(renderer.active_view()) {
             draw_ruler(&renderer, ruler, e, &selected);
         }
     }
 
     for (e, transform, sphere, vis) in q_sphere.iter() {
-        if vis.is_visible(renderer.active_view) {
+        if vis.is_visible(renderer.active_view()) {
             draw_sphere(&renderer, transform, sphere, e, &selected);
         }
     }
 
     for (e, transform, beacon, vis) in q_beacon.iter() {
-        if vis.is_visible(renderer.active_view) {
+        if vis.is_visible(renderer.active_view()) {
             draw_beacon(&renderer, transform, beacon, e, &selected);
         }
     }
-    for (e, route, children, vis) in q_route.iter() {
-        if vis.is_visible(renderer.active_view) {
+
+    for (e, transform, clip_plane, vis) in q_clip_plane.iter() {
+        if vis.is_visible(renderer.active_view()) {
+            draw_clip_plane(&renderer, transform, clip_plane, e, &selected);
+        }
+    }
+    for (e, route, children, validation, vis) in q_route.iter() {
+        if vis.is_visible(renderer.active_view()) {
             if let Some(map_info) = &map_info {
                 draw_route(
                     &renderer,
                     route,
                     children,
+                    validation,
                     &q_route_node,
                     e,
                     map_info.map_hash,
@@ -408,10 +462,49 @@ fn draw_beacon(
     // );
 }
 
+fn draw_clip_plane(
+    renderer: &Renderer,
+    transform: &Transform,
+    clip_plane: &ClipPlane,
+    entity: Entity,
+    selected: &SelectedEntity,
+) {
+    let color = selected.select_fade_color(clip_plane.color, Some(entity));
+    let center = transform.translation;
+    let right = transform.rotation * Vec3::X * clip_plane.size;
+    let up = transform.rotation * Vec3::Y * clip_plane.size;
+    let corners = [
+        center - right - up,
+        center + right - up,
+        center + right + up,
+        center - right + up,
+    ];
+
+    for i in 0..4 {
+        renderer
+            .immediate
+            .line(corners[i], corners[(i + 1) % 4], color, 1.0);
+    }
+    renderer
+        .immediate
+        .line_dotted(corners[0], corners[2], color, color, 1.0, 1.0, 0.5, 0.0);
+    renderer
+        .immediate
+        .line_dotted(corners[1], corners[3], color, color, 1.0, 1.0, 0.5, 0.0);
+
+    // Points towards the side that is kept
+    let normal_end = center + ClipPlane::normal(transform) * clip_plane.size * 0.5;
+    renderer.immediate.line(center, normal_end, color, 1.0);
+    renderer
+        .immediate
+        .sphere(normal_end, clip_plane.size * 0.03, color);
+}
+
 fn draw_route(
     renderer: &Renderer,
     route: &Route,
     children: &Children,
+    validation: Option<&RouteValidation>,
     q_route_node: &Query<(Entity, &Transform, &RouteNode)>,
     entity: Entity,
     current_hash: TagHash,
@@ -465,6 +558,16 @@ fn draw_route(
                     if next_node.is_teleport { 0.10 } else { 0.75 },
                     if next_node.is_teleport { 1.5 } else { 0.5 },
                 );
+                if let Some(validation) = validation {
+                    draw_route_segment_issues(
+                        renderer,
+                        route,
+                        validation,
+                        i,
+                        pos.translation,
+                        next_pos.translation,
+                    );
+                }
                 if route.marker_interval > 0.0 {
                     let sphere_color = color.invert().keep_bright();
                     let sphere_color = Color::from_rgba_premultiplied(
@@ -517,6 +620,39 @@ fn draw_route(
     }
 }
 
+fn draw_route_segment_issues(
+    renderer: &Renderer,
+    route: &Route,
+    validation: &RouteValidation,
+    segment: usize,
+    start: Vec3,
+    end: Vec3,
+) {
+    let mut issues = validation.segment_issues(segment).peekable();
+    if issues.peek().is_none() {
+        return;
+    }
+
+    renderer.immediate.line(start, end, Color::RED, 2.0);
+    let mut text = String::new();
+    for issue in issues {
+        renderer
+            .immediate
+            .cross(issue.position, route.scale * 0.5, Color::RED);
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&issue.kind.to_string());
+    }
+
+    renderer.immediate.label(
+        text,
+        (start + end) / 2.0,
+        LabelAlign::CENTER_BOTTOM,
+        Color::RED,
+    );
+}
+
 fn draw_route_node(
     renderer: &Renderer,
     route: &Route,
@@ -4,8 +4,9 @@ use bevy_ecs::{
     component::Component,
     entity::Entity,
     query::{Has, QueryData, With, Without},
-    system::{In, Query},
+    system::{Query, Res, Resource},
 };
+use glam::Vec3;
 
 use super::{
     culling::Frustum,
@@ -13,7 +14,7 @@ use super::{
     render::static_geometry::StaticInstance,
     transform::Transform,
 };
-use crate::{ecs::culling::Sphere, util::Hocus};
+use crate::ecs::culling::Sphere;
 
 #[derive(Bundle, Default)]
 pub struct VisibilityBundle {
@@ -99,14 +100,15 @@ impl VisibilityHelper for Option<&ViewVisibility> {
 
 pub fn propagate_entity_visibility_system(
     q_root: Query<(&Children, Option<&Visibility>), Without<Parent>>,
-    q_visibility: Query<(Option<&Children>, Option<&Visibility>), With<Parent>>,
+    q_children: Query<&Children, With<Parent>>,
+    mut q_visibility: Query<&mut Visibility, With<Parent>>,
 ) {
     puffin::profile_function!();
 
     for (children, vis) in q_root.iter() {
         let vis = vis.cloned().unwrap_or_default();
         for child in children.iter() {
-            propagate_entity_visibility_recursive(*child, vis, &q_visibility);
+            propagate_entity_visibility_recursive(*child, vis, &q_children, &mut q_visibility);
         }
     }
 }
@@ -114,24 +116,51 @@ pub fn propagate_entity_visibility_system(
 fn propagate_entity_visibility_recursive(
     entity: Entity,
     parent_visibility: Visibility,
-    q_visibility: &Query<(Option<&Children>, Option<&Visibility>), With<Parent>>,
+    q_children: &Query<&Children, With<Parent>>,
+    q_visibility: &mut Query<&mut Visibility, With<Parent>>,
 ) {
-    let Ok((children, vis)) = q_visibility.get(entity) else {
-        return;
+    let vis = match q_visibility.get_mut(entity) {
+        Ok(mut vis) => {
+            if let Some(new_vis) = parent_visibility.compare(&vis) {
+                *vis = new_vis;
+            }
+            *vis
+        }
+        Err(_) => Visibility::default(),
     };
 
-    if let Some(vis) = vis.as_ref() {
-        if let Some(new_vis) = parent_visibility.compare(vis) {
-            *(*vis).pocus() = new_vis;
+    if let Ok(children) = q_children.get(entity) {
+        for child in children.iter() {
+            propagate_entity_visibility_recursive(*child, vis, q_children, q_visibility);
         }
     }
+}
 
-    let vis = vis.copied().unwrap_or_default();
-    if let Some(children) = &children {
-        for child in children.iter() {
-            propagate_entity_visibility_recursive(*child, vis, q_visibility);
+/// Ratio between the bounding radius of a decorator model and the distance beyond which the
+/// decorator is culled.
+///
+/// This is a heuristic picked by comparing against the game by eye, the actual fade distances
+/// are not known to be stored in the map data. A model with a 1m radius is culled past 200m.
+const DETAIL_FADE_RADIUS_RATIO: f32 = 0.005;
+
+/// Entity that the game fades out beyond a certain distance. Only attached to decorators, and
+/// only applied when detail fade emulation is enabled in the renderer settings
+#[derive(Component, Copy, Clone)]
+pub struct DetailFade {
+    pub max_distance: f32,
+}
+
+impl DetailFade {
+    /// Approximate fade distance for an object with the given bounding radius
+    pub fn from_radius(radius: f32) -> Self {
+        Self {
+            max_distance: radius / DETAIL_FADE_RADIUS_RATIO,
         }
     }
+
+    fn is_faded(&self, bounds: &Sphere, view_position: Vec3) -> bool {
+        bounds.center.distance(view_position) - bounds.radius > self.max_distance
+    }
 }
 
 /// Reset the view visibility of all entities to visible
@@ -148,20 +177,34 @@ pub struct CalculateViewVisibilityQuery {
     view_vis: &'static mut ViewVisibility,
     aabb: Option<&'static Aabb>,
     transform: Option<&'static Transform>,
+    detail_fade: Option<&'static DetailFade>,
     is_static_instance: Has<StaticInstance>,
 }
 
+/// View that [`calculate_view_visibility_system`] calculates visibility for
+#[derive(Resource, Clone, Copy)]
+pub struct VisibilityView {
+    pub frustum: Frustum,
+    /// Only set when detail fade emulation is enabled
+    pub fade_position: Option<Vec3>,
+}
+
 pub fn calculate_view_visibility_system(
-    In(frustum): In<Frustum>,
+    view: Res<VisibilityView>,
     mut q_visibility: Query<CalculateViewVisibilityQuery>,
 ) {
     puffin::profile_function!();
+    let VisibilityView {
+        frustum,
+        fade_position: view_position,
+    } = *view;
     q_visibility.par_iter_mut().for_each(
         |CalculateViewVisibilityQueryItem {
              vis,
              mut view_vis,
              aabb,
              transform,
+             detail_fade,
              is_static_instance,
          }| {
             view_vis.reset();
@@ -183,7 +226,11 @@ pub fn calculate_view_visibility_system(
                         sphere = sphere.transform(transform.local_to_world());
                     }
 
-                    if frustum.contains_sphere(sphere) {
+                    let faded = view_position
+                        .zip(detail_fade)
+                        .is_some_and(|(position, fade)| fade.is_faded(&sphere, position));
+
+                    if !faded && frustum.contains_sphere(sphere) {
                         view_vis.set();
                     }
                 } else {
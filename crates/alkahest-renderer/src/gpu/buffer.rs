@@ -8,7 +8,12 @@ use std::{
 
 use alkahest_data::tfx::TfxShaderStage;
 use anyhow::Context;
-use windows::Win32::Graphics::Direct3D11::*;
+use parking_lot::{
+    MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+use windows::Win32::Graphics::{
+    Direct3D::D3D11_SRV_DIMENSION_BUFFER, Direct3D11::*, Dxgi::Common::DXGI_FORMAT_UNKNOWN,
+};
 
 use crate::gpu::{GpuContext, SharedGpuContext};
 
@@ -153,9 +158,10 @@ impl<T> ConstantBuffer<T> {
     }
 }
 
-/// A constant buffer that caches the data on the CPU side
+/// A constant buffer that caches the data on the CPU side. The data is written to the GPU the
+/// next time the buffer is bound after it has been modified
 pub struct ConstantBufferCached<T: Sized> {
-    data: Vec<T>,
+    data: RwLock<Vec<T>>,
     cbuffer: ConstantBuffer<T>,
     updated: AtomicBool,
 }
@@ -164,7 +170,7 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
     pub fn create_empty(gctx: Arc<GpuContext>) -> anyhow::Result<Self> {
         Ok(Self {
             cbuffer: ConstantBuffer::create(gctx, None)?,
-            data: vec![],
+            data: RwLock::new(vec![]),
             updated: AtomicBool::new(false),
         })
     }
@@ -172,7 +178,7 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
     pub fn create_init(gctx: Arc<GpuContext>, initial_data: &T) -> anyhow::Result<Self> {
         Ok(Self {
             cbuffer: ConstantBuffer::create(gctx, Some(initial_data))?,
-            data: vec![initial_data.clone()],
+            data: RwLock::new(vec![initial_data.clone()]),
             updated: AtomicBool::new(false),
         })
     }
@@ -180,7 +186,7 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
     pub fn create_array_init(gctx: Arc<GpuContext>, initial_data: &[T]) -> anyhow::Result<Self> {
         Ok(Self {
             cbuffer: ConstantBuffer::create_array_init(gctx, initial_data)?,
-            data: initial_data.to_vec(),
+            data: RwLock::new(initial_data.to_vec()),
             updated: AtomicBool::new(false),
         })
     }
@@ -188,10 +194,13 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
     /// Writes the buffer data to the GPU
     pub fn write(&self) -> anyhow::Result<()> {
         if self.updated.load(Ordering::Relaxed) {
+            // The flag is only set while the data is locked for writing, so it can't be set again
+            // between copying the data and clearing it
+            let data = self.data.read();
             self.cbuffer.map(D3D11_MAP_WRITE_DISCARD, |map| unsafe {
                 map.pData.copy_from_nonoverlapping(
-                    self.data.as_ptr() as _,
-                    std::mem::size_of_val(self.data.as_slice()),
+                    data.as_ptr() as _,
+                    std::mem::size_of_val(data.as_slice()),
                 );
             })?;
 
@@ -206,16 +215,24 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
         self.cbuffer.buffer()
     }
 
-    #[allow(clippy::mut_from_ref)]
-    pub fn data_array(&self) -> &mut [T] {
+    /// Reads the cached data without marking the buffer for writing
+    pub fn data_slice(&self) -> MappedRwLockReadGuard<'_, [T]> {
+        RwLockReadGuard::map(self.data.read(), |d| d.as_slice())
+    }
+
+    /// Locks the cached data for writing, and marks the buffer to be written to the GPU.
+    /// The guard has to be dropped before the buffer is written or bound
+    pub fn data_array(&self) -> MappedRwLockWriteGuard<'_, [T]> {
+        let data = self.data.write();
         self.updated.store(true, Ordering::Relaxed);
-        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr() as *mut T, self.data.len()) }
+        RwLockWriteGuard::map(data, |d| d.as_mut_slice())
     }
 
-    #[allow(clippy::mut_from_ref)]
-    pub fn data(&self) -> &mut T {
+    /// Like [`Self::data_array`], for buffers holding a single element
+    pub fn data(&self) -> MappedRwLockWriteGuard<'_, T> {
+        let data = self.data.write();
         self.updated.store(true, Ordering::Relaxed);
-        unsafe { &mut *(self.data.as_ptr() as *mut T) }
+        RwLockWriteGuard::map(data, |d| &mut d[0])
     }
 
     pub fn bind(&self, slot: u32, stage: TfxShaderStage) {
@@ -226,3 +243,97 @@ impl<T: Sized + Clone> ConstantBufferCached<T> {
         self.cbuffer.bind(slot, stage)
     }
 }
+
+/// A dynamic structured buffer that grows to fit the data written to it. The buffer is kept
+/// between writes, and is only recreated when it's too small.
+pub struct DynamicStructuredBuffer<T: Sized> {
+    gctx: SharedGpuContext,
+    buffer: Option<(ID3D11Buffer, ID3D11ShaderResourceView)>,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Sized> DynamicStructuredBuffer<T> {
+    const MIN_CAPACITY: usize = 64;
+
+    pub fn new(gctx: SharedGpuContext) -> Self {
+        Self {
+            gctx,
+            buffer: None,
+            capacity: 0,
+            _marker: Default::default(),
+        }
+    }
+
+    pub fn write(&mut self, data: &[T]) -> anyhow::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if data.len() > self.capacity {
+            let capacity = data.len().next_power_of_two().max(Self::MIN_CAPACITY);
+            self.buffer = Some(self.create(capacity)?);
+            self.capacity = capacity;
+        }
+
+        let (buffer, _) = self.buffer.as_ref().unwrap();
+        unsafe {
+            let mut ptr = D3D11_MAPPED_SUBRESOURCE::default();
+            self.gctx
+                .lock_context()
+                .Map(buffer, 0, D3D11_MAP_WRITE_DISCARD, 0, Some(&mut ptr))
+                .context("Failed to map DynamicStructuredBuffer")?;
+
+            ptr.pData
+                .copy_from_nonoverlapping(data.as_ptr() as _, std::mem::size_of_val(data));
+
+            self.gctx.lock_context().Unmap(buffer, 0);
+        }
+
+        Ok(())
+    }
+
+    fn create(&self, capacity: usize) -> anyhow::Result<(ID3D11Buffer, ID3D11ShaderResourceView)> {
+        unsafe {
+            let mut buffer = None;
+            self.gctx.device.CreateBuffer(
+                &D3D11_BUFFER_DESC {
+                    ByteWidth: (capacity * std::mem::size_of::<T>()) as u32,
+                    Usage: D3D11_USAGE_DYNAMIC,
+                    BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+                    CPUAccessFlags: D3D11_CPU_ACCESS_WRITE.0 as u32,
+                    MiscFlags: D3D11_RESOURCE_MISC_BUFFER_STRUCTURED.0 as u32,
+                    StructureByteStride: std::mem::size_of::<T>() as u32,
+                },
+                None,
+                Some(&mut buffer),
+            )?;
+            let buffer = buffer.unwrap();
+
+            let mut srv = None;
+            self.gctx.device.CreateShaderResourceView(
+                &buffer,
+                Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
+                    Format: DXGI_FORMAT_UNKNOWN,
+                    ViewDimension: D3D11_SRV_DIMENSION_BUFFER,
+                    Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                        Buffer: D3D11_BUFFER_SRV {
+                            Anonymous1: D3D11_BUFFER_SRV_0 { ElementOffset: 0 },
+                            Anonymous2: D3D11_BUFFER_SRV_1 {
+                                NumElements: capacity as u32,
+                            },
+                        },
+                    },
+                }),
+                Some(&mut srv),
+            )?;
+
+            Ok((buffer, srv.unwrap()))
+        }
+    }
+
+    /// Shader resource view of the buffer, `None` if nothing has been written to it yet
+    pub fn srv(&self) -> Option<&ID3D11ShaderResourceView> {
+        self.buffer.as_ref().map(|(_, srv)| srv)
+    }
+}
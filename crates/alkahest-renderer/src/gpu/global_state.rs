@@ -3,6 +3,7 @@ use std::{ffi::CStr, fmt::Write};
 use alkahest_data::dxgi::DxgiFormat;
 use anyhow::Context;
 use itertools::Itertools;
+use rustc_hash::FxHashMap;
 use windows::{
     core::{s, PCSTR},
     Win32::{
@@ -229,8 +230,50 @@ impl RenderStates {
             .iter()
             .any(|e| e.is_instance_data)
     }
+
+    /// Describes the elements of an input layout, with their offsets within their vertex buffer
+    pub fn input_layout_elements(index: usize) -> Vec<InputElementInfo> {
+        let Some(layout) = INPUT_LAYOUTS.get(index) else {
+            return vec![];
+        };
+
+        // Elements are appended to the end of the previous element in the same buffer
+        let mut buffer_offsets = FxHashMap::default();
+        layout
+            .elements
+            .iter()
+            .map(|e| {
+                let offset = buffer_offsets.entry(e.buffer_index).or_insert(0);
+                let info = InputElementInfo {
+                    semantic_name: e.semantic_name.to_string_lossy().to_string(),
+                    semantic_index: e.semantic_index,
+                    format: e.format,
+                    buffer_index: e.buffer_index,
+                    offset: *offset,
+                    is_instance_data: e.is_instance_data,
+                };
+                *offset += e._stride;
+                info
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InputElementInfo {
+    pub semantic_name: String,
+    pub semantic_index: u32,
+    pub format: DxgiFormat,
+    /// Vertex buffer slot the element is read from
+    pub buffer_index: u32,
+    /// Byte offset of the element within a vertex
+    pub offset: u32,
+    pub is_instance_data: bool,
 }
 
+/// Premultiplied alpha "over" on every target, `src + dst * (1 - src.a)`
+pub const BLEND_STATE_PREMULTIPLIED: usize = 8;
+
 /// Same as regular D3D11 blend desc, but with only 4 render targets
 #[allow(non_snake_case)]
 struct TigerBlendDesc {
@@ -11296,3 +11339,12 @@ const STENCIL_STATES: [BungieStencilDesc; 49] = [
 ];
 
 //endregion
+
+#[test]
+fn test_blend_state_premultiplied() {
+    let rt = BLEND_STATE_DESCS[BLEND_STATE_PREMULTIPLIED].RenderTarget[0];
+    assert_eq!(rt.BlendEnable, BOOL(1));
+    assert_eq!(rt.SrcBlend, D3D11_BLEND_ONE);
+    assert_eq!(rt.DestBlend, D3D11_BLEND_INV_SRC_ALPHA);
+    assert_eq!(rt.BlendOp, D3D11_BLEND_OP_ADD);
+}
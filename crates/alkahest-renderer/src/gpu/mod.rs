@@ -46,9 +46,10 @@ pub struct GpuContext {
     context: ReentrantMutex<ID3D11DeviceContext>,
     annotation: ID3DUserDefinedAnnotation,
 
-    pub swap_chain: Option<IDXGISwapChain>,
+    swap_chain: RwLock<Option<IDXGISwapChain>>,
     pub swapchain_target: RwLock<Option<ID3D11RenderTargetView>>,
     pub swapchain_resolution: AtomicCell<(u32, u32)>,
+    pub swapchain_format: AtomicCell<DXGI_FORMAT>,
 
     pub fallback_texture: Texture,
     pub color0_fallback: VertexBuffer,
@@ -66,6 +67,8 @@ pub struct GpuContext {
     pub states: RenderStates,
 
     present_parameters: AtomicU32,
+    /// Set when presenting reported that the device was removed or reset
+    device_lost: AtomicBool,
 
     current_blend_state: AtomicUsize,
     current_input_layout: AtomicUsize,
@@ -78,7 +81,13 @@ pub struct GpuContext {
     pub current_states: AtomicCell<StateSelection>,
 
     pub util_resources: UtilResources,
-    pub custom_pixel_shader: Option<ID3D11PixelShader>,
+    /// Pixel shader that replaces the shader of every draw while set (eg. for the pickbuffer)
+    pub custom_pixel_shader: RwLock<Option<ID3D11PixelShader>>,
+    /// Draws all geometry as wireframe while set, regardless of the rasterizer state requested
+    pub wireframe_override: AtomicBool,
+    /// Rasterizer state that replaces the state of every draw while set (eg. for the wireframe
+    /// overlay). States have to be flushed after changing it
+    pub custom_rasterizer_state: RwLock<Option<ID3D11RasterizerState>>,
 
     pending_timestamp_queries: Mutex<Vec<PendingGpuTimestampRange>>,
 }
@@ -265,10 +274,12 @@ impl GpuContext {
             annotation: device_context.cast()?,
             context: ReentrantMutex::new(device_context),
 
-            swap_chain,
+            swap_chain: RwLock::new(swap_chain),
             swapchain_target: RwLock::new(swapchain_target),
             present_parameters: AtomicU32::new(0),
+            device_lost: AtomicBool::new(false),
             swapchain_resolution: AtomicCell::new((0, 0)),
+            swapchain_format: AtomicCell::new(DXGI_FORMAT_B8G8R8A8_UNORM),
 
             fallback_texture,
             color0_fallback,
@@ -298,7 +309,9 @@ impl GpuContext {
                 Some(2),
                 Some(0),
             )),
-            custom_pixel_shader: None,
+            custom_pixel_shader: RwLock::new(None),
+            wireframe_override: AtomicBool::new(false),
+            custom_rasterizer_state: RwLock::new(None),
 
             pending_timestamp_queries: Mutex::new(Vec::new()),
         })
@@ -381,20 +394,53 @@ impl GpuContext {
         self.flush_states();
     }
 
+    pub fn swap_chain(&self) -> Option<IDXGISwapChain> {
+        self.swap_chain.read().clone()
+    }
+
+    /// Returns the reason the device was removed (eg. a driver crash or update), or `None` if
+    /// the device is still usable. A removed device can't be used for anything anymore, it has
+    /// to be recreated along with every resource created on it
+    pub fn device_removed_reason(&self) -> Option<windows::core::Error> {
+        unsafe { self.device.GetDeviceRemovedReason() }.err()
+    }
+
+    /// Whether the device has to be recreated. Calls that fail mid-frame because of a lost device
+    /// only log their error, the app recovers at the start of the next frame
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed) || self.device_removed_reason().is_some()
+    }
+
+    /// Unbinds and releases the swapchain, so a new device can create one for the same window
+    pub fn release_swapchain(&self) {
+        drop(self.swapchain_target.write().take());
+        unsafe {
+            let ctx = self.lock_context();
+            ctx.ClearState();
+            ctx.Flush();
+        }
+        drop(self.swap_chain.write().take());
+    }
+
     pub fn present(&self, vsync: bool) {
-        if let Some(swap_chain) = &self.swap_chain {
-            unsafe {
-                if swap_chain.Present(
+        if let Some(swap_chain) = self.swap_chain() {
+            let result = unsafe {
+                swap_chain.Present(
                     vsync as u32,
                     self.present_parameters.load(Ordering::Relaxed),
-                ) == DXGI_STATUS_OCCLUDED
-                {
-                    self.present_parameters
-                        .store(DXGI_PRESENT_TEST, Ordering::Relaxed);
-                    std::thread::sleep(Duration::from_millis(50));
-                } else {
-                    self.present_parameters.store(0, Ordering::Relaxed);
+                )
+            };
+
+            if result == DXGI_STATUS_OCCLUDED {
+                self.present_parameters
+                    .store(DXGI_PRESENT_TEST, Ordering::Relaxed);
+                std::thread::sleep(Duration::from_millis(50));
+            } else {
+                if result == DXGI_ERROR_DEVICE_REMOVED || result == DXGI_ERROR_DEVICE_RESET {
+                    error!("Failed to present: {}", windows::core::Error::from(result));
+                    self.device_lost.store(true, Ordering::Relaxed);
                 }
+                self.present_parameters.store(0, Ordering::Relaxed);
             }
         } else if vsync {
             std::thread::sleep(Duration::from_millis(1000 / 60));
@@ -404,29 +450,100 @@ impl GpuContext {
         let width = width.max(4);
         let height = height.max(4);
 
-        if let Some(swap_chain) = &self.swap_chain {
-            unsafe {
-                drop(self.swapchain_target.write().take());
-
-                swap_chain
-                    .ResizeBuffers(2, width, height, DXGI_FORMAT_B8G8R8A8_UNORM, 0)
-                    .unwrap();
+        if let Some(swap_chain) = self.swap_chain() {
+            if let Err(e) = self.recreate_swapchain_buffers(&swap_chain, width, height) {
+                error!("Failed to resize the swapchain: {e:?}");
+            }
+        }
 
-                let bb: ID3D11Texture2D = swap_chain.GetBuffer(0).unwrap();
+        self.swapchain_resolution.store((width, height));
+    }
 
-                let mut new_rtv = None;
-                self.device
-                    .CreateRenderTargetView(&bb, None, Some(&mut new_rtv))
-                    .unwrap();
+    /// Recreates the swapchain buffers with a new format and color space, keeping their size.
+    /// The previous format is restored if the display doesn't support the color space.
+    pub fn set_swapchain_format(
+        &self,
+        format: DXGI_FORMAT,
+        color_space: DXGI_COLOR_SPACE_TYPE,
+    ) -> anyhow::Result<()> {
+        let Some(swap_chain) = self.swap_chain() else {
+            return Ok(());
+        };
+        let swap_chain3: IDXGISwapChain3 = swap_chain.cast()?;
 
-                self.lock_context()
-                    .OMSetRenderTargets(Some(&[new_rtv.clone()]), None);
+        let previous_format = self.swapchain_format.swap(format);
+        self.recreate_swapchain_buffers(&swap_chain, 0, 0)?;
 
-                *self.swapchain_target.write() = new_rtv;
+        unsafe {
+            let support = swap_chain3.CheckColorSpaceSupport(color_space)?;
+            if (support & DXGI_SWAP_CHAIN_COLOR_SPACE_SUPPORT_FLAG_PRESENT.0 as u32) == 0 {
+                self.swapchain_format.store(previous_format);
+                self.recreate_swapchain_buffers(&swap_chain, 0, 0)?;
+                anyhow::bail!(
+                    "Color space {color_space:?} is not supported by the display, is HDR enabled in the display settings?"
+                );
             }
+
+            swap_chain3
+                .SetColorSpace1(color_space)
+                .context("Failed to set swapchain color space")?;
         }
 
-        self.swapchain_resolution.store((width, height));
+        Ok(())
+    }
+
+    /// Whether the display the window is on has HDR enabled, and its peak luminance in nits
+    pub fn display_hdr_info(&self) -> Option<(bool, f32)> {
+        let swap_chain = self.swap_chain()?;
+        unsafe {
+            let output: IDXGIOutput6 = swap_chain.GetContainingOutput().ok()?.cast().ok()?;
+            let desc = output.GetDesc1().ok()?;
+            Some((
+                desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+                desc.MaxLuminance,
+            ))
+        }
+    }
+
+    /// Video memory used by this process and the budget the OS has given it, in bytes, as
+    /// reported by the driver
+    pub fn video_memory_info(&self) -> Option<(u64, u64)> {
+        unsafe {
+            let device: IDXGIDevice = self.device.cast().ok()?;
+            let adapter: IDXGIAdapter3 = device.GetAdapter().ok()?.cast().ok()?;
+            let mut info = DXGI_QUERY_VIDEO_MEMORY_INFO::default();
+            adapter
+                .QueryVideoMemoryInfo(0, DXGI_MEMORY_SEGMENT_GROUP_LOCAL, &mut info)
+                .ok()?;
+            Some((info.CurrentUsage, info.Budget))
+        }
+    }
+
+    /// Resizes the swapchain buffers using the current swapchain format. A size of 0 keeps the current size
+    fn recreate_swapchain_buffers(
+        &self,
+        swap_chain: &IDXGISwapChain,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<()> {
+        unsafe {
+            drop(self.swapchain_target.write().take());
+
+            swap_chain.ResizeBuffers(2, width, height, self.swapchain_format.load(), 0)?;
+
+            let bb: ID3D11Texture2D = swap_chain.GetBuffer(0)?;
+
+            let mut new_rtv = None;
+            self.device
+                .CreateRenderTargetView(&bb, None, Some(&mut new_rtv))?;
+
+            self.lock_context()
+                .OMSetRenderTargets(Some(&[new_rtv.clone()]), None);
+
+            *self.swapchain_target.write() = new_rtv;
+        }
+
+        Ok(())
     }
 }
 
@@ -463,13 +580,7 @@ impl GpuContext {
 
     pub fn set_rasterizer_state(&self, index: usize) {
         if self.current_rasterizer_state.load(Ordering::Relaxed) != index {
-            unsafe {
-                let depth_bias = self.current_depth_bias.load(Ordering::Relaxed);
-                if index < 9 && depth_bias < 9 {
-                    self.lock_context()
-                        .RSSetState(self.states.rasterizer_states[depth_bias][index].as_ref());
-                }
-            }
+            self.apply_rasterizer_state(index, self.current_depth_bias.load(Ordering::Relaxed));
             self.current_rasterizer_state
                 .store(index, Ordering::Relaxed);
         }
@@ -477,18 +588,55 @@ impl GpuContext {
 
     pub fn set_depth_bias(&self, index: usize) {
         if self.current_depth_bias.load(Ordering::Relaxed) != index {
-            unsafe {
-                let rasterizer_state = self.current_rasterizer_state.load(Ordering::Relaxed);
-                if index < 9 && rasterizer_state < 9 {
-                    self.lock_context().RSSetState(
-                        self.states.rasterizer_states[index][rasterizer_state].as_ref(),
-                    );
-                }
-            }
+            self.apply_rasterizer_state(
+                self.current_rasterizer_state.load(Ordering::Relaxed),
+                index,
+            );
             self.current_depth_bias.store(index, Ordering::Relaxed);
         }
     }
 
+    /// Binds the rasterizer state for the given rasterizer and depth bias state combination,
+    /// unless a custom rasterizer state is set
+    fn apply_rasterizer_state(&self, rasterizer_state: usize, depth_bias: usize) {
+        unsafe {
+            if let Some(state) = self.custom_rasterizer_state.read().as_ref() {
+                self.lock_context().RSSetState(state);
+            } else if rasterizer_state < 9 && depth_bias < 9 {
+                self.lock_context().RSSetState(
+                    self.states.rasterizer_states[depth_bias]
+                        [self.override_rasterizer(rasterizer_state)]
+                    .as_ref(),
+                );
+            }
+        }
+    }
+
+    /// Swaps the rasterizer state for its wireframe equivalent when the wireframe override is set
+    fn override_rasterizer(&self, index: usize) -> usize {
+        if !self.wireframe_override.load(Ordering::Relaxed) {
+            return index;
+        }
+
+        match index {
+            // Back face culling
+            2 | 4 | 6 => 4,
+            _ => 5,
+        }
+    }
+
+    /// Enables or disables the wireframe override, and reapplies the current rasterizer state
+    pub fn set_wireframe_override(&self, wireframe: bool) {
+        if self.wireframe_override.swap(wireframe, Ordering::Relaxed) != wireframe {
+            let index = self
+                .current_rasterizer_state
+                .swap(usize::MAX, Ordering::Relaxed);
+            if index != usize::MAX {
+                self.set_rasterizer_state(index);
+            }
+        }
+    }
+
     pub fn set_input_layout(&self, index: usize) {
         if self.current_input_layout.load(Ordering::Relaxed) != index {
             unsafe {
@@ -553,9 +701,10 @@ impl GpuContext {
     pub fn bind_pixel_shader<'a, S: Into<Option<&'a ID3D11PixelShader>>>(&self, shader: S) {
         let shader = shader.into();
         if shader.is_some() {
+            let custom_pixel_shader = self.custom_pixel_shader.read();
             unsafe {
                 self.lock_context()
-                    .PSSetShader(self.custom_pixel_shader.as_ref().or(shader), None);
+                    .PSSetShader(custom_pixel_shader.as_ref().or(shader), None);
             }
         } else {
             unsafe {
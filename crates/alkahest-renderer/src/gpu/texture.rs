@@ -71,7 +71,17 @@ impl Texture {
     }
 
     pub fn load(device: &ID3D11Device, hash: WideHash) -> anyhow::Result<Texture> {
-        let _span = debug_span!("Load texture", ?hash).entered();
+        Self::load_with_mip_skip(device, hash, 0)
+    }
+
+    /// Loads a texture without its `skip_mips` most detailed mip levels, used for streaming in
+    /// textures at a reduced quality. Only applies to 2D textures, and never skips below 4x4
+    pub fn load_with_mip_skip(
+        device: &ID3D11Device,
+        hash: WideHash,
+        skip_mips: usize,
+    ) -> anyhow::Result<Texture> {
+        let _span = debug_span!("Load texture", ?hash, skip_mips).entered();
         let (texture, texture_data) = Self::load_data(hash, true)?;
 
         let (tex, view) = unsafe {
@@ -256,6 +266,19 @@ impl Texture {
                     }
                 }
 
+                let mut skipped_mips = 0;
+                if verylowres_mip == 0 {
+                    while skipped_mips < skip_mips
+                        && initial_data.len() > 1
+                        && (texture.width >> (skipped_mips + 1)) >= 4
+                        && (texture.height >> (skipped_mips + 1)) >= 4
+                    {
+                        skipped_mips += 1;
+                    }
+
+                    initial_data.drain(..skipped_mips);
+                }
+
                 if mipcount_fixed < 1 {
                     error!(
                         "Invalid mipcount for texture {hash:?} (width={}, height={}, mips={})",
@@ -268,8 +291,8 @@ impl Texture {
                 device
                     .CreateTexture2D(
                         &D3D11_TEXTURE2D_DESC {
-                            Width: (texture.width >> verylowres_mip) as _,
-                            Height: (texture.height >> verylowres_mip) as _,
+                            Width: (texture.width >> verylowres_mip >> skipped_mips) as _,
+                            Height: (texture.height >> verylowres_mip >> skipped_mips) as _,
                             MipLevels: initial_data.len() as u32,
                             ArraySize: 1 as _,
                             Format: dxgi_to_win(texture.format),
@@ -454,6 +477,90 @@ impl Texture {
         }
     }
 
+    /// Creates a cubemap from the first mip of each face (in +X, -X, +Y, -Y, +Z, -Z order), the
+    /// rest of the mip chain is generated on the GPU
+    pub fn load_cube_raw_with_mips(
+        gctx: &GpuContext,
+        size: u32,
+        faces: &[&[u8]; 6],
+        format: DxgiFormat,
+        name: Option<&str>,
+    ) -> anyhow::Result<Texture> {
+        unsafe {
+            let mut tex = None;
+            gctx.device
+                .CreateTexture2D(
+                    &D3D11_TEXTURE2D_DESC {
+                        Width: size,
+                        Height: size,
+                        MipLevels: 0,
+                        ArraySize: 6,
+                        Format: dxgi_to_win(format),
+                        SampleDesc: DXGI_SAMPLE_DESC {
+                            Count: 1,
+                            Quality: 0,
+                        },
+                        Usage: D3D11_USAGE_DEFAULT,
+                        BindFlags: (D3D11_BIND_SHADER_RESOURCE.0 | D3D11_BIND_RENDER_TARGET.0)
+                            as u32,
+                        CPUAccessFlags: Default::default(),
+                        MiscFlags: (D3D11_RESOURCE_MISC_TEXTURECUBE.0
+                            | D3D11_RESOURCE_MISC_GENERATE_MIPS.0)
+                            as u32,
+                    },
+                    None,
+                    Some(&mut tex),
+                )
+                .context("Failed to create cube texture")?;
+
+            let tex = tex.unwrap();
+
+            if let Some(name) = name {
+                tex.set_debug_name(name);
+            }
+
+            let mut view = None;
+            gctx.device.CreateShaderResourceView(
+                &tex,
+                Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
+                    Format: dxgi_to_win(format),
+                    ViewDimension: D3D11_SRV_DIMENSION_TEXTURECUBE,
+                    Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                        TextureCube: D3D11_TEXCUBE_SRV {
+                            MostDetailedMip: 0,
+                            MipLevels: u32::MAX,
+                        },
+                    },
+                }),
+                Some(&mut view),
+            )?;
+
+            let view = view.unwrap();
+
+            let mut desc = Default::default();
+            tex.GetDesc(&mut desc);
+            let pitch = format.calculate_pitch(size as usize, size as usize).0;
+            let ctx = gctx.lock_context();
+            for (i, face) in faces.iter().enumerate() {
+                ctx.UpdateSubresource(
+                    &tex,
+                    calc_dx_subresource(0, i, desc.MipLevels as usize) as u32,
+                    None,
+                    face.as_ptr() as _,
+                    pitch as u32,
+                    0,
+                );
+            }
+            ctx.GenerateMips(&view);
+
+            Ok(Texture {
+                handle: TextureHandle::TextureCube(tex),
+                view,
+                format,
+            })
+        }
+    }
+
     pub fn load_png(
         device: &ID3D11Device,
         png: &Png,
@@ -486,6 +593,47 @@ impl Texture {
     pub fn bind(&self, gctx: &GpuContext, slot: u32, stage: TfxShaderStage) {
         gctx.bind_srv(Some(self.view.clone()), slot, stage);
     }
+
+    /// Width, height, depth, mip count and array size of the texture
+    fn desc(&self) -> (u32, u32, u32, u32, u32) {
+        unsafe {
+            match &self.handle {
+                TextureHandle::Texture2D(tex) | TextureHandle::TextureCube(tex) => {
+                    let mut desc = Default::default();
+                    tex.GetDesc(&mut desc);
+                    (desc.Width, desc.Height, 1, desc.MipLevels, desc.ArraySize)
+                }
+                TextureHandle::Texture3D(tex) => {
+                    let mut desc = Default::default();
+                    tex.GetDesc(&mut desc);
+                    (desc.Width, desc.Height, desc.Depth, desc.MipLevels, 1)
+                }
+            }
+        }
+    }
+
+    /// Width, height and depth of the first mip level
+    pub fn dimensions(&self) -> (u32, u32, u32) {
+        let (width, height, depth, _, _) = self.desc();
+        (width, height, depth)
+    }
+
+    /// Approximate amount of GPU memory used by the texture, including all mips and array slices
+    pub fn memory_size(&self) -> usize {
+        let (width, height, depth, mip_count, array_size) = self.desc();
+
+        let mip_sizes: usize = (0..mip_count)
+            .map(|mip| {
+                let (_, slice_pitch) = self.format.calculate_pitch(
+                    (width >> mip).max(1) as usize,
+                    (height >> mip).max(1) as usize,
+                );
+                slice_pitch * (depth >> mip).max(1) as usize
+            })
+            .sum();
+
+        mip_sizes * array_size as usize
+    }
 }
 
 fn dxgi_to_win(v: DxgiFormat) -> DXGI_FORMAT {
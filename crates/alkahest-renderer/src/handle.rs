@@ -1,7 +1,10 @@
 use std::{
     fmt::{Debug, Formatter},
     hash::{BuildHasherDefault, Hash},
-    sync::{Arc, Weak},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc, Weak,
+    },
 };
 
 use destiny_pkg::TagHash;
@@ -213,6 +216,26 @@ impl Asset for IndexBuffer {}
 struct AssetStorage<T: Asset> {
     refcount: Weak<()>,
     asset: Option<Arc<T>>,
+    /// Frame in which the asset was last accessed
+    last_access: AtomicU64,
+    /// Frame in which the asset was evicted, if it has been unloaded to save memory
+    evicted_at: Option<u64>,
+    /// Largest screen coverage the asset was used with in `coverage_frame`, as `f32` bits
+    coverage: AtomicU32,
+    coverage_frame: AtomicU64,
+}
+
+impl<T: Asset> AssetStorage<T> {
+    fn new(refcount: Weak<()>, asset: Option<Arc<T>>, frame: u64) -> Self {
+        Self {
+            refcount,
+            asset,
+            last_access: AtomicU64::new(frame),
+            evicted_at: None,
+            coverage: AtomicU32::new(0),
+            coverage_frame: AtomicU64::new(frame),
+        }
+    }
 }
 
 type FastHasher = BuildHasherDefault<FxHasher>;
@@ -221,26 +244,40 @@ pub struct AssetRegistry<T: Asset> {
     handle_map: IndexMap<AssetId, AssetStorage<T>, FastHasher>,
     next_id: usize,
     disabled: bool,
+    frame: u64,
 }
 
 impl<T: Asset + 'static> AssetRegistry<T> {
     pub fn new(enabled: bool) -> Self {
         Self {
             handle_map: IndexMap::with_hasher(FastHasher::default()),
-            next_id: 0,
+            // Alkahest ID 0 is reserved for `Handle::none`
+            next_id: 1,
             disabled: !enabled,
+            frame: 0,
         }
     }
 
-    // pub fn reserve_handle(&mut self) -> Handle<T> {
-    //     let id = self.next_id;
-    //     self.next_id += 1;
-    //     Handle {
-    //         ref_count: Arc::new(()),
-    //         id: AssetId::new_alkahest(id as u64),
-    //         _phantom: std::marker::PhantomData,
-    //     }
-    // }
+    /// Reserve a handle for an asset that isn't loaded from the packages, the asset is set later
+    /// using [`Self::overwrite`]
+    pub fn reserve_handle(&mut self) -> Handle<T> {
+        if self.disabled {
+            return Handle::none();
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let handle = Handle {
+            refcount: Arc::new(()),
+            id: AssetId::new_alkahest(id as u64),
+            _phantom: std::marker::PhantomData,
+        };
+
+        self.handle_map.insert(
+            handle.id,
+            AssetStorage::new(Arc::downgrade(&handle.refcount), None, self.frame),
+        );
+        handle
+    }
 
     /// Reserve handle or return the existing handle if it already exists
     pub fn get_handle_tiger(&mut self, taghash: TagHash) -> Handle<T> {
@@ -264,10 +301,7 @@ impl<T: Asset + 'static> AssetRegistry<T> {
 
             self.handle_map.insert(
                 h.id,
-                AssetStorage {
-                    refcount: Arc::downgrade(&h.refcount),
-                    asset: None,
-                },
+                AssetStorage::new(Arc::downgrade(&h.refcount), None, self.frame),
             );
 
             h
@@ -302,6 +336,7 @@ impl<T: Asset + 'static> AssetRegistry<T> {
         let id = handle.id;
         if let Some(storage) = self.handle_map.get_mut(&id) {
             let _ = storage.asset.insert(Arc::new(asset));
+            storage.evicted_at = None;
         } else {
             error!("Tried to overwrite non-existent asset {id:?}")
         }
@@ -321,10 +356,11 @@ impl<T: Asset + 'static> AssetRegistry<T> {
 
         self.handle_map.insert(
             handle.id,
-            AssetStorage {
-                refcount: Arc::downgrade(&handle.refcount),
-                asset: Some(Arc::new(asset)),
-            },
+            AssetStorage::new(
+                Arc::downgrade(&handle.refcount),
+                Some(Arc::new(asset)),
+                self.frame,
+            ),
         );
         handle
     }
@@ -334,9 +370,10 @@ impl<T: Asset + 'static> AssetRegistry<T> {
             return None;
         }
 
-        self.handle_map
-            .get(&handle.id)
-            .and_then(|storage| storage.asset.as_ref().map(|v| v.as_ref()))
+        self.handle_map.get(&handle.id).and_then(|storage| {
+            storage.last_access.store(self.frame, Ordering::Relaxed);
+            storage.asset.as_ref().map(|v| v.as_ref())
+        })
     }
 
     pub fn get_shared(&self, handle: &Handle<T>) -> Option<Arc<T>> {
@@ -344,9 +381,35 @@ impl<T: Asset + 'static> AssetRegistry<T> {
             return None;
         }
 
-        self.handle_map
-            .get(&handle.id)
-            .and_then(|storage| storage.asset.clone())
+        self.handle_map.get(&handle.id).and_then(|storage| {
+            storage.last_access.store(self.frame, Ordering::Relaxed);
+            storage.asset.clone()
+        })
+    }
+
+    /// Records that the asset is used by something covering `coverage` of the screen this frame
+    pub fn record_coverage(&self, handle: &Handle<T>, coverage: f32) {
+        if handle.is_none() || self.disabled {
+            return;
+        }
+
+        if let Some(storage) = self.handle_map.get(&handle.id) {
+            // The bits of non-negative floats sort the same way as the floats themselves
+            let bits = coverage.max(0.0).to_bits();
+            if storage.coverage_frame.swap(self.frame, Ordering::Relaxed) == self.frame {
+                storage.coverage.fetch_max(bits, Ordering::Relaxed);
+            } else {
+                storage.coverage.store(bits, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Largest screen coverage the asset was used with during the previous frame, if it was used
+    pub fn last_coverage(&self, id: AssetId) -> Option<f32> {
+        self.handle_map.get(&id).and_then(|storage| {
+            (storage.coverage_frame.load(Ordering::Relaxed) + 1 >= self.frame)
+                .then(|| f32::from_bits(storage.coverage.load(Ordering::Relaxed)))
+        })
     }
 
     pub fn remove_all_dead(&mut self) -> usize {
@@ -361,4 +424,97 @@ impl<T: Asset + 'static> AssetRegistry<T> {
 
         removed
     }
+    /// Number of handles in the registry, including assets that haven't finished loading
+    pub fn len(&self) -> usize {
+        self.handle_map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handle_map.is_empty()
+    }
+
+    /// Number of assets that are no longer referenced, but haven't been removed yet
+    pub fn dead_count(&self) -> usize {
+        self.handle_map
+            .values()
+            .filter(|storage| storage.refcount.strong_count() == 0)
+            .count()
+    }
+
+    /// Iterates over all loaded assets that are still referenced, without marking them as accessed
+    pub fn iter_loaded(&self) -> impl Iterator<Item = (AssetId, &T)> {
+        self.handle_map
+            .iter()
+            .filter(|(_, storage)| storage.refcount.strong_count() > 0)
+            .filter_map(|(id, storage)| storage.asset.as_deref().map(|asset| (*id, asset)))
+    }
+
+    /// Advances the frame counter used for tracking asset access
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns whether the asset has been accessed within the last `frames` frames
+    pub fn used_within(&self, id: AssetId, frames: u64) -> bool {
+        self.handle_map.get(&id).map_or(false, |storage| {
+            storage.last_access.load(Ordering::Relaxed) + frames >= self.frame
+        })
+    }
+
+    /// Loaded assets that haven't been accessed for at least `min_age` frames, least recently used first
+    pub fn least_recently_used(&self, min_age: u64) -> Vec<AssetId> {
+        let mut candidates: Vec<(AssetId, u64)> = self
+            .handle_map
+            .iter()
+            .filter(|(_, storage)| storage.asset.is_some() && storage.refcount.strong_count() > 0)
+            .map(|(id, storage)| (*id, storage.last_access.load(Ordering::Relaxed)))
+            .filter(|(_, last_access)| last_access + min_age <= self.frame)
+            .collect();
+
+        candidates.sort_by_key(|(_, last_access)| *last_access);
+        candidates.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Unloads the asset while keeping its handle valid, so it can be loaded again once it's needed
+    pub fn evict(&mut self, id: AssetId) -> bool {
+        let frame = self.frame;
+        if let Some(storage) = self.handle_map.get_mut(&id) {
+            if storage.asset.take().is_some() {
+                storage.evicted_at = Some(frame);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Returns handles for evicted assets that have been accessed since they were evicted.
+    /// The returned assets are no longer marked as evicted, so they will only be returned once
+    pub fn take_evicted_in_use(&mut self) -> Vec<Handle<T>> {
+        let mut handles = vec![];
+        for (id, storage) in self.handle_map.iter_mut() {
+            let Some(evicted_at) = storage.evicted_at else {
+                continue;
+            };
+
+            if storage.last_access.load(Ordering::Relaxed) < evicted_at {
+                continue;
+            }
+
+            if let Some(refcount) = storage.refcount.upgrade() {
+                storage.evicted_at = None;
+                handles.push(Handle {
+                    refcount,
+                    id: *id,
+                    _phantom: std::marker::PhantomData,
+                });
+            }
+        }
+
+        handles
+    }
 }
@@ -24,6 +24,16 @@ pub struct IndexBuffer {
 }
 
 impl IndexBuffer {
+    /// Size of the buffer, in bytes
+    pub fn size(&self) -> usize {
+        let index_size = if self.format == DxgiFormat::R16_UINT {
+            2
+        } else {
+            4
+        };
+        self.length * index_size
+    }
+
     pub fn load_u16(gpu: &GpuContext, data: &[u16]) -> anyhow::Result<Self> {
         let mut buffer = None;
         unsafe {
@@ -1,15 +1,23 @@
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::{
+    io::{Cursor, Seek, SeekFrom},
+    sync::Arc,
+    time::Instant,
+};
 
 use alkahest_data::{
-    activity::{SActivity, SEntityResource, SUnk8080460c, Unk80808cef, Unk80808e89, Unk808092d8},
+    activity::{
+        SActivity, SDestination, SEntityResource, SUnk8080460c, Unk80808cef, Unk80808e89,
+        Unk808092d8,
+    },
     common::ResourceHash,
     decorator::SDecorator,
-    entity::{SEntity, Unk808072c5, Unk8080906b, Unk80809905},
+    entity::{SEntity, SSkeleton, Unk808072c5, Unk8080906b, Unk80809905},
     map::{
-        SAudioClipCollection, SBubbleDefinition, SBubbleParent, SCubemapVolume,
+        CubemapVolumeInfo, SAudioClipCollection, SBubbleDefinition, SBubbleParent,
         SDecalCollectionResource, SHavokShapeRef, SLensFlare, SLightCollection, SMapAtmosphere,
-        SMapDataTable, SShadowingLight, SStaticAmbientOcclusion, SUnk808068d4, SUnk80806aa7,
-        SUnk80806ef4, SUnk8080714b, SUnk80808604, SUnk80808cb7, SUnk80809178, SUnk8080917b,
+        SMapDataTable, SShadowingLight, SSlipSurfaceVolume, SStaticAmbientOcclusion, SUnk808068d4,
+        SUnk80806aa7, SUnk80806ef4, SUnk8080714b, SUnk80808246, SUnk80808604, SUnk80808cb7,
+        SUnk80809178, SUnk8080917b,
     },
     occlusion::Aabb,
     text::{StringContainer, StringContainerShared},
@@ -22,8 +30,9 @@ use bevy_ecs::{bundle::Bundle, entity::Entity, query::With};
 use binrw::BinReaderExt;
 use destiny_pkg::TagHash;
 use ecolor::Color32;
-use glam::{Mat4, Vec3, Vec4Swizzles};
+use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
 use itertools::{multizip, Itertools};
+use rayon::prelude::*;
 use rustc_hash::{FxHashMap, FxHashSet};
 use tiger_parse::{Endian, FnvHash, PackageManagerExt, TigerReadable};
 
@@ -31,7 +40,7 @@ use crate::{
     camera::CameraProjection,
     ecs::{
         audio::AmbientAudio,
-        common::{Icon, Label, RenderCommonBundle, ResourceOrigin},
+        common::{ActivityGroup, Icon, Label, RenderCommonBundle, ResourceOrigin},
         hierarchy::{Children, Parent},
         map::{CubemapVolume, MapAtmosphere, MapStaticAO, NodeMetadata},
         render::{
@@ -39,12 +48,16 @@ use crate::{
             dynamic_geometry::DynamicModelComponent,
             havok::HavokShapeRenderer,
             light::{LightRenderer, LightShape, ShadowMapRenderer},
-            static_geometry::{StaticInstance, StaticInstances, StaticModel, StaticModelSingle},
+            static_geometry::{
+                StaticInstance, StaticInstanceGroup, StaticInstances, StaticModel,
+                StaticModelSingle,
+            },
             terrain::TerrainPatches,
         },
+        skeleton::Skeleton,
         tags::{insert_tag, EntityTag, NodeFilter},
         transform::{OriginalTransform, Transform, TransformFlags},
-        visibility::VisibilityBundle,
+        visibility::{DetailFade, VisibilityBundle},
         Scene, SceneInfo,
     },
     icons::{
@@ -53,6 +66,7 @@ use crate::{
         ICON_SPHERE, ICON_SPOTLIGHT_BEAM, ICON_STICKER, ICON_TREE, ICON_WAVES, ICON_WEATHER_FOG,
         ICON_WEATHER_PARTLY_CLOUDY,
     },
+    loaders::map_report::{LoadWarnings, MapLoadReport, SkippedResource},
     renderer::{Renderer, RendererShared},
     util::{
         black_magic::EntityRefDarkMagic,
@@ -68,23 +82,73 @@ pub async fn load_map(
     stringmap: StringContainerShared,
     load_ambient_activity: bool,
 ) -> anyhow::Result<Scene> {
+    load_map_inner(
+        Some(&**renderer),
+        map_hash,
+        activity_hash,
+        stringmap,
+        load_ambient_activity,
+    )
+    .await
+}
+
+/// Loads a map without creating any GPU resources (no textures, buffers, models or lights).
+///
+/// Every data table entry is still spawned with its transform, metadata and resource origin, so the resulting scene
+/// can be queried and exported on machines without a D3D11 device.
+pub async fn load_map_data_only(
+    map_hash: TagHash,
+    activity_hash: Option<TagHash>,
+    stringmap: StringContainerShared,
+    load_ambient_activity: bool,
+) -> anyhow::Result<Scene> {
+    load_map_inner(
+        None,
+        map_hash,
+        activity_hash,
+        stringmap,
+        load_ambient_activity,
+    )
+    .await
+}
+
+async fn load_map_inner(
+    renderer: Option<&Renderer>,
+    map_hash: TagHash,
+    activity_hash: Option<TagHash>,
+    stringmap: StringContainerShared,
+    load_ambient_activity: bool,
+) -> anyhow::Result<Scene> {
+    let mut phase_start = Instant::now();
     let bubble_parent = package_manager()
         .read_tag_struct::<SBubbleParent>(map_hash)
         .context("Failed to read SBubbleParent")?;
 
     let mut scene = Scene::new_with_info(activity_hash, map_hash);
+    scene.insert_resource(MapLoadReport {
+        map_hash: map_hash.to_string(),
+        ..Default::default()
+    });
     let bubble_definition = if bubble_parent.child_map.is_some() {
         package_manager()
             .read_tag_struct::<SBubbleDefinition>(bubble_parent.child_map)
             .context("Failed to read bubble definition")?
     } else {
         warn!("Map {map_hash} is missing a bubble definition!");
+        MapLoadReport::record_failure(&mut scene, "Map is missing a bubble definition");
         return Ok(scene);
     };
     // let Ok(bubble_definition) = package_manager().read_tag::<SBubbleDefinition>(bubble_parent.child_map) else {
     //     warn!("Failed to load bubble definition for map {}", map_hash);
     // }
 
+    let strings = LabelStrings {
+        global: &stringmap,
+        destination: activity_hash
+            .map(load_destination_strings)
+            .unwrap_or_default(),
+    };
+
     let mut data_tables = FxHashMap::<TagHash, Entity>::default();
     for map_container in &bubble_definition.map_resources {
         let parent_entity =
@@ -94,23 +158,21 @@ pub async fn load_map(
         }
     }
 
-    for (table_hash, parent_entity) in data_tables {
-        let table_data = package_manager().read_tag(table_hash).unwrap();
-        let mut cur = Cursor::new(&table_data);
-        let table = TigerReadable::read_ds(&mut cur)?;
-
-        load_datatable_into_scene(
-            &table,
-            table_hash,
-            &mut cur,
-            &mut scene,
-            &renderer,
-            ResourceOrigin::Map,
-            Some(parent_entity),
-            &stringmap,
-        )
-        .context("Failed to load map datatable")?;
-    }
+    let data_tables = data_tables
+        .into_iter()
+        .map(|(table_hash, parent_entity)| (table_hash, Some(parent_entity)))
+        .collect_vec();
+    load_datatables(
+        &data_tables,
+        &mut scene,
+        renderer,
+        ResourceOrigin::Map,
+        &strings,
+    );
+    scene
+        .resource_mut::<MapLoadReport>()
+        .record_phase("Map data tables", phase_start);
+    phase_start = Instant::now();
 
     let mut activity_entrefs: Vec<(Tag<Unk80808e89>, ResourceHash, ResourceOrigin)> =
         Default::default();
@@ -176,6 +238,13 @@ pub async fn load_map(
                         "Failed to load ambient activity {}: {e}",
                         activity.ambient_activity
                     );
+                    MapLoadReport::record_failure(
+                        &mut scene,
+                        format!(
+                            "Failed to load ambient activity {}: {e}",
+                            activity.ambient_activity
+                        ),
+                    );
                 }
             }
         }
@@ -194,12 +263,12 @@ pub async fn load_map(
     let mut phase_entities = FxHashMap::<ResourceHash, Entity>::default();
     for (e, phase_name2, origin) in activity_entrefs {
         let parent_entity = *phase_entities.entry(phase_name2).or_insert_with(|| {
-            scene
-                .spawn((Label::from(format!(
-                    "Activity Phase 0x{:08X}",
-                    phase_name2.0
-                )),))
-                .id()
+            let raw_label = format!("Activity Phase 0x{:08X}", phase_name2.0);
+            let label = match strings.try_get(phase_name2) {
+                Some(name) => Label::from(format!("Activity Phase '{name}'")).with_raw(raw_label),
+                None => Label::from(raw_label),
+            };
+            scene.spawn((label,)).id()
         });
 
         for resource in &e.unk18.entity_resources {
@@ -274,50 +343,38 @@ pub async fn load_map(
                     );
                 }
 
-                for (table_hash, table_entity) in data_tables {
-                    let data = package_manager().read_tag(table_hash)?;
-                    let mut cur = Cursor::new(&data);
-                    let table: SMapDataTable =
-                        TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
-
-                    load_datatable_into_scene(
-                        &table,
-                        table_hash,
-                        &mut cur,
-                        &mut scene,
-                        &renderer,
-                        ResourceOrigin::Map,
-                        table_entity.or(Some(parent_entity)),
-                        &stringmap,
-                    )
-                    .context("Failed to load activity datatable")?;
-                }
-
-                for table_hash in data_tables2 {
-                    let data = package_manager().read_tag(table_hash)?;
-                    let mut cur = Cursor::new(&data);
-                    let table: SMapDataTable =
-                        TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
+                let data_tables = data_tables
+                    .into_iter()
+                    .map(|(table_hash, table_entity)| {
+                        (table_hash, table_entity.or(Some(parent_entity)))
+                    })
+                    .collect_vec();
+                load_datatables(
+                    &data_tables,
+                    &mut scene,
+                    renderer,
+                    ResourceOrigin::Map,
+                    &strings,
+                );
 
-                    load_datatable_into_scene(
-                        &table,
-                        table_hash,
-                        &mut cur,
-                        &mut scene,
-                        &renderer,
-                        // cohae: yes, this means bruteforced ambient data tables will always be
-                        // shown as ambient, but i don't think it matters once we fix the normal
-                        // bruteforced activity tables
-                        if origin == ResourceOrigin::Ambient {
-                            origin
-                        } else {
-                            ResourceOrigin::ActivityBruteforce
-                        },
-                        Some(parent_entity),
-                        &stringmap,
-                    )
-                    .context("Failed to load AB datatable")?;
-                }
+                let data_tables2 = data_tables2
+                    .into_iter()
+                    .map(|table_hash| (table_hash, Some(parent_entity)))
+                    .collect_vec();
+                load_datatables(
+                    &data_tables2,
+                    &mut scene,
+                    renderer,
+                    // cohae: yes, this means bruteforced ambient data tables will always be
+                    // shown as ambient, but i don't think it matters once we fix the normal
+                    // bruteforced activity tables
+                    if origin == ResourceOrigin::Ambient {
+                        origin
+                    } else {
+                        ResourceOrigin::ActivityBruteforce
+                    },
+                    &strings,
+                );
 
                 if origin != ResourceOrigin::Ambient {
                     for r in &res.resource_table2 {
@@ -335,7 +392,7 @@ pub async fn load_map(
                             load_entity_into_scene(
                                 r.unk0.hash32(),
                                 &mut scene,
-                                &renderer,
+                                renderer,
                                 origin,
                                 None,
                                 transform,
@@ -352,6 +409,21 @@ pub async fn load_map(
         }
     }
 
+    for (phase_name, phase_entity) in phase_entities {
+        let mut stack = vec![phase_entity];
+        while let Some(entity) = stack.pop() {
+            if let Some(children) = scene.get::<Children>(entity) {
+                stack.extend(children.iter().copied());
+            }
+
+            scene.entity_mut(entity).insert(ActivityGroup(phase_name.0));
+        }
+    }
+    scene
+        .resource_mut::<MapLoadReport>()
+        .record_phase("Activity entities", phase_start);
+    phase_start = Instant::now();
+
     // TODO(cohae): The persistent tag system is used exlusively for filtering, it's otherwise entirely redundant and should be replaced by components where possible
     let mut tags: Vec<(Entity, Vec<EntityTag>)> = vec![];
     for e in scene.iter_entities() {
@@ -390,7 +462,10 @@ pub async fn load_map(
     }
 
     for (entity, name) in new_entity_names {
-        scene.entity_mut(entity).insert_one(Label::from(name));
+        let raw_label = scene.get::<Label>(entity).map(|l| l.label.clone());
+        let mut label = Label::from(name);
+        label.raw = raw_label;
+        scene.entity_mut(entity).insert_one(label);
     }
 
     let mut entity_ogtransforms: Vec<(Entity, OriginalTransform)> = vec![];
@@ -410,7 +485,7 @@ pub async fn load_map(
         to_update.push(entity);
     }
     // Vertex AO: refresh terrain constants
-    if let Some(map_ao) = scene.get_resource::<MapStaticAO>() {
+    if let (Some(renderer), Some(map_ao)) = (renderer, scene.get_resource::<MapStaticAO>()) {
         for e in to_update {
             let patches = scene.entity(e);
             patches
@@ -420,20 +495,111 @@ pub async fn load_map(
         }
     }
 
+    if let Some(mut report) = scene.remove_resource::<MapLoadReport>() {
+        report.record_phase("Finalize", phase_start);
+        report.count_entities(&scene);
+        scene.insert_resource(report);
+    }
+
     Ok(scene)
 }
 
-#[allow(clippy::too_many_arguments)]
-fn load_datatable_into_scene<R: Read + Seek>(
-    table: &SMapDataTable,
-    table_hash: TagHash,
-    table_data: &mut R,
+/// A data table that has been read and parsed, ready to be loaded into a scene
+struct ParsedDataTable {
+    hash: TagHash,
+    data: Vec<u8>,
+    table: SMapDataTable,
+    parent_entity: Option<Entity>,
+    /// Static placements referenced by the table, these make up most of the parsing work on
+    /// larger maps
+    placements: FxHashMap<TagHash, SUnk80806ef4>,
+}
+
+/// Parses the given data tables and loads them into the scene. Tables that fail to parse or load
+/// are skipped and recorded in the scene's [`LoadWarnings`]
+fn load_datatables(
+    tables: &[(TagHash, Option<Entity>)],
     scene: &mut Scene,
-    renderer: &Renderer,
+    renderer: Option<&Renderer>,
     resource_origin: ResourceOrigin,
+    strings: &LabelStrings,
+) {
+    for (hash, table) in parse_datatables(tables) {
+        let result = table.and_then(|table| {
+            load_datatable_into_scene(table, scene, renderer, resource_origin, strings)
+        });
+        if let Err(e) = result {
+            LoadWarnings::skip(scene, SkippedResource::DataTable, hash, &e);
+        }
+    }
+}
+
+/// Reads and parses data tables on the rayon thread pool. Only parsing is done here, everything
+/// that touches the scene or the GPU happens in [`load_datatable_into_scene`], in the order the
+/// tables were given in
+fn parse_datatables(
+    tables: &[(TagHash, Option<Entity>)],
+) -> Vec<(TagHash, anyhow::Result<ParsedDataTable>)> {
+    tables
+        .par_iter()
+        .map(|&(hash, parent_entity)| (hash, parse_datatable(hash, parent_entity)))
+        .collect()
+}
+
+fn parse_datatable(
+    hash: TagHash,
     parent_entity: Option<Entity>,
-    stringmap: &StringContainer,
+) -> anyhow::Result<ParsedDataTable> {
+    let data = package_manager()
+        .read_tag(hash)
+        .with_context(|| format!("Failed to read datatable {hash}"))?;
+    let mut cur = Cursor::new(&data);
+    let table: SMapDataTable = TigerReadable::read_ds_endian(&mut cur, Endian::Little)
+        .with_context(|| format!("Failed to parse datatable {hash}"))?;
+
+    let mut placements = FxHashMap::default();
+    for entry in table
+        .data_entries
+        .iter()
+        .filter(|e| e.data_resource.resource_type == 0x80806cc9)
+    {
+        cur.seek(SeekFrom::Start(entry.data_resource.offset + 16))?;
+        let preheader_tag: TagHash = cur.read_le()?;
+        if placements.contains_key(&preheader_tag) {
+            continue;
+        }
+
+        // Failures are left for the loader to report
+        if let Ok(preheader) = package_manager().read_tag_struct(preheader_tag) {
+            placements.insert(preheader_tag, preheader);
+        }
+    }
+
+    Ok(ParsedDataTable {
+        hash,
+        data,
+        table,
+        parent_entity,
+        placements,
+    })
+}
+
+fn load_datatable_into_scene(
+    parsed: ParsedDataTable,
+    scene: &mut Scene,
+    renderer: Option<&Renderer>,
+    resource_origin: ResourceOrigin,
+    strings: &LabelStrings,
 ) -> anyhow::Result<()> {
+    let ParsedDataTable {
+        hash: table_hash,
+        data: table_bytes,
+        table,
+        parent_entity,
+        mut placements,
+    } = parsed;
+    let table_data = &mut Cursor::new(&table_bytes);
+
     for data in table.data_entries.iter() {
         let transform = Transform {
             translation: Vec3::new(data.translation.x, data.translation.y, data.translation.z),
@@ -443,7 +609,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
         };
 
         let metadata = NodeMetadata {
-            entity_tag: data.entity.hash32(),
+            entity_tag: data.entity_hash(),
             world_id: data.world_id,
             source_table: table_hash,
             source_table_resource_offset: data.data_resource.offset,
@@ -451,6 +617,22 @@ fn load_datatable_into_scene<R: Read + Seek>(
             name: None,
         };
 
+        let Some(renderer) = renderer else {
+            // Data-only loading, keep the placement and metadata without creating any GPU resources
+            spawn_data_entity(
+                scene,
+                (
+                    Icon::Unicode(ICON_CUBE_OUTLINE),
+                    Label::from(format!("Resource {:08X}", data.data_resource.resource_type)),
+                    transform,
+                    resource_origin,
+                    metadata,
+                ),
+                parent_entity,
+            );
+            continue;
+        };
+
         match data.data_resource.resource_type {
             // D2Class_C96C8080 (placement)
             0x80806cc9 => {
@@ -458,14 +640,53 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     .seek(SeekFrom::Start(data.data_resource.offset + 16))
                     .unwrap();
                 let preheader_tag: TagHash = table_data.read_le().unwrap();
-                let preheader: SUnk80806ef4 =
-                    package_manager().read_tag_struct(preheader_tag).unwrap();
+                let preheader: SUnk80806ef4 = match placements.remove(&preheader_tag) {
+                    Some(preheader) => preheader,
+                    None => match package_manager()
+                        .read_tag_struct(preheader_tag)
+                        .context("Failed to read placements")
+                    {
+                        Ok(preheader) => preheader,
+                        Err(e) => {
+                            LoadWarnings::skip(
+                                scene,
+                                SkippedResource::StaticMesh,
+                                preheader_tag,
+                                &e,
+                            );
+                            continue;
+                        }
+                    },
+                };
+
+                let groups: Arc<[(TagHash, usize)]> = preheader
+                    .instances
+                    .instance_groups
+                    .iter()
+                    .map(|s| {
+                        (
+                            preheader.instances.statics[s.static_index as usize],
+                            s.instance_count as usize,
+                        )
+                    })
+                    .collect();
 
-                for s in &preheader.instances.instance_groups {
+                for (group_index, s) in preheader.instances.instance_groups.iter().enumerate() {
                     let mesh_tag = preheader.instances.statics[s.static_index as usize];
-                    let model =
-                        StaticModel::load(&mut renderer.data.lock().asset_manager, mesh_tag)
-                            .context("Failed to load StaticModel")?;
+                    let group = StaticInstanceGroup {
+                        index: group_index,
+                        groups: groups.clone(),
+                    };
+                    let model = match StaticModel::load(
+                        &mut renderer.data.lock().asset_manager,
+                        mesh_tag,
+                    ) {
+                        Ok(model) => model,
+                        Err(e) => {
+                            LoadWarnings::skip(scene, SkippedResource::StaticMesh, mesh_tag, &e);
+                            continue;
+                        }
+                    };
 
                     let transforms = &preheader.instances.transforms
                         [s.instance_start as usize..(s.instance_start + s.instance_count) as usize];
@@ -494,6 +715,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             Label::from(format!("Static Model {mesh_tag}")),
                             transform,
                             StaticModelSingle::new(renderer.gpu.clone(), model)?,
+                            group,
                             TfxFeatureRenderer::StaticObjects,
                             resource_origin,
                             NodeFilter::Static,
@@ -538,6 +760,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             Label::from(format!("Static Instances {mesh_tag}")),
                             StaticInstances::new(renderer.gpu.clone(), model, instances.len())?,
                             Children::from_slice(&instances),
+                            group,
                             TfxFeatureRenderer::StaticObjects,
                             resource_origin,
                             NodeFilter::Static,
@@ -653,6 +876,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                         }
                         Err(e) => {
                             error!(error=?e, tag=%tag, "Failed to load ambient audio");
+                            MapLoadReport::record_failure(
+                                scene,
+                                format!("Failed to load ambient audio {tag}: {e:?}"),
+                            );
                         }
                     }
                 }
@@ -779,6 +1006,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                         Ok(static_ao) => static_ao,
                         Err(e) => {
                             error!(error=?e, tag=%tag, "Failed to load static AO");
+                            MapLoadReport::record_failure(
+                                scene,
+                                format!("Failed to load static AO {tag}: {e:?}"),
+                            );
                             continue;
                         }
                     };
@@ -787,6 +1018,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     Ok(o) => scene.insert_resource(o),
                     Err(e) => {
                         error!(error=?e, tag=%tag, "Failed to load static AO");
+                        MapLoadReport::record_failure(
+                            scene,
+                            format!("Failed to load static AO {tag}: {e:?}"),
+                        );
                     }
                 }
             }
@@ -799,14 +1034,40 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     continue;
                 }
 
-                let light_collection: SLightCollection =
-                    package_manager().read_tag_struct(tag).unwrap();
+                let light_collection = package_manager()
+                    .read_tag_struct::<SLightCollection>(tag)
+                    .context("Failed to read light collection")
+                    .and_then(|light_collection| {
+                        let lights = light_collection
+                            .unk30
+                            .iter()
+                            .enumerate()
+                            .map(|(i, light)| {
+                                LightRenderer::load(
+                                    renderer.gpu.clone(),
+                                    &mut renderer.data.lock().asset_manager,
+                                    light,
+                                    format!("light {tag}+{i}"),
+                                )
+                                .with_context(|| format!("Failed to load light {i}"))
+                            })
+                            .collect::<anyhow::Result<Vec<_>>>()?;
+                        Ok((light_collection, lights))
+                    });
+                let (light_collection, light_renderers) = match light_collection {
+                    Ok(l) => l,
+                    Err(e) => {
+                        LoadWarnings::skip(scene, SkippedResource::LightCollection, tag, &e);
+                        continue;
+                    }
+                };
 
                 let light_collection_entity =
                     spawn_data_entity(scene, (metadata.clone(),), parent_entity);
                 let mut children = vec![];
-                for (i, (light, transform, bounds)) in multizip((
+                for (i, (light, light_renderer, transform, bounds)) in multizip((
                     light_collection.unk30.clone(),
+                    light_renderers,
                     light_collection.unk40.clone(),
                     light_collection.occlusion_bounds.bounds.iter(),
                 ))
@@ -825,13 +1086,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                 Icon::Colored(shape.icon(), Color32::YELLOW),
                                 Label::from(format!("{} Light {tag}[{i}]", shape.name())),
                                 transform,
-                                LightRenderer::load(
-                                    renderer.gpu.clone(),
-                                    &mut renderer.data.lock().asset_manager,
-                                    &light,
-                                    format!("light {tag}+{i}"),
-                                )
-                                .context("Failed to load light")?,
+                                light_renderer,
                                 light,
                                 bounds.bb.untransform(transform.local_to_world()),
                                 TfxFeatureRenderer::DeferredLights,
@@ -865,7 +1120,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                         0.5,
                         light.far_plane,
                     ),
-                    renderer.settings.shadow_quality.resolution(),
+                    renderer.settings().shadow_quality.resolution(),
                 )?;
 
                 let bb = Aabb::from_projection_matrix(light.light_to_world);
@@ -923,8 +1178,12 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     .seek(SeekFrom::Start(data.data_resource.offset))
                     .unwrap();
 
-                match SCubemapVolume::read_ds(table_data) {
+                match CubemapVolumeInfo::read(table_data) {
                     Ok(cubemap_volume) => {
+                        let name = cubemap_volume
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| "<unknown>".to_string());
                         let voxel_diffuse = if cubemap_volume.voxel_ibl_texture.is_some() {
                             Some(
                                 renderer
@@ -944,10 +1203,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                 Icon::Unicode(ICON_SPHERE),
                                 Label::from(format!(
                                     "Cubemap Volume '{}'",
-                                    "<unknown>" // cubemap_volume
-                                                //     .cubemap_name
-                                                //     .to_string()
-                                                //     .truncate_ellipsis(48)
+                                    name.truncate_ellipsis(48)
                                 )),
                                 Transform {
                                     translation: data.translation.xyz(),
@@ -961,16 +1217,21 @@ fn load_datatable_into_scene<R: Read + Seek>(
                                         .asset_manager
                                         .get_or_load_texture(cubemap_volume.cubemap_texture),
                                     voxel_diffuse,
-                                    extents: cubemap_volume.cubemap_extents.truncate(),
-                                    // name: cubemap_volume.cubemap_name.to_string(),
-                                    name: "<unknown>".to_string(),
+                                    extents: cubemap_volume.extents.truncate(),
+                                    name,
                                 },
                                 metadata.clone(),
                             ),
                             parent_entity,
                         );
                     }
-                    Err(e) => error!("Failed to load cubemap volume: {e:?}"),
+                    Err(e) => {
+                        error!("Failed to load cubemap volume: {e:?}");
+                        MapLoadReport::record_failure(
+                            scene,
+                            format!("Failed to load cubemap volume (table {table_hash}): {e:?}"),
+                        );
+                    }
                 }
             }
             0x808067b5 => {
@@ -1037,10 +1298,18 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     .unwrap();
                 let header_tag: TagHash = table_data.read_le().unwrap();
                 let header: SDecorator = package_manager().read_tag_struct(header_tag)?;
+                let bounds = header.bounds;
+                // Decorators fade out based on the size of the models they scatter, not the size
+                // of the area they cover
+                let model_radius = header
+                    .unk8
+                    .iter()
+                    .map(|m| m.bb.radius())
+                    .fold(0.0, f32::max);
 
                 match DecoratorRenderer::load(renderer, header_tag, header) {
                     Ok(decorator_renderer) => {
-                        spawn_data_entity(
+                        let e = spawn_data_entity(
                             scene,
                             (
                                 NodeFilter::Decorator,
@@ -1051,9 +1320,19 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             ),
                             parent_entity,
                         );
+
+                        if model_radius.is_finite() && model_radius > 0.0 {
+                            scene
+                                .entity_mut(e)
+                                .insert((bounds, DetailFade::from_radius(model_radius)));
+                        }
                     }
                     Err(e) => {
                         error!("Failed to load decorator {header_tag}: {e}");
+                        MapLoadReport::record_failure(
+                            scene,
+                            format!("Failed to load decorator {header_tag}: {e}"),
+                        );
                     }
                 }
             }
@@ -1063,7 +1342,11 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     .unwrap();
 
                 let d: SUnk80809178 = TigerReadable::read_ds(table_data)?;
-                let name = stringmap.get(d.area_name);
+                let raw_label = format!("Named Area 0x{:08X}", d.area_name.0);
+                let label = match strings.try_get(d.area_name) {
+                    Some(name) => Label::from(format!("Named Area '{name}'")).with_raw(raw_label),
+                    None => Label::from(raw_label),
+                };
 
                 let (havok_debugshape, new_transform) =
                     if let Ok(havok_data) = package_manager().read_tag(d.unk0.havok_file) {
@@ -1090,6 +1373,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             }
                             Err(e) => {
                                 error!("Failed to read shapes: {e}");
+                                MapLoadReport::record_failure(
+                                    scene,
+                                    format!("Failed to read shapes (table {table_hash}): {e}"),
+                                );
                                 (None, None)
                             }
                         }
@@ -1104,7 +1391,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             new_transform.unwrap_or(transform),
                             NodeFilter::NamedArea,
                             Icon::Colored(ICON_LABEL, Color32::GREEN),
-                            Label::from(format!("Named Area '{name}'")),
+                            label,
                             havok_debugshape,
                             metadata.clone(),
                         ),
@@ -1204,6 +1491,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             }
                             Err(e) => {
                                 error!("Failed to read shapes: {e}");
+                                MapLoadReport::record_failure(
+                                    scene,
+                                    format!("Failed to read shapes (table {table_hash}): {e}"),
+                                );
                                 None
                             }
                         }
@@ -1284,6 +1575,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             }
                             Err(e) => {
                                 error!("Failed to read shapes: {e}");
+                                MapLoadReport::record_failure(
+                                    scene,
+                                    format!("Failed to read shapes (table {table_hash}): {e}"),
+                                );
                                 (None, None)
                             }
                         }
@@ -1307,145 +1602,80 @@ fn load_datatable_into_scene<R: Read + Seek>(
                     );
                 }
             }
-            // 0x80808246 => {
-            //     table_data
-            //         .seek(SeekFrom::Start(data.data_resource.offset))
-            //         .unwrap();
-            //
-            //     let d: SUnk80808246 = TigerReadable::read_ds(table_data)?;
-            //
-            //     match package_manager().read_tag(d.unk10.havok_file) {
-            //         Ok(havok_data) => {
-            //             let mut cur = Cursor::new(&havok_data);
-            //             match destiny_havok::shape_collection::read_shape_collection(&mut cur) {
-            //                 Ok(shapes) => {
-            //                     for t in &d.unk10.unk10 {
-            //                         if t.shape_index as usize >= shapes.len() {
-            //                             error!(
-            //                                 "Shape index out of bounds for Unk80808246 (table {}, \
-            //                                  {} shapes, index {})",
-            //                                 table_hash,
-            //                                 shapes.len(),
-            //                                 t.shape_index
-            //                             );
-            //                             continue;
-            //                         }
-            //
-            //                         let transform = Transform {
-            //                             translation: t.translation.truncate(),
-            //                             rotation: t.rotation,
-            //                             ..Default::default()
-            //                         };
-            //                         //
-            //                         // ents.push(
-            //                         //     scene.spawn((
-            //                         //         transform,
-            //                         //         ResourcePoint {
-            //                         //             resource: MapResource::Unk80808246(
-            //                         //                 d.unk10.havok_file,
-            //                         //                 t.shape_index,
-            //                         //                 CustomDebugShape::from_havok_shape(
-            //                         //                     &dcs,
-            //                         //                     &shapes[t.shape_index as usize],
-            //                         //                 )
-            //                         //                 .ok(),
-            //                         //             ),
-            //                         //             has_havok_data: true,
-            //                         //             entity_cbuffer: ConstantBufferCached::create_empty(
-            //                         //                 dcs.clone(),
-            //                         //             )?,
-            //                         //             ..base_rp
-            //                         //         },
-            //                         //         EntityWorldId(data.world_id),
-            //                         //     )),
-            //                         // );
-            //                     }
-            //
-            //                     // let new_transform = Transform {
-            //                     //     translation: center,
-            //                     //     ..Default::default()
-            //                     // };
-            //
-            //                     // (
-            //                     //     CustomDebugShape::from_havok_shape(&dcs, &final_shape).ok(),
-            //                     //     Some(new_transform),
-            //                     // )
-            //                 }
-            //                 Err(e) => {
-            //                     error!("Failed to read shapes: {e}");
-            //                 }
-            //             }
-            //         }
-            //         Err(e) => {
-            //             error!("Failed to read shapes: {e}");
-            //         }
-            //     };
-            // }
-            // 0x80806ac2 => {
-            //     table_data
-            //         .seek(SeekFrom::Start(data.data_resource.offset))
-            //         .unwrap();
-            //
-            //     let d: SUnk80806ac2 = TigerReadable::read_ds(table_data)?;
-            //
-            //     match package_manager().read_tag(d.unk10.havok_file) {
-            //         Ok(havok_data) => {
-            //             let mut cur = Cursor::new(&havok_data);
-            //             match destiny_havok::shape_collection::read_shape_collection(&mut cur) {
-            //                 Ok(shapes) => {
-            //                     if let Some(t) = d.unk10.unk10.get(d.array_index as usize) {
-            //                         if t.shape_index as usize >= shapes.len() {
-            //                             error!(
-            //                                 "Shape index out of bounds for Unk80808246 (table {}, \
-            //                                  {} shapes, index {})",
-            //                                 table_hash,
-            //                                 shapes.len(),
-            //                                 t.shape_index
-            //                             );
-            //
-            //                             continue;
-            //                         }
-            //
-            //                         let transform = Transform {
-            //                             translation: t.translation.truncate(),
-            //                             rotation: t.rotation,
-            //                             ..Default::default()
-            //                         };
-            //
-            //                         ents.push(
-            //                             scene.spawn((
-            //                                 transform,
-            //                                 ResourcePoint {
-            //                                     resource: MapResource::Unk80806ac2(
-            //                                         d.unk10.havok_file,
-            //                                         t.shape_index,
-            //                                         CustomDebugShape::from_havok_shape(
-            //                                             &dcs,
-            //                                             &shapes[t.shape_index as usize],
-            //                                         )
-            //                                         .ok(),
-            //                                     ),
-            //                                     has_havok_data: true,
-            //                                     entity_cbuffer: ConstantBufferCached::create_empty(
-            //                                         dcs.clone(),
-            //                                     )?,
-            //                                     ..base_rp
-            //                                 },
-            //                                 EntityWorldId(data.world_id),
-            //                             )),
-            //                         );
-            //                     }
-            //                 }
-            //                 Err(e) => {
-            //                     error!("Failed to read shapes: {e}");
-            //                 }
-            //             }
-            //         }
-            //         Err(e) => {
-            //             error!("Failed to read shapes: {e}");
-            //         }
-            //     };
-            // }
+            0x80808246 => {
+                table_data
+                    .seek(SeekFrom::Start(data.data_resource.offset))
+                    .unwrap();
+
+                let d: SUnk80808246 = TigerReadable::read_ds(table_data)?;
+                let placements = d
+                    .unk10
+                    .unk10
+                    .iter()
+                    .map(|t| (t.rotation, t.translation.truncate(), t.shape_index));
+
+                let filter = NodeFilter::Unknown80808246;
+                for (transform, havok_debugshape, shape_index) in
+                    load_volume_shapes(scene, renderer, table_hash, d.unk10.havok_file, placements)
+                {
+                    spawn_data_entity(
+                        scene,
+                        (
+                            transform,
+                            filter,
+                            Icon::Colored(filter.icon(), filter.color().into()),
+                            Label::from(format!(
+                                "Unknown80808246\n(havok={}:{})",
+                                d.unk10.havok_file, shape_index
+                            )),
+                            havok_debugshape,
+                            metadata.clone(),
+                        ),
+                        parent_entity,
+                    );
+                }
+            }
+            0x80806ac2 => {
+                table_data
+                    .seek(SeekFrom::Start(data.data_resource.offset))
+                    .unwrap();
+
+                let d: SSlipSurfaceVolume = TigerReadable::read_ds(table_data)?;
+                let Some(t) = d.unk10.unk10.get(d.array_index as usize) else {
+                    error!(
+                        "Slip surface index out of bounds (table {}, {} volumes, index {})",
+                        table_hash,
+                        d.unk10.unk10.len(),
+                        d.array_index
+                    );
+                    continue;
+                };
+
+                let filter = NodeFilter::SlipSurfaceVolume;
+                for (transform, havok_debugshape, shape_index) in load_volume_shapes(
+                    scene,
+                    renderer,
+                    table_hash,
+                    d.unk10.havok_file,
+                    [(t.rotation, t.translation.truncate(), t.shape_index)],
+                ) {
+                    spawn_data_entity(
+                        scene,
+                        (
+                            transform,
+                            filter,
+                            Icon::Colored(filter.icon(), filter.color().into()),
+                            Label::from(format!(
+                                "Slip Surface Volume\n(havok={}:{})",
+                                d.unk10.havok_file, shape_index
+                            )),
+                            havok_debugshape,
+                            metadata.clone(),
+                        ),
+                        parent_entity,
+                    );
+                }
+            }
             0x80809121 => {
                 table_data
                     .seek(SeekFrom::Start(data.data_resource.offset))
@@ -1478,6 +1708,10 @@ fn load_datatable_into_scene<R: Read + Seek>(
                             }
                             Err(e) => {
                                 error!("Failed to read shapes: {e}");
+                                MapLoadReport::record_failure(
+                                    scene,
+                                    format!("Failed to read shapes (table {table_hash}): {e}"),
+                                );
                                 (None, None)
                             }
                         }
@@ -1508,7 +1742,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                 if u != u32::MAX {
                     warn!("Unknown resource type {u:08X} in table {table_hash}");
                 }
-                let entity_hash = data.entity.hash32();
+                let entity_hash = data.entity_hash();
                 if entity_hash.is_none() {
                     continue;
                 }
@@ -1516,7 +1750,7 @@ fn load_datatable_into_scene<R: Read + Seek>(
                 load_entity_into_scene(
                     entity_hash,
                     scene,
-                    renderer,
+                    Some(renderer),
                     resource_origin,
                     parent_entity,
                     transform,
@@ -1532,6 +1766,66 @@ fn load_datatable_into_scene<R: Read + Seek>(
     Ok(())
 }
 
+/// Loads the shapes of `havok_file` for every `(rotation, translation, shape_index)` placement.
+/// Placements are in world space, the returned shapes are re-centered around their transform
+fn load_volume_shapes(
+    scene: &mut Scene,
+    renderer: &Renderer,
+    table_hash: TagHash,
+    havok_file: TagHash,
+    placements: impl IntoIterator<Item = (Quat, Vec3, u32)>,
+) -> Vec<(Transform, HavokShapeRenderer, u32)> {
+    let Ok(havok_data) = package_manager().read_tag(havok_file) else {
+        return vec![];
+    };
+
+    let mut cur = Cursor::new(&havok_data);
+    let shapes = match destiny_havok::shape_collection::read_shape_collection(&mut cur) {
+        Ok(shapes) => shapes,
+        Err(e) => {
+            error!("Failed to read shapes: {e}");
+            MapLoadReport::record_failure(
+                scene,
+                format!("Failed to read shapes (table {table_hash}): {e}"),
+            );
+            return vec![];
+        }
+    };
+
+    placements
+        .into_iter()
+        .filter_map(|(rotation, translation, shape_index)| {
+            let Some(shape) = shapes.get(shape_index as usize) else {
+                error!(
+                    "Shape index out of bounds for volume (table {}, {} shapes, index {})",
+                    table_hash,
+                    shapes.len(),
+                    shape_index
+                );
+                return None;
+            };
+
+            let mut shape = shape.clone();
+            shape.apply_transform(Mat4::from_rotation_translation(rotation, translation));
+
+            // Re-center the shape
+            let center = shape.center();
+            shape.apply_transform(Mat4::from_translation(-center));
+
+            let transform = Transform {
+                translation: center,
+                ..Default::default()
+            };
+
+            Some((
+                transform,
+                HavokShapeRenderer::new(renderer.gpu.clone(), &shape).ok()?,
+                shape_index,
+            ))
+        })
+        .collect()
+}
+
 fn spawn_data_entity(scene: &mut Scene, components: impl Bundle, parent: Option<Entity>) -> Entity {
     let mut child = scene.spawn(components);
     child.insert(VisibilityBundle::default());
@@ -1545,6 +1839,40 @@ fn spawn_data_entity(scene: &mut Scene, components: impl Bundle, parent: Option<
     child_id
 }
 
+/// Localized strings used to name entities. The strings of the activity's destination take
+/// priority over the global ones
+struct LabelStrings<'a> {
+    global: &'a StringContainer,
+    destination: StringContainer,
+}
+
+impl LabelStrings<'_> {
+    fn try_get(&self, hash: impl Into<u32>) -> Option<String> {
+        let hash = hash.into();
+        self.destination
+            .try_get(hash)
+            .or_else(|| self.global.try_get(hash))
+    }
+}
+
+fn load_destination_strings(activity_hash: TagHash) -> StringContainer {
+    let strings = package_manager()
+        .read_tag_struct::<SActivity>(activity_hash)
+        .and_then(|activity| {
+            package_manager().read_tag_struct::<SDestination>(activity.destination)
+        })
+        .map_err(anyhow::Error::from)
+        .and_then(|destination| StringContainer::load(destination.string_container));
+
+    match strings {
+        Ok(strings) => strings,
+        Err(e) => {
+            warn!("Failed to load destination strings for activity {activity_hash}: {e}");
+            StringContainer::default()
+        }
+    }
+}
+
 fn get_entity_labels(entity: TagHash) -> Option<FxHashMap<u64, String>> {
     let data: Vec<u8> = package_manager().read_tag(entity).ok()?;
     let mut cur = Cursor::new(&data);
@@ -1601,7 +1929,7 @@ fn get_entity_labels(entity: TagHash) -> Option<FxHashMap<u64, String>> {
 fn load_entity_into_scene(
     entity_hash: TagHash,
     scene: &mut Scene,
-    renderer: &Renderer,
+    renderer: Option<&Renderer>,
     resource_origin: ResourceOrigin,
     parent_entity: Option<Entity>,
     transform: Transform,
@@ -1653,31 +1981,35 @@ fn load_entity_into_scene(
 
         match entres.unk10.resource_type {
             0x80806d8a => {
-                let mut cur = Cursor::new(package_manager().read_tag(entres.taghash())?);
-                cur.seek(SeekFrom::Start(entres.unk18.offset + 0x224))?;
-                let model_hash: TagHash = TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
+                // Dynamic models need the GPU, data-only loads skip them
+                if let Some(renderer) = renderer {
+                    let mut cur = Cursor::new(package_manager().read_tag(entres.taghash())?);
+                    cur.seek(SeekFrom::Start(entres.unk18.offset + 0x224))?;
+                    let model_hash: TagHash =
+                        TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
 
-                cur.seek(SeekFrom::Start(entres.unk18.offset + 0x3c0))?;
-                let entity_material_map: Vec<Unk808072c5> =
-                    TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
+                    cur.seek(SeekFrom::Start(entres.unk18.offset + 0x3c0))?;
+                    let entity_material_map: Vec<Unk808072c5> =
+                        TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
 
-                cur.seek(SeekFrom::Start(entres.unk18.offset + 0x400))?;
-                let materials: Vec<TagHash> =
-                    TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
+                    cur.seek(SeekFrom::Start(entres.unk18.offset + 0x400))?;
+                    let materials: Vec<TagHash> =
+                        TigerReadable::read_ds_endian(&mut cur, Endian::Little)?;
 
-                let model = DynamicModelComponent::load(
-                    renderer,
-                    &transform,
-                    model_hash,
-                    entity_material_map,
-                    materials,
-                    TfxFeatureRenderer::DynamicObjects,
-                )?;
-                scene.entity_mut(scene_entity).insert((
-                    model.model.occlusion_bounds(),
-                    model,
-                    TfxFeatureRenderer::DynamicObjects,
-                ));
+                    let model = DynamicModelComponent::load(
+                        renderer,
+                        &transform,
+                        model_hash,
+                        entity_material_map,
+                        materials,
+                        TfxFeatureRenderer::DynamicObjects,
+                    )?;
+                    scene.entity_mut(scene_entity).insert((
+                        model.model.occlusion_bounds(),
+                        model,
+                        TfxFeatureRenderer::DynamicObjects,
+                    ));
+                }
             }
             u => {
                 debug!(
@@ -1689,6 +2021,21 @@ fn load_entity_into_scene(
             }
         }
 
+        if entres.unk18.resource_type == SSkeleton::RESOURCE_TYPE {
+            let mut cur = Cursor::new(package_manager().read_tag(entres.taghash())?);
+            cur.seek(SeekFrom::Start(entres.unk18.offset))?;
+            match SSkeleton::read_ds_endian(&mut cur, Endian::Little) {
+                Ok(skeleton) => {
+                    scene
+                        .entity_mut(scene_entity)
+                        .insert(Skeleton::from_data(&skeleton));
+                }
+                Err(e) => {
+                    warn!("Failed to read skeleton from {}: {e:?}", entres.taghash());
+                }
+            }
+        }
+
         let mut loaded = FxHashSet::<WideHash>::default();
         for r in &entres.resource_table2 {
             // if matches!(r.unk14, 0 | 0xFFFFFFFF) {
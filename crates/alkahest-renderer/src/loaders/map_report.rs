@@ -0,0 +1,112 @@
+//! Timings and statistics of map loads, for tracking down maps that load slowly or incompletely
+
+use std::{collections::BTreeMap, time::Instant};
+
+use bevy_ecs::system::Resource;
+use destiny_pkg::TagHash;
+use serde::Serialize;
+
+use crate::ecs::{tags::NodeFilter, Scene};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MapLoadPhase {
+    pub name: String,
+    pub seconds: f64,
+}
+
+/// Report of a single map load. The loader inserts it as a resource into the scene it creates,
+/// the application takes it out once the scene is ready and adds the asset streaming phase
+#[derive(Resource, Debug, Default, Clone, Serialize)]
+pub struct MapLoadReport {
+    pub map_hash: String,
+    pub map_name: String,
+    pub phases: Vec<MapLoadPhase>,
+    pub entity_count: usize,
+    /// Number of entities per node filter (statics, lights, decals, decorators, ...)
+    pub counts: BTreeMap<String, usize>,
+    /// Texture data uploaded while the map was loading and streaming in its assets. Includes
+    /// uploads for anything else that was loading at the same time
+    pub texture_bytes_uploaded: u64,
+    /// Number of textures, techniques and buffers that failed to load
+    pub asset_failures: usize,
+    /// Errors encountered while loading, the map is still shown without the affected resources
+    pub failures: Vec<String>,
+    /// Set while assets are still streaming in, the totals aren't final until then
+    pub streaming: bool,
+}
+
+impl MapLoadReport {
+    /// Records a phase that started at `started` and ends now
+    pub fn record_phase(&mut self, name: &str, started: Instant) {
+        self.phases.push(MapLoadPhase {
+            name: name.to_string(),
+            seconds: started.elapsed().as_secs_f64(),
+        });
+    }
+
+    pub fn total_seconds(&self) -> f64 {
+        self.phases.iter().map(|p| p.seconds).sum()
+    }
+
+    /// Counts the entities of the scene, grouped by their node filter
+    pub fn count_entities(&mut self, scene: &Scene) {
+        self.entity_count = scene.entities().len() as usize;
+        self.counts.clear();
+        for e in scene.iter_entities() {
+            if let Some(filter) = e.get::<NodeFilter>() {
+                *self.counts.entry(filter.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Adds a failure to the load report of `scene`, if it has one
+    pub fn record_failure(scene: &mut Scene, message: impl Into<String>) {
+        if let Some(mut report) = scene.get_resource_mut::<MapLoadReport>() {
+            report.failures.push(message.into());
+        }
+    }
+}
+
+/// Kind of resource that was skipped because it failed to load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, strum::Display, strum::EnumIter)]
+pub enum SkippedResource {
+    #[strum(to_string = "Data table")]
+    DataTable,
+    #[strum(to_string = "Static mesh")]
+    StaticMesh,
+    #[strum(to_string = "Light collection")]
+    LightCollection,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadWarning {
+    pub kind: SkippedResource,
+    pub tag: TagHash,
+    pub error: String,
+}
+
+/// Resources that failed to load and were skipped. Only present on scenes that were loaded
+/// partially, the rest of the map is loaded as usual
+#[derive(Resource, Debug, Default, Clone)]
+pub struct LoadWarnings(pub Vec<LoadWarning>);
+
+impl LoadWarnings {
+    /// Records a skipped resource in the warnings and the load report of `scene`
+    pub fn skip(scene: &mut Scene, kind: SkippedResource, tag: TagHash, error: &anyhow::Error) {
+        warn!("Skipping {kind} {tag}: {error:?}");
+        MapLoadReport::record_failure(scene, format!("Skipped {kind} {tag}: {error:?}"));
+        scene
+            .get_resource_or_insert_with(LoadWarnings::default)
+            .0
+            .push(LoadWarning {
+                kind,
+                tag,
+                error: format!("{error:#}"),
+            });
+    }
+
+    /// Number of skipped resources of the given kind
+    pub fn count(&self, kind: SkippedResource) -> usize {
+        self.0.iter().filter(|w| w.kind == kind).count()
+    }
+}
@@ -1,20 +1,29 @@
+use std::{path::PathBuf, sync::Arc};
+
+use alkahest_data::tfx::TfxShaderStage;
 use crossbeam::channel::{Receiver, Sender};
 use destiny_pkg::TagHash;
-use rustc_hash::FxHashSet;
-use strum::AsRefStr;
+use itertools::Itertools;
+use parking_lot::RwLock;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
 
 use crate::{
+    ecs::render::lod::mip_skip_for_coverage,
     gpu::{texture::Texture, SharedGpuContext},
     handle::{AssetId, AssetIdValue, AssetRegistry, Handle, RawHandle},
     loaders::{index_buffer::IndexBuffer, vertex_buffer::VertexBuffer},
     tfx::technique::Technique,
-    util::{d3d::ErrorExt, packages::TagHashExt},
+    util::{background_errors::report_background_error, d3d::ErrorExt, packages::TagHashExt},
 };
 
 pub mod index_buffer;
 pub mod map;
+pub mod map_report;
+pub mod snapshot;
 pub mod technique;
 pub mod texture;
+pub mod texture_usage;
 pub mod vertex_buffer;
 
 pub struct AssetManager {
@@ -32,14 +41,39 @@ pub struct AssetManager {
     _workers: Vec<std::thread::JoinHandle<()>>,
 
     pending_requests: FxHashSet<AssetId>,
+
+    /// Maximum amount of texture memory (in bytes) before the least recently used textures are evicted, 0 for no limit
+    texture_budget: usize,
+    /// Memory usage of every loaded texture
+    texture_sizes: FxHashMap<AssetId, usize>,
+    /// Textures that were streamed in at a reduced quality, along with the number of skipped mips
+    reduced_textures: FxHashMap<AssetId, usize>,
+    /// Total amount of texture data uploaded since startup, including textures that were evicted
+    texture_bytes_uploaded: u64,
+    /// Assets that failed to load, can be requested again with [`Self::retry_failed`]
+    failed_assets: FxHashMap<AssetId, AssetKind>,
+    /// Source files of textures loaded from disk, and whether they should be loaded as sRGB
+    texture_files: TextureFiles,
+    /// Textures bound instead of the original texture of a technique
+    texture_overrides: FxHashMap<TextureSlot, Handle<Texture>>,
 }
 
 impl AssetManager {
+    /// Number of frames a texture has to be unused for before it can be evicted
+    const EVICTION_MIN_AGE: u64 = 60;
+    /// Minimum number of mips skipped when reloading evicted textures while texture memory is close
+    /// to the budget
+    const REDUCED_QUALITY_MIP_SKIP: usize = 2;
+    /// Maximum number of reduced quality textures that are reloaded at a higher quality per frame
+    const MAX_UPGRADES_PER_FRAME: usize = 8;
+
     pub fn new(gctx: SharedGpuContext) -> Self {
         let (request_tx, request_rx) = crossbeam::channel::unbounded();
         let (asset_tx, asset_rx) = crossbeam::channel::unbounded();
 
-        let workers = spawn_load_workers(gctx.clone(), 4, request_rx, asset_tx);
+        let texture_files = TextureFiles::default();
+        let workers =
+            spawn_load_workers(gctx.clone(), 4, request_rx, asset_tx, texture_files.clone());
 
         Self {
             gctx,
@@ -52,6 +86,13 @@ impl AssetManager {
             asset_rx,
             _workers: workers,
             pending_requests: FxHashSet::default(),
+            texture_budget: 0,
+            texture_sizes: FxHashMap::default(),
+            reduced_textures: FxHashMap::default(),
+            texture_bytes_uploaded: 0,
+            failed_assets: FxHashMap::default(),
+            texture_files,
+            texture_overrides: FxHashMap::default(),
         }
     }
 
@@ -70,6 +111,13 @@ impl AssetManager {
             asset_rx,
             _workers: vec![],
             pending_requests: FxHashSet::default(),
+            texture_budget: 0,
+            texture_sizes: FxHashMap::default(),
+            reduced_textures: FxHashMap::default(),
+            texture_bytes_uploaded: 0,
+            failed_assets: FxHashMap::default(),
+            texture_files: TextureFiles::default(),
+            texture_overrides: FxHashMap::default(),
         }
     }
 
@@ -81,16 +129,67 @@ impl AssetManager {
 
         if !self.textures.exists(AssetId::new_tiger(hash)) {
             let h = self.textures.get_handle_tiger(hash);
-            self.pending_requests.insert(h.id());
-            self.request_tx
-                .send(LoadRequest::Texture(h.clone().to_raw()))
-                .unwrap();
+            self.request_texture(&h, 0);
             h
         } else {
             self.textures.get_handle_tiger(hash)
         }
     }
 
+    fn request_texture(&mut self, handle: &Handle<Texture>, skip_mips: usize) {
+        self.pending_requests.insert(handle.id());
+        if skip_mips > 0 {
+            self.reduced_textures.insert(handle.id(), skip_mips);
+        } else {
+            self.reduced_textures.remove(&handle.id());
+        }
+
+        self.request_tx
+            .send(LoadRequest::Texture(handle.clone().to_raw(), skip_mips))
+            .unwrap();
+    }
+
+    /// Loads a PNG or DDS file from disk as a texture
+    pub fn load_texture_file(&mut self, path: PathBuf, srgb: bool) -> Handle<Texture> {
+        if self.disabled {
+            return Handle::none();
+        }
+
+        let h = self.textures.reserve_handle();
+        self.texture_files.write().insert(h.id(), (path, srgb));
+        self.request_texture(&h, 0);
+        h
+    }
+
+    /// Source file of a texture loaded with [`Self::load_texture_file`]
+    pub fn texture_file(&self, id: AssetId) -> Option<PathBuf> {
+        self.texture_files
+            .read()
+            .get(&id)
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Replaces the texture bound to a slot of a technique, or restores the original texture
+    /// when `texture` is `None`
+    pub fn set_texture_override(&mut self, slot: TextureSlot, texture: Option<Handle<Texture>>) {
+        match texture {
+            Some(texture) => self.texture_overrides.insert(slot, texture),
+            None => self.texture_overrides.remove(&slot),
+        };
+    }
+
+    pub fn texture_override(&self, slot: TextureSlot) -> Option<&Handle<Texture>> {
+        self.texture_overrides.get(&slot)
+    }
+
+    pub fn clear_texture_overrides(&mut self) {
+        self.texture_overrides.clear();
+    }
+
+    pub fn texture_override_count(&self) -> usize {
+        self.texture_overrides.len()
+    }
+
     #[track_caller]
     pub fn get_or_load_technique(&mut self, hash: TagHash) -> Handle<Technique> {
         if hash.is_none() || self.disabled {
@@ -164,12 +263,7 @@ impl AssetManager {
             debug!("Polling asset manager ({} assets to process)", budget);
         }
 
-        let mut total_removed = 0;
-        total_removed += self.textures.remove_all_dead();
-        total_removed += self.techniques.remove_all_dead();
-        total_removed += self.vertex_buffers.remove_all_dead();
-        total_removed += self.index_buffers.remove_all_dead();
-
+        let total_removed = self.remove_all_dead();
         if total_removed > 0 {
             trace!("Removed {total_removed} dead assets");
         }
@@ -188,14 +282,17 @@ impl AssetManager {
                     match asset {
                         LoadedAsset::Texture(h, t) => match t {
                             Ok(t) => {
+                                self.texture_bytes_uploaded += t.memory_size() as u64;
+                                self.texture_sizes.insert(h.id(), t.memory_size());
                                 self.textures.overwrite(h, t);
                             }
                             Err(e) => {
-                                error!(
+                                let message = format!(
                                     "Failed to load texture {:?}: {:?}",
                                     h.id(),
                                     e.with_d3d_error(&self.gctx)
                                 );
+                                self.report_failed(h.id(), AssetKind::Texture, message);
                             }
                         },
                         LoadedAsset::Technique(h, t) => match t {
@@ -213,11 +310,12 @@ impl AssetManager {
                                 self.techniques.overwrite(h, t);
                             }
                             Err(e) => {
-                                error!(
+                                let message = format!(
                                     "Failed to load technique {:?}: {:?}",
                                     h.id(),
                                     e.with_d3d_error(&self.gctx)
                                 );
+                                self.report_failed(h.id(), AssetKind::Technique, message);
                             }
                         },
                         LoadedAsset::VertexBuffer(h, vb) => match vb {
@@ -225,11 +323,12 @@ impl AssetManager {
                                 self.vertex_buffers.overwrite(h, vb);
                             }
                             Err(e) => {
-                                error!(
+                                let message = format!(
                                     "Failed to load vertex buffer {:?}: {:?}",
                                     h.id(),
                                     e.with_d3d_error(&self.gctx)
                                 );
+                                self.report_failed(h.id(), AssetKind::VertexBuffer, message);
                             }
                         },
                         LoadedAsset::IndexBuffer(h, ib) => match ib {
@@ -237,11 +336,12 @@ impl AssetManager {
                                 self.index_buffers.overwrite(h, ib);
                             }
                             Err(e) => {
-                                error!(
+                                let message = format!(
                                     "Failed to load index buffer {:?}: {:?}",
                                     h.id(),
                                     e.with_d3d_error(&self.gctx)
                                 );
+                                self.report_failed(h.id(), AssetKind::IndexBuffer, message);
                             }
                         },
                     }
@@ -253,6 +353,120 @@ impl AssetManager {
         }
     }
 
+    /// Removes all assets that are no longer referenced, returns the number of removed assets
+    pub fn remove_all_dead(&mut self) -> usize {
+        let mut total_removed = 0;
+        total_removed += self.textures.remove_all_dead();
+        total_removed += self.techniques.remove_all_dead();
+        total_removed += self.vertex_buffers.remove_all_dead();
+        total_removed += self.index_buffers.remove_all_dead();
+
+        if total_removed > 0 {
+            self.texture_sizes.retain(|id, _| self.textures.exists(*id));
+            self.reduced_textures
+                .retain(|id, _| self.textures.exists(*id));
+            self.texture_files
+                .write()
+                .retain(|id, _| self.textures.exists(*id));
+            self.failed_assets.retain(|id, kind| match kind {
+                AssetKind::Texture => self.textures.exists(*id),
+                AssetKind::Technique => self.techniques.exists(*id),
+                AssetKind::VertexBuffer => self.vertex_buffers.exists(*id),
+                AssetKind::IndexBuffer => self.index_buffers.exists(*id),
+            });
+        }
+
+        total_removed
+    }
+
+    /// Handle, loaded and dead asset counts and memory usage for every asset registry
+    pub fn registry_stats(&self) -> Vec<(AssetKind, AssetRegistryStats)> {
+        let snapshot = self.snapshot("");
+        AssetKind::iter()
+            .map(|kind| {
+                let (handles, dead) = match kind {
+                    AssetKind::Texture => (self.textures.len(), self.textures.dead_count()),
+                    AssetKind::Technique => (self.techniques.len(), self.techniques.dead_count()),
+                    AssetKind::VertexBuffer => {
+                        (self.vertex_buffers.len(), self.vertex_buffers.dead_count())
+                    }
+                    AssetKind::IndexBuffer => {
+                        (self.index_buffers.len(), self.index_buffers.dead_count())
+                    }
+                };
+                let loaded = snapshot.assets.values().filter(|a| a.kind == kind);
+
+                (
+                    kind,
+                    AssetRegistryStats {
+                        handles,
+                        loaded: loaded.clone().count(),
+                        dead,
+                        size: loaded.map(|a| a.size).sum(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn report_failed(&mut self, id: AssetId, kind: AssetKind, message: String) {
+        self.failed_assets.insert(id, kind);
+        report_background_error("Asset loader", message, Some("asset.retry".to_string()));
+    }
+
+    /// Number of assets that failed to load and haven't been retried yet
+    pub fn failed_count(&self) -> usize {
+        self.failed_assets.len()
+    }
+
+    /// Requests assets that failed to load again, either a single asset or all of them. Returns
+    /// the number of assets that were requested.
+    pub fn retry_failed(&mut self, hash: Option<TagHash>) -> usize {
+        let failed = self
+            .failed_assets
+            .iter()
+            .filter(|(id, _)| hash.map_or(true, |hash| id.tiger_taghash() == Some(hash)))
+            .map(|(id, kind)| (*id, *kind))
+            .collect_vec();
+
+        let mut retried = 0;
+        for (id, kind) in failed {
+            self.failed_assets.remove(&id);
+            let Some(hash) = id.tiger_taghash() else {
+                continue;
+            };
+
+            let request = match kind {
+                AssetKind::Texture => self.textures.get_existing_handle_tiger(hash).map(|h| {
+                    LoadRequest::Texture(
+                        h.to_raw(),
+                        self.reduced_textures.get(&id).copied().unwrap_or(0),
+                    )
+                }),
+                AssetKind::Technique => self
+                    .techniques
+                    .get_existing_handle_tiger(hash)
+                    .map(|h| LoadRequest::Technique(h.to_raw())),
+                AssetKind::VertexBuffer => self
+                    .vertex_buffers
+                    .get_existing_handle_tiger(hash)
+                    .map(|h| LoadRequest::VertexBuffer(h.to_raw())),
+                AssetKind::IndexBuffer => self
+                    .index_buffers
+                    .get_existing_handle_tiger(hash)
+                    .map(|h| LoadRequest::IndexBuffer(h.to_raw())),
+            };
+
+            if let Some(request) = request {
+                self.pending_requests.insert(id);
+                self.request_tx.send(request).unwrap();
+                retried += 1;
+            }
+        }
+
+        retried
+    }
+
     /// Blocks until all pending requests have been processed.
     pub fn block_until_idle(&mut self) {
         if self.disabled {
@@ -272,6 +486,167 @@ impl AssetManager {
     pub fn remaining_requests(&self) -> usize {
         self.pending_requests.len()
     }
+
+    /// Sets the texture memory budget in bytes, 0 disables the budget
+    pub fn set_texture_budget(&mut self, budget: usize) {
+        self.texture_budget = budget;
+    }
+
+    pub fn texture_budget(&self) -> usize {
+        self.texture_budget
+    }
+
+    /// Approximate amount of GPU memory used by all loaded textures, in bytes
+    pub fn texture_memory(&self) -> usize {
+        self.texture_sizes.values().sum()
+    }
+
+    /// Total amount of texture data uploaded since startup, in bytes
+    pub fn texture_bytes_uploaded(&self) -> u64 {
+        self.texture_bytes_uploaded
+    }
+
+    /// Approximate amount of GPU memory used by all loaded textures and geometry buffers, in bytes
+    pub fn loaded_memory(&self) -> usize {
+        let vertex_buffers: usize = self
+            .vertex_buffers
+            .iter_loaded()
+            .map(|(_, vb)| vb.size as usize)
+            .sum();
+        let index_buffers: usize = self
+            .index_buffers
+            .iter_loaded()
+            .map(|(_, ib)| ib.size())
+            .sum();
+
+        self.texture_memory() + vertex_buffers + index_buffers
+    }
+
+    /// Number of textures that are currently loaded at a reduced quality
+    pub fn reduced_texture_count(&self) -> usize {
+        self.reduced_textures.len()
+    }
+
+    /// Mips a texture can do without, based on the screen coverage of the models that used it last
+    /// frame. Textures with an unknown coverage keep every mip
+    fn coverage_mip_skip(&self, id: AssetId) -> usize {
+        self.textures
+            .last_coverage(id)
+            .map_or(0, mip_skip_for_coverage)
+    }
+
+    /// Advances asset access tracking by one frame and enforces the texture memory budget.
+    ///
+    /// Textures that haven't been used recently are evicted (least recently used first) until memory usage is back under
+    /// the budget. Evicted textures are loaded again once something tries to use them, skipping the mips that the
+    /// screen coverage of the models using them doesn't need (see [`mip_skip_for_coverage`]), and at least
+    /// [`Self::REDUCED_QUALITY_MIP_SKIP`] if memory is still tight. Reduced textures get their mips streamed back in
+    /// once there's enough room and the models using them come closer.
+    pub fn next_frame(&mut self) {
+        if self.disabled {
+            return;
+        }
+
+        profiling::scope!("AssetManager::next_frame");
+        self.textures.next_frame();
+        self.techniques.next_frame();
+        self.vertex_buffers.next_frame();
+        self.index_buffers.next_frame();
+
+        let budget = self.texture_budget;
+        let mut usage = self.texture_memory();
+
+        let memory_tight = budget != 0 && usage > budget / 4 * 3;
+        for handle in self.textures.take_evicted_in_use() {
+            let mut skip_mips = self.coverage_mip_skip(handle.id());
+            if memory_tight {
+                skip_mips = skip_mips.max(Self::REDUCED_QUALITY_MIP_SKIP);
+            }
+
+            self.request_texture(&handle, skip_mips);
+        }
+
+        if budget != 0 && usage > budget {
+            let mut evicted = 0;
+            for id in self.textures.least_recently_used(Self::EVICTION_MIN_AGE) {
+                if usage <= budget {
+                    break;
+                }
+
+                // Only textures from packages can be loaded again
+                if id.tiger_taghash().is_none() || self.pending_requests.contains(&id) {
+                    continue;
+                }
+
+                if self.textures.evict(id) {
+                    usage =
+                        usage.saturating_sub(self.texture_sizes.remove(&id).unwrap_or_default());
+                    self.reduced_textures.remove(&id);
+                    evicted += 1;
+                }
+            }
+
+            if evicted > 0 {
+                debug!(
+                    "Evicted {evicted} textures, texture memory is now {:.1}MiB",
+                    usage as f64 / (1024.0 * 1024.0)
+                );
+            }
+        }
+
+        if budget == 0 || usage < budget / 2 {
+            let upgrades = self
+                .reduced_textures
+                .iter()
+                .filter(|(id, _)| {
+                    self.textures.used_within(**id, 1) && !self.pending_requests.contains(id)
+                })
+                .filter_map(|(&id, &skipped)| {
+                    let skip_mips = self.coverage_mip_skip(id);
+                    (skip_mips < skipped).then_some((id, skip_mips))
+                })
+                .take(Self::MAX_UPGRADES_PER_FRAME)
+                .collect_vec();
+
+            for (id, skip_mips) in upgrades {
+                if let Some(handle) = id
+                    .tiger_taghash()
+                    .and_then(|hash| self.textures.get_existing_handle_tiger(hash))
+                {
+                    self.request_texture(&handle, skip_mips);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct AssetRegistryStats {
+    /// Every handle in the registry, including assets that are still loading
+    pub handles: usize,
+    pub loaded: usize,
+    /// Assets that are no longer referenced, these are removed on the next poll
+    pub dead: usize,
+    /// Approximate GPU memory used by the loaded assets, in bytes. Always 0 for techniques
+    pub size: usize,
+}
+
+/// Texture slot of a single technique stage
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TextureSlot {
+    pub technique: TagHash,
+    pub stage: TfxShaderStage,
+    pub slot: u32,
+}
+
+type TextureFiles = Arc<RwLock<FxHashMap<AssetId, (PathBuf, bool)>>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, strum::Display, EnumIter)]
+pub enum AssetKind {
+    Texture,
+    Technique,
+    VertexBuffer,
+    IndexBuffer,
 }
 
 #[derive(AsRefStr)]
@@ -295,7 +670,8 @@ impl LoadedAsset {
 
 #[derive(AsRefStr)]
 pub enum LoadRequest {
-    Texture(RawHandle),
+    /// Texture handle, along with the number of mips to skip
+    Texture(RawHandle, usize),
     Technique(RawHandle),
     VertexBuffer(RawHandle),
     IndexBuffer(RawHandle),
@@ -304,7 +680,7 @@ pub enum LoadRequest {
 impl LoadRequest {
     pub fn handle(&self) -> &RawHandle {
         match self {
-            Self::Texture(h) => h,
+            Self::Texture(h, _) => h,
             Self::Technique(h) => h,
             Self::VertexBuffer(h) => h,
             Self::IndexBuffer(h) => h,
@@ -316,6 +692,7 @@ fn load_worker_thread(
     gctx: SharedGpuContext,
     rx_request: Receiver<LoadRequest>,
     tx: Sender<LoadedAsset>,
+    texture_files: TextureFiles,
 ) -> anyhow::Result<()> {
     profiling::register_thread!();
     loop {
@@ -326,16 +703,19 @@ fn load_worker_thread(
                     &format!("{} {:?}", request.as_ref(), request.handle().id())
                 );
                 match request {
-                    LoadRequest::Texture(h) => match h.id().value() {
-                        AssetIdValue::Alkahest(_e) => {
-                            todo!(
-                                "Alkahest custom texture loading unimplemented (texture handle \
-                                 {:?})",
-                                h.id()
-                            );
+                    LoadRequest::Texture(h, skip_mips) => match h.id().value() {
+                        AssetIdValue::Alkahest(_) => {
+                            let file = texture_files.read().get(&h.id()).cloned();
+                            let t = match file {
+                                Some((path, srgb)) => {
+                                    texture::load_texture_file(&gctx, &path, srgb)
+                                }
+                                None => Err(anyhow::anyhow!("Texture has no source file")),
+                            };
+                            tx.send(LoadedAsset::Texture(h, t))?;
                         }
                         AssetIdValue::Tiger(hash) => {
-                            let t = texture::load_texture(&gctx, hash);
+                            let t = texture::load_texture_with_mip_skip(&gctx, hash, skip_mips);
                             tx.send(LoadedAsset::Texture(h, t))?;
                         }
                     },
@@ -392,21 +772,25 @@ pub fn spawn_load_workers(
     num_workers: usize,
     rx_request: Receiver<LoadRequest>,
     tx: Sender<LoadedAsset>,
+    texture_files: TextureFiles,
 ) -> Vec<std::thread::JoinHandle<()>> {
     (0..num_workers)
         .map(|i| {
             let gctx = gctx.clone();
             let rx_request = rx_request.clone();
             let tx = tx.clone();
+            let texture_files = texture_files.clone();
 
             std::thread::Builder::new()
                 .name(format!("alkahest-loader-{i}"))
-                .spawn(move || match load_worker_thread(gctx, rx_request, tx) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        debug!("Loader thread exited: {:?}", e);
-                    }
-                })
+                .spawn(
+                    move || match load_worker_thread(gctx, rx_request, tx, texture_files) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            debug!("Loader thread exited: {:?}", e);
+                        }
+                    },
+                )
                 .unwrap()
         })
         .collect()
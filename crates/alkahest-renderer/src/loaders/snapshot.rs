@@ -0,0 +1,145 @@
+//! Snapshots of the loaded assets, used to compare what stays in memory between two points in
+//! time (eg. before and after switching maps)
+
+use std::time::Instant;
+
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use strum::IntoEnumIterator;
+
+use crate::{
+    handle::AssetId,
+    loaders::{AssetKind, AssetManager},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct AssetSnapshotEntry {
+    pub kind: AssetKind,
+    /// Approximate GPU memory used by the asset, in bytes. Always 0 for techniques
+    pub size: usize,
+}
+
+pub struct AssetSnapshot {
+    pub label: String,
+    pub time: Instant,
+    pub assets: FxHashMap<AssetId, AssetSnapshotEntry>,
+}
+
+impl AssetSnapshot {
+    pub fn total_size(&self) -> usize {
+        self.assets.values().map(|a| a.size).sum()
+    }
+
+    /// Compares this snapshot to a later one
+    pub fn diff(&self, after: &AssetSnapshot) -> AssetSnapshotDiff {
+        let mut diff = AssetSnapshotDiff::default();
+        for (id, entry) in &self.assets {
+            if after.assets.contains_key(id) {
+                diff.persisted.push((*id, *entry));
+            } else {
+                diff.freed.push((*id, *entry));
+            }
+        }
+
+        diff.new = after
+            .assets
+            .iter()
+            .filter(|(id, _)| !self.assets.contains_key(id))
+            .map(|(id, entry)| (*id, *entry))
+            .collect();
+
+        for list in [&mut diff.freed, &mut diff.persisted, &mut diff.new] {
+            list.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.size));
+        }
+
+        diff
+    }
+}
+
+#[derive(Default)]
+pub struct AssetSnapshotDiff {
+    /// Assets that were loaded in the first snapshot, but not in the second
+    pub freed: Vec<(AssetId, AssetSnapshotEntry)>,
+    /// Assets that are loaded in both snapshots
+    pub persisted: Vec<(AssetId, AssetSnapshotEntry)>,
+    /// Assets that were only loaded in the second snapshot
+    pub new: Vec<(AssetId, AssetSnapshotEntry)>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct AssetDiffTotals {
+    pub count: usize,
+    pub size: usize,
+}
+
+impl AssetSnapshotDiff {
+    /// Count and size of the given assets, per asset kind
+    pub fn totals(assets: &[(AssetId, AssetSnapshotEntry)]) -> Vec<(AssetKind, AssetDiffTotals)> {
+        let grouped = assets.iter().into_group_map_by(|(_, entry)| entry.kind);
+        AssetKind::iter()
+            .map(|kind| {
+                let entries = grouped.get(&kind).map(Vec::as_slice).unwrap_or_default();
+                (
+                    kind,
+                    AssetDiffTotals {
+                        count: entries.len(),
+                        size: entries.iter().map(|(_, entry)| entry.size).sum(),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl AssetManager {
+    /// Records all assets that are currently loaded
+    pub fn snapshot(&self, label: impl Into<String>) -> AssetSnapshot {
+        let mut assets = FxHashMap::default();
+        for (id, _) in self.textures.iter_loaded() {
+            let size = self.texture_sizes.get(&id).copied().unwrap_or_default();
+            assets.insert(
+                id,
+                AssetSnapshotEntry {
+                    kind: AssetKind::Texture,
+                    size,
+                },
+            );
+        }
+
+        for (id, _) in self.techniques.iter_loaded() {
+            assets.insert(
+                id,
+                AssetSnapshotEntry {
+                    kind: AssetKind::Technique,
+                    size: 0,
+                },
+            );
+        }
+
+        for (id, vb) in self.vertex_buffers.iter_loaded() {
+            assets.insert(
+                id,
+                AssetSnapshotEntry {
+                    kind: AssetKind::VertexBuffer,
+                    size: vb.size as usize,
+                },
+            );
+        }
+
+        for (id, ib) in self.index_buffers.iter_loaded() {
+            assets.insert(
+                id,
+                AssetSnapshotEntry {
+                    kind: AssetKind::IndexBuffer,
+                    size: ib.size(),
+                },
+            );
+        }
+
+        AssetSnapshot {
+            label: label.into(),
+            time: Instant::now(),
+            assets,
+        }
+    }
+}
@@ -99,6 +99,7 @@ fn load_technique_stage(
     };
 
     let mut stage = Box::new(TechniqueStage {
+        technique: technique_hash,
         stage,
         shader: shader.clone(),
 
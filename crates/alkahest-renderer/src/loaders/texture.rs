@@ -1,8 +1,266 @@
-use alkahest_data::WideHash;
+use std::path::Path;
+
+use alkahest_data::{dxgi::DxgiFormat, WideHash};
+use anyhow::Context;
 use destiny_pkg::TagHash;
 
-use crate::gpu::{texture::Texture, GpuContext};
+use crate::{
+    gpu::{texture::Texture, GpuContext},
+    util::image::Png,
+};
 
 pub fn load_texture(gctx: &GpuContext, hash: TagHash) -> anyhow::Result<Texture> {
     Texture::load(&gctx.device, WideHash::Hash32(hash))
 }
+
+pub fn load_texture_with_mip_skip(
+    gctx: &GpuContext,
+    hash: TagHash,
+    skip_mips: usize,
+) -> anyhow::Result<Texture> {
+    Texture::load_with_mip_skip(&gctx.device, WideHash::Hash32(hash), skip_mips)
+}
+
+/// Loads a PNG or DDS file from disk. Only the first mip level is used.
+///
+/// `srgb` selects the sRGB variant of the format for PNGs and DDS files without a DX10 header,
+/// DX10 DDS files always use the format stored in the file
+pub fn load_texture_file(gctx: &GpuContext, path: &Path, srgb: bool) -> anyhow::Result<Texture> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => {
+            // Palette, low bit depth and 16-bit PNGs are normalized to 8-bit channels
+            let png = Png::from_bytes_normalized(&data)?.into_rgba()?;
+            let format = if srgb {
+                DxgiFormat::R8G8B8A8_UNORM_SRGB
+            } else {
+                DxgiFormat::R8G8B8A8_UNORM
+            };
+
+            Texture::load_2d_raw(
+                &gctx.device,
+                png.dimensions[0] as u32,
+                png.dimensions[1] as u32,
+                &png.data,
+                format,
+                Some(&name),
+            )
+        }
+        "dds" => {
+            let dds = DdsImage::parse(&data, srgb)?;
+            Texture::load_2d_raw(
+                &gctx.device,
+                dds.width,
+                dds.height,
+                dds.data,
+                dds.format,
+                Some(&name),
+            )
+        }
+        e => anyhow::bail!("Unsupported texture file extension '{e}', expected png or dds"),
+    }
+}
+
+/// Writes the first mip level of a package texture to a DDS file. Cubemaps keep all of their
+/// faces, volume textures all of their slices
+pub fn texture_to_dds(hash: TagHash) -> anyhow::Result<Vec<u8>> {
+    let (header, data) = Texture::load_data(WideHash::Hash32(hash), true)?;
+    let (_, slice_size) = header
+        .format
+        .calculate_pitch(header.width as usize, header.height as usize);
+    // Faces and slices of the first mip are stored before any of the smaller mips
+    let slices = (header.depth.max(1) as usize) * (header.array_size.max(1) as usize);
+    let data = data
+        .get(..slice_size * slices)
+        .context("Texture data is truncated")?;
+
+    Ok(DdsImage::write(
+        header.width as u32,
+        header.height as u32,
+        header.depth as u32,
+        header.array_size as u32,
+        header.format,
+        data,
+    ))
+}
+
+/// First mip level of a 2D DDS texture
+struct DdsImage<'a> {
+    width: u32,
+    height: u32,
+    format: DxgiFormat,
+    data: &'a [u8],
+}
+
+impl<'a> DdsImage<'a> {
+    const MAGIC: &'static [u8; 4] = b"DDS ";
+    const HEADER_SIZE: usize = 128;
+    const DX10_HEADER_SIZE: usize = 20;
+    /// DDPF_RGB, uncompressed RGB(A) data described by the channel masks
+    const PIXEL_FORMAT_RGB: u32 = 0x40;
+    /// DDPF_FOURCC
+    const PIXEL_FORMAT_FOURCC: u32 = 0x4;
+
+    /// Writes a DDS file with a DX10 header and a single mip level. `array_size` > 1 is written as
+    /// a cubemap, as that's the only kind of texture array in the packages
+    fn write(
+        width: u32,
+        height: u32,
+        depth: u32,
+        array_size: u32,
+        format: DxgiFormat,
+        data: &[u8],
+    ) -> Vec<u8> {
+        // DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT, plus DDSD_DEPTH for volumes
+        let flags = if depth > 1 { 0x801007 } else { 0x1007 };
+        // DDSCAPS_TEXTURE, plus DDSCAPS_COMPLEX for cubemaps and volumes
+        let caps = if depth > 1 || array_size > 1 {
+            0x1008
+        } else {
+            0x1000
+        };
+        // DDSCAPS2_CUBEMAP | all faces, DDSCAPS2_VOLUME
+        let caps2 = if depth > 1 {
+            0x200000
+        } else if array_size > 1 {
+            0xfe00
+        } else {
+            0
+        };
+        // D3D10_RESOURCE_DIMENSION_TEXTURE3D/TEXTURE2D
+        let dimension = if depth > 1 { 4 } else { 3 };
+        // D3D10_RESOURCE_MISC_TEXTURECUBE
+        let misc_flags = if array_size > 1 { 0x4 } else { 0 };
+        let (pitch, _) = format.calculate_pitch(width as usize, height as usize);
+
+        let mut header = [0u32; (Self::HEADER_SIZE + Self::DX10_HEADER_SIZE) / 4];
+        header[0] = u32::from_le_bytes(*Self::MAGIC);
+        header[1] = 124;
+        header[2] = flags;
+        header[3] = height;
+        header[4] = width;
+        header[5] = pitch as u32;
+        header[6] = depth.max(1);
+        header[7] = 1;
+        header[19] = 32;
+        header[20] = Self::PIXEL_FORMAT_FOURCC;
+        header[21] = u32::from_le_bytes(*b"DX10");
+        header[27] = caps;
+        header[28] = caps2;
+        header[32] = format.into();
+        header[33] = dimension;
+        header[34] = misc_flags;
+        // Number of cubes for cubemaps
+        header[35] = if array_size > 1 { array_size / 6 } else { 1 };
+
+        let mut file = Vec::with_capacity(header.len() * 4 + data.len());
+        for v in header {
+            file.extend(v.to_le_bytes());
+        }
+        file.extend_from_slice(data);
+        file
+    }
+
+    fn parse(data: &'a [u8], srgb: bool) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            data.len() >= Self::HEADER_SIZE && data.starts_with(Self::MAGIC),
+            "Not a DDS file"
+        );
+
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .context("DDS header is truncated")
+        };
+
+        let height = read_u32(12)?;
+        let width = read_u32(16)?;
+        let depth = read_u32(24)?;
+        anyhow::ensure!(depth <= 1, "3D DDS textures are not supported");
+
+        let pixel_flags = read_u32(80)?;
+        let fourcc = &data[84..88];
+        let pick = |unorm, srgb_format| if srgb { srgb_format } else { unorm };
+        let (format, data_offset) = match fourcc {
+            b"DX10" => {
+                let format = DxgiFormat::try_from(read_u32(Self::HEADER_SIZE)?)?;
+                let array_size = read_u32(Self::HEADER_SIZE + 12)?;
+                anyhow::ensure!(array_size <= 1, "DDS texture arrays are not supported");
+                (format, Self::HEADER_SIZE + Self::DX10_HEADER_SIZE)
+            }
+            b"DXT1" => (
+                pick(DxgiFormat::BC1_UNORM, DxgiFormat::BC1_UNORM_SRGB),
+                Self::HEADER_SIZE,
+            ),
+            b"DXT2" | b"DXT3" => (
+                pick(DxgiFormat::BC2_UNORM, DxgiFormat::BC2_UNORM_SRGB),
+                Self::HEADER_SIZE,
+            ),
+            b"DXT4" | b"DXT5" => (
+                pick(DxgiFormat::BC3_UNORM, DxgiFormat::BC3_UNORM_SRGB),
+                Self::HEADER_SIZE,
+            ),
+            b"ATI1" | b"BC4U" => (DxgiFormat::BC4_UNORM, Self::HEADER_SIZE),
+            b"ATI2" | b"BC5U" => (DxgiFormat::BC5_UNORM, Self::HEADER_SIZE),
+            _ if pixel_flags & Self::PIXEL_FORMAT_RGB != 0 && read_u32(88)? == 32 => {
+                let format = match read_u32(92)? {
+                    0x000000ff => pick(DxgiFormat::R8G8B8A8_UNORM, DxgiFormat::R8G8B8A8_UNORM_SRGB),
+                    0x00ff0000 => pick(DxgiFormat::B8G8R8A8_UNORM, DxgiFormat::B8G8R8A8_UNORM_SRGB),
+                    m => anyhow::bail!("Unsupported DDS channel layout (red mask 0x{m:08x})"),
+                };
+                (format, Self::HEADER_SIZE)
+            }
+            _ => anyhow::bail!(
+                "Unsupported DDS pixel format (fourcc '{}')",
+                String::from_utf8_lossy(fourcc)
+            ),
+        };
+
+        let (_, mip_size) = format.calculate_pitch(width as usize, height as usize);
+        let data = data
+            .get(data_offset..data_offset + mip_size)
+            .context("DDS texture data is truncated")?;
+
+        Ok(Self {
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+}
+
+#[test]
+fn test_parse_dds() {
+    let mut data = vec![0u8; DdsImage::HEADER_SIZE];
+    data[..4].copy_from_slice(DdsImage::MAGIC);
+    data[12..16].copy_from_slice(&8u32.to_le_bytes());
+    data[16..20].copy_from_slice(&4u32.to_le_bytes());
+    data[84..88].copy_from_slice(b"DXT1");
+    // Two 4x4 BC1 blocks, plus a smaller mip that should be ignored
+    data.extend([0xaa; 16 + 8]);
+
+    let dds = DdsImage::parse(&data, true).unwrap();
+    assert_eq!((dds.width, dds.height), (4, 8));
+    assert_eq!(dds.format, DxgiFormat::BC1_UNORM_SRGB);
+    assert_eq!(dds.data.len(), 16);
+
+    data[84..88].copy_from_slice(b"DXT9");
+    assert!(DdsImage::parse(&data, false).is_err());
+    assert!(DdsImage::parse(&data[..64], false).is_err());
+
+    let written = DdsImage::write(4, 8, 1, 1, DxgiFormat::BC1_UNORM, &[0x55; 16]);
+    let dds = DdsImage::parse(&written, false).unwrap();
+    assert_eq!((dds.width, dds.height), (4, 8));
+    assert_eq!(dds.format, DxgiFormat::BC1_UNORM);
+    assert_eq!(dds.data, &[0x55; 16]);
+}
@@ -0,0 +1,66 @@
+//! Cross-reference of the loaded textures and the techniques that bind them
+
+use alkahest_data::dxgi::DxgiFormat;
+use destiny_pkg::TagHash;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+
+use crate::{handle::AssetId, loaders::AssetManager};
+
+pub struct TextureUsage {
+    pub id: AssetId,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub format: DxgiFormat,
+    /// Approximate GPU memory used by the texture, in bytes
+    pub size: usize,
+    /// Mips that were skipped when the texture was streamed in, 0 for full quality
+    pub skipped_mips: usize,
+    /// Loaded techniques with a stage that binds the texture
+    pub techniques: Vec<TagHash>,
+}
+
+impl AssetManager {
+    /// Every loaded texture along with the techniques referencing it, largest textures first
+    pub fn texture_usage(&self) -> Vec<TextureUsage> {
+        let mut references: FxHashMap<AssetId, Vec<TagHash>> = FxHashMap::default();
+        for (_, technique) in self.techniques.iter_loaded() {
+            for (_, stage) in technique.all_stages() {
+                let Some(stage) = stage else {
+                    continue;
+                };
+
+                for (_, texture) in &stage.textures {
+                    references
+                        .entry(texture.id())
+                        .or_default()
+                        .push(technique.hash);
+                }
+            }
+        }
+
+        self.textures
+            .iter_loaded()
+            .map(|(id, texture)| {
+                let (width, height, depth) = texture.dimensions();
+                TextureUsage {
+                    id,
+                    width,
+                    height,
+                    depth,
+                    format: texture.format,
+                    size: self.texture_sizes.get(&id).copied().unwrap_or_default(),
+                    skipped_mips: self.reduced_textures.get(&id).copied().unwrap_or_default(),
+                    techniques: references
+                        .remove(&id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .unique()
+                        .collect(),
+                }
+            })
+            .sorted_by_key(|t| std::cmp::Reverse(t.size))
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+use alkahest_data::tfx::TfxShaderStage;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP, D3D11_VIEWPORT,
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    postprocess::{draw_fullscreen, EffectQuality},
+    renderer::Renderer,
+};
+
+/// Number of bloom levels allocated in the gbuffer, starting at half resolution
+pub const BLOOM_MAX_LEVELS: usize = 6;
+
+impl EffectQuality {
+    /// Number of downsampled levels the bloom is spread over, more levels give a wider glow
+    pub fn bloom_levels(&self) -> usize {
+        match self {
+            EffectQuality::Low => 4,
+            EffectQuality::Medium => 5,
+            EffectQuality::High => BLOOM_MAX_LEVELS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestBloom {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _pad: u32,
+}
+
+/// Threshold bloom, blurring the bright parts of the shading result over a chain of downsampled
+/// targets and adding them back on top
+pub struct BloomRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_prefilter_ps: ID3D11PixelShader,
+    shader_downsample_ps: ID3D11PixelShader,
+    shader_upsample_ps: ID3D11PixelShader,
+    shader_composite_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    scope: ConstantBuffer<ScopeAlkahestBloom>,
+}
+
+impl BloomRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/bloom_prefilter.hlsl"))?,
+            shader_prefilter_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/bloom_prefilter.hlsl"))?,
+            shader_downsample_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/bloom_downsample.hlsl"))?,
+            shader_upsample_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/bloom_upsample.hlsl"))?,
+            shader_composite_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/bloom_composite.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            scope: ConstantBuffer::create(gctx, None)?,
+        })
+    }
+
+    /// Adds bloom to the shading result in place
+    pub fn draw(&self, renderer: &Renderer) {
+        gpu_event!(renderer.gpu, "bloom");
+        let settings = renderer.settings();
+        let levels = settings.bloom_quality.bloom_levels();
+
+        let mut viewport_count = 1;
+        let mut previous_viewport = D3D11_VIEWPORT::default();
+        unsafe {
+            renderer
+                .gpu
+                .lock_context()
+                .RSGetViewports(&mut viewport_count, Some(&mut previous_viewport));
+        }
+
+        self.scope
+            .write(&ScopeAlkahestBloom {
+                threshold: settings.bloom_threshold,
+                knee: settings.bloom_threshold * 0.5,
+                intensity: settings.bloom_intensity / levels as f32,
+                _pad: 0,
+            })
+            .unwrap();
+        self.scope.bind(0, TfxShaderStage::Pixel);
+
+        let data = renderer.data.lock();
+        let gbuffers = &data.gbuffers;
+        let down = &gbuffers.bloom_downsample[..levels];
+        let up = &gbuffers.bloom_upsample[..levels];
+
+        // Threshold and downsample
+        for (i, target) in down.iter().enumerate() {
+            let (shader, source) = if i == 0 {
+                (&self.shader_prefilter_ps, &gbuffers.shading_result.view)
+            } else {
+                (&self.shader_downsample_ps, &down[i - 1].view)
+            };
+
+            Self::set_viewport(renderer, target.viewport());
+            draw_fullscreen(
+                renderer,
+                &self.shader_vs,
+                shader,
+                &self.sampler_linear,
+                &[Some(source.clone())],
+                &target.render_target,
+            );
+        }
+
+        // Upsample back up the chain, accumulating every level. The lowest level is used as is
+        for i in (0..levels - 1).rev() {
+            let lower = if i == levels - 2 {
+                &down[i + 1]
+            } else {
+                &up[i + 1]
+            };
+
+            Self::set_viewport(renderer, up[i].viewport());
+            draw_fullscreen(
+                renderer,
+                &self.shader_vs,
+                &self.shader_upsample_ps,
+                &self.sampler_linear,
+                &[Some(down[i].view.clone()), Some(lower.view.clone())],
+                &up[i].render_target,
+            );
+        }
+
+        Self::set_viewport(renderer, previous_viewport);
+        gbuffers
+            .shading_result
+            .copy_to(&gbuffers.shading_result_read);
+        draw_fullscreen(
+            renderer,
+            &self.shader_vs,
+            &self.shader_composite_ps,
+            &self.sampler_linear,
+            &[
+                Some(gbuffers.shading_result_read.view.clone()),
+                Some(up[0].view.clone()),
+            ],
+            &gbuffers.shading_result.render_target,
+        );
+    }
+
+    fn set_viewport(renderer: &Renderer, viewport: D3D11_VIEWPORT) {
+        unsafe {
+            renderer
+                .gpu
+                .lock_context()
+                .RSSetViewports(Some(std::slice::from_ref(&viewport)));
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use alkahest_data::tfx::TfxShaderStage;
+use glam::Mat4;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP,
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    postprocess::{draw_fullscreen, EffectQuality},
+    renderer::Renderer,
+};
+
+impl EffectQuality {
+    /// Number of samples gathered for every pixel by the depth of field
+    pub fn dof_samples(&self) -> u32 {
+        match self {
+            EffectQuality::Low => 24,
+            EffectQuality::Medium => 48,
+            EffectQuality::High => 96,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestDof {
+    pixel_to_camera: Mat4,
+    focus_distance: f32,
+    max_blur_radius: f32,
+    sample_count: u32,
+    _pad: u32,
+}
+
+/// Bokeh depth of field, blurring the shading result based on the distance from the focal plane
+pub struct DofRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    scope: ConstantBuffer<ScopeAlkahestDof>,
+}
+
+impl DofRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/dof.hlsl"))?,
+            shader_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/dof.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            scope: ConstantBuffer::create(gctx, None)?,
+        })
+    }
+
+    /// Blurs the shading result in place. With autofocus enabled, the focus distance is taken from
+    /// the depth buffer in the center of the view (under the crosshair)
+    pub fn draw(&self, renderer: &Renderer) {
+        gpu_event!(renderer.gpu, "depth_of_field");
+        let settings = renderer.settings();
+        let (source, target, depth) = {
+            let data = renderer.data.lock();
+            let Some(view) = &data.externs.view else {
+                return;
+            };
+
+            let height = data.gbuffers.shading_result.viewport().Height;
+            self.scope
+                .write(&ScopeAlkahestDof {
+                    pixel_to_camera: view.target_pixel_to_camera,
+                    focus_distance: if settings.dof_autofocus {
+                        0.0
+                    } else {
+                        settings.dof_focus_distance.max(0.01)
+                    },
+                    max_blur_radius: settings.dof_blur_size * height,
+                    sample_count: settings.dof_quality.dof_samples(),
+                    _pad: 0,
+                })
+                .unwrap();
+
+            data.gbuffers
+                .shading_result
+                .copy_to(&data.gbuffers.shading_result_read);
+            (
+                data.gbuffers.shading_result_read.view.clone(),
+                data.gbuffers.shading_result.render_target.clone(),
+                data.gbuffers.depth.texture_view.clone(),
+            )
+        };
+
+        self.scope.bind(0, TfxShaderStage::Pixel);
+        draw_fullscreen(
+            renderer,
+            &self.shader_vs,
+            &self.shader_ps,
+            &self.sampler_linear,
+            &[Some(source), Some(depth)],
+            &target,
+        );
+    }
+}
@@ -1 +1,60 @@
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11RenderTargetView, ID3D11SamplerState, ID3D11ShaderResourceView,
+    ID3D11VertexShader,
+};
+
+use crate::renderer::Renderer;
+
+pub mod bloom;
+pub mod dof;
 pub mod ssao;
+pub mod ssgi;
+pub mod ssr;
+
+/// Quality levels of the optional post effects, trading sample counts for performance
+#[derive(
+    Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, EnumIter, strum::Display,
+)]
+pub enum EffectQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// Draws a fullscreen triangle into `target`, unbinding the target and sources afterwards so they
+/// can be used by the next pass
+pub(crate) fn draw_fullscreen(
+    renderer: &Renderer,
+    shader_vs: &ID3D11VertexShader,
+    shader_ps: &ID3D11PixelShader,
+    sampler: &ID3D11SamplerState,
+    sources: &[Option<ID3D11ShaderResourceView>],
+    target: &ID3D11RenderTargetView,
+) {
+    renderer
+        .gpu
+        .current_states
+        .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+    renderer.gpu.flush_states();
+
+    unsafe {
+        let ctx = renderer.gpu.lock_context();
+        ctx.OMSetRenderTargets(Some(&[Some(target.clone())]), None);
+        ctx.RSSetState(None);
+        renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+        ctx.OMSetDepthStencilState(None, 0);
+        ctx.VSSetShader(shader_vs, None);
+        ctx.PSSetShader(shader_ps, None);
+        ctx.PSSetShaderResources(0, Some(sources));
+        ctx.PSSetSamplers(1, Some(&[Some(sampler.clone())]));
+
+        ctx.Draw(3, 0);
+
+        ctx.OMSetRenderTargets(Some(&[]), None);
+        ctx.PSSetShaderResources(0, Some(&vec![None; sources.len()]));
+    }
+}
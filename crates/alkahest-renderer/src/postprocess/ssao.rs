@@ -66,7 +66,7 @@ impl SsaoRenderer {
         };
         let externs = &mut renderer.data.lock().externs;
         {
-            let scope = self.scope.data();
+            let mut scope = self.scope.data();
             if let Some(view) = &externs.view {
                 scope.target_pixel_to_world = view.target_pixel_to_world
             } else {
@@ -0,0 +1,167 @@
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage};
+use crossbeam::atomic::AtomicCell;
+use glam::Mat4;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP, D3D11_VIEWPORT,
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    postprocess::draw_fullscreen,
+    renderer::Renderer,
+};
+
+/// Weight of the accumulated history, the noise of a single frame is averaged out over roughly
+/// `1 / (1 - weight)` frames
+const SSGI_HISTORY_WEIGHT: f32 = 0.9;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestSsgi {
+    target_pixel_to_world: Mat4,
+    previous_world_to_projective: Mat4,
+    /// Maximum distance a ray travels, in meters
+    radius: f32,
+    intensity: f32,
+    /// Weight of the history, 0 when there is no (valid) history
+    history_weight: f32,
+    /// Rotates the sample directions every frame, so the history accumulates different samples
+    frame_index: u32,
+}
+
+/// Experimental screen-space global illumination. Traces the diffuse lighting of nearby surfaces
+/// at half resolution, accumulates it over time and adds it to the diffuse light buffer as a
+/// single bounce
+pub struct SsgiRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_trace_ps: ID3D11PixelShader,
+    shader_apply_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    scope: ConstantBuffer<ScopeAlkahestSsgi>,
+    /// world_to_projective of the frame in the history, `None` if there is no usable history
+    previous_world_to_projective: AtomicCell<Option<Mat4>>,
+}
+
+impl SsgiRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/ssgi.hlsl"))?,
+            shader_trace_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/ssgi.hlsl"))?,
+            shader_apply_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/ssgi_apply.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            scope: ConstantBuffer::create(gctx, None)?,
+            previous_world_to_projective: AtomicCell::new(None),
+        })
+    }
+
+    /// Discards the accumulated bounce lighting, so the next frame doesn't blend with a stale image
+    pub fn reset_history(&self) {
+        self.previous_world_to_projective.store(None);
+    }
+
+    /// Adds the bounce lighting to the diffuse light buffer. Expects the direct lighting to be done
+    pub fn draw(&self, renderer: &Renderer, frame_index: usize) {
+        gpu_event!(renderer.gpu, "ssgi");
+        let settings = renderer.settings();
+
+        let data = renderer.data.lock();
+        let Some(view) = &data.externs.view else {
+            return;
+        };
+
+        let previous = self.previous_world_to_projective.load();
+        self.scope
+            .write(&ScopeAlkahestSsgi {
+                target_pixel_to_world: view.target_pixel_to_world,
+                previous_world_to_projective: previous.unwrap_or(Mat4::IDENTITY),
+                radius: settings.ssgi_radius,
+                intensity: settings.ssgi_intensity,
+                history_weight: if previous.is_some() {
+                    SSGI_HISTORY_WEIGHT
+                } else {
+                    0.0
+                },
+                frame_index: frame_index as u32,
+            })
+            .unwrap();
+        self.scope.bind(0, TfxShaderStage::Pixel);
+        let world_to_projective = view.world_to_projective;
+
+        let gbuffers = &data.gbuffers;
+        let dxstate = renderer.gpu.backup_state();
+        let mut viewport_count = 1;
+        let mut previous_viewport = D3D11_VIEWPORT::default();
+        unsafe {
+            let ctx = renderer.gpu.lock_context();
+            ctx.RSGetViewports(&mut viewport_count, Some(&mut previous_viewport));
+            ctx.RSSetViewports(Some(&[gbuffers.ssgi.viewport()]));
+        }
+
+        draw_fullscreen(
+            renderer,
+            &self.shader_vs,
+            &self.shader_trace_ps,
+            &self.sampler_linear,
+            &[
+                Some(gbuffers.depth.texture_view.clone()),
+                Some(gbuffers.rt1.view.clone()),
+                Some(gbuffers.rt0.view.clone()),
+                Some(gbuffers.light_diffuse.view.clone()),
+                Some(gbuffers.ssgi_history.view.clone()),
+            ],
+            &gbuffers.ssgi.render_target,
+        );
+        gbuffers.ssgi.copy_to(&gbuffers.ssgi_history);
+        self.previous_world_to_projective
+            .store(Some(world_to_projective));
+
+        // Add the (upsampled) bounce to the diffuse lighting. The shader writes an alpha of 0, so
+        // the premultiplied alpha blend state adds to the existing lighting
+        renderer
+            .gpu
+            .current_states
+            .store(StateSelection::new(Some(8), Some(0), Some(0), Some(0)));
+        renderer.gpu.flush_states();
+        unsafe {
+            let ctx = renderer.gpu.lock_context();
+            ctx.RSSetViewports(Some(&[previous_viewport]));
+            ctx.OMSetRenderTargets(
+                Some(&[Some(gbuffers.light_diffuse.render_target.clone())]),
+                None,
+            );
+            ctx.RSSetState(None);
+            renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.VSSetShader(&self.shader_vs, None);
+            ctx.PSSetShader(&self.shader_apply_ps, None);
+            ctx.PSSetShaderResources(
+                0,
+                Some(&[
+                    Some(gbuffers.depth.texture_view.clone()),
+                    Some(gbuffers.ssgi.view.clone()),
+                ]),
+            );
+            ctx.PSSetSamplers(1, Some(&[Some(self.sampler_linear.clone())]));
+
+            ctx.Draw(3, 0);
+
+            ctx.PSSetShaderResources(0, Some(&[None, None]));
+        }
+        renderer.gpu.restore_state(&dxstate);
+    }
+}
@@ -0,0 +1,162 @@
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage};
+use crossbeam::atomic::AtomicCell;
+use glam::Mat4;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11VertexShader, D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+    D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP,
+};
+
+use crate::{
+    gpu::{
+        buffer::ConstantBuffer, global_state::BLEND_STATE_PREMULTIPLIED, util::DxDeviceExt,
+        SharedGpuContext,
+    },
+    gpu_event, include_dxbc,
+    postprocess::EffectQuality,
+    renderer::Renderer,
+};
+
+impl EffectQuality {
+    /// Number of steps a reflection ray is marched in before it is refined
+    pub fn ssr_steps(&self) -> u32 {
+        match self {
+            EffectQuality::Low => 16,
+            EffectQuality::Medium => 32,
+            EffectQuality::High => 64,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestSsr {
+    target_pixel_to_world: Mat4,
+    previous_world_to_projective: Mat4,
+    /// Maximum distance a ray travels, in meters
+    max_distance: f32,
+    /// Reflections fade out towards this roughness, rougher surfaces only use the cubemaps
+    max_roughness: f32,
+    step_count: u32,
+    frame_index: u32,
+}
+
+/// Screen-space reflections. Rays are traced against the depth buffer and shade their hit with
+/// the previous frame's shading result. The reflection replaces the cubemap reflection in the
+/// specular IBL buffer where it hits, so the cubemaps remain as the fallback
+pub struct SsrRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    scope: ConstantBuffer<ScopeAlkahestSsr>,
+    /// world_to_projective of the frame in `ssr_history`, `None` if there is no usable history
+    previous_world_to_projective: AtomicCell<Option<Mat4>>,
+}
+
+impl SsrRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/ssr.hlsl"))?,
+            shader_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/ssr.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            scope: ConstantBuffer::create(gctx, None)?,
+            previous_world_to_projective: AtomicCell::new(None),
+        })
+    }
+
+    /// Discards the stored shading result, reflections are skipped until a new one is stored
+    pub fn reset_history(&self) {
+        self.previous_world_to_projective.store(None);
+    }
+
+    /// Keeps the shading result of this frame around, reflections of the next frame are shaded
+    /// with it. Expects the transparents to be done
+    pub fn store_history(&self, renderer: &Renderer) {
+        let data = renderer.data.lock();
+        let Some(view) = &data.externs.view else {
+            return;
+        };
+
+        data.gbuffers
+            .shading_result
+            .copy_to(&data.gbuffers.ssr_history);
+        self.previous_world_to_projective
+            .store(Some(view.world_to_projective));
+    }
+
+    /// Blends the reflections into the specular IBL buffer. Expects the cubemaps to be done
+    pub fn draw(&self, renderer: &Renderer, frame_index: usize) {
+        let Some(previous) = self.previous_world_to_projective.load() else {
+            return;
+        };
+
+        gpu_event!(renderer.gpu, "ssr");
+        let settings = renderer.settings();
+
+        let data = renderer.data.lock();
+        let Some(view) = &data.externs.view else {
+            return;
+        };
+
+        self.scope
+            .write(&ScopeAlkahestSsr {
+                target_pixel_to_world: view.target_pixel_to_world,
+                previous_world_to_projective: previous,
+                max_distance: settings.ssr_max_distance,
+                max_roughness: settings.ssr_max_roughness,
+                step_count: settings.ssr_quality.ssr_steps(),
+                frame_index: frame_index as u32,
+            })
+            .unwrap();
+        self.scope.bind(0, TfxShaderStage::Pixel);
+
+        // The shader writes the reflection premultiplied by its confidence `a`, blending it over
+        // the cubemap specular as `reflection + cubemap * (1 - a)`. The destination alpha isn't
+        // used, so it doesn't matter that the target's alpha is cleared to 0
+        renderer.gpu.current_states.store(StateSelection::new(
+            Some(BLEND_STATE_PREMULTIPLIED),
+            Some(0),
+            Some(0),
+            Some(0),
+        ));
+        renderer.gpu.flush_states();
+
+        let gbuffers = &data.gbuffers;
+        unsafe {
+            let ctx = renderer.gpu.lock_context();
+            ctx.OMSetRenderTargets(
+                Some(&[Some(gbuffers.light_ibl_specular.render_target.clone())]),
+                None,
+            );
+            ctx.RSSetState(None);
+            renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.VSSetShader(&self.shader_vs, None);
+            ctx.PSSetShader(&self.shader_ps, None);
+            ctx.PSSetShaderResources(
+                0,
+                Some(&[
+                    Some(gbuffers.depth.texture_view.clone()),
+                    Some(gbuffers.rt1.view.clone()),
+                    Some(gbuffers.ssr_history.view.clone()),
+                ]),
+            );
+            ctx.PSSetSamplers(1, Some(&[Some(self.sampler_linear.clone())]));
+
+            ctx.Draw(3, 0);
+
+            ctx.OMSetRenderTargets(Some(&[]), None);
+            ctx.PSSetShaderResources(0, Some(&[None, None, None]));
+        }
+    }
+}
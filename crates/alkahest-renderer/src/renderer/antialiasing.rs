@@ -0,0 +1,257 @@
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage};
+use crossbeam::atomic::AtomicCell;
+use glam::{Mat4, Vec2};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11RenderTargetView, ID3D11SamplerState, ID3D11ShaderResourceView,
+    ID3D11VertexShader, D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_SAMPLER_DESC,
+    D3D11_TEXTURE_ADDRESS_CLAMP,
+};
+
+use crate::{
+    camera::Viewport,
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::Renderer,
+    tfx::externs,
+};
+
+#[derive(
+    Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, EnumIter, strum::Display,
+)]
+pub enum AntiAliasing {
+    None,
+    /// The game's own FXAA pipeline
+    #[default]
+    Fxaa,
+    /// Subpixel morphological anti-aliasing (1x)
+    Smaa,
+    /// Temporal anti-aliasing, accumulates frames rendered with a jittered projection
+    Taa,
+}
+
+/// Weight of the accumulated history when resolving TAA
+const TAA_HISTORY_WEIGHT: f32 = 0.9;
+/// Number of subpixel positions the TAA jitter cycles through
+const TAA_JITTER_SAMPLES: usize = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestTaa {
+    target_pixel_to_world_jittered: Mat4,
+    previous_world_to_projective: Mat4,
+    history_weight: f32,
+    _pad: [u32; 3],
+}
+
+/// SMAA and TAA, as alternatives to the game's FXAA
+pub struct AntiAliasingRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_smaa_edges_ps: ID3D11PixelShader,
+    shader_smaa_weights_ps: ID3D11PixelShader,
+    shader_smaa_blend_ps: ID3D11PixelShader,
+    shader_taa_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    taa_scope: ConstantBuffer<ScopeAlkahestTaa>,
+    /// Unjittered world_to_projective of the frame that is currently being rendered
+    current_world_to_projective: AtomicCell<Mat4>,
+    /// Unjittered world_to_projective of the frame in the TAA history, `None` if there is no
+    /// usable history
+    previous_world_to_projective: AtomicCell<Option<Mat4>>,
+}
+
+impl AntiAliasingRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/taa.hlsl"))?,
+            shader_smaa_edges_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/smaa_edges.hlsl"))?,
+            shader_smaa_weights_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/smaa_weights.hlsl"))?,
+            shader_smaa_blend_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/smaa_blend.hlsl"))?,
+            shader_taa_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/taa.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            taa_scope: ConstantBuffer::create(gctx, None)?,
+            current_world_to_projective: AtomicCell::new(Mat4::IDENTITY),
+            previous_world_to_projective: AtomicCell::new(None),
+        })
+    }
+
+    /// Offsets the projection of the view by a subpixel amount that changes every frame
+    pub fn jitter_view(&self, view: &mut externs::View, viewport: &Viewport, frame_index: usize) {
+        self.current_world_to_projective
+            .store(view.world_to_projective);
+
+        if viewport.size.x == 0 || viewport.size.y == 0 {
+            return;
+        }
+
+        // Pixels to clip space, with Y pointing up
+        let offset = taa_jitter(frame_index) * 2.0 / viewport.size.as_vec2() * Vec2::new(1.0, -1.0);
+        view.camera_to_projective =
+            Mat4::from_translation(offset.extend(0.0)) * view.camera_to_projective;
+        view.derive_matrices(viewport);
+    }
+
+    /// Discards the TAA history, so the next frame doesn't blend with a stale image
+    pub fn reset_history(&self) {
+        self.previous_world_to_projective.store(None);
+    }
+
+    /// Applies SMAA from the current postprocess target to the next one
+    pub fn draw_smaa(&self, renderer: &Renderer) {
+        gpu_event!(renderer.gpu, "smaa");
+        let (source, target, edges, weights) = {
+            let data = renderer.data.lock();
+            let (source, target) = data.gbuffers.get_postprocess_rt(true);
+            data.gbuffers.smaa_edges.clear(&[0.0; 4]);
+            (
+                source.view.clone(),
+                target.render_target.clone(),
+                (
+                    data.gbuffers.smaa_edges.render_target.clone(),
+                    data.gbuffers.smaa_edges.view.clone(),
+                ),
+                (
+                    data.gbuffers.smaa_weights.render_target.clone(),
+                    data.gbuffers.smaa_weights.view.clone(),
+                ),
+            )
+        };
+
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_smaa_edges_ps,
+            &[Some(source.clone())],
+            &edges.0,
+        );
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_smaa_weights_ps,
+            &[Some(edges.1)],
+            &weights.0,
+        );
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_smaa_blend_ps,
+            &[Some(source), Some(weights.1)],
+            &target,
+        );
+    }
+
+    /// Blends the current postprocess target with the reprojected history into the next one
+    pub fn draw_taa(&self, renderer: &Renderer) {
+        gpu_event!(renderer.gpu, "taa");
+        let (source, target, history, depth) = {
+            let data = renderer.data.lock();
+            let Some(view) = &data.externs.view else {
+                return;
+            };
+
+            let previous = self.previous_world_to_projective.load();
+            self.taa_scope
+                .write(&ScopeAlkahestTaa {
+                    target_pixel_to_world_jittered: view.target_pixel_to_world,
+                    previous_world_to_projective: previous.unwrap_or(Mat4::IDENTITY),
+                    history_weight: if previous.is_some() {
+                        TAA_HISTORY_WEIGHT
+                    } else {
+                        0.0
+                    },
+                    _pad: [0; 3],
+                })
+                .unwrap();
+
+            let (source, target) = data.gbuffers.get_postprocess_rt(true);
+            (
+                source.view.clone(),
+                target.render_target.clone(),
+                data.gbuffers.taa_history.view.clone(),
+                data.gbuffers.depth.texture_view.clone(),
+            )
+        };
+
+        self.taa_scope.bind(0, TfxShaderStage::Pixel);
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_taa_ps,
+            &[Some(source), Some(history), Some(depth)],
+            &target,
+        );
+
+        {
+            let data = renderer.data.lock();
+            data.gbuffers
+                .get_postprocess_output()
+                .copy_to(&data.gbuffers.taa_history);
+        }
+        self.previous_world_to_projective
+            .store(Some(self.current_world_to_projective.load()));
+    }
+
+    fn draw_fullscreen(
+        &self,
+        renderer: &Renderer,
+        shader_ps: &ID3D11PixelShader,
+        sources: &[Option<ID3D11ShaderResourceView>],
+        target: &ID3D11RenderTargetView,
+    ) {
+        renderer
+            .gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+        renderer.gpu.flush_states();
+
+        unsafe {
+            let ctx = renderer.gpu.lock_context();
+            ctx.OMSetRenderTargets(Some(&[Some(target.clone())]), None);
+            ctx.RSSetState(None);
+            renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.VSSetShader(&self.shader_vs, None);
+            ctx.PSSetShader(shader_ps, None);
+            ctx.PSSetShaderResources(0, Some(sources));
+            ctx.PSSetSamplers(1, Some(&[Some(self.sampler_linear.clone())]));
+
+            ctx.Draw(3, 0);
+
+            // Unbind everything so the targets can be used as shader resources (and vice versa)
+            ctx.OMSetRenderTargets(Some(&[]), None);
+            ctx.PSSetShaderResources(0, Some(&vec![None; sources.len()]));
+        }
+    }
+}
+
+/// Halton (2, 3) sequence, in pixels relative to the pixel center
+fn taa_jitter(frame_index: usize) -> Vec2 {
+    let index = frame_index % TAA_JITTER_SAMPLES + 1;
+    Vec2::new(halton(index, 2), halton(index, 3)) - 0.5
+}
+
+fn halton(mut index: usize, base: usize) -> f32 {
+    let mut f = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        f /= base as f32;
+        result += f * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
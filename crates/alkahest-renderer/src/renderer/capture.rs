@@ -0,0 +1,493 @@
+use std::f32::consts::{PI, TAU};
+
+use alkahest_data::dxgi::DxgiFormat;
+use bevy_ecs::entity::Entity;
+use glam::{Mat4, UVec2, Vec3};
+use png::{BitDepth, ColorType};
+use windows::Win32::Graphics::Direct3D11::{ID3D11Texture2D, D3D11_MAP_READ};
+
+use crate::{
+    camera::{Camera, Viewport},
+    ecs::{render::light::ShadowMapRenderer, Scene},
+    gpu::{texture::Texture, GpuContext},
+    renderer::{
+        gbuffer::{CpuStagingBuffer, RenderTarget},
+        Renderer,
+    },
+    resources::AppResources,
+    util::image::Png,
+};
+
+/// Pixel data of a render target read back to the CPU
+pub enum CapturedPixels {
+    /// Normalized/floating point channels, as stored in the target (no color space conversion)
+    Float(Vec<[f32; 4]>),
+    /// Integer targets, such as the pick buffer
+    Uint(Vec<u32>),
+}
+
+pub struct CapturedTarget {
+    pub name: String,
+    pub format: DxgiFormat,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: CapturedPixels,
+}
+
+impl CapturedTarget {
+    /// Whether the target only stores 8-bit normalized values, and can be saved losslessly as a PNG
+    pub fn is_8bit(&self) -> bool {
+        matches!(
+            self.format,
+            DxgiFormat::R8G8B8A8_UNORM
+                | DxgiFormat::R8G8B8A8_UNORM_SRGB
+                | DxgiFormat::B8G8R8A8_UNORM
+                | DxgiFormat::B8G8R8A8_UNORM_SRGB
+                | DxgiFormat::R8_UNORM
+        )
+    }
+
+    /// Number of meaningful channels in the target
+    pub fn channel_count(&self) -> usize {
+        match self.format {
+            DxgiFormat::R8_UNORM
+            | DxgiFormat::R32_TYPELESS
+            | DxgiFormat::R32_FLOAT
+            | DxgiFormat::R32_UINT => 1,
+            DxgiFormat::R11G11B10_FLOAT => 3,
+            _ => 4,
+        }
+    }
+}
+
+/// HDR cubemap rendered from a single point
+pub struct CapturedCubemap {
+    pub position: Vec3,
+    /// Width and height of each face
+    pub size: u32,
+    /// Linear RGBA pixels of each face, in D3D cubemap order (+X, -X, +Y, -Y, +Z, -Z)
+    pub faces: [Vec<[f32; 4]>; 6],
+}
+
+impl CapturedCubemap {
+    /// Looks up the color in the given direction (nearest neighbor)
+    pub fn sample(&self, direction: Vec3) -> [f32; 4] {
+        let abs = direction.abs();
+        // Face index, the direction along the face's U and V axes, and the major axis
+        let (face, u, v, major) = if abs.x >= abs.y && abs.x >= abs.z {
+            if direction.x > 0.0 {
+                (0, -direction.z, -direction.y, abs.x)
+            } else {
+                (1, direction.z, -direction.y, abs.x)
+            }
+        } else if abs.y >= abs.z {
+            if direction.y > 0.0 {
+                (2, direction.x, direction.z, abs.y)
+            } else {
+                (3, direction.x, -direction.z, abs.y)
+            }
+        } else if direction.z > 0.0 {
+            (4, direction.x, -direction.y, abs.z)
+        } else {
+            (5, -direction.x, -direction.y, abs.z)
+        };
+
+        let to_texel = |c: f32| {
+            (((c / major) * 0.5 + 0.5) * self.size as f32).clamp(0.0, self.size as f32 - 1.0)
+                as usize
+        };
+        self.faces[face][to_texel(v) * self.size as usize + to_texel(u)]
+    }
+
+    /// Converts the cubemap to an equirectangular (latitude/longitude) panorama, with +Z as up
+    pub fn to_equirectangular(&self, width: u32) -> Vec<[f32; 4]> {
+        let height = width / 2;
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            let theta = (y as f32 + 0.5) / height as f32 * PI;
+            for x in 0..width {
+                let phi = (x as f32 + 0.5) / width as f32 * TAU;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                pixels.push(self.sample(direction));
+            }
+        }
+
+        pixels
+    }
+
+    /// Converts the faces to RGBA16F, as used by [`Texture::load_cube_raw_with_mips`]
+    pub fn faces_rgba16f(&self) -> [Vec<u8>; 6] {
+        self.faces.each_ref().map(|face| {
+            face.iter()
+                .flat_map(|p| p.iter().flat_map(|&c| f32_to_f16(c).to_le_bytes()))
+                .collect()
+        })
+    }
+
+    pub fn create_texture(&self, gctx: &GpuContext) -> anyhow::Result<Texture> {
+        let faces = self.faces_rgba16f();
+        Texture::load_cube_raw_with_mips(
+            gctx,
+            self.size,
+            &faces.each_ref().map(|f| f.as_slice()),
+            DxgiFormat::R16G16B16A16_FLOAT,
+            Some("Captured cubemap"),
+        )
+    }
+}
+
+impl Renderer {
+    /// Renders the scene in all 6 directions from `position` and reads back the HDR shading
+    /// result of each face. Overlays are not included.
+    pub fn capture_cubemap(
+        &self,
+        position: Vec3,
+        size: u32,
+        scene: &mut Scene,
+        resources: &AppResources,
+    ) -> anyhow::Result<CapturedCubemap> {
+        const FACES: [(Vec3, Vec3); 6] = [
+            (Vec3::X, Vec3::Y),
+            (Vec3::NEG_X, Vec3::Y),
+            (Vec3::Y, Vec3::NEG_Z),
+            (Vec3::NEG_Y, Vec3::Z),
+            (Vec3::Z, Vec3::Y),
+            (Vec3::NEG_Z, Vec3::Y),
+        ];
+
+        let original_size = {
+            let desc = self.data.lock().gbuffers.shading_result.get_desc();
+            (desc.Width, desc.Height)
+        };
+
+        self.resize_buffers(size, size);
+        let result = self.probe(|| {
+            let mut camera = Camera::new_fps(Viewport {
+                origin: UVec2::ZERO,
+                size: UVec2::splat(size),
+            });
+            camera.set_position(position);
+            camera.update_matrices();
+
+            let mut faces: [Vec<[f32; 4]>; 6] = Default::default();
+            for (face, (forward, up)) in faces.iter_mut().zip(FACES) {
+                camera.set_view_matrix(Mat4::look_at_rh(position, position + forward, up));
+                self.render_world(&camera, scene, resources);
+
+                let captured = {
+                    let data = self.data.lock();
+                    let rt = &data.gbuffers.shading_result_read;
+                    self.read_back_texture("cubemap_face", &rt.texture, rt.format, (size, size))?
+                };
+                let CapturedPixels::Float(mut pixels) = captured.pixels else {
+                    anyhow::bail!("Unexpected shading result format {:?}", captured.format);
+                };
+
+                // The view is right handed, cubemap faces are left handed
+                for row in pixels.chunks_exact_mut(size as usize) {
+                    row.reverse();
+                }
+                *face = pixels;
+            }
+
+            Ok(faces)
+        });
+        self.resize_buffers(original_size.0, original_size.1);
+
+        Ok(CapturedCubemap {
+            position,
+            size,
+            faces: result?,
+        })
+    }
+
+    /// Renders `camera` into an image of the size of its viewport, without overlays and without
+    /// affecting the next frame
+    pub fn capture_view(
+        &self,
+        camera: &Camera,
+        scene: &mut Scene,
+        resources: &AppResources,
+    ) -> anyhow::Result<Png> {
+        let original_size = {
+            let desc = self.data.lock().gbuffers.shading_result.get_desc();
+            (desc.Width, desc.Height)
+        };
+
+        let size = camera.viewport().size;
+        self.resize_buffers(size.x, size.y);
+        let result = self.probe(|| {
+            self.render_world(camera, scene, resources);
+            self.capture_final_image()
+        });
+        self.resize_buffers(original_size.0, original_size.1);
+
+        result
+    }
+
+    /// Waits until every requested asset has been loaded, so a capture doesn't contain
+    /// placeholders. The renderer data is only locked while polling, so anything that needs it in
+    /// the meantime (eg. a map loader) can still make progress
+    pub fn wait_for_assets(&self) {
+        loop {
+            {
+                let mut data = self.data.lock();
+                data.asset_manager.poll();
+                if data.asset_manager.is_idle() {
+                    return;
+                }
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// Reads back the final image of the last rendered frame as 8-bit RGBA.
+    ///
+    /// This goes through the same blit as the swapchain output, so the result matches what would
+    /// be presented to a window.
+    pub fn capture_final_image(&self) -> anyhow::Result<Png> {
+        let data = self.data.lock();
+        let desc = data.gbuffers.shading_result.get_desc();
+        let size = (desc.Width, desc.Height);
+
+        let target = RenderTarget::create(
+            size,
+            DxgiFormat::R8G8B8A8_UNORM,
+            self.gpu.clone(),
+            "Capture",
+        )?;
+        let staging = CpuStagingBuffer::create(
+            size,
+            DxgiFormat::R8G8B8A8_UNORM,
+            self.gpu.clone(),
+            "Capture_Staging",
+        )?;
+
+        self.gpu.blit_texture(
+            &data.gbuffers.shading_result.view,
+            &target.render_target,
+            self.final_blit_srgb(),
+        );
+        target.copy_to_staging(&staging);
+
+        let row_size = size.0 as usize * 4;
+        let mut pixels = vec![0u8; row_size * size.1 as usize];
+        staging.map(D3D11_MAP_READ, |mapped| unsafe {
+            for (y, row) in pixels.chunks_exact_mut(row_size).enumerate() {
+                let src = (mapped.pData as *const u8).add(y * mapped.RowPitch as usize);
+                std::ptr::copy_nonoverlapping(src, row.as_mut_ptr(), row_size);
+            }
+        })?;
+
+        // The shading result doesn't have an alpha channel
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 0xff;
+        }
+
+        Ok(Png {
+            data: pixels.into(),
+            dimensions: [size.0 as usize, size.1 as usize],
+            color_type: ColorType::Rgba,
+            bit_depth: BitDepth::Eight,
+        })
+    }
+
+    /// Reads back every render target of the last rendered frame (GBuffer, lighting, shading
+    /// result, depth, pick buffer and shadow maps), for frame dumps
+    pub fn capture_render_targets(&self, scene: &mut Scene) -> anyhow::Result<Vec<CapturedTarget>> {
+        let mut captured = vec![];
+
+        {
+            let data = self.data.lock();
+            let gbuffers = &data.gbuffers;
+            let pick_targets = self.pickbuffer.targets.lock();
+            for (name, rt) in [
+                ("rt0_albedo", &gbuffers.rt0),
+                ("rt1_normal", &gbuffers.rt1),
+                ("rt2", &gbuffers.rt2),
+                ("rt3", &gbuffers.rt3),
+                ("light_diffuse", &gbuffers.light_diffuse),
+                ("light_specular", &gbuffers.light_specular),
+                ("light_ibl_specular", &gbuffers.light_ibl_specular),
+                ("ssao", &gbuffers.ssao_intermediate),
+                ("ssgi", &gbuffers.ssgi),
+                // Input for the final combine/debug view pass
+                ("shading_hdr", &gbuffers.shading_result_read),
+                ("shading_result", &gbuffers.shading_result),
+                ("pick_buffer", &pick_targets.pick_buffer),
+            ] {
+                let desc = rt.get_desc();
+                captured.push(self.read_back_texture(
+                    name,
+                    &rt.texture,
+                    rt.format,
+                    (desc.Width, desc.Height),
+                )?);
+            }
+
+            let desc = gbuffers.rt0.get_desc();
+            captured.push(self.read_back_texture(
+                "depth",
+                &gbuffers.depth.texture,
+                DxgiFormat::R32_TYPELESS,
+                (desc.Width, desc.Height),
+            )?);
+        }
+
+        for (entity, shadowmap) in scene.query::<(Entity, &ShadowMapRenderer)>().iter(scene) {
+            let resolution = shadowmap.resolution();
+            captured.push(self.read_back_texture(
+                &format!("shadowmap_{}", entity.index()),
+                &shadowmap.depth_map().texture,
+                DxgiFormat::R32_TYPELESS,
+                (resolution, resolution),
+            )?);
+        }
+
+        Ok(captured)
+    }
+
+    fn read_back_texture(
+        &self,
+        name: &str,
+        texture: &ID3D11Texture2D,
+        format: DxgiFormat,
+        size: (u32, u32),
+    ) -> anyhow::Result<CapturedTarget> {
+        let staging = CpuStagingBuffer::create(
+            size,
+            format,
+            self.gpu.clone(),
+            &format!("{name}_dump_staging"),
+        )?;
+        unsafe {
+            self.gpu
+                .lock_context()
+                .CopyResource(&staging.texture, texture);
+        }
+
+        let bytes_per_pixel = format.bpp() / 8;
+        let row_size = size.0 as usize * bytes_per_pixel;
+        let mut raw = vec![0u8; row_size * size.1 as usize];
+        staging.map(D3D11_MAP_READ, |mapped| unsafe {
+            for (y, row) in raw.chunks_exact_mut(row_size).enumerate() {
+                let src = (mapped.pData as *const u8).add(y * mapped.RowPitch as usize);
+                std::ptr::copy_nonoverlapping(src, row.as_mut_ptr(), row_size);
+            }
+        })?;
+
+        let words = || {
+            raw.chunks_exact(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        };
+        let unorm8 = |v: u8| v as f32 / 255.0;
+        let pixels = match format {
+            DxgiFormat::R32_UINT => CapturedPixels::Uint(words().collect()),
+            DxgiFormat::R32_TYPELESS | DxgiFormat::R32_FLOAT => CapturedPixels::Float(
+                words()
+                    .map(|w| [f32::from_bits(w), 0.0, 0.0, 1.0])
+                    .collect(),
+            ),
+            DxgiFormat::R8G8B8A8_UNORM | DxgiFormat::R8G8B8A8_UNORM_SRGB => CapturedPixels::Float(
+                raw.chunks_exact(4)
+                    .map(|c| [unorm8(c[0]), unorm8(c[1]), unorm8(c[2]), unorm8(c[3])])
+                    .collect(),
+            ),
+            DxgiFormat::B8G8R8A8_UNORM | DxgiFormat::B8G8R8A8_UNORM_SRGB => CapturedPixels::Float(
+                raw.chunks_exact(4)
+                    .map(|c| [unorm8(c[2]), unorm8(c[1]), unorm8(c[0]), unorm8(c[3])])
+                    .collect(),
+            ),
+            DxgiFormat::R8_UNORM => {
+                CapturedPixels::Float(raw.iter().map(|&v| [unorm8(v), 0.0, 0.0, 1.0]).collect())
+            }
+            DxgiFormat::R10G10B10A2_UNORM => CapturedPixels::Float(
+                words()
+                    .map(|w| {
+                        [
+                            (w & 0x3ff) as f32 / 1023.0,
+                            ((w >> 10) & 0x3ff) as f32 / 1023.0,
+                            ((w >> 20) & 0x3ff) as f32 / 1023.0,
+                            (w >> 30) as f32 / 3.0,
+                        ]
+                    })
+                    .collect(),
+            ),
+            DxgiFormat::R11G11B10_FLOAT => CapturedPixels::Float(
+                words()
+                    .map(|w| {
+                        [
+                            unpack_small_float(w & 0x7ff, 6),
+                            unpack_small_float((w >> 11) & 0x7ff, 6),
+                            unpack_small_float(w >> 22, 5),
+                            1.0,
+                        ]
+                    })
+                    .collect(),
+            ),
+            DxgiFormat::R16G16B16A16_FLOAT => CapturedPixels::Float(
+                raw.chunks_exact(8)
+                    .map(|c| {
+                        let h = |i: usize| f16_to_f32(u16::from_le_bytes([c[i], c[i + 1]]));
+                        [h(0), h(2), h(4), h(6)]
+                    })
+                    .collect(),
+            ),
+            u => anyhow::bail!("Unsupported render target format {u:?} for {name}"),
+        };
+
+        Ok(CapturedTarget {
+            name: name.to_string(),
+            format,
+            width: size.0,
+            height: size.1,
+            pixels,
+        })
+    }
+}
+
+/// Decodes an unsigned 11/10-bit float with a 5-bit exponent (as used by R11G11B10_FLOAT)
+fn unpack_small_float(bits: u32, mantissa_bits: u32) -> f32 {
+    let exponent = (bits >> mantissa_bits) & 0x1f;
+    let mantissa = (bits & ((1 << mantissa_bits) - 1)) as f32 / (1 << mantissa_bits) as f32;
+    match exponent {
+        0 => mantissa * 2f32.powi(-14),
+        31 => f32::INFINITY,
+        e => (1.0 + mantissa) * 2f32.powi(e as i32 - 15),
+    }
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent >= 31 {
+        // Too large, clamp to infinity
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // Denormals are too small to matter for lighting
+        sign
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32 / 1024.0;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-14),
+        31 => f32::INFINITY,
+        e => (1.0 + mantissa) * 2f32.powi(e - 15),
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    ffi::c_void,
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alkahest_data::tfx::TfxShaderStage;
+use alkahest_pm::package_manager;
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Mat4, Vec4};
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use windows::{
+    core::{s, Interface, PCSTR},
+    Win32::Graphics::{
+        Direct3D::{
+            Fxc::{D3DCompile, D3DReflect, D3DCOMPILE_OPTIMIZATION_LEVEL3},
+            ID3DBlob, D3D_NAME_CLIP_DISTANCE, D3D_NAME_CULL_DISTANCE, D3D_NAME_POSITION,
+            D3D_REGISTER_COMPONENT_SINT32, D3D_REGISTER_COMPONENT_UINT32,
+        },
+        Direct3D11::{
+            ID3D11GeometryShader, ID3D11ShaderReflection, D3D11_SHADER_DESC,
+            D3D11_SIGNATURE_PARAMETER_DESC,
+        },
+    },
+};
+
+use crate::{
+    ecs::{
+        transform::Transform,
+        utility::ClipPlane,
+        visibility::{Visibility, VisibilityHelper},
+        Scene,
+    },
+    gpu::{buffer::ConstantBuffer, SharedGpuContext},
+    renderer::Renderer,
+};
+
+/// Maximum number of clip planes that are applied at once, any planes after these are ignored
+pub const MAX_CLIP_PLANES: usize = 3;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestClipPlanes {
+    projective_to_world: Mat4,
+    /// Plane equations, unused planes never clip anything
+    planes: [Vec4; MAX_CLIP_PLANES],
+}
+
+/// Output element of a vertex shader, as found in its signature
+#[derive(Clone, PartialEq)]
+struct SignatureElement {
+    semantic_name: String,
+    semantic_index: u32,
+    register: u32,
+    mask: u8,
+    is_position: bool,
+    is_clip_distance: bool,
+    /// HLSL type of a single component
+    component_type: &'static str,
+}
+
+#[derive(Default)]
+struct ClipShaderCache {
+    /// Geometry shader for every vertex shader, `None` if the vertex shader can't be clipped
+    by_vertex_shader: FxHashMap<TagHash, Option<ID3D11GeometryShader>>,
+    /// Vertex shaders with the same outputs share their geometry shader
+    by_source: FxHashMap<String, Option<ID3D11GeometryShader>>,
+}
+
+/// Applies user-placed clip planes to scene geometry. The game's shaders are precompiled, so
+/// instead of patching them, every vertex shader gets a generated pass-through geometry shader
+/// that adds `SV_ClipDistance` to its outputs
+pub struct ClipPlaneRenderer {
+    gpu: SharedGpuContext,
+    scope: ConstantBuffer<ScopeAlkahestClipPlanes>,
+    /// Set while drawing the passes that are clipped
+    active: AtomicBool,
+    cache: Mutex<ClipShaderCache>,
+}
+
+impl ClipPlaneRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            scope: ConstantBuffer::create(gctx.clone(), None)?,
+            active: AtomicBool::new(false),
+            cache: Mutex::new(ClipShaderCache::default()),
+            gpu: gctx,
+        })
+    }
+
+    /// Binds the clipping geometry shader for the given vertex shader. Unbinds the geometry stage
+    /// when no planes are active, or when the vertex shader can't be clipped
+    pub fn bind_geometry_shader(&self, vertex_shader: TagHash) {
+        let shader = if self.active.load(Ordering::Relaxed) {
+            self.geometry_shader(vertex_shader)
+        } else {
+            None
+        };
+
+        if shader.is_some() {
+            // Techniques with their own geometry stage may have replaced the constant buffer
+            self.scope.bind(0, TfxShaderStage::Geometry);
+        }
+
+        unsafe {
+            self.gpu.lock_context().GSSetShader(shader.as_ref(), None);
+        }
+    }
+
+    fn geometry_shader(&self, vertex_shader: TagHash) -> Option<ID3D11GeometryShader> {
+        let mut cache = self.cache.lock();
+        if let Some(shader) = cache.by_vertex_shader.get(&vertex_shader) {
+            return shader.clone();
+        }
+
+        let shader = match self.create_geometry_shader(&mut cache, vertex_shader) {
+            Ok(shader) => shader,
+            Err(e) => {
+                warn!("Failed to create clip plane geometry shader for {vertex_shader}: {e:?}");
+                None
+            }
+        };
+
+        cache.by_vertex_shader.insert(vertex_shader, shader.clone());
+        shader
+    }
+
+    fn create_geometry_shader(
+        &self,
+        cache: &mut ClipShaderCache,
+        vertex_shader: TagHash,
+    ) -> anyhow::Result<Option<ID3D11GeometryShader>> {
+        let entry = package_manager()
+            .get_entry(vertex_shader)
+            .context("Entry not found")?;
+        let bytecode = package_manager()
+            .read_tag(entry.reference)
+            .context("Failed to read shader data")?;
+
+        let outputs = output_signature(&bytecode)?;
+        // Shaders that already clip would lose their own clip distances
+        if outputs.iter().any(|e| e.is_clip_distance) {
+            return Ok(None);
+        }
+        let Some(source) = generate_geometry_shader(&outputs) else {
+            return Ok(None);
+        };
+
+        if let Some(shader) = cache.by_source.get(&source) {
+            return Ok(shader.clone());
+        }
+
+        let shader = self.compile_geometry_shader(&source, &outputs)?;
+        cache.by_source.insert(source, shader.clone());
+        Ok(shader)
+    }
+
+    fn compile_geometry_shader(
+        &self,
+        source: &str,
+        vertex_outputs: &[SignatureElement],
+    ) -> anyhow::Result<Option<ID3D11GeometryShader>> {
+        let mut blob = None;
+        let mut errors = None;
+        let result = unsafe {
+            D3DCompile(
+                source.as_ptr() as _,
+                source.len(),
+                PCSTR::from_raw(c"alkahest_clip_planes".as_ptr() as _),
+                None,
+                None,
+                s!("GSMain"),
+                s!("gs_5_0"),
+                D3DCOMPILE_OPTIMIZATION_LEVEL3,
+                0,
+                &mut blob,
+                Some(&mut errors),
+            )
+        };
+
+        if let Err(e) = result {
+            let message = errors
+                .map(|b| String::from_utf8_lossy(blob_bytes(&b)).to_string())
+                .unwrap_or_default();
+            anyhow::bail!("{e}: {message}");
+        }
+
+        let blob = blob.context("No bytecode returned")?;
+        let bytecode = blob_bytes(&blob);
+
+        // The pixel shader reads its inputs by register, so those have to stay where the vertex
+        // shader put them
+        let outputs = output_signature(bytecode)?;
+        let layout_matches = vertex_outputs.iter().all(|v| {
+            outputs.iter().any(|g| {
+                g.semantic_name.eq_ignore_ascii_case(&v.semantic_name)
+                    && g.semantic_index == v.semantic_index
+                    && g.register == v.register
+                    && g.mask == v.mask
+            })
+        });
+        if !layout_matches {
+            return Ok(None);
+        }
+
+        let mut shader = None;
+        unsafe {
+            self.gpu
+                .device
+                .CreateGeometryShader(bytecode, None, Some(&mut shader))?;
+        }
+
+        Ok(shader)
+    }
+}
+
+fn blob_bytes(blob: &ID3DBlob) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    }
+}
+
+fn output_signature(bytecode: &[u8]) -> anyhow::Result<Vec<SignatureElement>> {
+    unsafe {
+        let mut reflector: *mut c_void = std::ptr::null_mut();
+        D3DReflect(
+            bytecode.as_ptr() as _,
+            bytecode.len(),
+            &ID3D11ShaderReflection::IID,
+            &mut reflector,
+        )
+        .context("Failed to reflect shader")?;
+        let reflection = ID3D11ShaderReflection::from_raw(reflector);
+
+        let mut desc = D3D11_SHADER_DESC::default();
+        reflection.GetDesc(&mut desc)?;
+
+        let mut elements = vec![];
+        for i in 0..desc.OutputParameters {
+            let mut param = D3D11_SIGNATURE_PARAMETER_DESC::default();
+            reflection.GetOutputParameterDesc(i, &mut param)?;
+            elements.push(SignatureElement {
+                semantic_name: param.SemanticName.to_string()?,
+                semantic_index: param.SemanticIndex,
+                register: param.Register,
+                mask: param.Mask,
+                is_position: param.SystemValueType == D3D_NAME_POSITION,
+                is_clip_distance: param.SystemValueType == D3D_NAME_CLIP_DISTANCE
+                    || param.SystemValueType == D3D_NAME_CULL_DISTANCE,
+                component_type: match param.ComponentType {
+                    D3D_REGISTER_COMPONENT_UINT32 => "uint",
+                    D3D_REGISTER_COMPONENT_SINT32 => "int",
+                    _ => "float",
+                },
+            });
+        }
+
+        Ok(elements)
+    }
+}
+
+/// Generates a geometry shader that passes the vertex shader outputs through unchanged, adding the
+/// distance to every clip plane. Returns `None` for vertex shaders without a position output
+fn generate_geometry_shader(outputs: &[SignatureElement]) -> Option<String> {
+    let position = outputs.iter().position(|e| e.is_position)?;
+
+    // Declaring the elements in register order makes the compiler pack them the same way
+    let mut elements = outputs.iter().enumerate().collect::<Vec<_>>();
+    elements.sort_by_key(|(_, e)| (e.register, e.mask.trailing_zeros()));
+
+    let mut fields = String::new();
+    let mut copies = String::new();
+    for (i, e) in elements {
+        let components = if e.is_position {
+            4
+        } else {
+            e.mask.count_ones()
+        };
+        let ty = if components == 1 {
+            e.component_type.to_string()
+        } else {
+            format!("{}{components}", e.component_type)
+        };
+        writeln!(
+            fields,
+            "    {ty} v{i} : {}{};",
+            e.semantic_name, e.semantic_index
+        )
+        .ok();
+        writeln!(copies, "        output.v{i} = input[i].v{i};").ok();
+    }
+
+    Some(format!(
+        r#"cbuffer scope_alkahest_clip_planes : register(b0) {{
+    float4x4 projective_to_world;
+    float4 planes[{MAX_CLIP_PLANES}];
+}};
+
+struct Vertex {{
+{fields}}};
+
+struct ClippedVertex {{
+{fields}    float3 clip_distance : SV_ClipDistance0;
+}};
+
+[maxvertexcount(3)]
+void GSMain(triangle Vertex input[3], inout TriangleStream<ClippedVertex> stream) {{
+    for (uint i = 0; i < 3; i++) {{
+        ClippedVertex output;
+{copies}
+        float4 world = mul(projective_to_world, input[i].v{position});
+        world /= world.w;
+        output.clip_distance = float3(
+            dot(planes[0], world),
+            dot(planes[1], world),
+            dot(planes[2], world)
+        );
+        stream.Append(output);
+    }}
+}}
+"#
+    ))
+}
+
+impl Renderer {
+    /// Equations of the clip planes in the scene that are not hidden
+    fn clip_planes(&self, scene: &Scene) -> Vec<Vec4> {
+        scene
+            .query::<(&Transform, &ClipPlane, Option<&Visibility>)>()
+            .iter(scene)
+            .filter(|(_, _, vis)| vis.is_visible(self.active_view()))
+            .map(|(transform, _, _)| ClipPlane::equation(transform))
+            .take(MAX_CLIP_PLANES)
+            .collect()
+    }
+
+    /// Starts clipping the geometry drawn by techniques against the clip planes in the scene.
+    /// Expects the view to be bound
+    pub(super) fn begin_clip_planes(&self, scene: &Scene) {
+        if self.is_probing() {
+            return;
+        }
+
+        let planes = self.clip_planes(scene);
+        if planes.is_empty() {
+            return;
+        }
+
+        let Some(view) = self.data.lock().externs.view.clone() else {
+            return;
+        };
+
+        // A plane with only a w component keeps everything
+        let mut scope = ScopeAlkahestClipPlanes {
+            projective_to_world: view.projective_to_world,
+            planes: [Vec4::W; MAX_CLIP_PLANES],
+        };
+        scope.planes[..planes.len()].copy_from_slice(&planes);
+
+        if self.clip_planes.scope.write(&scope).is_ok() {
+            self.clip_planes.active.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub(super) fn end_clip_planes(&self) {
+        if self.clip_planes.active.swap(false, Ordering::Relaxed) {
+            unsafe {
+                self.gpu.lock_context().GSSetShader(None, None);
+            }
+        }
+    }
+}
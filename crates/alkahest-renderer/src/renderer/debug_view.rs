@@ -0,0 +1,357 @@
+use alkahest_data::{
+    geometry::EPrimitiveType,
+    technique::StateSelection,
+    tfx::{TfxRenderStage, TfxShaderStage},
+};
+use anyhow::Context;
+use glam::Mat4;
+use parking_lot::Mutex;
+use windows::Win32::Graphics::{
+    Direct3D11::{
+        ID3D11PixelShader, ID3D11ShaderResourceView, ID3D11UnorderedAccessView, ID3D11VertexShader,
+        D3D11_BIND_SHADER_RESOURCE, D3D11_BIND_UNORDERED_ACCESS, D3D11_TEXTURE2D_DESC,
+        D3D11_USAGE_DEFAULT,
+    },
+    Dxgi::Common::{DXGI_FORMAT_R32_UINT, DXGI_SAMPLE_DESC},
+};
+
+use crate::{
+    ecs::{map::CubemapVolume, transform::Transform, Scene},
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_profile_event, include_dxbc,
+    renderer::{gbuffer::DepthState, Renderer},
+    util::d3d::D3dResource,
+};
+
+const MAX_CUBEMAP_VOLUMES: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestCubemapInfluence {
+    target_pixel_to_world: Mat4,
+    volume_count: u32,
+    _pad: [u32; 3],
+    world_to_volume: [Mat4; MAX_CUBEMAP_VOLUMES],
+}
+
+/// Per-pixel fragment counter for the overdraw view, with its own depth buffer so the scene depth
+/// is left alone
+struct OverdrawTargets {
+    size: (u32, u32),
+    counts_uav: ID3D11UnorderedAccessView,
+    counts_view: ID3D11ShaderResourceView,
+    depth: DepthState,
+}
+
+impl OverdrawTargets {
+    fn create(gctx: SharedGpuContext, size: (u32, u32)) -> anyhow::Result<Self> {
+        let mut counts = None;
+        let mut counts_uav = None;
+        let mut counts_view = None;
+        unsafe {
+            gctx.device
+                .CreateTexture2D(
+                    &D3D11_TEXTURE2D_DESC {
+                        Width: size.0,
+                        Height: size.1,
+                        MipLevels: 1,
+                        ArraySize: 1,
+                        Format: DXGI_FORMAT_R32_UINT,
+                        SampleDesc: DXGI_SAMPLE_DESC {
+                            Count: 1,
+                            Quality: 0,
+                        },
+                        Usage: D3D11_USAGE_DEFAULT,
+                        BindFlags: (D3D11_BIND_UNORDERED_ACCESS.0 | D3D11_BIND_SHADER_RESOURCE.0)
+                            as u32,
+                        CPUAccessFlags: Default::default(),
+                        MiscFlags: Default::default(),
+                    },
+                    None,
+                    Some(&mut counts),
+                )
+                .context("Failed to create overdraw texture")?;
+            let counts = counts.unwrap();
+            counts.set_debug_name("Overdraw_Counts");
+
+            gctx.device
+                .CreateUnorderedAccessView(&counts, None, Some(&mut counts_uav))
+                .context("Failed to create overdraw UAV")?;
+            gctx.device
+                .CreateShaderResourceView(&counts, None, Some(&mut counts_view))
+                .context("Failed to create overdraw SRV")?;
+
+            Ok(Self {
+                size,
+                counts_uav: counts_uav.unwrap(),
+                counts_view: counts_view.unwrap(),
+                depth: DepthState::create(gctx, size, "overdraw_depth")?,
+            })
+        }
+    }
+}
+
+/// Debug views that aren't backed by a game technique, and the split-screen debug view compare
+pub struct DebugViewRenderer {
+    gctx: SharedGpuContext,
+    shader_vs: ID3D11VertexShader,
+    shader_cubemap_influence_ps: ID3D11PixelShader,
+    shader_overdraw_count_ps: ID3D11PixelShader,
+    shader_overdraw_heatmap_ps: ID3D11PixelShader,
+    shader_split_ps: ID3D11PixelShader,
+
+    cubemap_influence_cb: ConstantBuffer<ScopeAlkahestCubemapInfluence>,
+    overdraw_cb: ConstantBuffer<[u32; 4]>,
+    split_cb: ConstantBuffer<[f32; 4]>,
+
+    /// Only created once the overdraw view is used
+    overdraw_targets: Mutex<Option<OverdrawTargets>>,
+}
+
+impl DebugViewRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        let shader_vs = gctx
+            .device
+            .load_vertex_shader(include_dxbc!(vs "debug/debug_view_split.hlsl"))
+            .unwrap();
+        let shader_cubemap_influence_ps = gctx
+            .device
+            .load_pixel_shader(include_dxbc!(ps "debug/cubemap_influence.hlsl"))
+            .unwrap();
+        let shader_overdraw_count_ps = gctx
+            .device
+            .load_pixel_shader(include_dxbc!(ps "debug/overdraw_count.hlsl"))
+            .unwrap();
+        let shader_overdraw_heatmap_ps = gctx
+            .device
+            .load_pixel_shader(include_dxbc!(ps "debug/overdraw_heatmap.hlsl"))
+            .unwrap();
+        let shader_split_ps = gctx
+            .device
+            .load_pixel_shader(include_dxbc!(ps "debug/debug_view_split.hlsl"))
+            .unwrap();
+
+        Ok(Self {
+            shader_vs,
+            shader_cubemap_influence_ps,
+            shader_overdraw_count_ps,
+            shader_overdraw_heatmap_ps,
+            shader_split_ps,
+            cubemap_influence_cb: ConstantBuffer::create(gctx.clone(), None)?,
+            overdraw_cb: ConstantBuffer::create(gctx.clone(), None)?,
+            split_cb: ConstantBuffer::create(gctx.clone(), None)?,
+            overdraw_targets: Mutex::new(None),
+            gctx,
+        })
+    }
+
+    /// Colors every pixel by the cubemap volumes that cover it, weighted by how far the pixel is from the volume edges
+    pub fn draw_cubemap_influence(&self, renderer: &Renderer, scene: &mut Scene) {
+        let mut scope = ScopeAlkahestCubemapInfluence {
+            target_pixel_to_world: Mat4::IDENTITY,
+            volume_count: 0,
+            _pad: [0; 3],
+            world_to_volume: [Mat4::IDENTITY; MAX_CUBEMAP_VOLUMES],
+        };
+
+        if let Some(view) = &renderer.data.lock().externs.view {
+            scope.target_pixel_to_world = view.target_pixel_to_world;
+        } else {
+            return;
+        }
+
+        // Volumes past MAX_CUBEMAP_VOLUMES are not visualized
+        for (transform, cubemap) in scene
+            .query::<(&Transform, &CubemapVolume)>()
+            .iter(scene)
+            .take(MAX_CUBEMAP_VOLUMES)
+        {
+            scope.world_to_volume[scope.volume_count as usize] =
+                Mat4::from_scale_rotation_translation(
+                    cubemap.extents,
+                    transform.rotation,
+                    transform.translation,
+                )
+                .inverse();
+            scope.volume_count += 1;
+        }
+
+        if let Err(e) = self.cubemap_influence_cb.write(&scope) {
+            error!("Failed to write cubemap influence constants: {e:?}");
+            return;
+        }
+        self.cubemap_influence_cb.bind(0, TfxShaderStage::Pixel);
+
+        unsafe {
+            renderer.gpu.lock_context().PSSetShaderResources(
+                0,
+                Some(&[Some(
+                    renderer.data.lock().gbuffers.depth.texture_view.clone(),
+                )]),
+            );
+        }
+
+        self.draw_fullscreen(renderer, &self.shader_cubemap_influence_ps);
+    }
+
+    /// Colors every pixel by the number of fragments counted by [`Renderer::draw_overdraw_counts`]
+    pub fn draw_overdraw(&self, renderer: &Renderer) {
+        let Some(counts_view) = self
+            .overdraw_targets
+            .lock()
+            .as_ref()
+            .map(|t| t.counts_view.clone())
+        else {
+            return;
+        };
+
+        let max_overdraw = renderer.settings().overdraw_max.max(1);
+        if let Err(e) = self.overdraw_cb.write(&[max_overdraw, 0, 0, 0]) {
+            error!("Failed to write overdraw constants: {e:?}");
+            return;
+        }
+        self.overdraw_cb.bind(0, TfxShaderStage::Pixel);
+
+        unsafe {
+            renderer
+                .gpu
+                .lock_context()
+                .PSSetShaderResources(0, Some(&[Some(counts_view)]));
+        }
+
+        self.draw_fullscreen(renderer, &self.shader_overdraw_heatmap_ps);
+
+        unsafe {
+            renderer
+                .gpu
+                .lock_context()
+                .PSSetShaderResources(0, Some(&[None]));
+        }
+    }
+
+    /// Draws the debug view captured in `gbuffers.debug_view_compare` over the left `split` fraction of the final image
+    pub fn draw_split(&self, renderer: &Renderer, split: f32) {
+        if let Err(e) = self.split_cb.write(&[split, 0.0, 0.0, 0.0]) {
+            error!("Failed to write debug view split constants: {e:?}");
+            return;
+        }
+        self.split_cb.bind(0, TfxShaderStage::Pixel);
+
+        unsafe {
+            renderer.gpu.lock_context().PSSetShaderResources(
+                0,
+                Some(&[Some(
+                    renderer
+                        .data
+                        .lock()
+                        .gbuffers
+                        .debug_view_compare
+                        .view
+                        .clone(),
+                )]),
+            );
+        }
+
+        self.draw_fullscreen(renderer, &self.shader_split_ps);
+    }
+
+    fn draw_fullscreen(&self, renderer: &Renderer, shader_ps: &ID3D11PixelShader) {
+        renderer
+            .gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+        renderer.gpu.flush_states();
+
+        unsafe {
+            renderer.gpu.lock_context().RSSetState(None);
+            renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+            renderer.gpu.lock_context().OMSetDepthStencilState(None, 0);
+            renderer
+                .gpu
+                .lock_context()
+                .VSSetShader(&self.shader_vs, None);
+            renderer.gpu.lock_context().PSSetShader(shader_ps, None);
+
+            renderer.gpu.lock_context().Draw(3, 0);
+        }
+    }
+}
+
+impl Renderer {
+    /// Draws the opaque, decal and transparent stages again, counting the fragments written to
+    /// every pixel. The opaque stage fills a separate depth buffer in the same order as the actual
+    /// pass, so only fragments that pass the depth test are counted
+    pub(super) fn draw_overdraw_counts(&self, scene: &mut Scene) {
+        gpu_profile_event!(self.gpu, "overdraw");
+
+        let size = {
+            let desc = self.data.lock().gbuffers.rt0.get_desc();
+            (desc.Width, desc.Height)
+        };
+
+        let (counts_uav, depth_view) = {
+            let mut targets = self.debug_views.overdraw_targets.lock();
+            if targets.as_ref().map_or(true, |t| t.size != size) {
+                match OverdrawTargets::create(self.debug_views.gctx.clone(), size) {
+                    Ok(t) => *targets = Some(t),
+                    Err(e) => {
+                        error!("Failed to create overdraw targets: {e:?}");
+                        *targets = None;
+                        return;
+                    }
+                }
+            }
+
+            let targets = targets.as_ref().unwrap();
+            targets.depth.clear(0.0, 0);
+            (targets.counts_uav.clone(), targets.depth.view.clone())
+        };
+
+        let dxstate = self.gpu.backup_state();
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.ClearUnorderedAccessViewUint(&counts_uav, &[0; 4]);
+            ctx.OMSetRenderTargetsAndUnorderedAccessViews(
+                None,
+                &depth_view,
+                0,
+                1,
+                Some(&Some(counts_uav)),
+                None,
+            );
+        }
+
+        self.gpu
+            .bind_pixel_shader(&self.debug_views.shader_overdraw_count_ps);
+        *self.gpu.custom_pixel_shader.write() =
+            Some(self.debug_views.shader_overdraw_count_ps.clone());
+
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(2), Some(2), Some(0)));
+        self.gpu.flush_states();
+        self.run_renderstage_systems(scene, TfxRenderStage::GenerateGbuffer);
+
+        for stage in [
+            TfxRenderStage::Decals,
+            TfxRenderStage::DecalsAdditive,
+            TfxRenderStage::Transparents,
+        ] {
+            self.gpu
+                .current_states
+                .store(StateSelection::new(Some(8), Some(15), Some(2), Some(1)));
+            self.gpu.flush_states();
+            self.run_renderstage_systems(scene, stage);
+        }
+
+        *self.gpu.custom_pixel_shader.write() = None;
+        unsafe {
+            // The counter texture can't be read while it's still bound as a UAV
+            self.gpu
+                .lock_context()
+                .OMSetRenderTargetsAndUnorderedAccessViews(None, None, 0, 1, Some(&None), None);
+        }
+        self.gpu.restore_state(&dxstate);
+        self.gpu.flush_states();
+    }
+}
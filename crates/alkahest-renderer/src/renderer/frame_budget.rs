@@ -0,0 +1,186 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::renderer::{RenderFeatureVisibility, Renderer, RendererSettings};
+
+/// Frame time has to stay over the target for this long before another feature is degraded
+const DEGRADE_DELAY: Duration = Duration::from_secs(1);
+/// Restoring a feature waits longer, so the limiter doesn't flip back and forth between two levels
+const RESTORE_DELAY: Duration = Duration::from_secs(4);
+/// A feature is only restored when the frame time is below this fraction of the target
+const RESTORE_HEADROOM: f64 = 0.75;
+/// Weight of every new frame in the average frame time
+const AVERAGE_WEIGHT: f64 = 0.05;
+
+/// Features the frame budget limiter degrades, in the order they are degraded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, strum::Display)]
+pub enum BudgetDegradation {
+    #[strum(to_string = "SSAO")]
+    Ssao,
+    #[strum(to_string = "Shadow updates")]
+    ShadowUpdates,
+    #[strum(to_string = "Decorators")]
+    Decorators,
+    #[strum(to_string = "Cubemaps")]
+    Cubemaps,
+}
+
+impl BudgetDegradation {
+    /// Whether degrading this feature would make a difference with the given settings
+    fn applies_to(self, settings: &RendererSettings) -> bool {
+        match self {
+            BudgetDegradation::Ssao => settings.ssao,
+            BudgetDegradation::ShadowUpdates => {
+                settings.shadows_enabled() && settings.shadow_updates_per_frame > 1
+            }
+            BudgetDegradation::Decorators => !settings.feature_decorators.is_empty(),
+            BudgetDegradation::Cubemaps => settings.feature_cubemaps,
+        }
+    }
+
+    fn apply(self, settings: &mut RendererSettings) {
+        match self {
+            BudgetDegradation::Ssao => settings.ssao = false,
+            BudgetDegradation::ShadowUpdates => settings.shadow_updates_per_frame = 1,
+            BudgetDegradation::Decorators => {
+                settings.feature_decorators = RenderFeatureVisibility::empty()
+            }
+            BudgetDegradation::Cubemaps => settings.feature_cubemaps = false,
+        }
+    }
+}
+
+pub struct FrameBudget {
+    /// Settings as they were set, before any features were degraded
+    requested: Arc<RendererSettings>,
+    /// Moving average of the frame time, in seconds
+    average_frame_time: f64,
+    /// Number of features that are currently degraded, out of the ones that apply to the
+    /// requested settings
+    level: usize,
+    last_change: Instant,
+}
+
+impl Default for FrameBudget {
+    fn default() -> Self {
+        Self {
+            requested: Arc::new(RendererSettings::default()),
+            average_frame_time: 0.0,
+            level: 0,
+            last_change: Instant::now(),
+        }
+    }
+}
+
+impl FrameBudget {
+    fn applicable(&self) -> Vec<BudgetDegradation> {
+        BudgetDegradation::iter()
+            .filter(|d| d.applies_to(&self.requested))
+            .collect()
+    }
+
+    fn degraded(&self) -> Vec<BudgetDegradation> {
+        let mut applicable = self.applicable();
+        applicable.truncate(self.level);
+        applicable
+    }
+
+    /// The requested settings with every degraded feature turned down
+    fn effective_settings(&self) -> Arc<RendererSettings> {
+        if self.level == 0 {
+            return self.requested.clone();
+        }
+
+        let mut settings = (*self.requested).clone();
+        for degradation in self.degraded() {
+            degradation.apply(&mut settings);
+        }
+        Arc::new(settings)
+    }
+
+    /// Returns true if the level has changed
+    fn update(&mut self, frame_time: f64) -> bool {
+        if !self.requested.frame_budget || self.requested.frame_budget_fps <= 0.0 {
+            self.average_frame_time = 0.0;
+            return std::mem::take(&mut self.level) != 0;
+        }
+
+        if self.average_frame_time == 0.0 {
+            self.average_frame_time = frame_time;
+        } else {
+            self.average_frame_time += (frame_time - self.average_frame_time) * AVERAGE_WEIGHT;
+        }
+
+        let target = 1.0 / self.requested.frame_budget_fps as f64;
+        let since_change = self.last_change.elapsed();
+        let applicable = self.applicable().len();
+        self.level = self.level.min(applicable);
+
+        let new_level = if self.average_frame_time > target
+            && since_change > DEGRADE_DELAY
+            && self.level < applicable
+        {
+            self.level + 1
+        } else if self.average_frame_time < target * RESTORE_HEADROOM
+            && since_change > RESTORE_DELAY
+            && self.level > 0
+        {
+            self.level - 1
+        } else {
+            return false;
+        };
+
+        self.level = new_level;
+        self.last_change = Instant::now();
+        true
+    }
+}
+
+impl Renderer {
+    /// Features that are currently turned down to stay within the frame budget, in the order
+    /// they were degraded
+    pub fn frame_budget_degradations(&self) -> Vec<BudgetDegradation> {
+        self.frame_budget.lock().degraded()
+    }
+
+    /// Keeps the next frame from counting towards the frame budget, for frames that are slowed
+    /// down on purpose (eg. when the window is out of focus)
+    pub fn skip_frame_budget(&self) {
+        self.frame_budget_skip.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_render_settings(&self, settings: RendererSettings) {
+        let mut budget = self.frame_budget.lock();
+        budget.requested = Arc::new(settings);
+        *self.settings.write() = budget.effective_settings();
+    }
+
+    pub(super) fn update_frame_budget(&self, frame_time: f64) {
+        if self.frame_budget_skip.swap(false, Ordering::Relaxed) {
+            return;
+        }
+
+        let mut budget = self.frame_budget.lock();
+        if budget.update(frame_time) {
+            let degraded = budget.degraded();
+            if degraded.is_empty() {
+                info!("Frame budget: all features restored");
+            } else {
+                info!(
+                    "Frame budget: degraded {}",
+                    degraded
+                        .iter()
+                        .map(|d| d.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            *self.settings.write() = budget.effective_settings();
+        }
+    }
+}
@@ -10,7 +10,10 @@ use windows::Win32::Graphics::{
     Dxgi::Common::*,
 };
 
-use crate::{camera::Camera, gpu::SharedGpuContext, gpu_event, util::d3d::D3dResource};
+use crate::{
+    camera::Camera, gpu::SharedGpuContext, gpu_event, postprocess::bloom::BLOOM_MAX_LEVELS,
+    util::d3d::D3dResource,
+};
 
 pub struct GBuffer {
     pub rt0: RenderTarget,
@@ -25,10 +28,22 @@ pub struct GBuffer {
 
     pub shading_result: RenderTarget,
     pub shading_result_read: RenderTarget,
+    /// Debug view output, composited over the final image when comparing the two
+    pub debug_view_compare: RenderTarget,
+    /// UI drawn with premultiplied alpha, composited onto HDR swapchains
+    pub ui_overlay: RenderTarget,
     pub depth: DepthState,
     pub depth_staging: CpuStagingBuffer,
 
     pub ssao_intermediate: RenderTarget,
+    /// Half resolution bounce lighting, accumulated over time
+    pub ssgi: RenderTarget,
+    /// Accumulated bounce lighting of the previous frame
+    pub ssgi_history: RenderTarget,
+    /// Shading result of the previous frame, reflections are shaded with it
+    pub ssr_history: RenderTarget,
+    /// Half resolution in-scattered light (rgb) and transmittance (a) of the volumetric fog
+    pub volumetrics: RenderTarget,
     pub atmos_ss_far_lookup: RenderTarget,
     pub atmos_ss_near_lookup: RenderTarget,
 
@@ -38,9 +53,51 @@ pub struct GBuffer {
     pub postprocess_pong: RenderTarget,
     postprocess_pingpong: AtomicCell<PingPong>,
 
+    pub smaa_edges: RenderTarget,
+    pub smaa_weights: RenderTarget,
+    /// Output of the previous frame's TAA resolve
+    pub taa_history: RenderTarget,
+
+    /// Thresholded and downsampled shading result, from half resolution downwards
+    pub bloom_downsample: Vec<RenderTarget>,
+    /// Bloom levels with all the levels below them accumulated, same sizes as `bloom_downsample`
+    pub bloom_upsample: Vec<RenderTarget>,
+
     current_size: (u32, u32),
 }
 
+/// Size of a bloom level, level 0 being half of the full resolution
+fn bloom_level_size(size: (u32, u32), level: usize) -> (u32, u32) {
+    (
+        (size.0 >> (level + 1)).max(1),
+        (size.1 >> (level + 1)).max(1),
+    )
+}
+
+/// SSGI and volumetrics are traced at half resolution
+fn half_size(size: (u32, u32)) -> (u32, u32) {
+    ((size.0 / 2).max(1), (size.1 / 2).max(1))
+}
+
+fn create_bloom_chain(
+    size: (u32, u32),
+    gctx: &SharedGpuContext,
+    name: &str,
+) -> anyhow::Result<Vec<RenderTarget>> {
+    (0..BLOOM_MAX_LEVELS)
+        .map(|level| {
+            let name = format!("{name}_{level}");
+            RenderTarget::create(
+                bloom_level_size(size, level),
+                DxgiFormat::R11G11B10_FLOAT,
+                gctx.clone(),
+                &name,
+            )
+            .context(name)
+        })
+        .collect()
+}
+
 #[derive(PartialEq, Clone, Copy)]
 enum PingPong {
     Ping,
@@ -117,6 +174,20 @@ impl GBuffer {
                 "Staging_Clone",
             )
             .context("Staging_Clone")?,
+            debug_view_compare: RenderTarget::create(
+                size,
+                DxgiFormat::R11G11B10_FLOAT,
+                gctx.clone(),
+                "Debug_View_Compare",
+            )
+            .context("Debug_View_Compare")?,
+            ui_overlay: RenderTarget::create(
+                size,
+                DxgiFormat::R8G8B8A8_UNORM,
+                gctx.clone(),
+                "UI_Overlay",
+            )
+            .context("UI_Overlay")?,
             depth: DepthState::create(gctx.clone(), size, "gbuffer_depth").context("Depth")?,
             depth_staging: CpuStagingBuffer::create(
                 size,
@@ -132,6 +203,34 @@ impl GBuffer {
                 "SSAO_Intermediate",
             )
             .context("SSAO_Intermediate")?,
+            ssgi: RenderTarget::create(
+                half_size(size),
+                DxgiFormat::R11G11B10_FLOAT,
+                gctx.clone(),
+                "SSGI",
+            )
+            .context("SSGI")?,
+            ssgi_history: RenderTarget::create(
+                half_size(size),
+                DxgiFormat::R11G11B10_FLOAT,
+                gctx.clone(),
+                "SSGI_History",
+            )
+            .context("SSGI_History")?,
+            ssr_history: RenderTarget::create(
+                size,
+                DxgiFormat::R11G11B10_FLOAT,
+                gctx.clone(),
+                "SSR_History",
+            )
+            .context("SSR_History")?,
+            volumetrics: RenderTarget::create(
+                half_size(size),
+                DxgiFormat::R16G16B16A16_FLOAT,
+                gctx.clone(),
+                "Volumetrics",
+            )
+            .context("Volumetrics")?,
 
             atmos_ss_far_lookup: RenderTarget::create(
                 (size.0 / 4, size.1 / 4),
@@ -172,6 +271,31 @@ impl GBuffer {
             .context("postprocess_ping")?,
             postprocess_pingpong: AtomicCell::new(PingPong::Ping),
 
+            smaa_edges: RenderTarget::create(
+                size,
+                DxgiFormat::R8G8_UNORM,
+                gctx.clone(),
+                "SMAA_Edges",
+            )
+            .context("SMAA_Edges")?,
+            smaa_weights: RenderTarget::create(
+                size,
+                DxgiFormat::R8G8B8A8_UNORM,
+                gctx.clone(),
+                "SMAA_Weights",
+            )
+            .context("SMAA_Weights")?,
+            taa_history: RenderTarget::create(
+                size,
+                DxgiFormat::R8G8B8A8_UNORM_SRGB,
+                gctx.clone(),
+                "TAA_History",
+            )
+            .context("TAA_History")?,
+
+            bloom_downsample: create_bloom_chain(size, &gctx, "Bloom_Downsample")?,
+            bloom_upsample: create_bloom_chain(size, &gctx, "Bloom_Upsample")?,
+
             current_size: size,
         })
     }
@@ -201,6 +325,10 @@ impl GBuffer {
         self.shading_result_read
             .resize(new_size)
             .context("Staging_Clone")?;
+        self.debug_view_compare
+            .resize(new_size)
+            .context("Debug_View_Compare")?;
+        self.ui_overlay.resize(new_size).context("UI_Overlay")?;
         self.depth.resize(new_size).context("Depth")?;
         self.depth_staging.resize(new_size).context("Depth")?;
 
@@ -209,10 +337,31 @@ impl GBuffer {
         self.atmos_ss_far_lookup
             .resize((new_size.0 / 4, new_size.1 / 4))?;
         self.ssao_intermediate.resize(new_size)?;
+        self.ssgi.resize(half_size(new_size)).context("SSGI")?;
+        self.ssgi_history
+            .resize(half_size(new_size))
+            .context("SSGI_History")?;
+        self.ssr_history.resize(new_size).context("SSR_History")?;
+        self.volumetrics
+            .resize(half_size(new_size))
+            .context("Volumetrics")?;
 
         self.postprocess_ping.resize(new_size)?;
         self.postprocess_pong.resize(new_size)?;
 
+        self.smaa_edges.resize(new_size).context("SMAA_Edges")?;
+        self.smaa_weights.resize(new_size).context("SMAA_Weights")?;
+        self.taa_history.resize(new_size).context("TAA_History")?;
+        for (i, rt) in self
+            .bloom_downsample
+            .iter_mut()
+            .chain(self.bloom_upsample.iter_mut())
+            .enumerate()
+        {
+            rt.resize(bloom_level_size(new_size, i % BLOOM_MAX_LEVELS))
+                .context("Bloom")?;
+        }
+
         self.current_size = new_size;
         Ok(())
     }
@@ -270,6 +419,76 @@ impl GBuffer {
             PingPong::Pong => &self.postprocess_pong,
         }
     }
+
+    /// Every target that can be shown in the render target viewer, in the order they're drawn
+    pub fn inspectable_targets(&self) -> Vec<InspectableTarget> {
+        let mut targets: Vec<_> = [
+            ("RT0 (albedo)", &self.rt0),
+            ("RT1 (normal, smoothness)", &self.rt1),
+            ("RT2 (metal, emission, transmission)", &self.rt2),
+            ("RT3", &self.rt3),
+            ("SSAO", &self.ssao_intermediate),
+            ("SSGI", &self.ssgi),
+            ("Volumetrics", &self.volumetrics),
+            ("Light diffuse", &self.light_diffuse),
+            ("Light specular", &self.light_specular),
+            ("Specular IBL", &self.light_ibl_specular),
+            ("Atmosphere far lookup", &self.atmos_ss_far_lookup),
+            ("Atmosphere near lookup", &self.atmos_ss_near_lookup),
+            (
+                "Depth angle density lookup",
+                &self.depth_angle_density_lookup,
+            ),
+            ("Shading result", &self.shading_result_read),
+            ("SMAA edges", &self.smaa_edges),
+            ("SMAA weights", &self.smaa_weights),
+            ("TAA history", &self.taa_history),
+        ]
+        .into_iter()
+        .map(|(name, rt)| rt.inspectable(name))
+        .collect();
+
+        targets.insert(
+            4,
+            InspectableTarget {
+                name: "Depth".to_string(),
+                view: self.depth.texture_view.clone(),
+                kind: InspectableKind::Color,
+                size: self.current_size,
+                format: DxgiFormat::R32_FLOAT,
+            },
+        );
+
+        for (i, rt) in self.bloom_downsample.iter().enumerate() {
+            targets.push(rt.inspectable(&format!("Bloom downsample {i}")));
+        }
+        for (i, rt) in self.bloom_upsample.iter().enumerate() {
+            targets.push(rt.inspectable(&format!("Bloom upsample {i}")));
+        }
+
+        targets
+    }
+}
+
+/// How the render target viewer samples a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectableKind {
+    /// Float or normalized channels
+    Color,
+    /// Integer IDs, shown as a distinct color for every value
+    Uint,
+    /// Array of depth layers, shown one layer at a time
+    DepthArray { layers: u32 },
+}
+
+/// A texture that can be shown in the render target viewer
+#[derive(Clone)]
+pub struct InspectableTarget {
+    pub name: String,
+    pub view: ID3D11ShaderResourceView,
+    pub kind: InspectableKind,
+    pub size: (u32, u32),
+    pub format: DxgiFormat,
 }
 
 pub struct RenderTarget {
@@ -415,6 +634,21 @@ impl RenderTarget {
         }
     }
 
+    pub fn inspectable(&self, name: &str) -> InspectableTarget {
+        let desc = self.get_desc();
+        InspectableTarget {
+            name: name.to_string(),
+            view: self.view.clone(),
+            kind: if self.format == DxgiFormat::R32_UINT {
+                InspectableKind::Uint
+            } else {
+                InspectableKind::Color
+            },
+            size: (desc.Width, desc.Height),
+            format: self.format,
+        }
+    }
+
     /// Binds this render target to RT0, disabling depth/stencil and adjusting the viewport
     pub fn bind(&self) {
         unsafe {
@@ -758,6 +992,20 @@ pub struct ShadowDepthMap {
 }
 
 impl ShadowDepthMap {
+    pub fn inspectable(&self, name: &str) -> InspectableTarget {
+        let mut desc = Default::default();
+        unsafe { self.texture.GetDesc(&mut desc) };
+        InspectableTarget {
+            name: name.to_string(),
+            view: self.texture_view.clone(),
+            kind: InspectableKind::DepthArray {
+                layers: self.layers as u32,
+            },
+            size: (desc.Width, desc.Height),
+            format: DxgiFormat::R32_FLOAT,
+        }
+    }
+
     pub fn create(size: (u32, u32), layers: usize, device: &ID3D11Device) -> anyhow::Result<Self> {
         let mut texture = None;
         unsafe {
@@ -9,7 +9,10 @@ use glam::{Mat4, Vec3, Vec4};
 use parking_lot::Mutex;
 
 use crate::{
-    gpu::{buffer::ConstantBuffer, SharedGpuContext},
+    gpu::{
+        buffer::{ConstantBuffer, DynamicStructuredBuffer},
+        SharedGpuContext,
+    },
     gpu_event, include_dxbc,
     loaders::{index_buffer::IndexBuffer, vertex_buffer::VertexBuffer},
     renderer::shader::ShaderProgram,
@@ -20,13 +23,21 @@ use crate::{
 };
 
 #[repr(C)]
-struct ScopeAlkDebugShape {
+struct ScopeAlkDebugBatch {
+    instance_offset: u32,
+    _pad: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DebugShapeInstance {
     local_to_world: Mat4,
     color: Vec4,
 }
 
 #[repr(C)]
-struct ScopeAlkDebugLine {
+#[derive(Clone, Copy)]
+struct DebugLineInstance {
     line_start: Vec4,
     line_end: Vec4,
 
@@ -46,6 +57,84 @@ pub struct ImmediateLabel {
     pub color: Color,
 }
 
+/// Instances of a single shape type queued for drawing, split by blending mode
+struct InstanceBatch<T> {
+    opaque: Vec<T>,
+    transparent: Vec<T>,
+}
+
+impl<T> Default for InstanceBatch<T> {
+    fn default() -> Self {
+        Self {
+            opaque: vec![],
+            transparent: vec![],
+        }
+    }
+}
+
+impl<T: Copy> InstanceBatch<T> {
+    fn push(&mut self, instance: T, opaque: bool) {
+        if opaque {
+            self.opaque.push(instance);
+        } else {
+            self.transparent.push(instance);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.opaque.is_empty() && self.transparent.is_empty()
+    }
+
+    /// All instances, opaque instances first
+    fn instances(&self) -> Vec<T> {
+        self.opaque
+            .iter()
+            .chain(self.transparent.iter())
+            .copied()
+            .collect()
+    }
+
+    /// Offset and count of the opaque or transparent instances in [`Self::instances`]
+    fn range(&self, transparent: bool) -> (u32, u32) {
+        if transparent {
+            (self.opaque.len() as u32, self.transparent.len() as u32)
+        } else {
+            (0, self.opaque.len() as u32)
+        }
+    }
+}
+
+#[derive(Default)]
+struct DebugShapeBatches {
+    cubes: InstanceBatch<DebugShapeInstance>,
+    cube_outlines: InstanceBatch<DebugShapeInstance>,
+    spheres: InstanceBatch<DebugShapeInstance>,
+    lines: InstanceBatch<DebugLineInstance>,
+}
+
+impl DebugShapeBatches {
+    fn is_empty(&self) -> bool {
+        self.cubes.is_empty()
+            && self.cube_outlines.is_empty()
+            && self.spheres.is_empty()
+            && self.lines.is_empty()
+    }
+}
+
+/// Instance buffers, one per shape type. These are kept across frames, and only grow when a
+/// frame draws more shapes than they can hold.
+struct DebugShapeBuffers {
+    cubes: DynamicStructuredBuffer<DebugShapeInstance>,
+    cube_outlines: DynamicStructuredBuffer<DebugShapeInstance>,
+    spheres: DynamicStructuredBuffer<DebugShapeInstance>,
+    lines: DynamicStructuredBuffer<DebugLineInstance>,
+}
+
+/// Immediate mode debug shape renderer.
+///
+/// Shapes are queued and drawn in instanced batches (one per shape type and blending mode) by
+/// [`ImmediateRenderer::flush`], which is called at the end of the view overlay pass. Shapes
+/// queued after that (eg. by the UI) are drawn in the next frame.
 pub struct ImmediateRenderer {
     gpu: SharedGpuContext,
 
@@ -59,8 +148,10 @@ pub struct ImmediateRenderer {
     shader_simple: ShaderProgram,
     shader_line: ShaderProgram,
 
-    cb_debug_shape: ConstantBuffer<ScopeAlkDebugShape>,
-    cb_debug_line: ConstantBuffer<ScopeAlkDebugLine>,
+    cb_debug_batch: ConstantBuffer<ScopeAlkDebugBatch>,
+
+    batches: Mutex<DebugShapeBatches>,
+    buffers: Mutex<DebugShapeBuffers>,
 
     labels: Mutex<Vec<ImmediateLabel>>,
 }
@@ -137,8 +228,14 @@ impl ImmediateRenderer {
                 Some(include_dxbc!(gs "debug/line.hlsl")),
                 include_dxbc!(ps "debug/line.hlsl"),
             )?,
-            cb_debug_shape: ConstantBuffer::create(gpu.clone(), None)?,
-            cb_debug_line: ConstantBuffer::create(gpu.clone(), None)?,
+            cb_debug_batch: ConstantBuffer::create(gpu.clone(), None)?,
+            batches: Mutex::new(DebugShapeBatches::default()),
+            buffers: Mutex::new(DebugShapeBuffers {
+                cubes: DynamicStructuredBuffer::new(gpu.clone()),
+                cube_outlines: DynamicStructuredBuffer::new(gpu.clone()),
+                spheres: DynamicStructuredBuffer::new(gpu.clone()),
+                lines: DynamicStructuredBuffer::new(gpu.clone()),
+            }),
             gpu,
             labels: Mutex::new(vec![]),
         })
@@ -194,13 +291,10 @@ impl ImmediateRenderer {
         line_ratio: f32,
         scroll_speed: f32,
     ) {
-        gpu_event!(self.gpu, "imm_line");
         let start_color = start_color.into();
         let end_color = end_color.into();
-        self.shader_line.bind(&self.gpu);
-
-        self.cb_debug_line
-            .write(&ScopeAlkDebugLine {
+        self.batches.lock().lines.push(
+            DebugLineInstance {
                 line_start: start.extend(1.0),
                 line_end: end.extend(1.0),
                 color_start: start_color,
@@ -209,58 +303,20 @@ impl ImmediateRenderer {
                 dot_scale,
                 line_ratio,
                 scroll_speed,
-            })
-            .unwrap();
-
-        self.cb_debug_line.bind(0, TfxShaderStage::Vertex);
-        self.cb_debug_line.bind(0, TfxShaderStage::Geometry);
-        self.cb_debug_line.bind(0, TfxShaderStage::Pixel);
-
-        self.gpu.set_input_layout(0);
-        self.gpu.set_input_topology(EPrimitiveType::LineList);
-        if !start_color.is_opaque() || !end_color.is_opaque() {
-            self.gpu.set_blend_state(12);
-        } else {
-            self.gpu.set_blend_state(0);
-        }
-
-        unsafe {
-            self.gpu.lock_context().Draw(2, 0);
-            self.gpu.lock_context().GSSetShader(None, None);
-        }
+            },
+            start_color.is_opaque() && end_color.is_opaque(),
+        );
     }
 
     pub fn sphere<C: Into<Color>>(&self, center: Vec3, radius: f32, color: C) {
-        gpu_event!(self.gpu, "imm_sphere");
         let color = color.into();
-        self.shader_simple.bind(&self.gpu);
-
-        self.vb_sphere.bind_single(&self.gpu, 0);
-        self.ib_sphere.bind(&self.gpu);
-
-        self.cb_debug_shape
-            .write(&ScopeAlkDebugShape {
+        self.batches.lock().spheres.push(
+            DebugShapeInstance {
                 local_to_world: mat4_scale_translation(Vec3::splat(radius), center),
                 color: color.to_vec4(),
-            })
-            .unwrap();
-
-        self.cb_debug_shape.bind(0, TfxShaderStage::Vertex);
-        self.cb_debug_shape.bind(0, TfxShaderStage::Pixel);
-
-        self.gpu.set_input_layout(0);
-        self.gpu.set_input_topology(EPrimitiveType::Triangles);
-        if color.is_opaque() {
-            self.gpu.set_blend_state(0);
-        } else {
-            self.gpu.set_blend_state(12);
-        }
-
-        unsafe {
-            self.gpu
-                .lock_context()
-                .DrawIndexed(self.ib_sphere.length as u32, 0, 0);
-        }
+            },
+            color.is_opaque(),
+        );
     }
 
     pub fn cube_extents<C: Into<Color> + Copy>(
@@ -269,7 +325,6 @@ impl ImmediateRenderer {
         color: C,
         sides: bool,
     ) {
-        gpu_event!(self.gpu, "imm_cube_extents");
         let mat = transform.into();
         if sides {
             self.cube(mat, color);
@@ -279,36 +334,14 @@ impl ImmediateRenderer {
     }
 
     pub fn cube<C: Into<Color>>(&self, transform: impl Into<Mat4>, color: C) {
-        gpu_event!(self.gpu, "imm_cube");
         let color = color.into();
-        self.shader_simple.bind(&self.gpu);
-
-        self.vb_cube.bind_single(&self.gpu, 0);
-        self.ib_cube.bind(&self.gpu);
-
-        self.cb_debug_shape
-            .write(&ScopeAlkDebugShape {
+        self.batches.lock().cubes.push(
+            DebugShapeInstance {
                 local_to_world: transform.into(),
                 color: color.to_vec4(),
-            })
-            .unwrap();
-
-        self.cb_debug_shape.bind(0, TfxShaderStage::Vertex);
-        self.cb_debug_shape.bind(0, TfxShaderStage::Pixel);
-
-        self.gpu.set_input_layout(0);
-        self.gpu.set_input_topology(EPrimitiveType::Triangles);
-        if color.is_opaque() {
-            self.gpu.set_blend_state(0);
-        } else {
-            self.gpu.set_blend_state(12);
-        }
-
-        unsafe {
-            self.gpu
-                .lock_context()
-                .DrawIndexed(self.ib_cube.length as u32, 0, 0);
-        }
+            },
+            color.is_opaque(),
+        );
     }
 
     pub fn cube_outline_aabb<C: Into<Color>>(&self, aabb: &Aabb, color: C) {
@@ -318,36 +351,14 @@ impl ImmediateRenderer {
     }
 
     pub fn cube_outline<C: Into<Color>>(&self, transform: impl Into<Mat4>, color: C) {
-        gpu_event!(self.gpu, "imm_cube_outline");
         let color = color.into();
-        self.shader_simple.bind(&self.gpu);
-
-        self.vb_cube.bind_single(&self.gpu, 0);
-        self.ib_cube_outline.bind(&self.gpu);
-
-        self.cb_debug_shape
-            .write(&ScopeAlkDebugShape {
+        self.batches.lock().cube_outlines.push(
+            DebugShapeInstance {
                 local_to_world: transform.into(),
                 color: color.to_vec4(),
-            })
-            .unwrap();
-
-        self.cb_debug_shape.bind(0, TfxShaderStage::Vertex);
-        self.cb_debug_shape.bind(0, TfxShaderStage::Pixel);
-
-        self.gpu.set_input_layout(0);
-        self.gpu.set_input_topology(EPrimitiveType::LineList);
-        if color.is_opaque() {
-            self.gpu.set_blend_state(0);
-        } else {
-            self.gpu.set_blend_state(12);
-        }
-
-        unsafe {
-            self.gpu
-                .lock_context()
-                .DrawIndexed(self.ib_cube_outline.length as u32, 0, 0);
-        }
+            },
+            color.is_opaque(),
+        );
     }
 
     pub fn circle<C: Into<Color> + Copy>(&self, center: Vec3, axis: Vec3, edges: u8, color: C) {
@@ -384,6 +395,150 @@ impl ImmediateRenderer {
         });
     }
 
+    /// Draws all shapes queued since the last flush to the currently bound render targets
+    pub fn flush(&self) {
+        let batches = std::mem::take(&mut *self.batches.lock());
+        if batches.is_empty() {
+            return;
+        }
+
+        gpu_event!(self.gpu, "immediate_shapes");
+        let mut buffers = self.buffers.lock();
+        let result = (|| -> anyhow::Result<()> {
+            buffers.cubes.write(&batches.cubes.instances())?;
+            buffers
+                .cube_outlines
+                .write(&batches.cube_outlines.instances())?;
+            buffers.spheres.write(&batches.spheres.instances())?;
+            buffers.lines.write(&batches.lines.instances())?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            error!("Failed to upload debug shape instances: {e:?}");
+            return;
+        }
+
+        self.gpu.set_input_layout(0);
+        // Transparent shapes are drawn after all opaque shapes
+        for transparent in [false, true] {
+            if transparent {
+                self.gpu.set_blend_state(12);
+            } else {
+                self.gpu.set_blend_state(0);
+            }
+
+            self.shader_simple.bind(&self.gpu);
+            self.draw_shape_batch(
+                &batches.cubes,
+                &buffers.cubes,
+                &self.vb_cube,
+                &self.ib_cube,
+                EPrimitiveType::Triangles,
+                transparent,
+            );
+            self.draw_shape_batch(
+                &batches.cube_outlines,
+                &buffers.cube_outlines,
+                &self.vb_cube,
+                &self.ib_cube_outline,
+                EPrimitiveType::LineList,
+                transparent,
+            );
+            self.draw_shape_batch(
+                &batches.spheres,
+                &buffers.spheres,
+                &self.vb_sphere,
+                &self.ib_sphere,
+                EPrimitiveType::Triangles,
+                transparent,
+            );
+
+            self.draw_line_batch(&batches.lines, &buffers.lines, transparent);
+        }
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.GSSetShader(None, None);
+            ctx.VSSetShaderResources(0, Some(&[None]));
+            ctx.GSSetShaderResources(0, Some(&[None]));
+            ctx.PSSetShaderResources(0, Some(&[None]));
+        }
+    }
+
+    fn bind_batch(&self, instance_offset: u32) {
+        self.cb_debug_batch
+            .write(&ScopeAlkDebugBatch {
+                instance_offset,
+                _pad: [0; 3],
+            })
+            .unwrap();
+
+        self.cb_debug_batch.bind(0, TfxShaderStage::Vertex);
+        self.cb_debug_batch.bind(0, TfxShaderStage::Geometry);
+        self.cb_debug_batch.bind(0, TfxShaderStage::Pixel);
+    }
+
+    fn draw_shape_batch(
+        &self,
+        batch: &InstanceBatch<DebugShapeInstance>,
+        buffer: &DynamicStructuredBuffer<DebugShapeInstance>,
+        vertex_buffer: &VertexBuffer,
+        index_buffer: &IndexBuffer,
+        topology: EPrimitiveType,
+        transparent: bool,
+    ) {
+        let (offset, count) = batch.range(transparent);
+        let Some(srv) = buffer.srv() else {
+            return;
+        };
+
+        if count == 0 {
+            return;
+        }
+
+        self.bind_batch(offset);
+        vertex_buffer.bind_single(&self.gpu, 0);
+        index_buffer.bind(&self.gpu);
+        self.gpu.set_input_topology(topology);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.VSSetShaderResources(0, Some(&[Some(srv.clone())]));
+            ctx.DrawIndexedInstanced(index_buffer.length as u32, count, 0, 0, 0);
+        }
+    }
+
+    fn draw_line_batch(
+        &self,
+        batch: &InstanceBatch<DebugLineInstance>,
+        buffer: &DynamicStructuredBuffer<DebugLineInstance>,
+        transparent: bool,
+    ) {
+        let (offset, count) = batch.range(transparent);
+        let Some(srv) = buffer.srv() else {
+            return;
+        };
+
+        if count == 0 {
+            return;
+        }
+
+        self.bind_batch(offset);
+        self.shader_line.bind(&self.gpu);
+        self.gpu.set_input_topology(EPrimitiveType::LineList);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            let srv = Some(srv.clone());
+            ctx.VSSetShaderResources(0, Some(&[srv.clone()]));
+            ctx.GSSetShaderResources(0, Some(&[srv.clone()]));
+            ctx.PSSetShaderResources(0, Some(&[srv]));
+            ctx.DrawInstanced(2, count, 0, 0);
+            ctx.GSSetShader(None, None);
+        }
+    }
+
     /// Take all the labels rendered this frame
     #[must_use]
     pub fn drain_labels(&self) -> Vec<ImmediateLabel> {
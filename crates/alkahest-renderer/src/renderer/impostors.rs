@@ -0,0 +1,532 @@
+//! Billboard impostors for distant static instance groups
+//!
+//! Once the assets of a map have finished loading, every static model is rendered from a few
+//! angles around its up axis into an atlas holding the same GBuffer data as the opaque pass, so
+//! impostors are lit like the geometry they replace. Groups past the impostor distance are drawn
+//! as camera-facing quads in a single instanced draw instead of their meshes.
+
+use std::{
+    cmp::Reverse,
+    f32::consts::TAU,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+};
+
+use alkahest_data::{
+    dxgi::DxgiFormat,
+    geometry::EPrimitiveType,
+    occlusion::Aabb,
+    technique::StateSelection,
+    tfx::{TfxRenderStage, TfxShaderStage},
+};
+use bevy_ecs::{entity::Entity, system::Resource};
+use destiny_pkg::TagHash;
+use glam::{Mat4, UVec2, Vec3, Vec4};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    camera::{CameraProjection, Viewport},
+    ecs::{
+        culling::{Frustum, Sphere},
+        hierarchy::Children,
+        render::static_geometry::{create_instances_scope, StaticInstances, StaticModel},
+        transform::Transform,
+        visibility::{ViewVisibility, VisibilityHelper},
+        Scene,
+    },
+    gpu::{
+        buffer::{ConstantBuffer, DynamicStructuredBuffer},
+        SharedGpuContext,
+    },
+    gpu_event, include_dxbc,
+    renderer::{
+        gbuffer::{DepthState, RenderTarget},
+        shader::ShaderProgram,
+        Renderer, StatsCategory,
+    },
+    tfx::{
+        externs,
+        view::{RenderStageSubscriptions, View},
+    },
+};
+
+/// Number of angles around the up axis every model is captured from
+const IMPOSTOR_VIEWS: usize = 8;
+const ATLAS_SIZE: u32 = 2048;
+/// Resolution of a single captured view
+const CELL_SIZE: u32 = 64;
+const CELLS_PER_ROW: usize = (ATLAS_SIZE / CELL_SIZE) as usize;
+const CELL_COUNT: usize = CELLS_PER_ROW * CELLS_PER_ROW;
+/// Models captured per frame, so capturing doesn't stall a single frame
+const CAPTURES_PER_FRAME: usize = 8;
+
+static NEXT_SCENE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifies the scene the impostor atlas was captured for
+#[derive(Resource)]
+struct ImpostorSceneId(u64);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ImpostorInstance {
+    center: Vec3,
+    radius: f32,
+    /// Rotation of the instance, applied to the captured normals
+    rotation: Vec4,
+    /// Offset and size of the captured view in the atlas, in texture coordinates
+    atlas_rect: Vec4,
+}
+
+/// A model captured into the atlas, along with the local bounds the views were framed around
+struct ImpostorModel {
+    first_cell: usize,
+    center: Vec3,
+    radius: f32,
+}
+
+struct ImpostorAtlas {
+    rt0: RenderTarget,
+    rt1: RenderTarget,
+    rt2: RenderTarget,
+    depth: DepthState,
+
+    scene_id: u64,
+    models: FxHashMap<TagHash, ImpostorModel>,
+    next_cell: usize,
+}
+
+impl ImpostorAtlas {
+    fn create(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        let size = (ATLAS_SIZE, ATLAS_SIZE);
+        Ok(Self {
+            // Same formats as the GBuffer
+            rt0: RenderTarget::create(
+                size,
+                DxgiFormat::B8G8R8A8_UNORM_SRGB,
+                gpu.clone(),
+                "Impostor_RT0",
+            )?,
+            rt1: RenderTarget::create(
+                size,
+                DxgiFormat::R10G10B10A2_UNORM,
+                gpu.clone(),
+                "Impostor_RT1",
+            )?,
+            rt2: RenderTarget::create(
+                size,
+                DxgiFormat::B8G8R8A8_UNORM,
+                gpu.clone(),
+                "Impostor_RT2",
+            )?,
+            depth: DepthState::create(gpu, size, "Impostor_Depth")?,
+            scene_id: 0,
+            models: FxHashMap::default(),
+            next_cell: 0,
+        })
+    }
+
+    fn reset(&mut self, scene_id: u64) {
+        self.rt0.clear(&[0.0, 0.0, 0.0, 0.0]);
+        self.rt1.clear(&[0.0, 0.0, 0.0, 0.0]);
+        self.rt2.clear(&[1.0, 0.5, 1.0, 1.0]);
+        self.depth.clear(0.0, 0);
+
+        self.scene_id = scene_id;
+        self.models.clear();
+        self.next_cell = 0;
+    }
+
+    fn free_models(&self) -> usize {
+        (CELL_COUNT - self.next_cell) / IMPOSTOR_VIEWS
+    }
+
+    fn cell_origin(cell: usize) -> UVec2 {
+        UVec2::new(
+            (cell % CELLS_PER_ROW) as u32 * CELL_SIZE,
+            (cell / CELLS_PER_ROW) as u32 * CELL_SIZE,
+        )
+    }
+
+    fn cell_rect(cell: usize) -> Vec4 {
+        let origin = Self::cell_origin(cell).as_vec2() / ATLAS_SIZE as f32;
+        let size = CELL_SIZE as f32 / ATLAS_SIZE as f32;
+        Vec4::new(origin.x, origin.y, size, size)
+    }
+}
+
+/// Direction from the model towards the camera for the given captured view, in model space
+fn view_direction(view: usize) -> Vec3 {
+    let angle = view as f32 / IMPOSTOR_VIEWS as f32 * TAU;
+    Vec3::new(angle.cos(), angle.sin(), 0.0)
+}
+
+/// Orthographic view framing the bounds of a model in a single atlas cell
+struct ImpostorCaptureView {
+    viewport: Viewport,
+    world_to_camera: Mat4,
+    camera_to_projective: Mat4,
+}
+
+impl ImpostorCaptureView {
+    fn new(cell: usize, view: usize, center: Vec3, radius: f32) -> Self {
+        let viewport = Viewport {
+            origin: ImpostorAtlas::cell_origin(cell),
+            size: UVec2::splat(CELL_SIZE),
+        };
+
+        // The orthographic depth range is centered on the camera, so it covers the whole model
+        Self {
+            world_to_camera: Mat4::look_at_rh(center, center - view_direction(view), Vec3::Z),
+            camera_to_projective: CameraProjection::orthographic(Vec3::splat(radius))
+                .matrix(viewport.aspect_ratio()),
+            viewport,
+        }
+    }
+}
+
+impl View for ImpostorCaptureView {
+    fn viewport(&self) -> Viewport {
+        self.viewport.clone()
+    }
+
+    fn subscribed_views(&self) -> RenderStageSubscriptions {
+        RenderStageSubscriptions::GENERATE_GBUFFER
+    }
+
+    fn name(&self) -> String {
+        "ImpostorCapture".to_string()
+    }
+
+    fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.camera_to_projective * self.world_to_camera)
+    }
+
+    fn position(&self) -> Vec3 {
+        self.world_to_camera.inverse().w_axis.truncate()
+    }
+
+    fn update_extern(&self, x: &mut externs::View) {
+        x.world_to_camera = self.world_to_camera;
+        x.camera_to_projective = self.camera_to_projective;
+
+        x.derive_matrices(&self.viewport);
+
+        x.view_miscellaneous = Vec4::new(0., 1., 0., 0.);
+    }
+}
+
+pub struct ImpostorRenderer {
+    shader: ShaderProgram,
+    /// Created the first time impostors are enabled
+    atlas: Mutex<Option<ImpostorAtlas>>,
+    atlas_failed: AtomicBool,
+    instances: Mutex<DynamicStructuredBuffer<ImpostorInstance>>,
+    /// Number of impostors in `instances` for the current view
+    instance_count: AtomicUsize,
+    capture_cb: ConstantBuffer<u8>,
+}
+
+impl ImpostorRenderer {
+    pub fn new(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader: ShaderProgram::load(
+                &gpu,
+                include_dxbc!(vs "misc/impostor.hlsl"),
+                None,
+                include_dxbc!(ps "misc/impostor.hlsl"),
+            )?,
+            atlas: Mutex::new(None),
+            atlas_failed: AtomicBool::new(false),
+            instances: Mutex::new(DynamicStructuredBuffer::new(gpu.clone())),
+            instance_count: AtomicUsize::new(0),
+            capture_cb: ConstantBuffer::create_array_init(gpu, &[0u8; 32 + 64])?,
+        })
+    }
+}
+
+impl Renderer {
+    /// Captures impostors for models that don't have one yet, and picks the static instance
+    /// groups that are drawn as impostors from `view_position`. Like LOD selection, a group is
+    /// only replaced once its closest instance is past the impostor distance
+    pub(super) fn update_impostors(&self, scene: &mut Scene, view_position: Vec3) {
+        self.impostors.instance_count.store(0, Ordering::Relaxed);
+        if !self.settings().impostors || self.is_probing() {
+            for mut instances in scene.query::<&mut StaticInstances>().iter_mut(scene) {
+                instances.impostor = false;
+            }
+            return;
+        }
+
+        let scene_id = match scene.get_resource::<ImpostorSceneId>() {
+            Some(id) => id.0,
+            None => {
+                let id = NEXT_SCENE_ID.fetch_add(1, Ordering::Relaxed);
+                scene.insert_resource(ImpostorSceneId(id));
+                id
+            }
+        };
+
+        let mut atlas = self.impostors.atlas.lock();
+        if atlas.is_none() {
+            if self.impostors.atlas_failed.load(Ordering::Relaxed) {
+                return;
+            }
+
+            match ImpostorAtlas::create(self.gpu.clone()) {
+                Ok(a) => *atlas = Some(a),
+                Err(e) => {
+                    error!("Failed to create impostor atlas: {e:?}");
+                    self.impostors.atlas_failed.store(true, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+        let atlas = atlas.as_mut().unwrap();
+
+        if atlas.scene_id != scene_id {
+            atlas.reset(scene_id);
+        }
+
+        // Captures need every texture and buffer of the model to be loaded
+        if self.data.lock().asset_manager.is_idle() {
+            self.capture_impostors(scene, atlas);
+        }
+
+        self.select_impostors(scene, atlas, view_position);
+    }
+
+    fn capture_impostors(&self, scene: &mut Scene, atlas: &mut ImpostorAtlas) {
+        let budget = atlas.free_models().min(CAPTURES_PER_FRAME);
+        if budget == 0 {
+            return;
+        }
+
+        // Models with the most instances save the most geometry
+        let candidates = scene
+            .query::<(Entity, &StaticInstances, &Children)>()
+            .iter(scene)
+            .filter(|(_, instances, _)| {
+                !atlas.models.contains_key(&instances.model.hash)
+                    && instances
+                        .model
+                        .subscribed_stages
+                        .is_subscribed(TfxRenderStage::GenerateGbuffer)
+            })
+            .filter_map(|(e, instances, children)| {
+                let bounds = scene.get::<Aabb>(*children.first()?)?;
+                Some((
+                    e,
+                    instances.model.hash,
+                    instances.instance_count,
+                    bounds.clone(),
+                ))
+            })
+            .sorted_by_key(|(_, _, count, _)| Reverse(*count))
+            .unique_by(|(_, hash, _, _)| *hash)
+            .take(budget)
+            .collect_vec();
+
+        if candidates.is_empty() {
+            return;
+        }
+
+        gpu_event!(self.gpu, "impostor_capture");
+        unsafe {
+            self.gpu.lock_context().OMSetRenderTargets(
+                Some(&[
+                    Some(atlas.rt0.render_target.clone()),
+                    Some(atlas.rt1.render_target.clone()),
+                    Some(atlas.rt2.render_target.clone()),
+                ]),
+                &atlas.depth.view,
+            );
+        }
+
+        for (e, hash, _, bounds) in candidates {
+            let Some(instances) = scene.get::<StaticInstances>(e) else {
+                continue;
+            };
+
+            let model = ImpostorModel {
+                first_cell: atlas.next_cell,
+                center: bounds.center(),
+                radius: bounds.radius().max(0.01),
+            };
+            self.capture_impostor(&instances.model, &model);
+
+            atlas.next_cell += IMPOSTOR_VIEWS;
+            atlas.models.insert(hash, model);
+        }
+
+        debug!(
+            "Captured impostors for {} models, {}/{CELL_COUNT} atlas cells used",
+            atlas.models.len(),
+            atlas.next_cell
+        );
+    }
+
+    /// ⚠ Expects the atlas to be bound
+    fn capture_impostor(&self, model: &StaticModel, impostor: &ImpostorModel) {
+        unsafe {
+            self.impostors
+                .capture_cb
+                .write_array(
+                    create_instances_scope(
+                        &model.model.opaque_meshes,
+                        &[Transform::default()],
+                        &[],
+                    )
+                    .write()
+                    .as_slice(),
+                )
+                .unwrap();
+        }
+        self.impostors.capture_cb.bind(
+            self.render_globals.scopes.chunk_model.vertex_slot() as u32,
+            TfxShaderStage::Vertex,
+        );
+
+        let lod = model.lod_levels.resolve(0);
+        for view in 0..IMPOSTOR_VIEWS {
+            self.bind_view(
+                &ImpostorCaptureView::new(
+                    impostor.first_cell + view,
+                    view,
+                    impostor.center,
+                    impostor.radius,
+                ),
+                0,
+                false,
+            );
+            self.gpu
+                .current_states
+                .store(StateSelection::new(Some(0), Some(2), Some(2), Some(0)));
+            model.draw_lod(self, TfxRenderStage::GenerateGbuffer, 1, lod);
+        }
+    }
+
+    fn select_impostors(&self, scene: &mut Scene, atlas: &ImpostorAtlas, view_position: Vec3) {
+        profiling::scope!("Renderer::select_impostors");
+        let distance = self.settings().impostor_distance;
+
+        let mut replaced = FxHashSet::default();
+        let mut impostors = vec![];
+        for (e, instances, children, vis) in scene
+            .query::<(Entity, &StaticInstances, &Children, Option<&ViewVisibility>)>()
+            .iter(scene)
+        {
+            let Some(model) = atlas.models.get(&instances.model.hash) else {
+                continue;
+            };
+
+            if !vis.is_visible(0) {
+                continue;
+            }
+
+            let bounds = Sphere {
+                center: model.center,
+                radius: model.radius,
+            };
+            let spheres = children
+                .iter()
+                .filter_map(|&c| scene.get::<Transform>(c))
+                .map(|t| (t, bounds.transform(t.local_to_world())))
+                .collect_vec();
+
+            if spheres
+                .iter()
+                .any(|(_, s)| s.center.distance(view_position) - s.radius < distance)
+            {
+                continue;
+            }
+
+            replaced.insert(e);
+            for (transform, sphere) in spheres {
+                // Use the captured view closest to the direction of the camera
+                let to_camera = transform.rotation.inverse() * (view_position - sphere.center);
+                let angle = to_camera.y.atan2(to_camera.x) / TAU * IMPOSTOR_VIEWS as f32;
+                let view = angle.round().rem_euclid(IMPOSTOR_VIEWS as f32) as usize;
+
+                impostors.push(ImpostorInstance {
+                    center: sphere.center,
+                    radius: sphere.radius,
+                    rotation: Vec4::from(transform.rotation),
+                    atlas_rect: ImpostorAtlas::cell_rect(model.first_cell + view),
+                });
+            }
+        }
+
+        for (e, mut instances) in scene
+            .query::<(Entity, &mut StaticInstances)>()
+            .iter_mut(scene)
+        {
+            instances.impostor = replaced.contains(&e);
+        }
+
+        if let Err(e) = self.impostors.instances.lock().write(&impostors) {
+            error!("Failed to write impostor instances: {e:?}");
+            return;
+        }
+        self.impostors
+            .instance_count
+            .store(impostors.len(), Ordering::Relaxed);
+    }
+
+    /// Draws the impostors picked by [`Self::update_impostors`]. Expects the GBuffer to be bound
+    pub(super) fn draw_impostors(&self) {
+        let count = self.impostors.instance_count.load(Ordering::Relaxed);
+        if count == 0 {
+            return;
+        }
+
+        let atlas = self.impostors.atlas.lock();
+        let instances = self.impostors.instances.lock();
+        let (Some(atlas), Some(srv)) = (atlas.as_ref(), instances.srv()) else {
+            return;
+        };
+
+        gpu_event!(self.gpu, "impostors");
+        // Quads are drawn without culling
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(2), Some(0), Some(0)));
+        self.gpu.flush_states();
+        self.impostors.shader.bind(&self.gpu);
+        self.gpu.set_input_topology(EPrimitiveType::TriangleStrip);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.GSSetShader(None, None);
+            ctx.VSSetShaderResources(0, Some(&[Some(srv.clone())]));
+            ctx.PSSetShaderResources(
+                0,
+                Some(&[
+                    Some(atlas.rt0.view.clone()),
+                    Some(atlas.rt1.view.clone()),
+                    Some(atlas.rt2.view.clone()),
+                    Some(atlas.depth.texture_view.clone()),
+                ]),
+            );
+            ctx.PSSetSamplers(
+                0,
+                Some(&[Some(self.gpu.util_resources.point_sampler.clone())]),
+            );
+            ctx.DrawInstanced(4, count as u32, 0, 0);
+
+            ctx.VSSetShaderResources(0, Some(&[None]));
+            ctx.PSSetShaderResources(0, Some(&[None, None, None, None]));
+        }
+        self.stats.record_draw(
+            StatsCategory::Statics,
+            4,
+            EPrimitiveType::TriangleStrip,
+            count as u32,
+        );
+
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(2), Some(2), Some(0)));
+        self.gpu.flush_states();
+    }
+}
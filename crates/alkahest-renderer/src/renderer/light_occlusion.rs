@@ -0,0 +1,572 @@
+//! Light volume occlusion culling. The view is divided into a froxel grid (clusters), and every
+//! light volume is assigned to the clusters it overlaps on the CPU. A compute pass then marks the
+//! clusters that contain visible geometry, and lights that only touch empty clusters are drawn
+//! with an instance count of 0.
+//!
+//! This is not clustered shading: the per-cluster light lists are only used for culling and the
+//! light count debug view. Every light is still drawn as its own volume with its own technique,
+//! as the lighting math lives in the game shaders.
+
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage};
+use anyhow::Context;
+use glam::{Mat4, UVec2, UVec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+use parking_lot::Mutex;
+use windows::Win32::Graphics::{
+    Direct3D::D3D11_SRV_DIMENSION_BUFFER,
+    Direct3D11::{
+        ID3D11Buffer, ID3D11ComputeShader, ID3D11PixelShader, ID3D11ShaderResourceView,
+        ID3D11UnorderedAccessView, D3D11_BIND_FLAG, D3D11_BIND_SHADER_RESOURCE,
+        D3D11_BIND_UNORDERED_ACCESS, D3D11_BOX, D3D11_BUFFER_DESC, D3D11_BUFFER_SRV,
+        D3D11_BUFFER_SRV_0, D3D11_BUFFER_SRV_1, D3D11_BUFFER_UAV,
+        D3D11_RESOURCE_MISC_DRAWINDIRECT_ARGS, D3D11_RESOURCE_MISC_FLAG,
+        D3D11_SHADER_RESOURCE_VIEW_DESC, D3D11_SHADER_RESOURCE_VIEW_DESC_0,
+        D3D11_UAV_DIMENSION_BUFFER, D3D11_UNORDERED_ACCESS_VIEW_DESC,
+        D3D11_UNORDERED_ACCESS_VIEW_DESC_0, D3D11_USAGE_DEFAULT,
+    },
+    Dxgi::Common::DXGI_FORMAT_R32_UINT,
+};
+
+use crate::{
+    gpu::{
+        buffer::{ConstantBuffer, DynamicStructuredBuffer},
+        util::DxDeviceExt,
+        SharedGpuContext,
+    },
+    gpu_event, include_dxbc,
+    renderer::Renderer,
+    tfx::externs,
+    util::d3d::D3dResource,
+};
+
+// Has to match light_clusters.hlsli
+pub const CLUSTERS_X: u32 = 16;
+pub const CLUSTERS_Y: u32 = 9;
+pub const CLUSTERS_Z: u32 = 24;
+const CLUSTER_COUNT: usize = (CLUSTERS_X * CLUSTERS_Y * CLUSTERS_Z) as usize;
+
+/// View depth at which the first depth slice ends, anything closer shares that slice
+const CLUSTER_NEAR: f32 = 0.5;
+/// View depth at which the last depth slice starts, anything further shares that slice
+const CLUSTER_FAR: f32 = 2000.0;
+
+/// Size of the `DrawIndexedInstancedIndirect` arguments for a single light, in dwords
+const DRAW_ARGS_DWORDS: u32 = 5;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LightClusterParams {
+    target_pixel_to_world: Mat4,
+    camera_pos: Vec4,
+    camera_forward: Vec4,
+    /// x = target width, y = target height, z = depth slice scale, w = depth slice bias
+    cluster_params: Vec4,
+    heatmap_max: u32,
+    _pad: [u32; 3],
+}
+
+/// Maps positions to clusters, the same way `ClusterIndex` in light_clusters.hlsli does
+struct ClusterGrid {
+    world_to_projective: Mat4,
+    camera_pos: Vec3,
+    camera_forward: Vec3,
+    slice_scale: f32,
+    slice_bias: f32,
+}
+
+impl ClusterGrid {
+    fn new(view: &externs::View) -> Self {
+        // Reverse-Z, so 1.0 is the near plane
+        let near = view
+            .projective_to_world
+            .project_point3(Vec3::new(0.0, 0.0, 1.0));
+        let further = view
+            .projective_to_world
+            .project_point3(Vec3::new(0.0, 0.0, 0.5));
+        let slice_scale = CLUSTERS_Z as f32 / (CLUSTER_FAR / CLUSTER_NEAR).ln();
+
+        Self {
+            world_to_projective: view.world_to_projective,
+            camera_pos: view.position.xyz(),
+            camera_forward: (further - near).normalize_or_zero(),
+            slice_scale,
+            slice_bias: -CLUSTER_NEAR.ln() * slice_scale,
+        }
+    }
+
+    fn slice(&self, depth: f32) -> u32 {
+        (depth.max(1e-4).ln() * self.slice_scale + self.slice_bias)
+            .floor()
+            .clamp(0.0, (CLUSTERS_Z - 1) as f32) as u32
+    }
+
+    /// Tile containing the given NDC position
+    fn tile(ndc: Vec2) -> UVec2 {
+        // Y points down in pixel coordinates
+        let x = ((ndc.x * 0.5 + 0.5) * CLUSTERS_X as f32).floor();
+        let y = ((0.5 - ndc.y * 0.5) * CLUSTERS_Y as f32).floor();
+        UVec2::new(
+            x.clamp(0.0, (CLUSTERS_X - 1) as f32) as u32,
+            y.clamp(0.0, (CLUSTERS_Y - 1) as f32) as u32,
+        )
+    }
+
+    /// Inclusive range of clusters overlapped by a unit cube transformed by `volume`, or `None`
+    /// if the volume is outside of the view
+    fn volume_range(&self, volume: &Mat4) -> Option<(UVec3, UVec3)> {
+        let mut depth_min = f32::MAX;
+        let mut depth_max = f32::MIN;
+        let mut ndc_min = Vec2::splat(f32::MAX);
+        let mut ndc_max = Vec2::splat(f32::MIN);
+        let mut crosses_camera = false;
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            );
+            let world = volume.transform_point3(corner);
+            let depth = (world - self.camera_pos).dot(self.camera_forward);
+            depth_min = depth_min.min(depth);
+            depth_max = depth_max.max(depth);
+
+            let clip = self.world_to_projective * world.extend(1.0);
+            if clip.w <= 1e-4 {
+                crosses_camera = true;
+            } else {
+                let ndc = clip.xy() / clip.w;
+                ndc_min = ndc_min.min(ndc);
+                ndc_max = ndc_max.max(ndc);
+            }
+        }
+
+        if depth_max < 0.0 {
+            return None;
+        }
+
+        // Projected bounds aren't reliable for volumes that reach behind the camera
+        let (tile_min, tile_max) = if crosses_camera {
+            (UVec2::ZERO, UVec2::new(CLUSTERS_X - 1, CLUSTERS_Y - 1))
+        } else {
+            if ndc_max.x < -1.0 || ndc_min.x > 1.0 || ndc_max.y < -1.0 || ndc_min.y > 1.0 {
+                return None;
+            }
+
+            let a = Self::tile(ndc_min);
+            let b = Self::tile(ndc_max);
+            (a.min(b), a.max(b))
+        };
+
+        Some((
+            tile_min.extend(self.slice(depth_min)),
+            tile_max.extend(self.slice(depth_max)),
+        ))
+    }
+}
+
+fn cluster_index(x: u32, y: u32, z: u32) -> usize {
+    (x + y * CLUSTERS_X + z * CLUSTERS_X * CLUSTERS_Y) as usize
+}
+
+fn for_each_cluster((min, max): (UVec3, UVec3), mut f: impl FnMut(usize)) {
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                f(cluster_index(x, y, z));
+            }
+        }
+    }
+}
+
+/// Builds the light list of every cluster, as an (offset, count) pair into the returned light
+/// indices
+fn build_cluster_lists(ranges: &[(UVec3, UVec3)]) -> (Vec<[u32; 2]>, Vec<u32>) {
+    let mut lists = vec![[0u32; 2]; CLUSTER_COUNT];
+    for range in ranges {
+        for_each_cluster(*range, |i| lists[i][1] += 1);
+    }
+
+    let mut offset = 0;
+    for list in &mut lists {
+        list[0] = offset;
+        offset += list[1];
+        list[1] = 0;
+    }
+
+    let mut indices = vec![0; offset as usize];
+    for (light, range) in ranges.iter().enumerate() {
+        for_each_cluster(*range, |i| {
+            indices[(lists[i][0] + lists[i][1]) as usize] = light as u32;
+            lists[i][1] += 1;
+        });
+    }
+
+    (lists, indices)
+}
+
+/// A buffer of `uint`s that can be written by compute shaders
+struct UintBuffer {
+    buffer: ID3D11Buffer,
+    uav: ID3D11UnorderedAccessView,
+    srv: Option<ID3D11ShaderResourceView>,
+    len: usize,
+}
+
+impl UintBuffer {
+    fn create(
+        gpu: &SharedGpuContext,
+        len: usize,
+        bind_flags: D3D11_BIND_FLAG,
+        misc_flags: D3D11_RESOURCE_MISC_FLAG,
+        name: &str,
+    ) -> anyhow::Result<Self> {
+        unsafe {
+            let mut buffer = None;
+            gpu.device
+                .CreateBuffer(
+                    &D3D11_BUFFER_DESC {
+                        ByteWidth: (len * 4) as u32,
+                        Usage: D3D11_USAGE_DEFAULT,
+                        BindFlags: (D3D11_BIND_UNORDERED_ACCESS.0 | bind_flags.0) as u32,
+                        CPUAccessFlags: 0,
+                        MiscFlags: misc_flags.0 as u32,
+                        StructureByteStride: 0,
+                    },
+                    None,
+                    Some(&mut buffer),
+                )
+                .with_context(|| format!("Failed to create {name}"))?;
+            let buffer = buffer.unwrap();
+            buffer.set_debug_name(name);
+
+            let mut uav = None;
+            gpu.device
+                .CreateUnorderedAccessView(
+                    &buffer,
+                    Some(&D3D11_UNORDERED_ACCESS_VIEW_DESC {
+                        Format: DXGI_FORMAT_R32_UINT,
+                        ViewDimension: D3D11_UAV_DIMENSION_BUFFER,
+                        Anonymous: D3D11_UNORDERED_ACCESS_VIEW_DESC_0 {
+                            Buffer: D3D11_BUFFER_UAV {
+                                FirstElement: 0,
+                                NumElements: len as u32,
+                                Flags: 0,
+                            },
+                        },
+                    }),
+                    Some(&mut uav),
+                )
+                .with_context(|| format!("Failed to create {name} UAV"))?;
+
+            let mut srv = None;
+            if bind_flags.0 & D3D11_BIND_SHADER_RESOURCE.0 != 0 {
+                gpu.device
+                    .CreateShaderResourceView(
+                        &buffer,
+                        Some(&D3D11_SHADER_RESOURCE_VIEW_DESC {
+                            Format: DXGI_FORMAT_R32_UINT,
+                            ViewDimension: D3D11_SRV_DIMENSION_BUFFER,
+                            Anonymous: D3D11_SHADER_RESOURCE_VIEW_DESC_0 {
+                                Buffer: D3D11_BUFFER_SRV {
+                                    Anonymous1: D3D11_BUFFER_SRV_0 { ElementOffset: 0 },
+                                    Anonymous2: D3D11_BUFFER_SRV_1 {
+                                        NumElements: len as u32,
+                                    },
+                                },
+                            },
+                        }),
+                        Some(&mut srv),
+                    )
+                    .with_context(|| format!("Failed to create {name} SRV"))?;
+            }
+
+            Ok(Self {
+                buffer,
+                uav: uav.unwrap(),
+                srv,
+                len,
+            })
+        }
+    }
+}
+
+/// Statistics of the last cluster assignment, for the debug view
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LightOcclusionStats {
+    /// Lights overlapping at least one cluster
+    pub lights: usize,
+    /// Lights that were outside of the view entirely
+    pub culled: usize,
+    pub max_lights_per_cluster: u32,
+}
+
+/// Indirect draw arguments for the lights drawn by `draw_light_system`
+pub struct LightOcclusionDraws {
+    args: Option<ID3D11Buffer>,
+    /// Byte offset of the arguments for every light, `None` for lights that are outside of the
+    /// view
+    offsets: Vec<Option<u32>>,
+}
+
+impl LightOcclusionDraws {
+    /// Arguments buffer and offset for the given light, `None` if it shouldn't be drawn at all
+    pub fn draw_args(&self, light: usize) -> Option<(&ID3D11Buffer, u32)> {
+        let offset = self.offsets.get(light).copied().flatten()?;
+        Some((self.args.as_ref()?, offset))
+    }
+}
+
+pub struct LightOcclusionRenderer {
+    gpu: SharedGpuContext,
+    occupancy_cs: ID3D11ComputeShader,
+    cull_cs: ID3D11ComputeShader,
+    debug_ps: ID3D11PixelShader,
+    params_cb: ConstantBuffer<LightClusterParams>,
+
+    occupancy: UintBuffer,
+    cluster_lists: Mutex<DynamicStructuredBuffer<[u32; 2]>>,
+    light_indices: Mutex<DynamicStructuredBuffer<u32>>,
+    /// Grows to fit the number of lights in view
+    draw_args: Mutex<Option<UintBuffer>>,
+    stats: Mutex<Option<LightOcclusionStats>>,
+}
+
+impl LightOcclusionRenderer {
+    pub fn new(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            occupancy_cs: gpu
+                .device
+                .load_compute_shader(include_dxbc!(cs "misc/light_cluster_occupancy.hlsl"))?,
+            cull_cs: gpu
+                .device
+                .load_compute_shader(include_dxbc!(cs "misc/light_cluster_cull.hlsl"))?,
+            debug_ps: gpu
+                .device
+                .load_pixel_shader(include_dxbc!(ps "debug/light_volume_counts.hlsl"))?,
+            params_cb: ConstantBuffer::create(gpu.clone(), None)?,
+            occupancy: UintBuffer::create(
+                &gpu,
+                CLUSTER_COUNT,
+                D3D11_BIND_SHADER_RESOURCE,
+                D3D11_RESOURCE_MISC_FLAG(0),
+                "Light_Cluster_Occupancy",
+            )?,
+            cluster_lists: Mutex::new(DynamicStructuredBuffer::new(gpu.clone())),
+            light_indices: Mutex::new(DynamicStructuredBuffer::new(gpu.clone())),
+            draw_args: Mutex::new(None),
+            stats: Mutex::new(None),
+            gpu,
+        })
+    }
+
+    /// Uploads the draw arguments, growing the buffer if needed
+    fn write_draw_args(&self, args: &[u32]) -> anyhow::Result<ID3D11Buffer> {
+        let mut draw_args = self.draw_args.lock();
+        if draw_args.as_ref().map_or(true, |b| b.len < args.len()) {
+            *draw_args = Some(UintBuffer::create(
+                &self.gpu,
+                args.len()
+                    .next_power_of_two()
+                    .max(64 * DRAW_ARGS_DWORDS as usize),
+                D3D11_BIND_FLAG(0),
+                D3D11_RESOURCE_MISC_DRAWINDIRECT_ARGS,
+                "Light_Cluster_Draw_Args",
+            )?);
+        }
+
+        let buffer = draw_args.as_ref().unwrap().buffer.clone();
+        unsafe {
+            self.gpu.lock_context().UpdateSubresource(
+                &buffer,
+                0,
+                Some(&D3D11_BOX {
+                    left: 0,
+                    top: 0,
+                    front: 0,
+                    right: (args.len() * 4) as u32,
+                    bottom: 1,
+                    back: 1,
+                }),
+                args.as_ptr() as _,
+                0,
+                0,
+            );
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl Renderer {
+    /// Assigns the given light volumes to clusters, and culls the lights that don't touch any
+    /// cluster with visible geometry. `volumes` holds the transform and index count of every light
+    /// in the order they're drawn, `None` for lights that are hidden
+    pub(crate) fn cull_light_volumes(
+        &self,
+        volumes: &[Option<(Mat4, u32)>],
+    ) -> Option<LightOcclusionDraws> {
+        let view = self.data.lock().externs.view.clone()?;
+        let grid = ClusterGrid::new(&view);
+
+        let mut ranges = vec![];
+        let mut args = vec![];
+        let mut culled = 0;
+        let offsets = volumes
+            .iter()
+            .map(|volume| {
+                let (transform, index_count) = (*volume)?;
+                let Some(range) = grid.volume_range(&transform) else {
+                    culled += 1;
+                    return None;
+                };
+
+                let offset = args.len() as u32 * 4;
+                ranges.push(range);
+                // Instance count is set to 1 by the culling pass
+                args.extend_from_slice(&[index_count, 0, 0, 0, 0]);
+                Some(offset)
+            })
+            .collect();
+
+        let (lists, indices) = build_cluster_lists(&ranges);
+        *self.light_occlusion.stats.lock() = Some(LightOcclusionStats {
+            lights: ranges.len(),
+            culled,
+            max_lights_per_cluster: lists.iter().map(|l| l[1]).max().unwrap_or_default(),
+        });
+
+        let clusters = &self.light_occlusion;
+        clusters
+            .params_cb
+            .write(&LightClusterParams {
+                target_pixel_to_world: view.target_pixel_to_world,
+                camera_pos: view.position,
+                camera_forward: grid.camera_forward.extend(0.0),
+                cluster_params: Vec4::new(
+                    view.resolution_width,
+                    view.resolution_height,
+                    grid.slice_scale,
+                    grid.slice_bias,
+                ),
+                heatmap_max: self.settings().light_volume_heatmap_max.max(1),
+                _pad: [0; 3],
+            })
+            .ok();
+
+        let mut cluster_lists = clusters.cluster_lists.lock();
+        let mut light_indices = clusters.light_indices.lock();
+        if let Err(e) = cluster_lists
+            .write(&lists)
+            .and_then(|_| light_indices.write(&indices))
+        {
+            error!("Failed to write light cluster lists: {e:?}");
+            return None;
+        }
+
+        if ranges.is_empty() {
+            return Some(LightOcclusionDraws {
+                args: None,
+                offsets,
+            });
+        }
+
+        let args = match clusters.write_draw_args(&args) {
+            Ok(args) => args,
+            Err(e) => {
+                error!("Failed to write light draw arguments: {e:?}");
+                return None;
+            }
+        };
+        let args_uav = clusters.draw_args.lock().as_ref()?.uav.clone();
+
+        gpu_event!(self.gpu, "light_occlusion_culling");
+        clusters.params_cb.bind(0, TfxShaderStage::Compute);
+        unsafe {
+            let depth = self.data.lock().gbuffers.depth.texture_view.clone();
+            let ctx = self.gpu.lock_context();
+            ctx.ClearUnorderedAccessViewUint(&clusters.occupancy.uav, &[0; 4]);
+
+            ctx.CSSetShader(&clusters.occupancy_cs, None);
+            ctx.CSSetShaderResources(0, Some(&[Some(depth)]));
+            ctx.CSSetUnorderedAccessViews(0, 1, Some(&Some(clusters.occupancy.uav.clone())), None);
+            ctx.Dispatch(
+                (view.resolution_width as u32).div_ceil(8),
+                (view.resolution_height as u32).div_ceil(8),
+                1,
+            );
+            ctx.CSSetUnorderedAccessViews(0, 1, Some(&None), None);
+
+            ctx.CSSetShader(&clusters.cull_cs, None);
+            ctx.CSSetShaderResources(
+                0,
+                Some(&[
+                    clusters.occupancy.srv.clone(),
+                    cluster_lists.srv().cloned(),
+                    light_indices.srv().cloned(),
+                ]),
+            );
+            ctx.CSSetUnorderedAccessViews(0, 1, Some(&Some(args_uav)), None);
+            ctx.Dispatch((CLUSTER_COUNT as u32).div_ceil(64), 1, 1);
+
+            ctx.CSSetUnorderedAccessViews(0, 1, Some(&None), None);
+            ctx.CSSetShaderResources(0, Some(&[None, None, None]));
+            ctx.CSSetShader(None, None);
+        }
+
+        Some(LightOcclusionDraws {
+            args: Some(args),
+            offsets,
+        })
+    }
+
+    /// Light counts of the last cluster assignment, `None` if light occlusion culling isn't in use
+    pub fn light_occlusion_stats(&self) -> Option<LightOcclusionStats> {
+        *self.light_occlusion.stats.lock()
+    }
+
+    /// Colors every pixel by the number of light volumes overlapping its cluster
+    pub(super) fn draw_light_volume_count_view(&self) {
+        let Some(lists) = self.light_occlusion.cluster_lists.lock().srv().cloned() else {
+            return;
+        };
+
+        self.light_occlusion
+            .params_cb
+            .bind(0, TfxShaderStage::Pixel);
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+        self.gpu.flush_states();
+
+        unsafe {
+            let depth = self.data.lock().gbuffers.depth.texture_view.clone();
+            let ctx = self.gpu.lock_context();
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.RSSetState(None);
+            ctx.VSSetShader(&self.gpu.util_resources.blit_vs, None);
+            ctx.PSSetShader(&self.light_occlusion.debug_ps, None);
+            ctx.PSSetShaderResources(0, Some(&[Some(depth), Some(lists)]));
+        }
+        self.gpu.set_input_topology(EPrimitiveType::Triangles);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.Draw(3, 0);
+            ctx.PSSetShaderResources(0, Some(&[None, None]));
+        }
+    }
+}
+
+#[test]
+fn test_cluster_lists() {
+    let ranges = [
+        (UVec3::ZERO, UVec3::new(1, 0, 0)),
+        (UVec3::new(1, 0, 0), UVec3::new(1, 1, 0)),
+    ];
+
+    let (lists, indices) = build_cluster_lists(&ranges);
+    assert_eq!(lists[cluster_index(0, 0, 0)], [0, 1]);
+    assert_eq!(lists[cluster_index(1, 0, 0)], [1, 2]);
+    assert_eq!(lists[cluster_index(1, 1, 0)], [3, 1]);
+    assert_eq!(lists[cluster_index(2, 0, 0)], [3, 0]);
+    assert_eq!(indices, [0, 0, 1, 1]);
+}
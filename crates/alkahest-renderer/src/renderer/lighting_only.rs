@@ -0,0 +1,126 @@
+use alkahest_data::{geometry::EPrimitiveType, tfx::TfxShaderStage};
+use anyhow::Context;
+use glam::Vec4;
+use windows::Win32::{
+    Foundation::BOOL,
+    Graphics::Direct3D11::{
+        ID3D11BlendState, ID3D11PixelShader, D3D11_BLEND_DESC, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
+        D3D11_BLEND_ZERO, D3D11_COLOR_WRITE_ENABLE_BLUE, D3D11_COLOR_WRITE_ENABLE_GREEN,
+        D3D11_COLOR_WRITE_ENABLE_RED, D3D11_RENDER_TARGET_BLEND_DESC,
+    },
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::Renderer,
+};
+
+/// Replaces the surface color in the GBuffer with grey for the lighting-only viewport shading, so
+/// every light, shadow and cubemap still gets applied as usual
+pub struct LightingOnlyRenderer {
+    pixel_shader: ID3D11PixelShader,
+    /// Only writes the color channels of RT0, and the emission channel of RT2
+    blend_state: ID3D11BlendState,
+    albedo_cb: ConstantBuffer<Vec4>,
+}
+
+impl LightingOnlyRenderer {
+    pub fn new(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        let pixel_shader = gpu
+            .device
+            .load_pixel_shader(include_dxbc!(ps "misc/lighting_only.hlsl"))?;
+
+        let write_mask = |mask: u8| D3D11_RENDER_TARGET_BLEND_DESC {
+            BlendEnable: BOOL(0),
+            SrcBlend: D3D11_BLEND_ONE,
+            DestBlend: D3D11_BLEND_ZERO,
+            BlendOp: D3D11_BLEND_OP_ADD,
+            SrcBlendAlpha: D3D11_BLEND_ONE,
+            DestBlendAlpha: D3D11_BLEND_ZERO,
+            BlendOpAlpha: D3D11_BLEND_OP_ADD,
+            RenderTargetWriteMask: mask,
+        };
+        let color_mask = (D3D11_COLOR_WRITE_ENABLE_RED.0
+            | D3D11_COLOR_WRITE_ENABLE_GREEN.0
+            | D3D11_COLOR_WRITE_ENABLE_BLUE.0) as u8;
+
+        let mut blend_state = None;
+        unsafe {
+            gpu.device.CreateBlendState(
+                &D3D11_BLEND_DESC {
+                    AlphaToCoverageEnable: BOOL(0),
+                    IndependentBlendEnable: BOOL(1),
+                    RenderTarget: [
+                        write_mask(color_mask),
+                        write_mask(D3D11_COLOR_WRITE_ENABLE_GREEN.0 as u8),
+                        write_mask(0),
+                        write_mask(0),
+                        write_mask(0),
+                        write_mask(0),
+                        write_mask(0),
+                        write_mask(0),
+                    ],
+                },
+                Some(&mut blend_state),
+            )?;
+        }
+
+        Ok(Self {
+            pixel_shader,
+            blend_state: blend_state.context("Failed to create lighting-only blend state")?,
+            albedo_cb: ConstantBuffer::create(gpu, None)?,
+        })
+    }
+}
+
+impl Renderer {
+    /// Overwrites the surface color of everything in the GBuffer with grey, and the emission as
+    /// well unless it is kept. Expects the depth buffer copy to be up to date
+    pub(super) fn draw_lighting_only_albedo(&self) {
+        let settings = self.settings();
+        if !settings.is_lighting_only() {
+            return;
+        }
+
+        gpu_event!(self.gpu, "lighting_only_albedo");
+        let albedo = settings.lighting_only_albedo;
+        self.lighting_only
+            .albedo_cb
+            .write(&Vec4::new(albedo, albedo, albedo, 1.0))
+            .ok();
+        self.lighting_only.albedo_cb.bind(0, TfxShaderStage::Pixel);
+
+        unsafe {
+            let data = self.data.lock();
+            let ctx = self.gpu.lock_context();
+            ctx.OMSetRenderTargets(
+                Some(&[
+                    Some(data.gbuffers.rt0.render_target.clone()),
+                    (!settings.lighting_only_emission)
+                        .then(|| data.gbuffers.rt2.render_target.clone()),
+                ]),
+                None,
+            );
+            ctx.OMSetBlendState(&self.lighting_only.blend_state, None, 0xFFFFFFFF);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.RSSetState(None);
+
+            ctx.VSSetShader(&self.gpu.util_resources.blit_vs, None);
+            ctx.PSSetShader(&self.lighting_only.pixel_shader, None);
+            ctx.PSSetShaderResources(
+                0,
+                Some(&[Some(data.gbuffers.depth.texture_copy_view.clone())]),
+            );
+        }
+        self.gpu.set_input_topology(EPrimitiveType::Triangles);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.Draw(3, 0);
+            ctx.PSSetShaderResources(0, Some(&[None]));
+        }
+
+        self.gpu.flush_states();
+    }
+}
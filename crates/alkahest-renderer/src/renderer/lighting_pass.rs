@@ -1,3 +1,5 @@
+use std::sync::atomic::Ordering;
+
 use alkahest_data::technique::StateSelection;
 use glam::Vec4;
 
@@ -11,6 +13,7 @@ use crate::{
 impl Renderer {
     pub fn draw_lighting_pass(&self, scene: &mut Scene) {
         gpu_profile_event!(self.gpu, "lighting_pass");
+        let settings = self.settings();
 
         unsafe {
             let data = &mut self.data.lock();
@@ -46,11 +49,11 @@ impl Renderer {
         }
 
         {
-            if self.settings.matcap {
+            if settings.effective_matcap() {
                 gpu_event!(self.gpu, "matcap");
                 self.matcap.draw(self);
             } else {
-                if self.settings.feature_global_lighting {
+                if settings.feature_global_lighting {
                     gpu_profile_event!(self.gpu, "global_lighting");
 
                     self.gpu.current_states.store(StateSelection::new(
@@ -76,7 +79,7 @@ impl Renderer {
                     draw_light_system(self, scene)
                 }
 
-                if self.settings.feature_cubemaps {
+                if settings.cubemaps_enabled() {
                     unsafe {
                         let data = &mut self.data.lock();
                         self.gpu.lock_context().OMSetRenderTargets(
@@ -94,8 +97,24 @@ impl Renderer {
             }
         }
 
+        if self.ssr_enabled() {
+            gpu_profile_event!(self.gpu, "ssr");
+            self.ssr
+                .draw(self, self.frame_index.load(Ordering::Relaxed));
+        } else {
+            self.ssr.reset_history();
+        }
+
+        if settings.ssgi && !settings.effective_matcap() && !self.is_probing() {
+            gpu_profile_event!(self.gpu, "ssgi");
+            self.ssgi
+                .draw(self, self.frame_index.load(Ordering::Relaxed));
+        } else {
+            self.ssgi.reset_history();
+        }
+
         {
-            if self.settings.ssao {
+            if settings.ssao {
                 gpu_profile_event!(self.gpu, "ssao");
                 self.ssao.draw(self);
             }
@@ -117,7 +136,7 @@ impl Renderer {
         {
             gpu_event!(self.gpu, "deferred_shading");
             let pipeline = if scene.get_resource::<MapAtmosphere>().is_some()
-                && self.settings.feature_atmosphere
+                && self.settings().feature_atmosphere
             {
                 &self.render_globals.pipelines.deferred_shading
             } else {
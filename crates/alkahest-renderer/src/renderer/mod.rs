@@ -1,24 +1,51 @@
+mod antialiasing;
+pub use antialiasing::AntiAliasing;
+mod capture;
+pub use capture::{CapturedCubemap, CapturedPixels, CapturedTarget};
+mod clip_planes;
+pub use clip_planes::MAX_CLIP_PLANES;
 mod cubemaps;
+mod debug_view;
+mod frame_budget;
+pub use frame_budget::BudgetDegradation;
 pub mod gbuffer;
 mod immediate;
+mod impostors;
+mod light_occlusion;
+pub use light_occlusion::LightOcclusionStats;
+mod lighting_only;
 use crossbeam::atomic::AtomicCell;
-use glam::{Mat4, Quat};
+use glam::{Mat4, Quat, Vec3};
 pub use immediate::{ImmediateLabel, LabelAlign};
 mod lighting_pass;
 mod opaque_pass;
 mod pickbuffer;
+pub use pickbuffer::PickLayer;
 mod postprocess;
+mod search_highlight;
 pub mod shader;
 mod shadows;
+mod sky;
 pub use shadows::{ShadowPcfSamples, ShadowQuality};
+pub use sky::{SkyIsolation, SkyReplacement};
+mod stats;
+pub use stats::{FeatureStats, FrameStats, RenderStats, StatsCategory, StatsSource};
+mod stereo;
+pub use stereo::{StereoEye, StereoEyeView};
 mod systems;
+mod target_viewer;
+pub use target_viewer::{TargetChannels, TargetViewRequest};
+mod tonemap;
+pub use tonemap::{OutputMode, Tonemapper};
 mod transparents_pass;
 mod util;
+mod volumetrics;
+mod wireframe;
 
 use std::{
     ops::Deref,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
     time::{Duration, Instant},
@@ -30,34 +57,43 @@ use alkahest_data::{
     tfx::{TfxFeatureRenderer, TfxRenderStage, TfxShaderStage},
 };
 use anyhow::Context;
-use bevy_ecs::system::{Resource, RunSystemOnce};
+use bevy_ecs::system::Resource;
 use bitflags::bitflags;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use strum::{EnumCount, EnumIter};
 use windows::Win32::Graphics::Direct3D11::D3D11_VIEWPORT;
 
 use crate::{
+    camera::Camera,
     ecs::{
-        render::{havok::draw_debugshapes_system, light::ShadowGenerationMode},
+        render::{light::ShadowGenerationMode, lod::LodSelectionParams},
         resources::SelectedEntity,
+        schedule::{run_frame_schedule, DrawOverlays, ViewUpdate},
         tags::NodeFilterSet,
         transform::Transform,
-        utility::draw_utilities_system,
-        visibility::{calculate_view_visibility_system, ViewVisibility, VisibilityHelper},
+        visibility::{ViewVisibility, VisibilityHelper, VisibilityView},
         Scene,
     },
     gpu::SharedGpuContext,
     gpu_event, gpu_profile_event,
     handle::Handle,
     loaders::AssetManager,
-    postprocess::ssao::SsaoRenderer,
+    postprocess::{
+        bloom::BloomRenderer, dof::DofRenderer, ssao::SsaoRenderer, ssgi::SsgiRenderer,
+        ssr::SsrRenderer, EffectQuality,
+    },
     renderer::{
-        cubemaps::CubemapRenderer, gbuffer::GBuffer, immediate::ImmediateRenderer,
-        pickbuffer::Pickbuffer,
+        antialiasing::AntiAliasingRenderer, clip_planes::ClipPlaneRenderer,
+        cubemaps::CubemapRenderer, debug_view::DebugViewRenderer, frame_budget::FrameBudget,
+        gbuffer::GBuffer, immediate::ImmediateRenderer, impostors::ImpostorRenderer,
+        light_occlusion::LightOcclusionRenderer, lighting_only::LightingOnlyRenderer,
+        pickbuffer::Pickbuffer, sky::SkyReplacementRenderer, target_viewer::TargetViewer,
+        tonemap::TonemapRenderer, volumetrics::VolumetricsRenderer,
+        wireframe::WireframeOverlayRenderer,
     },
     resources::AppResources,
-    shader::matcap::MatcapRenderer,
+    shader::{matcap::MatcapRenderer, shader_ball::ShaderBallRenderer},
     tfx::{
         externs::{self, ExternStorage, Frame},
         globals::RenderGlobals,
@@ -65,7 +101,6 @@ use crate::{
         technique::Technique,
         view::View,
     },
-    util::Hocus,
     Color,
 };
 
@@ -92,23 +127,58 @@ pub struct Renderer {
     pub render_globals: RenderGlobals,
     pub data: Mutex<RendererData>,
 
-    pub settings: RendererSettings,
+    /// Settings in effect for the current frame, with the features degraded by the frame budget
+    /// turned down
+    settings: RwLock<Arc<RendererSettings>>,
+    frame_budget: Mutex<FrameBudget>,
+    frame_budget_skip: AtomicBool,
 
     pub ssao: SsaoRenderer,
+    ssgi: SsgiRenderer,
+    ssr: SsrRenderer,
+    dof: DofRenderer,
+    bloom: BloomRenderer,
     matcap: MatcapRenderer,
+    /// Shared sphere mesh for every [`LightProbeSphere`](crate::shader::shader_ball::LightProbeSphere)
+    pub light_probe_sphere: ShaderBallRenderer,
     pub immediate: ImmediateRenderer,
     cubemap_renderer: CubemapRenderer,
+    debug_views: DebugViewRenderer,
+    tonemap: TonemapRenderer,
+    antialiasing: AntiAliasingRenderer,
+    wireframe: WireframeOverlayRenderer,
+    impostors: ImpostorRenderer,
+    lighting_only: LightingOnlyRenderer,
+    light_occlusion: LightOcclusionRenderer,
+    target_viewer: TargetViewer,
+    sky_replacement: SkyReplacementRenderer,
+    volumetrics: VolumetricsRenderer,
+    pub clip_planes: ClipPlaneRenderer,
     pub pickbuffer: Pickbuffer,
+    pub stats: RenderStats,
 
     pub time: AtomicCell<Time>,
-    last_frame: Instant,
-    pub delta_time: f64,
+    last_frame: Mutex<Instant>,
+    pub delta_time: AtomicCell<f64>,
     pub frame_index: AtomicUsize,
 
-    pub active_view: usize,
+    active_view: AtomicUsize,
+    /// Set while rendering a depth probe, which skips everything that isn't scene geometry
+    probing: AtomicBool,
+    stereo_eye: AtomicCell<Option<StereoEye>>,
     // Hacky way to obtain these filters for now
-    pub lastfilters: NodeFilterSet,
-    pub active_shadow_generation_mode: ShadowGenerationMode,
+    pub lastfilters: RwLock<NodeFilterSet>,
+    pub active_shadow_generation_mode: AtomicCell<ShadowGenerationMode>,
+    /// Screen coverage of the model that's being drawn, see [`Self::draw_coverage_scope`]
+    draw_coverage: AtomicCell<f32>,
+}
+
+pub struct DrawCoverageScope<'a>(&'a Renderer);
+
+impl Drop for DrawCoverageScope<'_> {
+    fn drop(&mut self) {
+        self.0.draw_coverage.store(f32::INFINITY);
+    }
 }
 
 pub struct RendererData {
@@ -137,33 +207,80 @@ impl Renderer {
                 externs: ExternStorage::default(),
             }),
             ssao: SsaoRenderer::new(gpu.clone()).context("failed to create SsaoRenderer")?,
+            ssgi: SsgiRenderer::new(gpu.clone()).context("failed to create SsgiRenderer")?,
+            ssr: SsrRenderer::new(gpu.clone()).context("failed to create SsrRenderer")?,
+            dof: DofRenderer::new(gpu.clone()).context("failed to create DofRenderer")?,
+            bloom: BloomRenderer::new(gpu.clone()).context("failed to create BloomRenderer")?,
             matcap: MatcapRenderer::new(gpu.clone()).context("failed to create MatcapRenderer")?,
+            light_probe_sphere: ShaderBallRenderer::sphere(&gpu)
+                .context("failed to create light probe sphere")?,
             immediate: ImmediateRenderer::new(gpu.clone())
                 .context("failed to create ImmediateRenderer")?,
             cubemap_renderer: CubemapRenderer::new(gpu.clone())
                 .context("failed to create CubemapRenderer")?,
+            debug_views: DebugViewRenderer::new(gpu.clone())
+                .context("failed to create DebugViewRenderer")?,
+            tonemap: TonemapRenderer::new(gpu.clone())
+                .context("failed to create TonemapRenderer")?,
+            antialiasing: AntiAliasingRenderer::new(gpu.clone())
+                .context("failed to create AntiAliasingRenderer")?,
+            wireframe: WireframeOverlayRenderer::new(gpu.clone())
+                .context("failed to create WireframeOverlayRenderer")?,
+            impostors: ImpostorRenderer::new(gpu.clone())
+                .context("failed to create ImpostorRenderer")?,
+            lighting_only: LightingOnlyRenderer::new(gpu.clone())
+                .context("failed to create LightingOnlyRenderer")?,
+            light_occlusion: LightOcclusionRenderer::new(gpu.clone())
+                .context("failed to create LightOcclusionRenderer")?,
+            target_viewer: TargetViewer::new(gpu.clone())
+                .context("failed to create TargetViewer")?,
+            sky_replacement: SkyReplacementRenderer::new(gpu.clone())
+                .context("failed to create SkyReplacementRenderer")?,
+            volumetrics: VolumetricsRenderer::new(gpu.clone())
+                .context("failed to create VolumetricsRenderer")?,
+            clip_planes: ClipPlaneRenderer::new(gpu.clone())
+                .context("failed to create ClipPlaneRenderer")?,
             pickbuffer: Pickbuffer::new(gpu.clone(), window_size)
                 .context("failed to create Pickbuffer")?,
+            stats: RenderStats::default(),
             gpu,
             render_globals,
-            settings: RendererSettings::default(),
+            settings: RwLock::new(Arc::new(RendererSettings::default())),
+            frame_budget: Mutex::new(FrameBudget::default()),
+            frame_budget_skip: AtomicBool::new(false),
             time: AtomicCell::new(Time::now()),
-            last_frame: Instant::now(),
-            delta_time: 0.0,
+            last_frame: Mutex::new(Instant::now()),
+            delta_time: AtomicCell::new(0.0),
             frame_index: AtomicUsize::default(),
-            active_shadow_generation_mode: ShadowGenerationMode::StationaryOnly,
-            lastfilters: NodeFilterSet::default(),
-            active_view: 0,
+            active_shadow_generation_mode: AtomicCell::new(ShadowGenerationMode::StationaryOnly),
+            lastfilters: RwLock::new(NodeFilterSet::default()),
+            active_view: AtomicUsize::new(0),
+            probing: AtomicBool::new(false),
+            stereo_eye: AtomicCell::new(None),
+            draw_coverage: AtomicCell::new(f32::INFINITY),
         })))
     }
 
+    /// Marks every texture bound until the returned scope is dropped as being used by a model
+    /// covering `coverage` of the screen, which decides how many mips it needs when streamed in
+    pub fn draw_coverage_scope(&self, coverage: f32) -> DrawCoverageScope<'_> {
+        self.draw_coverage.store(coverage);
+        DrawCoverageScope(self)
+    }
+
+    /// Screen coverage of the model that's being drawn, infinite outside of model draws
+    pub fn draw_coverage(&self) -> f32 {
+        self.draw_coverage.load()
+    }
+
     pub fn get_technique_shared(&self, handle: &Handle<Technique>) -> Option<Arc<Technique>> {
         let data = self.data.lock();
         data.asset_manager.techniques.get_shared(handle)
     }
 
     pub fn render_world(&self, view: &impl View, scene: &mut Scene, resources: &AppResources) {
-        self.pocus().lastfilters = resources.get::<NodeFilterSet>().clone();
+        let settings = self.settings();
+        *self.lastfilters.write() = resources.get::<NodeFilterSet>().clone();
 
         // Make sure immediate labels have been drained completely
         let _ = self.immediate.drain_labels();
@@ -171,40 +288,98 @@ impl Renderer {
         self.begin_world_frame(scene);
 
         let frustum = view.frustum();
-        scene.run_system_once_with(frustum, calculate_view_visibility_system);
+        // Captures always include full detail
+        let fade_position = (settings.detail_fade && !self.is_probing()).then(|| view.position());
+        scene.insert_resource(VisibilityView {
+            frustum,
+            fade_position,
+        });
+
+        // Same goes for LODs
+        let lod_params = (settings.auto_lod && !self.is_probing()).then(|| {
+            let mut x = externs::View::default();
+            view.update_extern(&mut x);
+            LodSelectionParams {
+                view_position: view.position(),
+                projection_scale: x.camera_to_projective.y_axis.y,
+                bias: settings.lod_bias,
+            }
+        });
+        match lod_params {
+            Some(params) => scene.insert_resource(params),
+            None => {
+                scene.remove_resource::<LodSelectionParams>();
+            }
+        }
+        run_frame_schedule(scene, ViewUpdate);
 
         self.update_shadow_maps(scene);
+        self.update_impostors(scene, view.position());
 
         {
             gpu_profile_event!(self.gpu, "view_0");
-            self.bind_view(view, 0);
+            let jitter = self.anti_aliasing() == AntiAliasing::Taa;
+            self.bind_view(view, 0, jitter);
 
             self.draw_atmosphere(scene);
             // if self.render_settings.depth_prepass {
             //     self.draw_depth_prepass(scene);
             // }
+            let wireframe = settings.shading == ViewportShading::Wireframe;
+            self.gpu.set_wireframe_override(wireframe);
+            self.begin_clip_planes(scene);
             self.draw_opaque_pass(scene);
+            self.end_clip_planes();
+            self.gpu.set_wireframe_override(false);
             self.draw_lighting_pass(scene);
             self.draw_shading_pass(scene);
+            if self.volumetrics_enabled() {
+                self.draw_volumetrics(scene);
+            }
+            self.gpu.set_wireframe_override(wireframe);
+            self.begin_clip_planes(scene);
             self.draw_transparents_pass(scene);
+            self.end_clip_planes();
+            self.gpu.set_wireframe_override(false);
+            if self.ssr_enabled() {
+                self.ssr.store_history(self);
+            }
+            if settings.effective_debug_view() == RenderDebugView::Overdraw && !self.is_probing() {
+                self.draw_overdraw_counts(scene);
+            }
 
             self.draw_postprocessing_pass(scene);
+            if jitter {
+                // Overlays and picking are drawn without the TAA jitter
+                self.bind_view(view, 0, false);
+            }
 
-            if self.pickbuffer.selection_request.load().is_some() {
+            // The pick buffer only matches the window when rendering a single view
+            if !self.is_probing()
+                && self.stereo_eye().is_none()
+                && self.pickbuffer.selection_request.load().is_some()
+            {
                 self.draw_pickbuffer(scene, resources.get::<SelectedEntity>().selected());
             }
         }
 
-        if self.settings.debug_view.is_gamma_converter() {
+        let debug_view_split = self.debug_view_split();
+        if self.final_output_is_gamma_corrected() && !self.is_probing() {
             self.draw_view_overlay(scene, resources);
         }
 
+        if settings.exposure != 0.0 {
+            self.tonemap.draw_exposure(self);
+        }
+
         unsafe {
             {
                 let mut data = self.data.lock();
-                data.gbuffers
-                    .shading_result
-                    .copy_to(&data.gbuffers.shading_result_read);
+                if settings.exposure == 0.0 {
+                    data.gbuffers
+                        .shading_result
+                        .copy_to(&data.gbuffers.shading_result_read);
+                }
                 data.externs.postprocess = Some(externs::Postprocess {
                     unk00: data.gbuffers.shading_result_read.view.clone().into(),
                     ..Default::default()
@@ -220,31 +395,66 @@ impl Renderer {
             }
 
             gpu_profile_event!(self.gpu, "final_or_debug_view");
-            let pipeline = self
+            if self.uses_custom_tonemapper() {
+                let target = self
+                    .data
+                    .lock()
+                    .gbuffers
+                    .shading_result
+                    .render_target
+                    .clone();
+                self.tonemap.draw_tonemap(self, &target);
+            } else if let Some(pipeline) = self
                 .render_globals
                 .pipelines
-                .get_debug_view_pipeline(self.settings.debug_view);
+                .get_debug_view_pipeline(settings.effective_debug_view())
+            {
+                self.gpu.current_states.store(StateSelection::new(
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                ));
+                self.execute_global_pipeline(pipeline, "final_or_debug_view");
+            } else if settings.effective_debug_view() == RenderDebugView::CubemapInfluence {
+                self.debug_views.draw_cubemap_influence(self, scene);
+            } else if settings.effective_debug_view() == RenderDebugView::Overdraw {
+                self.debug_views.draw_overdraw(self);
+            } else if settings.effective_debug_view() == RenderDebugView::LightVolumeCounts {
+                self.draw_light_volume_count_view();
+            }
 
-            self.gpu
-                .current_states
-                .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
-            self.execute_global_pipeline(pipeline, "final_or_debug_view");
+            if settings.effective_debug_view() == RenderDebugView::None {
+                self.draw_sky_replacement();
+            }
+
+            if let Some(split) = debug_view_split {
+                gpu_event!(self.gpu, "debug_view_compare");
+                {
+                    let data = self.data.lock();
+                    data.gbuffers
+                        .shading_result
+                        .copy_to(&data.gbuffers.debug_view_compare);
+                }
+
+                self.gpu.current_states.store(StateSelection::new(
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                ));
+                self.execute_global_pipeline(
+                    &self.render_globals.pipelines.final_combine,
+                    "final_combine",
+                );
+                self.debug_views.draw_split(self, split);
+            }
         }
 
-        if !self.settings.debug_view.is_gamma_converter() {
+        if !self.final_output_is_gamma_corrected() && !self.is_probing() {
             self.draw_view_overlay(scene, resources);
         }
 
-        self.gpu.blit_texture(
-            &self.data.lock().gbuffers.shading_result.view,
-            self.gpu.swapchain_target.read().as_ref().unwrap(),
-            // final_combine and final_combine_no_film_curve already apply gamma correction
-            !matches!(
-                self.settings.debug_view,
-                RenderDebugView::None | RenderDebugView::NoFilmCurve
-            ),
-        );
-
         {
             let data = self.data.lock();
             data.gbuffers
@@ -252,9 +462,100 @@ impl Renderer {
                 .copy_to_staging(&data.gbuffers.depth_staging);
         }
 
+        if self.is_probing() {
+            return;
+        }
+
+        self.draw_target_view(scene);
+
+        // Headless contexts don't have a swapchain to present to
+        if let Some(swapchain_target) = self.gpu.swapchain_target.read().as_ref() {
+            self.bind_stereo_output_viewport(view.viewport().size.x as f32);
+            self.present_final_image(swapchain_target);
+        }
+
+        // A stereo frame isn't finished until the right eye has been rendered
+        if self.stereo_eye() == Some(StereoEye::Left) {
+            return;
+        }
+
+        self.stats.end_frame(&self.data.lock().asset_manager);
         self.frame_index.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Renders the scene from the given camera without any overlays, and returns the distance to
+    /// and the position of the geometry in the center of the view.
+    /// Used to test visibility between two points against the actual scene geometry.
+    pub fn probe_depth(
+        &self,
+        camera: &Camera,
+        scene: &mut Scene,
+        resources: &AppResources,
+    ) -> (f32, Vec3) {
+        self.probe(|| self.render_world(camera, scene, resources));
+
+        self.data
+            .lock()
+            .gbuffers
+            .depth_buffer_distance_pos_center(camera)
+    }
+
+    /// Runs `f` with overlays, presenting and temporal effects disabled for any frame it renders
+    fn probe<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.probing.store(true, Ordering::Relaxed);
+        let result = f();
+        self.probing.store(false, Ordering::Relaxed);
+
+        // The probe has nothing in common with the next frame
+        self.antialiasing.reset_history();
+        self.ssgi.reset_history();
+        self.ssr.reset_history();
+
+        result
+    }
+
+    fn is_probing(&self) -> bool {
+        self.probing.load(Ordering::Relaxed)
+    }
+
+    /// Reflections need the previous frame, which probes don't have
+    fn ssr_enabled(&self) -> bool {
+        let settings = self.settings();
+        settings.ssr && !settings.effective_matcap() && !self.is_probing()
+    }
+
+    /// The anti-aliasing mode for the current frame. Probes can't use TAA, as they don't have any
+    /// history to blend with, and stereo eyes would blend with each other's history
+    fn anti_aliasing(&self) -> AntiAliasing {
+        match self.settings().anti_aliasing {
+            AntiAliasing::Taa if self.is_probing() || self.stereo_eye().is_some() => {
+                AntiAliasing::None
+            }
+            mode => mode,
+        }
+    }
+
+    /// Whether the final image needs gamma correction when blitting it to the output
+    fn final_blit_srgb(&self) -> bool {
+        !self.final_output_is_gamma_corrected()
+    }
+
+    /// Whether the shading result is gamma corrected after the final/debug view pass
+    fn final_output_is_gamma_corrected(&self) -> bool {
+        // final_combine and final_combine_no_film_curve already apply gamma correction, and the
+        // debug view compare converts the debug view half itself
+        self.settings().effective_debug_view().is_gamma_converter()
+            || self.debug_view_split().is_some()
+    }
+
+    /// Fraction of the screen covered by the debug view when it's being compared against the final image
+    fn debug_view_split(&self) -> Option<f32> {
+        let settings = self.settings();
+        let split = settings.debug_view_split;
+        (!settings.effective_debug_view().is_gamma_converter() && split < 1.0)
+            .then_some(split.max(0.0))
+    }
+
     fn draw_view_overlay(&self, scene: &mut Scene, resources: &AppResources) {
         gpu_profile_event!(self.gpu, "view_overlay");
 
@@ -271,19 +572,17 @@ impl Renderer {
             );
         }
 
-        // TODO(cohae): Move debug shapes to a separate system
-        scene.run_system_once_with(
-            resources.get::<RendererShared>().clone(),
-            draw_debugshapes_system,
-        );
-        scene.run_system_once_with(
-            resources.get::<RendererShared>().clone(),
-            draw_utilities_system,
-        );
-        // scene.run_system_once_with(resources.get::<RendererShared>().clone(), draw_aabb_system);
+        self.draw_lod_overlay(scene);
+        self.draw_terrain_material_overlay(scene);
+        self.draw_wireframe_overlay(scene);
+
+        scene.insert_resource(resources.get::<RendererShared>().clone());
+        run_frame_schedule(scene, DrawOverlays);
+
+        self.draw_search_highlight(scene, resources);
 
         if let Some(selected) = resources.get::<SelectedEntity>().selected() {
-            if self.settings.draw_selection_outline
+            if self.settings().draw_selection_outline
                 && scene
                     .get_entity(selected)
                     .map_or(true, |v| v.get::<ViewVisibility>().is_visible(0))
@@ -317,14 +616,35 @@ impl Renderer {
             }
         }
 
+        // The wireframe overlay and selection outline change the pipeline state, so it has to be set
+        // up again for the debug shapes
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(2), Some(2), Some(1)));
+        self.gpu.flush_states();
+        unsafe {
+            self.gpu.lock_context().OMSetRenderTargets(
+                Some(&dxstate.render_targets),
+                &self.data.lock().gbuffers.depth.view,
+            );
+        }
+        self.immediate.flush();
+
         self.gpu.restore_state(&dxstate);
     }
 
-    fn bind_view(&self, view: &impl View, index: usize) {
-        *self.active_view.pocus() = index;
+    fn bind_view(&self, view: &impl View, index: usize, jitter: bool) {
+        self.active_view.store(index, Ordering::Relaxed);
         self.data.lock().externs.view = Some({
             let mut e = externs::View::default();
             view.update_extern(&mut e);
+            if jitter {
+                self.antialiasing.jitter_view(
+                    &mut e,
+                    &view.viewport(),
+                    self.frame_index.load(Ordering::Relaxed),
+                );
+            }
             e
         });
 
@@ -348,15 +668,20 @@ impl Renderer {
     }
 
     fn begin_world_frame(&self, _scene: &Scene) {
-        self.pocus().delta_time = self.last_frame.elapsed().as_secs_f64();
-        self.pocus().last_frame = Instant::now();
+        // Both stereo eyes belong to the same frame
+        if !self.is_probing() && self.stereo_eye() != Some(StereoEye::Right) {
+            let mut last_frame = self.last_frame.lock();
+            self.delta_time.store(last_frame.elapsed().as_secs_f64());
+            *last_frame = Instant::now();
+            self.update_frame_budget(self.delta_time.load());
+        }
 
         {
             let externs = &mut self.data.lock().externs;
             externs.frame = Frame {
                 game_time: self.time.load().elapsed(),
                 render_time: self.time.load().elapsed(),
-                delta_game_time: self.delta_time as f32,
+                delta_game_time: self.delta_time.load() as f32 * self.time.load().speed(),
                 specular_lobe_3d_lookup: self
                     .render_globals
                     .textures
@@ -399,37 +724,46 @@ impl Renderer {
                 .cbuffer
                 .as_ref()
             {
-                assert!(
-                    std::mem::size_of_val(frame_cb.data_array())
-                        >= std::mem::size_of::<ScopeFrame>()
-                );
-
                 let scope_data = ScopeFrame::from(&externs.frame);
-                unsafe {
-                    (frame_cb.data_array().as_ptr() as *mut ScopeFrame).write(scope_data);
-                    let slot = self
-                        .render_globals
-                        .scopes
-                        .frame
-                        .stage_pixel
-                        .as_ref()
-                        .unwrap()
-                        .stage
-                        .constants
-                        .constant_buffer_slot as u32;
-
-                    frame_cb.bind(slot, TfxShaderStage::Pixel);
-                    frame_cb.bind(slot, TfxShaderStage::Vertex);
-                    frame_cb.bind(slot, TfxShaderStage::Compute);
+                {
+                    let mut cb_data = frame_cb.data_array();
+                    assert!(std::mem::size_of_val(&*cb_data) >= std::mem::size_of::<ScopeFrame>());
+
+                    unsafe {
+                        (cb_data.as_mut_ptr() as *mut ScopeFrame).write(scope_data);
+                    }
                 }
+
+                let slot = self
+                    .render_globals
+                    .scopes
+                    .frame
+                    .stage_pixel
+                    .as_ref()
+                    .unwrap()
+                    .stage
+                    .constants
+                    .constant_buffer_slot as u32;
+
+                frame_cb.bind(slot, TfxShaderStage::Pixel);
+                frame_cb.bind(slot, TfxShaderStage::Vertex);
+                frame_cb.bind(slot, TfxShaderStage::Compute);
             } else {
                 panic!("Frame scope does not have a pixel stage cbuffer!!");
             }
         }
     }
 
-    pub fn set_render_settings(&self, settings: RendererSettings) {
-        self.pocus().settings = settings;
+    /// Snapshot of the current render settings, including any features turned down by the frame
+    /// budget. Changes made through [`Self::set_render_settings`] are not reflected in snapshots
+    /// taken before the change.
+    pub fn settings(&self) -> Arc<RendererSettings> {
+        self.settings.read().clone()
+    }
+
+    /// Index of the view that is currently being drawn
+    pub fn active_view(&self) -> usize {
+        self.active_view.load(Ordering::Relaxed)
     }
 
     pub fn resize_buffers(&self, width: u32, height: u32) {
@@ -439,16 +773,30 @@ impl Renderer {
             .resize((width, height))
             .expect("Failed to resize GBuffer");
 
-        self.pocus()
-            .pickbuffer
+        self.pickbuffer
             .resize((width, height))
             .expect("Failed to resize Pickbuffer");
+
+        self.antialiasing.reset_history();
+        self.ssgi.reset_history();
+        self.ssr.reset_history();
     }
 
     /// Checks if we should render the given stage and feature, based on render settings
+    pub fn should_render(
+        &self,
+        stage: Option<TfxRenderStage>,
+        feature: Option<TfxFeatureRenderer>,
+    ) -> bool {
+        self.should_render_with(&self.settings(), stage, feature)
+    }
+
+    /// [`Self::should_render`] with a settings snapshot taken by the caller, for checks made per
+    /// entity
     #[rustfmt::skip]
-    pub fn should_render(&self, stage: Option<TfxRenderStage>, feature: Option<TfxFeatureRenderer>) -> bool {
-        let flags_to_check = if self.pickbuffer.is_drawing_selection {
+    pub fn should_render_with(&self, settings: &RendererSettings, stage: Option<TfxRenderStage>, feature: Option<TfxFeatureRenderer>) -> bool {
+        let shadow_generation_mode = self.active_shadow_generation_mode.load();
+        let flags_to_check = if self.pickbuffer.is_drawing_selection() {
             // An object needs to be visible for it to be selectable
             RenderFeatureVisibility::SELECTABLE | RenderFeatureVisibility::VISIBLE
         } else {
@@ -457,9 +805,9 @@ impl Renderer {
 
         // Can we render based on stages?
         let mut stages_ok = stage.map_or(true, |v| match v {
-            TfxRenderStage::Transparents => self.settings.stage_transparent,
-            TfxRenderStage::Decals => self.settings.stage_decals,
-            TfxRenderStage::DecalsAdditive => self.settings.stage_decals_additive,
+            TfxRenderStage::Transparents => settings.stage_transparent,
+            TfxRenderStage::Decals => settings.stage_decals,
+            TfxRenderStage::DecalsAdditive => settings.stage_decals_additive,
             _ => true,
         });
 
@@ -468,28 +816,34 @@ impl Renderer {
         {
             // If we're drawing terrain patches, we should only generate shadows in stationary only mode
             // StaticObjects may generate stationary as well as moving shadows, so it's not checked here
-            if feature == Some(TfxFeatureRenderer::TerrainPatch) && self.active_shadow_generation_mode != ShadowGenerationMode::StationaryOnly {
+            if feature == Some(TfxFeatureRenderer::TerrainPatch) && shadow_generation_mode != ShadowGenerationMode::StationaryOnly {
                 stages_ok = false;
             }
 
             // If we're not drawing statics (static objects/terrain), we should only generate shadows in moving only mode
-            if !matches!(feature, Some(TfxFeatureRenderer::TerrainPatch) | Some(TfxFeatureRenderer::StaticObjects)) && self.active_shadow_generation_mode != ShadowGenerationMode::MovingOnly {
+            if !matches!(feature, Some(TfxFeatureRenderer::TerrainPatch) | Some(TfxFeatureRenderer::StaticObjects)) && shadow_generation_mode != ShadowGenerationMode::MovingOnly {
                 stages_ok = false;
             }
         }
 
         let features_ok = feature.map_or(true, |v| match v {
-            TfxFeatureRenderer::StaticObjects => self.settings.feature_statics.contains(flags_to_check),
-            TfxFeatureRenderer::TerrainPatch => self.settings.feature_terrain.contains(flags_to_check),
-            TfxFeatureRenderer::RigidObject | TfxFeatureRenderer::DynamicObjects => self.settings.feature_dynamics.contains(flags_to_check),
-            TfxFeatureRenderer::SkyTransparent => self.settings.feature_sky.contains(flags_to_check),
-            TfxFeatureRenderer::Water => self.settings.feature_water.contains(flags_to_check),
-            TfxFeatureRenderer::SpeedtreeTrees => self.settings.feature_decorators.contains(flags_to_check),
-            TfxFeatureRenderer::Cubemaps => self.settings.feature_cubemaps,
+            TfxFeatureRenderer::StaticObjects => settings.feature_statics.contains(flags_to_check),
+            TfxFeatureRenderer::TerrainPatch => settings.feature_terrain.contains(flags_to_check),
+            TfxFeatureRenderer::RigidObject | TfxFeatureRenderer::DynamicObjects => settings.feature_dynamics.contains(flags_to_check),
+            TfxFeatureRenderer::SkyTransparent => {
+                settings.sky_visible() && settings.feature_sky.contains(flags_to_check)
+            }
+            TfxFeatureRenderer::Water => settings.feature_water.contains(flags_to_check),
+            TfxFeatureRenderer::SpeedtreeTrees => settings.feature_decorators.contains(flags_to_check),
+            TfxFeatureRenderer::Cubemaps => settings.feature_cubemaps,
             _ => true,
         });
 
-        stages_ok && features_ok
+        // Isolating the sky hides every other feature
+        let isolation_ok = settings.sky_isolation != SkyIsolation::SkyOnly
+            || matches!(feature, None | Some(TfxFeatureRenderer::SkyTransparent));
+
+        stages_ok && features_ok && isolation_ok
     }
 }
 
@@ -500,17 +854,228 @@ fn default_true() -> bool {
 fn default_false() -> bool {
     false
 }
+fn default_debug_view_split() -> f32 {
+    1.0
+}
+fn default_hdr_paper_white() -> f32 {
+    200.0
+}
+fn default_hdr_peak_luminance() -> f32 {
+    1000.0
+}
+
+fn default_stereo_ipd() -> f32 {
+    0.064
+}
+fn default_wireframe_overlay_bias() -> i32 {
+    10
+}
+fn default_overdraw_max() -> u32 {
+    8
+}
+fn default_light_volume_heatmap_max() -> u32 {
+    16
+}
+fn default_dof_focus_distance() -> f32 {
+    10.0
+}
+fn default_dof_blur_size() -> f32 {
+    0.01
+}
+fn default_bloom_threshold() -> f32 {
+    1.0
+}
+fn default_bloom_intensity() -> f32 {
+    0.3
+}
+fn default_ssgi_radius() -> f32 {
+    4.0
+}
+fn default_ssgi_intensity() -> f32 {
+    1.0
+}
+fn default_ssr_max_distance() -> f32 {
+    40.0
+}
+fn default_ssr_max_roughness() -> f32 {
+    0.6
+}
+fn default_volumetrics_density() -> f32 {
+    0.01
+}
+fn default_volumetrics_anisotropy() -> f32 {
+    0.4
+}
+fn default_volumetrics_height_falloff() -> f32 {
+    0.02
+}
+fn default_volumetrics_max_distance() -> f32 {
+    300.0
+}
+fn default_volumetrics_fog_color() -> [f32; 3] {
+    [0.5, 0.55, 0.6]
+}
+fn default_volumetrics_light_color() -> [f32; 3] {
+    [1.0, 0.9, 0.75]
+}
+fn default_volumetrics_light_intensity() -> f32 {
+    4.0
+}
+fn default_selection_outline_color() -> [f32; 3] {
+    [0.6, 0.36, 0.12]
+}
+fn default_selection_outline_width() -> u32 {
+    2
+}
+fn default_impostor_distance() -> f32 {
+    400.0
+}
+fn default_lighting_only_albedo() -> f32 {
+    0.18
+}
+fn default_frame_budget_fps() -> f32 {
+    30.0
+}
+fn default_sky_replacement_color() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct RendererSettings {
     pub vsync: bool,
     pub ssao: bool,
+    /// Experimental screen-space global illumination, approximates a single bounce of diffuse
+    /// lighting for areas without baked lighting
+    #[serde(default)]
+    pub ssgi: bool,
+    /// Maximum distance light is gathered from, in meters
+    #[serde(default = "default_ssgi_radius")]
+    pub ssgi_radius: f32,
+    #[serde(default = "default_ssgi_intensity")]
+    pub ssgi_intensity: f32,
+    /// Screen-space reflections, traced on top of the cubemap reflections
+    #[serde(default)]
+    pub ssr: bool,
+    /// Maximum distance a reflection ray travels, in meters
+    #[serde(default = "default_ssr_max_distance")]
+    pub ssr_max_distance: f32,
+    /// Surfaces rougher than this only reflect the cubemaps
+    #[serde(default = "default_ssr_max_roughness")]
+    pub ssr_max_roughness: f32,
+    #[serde(default)]
+    pub ssr_quality: EffectQuality,
+    /// Raymarched depth fog and light shafts of the shadowing lights
+    #[serde(default)]
+    pub volumetrics: bool,
+    /// Fog extinction per meter at the base height
+    #[serde(default = "default_volumetrics_density")]
+    pub volumetrics_density: f32,
+    /// Henyey-Greenstein anisotropy of the light shafts, positive values scatter light forwards
+    #[serde(default = "default_volumetrics_anisotropy")]
+    pub volumetrics_anisotropy: f32,
+    /// How quickly the fog thins out above the base height, per meter
+    #[serde(default = "default_volumetrics_height_falloff")]
+    pub volumetrics_height_falloff: f32,
+    /// Height the fog starts thinning out at, in meters
+    #[serde(default)]
+    pub volumetrics_base_height: f32,
+    /// Fog beyond this distance isn't marched, in meters
+    #[serde(default = "default_volumetrics_max_distance")]
+    pub volumetrics_max_distance: f32,
+    #[serde(default = "default_volumetrics_fog_color")]
+    pub volumetrics_fog_color: [f32; 3],
+    /// Light colors are computed by the light techniques, so every light shaft shares a color
+    #[serde(default = "default_volumetrics_light_color")]
+    pub volumetrics_light_color: [f32; 3],
+    #[serde(default = "default_volumetrics_light_intensity")]
+    pub volumetrics_light_intensity: f32,
     #[serde(skip)]
     pub matcap: bool,
     #[serde(skip, default = "default_true")]
     pub draw_selection_outline: bool,
+    #[serde(default = "default_selection_outline_color")]
+    pub selection_outline_color: [f32; 3],
+    /// Outline width in pixels
+    #[serde(default = "default_selection_outline_width")]
+    pub selection_outline_width: u32,
+    /// Pulses per second, 0 keeps the outline steady
+    #[serde(default)]
+    pub selection_pulse_speed: f32,
+    /// Tint the whole selected entity instead of outlining it
+    #[serde(default)]
+    pub selection_fill_tint: bool,
+    /// Outline entities matching the active outliner search or entity query
+    #[serde(default = "default_true")]
+    pub draw_search_highlight: bool,
+    /// Cull decorators beyond an approximation of the distance at which the game fades them out
+    #[serde(default)]
+    pub detail_fade: bool,
+    /// Pick the LOD level of models based on how much of the screen they cover, instead of always
+    /// drawing the highest detail
+    #[serde(default = "default_true")]
+    pub auto_lod: bool,
+    /// Positive values switch to lower detail levels sooner, every step halving the screen
+    /// coverage at which a level is picked
+    #[serde(default)]
+    pub lod_bias: f32,
+    /// Color models by their selected LOD level
+    #[serde(skip)]
+    pub lod_debug_colors: bool,
+    /// Color terrain patches by their material. Blend (splat) weights within a patch aren't shown
+    #[serde(skip)]
+    pub terrain_material_colors: bool,
+    /// Draw distant static instance groups as billboards captured at load time
+    #[serde(default)]
+    pub impostors: bool,
+    /// Distance from the camera to the closest instance of a group at which it is replaced by
+    /// impostors, in meters
+    #[serde(default = "default_impostor_distance")]
+    pub impostor_distance: f32,
+    /// Render the scene once per eye, side by side
+    #[serde(skip)]
+    pub stereo: bool,
+    /// Distance between the eyes in stereo mode, in meters
+    #[serde(default = "default_stereo_ipd")]
+    pub stereo_ipd: f32,
+    /// Show the right eye on the left, for cross-eyed viewing
+    #[serde(default)]
+    pub stereo_swap_eyes: bool,
+    /// Draws the wireframe of the whole scene on top of the shaded image
+    #[serde(skip)]
+    pub wireframe_overlay: bool,
+    /// Depth bias of the wireframe overlay, higher values keep it from being hidden by its own
+    /// surface but let it show through nearby geometry
+    #[serde(default = "default_wireframe_overlay_bias")]
+    pub wireframe_overlay_bias: i32,
+    /// Draw utility entities (rulers, spheres, beacons, routes)
+    #[serde(skip, default = "default_true")]
+    pub draw_utilities: bool,
+    /// Utility entities (rulers, spheres, shader balls, etc.) can be picked in the viewport
+    #[serde(skip, default = "default_true")]
+    pub pick_utilities: bool,
+    /// Lights can be picked in the viewport
+    #[serde(skip, default = "default_true")]
+    pub pick_lights: bool,
+    /// Draw the bounds of cubemap volumes and lights, highlighting the ones the camera is in
+    #[serde(skip)]
+    pub draw_volume_bounds: bool,
+    /// Radius of the circle drawn around every respawn point, 0 to hide them
+    #[serde(default)]
+    pub respawn_influence_radius: f32,
     pub shadow_quality: ShadowQuality,
     pub shadow_updates_per_frame: usize,
+    /// Skip lights whose volume doesn't reach any cluster of the view containing visible geometry
+    #[serde(default = "default_true", alias = "clustered_lights")]
+    pub light_occlusion_culling: bool,
+    /// Texture memory budget in MiB, 0 for no limit
+    #[serde(default)]
+    pub texture_budget_mb: u32,
+    /// Turn down features when the frame rate drops below `frame_budget_fps`, and restore them
+    /// once there's room again
+    #[serde(default)]
+    pub frame_budget: bool,
+    #[serde(default = "default_frame_budget_fps")]
+    pub frame_budget_fps: f32,
 
     #[serde(skip, default = "RenderFeatureVisibility::all")]
     pub feature_statics: RenderFeatureVisibility,
@@ -527,7 +1092,46 @@ pub struct RendererSettings {
     pub feature_atmosphere: bool,
     pub feature_cubemaps: bool,
     pub feature_global_lighting: bool,
-    pub feature_fxaa: bool,
+    #[serde(default)]
+    pub anti_aliasing: AntiAliasing,
+    /// Blur geometry in front of and behind the focus distance
+    #[serde(default)]
+    pub dof: bool,
+    /// Focus on the geometry under the crosshair instead of `dof_focus_distance`
+    #[serde(default = "default_true")]
+    pub dof_autofocus: bool,
+    /// Distance that is in focus, in meters
+    #[serde(default = "default_dof_focus_distance")]
+    pub dof_focus_distance: f32,
+    /// Blur radius of geometry that is far out of focus, relative to the image height
+    #[serde(default = "default_dof_blur_size")]
+    pub dof_blur_size: f32,
+    #[serde(default)]
+    pub dof_quality: EffectQuality,
+    /// Let bright parts of the image glow into their surroundings
+    #[serde(default)]
+    pub bloom: bool,
+    /// Brightness at which pixels start to bloom
+    #[serde(default = "default_bloom_threshold")]
+    pub bloom_threshold: f32,
+    #[serde(default = "default_bloom_intensity")]
+    pub bloom_intensity: f32,
+    #[serde(default)]
+    pub bloom_quality: EffectQuality,
+
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    #[serde(default)]
+    pub tonemapper: Tonemapper,
+    /// Manual exposure compensation, in stops
+    #[serde(default)]
+    pub exposure: f32,
+    /// Brightness of SDR white on HDR displays, in nits
+    #[serde(default = "default_hdr_paper_white")]
+    pub hdr_paper_white: f32,
+    /// Peak brightness of the HDR display, in nits
+    #[serde(default = "default_hdr_peak_luminance")]
+    pub hdr_peak_luminance: f32,
 
     #[serde(skip, default = "default_true")]
     pub stage_transparent: bool,
@@ -542,7 +1146,50 @@ pub struct RendererSettings {
     // #[serde(skip, default = "default_true")]
     // pub depth_prepass: bool,
     #[serde(skip)]
+    pub shading: ViewportShading,
+    /// Linear grey the surface color is replaced with in the lighting-only shading
+    #[serde(default = "default_lighting_only_albedo")]
+    pub lighting_only_albedo: f32,
+    /// Overrides for the lighting-only shading, to look at the contribution of each part of the
+    /// lighting separately. Shadows and cubemaps still need to be enabled for these to apply
+    #[serde(default = "default_true")]
+    pub lighting_only_shadows: bool,
+    #[serde(default = "default_true")]
+    pub lighting_only_cubemaps: bool,
+    #[serde(default = "default_true")]
+    pub lighting_only_emission: bool,
+    /// Draw only the sky, or everything but the sky
+    #[serde(skip)]
+    pub sky_isolation: SkyIsolation,
+    #[serde(skip)]
+    pub sky_replacement: SkyReplacement,
+    /// Color the background is replaced with, in sRGB
+    #[serde(default = "default_sky_replacement_color")]
+    pub sky_replacement_color: [f32; 3],
+    /// Radiance HDR (.hdr) file the background is replaced with
+    #[serde(default)]
+    pub sky_hdri_path: Option<String>,
+    /// Rotation of the HDRI around the up axis, in degrees
+    #[serde(default)]
+    pub sky_hdri_rotation: f32,
+    /// HDRI exposure, in stops
+    #[serde(default)]
+    pub sky_hdri_exposure: f32,
+    #[serde(skip)]
     pub debug_view: RenderDebugView,
+    /// Fraction of the screen width (from the left) showing the debug view, the rest shows the final image
+    #[serde(skip, default = "default_debug_view_split")]
+    pub debug_view_split: f32,
+    /// Fragment count shown as the hottest color in the overdraw view, higher counts are white
+    #[serde(default = "default_overdraw_max")]
+    pub overdraw_max: u32,
+    /// Light count shown as the hottest color in the light volume count view, higher counts are
+    /// white
+    #[serde(
+        default = "default_light_volume_heatmap_max",
+        alias = "light_cluster_heatmap_max"
+    )]
+    pub light_volume_heatmap_max: u32,
 }
 
 impl Default for RendererSettings {
@@ -550,10 +1197,52 @@ impl Default for RendererSettings {
         Self {
             vsync: true,
             ssao: true,
+            ssgi: false,
+            ssgi_radius: default_ssgi_radius(),
+            ssgi_intensity: default_ssgi_intensity(),
+            ssr: false,
+            ssr_max_distance: default_ssr_max_distance(),
+            ssr_max_roughness: default_ssr_max_roughness(),
+            ssr_quality: EffectQuality::Medium,
+            volumetrics: false,
+            volumetrics_density: default_volumetrics_density(),
+            volumetrics_anisotropy: default_volumetrics_anisotropy(),
+            volumetrics_height_falloff: default_volumetrics_height_falloff(),
+            volumetrics_base_height: 0.0,
+            volumetrics_max_distance: default_volumetrics_max_distance(),
+            volumetrics_fog_color: default_volumetrics_fog_color(),
+            volumetrics_light_color: default_volumetrics_light_color(),
+            volumetrics_light_intensity: default_volumetrics_light_intensity(),
             matcap: false,
             draw_selection_outline: true,
+            selection_outline_color: default_selection_outline_color(),
+            selection_outline_width: default_selection_outline_width(),
+            selection_pulse_speed: 0.0,
+            selection_fill_tint: false,
+            draw_search_highlight: true,
+            detail_fade: false,
+            auto_lod: true,
+            lod_bias: 0.0,
+            lod_debug_colors: false,
+            terrain_material_colors: false,
+            impostors: false,
+            impostor_distance: default_impostor_distance(),
+            stereo: false,
+            stereo_ipd: default_stereo_ipd(),
+            stereo_swap_eyes: false,
+            wireframe_overlay: false,
+            wireframe_overlay_bias: default_wireframe_overlay_bias(),
+            draw_utilities: true,
+            pick_utilities: true,
+            pick_lights: true,
+            draw_volume_bounds: false,
+            respawn_influence_radius: 0.0,
             shadow_quality: ShadowQuality::Medium,
             shadow_updates_per_frame: 2,
+            light_occlusion_culling: true,
+            texture_budget_mb: 0,
+            frame_budget: false,
+            frame_budget_fps: default_frame_budget_fps(),
 
             feature_statics: RenderFeatureVisibility::all(),
             feature_terrain: RenderFeatureVisibility::all(),
@@ -564,7 +1253,22 @@ impl Default for RendererSettings {
             feature_atmosphere: false,
             feature_cubemaps: false,
             feature_global_lighting: false,
-            feature_fxaa: true,
+            anti_aliasing: AntiAliasing::Fxaa,
+            dof: false,
+            dof_autofocus: true,
+            dof_focus_distance: default_dof_focus_distance(),
+            dof_blur_size: default_dof_blur_size(),
+            dof_quality: EffectQuality::Medium,
+            bloom: false,
+            bloom_threshold: default_bloom_threshold(),
+            bloom_intensity: default_bloom_intensity(),
+            bloom_quality: EffectQuality::Medium,
+
+            output_mode: OutputMode::Sdr,
+            tonemapper: Tonemapper::FilmCurve,
+            exposure: 0.0,
+            hdr_paper_white: default_hdr_paper_white(),
+            hdr_peak_luminance: default_hdr_peak_luminance(),
 
             stage_transparent: true,
             stage_decals: true,
@@ -573,8 +1277,67 @@ impl Default for RendererSettings {
             fxaa_noise: false,
 
             // depth_prepass: true,
+            shading: ViewportShading::Lit,
+            lighting_only_albedo: default_lighting_only_albedo(),
+            lighting_only_shadows: true,
+            lighting_only_cubemaps: true,
+            lighting_only_emission: true,
+            sky_isolation: SkyIsolation::None,
+            sky_replacement: SkyReplacement::None,
+            sky_replacement_color: default_sky_replacement_color(),
+            sky_hdri_path: None,
+            sky_hdri_rotation: 0.0,
+            sky_hdri_exposure: 0.0,
             debug_view: RenderDebugView::None,
+            debug_view_split: 1.0,
+            overdraw_max: default_overdraw_max(),
+            light_volume_heatmap_max: default_light_volume_heatmap_max(),
+        }
+    }
+}
+
+impl RendererSettings {
+    /// The debug view to draw, either picked directly or implied by the viewport shading mode.
+    /// An explicitly picked debug view takes priority
+    pub fn effective_debug_view(&self) -> RenderDebugView {
+        if self.debug_view != RenderDebugView::None {
+            return self.debug_view;
         }
+
+        match self.shading {
+            ViewportShading::Unlit | ViewportShading::Wireframe => RenderDebugView::SourceColor,
+            ViewportShading::Lit | ViewportShading::LightingOnly | ViewportShading::Matcap => {
+                RenderDebugView::None
+            }
+        }
+    }
+
+    /// Whether the surface color is replaced with grey. Unlike the grey diffuse debug view, this
+    /// keeps the full lighting
+    pub fn is_lighting_only(&self) -> bool {
+        self.shading == ViewportShading::LightingOnly && self.debug_view == RenderDebugView::None
+    }
+
+    /// Whether shadow maps are rendered and applied to lights
+    pub fn shadows_enabled(&self) -> bool {
+        self.shadow_quality != ShadowQuality::Off
+            && !self.effective_matcap()
+            && (self.lighting_only_shadows || !self.is_lighting_only())
+    }
+
+    /// Whether cubemaps are applied in the lighting pass
+    pub fn cubemaps_enabled(&self) -> bool {
+        self.feature_cubemaps && (self.lighting_only_cubemaps || !self.is_lighting_only())
+    }
+
+    /// Whether sky objects are drawn, they're hidden when the sky is isolated away or replaced
+    pub fn sky_visible(&self) -> bool {
+        self.sky_isolation != SkyIsolation::HideSky && self.sky_replacement == SkyReplacement::None
+    }
+
+    /// Whether the light buffers are replaced by the matcap
+    pub fn effective_matcap(&self) -> bool {
+        self.matcap || self.shading == ViewportShading::Matcap
     }
 }
 
@@ -616,6 +1379,8 @@ pub enum RenderDebugView {
     Metalness,
     AmbientOcclusion,
     TextureAo,
+    /// Baked per-vertex ambient occlusion (the color_ao stream)
+    VertexAo,
     Transmission,
 
     ColoredOvercoatId,
@@ -623,12 +1388,21 @@ pub enum RenderDebugView {
 
     DiffuseColor,
     DiffuseLight,
+    /// Baked diffuse lighting contribution (lightmaps/diffuse probes)
+    Lightmap,
     SpecularColor,
     SpecularLight,
     SpecularOcclusion,
     SpecularSmoothness,
     SpecularOnly,
 
+    /// Per-pixel influence of cubemap volumes, colored per volume and faded towards volume edges
+    CubemapInfluence,
+    /// Number of fragments drawn to every pixel by the opaque, decal and transparent stages
+    Overdraw,
+    /// Number of light volumes overlapping the light occlusion culling cluster of every pixel
+    LightVolumeCounts,
+
     Emissive,
     EmissiveIntensity,
     EmissiveLuminance,
@@ -653,10 +1427,34 @@ impl RenderDebugView {
     }
 }
 
+/// Shading presets for the viewport, built on top of the debug views and render overrides
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, strum::Display,
+)]
+pub enum ViewportShading {
+    #[default]
+    Lit,
+    /// Surface color without any lighting
+    Unlit,
+    /// Full lighting, shadows and cubemaps on a grey surface
+    LightingOnly,
+    /// Unlit triangle edges
+    Wireframe,
+    /// Lighting from a matcap texture instead of the scene lights
+    Matcap,
+}
+
+/// Scene time, drives `Renderer::time` and with that every time-based extern
 #[derive(Debug, Clone, Copy)]
 pub enum Time {
     Instant(Instant),
     Fixed(f32),
+    /// Time running at `speed` times real time, `offset` seconds in at `since`
+    Scaled {
+        since: Instant,
+        offset: f32,
+        speed: f32,
+    },
 }
 
 impl Time {
@@ -672,9 +1470,27 @@ impl Time {
         match self {
             Self::Instant(time) => time.elapsed().as_secs_f32(),
             Self::Fixed(time) => *time,
+            Self::Scaled {
+                since,
+                offset,
+                speed,
+            } => offset + since.elapsed().as_secs_f32() * speed,
         }
     }
 
+    /// Multiplier of real time, 0 when the time is paused
+    pub fn speed(&self) -> f32 {
+        match self {
+            Self::Instant(_) => 1.0,
+            Self::Fixed(_) => 0.0,
+            Self::Scaled { speed, .. } => *speed,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self, Self::Fixed(_))
+    }
+
     pub fn to_fixed(&self) -> Self {
         Self::Fixed(self.elapsed())
     }
@@ -682,7 +1498,40 @@ impl Time {
     pub fn to_instant(&self) -> Self {
         match self {
             Self::Instant(time) => Self::Instant(*time),
-            Self::Fixed(time) => Self::Instant(Instant::now() - Duration::from_secs_f32(*time)),
+            _ => {
+                let now = Instant::now();
+                let elapsed = self.elapsed();
+                // Instants can't go further back than the start of the system clock
+                match now.checked_sub(Duration::from_secs_f32(elapsed.max(0.0))) {
+                    Some(start) => Self::Instant(start),
+                    None => Self::Scaled {
+                        since: now,
+                        offset: elapsed,
+                        speed: 1.0,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Continues from the current time at `speed` times real time
+    pub fn with_speed(&self, speed: f32) -> Self {
+        if speed == 1.0 {
+            self.to_instant()
+        } else {
+            Self::Scaled {
+                since: Instant::now(),
+                offset: self.elapsed(),
+                speed,
+            }
+        }
+    }
+
+    /// Jumps to `time` seconds, keeping the current speed
+    pub fn seek(&self, time: f32) -> Self {
+        match self {
+            Self::Fixed(_) => Self::Fixed(time),
+            _ => Self::Fixed(time).with_speed(self.speed()),
         }
     }
 }
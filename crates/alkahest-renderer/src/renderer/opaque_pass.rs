@@ -59,6 +59,7 @@ impl Renderer {
 
         // Draw opaque pass
         self.run_renderstage_systems(scene, TfxRenderStage::GenerateGbuffer);
+        self.draw_impostors();
 
         {
             let mut data = self.data.lock();
@@ -90,5 +91,7 @@ impl Renderer {
             .current_states
             .store(StateSelection::new(Some(8), Some(15), Some(2), Some(1)));
         self.run_renderstage_systems(scene, TfxRenderStage::Decals);
+
+        self.draw_lighting_only_albedo();
     }
 }
@@ -12,22 +12,96 @@ use alkahest_data::{
 use anyhow::Context;
 use bevy_ecs::entity::Entity;
 use crossbeam::atomic::AtomicCell;
+use glam::Vec3;
+use parking_lot::Mutex;
+use strum::EnumIter;
 use windows::Win32::{
     Foundation::RECT,
     Graphics::Direct3D11::{ID3D11PixelShader, ID3D11VertexShader, D3D11_MAP_READ},
 };
 
 use crate::{
-    ecs::{render::draw_entity, Scene},
+    ecs::{render::draw_entity, tags::NodeFilter, Scene},
     gpu::{buffer::ConstantBuffer, util::DxDeviceExt, GpuContext, SharedGpuContext},
-    gpu_event, include_dxbc,
+    gpu_event,
+    icons::{ICON_CUBE, ICON_LIGHTBULB_ON, ICON_RUN, ICON_TERRAIN, ICON_TOOLBOX},
+    include_dxbc,
     renderer::{
         gbuffer::{CpuStagingBuffer, DepthState, RenderTarget},
-        Renderer,
+        RenderFeatureVisibility, Renderer, RendererSettings,
     },
-    util::Hocus,
 };
 
+/// Categories of entities that can be excluded from picking in the viewport, so large terrain
+/// patches and the like don't get in the way of selecting the smaller things behind them
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter, strum::Display)]
+pub enum PickLayer {
+    Terrain,
+    Statics,
+    Dynamics,
+    Utilities,
+    Lights,
+}
+
+impl PickLayer {
+    pub fn icon(&self) -> char {
+        match self {
+            PickLayer::Terrain => ICON_TERRAIN,
+            PickLayer::Statics => ICON_CUBE,
+            PickLayer::Dynamics => ICON_RUN,
+            PickLayer::Utilities => ICON_TOOLBOX,
+            PickLayer::Lights => ICON_LIGHTBULB_ON,
+        }
+    }
+
+    /// Layer that nodes with the given filter are picked with, nodes without one can always be
+    /// picked
+    pub fn from_node_filter(filter: NodeFilter) -> Option<Self> {
+        match filter {
+            NodeFilter::Static => Some(PickLayer::Statics),
+            NodeFilter::Utility => Some(PickLayer::Utilities),
+            NodeFilter::Light => Some(PickLayer::Lights),
+            _ => None,
+        }
+    }
+}
+
+impl RendererSettings {
+    /// Whether entities on the given layer can be picked. Geometry layers are tied to the
+    /// selectable flag of their render feature
+    pub fn is_pickable(&self, layer: PickLayer) -> bool {
+        match layer {
+            PickLayer::Terrain => self
+                .feature_terrain
+                .contains(RenderFeatureVisibility::SELECTABLE),
+            PickLayer::Statics => self
+                .feature_statics
+                .contains(RenderFeatureVisibility::SELECTABLE),
+            PickLayer::Dynamics => self
+                .feature_dynamics
+                .contains(RenderFeatureVisibility::SELECTABLE),
+            PickLayer::Utilities => self.pick_utilities,
+            PickLayer::Lights => self.pick_lights,
+        }
+    }
+
+    pub fn set_pickable(&mut self, layer: PickLayer, pickable: bool) {
+        match layer {
+            PickLayer::Terrain => self
+                .feature_terrain
+                .set(RenderFeatureVisibility::SELECTABLE, pickable),
+            PickLayer::Statics => self
+                .feature_statics
+                .set(RenderFeatureVisibility::SELECTABLE, pickable),
+            PickLayer::Dynamics => self
+                .feature_dynamics
+                .set(RenderFeatureVisibility::SELECTABLE, pickable),
+            PickLayer::Utilities => self.pick_utilities = pickable,
+            PickLayer::Lights => self.pick_lights = pickable,
+        }
+    }
+}
+
 impl Renderer {
     pub(super) fn draw_pickbuffer(&self, scene: &mut Scene, selected: Option<Entity>) {
         gpu_event!(self.gpu, "pickbuffer");
@@ -35,8 +109,8 @@ impl Renderer {
         self.pickbuffer.start(&self.gpu);
         self.pickbuffer.pick_cb.bind(7, TfxShaderStage::Pixel);
         self.gpu.bind_pixel_shader(&self.pickbuffer.pick_ps);
-        *self.gpu.custom_pixel_shader.pocus() = Some(self.pickbuffer.pick_ps.clone());
-        *self.pickbuffer.selected_entity.pocus() = selected;
+        *self.gpu.custom_pixel_shader.write() = Some(self.pickbuffer.pick_ps.clone());
+        self.pickbuffer.selected_entity.store(selected);
         self.run_renderstage_systems(scene, TfxRenderStage::GenerateGbuffer);
         // draw_static_instances_individual_system(
         //     self,
@@ -44,16 +118,29 @@ impl Renderer {
         //     &self.pickbuffer.static_instance_cb,
         //     TfxRenderStage::GenerateGbuffer,
         // );
-        *self.gpu.custom_pixel_shader.pocus() = None;
+        *self.gpu.custom_pixel_shader.write() = None;
         self.pickbuffer.end(&self.gpu);
         self.gpu.restore_state(&dxstate);
     }
 
+    /// Whether entities on the given layer are left out of the pickbuffer that is being drawn
+    pub fn is_excluded_from_picking(&self, layer: PickLayer) -> bool {
+        self.pickbuffer.is_drawing_selection() && !self.settings().is_pickable(layer)
+    }
+
     // TODO(cohae): move rendering logic to Pickbuffer (where possible)
     pub(super) fn draw_outline(&self, scene: &mut Scene, selected: Entity, time_since_select: f32) {
         gpu_event!(self.gpu, "selection_outline");
 
-        self.pickbuffer.outline_depth.clear(0.0, 0);
+        let (outline_depth_view, outline_depth_state, outline_depth_texture_view) = {
+            let targets = self.pickbuffer.targets.lock();
+            targets.outline_depth.clear(0.0, 0);
+            (
+                targets.outline_depth.view.clone(),
+                targets.outline_depth.state.clone(),
+                targets.outline_depth.texture_view.clone(),
+            )
+        };
 
         unsafe {
             let dxstate = self.gpu.backup_state();
@@ -61,10 +148,10 @@ impl Renderer {
             // Draw the selected entity into the outline depth buffer
             self.gpu
                 .lock_context()
-                .OMSetRenderTargets(None, Some(&self.pickbuffer.outline_depth.view));
+                .OMSetRenderTargets(None, Some(&outline_depth_view));
             self.gpu
                 .lock_context()
-                .OMSetDepthStencilState(Some(&self.pickbuffer.outline_depth.state), 0);
+                .OMSetDepthStencilState(Some(&outline_depth_state), 0);
             draw_entity(
                 scene,
                 selected,
@@ -94,11 +181,22 @@ impl Renderer {
             self.gpu.lock_context().PSSetShaderResources(
                 0,
                 Some(&[
-                    Some(self.pickbuffer.outline_depth.texture_view.clone()),
+                    Some(outline_depth_texture_view),
                     Some(self.data.lock().gbuffers.depth.texture_view.clone()),
                 ]),
             );
-            self.pickbuffer.outline_cb.write(&time_since_select).ok();
+            let settings = self.settings();
+            self.pickbuffer
+                .outline_cb
+                .write(&OutlineParams {
+                    color: Vec3::from(settings.selection_outline_color),
+                    time_since_selection: time_since_select,
+                    width: settings.selection_outline_width as f32,
+                    pulse_speed: settings.selection_pulse_speed,
+                    fill_tint: settings.selection_fill_tint as u32,
+                    _pad: 0,
+                })
+                .ok();
             self.pickbuffer.outline_cb.bind(0, TfxShaderStage::Pixel);
 
             self.gpu.lock_context().Draw(3, 0);
@@ -108,30 +206,46 @@ impl Renderer {
     }
 }
 
+/// Layout of `cb_outline` in gui/outline.hlsl
+#[repr(C)]
+pub(super) struct OutlineParams {
+    color: Vec3,
+    time_since_selection: f32,
+    width: f32,
+    pulse_speed: f32,
+    fill_tint: u32,
+    _pad: u32,
+}
+
+/// Window-sized targets of the pickbuffer, recreated on resize
+pub struct PickbufferTargets {
+    pub outline_depth: DepthState,
+    pub pick_buffer: RenderTarget,
+    pub pick_buffer_staging: CpuStagingBuffer,
+}
+
 pub struct Pickbuffer {
     /// Are we currently drawing the pickbuffer?
-    pub is_drawing_selection: bool,
+    is_drawing_selection: AtomicBool,
 
     pub(super) selection_request: AtomicCell<Option<(u32, u32)>>,
     selection_ready: AtomicBool,
 
-    pub outline_depth: DepthState,
-    pub pick_buffer: RenderTarget,
-    pub pick_buffer_staging: CpuStagingBuffer,
+    pub targets: Mutex<PickbufferTargets>,
     pub static_instance_cb: ConstantBuffer<u8>,
 
     pub(super) outline_vs: ID3D11VertexShader,
     pub(super) outline_ps: ID3D11PixelShader,
-    pub(super) outline_cb: ConstantBuffer<f32>,
+    pub(super) outline_cb: ConstantBuffer<OutlineParams>,
 
     clear_vs: ID3D11VertexShader,
     clear_ps: ID3D11PixelShader,
 
     pick_ps: ID3D11PixelShader,
     pick_cb: ConstantBuffer<u32>,
-    active_entity: Option<Entity>,
+    active_entity: AtomicCell<Option<Entity>>,
     /// The entity that's already selected. Will not be drawn into the pickbuffer
-    selected_entity: Option<Entity>,
+    selected_entity: AtomicCell<Option<Entity>>,
 }
 
 impl Pickbuffer {
@@ -154,25 +268,27 @@ impl Pickbuffer {
             .load_pixel_shader(include_dxbc!(ps "gui/pickbuffer.hlsl"))?;
 
         Ok(Self {
-            is_drawing_selection: false,
+            is_drawing_selection: AtomicBool::new(false),
             selection_request: AtomicCell::new(None),
             selection_ready: AtomicBool::new(false),
-            outline_depth: DepthState::create(gctx.clone(), window_size, "pickbuffer_depth")
-                .context("Outline Depth")?,
-            pick_buffer: RenderTarget::create(
-                window_size,
-                DxgiFormat::R32_UINT,
-                gctx.clone(),
-                "Entity_Pickbuffer",
-            )
-            .context("Entity_Pickbuffer")?,
-            pick_buffer_staging: CpuStagingBuffer::create(
-                window_size,
-                DxgiFormat::R32_UINT,
-                gctx.clone(),
-                "Entity_Pickbuffer_Staging",
-            )
-            .context("Entity_Pickbuffer_Staging")?,
+            targets: Mutex::new(PickbufferTargets {
+                outline_depth: DepthState::create(gctx.clone(), window_size, "pickbuffer_depth")
+                    .context("Outline Depth")?,
+                pick_buffer: RenderTarget::create(
+                    window_size,
+                    DxgiFormat::R32_UINT,
+                    gctx.clone(),
+                    "Entity_Pickbuffer",
+                )
+                .context("Entity_Pickbuffer")?,
+                pick_buffer_staging: CpuStagingBuffer::create(
+                    window_size,
+                    DxgiFormat::R32_UINT,
+                    gctx.clone(),
+                    "Entity_Pickbuffer_Staging",
+                )
+                .context("Entity_Pickbuffer_Staging")?,
+            }),
             static_instance_cb: ConstantBuffer::create_array_init(gctx.clone(), &[0u8; 32 + 64])?,
 
             outline_vs,
@@ -182,23 +298,27 @@ impl Pickbuffer {
             clear_ps,
             pick_ps,
             pick_cb: ConstantBuffer::create(gctx.clone(), None)?,
-            active_entity: None,
-            selected_entity: None,
+            active_entity: AtomicCell::new(None),
+            selected_entity: AtomicCell::new(None),
         })
     }
 
-    pub fn resize(&mut self, mut new_size: (u32, u32)) -> anyhow::Result<()> {
+    pub fn resize(&self, mut new_size: (u32, u32)) -> anyhow::Result<()> {
         if new_size.0 == 0 || new_size.1 == 0 {
             new_size = (1, 1);
         }
 
-        self.outline_depth
+        let mut targets = self.targets.lock();
+        targets
+            .outline_depth
             .resize(new_size)
             .context("Outline Depth")?;
-        self.pick_buffer
+        targets
+            .pick_buffer
             .resize(new_size)
             .context("Entity_Pickbuffer")?;
-        self.pick_buffer_staging
+        targets
+            .pick_buffer_staging
             .resize(new_size)
             .context("Entity_Pickbuffer_Staging")?;
 
@@ -206,12 +326,12 @@ impl Pickbuffer {
     }
 
     pub fn request_selection(&self, x: u32, y: u32) {
-        self.pocus().selection_request.store(Some((x, y)));
+        self.selection_request.store(Some((x, y)));
         self.selection_ready.store(false, Ordering::Relaxed);
     }
 
     pub fn cancel_request(&self) {
-        self.pocus().selection_request.store(None);
+        self.selection_request.store(None);
     }
 
     /// Finish the current selection request and return the entity id at the request coordinates
@@ -220,18 +340,23 @@ impl Pickbuffer {
         if !self.selection_ready.load(Ordering::Relaxed) {
             return None;
         }
-        self.pocus()
-            .selection_request
+        self.selection_request
             .take()
             .map(|(x, y)| self.get(x as usize, y as usize))
     }
 
+    /// Are we currently drawing the pickbuffer?
+    pub fn is_drawing_selection(&self) -> bool {
+        self.is_drawing_selection.load(Ordering::Relaxed)
+    }
+
     pub fn start(&self, gpu: &GpuContext) {
         self.clear(gpu);
         unsafe {
+            let targets = self.targets.lock();
             gpu.lock_context().OMSetRenderTargets(
-                Some(&[Some(self.pick_buffer.render_target.clone())]),
-                Some(&self.outline_depth.view),
+                Some(&[Some(targets.pick_buffer.render_target.clone())]),
+                Some(&targets.outline_depth.view),
             );
 
             gpu.current_states
@@ -248,12 +373,17 @@ impl Pickbuffer {
             }
         }
 
-        self.pocus().is_drawing_selection = true;
+        self.is_drawing_selection.store(true, Ordering::Relaxed);
     }
 
     pub fn end(&self, gpu: &GpuContext) {
-        self.pick_buffer.copy_to_staging(&self.pick_buffer_staging);
-        self.pocus().is_drawing_selection = false;
+        {
+            let targets = self.targets.lock();
+            targets
+                .pick_buffer
+                .copy_to_staging(&targets.pick_buffer_staging);
+        }
+        self.is_drawing_selection.store(false, Ordering::Relaxed);
         self.selection_ready.store(true, Ordering::Relaxed);
         unsafe {
             gpu.lock_context().RSSetScissorRects(None);
@@ -263,26 +393,28 @@ impl Pickbuffer {
     pub fn with_entity(&self, entity: Entity, f: impl FnOnce()) {
         self.set_entity(entity);
         f();
-        self.pocus().active_entity = None;
+        self.active_entity.store(None);
     }
 
     fn set_entity(&self, mut entity: Entity) {
-        if Some(entity) == self.selected_entity {
+        if Some(entity) == self.selected_entity.load() {
             entity = Entity::PLACEHOLDER;
         }
 
-        if Some(entity) != self.active_entity {
-            self.pocus().active_entity = Some(entity);
+        if self.active_entity.swap(Some(entity)) != Some(entity) {
             self.pick_cb.write(&entity.index()).ok();
         }
     }
 
     pub fn clear(&self, gpu: &GpuContext) {
-        self.outline_depth.clear(0.0, 0);
+        let targets = self.targets.lock();
+        targets.outline_depth.clear(0.0, 0);
 
         unsafe {
-            gpu.lock_context()
-                .OMSetRenderTargets(Some(&[Some(self.pick_buffer.render_target.clone())]), None);
+            gpu.lock_context().OMSetRenderTargets(
+                Some(&[Some(targets.pick_buffer.render_target.clone())]),
+                None,
+            );
 
             gpu.set_blend_state(0);
             gpu.lock_context().RSSetState(None);
@@ -296,7 +428,9 @@ impl Pickbuffer {
     }
 
     pub fn get(&self, x: usize, y: usize) -> u32 {
-        self.pick_buffer_staging
+        self.targets
+            .lock()
+            .pick_buffer_staging
             .map(D3D11_MAP_READ, |m| unsafe {
                 let data = m
                     .pData
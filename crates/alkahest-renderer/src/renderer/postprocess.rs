@@ -1,15 +1,34 @@
 use alkahest_data::technique::StateSelection;
 
-use crate::{ecs::Scene, gpu_event, renderer::Renderer, tfx::externs};
+use crate::{
+    ecs::Scene,
+    gpu_event,
+    renderer::{AntiAliasing, RenderDebugView, Renderer},
+    tfx::externs,
+};
 
 impl Renderer {
     pub fn draw_postprocessing_pass(&self, _scene: &mut Scene) {
         gpu_event!(self.gpu, "postprocess");
+        let settings = self.settings();
         unsafe {
             self.gpu.lock_context().OMSetRenderTargets(Some(&[]), None);
             self.gpu.lock_context().PSSetShaderResources(0, Some(&[]));
         }
 
+        // Cinematic effects work on the HDR shading result, and are meant for the final image only
+        if !self.is_probing()
+            && self.stereo_eye().is_none()
+            && settings.effective_debug_view() == RenderDebugView::None
+        {
+            if settings.dof {
+                self.dof.draw(self);
+            }
+            if settings.bloom {
+                self.bloom.draw(self);
+            }
+        }
+
         {
             let data = &mut self.data.lock();
             // Ping, Pong
@@ -21,35 +40,48 @@ impl Renderer {
             );
         }
 
-        if self.settings.feature_fxaa {
-            unsafe {
-                let data = &mut self.data.lock();
-                // (pong, ping)
-                // renders to ping
-                let (source, target) = data.gbuffers.get_postprocess_rt(true);
-                let rt = target.render_target.clone();
-                data.externs.fxaa = Some(externs::Fxaa {
-                    source_texture: source.view.clone().into(),
-                    noise_time: self.time.load().elapsed(),
-                    ..Default::default()
-                });
+        let anti_aliasing = self.anti_aliasing();
+        if anti_aliasing != AntiAliasing::Taa {
+            self.antialiasing.reset_history();
+        }
 
-                self.gpu
-                    .lock_context()
-                    .OMSetRenderTargets(Some(&[Some(rt), None]), None);
-            }
+        match anti_aliasing {
+            AntiAliasing::None => {}
+            AntiAliasing::Fxaa => {
+                unsafe {
+                    let data = &mut self.data.lock();
+                    // (pong, ping)
+                    // renders to ping
+                    let (source, target) = data.gbuffers.get_postprocess_rt(true);
+                    let rt = target.render_target.clone();
+                    data.externs.fxaa = Some(externs::Fxaa {
+                        source_texture: source.view.clone().into(),
+                        noise_time: self.time.load().elapsed(),
+                        ..Default::default()
+                    });
 
-            gpu_event!(self.gpu, "fxaa");
-            let pipeline = if self.settings.fxaa_noise {
-                &self.render_globals.pipelines.fxaa_noise
-            } else {
-                &self.render_globals.pipelines.fxaa
-            };
+                    self.gpu
+                        .lock_context()
+                        .OMSetRenderTargets(Some(&[Some(rt), None]), None);
+                }
 
-            self.gpu
-                .current_states
-                .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
-            self.execute_global_pipeline(pipeline, "fxaa(_noise)");
+                gpu_event!(self.gpu, "fxaa");
+                let pipeline = if settings.fxaa_noise {
+                    &self.render_globals.pipelines.fxaa_noise
+                } else {
+                    &self.render_globals.pipelines.fxaa
+                };
+
+                self.gpu.current_states.store(StateSelection::new(
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                    Some(0),
+                ));
+                self.execute_global_pipeline(pipeline, "fxaa(_noise)");
+            }
+            AntiAliasing::Smaa => self.antialiasing.draw_smaa(self),
+            AntiAliasing::Taa => self.antialiasing.draw_taa(self),
         }
 
         {
@@ -0,0 +1,134 @@
+use alkahest_data::occlusion::Aabb;
+use glam::{IVec3, Mat4, Quat, Vec3};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    ecs::{
+        resources::{SearchHighlight, SelectedEntity},
+        transform::Transform,
+        visibility::{ViewVisibility, VisibilityHelper},
+        Scene,
+    },
+    renderer::{LabelAlign, Renderer},
+    resources::AppResources,
+    util::color::Color,
+};
+
+/// Matches closer to the camera than this are always outlined individually
+const MAX_INDIVIDUAL_HIGHLIGHTS: usize = 256;
+/// Maximum number of clusters drawn for the remaining matches
+const MAX_CLUSTERS: usize = 256;
+/// Size of the smallest cluster cell, in meters
+const MIN_CLUSTER_SIZE: f32 = 16.0;
+
+struct HighlightedEntity {
+    /// Bounds (or the transform for entities without bounds) in world space
+    transform: Mat4,
+    center: Vec3,
+    has_bounds: bool,
+}
+
+impl Renderer {
+    /// Outlines entities matching the active search. Beyond [`MAX_INDIVIDUAL_HIGHLIGHTS`], the
+    /// farthest matches are grouped into clusters, drawn as a single box with a match count
+    pub(super) fn draw_search_highlight(&self, scene: &Scene, resources: &AppResources) {
+        let Some(highlight) = scene.get_resource::<SearchHighlight>() else {
+            return;
+        };
+        if !self.settings().draw_search_highlight || highlight.entities().is_empty() {
+            return;
+        }
+
+        let selected = resources.get::<SelectedEntity>().selected();
+        let mut matches = highlight
+            .entities()
+            .iter()
+            .filter(|&&e| Some(e) != selected)
+            .filter_map(|&e| {
+                let entity = scene.get_entity(e)?;
+                if !entity.get::<ViewVisibility>().is_visible(0) {
+                    return None;
+                }
+
+                let transform = entity.get::<Transform>()?.local_to_world();
+                Some(match entity.get::<Aabb>() {
+                    Some(bounds) => HighlightedEntity {
+                        transform: transform
+                            * Transform::new(bounds.center(), Quat::IDENTITY, bounds.extents())
+                                .local_to_world(),
+                        center: transform.transform_point3(bounds.center()),
+                        has_bounds: true,
+                    },
+                    None => HighlightedEntity {
+                        transform,
+                        center: transform.w_axis.truncate(),
+                        has_bounds: false,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let camera_position = self
+            .data
+            .lock()
+            .externs
+            .view
+            .as_ref()
+            .map_or(Vec3::ZERO, |v| v.position.truncate());
+        if matches.len() > MAX_INDIVIDUAL_HIGHLIGHTS {
+            matches.sort_by(|a, b| {
+                a.center
+                    .distance_squared(camera_position)
+                    .total_cmp(&b.center.distance_squared(camera_position))
+            });
+        }
+
+        let color = Color::from_rgba_premultiplied(0.2, 0.55, 0.9, 0.6);
+        let split = matches.len().min(MAX_INDIVIDUAL_HIGHLIGHTS);
+        for m in &matches[..split] {
+            if m.has_bounds {
+                self.immediate.cube_outline(m.transform, color);
+            } else {
+                self.immediate.cross(m.center, 0.5, color);
+            }
+        }
+
+        for (count, min, max) in cluster_positions(matches[split..].iter().map(|m| m.center)) {
+            self.immediate.cube_outline(
+                Mat4::from_scale_rotation_translation(
+                    (max - min) / 2.0 + 1.0,
+                    Quat::IDENTITY,
+                    (min + max) / 2.0,
+                ),
+                color,
+            );
+            self.immediate.label(
+                format!("{count} matches"),
+                (min + max) / 2.0,
+                LabelAlign::CENTER_CENTER,
+                color,
+            );
+        }
+    }
+}
+
+/// Groups positions into grid cells, doubling the cell size until there are at most
+/// [`MAX_CLUSTERS`] cells. Returns the number of positions and their bounds for every cell
+fn cluster_positions(positions: impl Iterator<Item = Vec3> + Clone) -> Vec<(usize, Vec3, Vec3)> {
+    let mut cell_size = MIN_CLUSTER_SIZE;
+    loop {
+        let mut cells: FxHashMap<IVec3, (usize, Vec3, Vec3)> = FxHashMap::default();
+        for p in positions.clone() {
+            let cell = (p / cell_size).floor().as_ivec3();
+            let entry = cells.entry(cell).or_insert((0, p, p));
+            entry.0 += 1;
+            entry.1 = entry.1.min(p);
+            entry.2 = entry.2.max(p);
+        }
+
+        if cells.len() <= MAX_CLUSTERS {
+            return cells.into_values().collect();
+        }
+        cell_size *= 2.0;
+    }
+}
@@ -17,12 +17,12 @@ use crate::{
     gpu::DepthMode,
     gpu_event, gpu_profile_event,
     renderer::Renderer,
-    util::{black_magic::EntityRefDarkMagic, Hocus},
 };
 
 impl Renderer {
     pub fn update_shadow_maps(&self, scene: &mut Scene) {
-        if self.settings.shadow_quality == ShadowQuality::Off || self.settings.matcap {
+        let settings = self.settings();
+        if !settings.shadows_enabled() {
             return;
         }
 
@@ -47,40 +47,75 @@ impl Renderer {
         }
 
         shadow_renderers.sort_by_key(|(_, last_update)| *last_update);
-        shadow_renderers.truncate(self.settings.shadow_updates_per_frame);
+        shadow_renderers.truncate(settings.shadow_updates_per_frame);
 
         for (e, _) in shadow_renderers {
             gpu_event!(self.gpu, "update_shadow_map", e.index().to_string());
 
-            let er = scene.entity(e);
-            let mut shadow = er.get_mut::<ShadowMapRenderer>().unwrap();
-            shadow.last_update = self.frame_index.load(Ordering::Relaxed);
-            let transform = er.get::<Transform>().unwrap();
+            let Some(transform) = scene.get::<Transform>(e).copied() else {
+                continue;
+            };
 
             self.gpu
                 .shadowmap_vs_t2
                 .bind(&self.gpu, 2, TfxShaderStage::Vertex);
 
-            self.bind_view(&*shadow, e.index() as usize);
-
-            if shadow.stationary_needs_update {
-                self.pocus().active_shadow_generation_mode = ShadowGenerationMode::StationaryOnly;
-                shadow.bind_for_generation(transform, self, ShadowGenerationMode::StationaryOnly);
+            let (stationary_needs_update, bias_state) = {
+                let mut shadow = scene.get_mut::<ShadowMapRenderer>(e).unwrap();
+                shadow.last_update = self.frame_index.load(Ordering::Relaxed);
+                self.bind_view(&*shadow, e.index() as usize, false);
+                (
+                    shadow.stationary_needs_update,
+                    shadow.depth_bias_rasterizer_state().cloned(),
+                )
+            };
+
+            let bias_overridden = bias_state.is_some();
+            if bias_overridden {
+                *self.gpu.custom_rasterizer_state.write() = bias_state;
+                self.gpu.flush_states();
+            }
 
-                self.run_renderstage_systems(scene.pocus(), TfxRenderStage::ShadowGenerate);
+            if stationary_needs_update {
+                self.generate_shadow_map(
+                    scene,
+                    e,
+                    &transform,
+                    ShadowGenerationMode::StationaryOnly,
+                );
 
                 if !self.data.lock().asset_manager.is_idle() {
-                    shadow.stationary_needs_update = true;
+                    if let Some(mut shadow) = scene.get_mut::<ShadowMapRenderer>(e) {
+                        shadow.stationary_needs_update = true;
+                    }
                 }
             }
 
-            self.pocus().active_shadow_generation_mode = ShadowGenerationMode::MovingOnly;
-            shadow.bind_for_generation(transform, self, ShadowGenerationMode::MovingOnly);
-            self.run_renderstage_systems(scene, TfxRenderStage::ShadowGenerate);
+            self.generate_shadow_map(scene, e, &transform, ShadowGenerationMode::MovingOnly);
+
+            if bias_overridden {
+                *self.gpu.custom_rasterizer_state.write() = None;
+                self.gpu.flush_states();
+            }
         }
 
         self.gpu.set_depth_mode(DepthMode::Normal);
     }
+
+    fn generate_shadow_map(
+        &self,
+        scene: &mut Scene,
+        entity: Entity,
+        transform: &Transform,
+        mode: ShadowGenerationMode,
+    ) {
+        self.active_shadow_generation_mode.store(mode);
+        if let Some(mut shadow) = scene.get_mut::<ShadowMapRenderer>(entity) {
+            shadow.bind_for_generation(transform, self, mode);
+        }
+
+        self.run_renderstage_systems(scene, TfxRenderStage::ShadowGenerate);
+    }
 }
 
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq, strum::EnumIter, strum::Display)]
@@ -0,0 +1,215 @@
+use alkahest_data::{
+    dxgi::DxgiFormat, geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage,
+};
+use glam::{Mat4, Vec4};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11ShaderResourceView, D3D11_COMPARISON_NEVER,
+    D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP,
+    D3D11_TEXTURE_ADDRESS_WRAP,
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, texture::Texture, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::Renderer,
+    util::image::Hdr,
+};
+
+/// Draw only the sky or only the world, to capture them separately
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, strum::Display,
+)]
+pub enum SkyIsolation {
+    #[default]
+    #[strum(to_string = "Off")]
+    None,
+    /// Only the `SkyTransparent` feature, without any world geometry
+    #[strum(to_string = "Sky Only")]
+    SkyOnly,
+    /// Everything but the `SkyTransparent` feature
+    #[strum(to_string = "Hide Sky")]
+    HideSky,
+}
+
+/// What the background is replaced with. Sky objects are hidden while it is replaced
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, EnumIter, strum::Display,
+)]
+pub enum SkyReplacement {
+    #[default]
+    #[strum(to_string = "Off")]
+    None,
+    /// A single color, for chroma keying
+    Color,
+    /// An equirectangular HDRI, loaded from a Radiance HDR file
+    #[strum(to_string = "HDRI")]
+    Hdri,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SkyReplaceParams {
+    target_pixel_to_world: Mat4,
+    camera_pos: Vec4,
+    color: Vec4,
+    params: Vec4,
+}
+
+pub struct SkyReplacementRenderer {
+    gpu: SharedGpuContext,
+    pixel_shader: ID3D11PixelShader,
+    sampler: ID3D11SamplerState,
+    params_cb: ConstantBuffer<SkyReplaceParams>,
+    /// The HDRI along with the path it was loaded from. The texture is `None` if loading failed,
+    /// so it's not attempted again every frame
+    hdri: Mutex<Option<(String, Option<Texture>)>>,
+}
+
+impl SkyReplacementRenderer {
+    pub fn new(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        let pixel_shader = gpu
+            .device
+            .load_pixel_shader(include_dxbc!(ps "misc/sky_replace.hlsl"))?;
+        let sampler = gpu.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+            Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+            AddressU: D3D11_TEXTURE_ADDRESS_WRAP,
+            AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+            AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+            MipLODBias: 0.0,
+            MaxAnisotropy: 1,
+            ComparisonFunc: D3D11_COMPARISON_NEVER,
+            BorderColor: [0.0, 0.0, 0.0, 0.0],
+            MinLOD: 0.0,
+            MaxLOD: f32::MAX,
+        })?;
+
+        Ok(Self {
+            pixel_shader,
+            sampler,
+            params_cb: ConstantBuffer::create(gpu.clone(), None)?,
+            hdri: Mutex::new(None),
+            gpu,
+        })
+    }
+
+    fn load_hdri(&self, path: &str) -> anyhow::Result<Texture> {
+        let hdr = Hdr::from_bytes(&std::fs::read(path)?)?;
+        Texture::load_2d_raw(
+            &self.gpu.device,
+            hdr.dimensions[0] as u32,
+            hdr.dimensions[1] as u32,
+            bytemuck::cast_slice(&hdr.data),
+            DxgiFormat::R32G32B32A32_FLOAT,
+            Some("Sky HDRI"),
+        )
+    }
+
+    /// The HDRI at `path`, loading it if it isn't loaded yet
+    fn hdri(&self, path: &str) -> Option<ID3D11ShaderResourceView> {
+        let mut hdri = self.hdri.lock();
+        if hdri.as_ref().map(|(p, _)| p.as_str()) != Some(path) {
+            let texture = match self.load_hdri(path) {
+                Ok(t) => Some(t),
+                Err(e) => {
+                    error!("Failed to load HDRI '{path}': {e:?}");
+                    None
+                }
+            };
+            *hdri = Some((path.to_string(), texture));
+        }
+
+        hdri.as_ref()
+            .and_then(|(_, t)| t.as_ref())
+            .map(|t| t.view.clone())
+    }
+}
+
+impl Renderer {
+    /// Fills the background with the sky replacement. Drawn over the final image, so the
+    /// replacement color ends up in the output exactly as it was picked
+    pub(super) fn draw_sky_replacement(&self) {
+        let settings = self.settings();
+        let hdri = match settings.sky_replacement {
+            SkyReplacement::None => return,
+            SkyReplacement::Color => None,
+            SkyReplacement::Hdri => {
+                let Some(path) = settings.sky_hdri_path.as_deref() else {
+                    return;
+                };
+                let Some(hdri) = self.sky_replacement.hdri(path) else {
+                    return;
+                };
+                Some(hdri)
+            }
+        };
+
+        let Some(view) = self.data.lock().externs.view.clone() else {
+            return;
+        };
+
+        gpu_event!(self.gpu, "sky_replacement");
+        let gamma_corrected = self.final_output_is_gamma_corrected();
+        let [r, g, b] = settings.sky_replacement_color;
+        let color = if gamma_corrected {
+            Vec4::new(r, g, b, 1.0)
+        } else {
+            Vec4::new(
+                ecolor::linear_from_gamma(r),
+                ecolor::linear_from_gamma(g),
+                ecolor::linear_from_gamma(b),
+                1.0,
+            )
+        };
+        self.sky_replacement
+            .params_cb
+            .write(&SkyReplaceParams {
+                target_pixel_to_world: view.target_pixel_to_world,
+                camera_pos: view.position,
+                color,
+                params: Vec4::new(
+                    hdri.is_some() as u32 as f32,
+                    2f32.powf(settings.sky_hdri_exposure),
+                    settings.sky_hdri_rotation.to_radians(),
+                    gamma_corrected as u32 as f32,
+                ),
+            })
+            .ok();
+        self.sky_replacement
+            .params_cb
+            .bind(0, TfxShaderStage::Pixel);
+
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+        self.gpu.flush_states();
+
+        unsafe {
+            let data = self.data.lock();
+            let ctx = self.gpu.lock_context();
+            ctx.OMSetRenderTargets(
+                Some(&[Some(data.gbuffers.shading_result.render_target.clone())]),
+                None,
+            );
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.RSSetState(None);
+
+            ctx.VSSetShader(&self.gpu.util_resources.blit_vs, None);
+            ctx.PSSetShader(&self.sky_replacement.pixel_shader, None);
+            ctx.PSSetSamplers(0, Some(&[Some(self.sky_replacement.sampler.clone())]));
+            ctx.PSSetShaderResources(
+                0,
+                Some(&[Some(data.gbuffers.depth.texture_view.clone()), hdri]),
+            );
+        }
+        self.gpu.set_input_topology(EPrimitiveType::Triangles);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.Draw(3, 0);
+            ctx.PSSetShaderResources(0, Some(&[None, None]));
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use alkahest_data::{geometry::EPrimitiveType, tfx::TfxFeatureRenderer};
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::{EnumCount, EnumIter};
+
+use crate::{
+    ecs::common::{ActivityGroup, ResourceOrigin},
+    gpu::texture::Texture,
+    handle::Handle,
+    loaders::AssetManager,
+    tfx::technique::Technique,
+};
+
+/// Feature renderer categories that draw statistics are grouped by
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumCount, EnumIter, strum::Display)]
+pub enum StatsCategory {
+    Statics,
+    Terrain,
+    Dynamics,
+    Decorators,
+    Water,
+}
+
+impl StatsCategory {
+    pub fn from_feature(feature: TfxFeatureRenderer) -> Self {
+        match feature {
+            TfxFeatureRenderer::StaticObjects | TfxFeatureRenderer::ChunkedInstanceObjects => {
+                Self::Statics
+            }
+            TfxFeatureRenderer::TerrainPatch => Self::Terrain,
+            TfxFeatureRenderer::SpeedtreeTrees => Self::Decorators,
+            TfxFeatureRenderer::Water => Self::Water,
+            _ => Self::Dynamics,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct FeatureStats {
+    pub draw_calls: usize,
+    pub triangles: usize,
+    pub instances: usize,
+    /// Number of unique textures bound by this feature
+    pub textures: usize,
+    /// Approximate size of all unique textures, in bytes
+    pub texture_memory: usize,
+}
+
+impl std::ops::AddAssign for FeatureStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.triangles += rhs.triangles;
+        self.instances += rhs.instances;
+        self.textures += rhs.textures;
+        self.texture_memory += rhs.texture_memory;
+    }
+}
+
+/// Where the entity that is currently being drawn was loaded from
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StatsSource {
+    pub origin: Option<ResourceOrigin>,
+    pub activity_group: Option<u32>,
+}
+
+impl StatsSource {
+    pub fn new(origin: Option<&ResourceOrigin>, activity_group: Option<&ActivityGroup>) -> Self {
+        Self {
+            origin: origin.copied(),
+            activity_group: activity_group.map(|g| g.0),
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct FrameStats {
+    pub features: [FeatureStats; StatsCategory::COUNT],
+    /// Statistics per resource origin. Draws without an entity (or without an origin) are
+    /// recorded under `None`
+    pub origins: FxHashMap<Option<ResourceOrigin>, FeatureStats>,
+    /// Statistics per activity phase name hash
+    pub activity_groups: FxHashMap<u32, FeatureStats>,
+}
+
+impl FrameStats {
+    pub fn get(&self, category: StatsCategory) -> &FeatureStats {
+        &self.features[category as usize]
+    }
+
+    pub fn total(&self) -> FeatureStats {
+        let mut total = FeatureStats::default();
+        for f in &self.features {
+            total += *f;
+        }
+        total
+    }
+}
+
+#[derive(Default)]
+struct StatsAccumulator {
+    stats: FrameStats,
+    textures: [FxHashSet<Handle<Texture>>; StatsCategory::COUNT],
+    origin_textures: FxHashMap<Option<ResourceOrigin>, FxHashSet<Handle<Texture>>>,
+    activity_group_textures: FxHashMap<u32, FxHashSet<Handle<Texture>>>,
+    source: StatsSource,
+}
+
+/// Counters gathered by the feature renderers during the render passes (including shadow and pickbuffer passes)
+///
+/// Nothing is recorded until [`RenderStats::set_enabled`] is called, so draw calls don't take the
+/// stats lock while nobody is looking at the numbers
+#[derive(Default)]
+pub struct RenderStats {
+    enabled: AtomicBool,
+    current: Mutex<StatsAccumulator>,
+    last: Mutex<FrameStats>,
+}
+
+impl RenderStats {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Attributes everything recorded within `f` to the given source
+    pub fn with_source(&self, source: StatsSource, f: impl FnOnce()) {
+        if !self.is_enabled() {
+            return f();
+        }
+
+        self.current.lock().source = source;
+        f();
+        self.current.lock().source = StatsSource::default();
+    }
+
+    pub fn record_draw(
+        &self,
+        category: StatsCategory,
+        index_count: u32,
+        primitive_type: EPrimitiveType,
+        instances: u32,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let triangles = match primitive_type {
+            EPrimitiveType::Triangles => index_count / 3,
+            EPrimitiveType::TriangleStrip => index_count.saturating_sub(2),
+            _ => 0,
+        };
+
+        let draw = FeatureStats {
+            draw_calls: 1,
+            triangles: triangles as usize * instances as usize,
+            instances: instances as usize,
+            ..Default::default()
+        };
+
+        let mut current = self.current.lock();
+        let source = current.source;
+        current.stats.features[category as usize] += draw;
+        *current.stats.origins.entry(source.origin).or_default() += draw;
+        if let Some(group) = source.activity_group {
+            *current.stats.activity_groups.entry(group).or_default() += draw;
+        }
+    }
+
+    pub fn record_technique(&self, category: StatsCategory, technique: &Technique) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut current = self.current.lock();
+        let current = &mut *current;
+        let source = current.source;
+        let mut sets = vec![
+            &mut current.textures[category as usize],
+            current.origin_textures.entry(source.origin).or_default(),
+        ];
+        if let Some(group) = source.activity_group {
+            sets.push(current.activity_group_textures.entry(group).or_default());
+        }
+
+        for stage in technique.all_stages().into_iter().filter_map(|(_, s)| s) {
+            for (_, texture) in &stage.textures {
+                for textures in sets.iter_mut() {
+                    if !textures.contains(texture) {
+                        textures.insert(texture.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Statistics of the last completed frame
+    pub fn last_frame(&self) -> FrameStats {
+        self.last.lock().clone()
+    }
+
+    pub(super) fn end_frame(&self, asset_manager: &AssetManager) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let StatsAccumulator {
+            mut stats,
+            textures,
+            origin_textures,
+            activity_group_textures,
+            ..
+        } = std::mem::take(&mut *self.current.lock());
+
+        for (feature, textures) in stats.features.iter_mut().zip(textures) {
+            add_texture_memory(feature, textures, asset_manager);
+        }
+
+        for (origin, textures) in origin_textures {
+            add_texture_memory(
+                stats.origins.entry(origin).or_default(),
+                textures,
+                asset_manager,
+            );
+        }
+
+        for (group, textures) in activity_group_textures {
+            add_texture_memory(
+                stats.activity_groups.entry(group).or_default(),
+                textures,
+                asset_manager,
+            );
+        }
+
+        *self.last.lock() = stats;
+    }
+}
+
+fn add_texture_memory(
+    stats: &mut FeatureStats,
+    textures: FxHashSet<Handle<Texture>>,
+    asset_manager: &AssetManager,
+) {
+    for texture in textures {
+        if let Some(texture) = asset_manager.textures.get(&texture) {
+            stats.textures += 1;
+            stats.texture_memory += texture.memory_size();
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use glam::{Mat4, Vec3, Vec4};
+use windows::Win32::Graphics::Direct3D11::D3D11_VIEWPORT;
+
+use crate::{
+    camera::{Camera, Viewport},
+    ecs::{culling::Frustum, Scene},
+    renderer::Renderer,
+    resources::AppResources,
+    tfx::{
+        externs,
+        view::{RenderStageSubscriptions, View},
+    },
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StereoEye {
+    Left,
+    Right,
+}
+
+impl StereoEye {
+    /// Direction of the eye from the center of the head, along the camera's right vector
+    fn sign(self) -> f32 {
+        match self {
+            StereoEye::Left => -1.0,
+            StereoEye::Right => 1.0,
+        }
+    }
+}
+
+/// One eye of a stereo camera. Both eyes are rendered at the full resolution of the camera
+/// viewport, but with the aspect ratio of half of it, so the images can be squeezed side by side.
+pub struct StereoEyeView {
+    viewport: Viewport,
+    position: Vec3,
+    world_to_camera: Mat4,
+    camera_to_projective: Mat4,
+    frustum: Frustum,
+}
+
+impl StereoEyeView {
+    /// Creates the view for `eye`, offset from the camera by half of the interpupillary distance
+    /// (in world units)
+    pub fn new(camera: &Camera, eye: StereoEye, ipd: f32) -> Self {
+        let offset = camera.right() * eye.sign() * ipd * 0.5;
+        let world_to_camera = camera.world_to_camera * Mat4::from_translation(-offset);
+        let camera_to_projective = camera
+            .projection
+            .matrix(camera.viewport().aspect_ratio() * 0.5);
+
+        Self {
+            viewport: camera.viewport().clone(),
+            position: camera.position() + offset,
+            world_to_camera,
+            camera_to_projective,
+            frustum: Frustum::from_matrix(camera_to_projective * world_to_camera),
+        }
+    }
+}
+
+impl View for StereoEyeView {
+    fn viewport(&self) -> Viewport {
+        self.viewport.clone()
+    }
+
+    fn subscribed_views(&self) -> RenderStageSubscriptions {
+        RenderStageSubscriptions::all()
+    }
+
+    fn name(&self) -> String {
+        "StereoEye".to_string()
+    }
+
+    fn frustum(&self) -> Frustum {
+        self.frustum
+    }
+
+    fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    fn update_extern(&self, x: &mut externs::View) {
+        x.world_to_camera = self.world_to_camera;
+        x.camera_to_projective = self.camera_to_projective;
+
+        x.derive_matrices(&self.viewport);
+
+        x.view_miscellaneous = Vec4::new(0., 1., 0., 0.);
+    }
+}
+
+impl Renderer {
+    /// Renders the scene once for each eye, and presents both images side by side (left eye on
+    /// the left half, unless the eyes are swapped for cross-eyed viewing)
+    pub fn render_stereo(&self, camera: &Camera, scene: &mut Scene, resources: &AppResources) {
+        let ipd = self.settings().stereo_ipd;
+        for eye in [StereoEye::Left, StereoEye::Right] {
+            self.stereo_eye.store(Some(eye));
+            self.render_world(&StereoEyeView::new(camera, eye, ipd), scene, resources);
+        }
+        self.stereo_eye.store(None);
+
+        // Anything drawn after the frame (the UI) covers the whole window again
+        let size = camera.viewport().size;
+        self.set_output_viewport(0.0, size.x as f32);
+    }
+
+    /// The eye currently being rendered, if rendering in stereo
+    pub fn stereo_eye(&self) -> Option<StereoEye> {
+        self.stereo_eye.load()
+    }
+
+    /// Restricts the final image output to the half of the window belonging to the eye being
+    /// rendered
+    pub(super) fn bind_stereo_output_viewport(&self, width: f32) {
+        let Some(eye) = self.stereo_eye() else {
+            return;
+        };
+
+        let left_half = (eye == StereoEye::Left) != self.settings().stereo_swap_eyes;
+        let half_width = width * 0.5;
+        self.set_output_viewport(if left_half { 0.0 } else { half_width }, half_width);
+    }
+
+    fn set_output_viewport(&self, x: f32, width: f32) {
+        let height = self.data.lock().gbuffers.shading_result.get_desc().Height;
+        unsafe {
+            self.gpu
+                .lock_context()
+                .RSSetViewports(Some(&[D3D11_VIEWPORT {
+                    TopLeftX: x,
+                    TopLeftY: 0.0,
+                    Width: width,
+                    Height: height as f32,
+                    MinDepth: 0.0,
+                    MaxDepth: 1.0,
+                }]));
+        }
+    }
+}
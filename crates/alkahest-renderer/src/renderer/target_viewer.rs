@@ -0,0 +1,212 @@
+use alkahest_data::{
+    dxgi::DxgiFormat, geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage,
+};
+use bevy_ecs::entity::Entity;
+use glam::Vec4;
+use parking_lot::Mutex;
+use strum::EnumIter;
+use windows::Win32::Graphics::Direct3D11::{ID3D11PixelShader, ID3D11ShaderResourceView};
+
+use crate::{
+    ecs::{render::light::ShadowMapRenderer, Scene},
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::{
+        gbuffer::{InspectableKind, InspectableTarget, RenderTarget},
+        Renderer,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter, strum::Display)]
+pub enum TargetChannels {
+    #[default]
+    #[strum(to_string = "RGB")]
+    Rgb,
+    #[strum(to_string = "R")]
+    Red,
+    #[strum(to_string = "G")]
+    Green,
+    #[strum(to_string = "B")]
+    Blue,
+    #[strum(to_string = "A")]
+    Alpha,
+}
+
+impl TargetChannels {
+    fn mask(self) -> Vec4 {
+        match self {
+            TargetChannels::Rgb => Vec4::new(1.0, 1.0, 1.0, 0.0),
+            TargetChannels::Red => Vec4::X,
+            TargetChannels::Green => Vec4::Y,
+            TargetChannels::Blue => Vec4::Z,
+            TargetChannels::Alpha => Vec4::W,
+        }
+    }
+}
+
+/// What the render target viewer shows, requested by the UI every frame it's open
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetViewRequest {
+    /// Name of the target, as listed by [`Renderer::inspectable_targets`]
+    pub target: String,
+    pub channels: TargetChannels,
+    /// Values in this range are mapped from black to white
+    pub range: (f32, f32),
+    /// Layer of array targets, such as shadow map cascades
+    pub layer: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct TargetViewParams {
+    channel_mask: Vec4,
+    range_min: f32,
+    range_max: f32,
+    layer: u32,
+    mode: u32,
+}
+
+/// Copies a single render target into a displayable image for the render target viewer
+pub struct TargetViewer {
+    pixel_shader: ID3D11PixelShader,
+    params_cb: ConstantBuffer<TargetViewParams>,
+    request: Mutex<Option<TargetViewRequest>>,
+    /// Targets that existed the last time a view was requested
+    targets: Mutex<Vec<InspectableTarget>>,
+    output: Mutex<Option<RenderTarget>>,
+}
+
+impl TargetViewer {
+    pub fn new(gpu: SharedGpuContext) -> anyhow::Result<Self> {
+        let pixel_shader = gpu
+            .device
+            .load_pixel_shader(include_dxbc!(ps "misc/target_view.hlsl"))?;
+
+        Ok(Self {
+            pixel_shader,
+            params_cb: ConstantBuffer::create(gpu, None)?,
+            request: Mutex::new(None),
+            targets: Mutex::new(vec![]),
+            output: Mutex::new(None),
+        })
+    }
+}
+
+impl Renderer {
+    /// Draws `request` into the target viewer output at the end of the next frame. Has to be
+    /// called every frame the viewer is open
+    pub fn request_target_view(&self, request: TargetViewRequest) {
+        *self.target_viewer.request.lock() = Some(request);
+    }
+
+    /// Targets that could be viewed during the last frame a view was requested
+    pub fn inspectable_targets(&self) -> Vec<InspectableTarget> {
+        self.target_viewer.targets.lock().clone()
+    }
+
+    /// The image drawn for the last request, in RGBA8
+    pub fn target_view_output(&self) -> Option<ID3D11ShaderResourceView> {
+        self.target_viewer
+            .output
+            .lock()
+            .as_ref()
+            .map(|rt| rt.view.clone())
+    }
+
+    pub(super) fn draw_target_view(&self, scene: &mut Scene) {
+        let Some(request) = self.target_viewer.request.lock().take() else {
+            return;
+        };
+
+        gpu_event!(self.gpu, "target_view");
+        let mut targets = self.data.lock().gbuffers.inspectable_targets();
+        targets.push(
+            self.pickbuffer
+                .targets
+                .lock()
+                .pick_buffer
+                .inspectable("Entity pickbuffer"),
+        );
+        for (entity, shadowmap) in scene.query::<(Entity, &ShadowMapRenderer)>().iter(scene) {
+            targets.push(
+                shadowmap
+                    .depth_map()
+                    .inspectable(&format!("Shadow map (entity {})", entity.index())),
+            );
+        }
+
+        let source = targets.iter().find(|t| t.name == request.target).cloned();
+        *self.target_viewer.targets.lock() = targets;
+        let Some(source) = source else {
+            return;
+        };
+
+        let mut output = self.target_viewer.output.lock();
+        let size_changed = output.as_ref().map_or(true, |rt| {
+            let desc = rt.get_desc();
+            (desc.Width, desc.Height) != source.size
+        });
+        if size_changed {
+            match RenderTarget::create(
+                source.size,
+                DxgiFormat::R8G8B8A8_UNORM,
+                self.gpu.clone(),
+                "Target_View",
+            ) {
+                Ok(rt) => *output = Some(rt),
+                Err(e) => {
+                    error!("Failed to create target view output: {e:?}");
+                    *output = None;
+                }
+            }
+        }
+        let Some(output) = output.as_ref() else {
+            return;
+        };
+
+        let mode = match source.kind {
+            InspectableKind::Color => 0,
+            InspectableKind::DepthArray { .. } => 1,
+            InspectableKind::Uint => 2,
+        };
+        self.target_viewer
+            .params_cb
+            .write(&TargetViewParams {
+                channel_mask: request.channels.mask(),
+                range_min: request.range.0,
+                range_max: request.range.1,
+                layer: request.layer,
+                mode,
+            })
+            .ok();
+        self.target_viewer.params_cb.bind(0, TfxShaderStage::Pixel);
+
+        self.gpu
+            .current_states
+            .store(StateSelection::new(Some(0), Some(0), Some(0), Some(0)));
+        self.gpu.flush_states();
+        output.bind();
+
+        let mut views = [None, None, None];
+        views[mode as usize] = Some(source.view.clone());
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.RSSetState(None);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.VSSetShader(&self.gpu.util_resources.blit_vs, None);
+            ctx.PSSetShader(&self.target_viewer.pixel_shader, None);
+            ctx.PSSetSamplers(
+                0,
+                Some(&[Some(self.gpu.util_resources.point_sampler.clone())]),
+            );
+            ctx.PSSetShaderResources(0, Some(&views));
+        }
+        self.gpu.set_input_topology(EPrimitiveType::Triangles);
+
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.Draw(3, 0);
+            ctx.PSSetShaderResources(0, Some(&[None, None, None]));
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use alkahest_data::{geometry::EPrimitiveType, technique::StateSelection, tfx::TfxShaderStage};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+use windows::Win32::Graphics::{
+    Direct3D11::{
+        ID3D11PixelShader, ID3D11RenderTargetView, ID3D11ShaderResourceView, ID3D11VertexShader,
+    },
+    Dxgi::Common::{
+        DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709, DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+        DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709, DXGI_COLOR_SPACE_TYPE, DXGI_FORMAT,
+        DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+    },
+};
+
+use crate::{
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::{RenderDebugView, Renderer},
+};
+
+#[derive(
+    Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, EnumIter, strum::Display,
+)]
+pub enum OutputMode {
+    #[default]
+    Sdr,
+    /// 10-bit ST 2084 (PQ) output in the Rec. 2020 color space
+    Hdr10,
+    /// 16-bit floating point output, linear Rec. 709
+    ScRgb,
+}
+
+impl OutputMode {
+    pub fn is_hdr(&self) -> bool {
+        *self != OutputMode::Sdr
+    }
+
+    /// Swapchain format and color space used for this output mode
+    pub fn swapchain_format(&self) -> (DXGI_FORMAT, DXGI_COLOR_SPACE_TYPE) {
+        match self {
+            OutputMode::Sdr => (
+                DXGI_FORMAT_B8G8R8A8_UNORM,
+                DXGI_COLOR_SPACE_RGB_FULL_G22_NONE_P709,
+            ),
+            OutputMode::Hdr10 => (
+                DXGI_FORMAT_R10G10B10A2_UNORM,
+                DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+            ),
+            OutputMode::ScRgb => (
+                DXGI_FORMAT_R16G16B16A16_FLOAT,
+                DXGI_COLOR_SPACE_RGB_FULL_G10_NONE_P709,
+            ),
+        }
+    }
+}
+
+#[derive(
+    Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, EnumIter, strum::Display,
+)]
+pub enum Tonemapper {
+    /// The game's own film curve (final_combine)
+    #[default]
+    FilmCurve,
+    Aces,
+    Reinhard,
+    Clamp,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestTonemap {
+    exposure_scale: f32,
+    tonemapper: u32,
+    output_mode: u32,
+    paper_white: f32,
+    peak_luminance: f32,
+    input_is_linear: u32,
+    _pad: [u32; 2],
+}
+
+/// Manual exposure, tonemapping and encoding of the final image for HDR swapchains
+pub struct TonemapRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_exposure_ps: ID3D11PixelShader,
+    shader_tonemap_ps: ID3D11PixelShader,
+    shader_output_ps: ID3D11PixelShader,
+
+    scope: ConstantBuffer<ScopeAlkahestTonemap>,
+}
+
+impl TonemapRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "postprocess/tonemap.hlsl"))?,
+            shader_exposure_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/exposure.hlsl"))?,
+            shader_tonemap_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "postprocess/tonemap.hlsl"))?,
+            shader_output_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "util/output_encode.hlsl"))?,
+            scope: ConstantBuffer::create(gctx, None)?,
+        })
+    }
+
+    fn bind_scope(&self, renderer: &Renderer, input_is_linear: bool) {
+        let settings = renderer.settings();
+        self.scope
+            .write(&ScopeAlkahestTonemap {
+                exposure_scale: 2f32.powf(settings.exposure),
+                tonemapper: settings.tonemapper as u32,
+                output_mode: settings.output_mode as u32,
+                paper_white: settings.hdr_paper_white,
+                peak_luminance: settings.hdr_peak_luminance.max(settings.hdr_paper_white),
+                input_is_linear: input_is_linear as u32,
+                _pad: [0; 2],
+            })
+            .unwrap();
+        self.scope.bind(0, TfxShaderStage::Pixel);
+    }
+
+    /// Writes the shading result to `shading_result_read` with the manual exposure applied
+    pub fn draw_exposure(&self, renderer: &Renderer) {
+        gpu_event!(renderer.gpu, "exposure");
+        let (source, target) = {
+            let data = renderer.data.lock();
+            (
+                data.gbuffers.shading_result.view.clone(),
+                data.gbuffers.shading_result_read.render_target.clone(),
+            )
+        };
+
+        self.bind_scope(renderer, true);
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_exposure_ps,
+            &source,
+            &target,
+            StateSelection::new(Some(0), Some(0), Some(0), Some(0)),
+        );
+    }
+
+    /// Tonemaps `shading_result_read` into `target`, as a replacement for final_combine
+    pub fn draw_tonemap(&self, renderer: &Renderer, target: &ID3D11RenderTargetView) {
+        gpu_event!(renderer.gpu, "tonemap");
+        let source = renderer
+            .data
+            .lock()
+            .gbuffers
+            .shading_result_read
+            .view
+            .clone();
+
+        self.bind_scope(renderer, true);
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_tonemap_ps,
+            &source,
+            target,
+            StateSelection::new(Some(0), Some(0), Some(0), Some(0)),
+        );
+    }
+
+    /// Converts `source` to the output color space and draws it to `target`. Premultiplied
+    /// sources (such as the UI) are blended over the target.
+    pub fn draw_output(
+        &self,
+        renderer: &Renderer,
+        source: &ID3D11ShaderResourceView,
+        target: &ID3D11RenderTargetView,
+        input_is_linear: bool,
+        blend: bool,
+    ) {
+        gpu_event!(renderer.gpu, "output_encode");
+        self.bind_scope(renderer, input_is_linear);
+        self.draw_fullscreen(
+            renderer,
+            &self.shader_output_ps,
+            source,
+            target,
+            // Blend state 8 is premultiplied alpha
+            StateSelection::new(Some(if blend { 8 } else { 0 }), Some(0), Some(0), Some(0)),
+        );
+    }
+
+    fn draw_fullscreen(
+        &self,
+        renderer: &Renderer,
+        shader_ps: &ID3D11PixelShader,
+        source: &ID3D11ShaderResourceView,
+        target: &ID3D11RenderTargetView,
+        states: StateSelection,
+    ) {
+        renderer.gpu.current_states.store(states);
+        renderer.gpu.flush_states();
+
+        unsafe {
+            let ctx = renderer.gpu.lock_context();
+            ctx.OMSetRenderTargets(Some(&[Some(target.clone())]), None);
+            ctx.RSSetState(None);
+            renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+            ctx.OMSetDepthStencilState(None, 0);
+            ctx.VSSetShader(&self.shader_vs, None);
+            ctx.PSSetShader(shader_ps, None);
+            ctx.PSSetShaderResources(0, Some(&[Some(source.clone())]));
+
+            ctx.Draw(3, 0);
+
+            // Unbind the source so it can be used as a render target again
+            ctx.PSSetShaderResources(0, Some(&[None]));
+        }
+    }
+}
+
+impl Renderer {
+    /// Whether the final image is tonemapped by alkahest instead of the game's film curve
+    pub(super) fn uses_custom_tonemapper(&self) -> bool {
+        let settings = self.settings();
+        settings.tonemapper != Tonemapper::FilmCurve
+            && settings.effective_debug_view() == RenderDebugView::None
+    }
+
+    /// Draws the final image to the swapchain
+    pub(super) fn present_final_image(&self, swapchain_target: &ID3D11RenderTargetView) {
+        let source = self.data.lock().gbuffers.shading_result.view.clone();
+        if self.settings().output_mode.is_hdr() {
+            self.tonemap.draw_output(
+                self,
+                &source,
+                swapchain_target,
+                self.final_blit_srgb(),
+                false,
+            );
+        } else {
+            self.gpu
+                .blit_texture(&source, swapchain_target, self.final_blit_srgb());
+        }
+    }
+
+    /// Render target the UI has to be drawn to when it can't be drawn to the swapchain directly
+    /// (HDR output). The target is cleared on every call, and has to be composited onto the
+    /// swapchain with [`Self::composite_ui_overlay`] afterwards.
+    pub fn ui_overlay_target(&self) -> Option<ID3D11RenderTargetView> {
+        if !self.settings().output_mode.is_hdr() || self.gpu.swap_chain().is_none() {
+            return None;
+        }
+
+        let data = self.data.lock();
+        data.gbuffers.ui_overlay.clear(&[0.0, 0.0, 0.0, 0.0]);
+        Some(data.gbuffers.ui_overlay.render_target.clone())
+    }
+
+    /// Blends the UI overlay onto the swapchain, converted to the output color space
+    pub fn composite_ui_overlay(&self) {
+        let Some(swapchain_target) = self.gpu.swapchain_target.read().clone() else {
+            return;
+        };
+
+        let source = self.data.lock().gbuffers.ui_overlay.view.clone();
+        self.tonemap
+            .draw_output(self, &source, &swapchain_target, false, true);
+    }
+}
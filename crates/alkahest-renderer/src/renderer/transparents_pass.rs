@@ -58,7 +58,13 @@ impl Renderer {
                 unk30: self.gpu.light_grey_texture.view.clone().into(),
                 unk38: self.gpu.light_grey_texture.view.clone().into(),
                 unk40: self.gpu.light_grey_texture.view.clone().into(),
-                unk48: externs::TextureView::Null, // volumetrics
+                // surf_volumetrics_result, assumed to hold in-scattering and transmittance like
+                // ours
+                unk48: if self.volumetrics_enabled() {
+                    data.gbuffers.volumetrics.view.clone().into()
+                } else {
+                    externs::TextureView::Null
+                },
                 unk50: externs::TextureView::Null, // volumetrics
                 unk58: self.gpu.light_grey_texture.view.clone().into(),
                 unk60: data.gbuffers.shading_result_read.view.clone().into(),
@@ -76,29 +82,33 @@ impl Renderer {
                 .cbuffer
                 .as_ref()
             {
-                assert!(
-                    std::mem::size_of_val(ta_cb.data_array())
-                        >= std::mem::size_of::<ScopeTransparentAdvanced>()
-                );
+                {
+                    let mut cb_data = ta_cb.data_array();
+                    assert!(
+                        std::mem::size_of_val(&*cb_data)
+                            >= std::mem::size_of::<ScopeTransparentAdvanced>()
+                    );
 
-                unsafe {
-                    (ta_cb.data_array().as_ptr() as *mut ScopeTransparentAdvanced)
-                        .write(ScopeTransparentAdvanced::default());
-                    let slot = self
-                        .render_globals
-                        .scopes
-                        .transparent_advanced
-                        .stage_pixel
-                        .as_ref()
-                        .unwrap()
-                        .stage
-                        .constants
-                        .constant_buffer_slot as u32;
-
-                    ta_cb.bind(slot, TfxShaderStage::Pixel);
-                    ta_cb.bind(slot, TfxShaderStage::Vertex);
-                    ta_cb.bind(slot, TfxShaderStage::Compute);
+                    unsafe {
+                        (cb_data.as_mut_ptr() as *mut ScopeTransparentAdvanced)
+                            .write(ScopeTransparentAdvanced::default());
+                    }
                 }
+
+                let slot = self
+                    .render_globals
+                    .scopes
+                    .transparent_advanced
+                    .stage_pixel
+                    .as_ref()
+                    .unwrap()
+                    .stage
+                    .constants
+                    .constant_buffer_slot as u32;
+
+                ta_cb.bind(slot, TfxShaderStage::Pixel);
+                ta_cb.bind(slot, TfxShaderStage::Vertex);
+                ta_cb.bind(slot, TfxShaderStage::Compute);
             }
 
             unsafe {
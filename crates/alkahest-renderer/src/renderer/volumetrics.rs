@@ -0,0 +1,218 @@
+use std::sync::atomic::Ordering;
+
+use alkahest_data::{map::SShadowingLight, tfx::TfxShaderStage};
+use glam::{Mat4, Vec4};
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11SamplerState, ID3D11ShaderResourceView, ID3D11VertexShader,
+    D3D11_FILTER_MIN_MAG_MIP_LINEAR, D3D11_SAMPLER_DESC, D3D11_TEXTURE_ADDRESS_CLAMP,
+    D3D11_VIEWPORT,
+};
+
+use crate::{
+    ecs::{
+        render::light::ShadowMapRenderer,
+        visibility::{ViewVisibility, VisibilityHelper},
+        Scene,
+    },
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    postprocess::draw_fullscreen,
+    renderer::Renderer,
+    tfx::view::View,
+};
+
+/// Number of shadowing lights that cast light shafts, the ones closest to the camera are used
+pub const MAX_SHAFT_LIGHTS: usize = 4;
+/// Number of samples along every ray
+const VOLUMETRICS_STEPS: u32 = 48;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ScopeAlkahestVolumetrics {
+    target_pixel_to_world: Mat4,
+    light_world_to_projective: [Mat4; MAX_SHAFT_LIGHTS],
+    /// World space position (xyz) and range (w) of every light
+    light_position_range: [Vec4; MAX_SHAFT_LIGHTS],
+    /// Fog color (rgb) and density (a)
+    fog_color_density: Vec4,
+    /// Light shaft color premultiplied by the intensity (rgb) and anisotropy (a)
+    light_color_anisotropy: Vec4,
+    height_falloff: f32,
+    base_height: f32,
+    max_distance: f32,
+    light_count: u32,
+    step_count: u32,
+    frame_index: u32,
+    _pad: [u32; 2],
+}
+
+/// Raymarched depth fog and light shafts. Marches through the scene at half resolution,
+/// accumulating fog and the light of the shadowing lights, then composites the result over the
+/// shading result before transparents are drawn
+pub struct VolumetricsRenderer {
+    shader_vs: ID3D11VertexShader,
+    shader_march_ps: ID3D11PixelShader,
+    shader_apply_ps: ID3D11PixelShader,
+    sampler_linear: ID3D11SamplerState,
+
+    scope: ConstantBuffer<ScopeAlkahestVolumetrics>,
+}
+
+impl VolumetricsRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        Ok(Self {
+            shader_vs: gctx
+                .device
+                .load_vertex_shader(include_dxbc!(vs "misc/volumetrics.hlsl"))?,
+            shader_march_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "misc/volumetrics.hlsl"))?,
+            shader_apply_ps: gctx
+                .device
+                .load_pixel_shader(include_dxbc!(ps "misc/volumetrics_apply.hlsl"))?,
+            sampler_linear: gctx.device.create_sampler_state(&D3D11_SAMPLER_DESC {
+                Filter: D3D11_FILTER_MIN_MAG_MIP_LINEAR,
+                AddressU: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressV: D3D11_TEXTURE_ADDRESS_CLAMP,
+                AddressW: D3D11_TEXTURE_ADDRESS_CLAMP,
+                ..Default::default()
+            })?,
+            scope: ConstantBuffer::create(gctx, None)?,
+        })
+    }
+}
+
+impl Renderer {
+    pub(super) fn volumetrics_enabled(&self) -> bool {
+        let settings = self.settings();
+        settings.volumetrics && !settings.effective_matcap()
+    }
+
+    /// Shadowing lights closest to the camera, along with their shadow maps
+    fn shaft_lights(
+        &self,
+        scene: &Scene,
+        camera_position: Vec4,
+    ) -> Vec<(Mat4, Vec4, ID3D11ShaderResourceView)> {
+        if !self.settings().shadows_enabled() {
+            return vec![];
+        }
+
+        let mut lights = scene
+            .query::<(
+                &ShadowMapRenderer,
+                &SShadowingLight,
+                Option<&ViewVisibility>,
+            )>()
+            .iter(scene)
+            .filter(|(_, _, vis)| vis.is_visible(self.active_view()))
+            .map(|(shadow, light, _)| {
+                (
+                    shadow.world_to_projective(),
+                    shadow.position().extend(light.far_plane),
+                    shadow.depth_map().texture_view.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        lights.sort_by(|(_, a, _), (_, b, _)| {
+            let da = a.truncate().distance_squared(camera_position.truncate());
+            let db = b.truncate().distance_squared(camera_position.truncate());
+            da.total_cmp(&db)
+        });
+        lights.truncate(MAX_SHAFT_LIGHTS);
+        lights
+    }
+
+    /// Marches the fog and light shafts and composites them over the shading result. Expects the
+    /// shading pass and the shadow maps to be done
+    pub(super) fn draw_volumetrics(&self, scene: &Scene) {
+        gpu_event!(self.gpu, "volumetrics");
+        let settings = self.settings();
+        let Some(view) = self.data.lock().externs.view.clone() else {
+            return;
+        };
+
+        let lights = self.shaft_lights(scene, view.position);
+        let mut light_world_to_projective = [Mat4::IDENTITY; MAX_SHAFT_LIGHTS];
+        let mut light_position_range = [Vec4::ZERO; MAX_SHAFT_LIGHTS];
+        let mut shadow_maps = vec![None; MAX_SHAFT_LIGHTS];
+        for (i, (world_to_projective, position_range, shadow_map)) in
+            lights.iter().cloned().enumerate()
+        {
+            light_world_to_projective[i] = world_to_projective;
+            light_position_range[i] = position_range;
+            shadow_maps[i] = Some(shadow_map);
+        }
+
+        let volumetrics = &self.volumetrics;
+        let [r, g, b] = settings.volumetrics_fog_color;
+        let [lr, lg, lb] = settings.volumetrics_light_color;
+        let light_intensity = settings.volumetrics_light_intensity;
+        volumetrics
+            .scope
+            .write(&ScopeAlkahestVolumetrics {
+                target_pixel_to_world: view.target_pixel_to_world,
+                light_world_to_projective,
+                light_position_range,
+                fog_color_density: Vec4::new(r, g, b, settings.volumetrics_density),
+                light_color_anisotropy: Vec4::new(
+                    lr * light_intensity,
+                    lg * light_intensity,
+                    lb * light_intensity,
+                    settings.volumetrics_anisotropy,
+                ),
+                height_falloff: settings.volumetrics_height_falloff,
+                base_height: settings.volumetrics_base_height,
+                max_distance: settings.volumetrics_max_distance,
+                light_count: lights.len() as u32,
+                step_count: VOLUMETRICS_STEPS,
+                frame_index: self.frame_index.load(Ordering::Relaxed) as u32,
+                _pad: [0; 2],
+            })
+            .ok();
+        volumetrics.scope.bind(0, TfxShaderStage::Pixel);
+
+        let data = self.data.lock();
+        let gbuffers = &data.gbuffers;
+        let mut viewport_count = 1;
+        let mut previous_viewport = D3D11_VIEWPORT::default();
+        unsafe {
+            let ctx = self.gpu.lock_context();
+            ctx.RSGetViewports(&mut viewport_count, Some(&mut previous_viewport));
+            ctx.RSSetViewports(Some(&[gbuffers.volumetrics.viewport()]));
+        }
+
+        let mut sources = vec![Some(gbuffers.depth.texture_view.clone())];
+        sources.extend(shadow_maps);
+        draw_fullscreen(
+            self,
+            &volumetrics.shader_vs,
+            &volumetrics.shader_march_ps,
+            &volumetrics.sampler_linear,
+            &sources,
+            &gbuffers.volumetrics.render_target,
+        );
+
+        unsafe {
+            self.gpu
+                .lock_context()
+                .RSSetViewports(Some(&[previous_viewport]));
+        }
+
+        gbuffers
+            .shading_result
+            .copy_to(&gbuffers.shading_result_read);
+        draw_fullscreen(
+            self,
+            &volumetrics.shader_vs,
+            &volumetrics.shader_apply_ps,
+            &volumetrics.sampler_linear,
+            &[
+                Some(gbuffers.shading_result_read.view.clone()),
+                Some(gbuffers.volumetrics.view.clone()),
+            ],
+            &gbuffers.shading_result.render_target,
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use alkahest_data::tfx::{TfxRenderStage, TfxShaderStage};
+use anyhow::Context;
+use bevy_ecs::{entity::Entity, query::With};
+use glam::Vec4;
+use parking_lot::Mutex;
+use windows::Win32::Graphics::Direct3D11::{
+    ID3D11PixelShader, ID3D11RasterizerState, D3D11_CULL_NONE, D3D11_FILL_SOLID,
+    D3D11_FILL_WIREFRAME, D3D11_RASTERIZER_DESC,
+};
+
+use crate::{
+    ecs::{
+        render::{
+            draw_entity,
+            lod::{selected_lod, LOD_DEBUG_COLORS},
+            terrain::{terrain_material_color, TerrainPatches},
+            ShowWireframe,
+        },
+        visibility::{ViewVisibility, VisibilityHelper},
+        Scene,
+    },
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
+    gpu_event, include_dxbc,
+    renderer::Renderer,
+};
+
+const WIREFRAME_COLOR: Vec4 = Vec4::new(0.1, 0.85, 1.0, 1.0);
+
+/// Draws the triangle edges of meshes on top of the shaded image
+pub struct WireframeOverlayRenderer {
+    gpu: SharedGpuContext,
+    pixel_shader: ID3D11PixelShader,
+    color_cb: ConstantBuffer<Vec4>,
+    static_instance_cb: ConstantBuffer<u8>,
+    /// Wireframe and solid rasterizer states, along with the depth bias they were created with
+    rasterizer_states: Mutex<[Option<(i32, ID3D11RasterizerState)>; 2]>,
+}
+
+impl WireframeOverlayRenderer {
+    pub fn new(gctx: SharedGpuContext) -> anyhow::Result<Self> {
+        let pixel_shader = gctx
+            .device
+            .load_pixel_shader(include_dxbc!(ps "debug/wireframe.hlsl"))
+            .unwrap();
+
+        Ok(Self {
+            pixel_shader,
+            color_cb: ConstantBuffer::create(gctx.clone(), None)?,
+            static_instance_cb: ConstantBuffer::create_array_init(gctx.clone(), &[0u8; 32 + 64])?,
+            rasterizer_states: Mutex::new([None, None]),
+            gpu: gctx,
+        })
+    }
+
+    /// Returns the wireframe (or solid) rasterizer state for the given depth bias, recreating it
+    /// when the bias has changed. The slope scale follows the same ratio as the game's depth bias
+    /// states
+    fn rasterizer_state(
+        &self,
+        depth_bias: i32,
+        solid: bool,
+    ) -> anyhow::Result<ID3D11RasterizerState> {
+        let mut states = self.rasterizer_states.lock();
+        let cached = &mut states[solid as usize];
+        if let Some((bias, state)) = cached.as_ref() {
+            if *bias == depth_bias {
+                return Ok(state.clone());
+            }
+        }
+
+        let mut state = None;
+        unsafe {
+            self.gpu.device.CreateRasterizerState(
+                &D3D11_RASTERIZER_DESC {
+                    FillMode: if solid {
+                        D3D11_FILL_SOLID
+                    } else {
+                        D3D11_FILL_WIREFRAME
+                    },
+                    CullMode: D3D11_CULL_NONE,
+                    FrontCounterClockwise: true.into(),
+                    DepthBias: depth_bias,
+                    DepthBiasClamp: 0.0,
+                    SlopeScaledDepthBias: depth_bias as f32 * 0.4,
+                    DepthClipEnable: true.into(),
+                    ScissorEnable: false.into(),
+                    MultisampleEnable: false.into(),
+                    AntialiasedLineEnable: false.into(),
+                },
+                Some(&mut state),
+            )?;
+        }
+
+        let state = state.context("Failed to create wireframe rasterizer state")?;
+        *cached = Some((depth_bias, state.clone()));
+        Ok(state)
+    }
+}
+
+impl Renderer {
+    /// Draws the wireframe of every entity with [`ShowWireframe`], or of the whole scene when the
+    /// global wireframe overlay is enabled. Expects the depth buffer to be bound
+    pub(super) fn draw_wireframe_overlay(&self, scene: &mut Scene) {
+        let settings = self.settings();
+        let entities = if settings.wireframe_overlay {
+            None
+        } else {
+            let entities = scene
+                .query_filtered::<Entity, With<ShowWireframe>>()
+                .iter(scene)
+                .filter(|&e| scene.entity(e).get::<ViewVisibility>().is_visible(0))
+                .collect::<Vec<_>>();
+            if entities.is_empty() {
+                return;
+            }
+            Some(entities)
+        };
+
+        let rasterizer_state = match self
+            .wireframe
+            .rasterizer_state(settings.wireframe_overlay_bias, false)
+        {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to draw wireframe overlay: {e:?}");
+                return;
+            }
+        };
+
+        gpu_event!(self.gpu, "wireframe_overlay");
+        self.wireframe.color_cb.write(&WIREFRAME_COLOR).ok();
+        self.wireframe.color_cb.bind(7, TfxShaderStage::Pixel);
+        self.gpu.bind_pixel_shader(&self.wireframe.pixel_shader);
+        *self.gpu.custom_pixel_shader.write() = Some(self.wireframe.pixel_shader.clone());
+        *self.gpu.custom_rasterizer_state.write() = Some(rasterizer_state);
+        self.gpu.flush_states();
+
+        match entities {
+            None => self.run_renderstage_systems(scene, TfxRenderStage::GenerateGbuffer),
+            Some(entities) => {
+                for e in entities {
+                    draw_entity(
+                        scene,
+                        e,
+                        self,
+                        Some(&self.wireframe.static_instance_cb),
+                        TfxRenderStage::GenerateGbuffer,
+                    );
+                }
+            }
+        }
+
+        *self.gpu.custom_pixel_shader.write() = None;
+        *self.gpu.custom_rasterizer_state.write() = None;
+        self.gpu.flush_states();
+    }
+
+    /// Covers every model with the color of its selected LOD level. Drawn with the same depth
+    /// bias as the wireframe overlay, so the colors aren't hidden by the shaded surfaces
+    pub(super) fn draw_lod_overlay(&self, scene: &mut Scene) {
+        let settings = self.settings();
+        if !settings.lod_debug_colors {
+            return;
+        }
+
+        let mut entities_by_lod: [Vec<Entity>; LOD_DEBUG_COLORS.len()] = Default::default();
+        for e in scene.iter_entities() {
+            if !e.get::<ViewVisibility>().is_visible(0) {
+                continue;
+            }
+
+            if let Some(lod) = selected_lod(&e) {
+                entities_by_lod[lod as usize].push(e.id());
+            }
+        }
+
+        let rasterizer_state = match self
+            .wireframe
+            .rasterizer_state(settings.wireframe_overlay_bias, true)
+        {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to draw LOD overlay: {e:?}");
+                return;
+            }
+        };
+
+        gpu_event!(self.gpu, "lod_overlay");
+        self.wireframe.color_cb.bind(7, TfxShaderStage::Pixel);
+        self.gpu.bind_pixel_shader(&self.wireframe.pixel_shader);
+        *self.gpu.custom_pixel_shader.write() = Some(self.wireframe.pixel_shader.clone());
+        *self.gpu.custom_rasterizer_state.write() = Some(rasterizer_state);
+        self.gpu.flush_states();
+
+        for (entities, color) in entities_by_lod.iter().zip(LOD_DEBUG_COLORS) {
+            self.wireframe.color_cb.write(&color).ok();
+            for &e in entities {
+                draw_entity(
+                    scene,
+                    e,
+                    self,
+                    Some(&self.wireframe.static_instance_cb),
+                    TfxRenderStage::GenerateGbuffer,
+                );
+            }
+        }
+
+        *self.gpu.custom_pixel_shader.write() = None;
+        *self.gpu.custom_rasterizer_state.write() = None;
+        self.gpu.flush_states();
+    }
+
+    /// Covers terrain patches with a color per material (technique), the same colors are shown
+    /// for the parts listed in the terrain inspector.
+    ///
+    /// This works per part only: the dye map isn't sampled for per-pixel blend weights, as neither
+    /// its channel layout nor the outputs of the terrain vertex shader (which this pixel shader
+    /// would need the texture coordinates from) are known
+    pub(super) fn draw_terrain_material_overlay(&self, scene: &mut Scene) {
+        let settings = self.settings();
+        if !settings.terrain_material_colors {
+            return;
+        }
+
+        let rasterizer_state = match self
+            .wireframe
+            .rasterizer_state(settings.wireframe_overlay_bias, true)
+        {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to draw terrain material overlay: {e:?}");
+                return;
+            }
+        };
+
+        gpu_event!(self.gpu, "terrain_material_overlay");
+        self.gpu.bind_pixel_shader(&self.wireframe.pixel_shader);
+        *self.gpu.custom_pixel_shader.write() = Some(self.wireframe.pixel_shader.clone());
+        *self.gpu.custom_rasterizer_state.write() = Some(rasterizer_state);
+        self.gpu.flush_states();
+
+        for (terrain, vis) in scene
+            .query::<(&TerrainPatches, Option<&ViewVisibility>)>()
+            .iter(scene)
+        {
+            if !vis.is_visible(0) {
+                continue;
+            }
+
+            terrain.draw_with(self, TfxRenderStage::GenerateGbuffer, |part| {
+                self.wireframe
+                    .color_cb
+                    .write(&terrain_material_color(part.technique))
+                    .ok();
+                self.wireframe.color_cb.bind(7, TfxShaderStage::Pixel);
+            });
+        }
+
+        *self.gpu.custom_pixel_shader.write() = None;
+        *self.gpu.custom_rasterizer_state.write() = None;
+        self.gpu.flush_states();
+    }
+}
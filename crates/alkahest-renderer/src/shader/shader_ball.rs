@@ -4,8 +4,11 @@ use alkahest_data::{
     geometry::EPrimitiveType,
     tfx::{TfxRenderStage, TfxShaderStage},
 };
+use std::f32::consts::{PI, TAU};
+
 use bevy_ecs::{component::Component, entity::Entity};
 use glam::{Mat4, Vec3, Vec4};
+use strum::EnumIter;
 use windows::Win32::Graphics::Direct3D11::{ID3D11PixelShader, ID3D11VertexShader};
 
 use crate::{
@@ -14,10 +17,10 @@ use crate::{
         visibility::{ViewVisibility, VisibilityHelper},
         Scene,
     },
-    gpu::{buffer::ConstantBuffer, util::DxDeviceExt},
+    gpu::{buffer::ConstantBuffer, util::DxDeviceExt, SharedGpuContext},
     gpu_event, include_dxbc,
     loaders::vertex_buffer::VertexBuffer,
-    renderer::Renderer,
+    renderer::{PickLayer, Renderer},
 };
 
 #[repr(C)]
@@ -65,26 +68,99 @@ impl ShaderBallRenderer {
             }
         }
 
-        let vshader = renderer
-            .gpu
+        Self::from_vertices(&renderer.gpu, &vertices)
+    }
+
+    /// A UV sphere with a radius of 1, used for light probe spheres
+    pub fn sphere(gpu: &SharedGpuContext) -> anyhow::Result<Self> {
+        const RINGS: usize = 16;
+        const SEGMENTS: usize = 32;
+
+        let vertex = |ring: usize, segment: usize| {
+            let u = segment as f32 / SEGMENTS as f32;
+            let v = ring as f32 / RINGS as f32;
+            let (sin_theta, cos_theta) = (v * PI).sin_cos();
+            let (sin_phi, cos_phi) = (u * TAU).sin_cos();
+            let n = Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+            [n.x, n.y, n.z, u, v, n.x, n.y, n.z]
+        };
+
+        let mut vertices = Vec::with_capacity(RINGS * SEGMENTS * 6);
+        for ring in 0..RINGS {
+            for segment in 0..SEGMENTS {
+                let quad = [
+                    vertex(ring, segment),
+                    vertex(ring + 1, segment),
+                    vertex(ring + 1, segment + 1),
+                    vertex(ring, segment + 1),
+                ];
+                vertices.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+            }
+        }
+
+        Self::from_vertices(gpu, &vertices)
+    }
+
+    /// Creates the renderer from a triangle list of position, texcoord and normal vertices
+    fn from_vertices(gpu: &SharedGpuContext, vertices: &[[f32; 8]]) -> anyhow::Result<Self> {
+        let vshader = gpu
             .device
             .load_vertex_shader(include_dxbc!(vs "misc/gbuffer_test.hlsl"))?;
-        let pshader = renderer
-            .gpu
+        let pshader = gpu
             .device
             .load_pixel_shader(include_dxbc!(ps "misc/gbuffer_test.hlsl"))?;
 
         Ok(Self {
             vertex_buffer: VertexBuffer::load_data(
-                &renderer.gpu.device,
-                bytemuck::cast_slice(&vertices),
+                &gpu.device,
+                bytemuck::cast_slice(vertices),
                 32,
             )?,
             vshader,
             pshader,
-            cbuffer: ConstantBuffer::create(renderer.gpu.clone(), None)?,
+            cbuffer: ConstantBuffer::create(gpu.clone(), None)?,
         })
     }
+
+    fn draw(&self, renderer: &Renderer, cbuffer: &ShaderBallCbuffer, render_stage: TfxRenderStage) {
+        if !matches!(
+            render_stage,
+            TfxRenderStage::GenerateGbuffer
+                | TfxRenderStage::ShadowGenerate
+                | TfxRenderStage::DepthPrepass
+        ) {
+            return;
+        }
+
+        gpu_event!(renderer.gpu, "draw_shaderball");
+        self.cbuffer.write(cbuffer).unwrap();
+
+        renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
+        renderer.gpu.set_input_layout(12);
+        self.vertex_buffer.bind_single(&renderer.gpu, 0);
+        self.cbuffer.bind(0, TfxShaderStage::Vertex);
+        self.cbuffer.bind(0, TfxShaderStage::Pixel);
+
+        renderer.gpu.set_blend_state(0);
+        renderer.gpu.set_depth_stencil_state(2);
+        renderer.gpu.set_rasterizer_state(2);
+        renderer.gpu.set_depth_bias(0);
+
+        unsafe {
+            renderer.gpu.lock_context().VSSetShader(&self.vshader, None);
+
+            if render_stage == TfxRenderStage::GenerateGbuffer {
+                renderer.gpu.bind_pixel_shader(&self.pshader);
+            } else {
+                renderer.gpu.bind_pixel_shader(None);
+            }
+
+            renderer
+                .gpu
+                .lock_context()
+                .Draw(self.vertex_buffer.length, 0);
+        }
+    }
 }
 
 #[derive(Component)]
@@ -113,60 +189,75 @@ impl ShaderBallComponent {
     }
 
     pub fn draw(&self, renderer: &Renderer, transform: &Transform, render_stage: TfxRenderStage) {
-        if !matches!(
-            render_stage,
-            TfxRenderStage::GenerateGbuffer
-                | TfxRenderStage::ShadowGenerate
-                | TfxRenderStage::DepthPrepass
-        ) {
-            return;
-        }
-
-        gpu_event!(renderer.gpu, "draw_shaderball");
-        self.renderer
-            .cbuffer
-            .write(&ShaderBallCbuffer {
+        self.renderer.draw(
+            renderer,
+            &ShaderBallCbuffer {
                 model_to_world: transform.local_to_world(),
                 rgb_iridescent: self.color.extend((self.iridescence + 128) as f32 / 254.0),
                 smoothness: self.smoothness,
                 metalness: self.metalness,
                 emission: self.emission,
                 transmission: self.transmission,
-            })
-            .unwrap();
+            },
+            render_stage,
+        );
+    }
+}
 
-        renderer.gpu.set_input_topology(EPrimitiveType::Triangles);
-        renderer.gpu.set_input_layout(12);
-        self.renderer.vertex_buffer.bind_single(&renderer.gpu, 0);
-        self.renderer.cbuffer.bind(0, TfxShaderStage::Vertex);
-        self.renderer.cbuffer.bind(0, TfxShaderStage::Pixel);
+/// Small sphere showing the ambient and cubemap lighting at its position, for checking which
+/// cubemap volumes are picked and how they blend
+#[derive(Component, Clone, Copy)]
+pub struct LightProbeSphere {
+    pub material: LightProbeMaterial,
+    pub radius: f32,
+}
 
-        renderer.gpu.set_blend_state(0);
-        renderer.gpu.set_depth_stencil_state(2);
-        renderer.gpu.set_rasterizer_state(2);
-        renderer.gpu.set_depth_bias(0);
+impl Default for LightProbeSphere {
+    fn default() -> Self {
+        Self {
+            material: LightProbeMaterial::Mirror,
+            radius: 0.25,
+        }
+    }
+}
 
-        unsafe {
-            renderer
-                .gpu
-                .lock_context()
-                .VSSetShader(&self.renderer.vshader, None);
+#[derive(Clone, Copy, PartialEq, Eq, EnumIter, strum::Display)]
+pub enum LightProbeMaterial {
+    /// Smooth metal, reflects the cubemap
+    Mirror,
+    /// Rough white surface, only lit by the diffuse ambient lighting and lights
+    Diffuse,
+}
 
-            if render_stage == TfxRenderStage::GenerateGbuffer {
-                renderer.gpu.bind_pixel_shader(&self.renderer.pshader);
-            } else {
-                renderer.gpu.bind_pixel_shader(None);
-            }
+impl LightProbeSphere {
+    pub fn draw(&self, renderer: &Renderer, transform: &Transform, render_stage: TfxRenderStage) {
+        let (smoothness, metalness) = match self.material {
+            LightProbeMaterial::Mirror => (1.0, 1.0),
+            LightProbeMaterial::Diffuse => (0.0, 0.0),
+        };
 
-            renderer
-                .gpu
-                .lock_context()
-                .Draw(self.renderer.vertex_buffer.length, 0);
-        }
+        renderer.light_probe_sphere.draw(
+            renderer,
+            &ShaderBallCbuffer {
+                model_to_world: Mat4::from_translation(transform.translation)
+                    * Mat4::from_scale(Vec3::splat(self.radius)),
+                rgb_iridescent: Vec3::ONE.extend(128.0 / 254.0),
+                smoothness,
+                metalness,
+                emission: 0.0,
+                transmission: 0.0,
+            },
+            render_stage,
+        );
     }
 }
 
 pub fn draw_shaderball_system(renderer: &Renderer, scene: &mut Scene, stage: TfxRenderStage) {
+    // Shader balls and probe spheres are placed as utilities
+    if renderer.is_excluded_from_picking(PickLayer::Utilities) {
+        return;
+    }
+
     for (e, transform, ball, view_vis) in scene
         .query::<(
             Entity,
@@ -176,10 +267,26 @@ pub fn draw_shaderball_system(renderer: &Renderer, scene: &mut Scene, stage: Tfx
         )>()
         .iter(scene)
     {
-        if view_vis.is_visible(renderer.active_view) {
+        if view_vis.is_visible(renderer.active_view()) {
             renderer.pickbuffer.with_entity(e, || {
                 ball.draw(renderer, transform, stage);
             });
         }
     }
+
+    for (e, transform, probe, view_vis) in scene
+        .query::<(
+            Entity,
+            &Transform,
+            &LightProbeSphere,
+            Option<&ViewVisibility>,
+        )>()
+        .iter(scene)
+    {
+        if view_vis.is_visible(renderer.active_view()) {
+            renderer.pickbuffer.with_entity(e, || {
+                probe.draw(renderer, transform, stage);
+            });
+        }
+    }
 }
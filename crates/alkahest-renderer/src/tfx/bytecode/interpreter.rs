@@ -515,7 +515,7 @@ impl TfxBytecodeInterpreter {
 
     pub fn dump(&self, constants: &[Vec4], buffer: &ConstantBufferCached<Vec4>) {
         debug!("Dumping TFX interpreter");
-        debug!("- cb0 size: {} elements", buffer.data_array().len());
+        debug!("- cb0 size: {} elements", buffer.data_slice().len());
         if !constants.is_empty() {
             debug!("- Constant table:");
             for (i, v) in constants.iter().enumerate() {
@@ -363,8 +363,8 @@ impl GlobalPipelines {
         pipeline_list[shape_index * 8 + alpha_index * 4 + probes_index * 2 + relighting_index]
     }
 
-    pub fn get_debug_view_pipeline(&self, view: RenderDebugView) -> &Technique {
-        match view {
+    pub fn get_debug_view_pipeline(&self, view: RenderDebugView) -> Option<&Technique> {
+        Some(match view {
             RenderDebugView::None => &self.final_combine,
             RenderDebugView::NoFilmCurve => &self.final_combine_no_film_curve,
             RenderDebugView::GbufferValidation => &self.debug_gbuffer_validation,
@@ -374,6 +374,7 @@ impl GlobalPipelines {
             RenderDebugView::Metalness => &self.debug_metalness,
             RenderDebugView::AmbientOcclusion => &self.debug_ambient_occlusion,
             RenderDebugView::TextureAo => &self.debug_texture_ao,
+            RenderDebugView::VertexAo => &self.debug_local_ambient_occlusion,
             RenderDebugView::Transmission => &self.debug_transmission,
 
             RenderDebugView::ColoredOvercoatId => &self.debug_colored_overcoat_id,
@@ -381,6 +382,7 @@ impl GlobalPipelines {
 
             RenderDebugView::DiffuseColor => &self.debug_diffuse_color,
             RenderDebugView::DiffuseLight => &self.debug_diffuse_light,
+            RenderDebugView::Lightmap => &self.debug_diffuse_ibl,
 
             RenderDebugView::SpecularColor => &self.debug_specular_color,
             RenderDebugView::SpecularLight => &self.debug_specular_light,
@@ -388,6 +390,11 @@ impl GlobalPipelines {
             RenderDebugView::SpecularSmoothness => &self.debug_specular_smoothness,
             RenderDebugView::SpecularOnly => &self.debug_specular_only,
 
+            // Drawn by the DebugViewRenderer
+            RenderDebugView::CubemapInfluence
+            | RenderDebugView::Overdraw
+            | RenderDebugView::LightVolumeCounts => return None,
+
             RenderDebugView::Emissive => &self.debug_emissive,
             RenderDebugView::EmissiveIntensity => &self.debug_emissive_intensity,
             RenderDebugView::EmissiveLuminance => &self.debug_emissive_luminance,
@@ -407,6 +414,6 @@ impl GlobalPipelines {
             RenderDebugView::ValidSourceColorSaturation => {
                 &self.debug_valid_source_color_saturation
             }
-        }
+        })
     }
 }
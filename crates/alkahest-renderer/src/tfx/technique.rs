@@ -19,6 +19,7 @@ use crate::{
     ecs::channels::ObjectChannels,
     gpu::{buffer::ConstantBufferCached, texture::Texture, GpuContext},
     handle::Handle,
+    loaders::TextureSlot,
     renderer::Renderer,
     tfx::bytecode::interpreter::TfxBytecodeInterpreter,
     util::d3d::D3dResource,
@@ -113,14 +114,16 @@ impl Technique {
                         .as_ref()
                         .context("Vertex stage not set")?
                         .bind(renderer, object_channels)?;
-                    if renderer.gpu.custom_pixel_shader.is_none() {
+                    if renderer.gpu.custom_pixel_shader.read().is_none() {
                         self.stage_pixel
                             .as_ref()
                             .context("Pixel stage not set")?
                             .bind(renderer, object_channels)?;
                     }
 
-                    ctx.GSSetShader(None, None);
+                    renderer
+                        .clip_planes
+                        .bind_geometry_shader(self.tech.shader_vertex.shader);
                     ctx.HSSetShader(None, None);
                     ctx.DSSetShader(None, None);
                     ctx.CSSetShader(None, None);
@@ -131,10 +134,12 @@ impl Technique {
                         .context("Vertex stage not set")?
                         .bind(renderer, object_channels)?;
 
-                    if renderer.gpu.custom_pixel_shader.is_none() {
+                    if renderer.gpu.custom_pixel_shader.read().is_none() {
                         ctx.PSSetShader(None, None);
                     }
-                    ctx.GSSetShader(None, None);
+                    renderer
+                        .clip_planes
+                        .bind_geometry_shader(self.tech.shader_vertex.shader);
                     ctx.HSSetShader(None, None);
                     ctx.DSSetShader(None, None);
                     ctx.CSSetShader(None, None);
@@ -178,7 +183,7 @@ impl Technique {
                         .as_ref()
                         .context("Vertex stage not set")?
                         .bind(renderer, object_channels)?;
-                    if renderer.gpu.custom_pixel_shader.is_none() {
+                    if renderer.gpu.custom_pixel_shader.read().is_none() {
                         self.stage_pixel
                             .as_ref()
                             .context("Pixel stage not set")?
@@ -212,6 +217,8 @@ impl Deref for Technique {
 }
 
 pub struct TechniqueStage {
+    /// Hash of the technique this stage belongs to
+    pub technique: TagHash,
     pub shader: STechniqueShader,
     pub stage: TfxShaderStage,
 
@@ -233,7 +240,19 @@ impl TechniqueStage {
     ) -> anyhow::Result<()> {
         self.shader_module.bind(&renderer.gpu);
         for (slot, tex) in &self.textures {
-            if let Some(tex) = renderer.data.lock().asset_manager.textures.get_shared(tex) {
+            let data = renderer.data.lock();
+            let tex = data
+                .asset_manager
+                .texture_override(TextureSlot {
+                    technique: self.technique,
+                    stage: self.stage,
+                    slot: *slot,
+                })
+                .unwrap_or(tex);
+            data.asset_manager
+                .textures
+                .record_coverage(tex, renderer.draw_coverage());
+            if let Some(tex) = data.asset_manager.textures.get_shared(tex) {
                 tex.bind(&renderer.gpu, *slot, self.stage);
             } else if tex.is_none() {
                 renderer.gpu.bind_srv(None, *slot, self.stage);
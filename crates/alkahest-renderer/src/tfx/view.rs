@@ -1,6 +1,8 @@
 use alkahest_data::tfx::TfxRenderStage;
 use std::ops::{BitOr, BitOrAssign};
 
+use glam::Vec3;
+
 use super::externs;
 use crate::{camera::Viewport, ecs::culling::Frustum};
 
@@ -9,12 +11,14 @@ pub trait View {
     fn subscribed_views(&self) -> RenderStageSubscriptions;
     fn name(&self) -> String;
     fn frustum(&self) -> Frustum;
+    /// World space position of the view origin
+    fn position(&self) -> Vec3;
 
     fn update_extern(&self, x: &mut externs::View);
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Copy, Clone)]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
     pub struct RenderStageSubscriptions: u32 {
         const GENERATE_GBUFFER                  = 1 << TfxRenderStage::GenerateGbuffer as u32;
         const DECALS                            = 1 << TfxRenderStage::Decals as u32;
@@ -0,0 +1,50 @@
+//! Errors from background work (asset loading, the Discord client, update checks) that would
+//! otherwise only end up in the log. They are collected here so the UI can show them.
+
+use std::time::Instant;
+
+use crossbeam::channel::{Receiver, Sender};
+use lazy_static::lazy_static;
+
+/// Maximum number of errors waiting to be picked up by the UI, errors past this are only logged
+const MAX_PENDING_ERRORS: usize = 256;
+
+lazy_static! {
+    static ref BACKGROUND_ERRORS: (Sender<BackgroundError>, Receiver<BackgroundError>) =
+        crossbeam::channel::bounded(MAX_PENDING_ERRORS);
+}
+
+#[derive(Clone)]
+pub struct BackgroundError {
+    /// What the error originated from (eg. "Asset loader"). Errors are grouped by source
+    pub source: &'static str,
+    pub message: String,
+    /// Console command that retries the failed operation
+    pub retry_command: Option<String>,
+    pub time: Instant,
+}
+
+/// Logs an error from a background thread and queues it to be shown in the UI
+pub fn report_background_error(
+    source: &'static str,
+    message: impl Into<String>,
+    retry_command: Option<String>,
+) {
+    let message = message.into();
+    error!("[{source}] {message}");
+
+    BACKGROUND_ERRORS
+        .0
+        .try_send(BackgroundError {
+            source,
+            message,
+            retry_command,
+            time: Instant::now(),
+        })
+        .ok();
+}
+
+/// Takes all errors that have been reported since the last call
+pub fn drain_background_errors() -> Vec<BackgroundError> {
+    BACKGROUND_ERRORS.1.try_iter().collect()
+}
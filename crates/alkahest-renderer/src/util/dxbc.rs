@@ -0,0 +1,207 @@
+//! Minimal DXBC container parsing, for inspecting the input signature of game shaders
+
+use std::{ffi::CStr, fmt::Display};
+
+use alkahest_pm::package_manager;
+use anyhow::Context;
+use destiny_pkg::TagHash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Unknown,
+    Uint,
+    Sint,
+    Float,
+}
+
+impl From<u32> for ComponentType {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Uint,
+            2 => Self::Sint,
+            3 => Self::Float,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl ComponentType {
+    fn hlsl_name(&self) -> &'static str {
+        match self {
+            ComponentType::Unknown => "unknown",
+            ComponentType::Uint => "uint",
+            ComponentType::Sint => "int",
+            ComponentType::Float => "float",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureElement {
+    pub semantic_name: String,
+    pub semantic_index: u32,
+    /// System value (eg. SV_VertexID), 0 for regular inputs
+    pub system_value: u32,
+    pub component_type: ComponentType,
+    pub register: u32,
+    /// Components present in the register
+    pub mask: u8,
+    /// Components actually read by the shader
+    pub used_mask: u8,
+}
+
+impl SignatureElement {
+    pub fn component_count(&self) -> u32 {
+        self.mask.count_ones()
+    }
+
+    /// Whether the shader reads this input at all
+    pub fn is_used(&self) -> bool {
+        self.used_mask != 0
+    }
+
+    pub fn hlsl_type(&self) -> String {
+        match self.component_count() {
+            1 => self.component_type.hlsl_name().to_string(),
+            n => format!("{}{n}", self.component_type.hlsl_name()),
+        }
+    }
+}
+
+impl Display for SignatureElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}{} (v{})",
+            self.hlsl_type(),
+            self.semantic_name,
+            self.semantic_index,
+            self.register
+        )
+    }
+}
+
+/// Parses the input signature (ISGN/ISG1 chunk) of a DXBC shader
+pub fn parse_input_signature(data: &[u8]) -> anyhow::Result<Vec<SignatureElement>> {
+    anyhow::ensure!(data.get(0..4) == Some(b"DXBC"), "Not a DXBC container");
+    let chunk_count = read_u32(data, 28)? as usize;
+
+    for i in 0..chunk_count {
+        let chunk_offset = read_u32(data, 32 + i * 4)? as usize;
+        let fourcc = data
+            .get(chunk_offset..chunk_offset + 4)
+            .context("Chunk offset out of bounds")?;
+        let chunk = data
+            .get(chunk_offset + 8..)
+            .context("Chunk offset out of bounds")?;
+
+        match fourcc {
+            b"ISGN" => return parse_signature_chunk(chunk, false),
+            b"ISG1" => return parse_signature_chunk(chunk, true),
+            _ => {}
+        }
+    }
+
+    anyhow::bail!("Shader has no input signature")
+}
+
+/// Reads the bytecode of a shader referenced by a technique
+pub fn read_shader_bytecode(shader: TagHash) -> anyhow::Result<Vec<u8>> {
+    let entry = package_manager()
+        .get_entry(shader)
+        .context("Entry not found")?;
+    anyhow::ensure!(entry.file_type == 33, "Not a shader header");
+
+    package_manager()
+        .read_tag(entry.reference)
+        .context("Failed to read shader data")
+}
+
+fn parse_signature_chunk(chunk: &[u8], extended: bool) -> anyhow::Result<Vec<SignatureElement>> {
+    let element_count = read_u32(chunk, 0)? as usize;
+    let element_offset = read_u32(chunk, 4)? as usize;
+    // ISG1 elements start with a stream index and end with the minimum precision
+    let (element_size, base) = if extended { (32, 4) } else { (24, 0) };
+
+    (0..element_count)
+        .map(|i| {
+            let offset = element_offset + i * element_size + base;
+            let name_offset = read_u32(chunk, offset)? as usize;
+            let semantic_name = chunk
+                .get(name_offset..)
+                .and_then(|s| CStr::from_bytes_until_nul(s).ok())
+                .context("Invalid semantic name")?
+                .to_string_lossy()
+                .to_string();
+
+            Ok(SignatureElement {
+                semantic_name,
+                semantic_index: read_u32(chunk, offset + 4)?,
+                system_value: read_u32(chunk, offset + 8)?,
+                component_type: read_u32(chunk, offset + 12)?.into(),
+                register: read_u32(chunk, offset + 16)?,
+                mask: *chunk.get(offset + 20).context("Element out of bounds")?,
+                used_mask: *chunk.get(offset + 21).context("Element out of bounds")?,
+            })
+        })
+        .collect()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .with_context(|| format!("Read at 0x{offset:x} out of bounds"))
+}
+
+#[test]
+fn test_parse_input_signature() {
+    fn element(
+        name_offset: u32,
+        index: u32,
+        ty: u32,
+        register: u32,
+        mask: u8,
+        used: u8,
+    ) -> Vec<u8> {
+        let mut e = vec![];
+        for v in [name_offset, index, 0, ty, register] {
+            e.extend(v.to_le_bytes());
+        }
+        e.extend([mask, used, 0, 0]);
+        e
+    }
+
+    // ISGN chunk with POSITION0 (float4) and TEXCOORD0 (float2, unused)
+    let mut isgn = vec![];
+    isgn.extend(2u32.to_le_bytes());
+    isgn.extend(8u32.to_le_bytes());
+    isgn.extend(element(56, 0, 3, 0, 0b1111, 0b0111));
+    isgn.extend(element(65, 0, 3, 1, 0b0011, 0));
+    isgn.extend(b"POSITION\0TEXCOORD\0");
+
+    let mut data = vec![];
+    data.extend(b"DXBC");
+    data.extend([0; 16]);
+    data.extend(1u32.to_le_bytes());
+    data.extend(0u32.to_le_bytes());
+    // A chunk without a signature, followed by the ISGN chunk
+    data.extend(2u32.to_le_bytes());
+    data.extend(40u32.to_le_bytes());
+    data.extend(52u32.to_le_bytes());
+    data.extend(b"RDEF");
+    data.extend(4u32.to_le_bytes());
+    data.extend([0; 4]);
+    data.extend(b"ISGN");
+    data.extend((isgn.len() as u32).to_le_bytes());
+    data.extend(isgn);
+
+    let signature = parse_input_signature(&data).unwrap();
+    assert_eq!(signature.len(), 2);
+    assert_eq!(signature[0].to_string(), "float4 POSITION0 (v0)");
+    assert!(signature[0].is_used());
+    assert_eq!(signature[1].semantic_name, "TEXCOORD");
+    assert_eq!(signature[1].hlsl_type(), "float2");
+    assert!(!signature[1].is_used());
+
+    assert!(parse_input_signature(b"DXBX").is_err());
+}
@@ -1,9 +1,9 @@
 use std::{
-    io::{Cursor, Read, Seek},
+    io::{Cursor, Read, Seek, Write},
     sync::Arc,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use png::{BitDepth, ColorType};
 
@@ -25,7 +25,18 @@ impl Png {
     /// Reads PNG data from a reader
     /// When reading APNG data, only the first frame will be returned
     pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self> {
-        let decoder = png::Decoder::new(reader);
+        Self::decode(png::Decoder::new(reader))
+    }
+
+    /// Reads PNG data, expanding palettes, low bit depths and transparency chunks and stripping
+    /// 16-bit channels, so the result is always 8-bit and can be converted with [`Png::into_rgba`]
+    pub fn from_bytes_normalized(data: &[u8]) -> Result<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        Self::decode(decoder)
+    }
+
+    fn decode<R: Read>(decoder: png::Decoder<R>) -> Result<Self> {
         let mut reader = decoder.read_info()?;
         let mut buf = vec![0; reader.output_buffer_size()];
         let frame_info = reader.next_frame(&mut buf)?;
@@ -53,7 +64,7 @@ impl Png {
         match self.bit_depth {
             BitDepth::Eight => self.into_rgba_impl::<u8>(),
             // BitDepth::Sixteen => self.into_rgba_impl::<u16>(),
-            u => todo!("into_rgba: Unsupported PNG bit depth {u:?}"),
+            u => anyhow::bail!("Unsupported PNG bit depth {u:?}, only 8-bit PNGs are supported"),
         }
     }
 
@@ -99,20 +110,198 @@ impl Png {
 
                 Ok(new_self)
             }
+            ColorType::GrayscaleAlpha => {
+                let mut new_self = self;
+
+                let new_data = bytemuck::cast_slice::<u8, T>(&new_self.data)
+                    .chunks_exact(2)
+                    .flat_map(|la| [la[0], la[0], la[0], la[1]])
+                    .collect_vec();
+
+                new_self.data = bytemuck::cast_slice::<T, u8>(&new_data).into();
+                new_self.color_type = ColorType::Rgba;
+
+                Ok(new_self)
+            }
             ColorType::Rgba => Ok(self),
-            c => todo!("Unsupported color conversion {c:?} -> RGBA"),
+            c => anyhow::bail!("Unsupported PNG color conversion {c:?} -> RGBA"),
+        }
+    }
+
+    /// Encodes the image as a PNG file
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut encoder =
+            png::Encoder::new(writer, self.dimensions[0] as u32, self.dimensions[1] as u32);
+        encoder.set_color(self.color_type);
+        encoder.set_depth(self.bit_depth);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Radiance HDR (.hdr) image, as used for HDRIs
+pub struct Hdr {
+    /// Linear RGBA, row by row from the top
+    pub data: Vec<[f32; 4]>,
+    pub dimensions: [usize; 2],
+}
+
+impl Hdr {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut cur = Cursor::new(data);
+        let mut read_line = || -> Result<String> {
+            let mut line = vec![];
+            let mut byte = [0u8];
+            loop {
+                cur.read_exact(&mut byte)?;
+                if byte[0] == b'\n' {
+                    break;
+                }
+                line.push(byte[0]);
+            }
+            Ok(String::from_utf8_lossy(&line).into_owned())
+        };
+
+        let magic = read_line()?;
+        anyhow::ensure!(
+            magic.starts_with("#?RADIANCE") || magic.starts_with("#?RGBE"),
+            "Not a Radiance HDR file"
+        );
+
+        loop {
+            let line = read_line()?;
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                anyhow::ensure!(
+                    format == "32-bit_rle_rgbe",
+                    "Unsupported HDR pixel format {format}"
+                );
+            }
+        }
+
+        let resolution = read_line()?;
+        let (height, width) = match resolution.split_whitespace().collect_vec()[..] {
+            ["-Y", height, "+X", width] => (height.parse::<usize>()?, width.parse::<usize>()?),
+            _ => anyhow::bail!("Unsupported HDR orientation '{resolution}'"),
+        };
+
+        let mut pixels = &data[cur.position() as usize..];
+        let mut rgbe = vec![[0u8; 4]; width * height];
+        for row in rgbe.chunks_exact_mut(width) {
+            pixels = Self::read_scanline(pixels, row)?;
         }
+
+        Ok(Self {
+            data: rgbe
+                .into_iter()
+                .map(|[r, g, b, e]| {
+                    if e == 0 {
+                        [0.0, 0.0, 0.0, 1.0]
+                    } else {
+                        let scale = 2f32.powi(e as i32 - 136);
+                        [r as f32 * scale, g as f32 * scale, b as f32 * scale, 1.0]
+                    }
+                })
+                .collect(),
+            dimensions: [width, height],
+        })
     }
 
-    // /// Converts RGBA data into PNG file data
-    // pub fn from_rgba(data: &[u8], dimensions: (u32, u32)) -> Result<Vec<u8>> {
-    //     let mut result = vec![];
-    //     let mut encoder = png::Encoder::new(&mut result, dimensions.0, dimensions.1);
-    //     encoder.set_color(ColorType::Rgba);
-    //     encoder.set_depth(png::BitDepth::Eight);
-    //     let mut writer = encoder.write_header()?;
-    //     writer.write_image_data(data)?;
-    //     writer.finish()?;
-    //     Ok(result)
-    // }
+    /// Reads a single row of RGBE pixels, returning the remaining data
+    fn read_scanline<'a>(data: &'a [u8], row: &mut [[u8; 4]]) -> Result<&'a [u8]> {
+        let width = row.len();
+        let is_rle = (8..0x8000).contains(&width)
+            && data.len() >= 4
+            && data[0] == 2
+            && data[1] == 2
+            && data[2] & 0x80 == 0;
+
+        if !is_rle {
+            let size = width * 4;
+            anyhow::ensure!(data.len() >= size, "Unexpected end of HDR data");
+            for (pixel, rgbe) in row.iter_mut().zip(data.chunks_exact(4)) {
+                pixel.copy_from_slice(rgbe);
+            }
+            return Ok(&data[size..]);
+        }
+
+        anyhow::ensure!(
+            ((data[2] as usize) << 8 | data[3] as usize) == width,
+            "HDR scanline width mismatch"
+        );
+
+        // Every channel is run-length encoded separately
+        let mut data = &data[4..];
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let (&count, rest) = data.split_first().context("Unexpected end of HDR data")?;
+                if count > 128 {
+                    let count = count as usize - 128;
+                    let &value = rest.first().context("Unexpected end of HDR data")?;
+                    anyhow::ensure!(x + count <= width, "HDR run exceeds the scanline");
+                    for pixel in &mut row[x..x + count] {
+                        pixel[channel] = value;
+                    }
+                    data = &rest[1..];
+                    x += count;
+                } else {
+                    let count = count as usize;
+                    anyhow::ensure!(
+                        count > 0 && x + count <= width && rest.len() >= count,
+                        "Invalid HDR literal run"
+                    );
+                    for (pixel, &value) in row[x..x + count].iter_mut().zip(rest) {
+                        pixel[channel] = value;
+                    }
+                    data = &rest[count..];
+                    x += count;
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+#[test]
+fn test_hdr_decode() {
+    let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+    // Flat row, every pixel 1.0 red
+    file.extend([128, 0, 0, 129].repeat(8));
+    // Run-length encoded row: a run of 8 for red, literals for green, zero blue, exponent 129
+    file.extend([2, 2, 0, 8]);
+    file.extend([136, 64]);
+    file.extend([8, 0, 32, 64, 96, 128, 160, 192, 224]);
+    file.extend([136, 0]);
+    file.extend([136, 129]);
+
+    let hdr = Hdr::from_bytes(&file).unwrap();
+    assert_eq!(hdr.dimensions, [8, 2]);
+    assert_eq!(hdr.data[0], [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(hdr.data[8], [0.5, 0.0, 0.0, 1.0]);
+    assert_eq!(hdr.data[15], [0.5, 1.75, 0.0, 1.0]);
+}
+
+#[test]
+fn test_png_indexed_to_rgba() {
+    let mut file = vec![];
+    let mut encoder = png::Encoder::new(&mut file, 2, 1);
+    encoder.set_color(ColorType::Indexed);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_palette(vec![255, 0, 0, 0, 0, 255]);
+    encoder.set_trns(vec![255, 128]);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&[1, 0]).unwrap();
+    writer.finish().unwrap();
+
+    let png = Png::from_bytes_normalized(&file)
+        .and_then(Png::into_rgba)
+        .unwrap();
+    assert_eq!(&*png.data, &[0, 0, 255, 128, 255, 0, 0, 255]);
 }
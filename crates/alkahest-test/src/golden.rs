@@ -0,0 +1,374 @@
+//! Rendering regression tests. Every case in `golden/cases.json` is rendered headlessly from a
+//! fixed camera position and compared against `golden/<name>.png`.
+//!
+//! The test is ignored by default, as no cases ship with the repository. To enable it, add map
+//! and/or model cases to `golden/cases.json`, generate their images once with
+//! `ALKTEST_UPDATE_GOLDEN=1 cargo test -p alkahest-test golden -- --ignored`, commit the PNGs,
+//! and run `cargo test -p alkahest-test golden -- --ignored` from then on. `ALKTEST_PACKAGES_DIR`
+//! has to point at the game packages the images were made from.
+//!
+//! Run with `ALKTEST_UPDATE_GOLDEN=1` to (re)generate the golden images from the current renderer.
+//! Failing cases write the rendered image, the expected image and a difference image to
+//! `target/golden_failures/<name>/`.
+
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alkahest_data::text::StringContainer;
+use alkahest_renderer::{
+    camera::{Camera, Viewport},
+    ecs::{
+        common::RenderCommonBundle,
+        render::{
+            dynamic_geometry::{update_dynamic_model_system, DynamicModelComponent},
+            static_geometry::update_static_instances_system,
+        },
+        resources::SelectedEntity,
+        tags::{NodeFilter, NodeFilterSet},
+        transform::Transform,
+        Scene, SceneInfo,
+    },
+    loaders::map::load_map,
+    renderer::{AntiAliasing, OutputMode, RendererSettings, RendererShared, Time},
+    resources::AppResources,
+    tfx::TfxFeatureRenderer,
+    util::image::Png,
+};
+use anyhow::Context;
+use bevy_ecs::system::RunSystemOnce;
+use destiny_pkg::TagHash;
+use futures::executor::block_on;
+use glam::{UVec2, Vec2, Vec3};
+use png::{BitDepth, ColorType};
+use serde::Deserialize;
+
+/// Maximum YIQ color distance (relative to the largest possible distance) at which two pixels are
+/// still considered the same
+const PIXEL_THRESHOLD: f32 = 0.1;
+
+/// Frames rendered before capturing, so streamed assets and temporal effects can settle
+const WARMUP_FRAMES: usize = 8;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct GoldenCase {
+    pub name: String,
+    /// Map to load, as a hex tag hash
+    #[serde(default)]
+    pub map: Option<String>,
+    /// Dynamic model to render on its own, as a hex tag hash. Ignored if `map` is set
+    #[serde(default)]
+    pub model: Option<String>,
+    pub position: [f32; 3],
+    /// Pitch and yaw, in degrees
+    #[serde(default)]
+    pub orientation: [f32; 2],
+    #[serde(default = "default_resolution")]
+    pub resolution: [u32; 2],
+    /// Fraction of pixels allowed to differ before the case fails
+    #[serde(default = "default_max_diff_ratio")]
+    pub max_diff_ratio: f32,
+}
+
+fn default_resolution() -> [u32; 2] {
+    [640, 360]
+}
+
+fn default_max_diff_ratio() -> f32 {
+    0.005
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+fn failure_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/golden_failures")
+}
+
+pub fn load_cases() -> anyhow::Result<Vec<GoldenCase>> {
+    let path = golden_dir().join("cases.json");
+    let data = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&data).context("Failed to parse golden cases")
+}
+
+fn parse_taghash(s: &str) -> anyhow::Result<TagHash> {
+    let s = s.trim_start_matches("0x").trim_start_matches("0X");
+    Ok(TagHash(u32::from_be(
+        u32::from_str_radix(s, 16).with_context(|| format!("Invalid tag hash '{s}'"))?,
+    )))
+}
+
+/// Renderer settings that don't depend on timing or the previous frame
+fn deterministic_settings() -> RendererSettings {
+    RendererSettings {
+        output_mode: OutputMode::Sdr,
+        anti_aliasing: AntiAliasing::None,
+        auto_lod: false,
+        draw_utilities: false,
+        draw_selection_outline: false,
+        ..Default::default()
+    }
+}
+
+fn create_resources(renderer: &RendererShared) -> AppResources {
+    let mut resources = AppResources::default();
+    resources.insert(SelectedEntity::default());
+    resources.insert(renderer.clone());
+    resources.insert(NodeFilterSet::from_iter([
+        NodeFilter::Entity,
+        NodeFilter::Light,
+        NodeFilter::Decorator,
+        NodeFilter::SkyObject,
+        NodeFilter::Cubemap,
+        NodeFilter::Static,
+        NodeFilter::Decal,
+    ]));
+    resources
+}
+
+fn load_case_scene(renderer: &RendererShared, case: &GoldenCase) -> anyhow::Result<Scene> {
+    if let Some(map) = &case.map {
+        return block_on(load_map(
+            renderer.clone(),
+            parse_taghash(map)?,
+            None,
+            Arc::new(StringContainer::default()),
+            false,
+        ));
+    }
+
+    let model = case
+        .model
+        .as_ref()
+        .context("Golden case needs either a map or a model")?;
+    let transform = Transform::default();
+    let model = DynamicModelComponent::load(
+        renderer,
+        &transform,
+        parse_taghash(model)?,
+        vec![],
+        vec![],
+        TfxFeatureRenderer::DynamicObjects,
+    )?;
+
+    let mut scene = Scene::new_with_info(None, TagHash::NONE);
+    scene.spawn((
+        transform,
+        model,
+        TfxFeatureRenderer::DynamicObjects,
+        RenderCommonBundle::default(),
+    ));
+    Ok(scene)
+}
+
+/// Renders a case, returning the final 8-bit RGBA image
+pub fn render_case(renderer: &RendererShared, case: &GoldenCase) -> anyhow::Result<Png> {
+    let mut scene = load_case_scene(renderer, case)?;
+    let resources = create_resources(renderer);
+
+    renderer.set_render_settings(deterministic_settings());
+    renderer.time.store(Time::fixed(0.0));
+
+    let mut camera = Camera::new_fps(Viewport {
+        origin: UVec2::ZERO,
+        size: UVec2::from_array(case.resolution),
+    });
+    camera.set_position(Vec3::from_array(case.position));
+    camera.set_orientation(Vec2::from_array(case.orientation));
+    camera.update_matrices();
+
+    let mut image = None;
+    for _ in 0..WARMUP_FRAMES {
+        renderer.wait_for_assets();
+        renderer.gpu.begin_frame();
+        scene.run_system_once(update_static_instances_system);
+        scene.run_system_once(update_dynamic_model_system);
+        image = Some(renderer.capture_view(&camera, &mut scene, &resources)?);
+    }
+
+    image.context("No frames were rendered")
+}
+
+/// Result of comparing two images of the same size
+pub struct ImageDiff {
+    pub differing_pixels: usize,
+    pub total_pixels: usize,
+    /// RGBA image highlighting the differing pixels in red on top of a faded copy of `expected`
+    pub diff_image: Vec<u8>,
+}
+
+impl ImageDiff {
+    pub fn ratio(&self) -> f32 {
+        self.differing_pixels as f32 / self.total_pixels.max(1) as f32
+    }
+}
+
+/// Squared YIQ distance between two RGB colors, normalized to 0-1. YIQ weighs the channels by how
+/// noticeable differences in them are, so this is a cheap approximation of perceptual difference
+fn yiq_delta(a: &[u8], b: &[u8]) -> f32 {
+    let yiq = |p: &[u8]| {
+        let [r, g, b] = [p[0], p[1], p[2]].map(|c| c as f32 / 255.0);
+        Vec3::new(
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_977_99 - g * 0.274_171_6 - b * 0.321_801_6,
+            r * 0.211_470_19 - g * 0.522_617_8 + b * 0.311_146_2,
+        )
+    };
+
+    let d = yiq(a) - yiq(b);
+    // Largest possible value of the weighted sum below, on the same scale as pixelmatch
+    const MAX_DELTA: f32 = 35215.0 / (255.0 * 255.0);
+    (0.5053 * d.x * d.x + 0.299 * d.y * d.y + 0.1957 * d.z * d.z) / MAX_DELTA
+}
+
+/// Compares two RGBA8 images pixel by pixel
+pub fn compare_images(actual: &[u8], expected: &[u8]) -> ImageDiff {
+    let threshold = PIXEL_THRESHOLD * PIXEL_THRESHOLD;
+    let mut differing_pixels = 0;
+    let mut diff_image = Vec::with_capacity(expected.len());
+    for (a, e) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        if yiq_delta(a, e) > threshold {
+            differing_pixels += 1;
+            diff_image.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = (e[0] as u32 + e[1] as u32 + e[2] as u32) / 3;
+            let faded = (gray / 4 + 192) as u8;
+            diff_image.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    ImageDiff {
+        differing_pixels,
+        total_pixels: expected.len() / 4,
+        diff_image,
+    }
+}
+
+fn write_png(path: &Path, data: Vec<u8>, dimensions: [usize; 2]) -> anyhow::Result<()> {
+    let png = Png {
+        data: data.into(),
+        dimensions,
+        color_type: ColorType::Rgba,
+        bit_depth: BitDepth::Eight,
+    };
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    png.write(BufWriter::new(file))
+}
+
+/// Renders a case and compares it against its golden image. Returns a description of the failure,
+/// if any
+pub fn check_case(renderer: &RendererShared, case: &GoldenCase) -> anyhow::Result<Option<String>> {
+    let actual = render_case(renderer, case)?;
+    let golden_path = golden_dir().join(format!("{}.png", case.name));
+
+    if std::env::var_os("ALKTEST_UPDATE_GOLDEN").is_some() {
+        write_png(&golden_path, actual.data.to_vec(), actual.dimensions)?;
+        info!("Updated golden image {}", golden_path.display());
+        return Ok(None);
+    }
+
+    let artifacts = failure_dir().join(&case.name);
+    let write_actual = || -> anyhow::Result<()> {
+        std::fs::create_dir_all(&artifacts)?;
+        write_png(
+            &artifacts.join("actual.png"),
+            actual.data.to_vec(),
+            actual.dimensions,
+        )
+    };
+
+    if !golden_path.exists() {
+        write_actual()?;
+        return Ok(Some(format!(
+            "No golden image at {}, run with ALKTEST_UPDATE_GOLDEN=1 to create it",
+            golden_path.display()
+        )));
+    }
+
+    let expected = Png::from_bytes(&std::fs::read(&golden_path)?)?.into_rgba()?;
+    if expected.dimensions != actual.dimensions {
+        write_actual()?;
+        return Ok(Some(format!(
+            "Image size {:?} does not match the golden image size {:?}",
+            actual.dimensions, expected.dimensions
+        )));
+    }
+
+    let diff = compare_images(&actual.data, &expected.data);
+    if diff.ratio() <= case.max_diff_ratio {
+        return Ok(None);
+    }
+
+    write_actual()?;
+    std::fs::copy(&golden_path, artifacts.join("expected.png"))?;
+    write_png(
+        &artifacts.join("diff.png"),
+        diff.diff_image,
+        actual.dimensions,
+    )?;
+    Ok(Some(format!(
+        "{} of {} pixels ({:.2}%) differ, at most {:.2}% allowed. See {}",
+        diff.differing_pixels,
+        diff.total_pixels,
+        diff.ratio() * 100.0,
+        case.max_diff_ratio * 100.0,
+        artifacts.display()
+    )))
+}
+
+#[test]
+#[ignore = "needs game packages and cases in golden/cases.json, see the module docs"]
+fn test_golden_images() {
+    use crate::TestHarness;
+
+    let cases = load_cases().expect("Failed to load golden cases");
+    assert!(
+        !cases.is_empty(),
+        "No golden image cases are defined in {}. Add at least one map or model case and run \
+         with ALKTEST_UPDATE_GOLDEN=1 to create its golden image",
+        golden_dir().join("cases.json").display()
+    );
+
+    let harness = TestHarness::with_asset_loading();
+    let mut failures = vec![];
+    for case in &cases {
+        info!("Rendering golden case '{}'", case.name);
+        match check_case(&harness.renderer, case) {
+            Ok(None) => {}
+            Ok(Some(failure)) => failures.push(format!("{}: {failure}", case.name)),
+            Err(e) => failures.push(format!("{}: {e:?}", case.name)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden image cases failed:\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_compare_images() {
+    let black = [0, 0, 0, 255].repeat(4);
+    let mut almost_black = black.clone();
+    almost_black[0] = 8;
+    let mut one_white = black.clone();
+    one_white[4..8].copy_from_slice(&[255, 255, 255, 255]);
+
+    assert_eq!(compare_images(&black, &black).differing_pixels, 0);
+    // Tiny differences are below the threshold
+    assert_eq!(compare_images(&almost_black, &black).differing_pixels, 0);
+
+    let diff = compare_images(&one_white, &black);
+    assert_eq!(diff.differing_pixels, 1);
+    assert_eq!(diff.ratio(), 0.25);
+    assert_eq!(&diff.diff_image[4..8], &[255, 0, 0, 255]);
+}
@@ -1,3 +1,4 @@
+mod golden;
 mod maps;
 
 #[allow(unused_imports)]
@@ -6,7 +7,7 @@ extern crate tracing;
 
 use std::{path::PathBuf, str::FromStr, sync::Arc};
 
-use alkahest_pm::PACKAGE_MANAGER;
+use alkahest_pm::set_package_manager;
 use alkahest_renderer::{
     gpu::GpuContext,
     renderer::{Renderer, RendererShared},
@@ -56,7 +57,7 @@ fn initialize_package_manager(/* args: &TestArgs*/) -> anyhow::Result<()> {
 
     let pm = PackageManager::new(package_dir, GameVersion::Destiny2TheFinalShape, None).unwrap();
 
-    *PACKAGE_MANAGER.write() = Some(Arc::new(pm));
+    set_package_manager(Arc::new(pm));
 
     Ok(())
 }
@@ -73,7 +74,17 @@ impl Default for TestHarness {
 }
 
 impl TestHarness {
+    /// Harness with asset loading disabled, for tests that only need the loaders
     pub fn new() -> Self {
+        Self::create(true)
+    }
+
+    /// Harness that loads textures and techniques, for tests that render
+    pub fn with_asset_loading() -> Self {
+        Self::create(false)
+    }
+
+    fn create(disable_asset_loading: bool) -> Self {
         // Using try_init() instead of init() to avoid panicking if the logger is already initialized by another test thread
         // tracing_subscriber::fmt::try_init().ok();
 
@@ -90,8 +101,8 @@ impl TestHarness {
             .expect("Failed to initialize package manager");
         let gpu =
             Arc::new(GpuContext::create_headless().expect("Failed to create headless GPU context"));
-        let renderer =
-            Renderer::create(gpu, (4, 4), true).expect("Failed to create headless renderer");
+        let renderer = Renderer::create(gpu, (4, 4), disable_asset_loading)
+            .expect("Failed to create headless renderer");
 
         Self { renderer }
     }
@@ -2,20 +2,20 @@ use std::sync::{atomic::Ordering, Arc};
 
 use alkahest_data::text::{StringContainer, StringContainerShared};
 use alkahest_renderer::{
-    camera::{Camera, Viewport},
+    camera::{walk::walk_camera_step, Camera, Viewport},
     ecs::{
-        channels::object_channels_discovery_system,
         new_scene,
         resources::SelectedEntity,
+        route::route_validation_step,
         tags::{NodeFilter, NodeFilterSet},
         Scene,
     },
     gpu::{texture::LOW_RES, GpuContext},
     gpu_event, gpu_profile_event,
     input::InputState,
-    renderer::{Renderer, RendererShared},
+    renderer::{OutputMode, Renderer, RendererShared},
+    util::background_errors::report_background_error,
 };
-use bevy_ecs::system::RunSystemOnce;
 use bevy_tasks::{ComputeTaskPool, TaskPool};
 use egui::{Key, KeyboardShortcut, Modifiers};
 use gilrs::{EventType, Gilrs};
@@ -31,23 +31,37 @@ use winit::{
 };
 
 use crate::{
+    autosave::{self, autosave_step, Autosave},
     config,
+    env_probe::{env_probe_capture_step, EnvProbeCaptures},
     gui::{
         activity_select::{get_map_name, set_activity, ActivityBrowser, CurrentActivity},
         console,
         context::{GuiContext, GuiViewManager, HiddenWindows},
+        entity_query::EntityQueryState,
         gizmo::draw_transform_gizmos,
+        hex_viewer::HexViewerTarget,
         hotkeys,
         inspector::FnvWordlist,
+        minimap::{minimap_capture_step, Minimap},
+        startup::StartupScreen,
         updater::{ChannelSelector, UpdateDownload},
         SelectionGizmoMode,
     },
+    investment,
     maplist::{Map, MapList},
+    presentation::{presentation_step, CameraBookmarks, Presentation},
+    recent::{recent_sessions_step, RecentSessions},
     resources::AppResources,
+    safe_mode,
+    scripting::ScriptHost,
+    thumbnails::{thumbnail_capture_step, MapThumbnails},
     updater::UpdateCheck,
     util::{
         action::{ActionBuffer, ActionList},
+        error::show_error_alert,
         iron,
+        window_placement::{validate_window_placement, MonitorRect},
     },
     ApplicationArgs,
 };
@@ -84,9 +98,22 @@ impl AlkahestApp {
         alkahest_renderer::gpu::DESKTOP_DISPLAY_MODE
             .store(iron::get_content_policy(), Ordering::SeqCst);
 
+        // Monitors may have been unplugged or rearranged since the window position was saved
+        let monitors = event_loop
+            .available_monitors()
+            .map(|m| MonitorRect::from_monitor(&m))
+            .collect::<Vec<_>>();
+        if config::with_mut(|c| validate_window_placement(&mut c.window, &monitors)) {
+            warn!("Saved window position is not on any connected monitor, moving the window");
+        }
+
         let window = winit::window::WindowBuilder::new()
-            .with_title("Alkahest")
-            .with_min_inner_size(PhysicalSize::new(1280, 720))
+            .with_title(if args.safe_mode {
+                "Alkahest (Safe Mode)"
+            } else {
+                "Alkahest"
+            })
+            .with_min_inner_size(safe_mode::MINIMUM_WINDOW_SIZE)
             .with_inner_size(config::with(|c| {
                 PhysicalSize::new(c.window.width, c.window.height)
             }))
@@ -130,8 +157,17 @@ impl AlkahestApp {
         resources.insert(maps);
         resources.insert(SelectionGizmoMode::default());
         resources.insert(HiddenWindows::default());
+        resources.insert(EntityQueryState::default());
         resources.insert(ActionList::default());
         resources.insert(ActionBuffer::default());
+        resources.insert(EnvProbeCaptures::default());
+        resources.insert(Minimap::default());
+        resources.insert(Autosave::start_session());
+        resources.insert(Presentation::default());
+        resources.insert(CameraBookmarks::default());
+        resources.insert(HexViewerTarget::default());
+        resources.insert(RecentSessions::load());
+        resources.insert(MapThumbnails::default());
         let renderer = Renderer::create(
             gctx.clone(),
             (window.inner_size().width, window.inner_size().height),
@@ -156,6 +192,10 @@ impl AlkahestApp {
                 &resources.get::<StringContainerShared>(),
             ));
 
+        if config::with(|c| c.investment.enabled) {
+            investment::start_loading(config::with(|c| c.investment.clone()));
+        }
+
         resources.insert(UpdateCheck::default());
         let update_channel_gui = ChannelSelector {
             open: config::with(|c| c.update_channel.is_none()),
@@ -181,6 +221,10 @@ impl AlkahestApp {
             resources
                 .get_mut::<MapList>()
                 .set_maps(&resources, &[(maphash, map_name)]);
+        } else if !config::with(|c| c.skip_startup_screen) {
+            resources
+                .get_mut::<GuiViewManager>()
+                .insert(StartupScreen::default());
         }
 
         let mut node_filter_set = NodeFilterSet::default();
@@ -193,6 +237,11 @@ impl AlkahestApp {
         });
         resources.insert(node_filter_set);
 
+        resources.insert(ScriptHost::default());
+        resources
+            .get_mut::<ScriptHost>()
+            .run_autoload_scripts(&resources);
+
         {
             let args = resources.get::<ApplicationArgs>();
             LOW_RES.store(args.low_res, std::sync::atomic::Ordering::Relaxed);
@@ -284,10 +333,10 @@ impl AlkahestApp {
                     WindowEvent::Resized(new_dims) => {
                         let minimized = window.is_minimized().unwrap_or(false);
                         if !minimized && new_dims.width > 0 && new_dims.height > 0 {
-                            if let Some(swap_chain) = gctx.swap_chain.as_ref() {
+                            if let Some(swap_chain) = gctx.swap_chain() {
                                 let _ = gui.renderer.as_mut().map(|renderer| {
                                     let _ = renderer
-                                        .resize_buffers(swap_chain, || {
+                                        .resize_buffers(&swap_chain, || {
                                             gctx.resize_swapchain(new_dims.width, new_dims.height);
                                             HRESULT(0)
                                         })
@@ -303,12 +352,56 @@ impl AlkahestApp {
                             });
 
                             config::with_mut(|c| {
-                                (c.window.width, c.window.height) =
-                                    (new_dims.width, new_dims.height)
+                                c.window.maximised = window.is_maximized();
+                                if !c.window.maximised {
+                                    (c.window.width, c.window.height) =
+                                        (new_dims.width, new_dims.height)
+                                }
+                            });
+                        }
+                    }
+                    WindowEvent::Moved(position) => {
+                        let minimized = window.is_minimized().unwrap_or(false);
+                        if !minimized && !window.is_maximized() && window.fullscreen().is_none() {
+                            config::with_mut(|c| {
+                                (c.window.pos_x, c.window.pos_y) = (position.x, position.y)
                             });
                         }
                     }
                     WindowEvent::RedrawRequested => {
+                        if gctx.is_device_lost() {
+                            let reason = gctx
+                                .device_removed_reason()
+                                .map_or("device reset".to_string(), |r| r.to_string());
+                            error!("The GPU device was removed: {reason}");
+                            match recover_lost_device(
+                                window,
+                                gctx,
+                                gui,
+                                renderer,
+                                scratch_map,
+                                resources,
+                            ) {
+                                Ok(()) => report_background_error(
+                                    "GPU",
+                                    format!(
+                                        "The GPU device was lost ({reason}). The renderer has \
+                                         been recreated, maps are being reloaded"
+                                    ),
+                                    None,
+                                ),
+                                Err(e) => {
+                                    show_error_alert(e.context(
+                                        "The GPU device was lost and could not be recreated",
+                                    ));
+                                    target.exit();
+                                }
+                            }
+
+                            window.request_redraw();
+                            return;
+                        }
+
                         if *next_config_save < std::time::Instant::now() {
                             config::try_persist().ok();
                             *next_config_save =
@@ -316,7 +409,38 @@ impl AlkahestApp {
                         }
 
                         resources.get_mut::<SelectedEntity>().changed_this_frame = false;
-                        renderer.data.lock().asset_manager.poll();
+                        {
+                            let mut data = renderer.data.lock();
+                            data.asset_manager.set_texture_budget(
+                                renderer.settings().texture_budget_mb as usize * 1024 * 1024,
+                            );
+                            data.asset_manager.next_frame();
+                            data.asset_manager.poll();
+                        }
+
+                        let output_mode = renderer.settings().output_mode;
+                        let (format, color_space) = output_mode.swapchain_format();
+                        if gctx.swapchain_format.load() != format {
+                            let mut result = Ok(());
+                            if let (Some(swap_chain), Some(egui_renderer)) =
+                                (gctx.swap_chain(), gui.renderer.as_mut())
+                            {
+                                egui_renderer
+                                    .resize_buffers(&swap_chain, || {
+                                        result = gctx.set_swapchain_format(format, color_space);
+                                        HRESULT(0)
+                                    })
+                                    .unwrap();
+                            } else {
+                                result = gctx.set_swapchain_format(format, color_space);
+                            }
+
+                            if let Err(e) = result {
+                                error!("Failed to switch to {output_mode} output: {e:?}");
+                                config::with_mut(|c| c.renderer.output_mode = OutputMode::Sdr);
+                                renderer.set_render_settings(config::with(|c| c.renderer.clone()));
+                            }
+                        }
 
                         gctx.begin_frame();
 
@@ -348,9 +472,10 @@ impl AlkahestApp {
                                 action_list.process(resources);
                             }
 
-                            resources
-                                .get_mut::<Camera>()
-                                .update(&resources.get::<InputState>(), renderer.delta_time as f32);
+                            resources.get_mut::<Camera>().update(
+                                &resources.get::<InputState>(),
+                                renderer.delta_time.load() as f32,
+                            );
 
                             // Process gamepad input
                             {
@@ -401,21 +526,25 @@ impl AlkahestApp {
                                         } else {
                                             0.0
                                         },
-                                        renderer.delta_time as f32,
+                                        renderer.delta_time.load() as f32,
                                     );
                                 }
                             }
 
+                            presentation_step(resources, renderer.delta_time.load() as f32);
+
                             let mut maps = resources.get_mut::<MapList>();
                             maps.update_maps(resources);
+                            autosave_step(&mut maps, resources);
 
                             if let Some(map) = maps.current_map_mut() {
-                                map.scene.run_system_once_with(
-                                    resources.get::<RendererShared>().clone(),
-                                    object_channels_discovery_system,
-                                );
+                                map.update(&resources.get::<RendererShared>());
+                            }
 
-                                map.update();
+                            if let Some(map) = maps.current_map_mut() {
+                                minimap_capture_step(&renderer, map, resources);
+                                recent_sessions_step(&renderer, map, resources);
+                                thumbnail_capture_step(&renderer, map, resources);
                             }
 
                             let scene = maps
@@ -423,7 +552,15 @@ impl AlkahestApp {
                                 .map(|m| &mut m.scene)
                                 .unwrap_or(scratch_map);
 
-                            renderer.render_world(&*resources.get::<Camera>(), scene, resources);
+                            route_validation_step(&renderer, scene, resources);
+                            walk_camera_step(&renderer, scene, resources);
+                            env_probe_capture_step(&renderer, scene, resources);
+                            let camera = resources.get::<Camera>();
+                            if renderer.settings().stereo {
+                                renderer.render_stereo(&camera, scene, resources);
+                            } else {
+                                renderer.render_world(&*camera, scene, resources);
+                            }
                         }
 
                         unsafe {
@@ -433,6 +570,11 @@ impl AlkahestApp {
                             );
                         }
 
+                        // HDR swapchains can't take the UI directly, it's composited separately
+                        let ui_overlay = renderer.ui_overlay_target();
+                        let composite_ui_overlay = ui_overlay.is_some();
+                        gui.set_render_target(ui_overlay);
+
                         renderer
                             .gpu
                             .begin_event_span("interface_and_hud", "")
@@ -498,6 +640,10 @@ impl AlkahestApp {
                                     drop(gui_views);
                                     hotkeys::process_hotkeys(ectx, resources);
                                 });
+
+                                if composite_ui_overlay {
+                                    renderer.composite_ui_overlay();
+                                }
                             });
 
                         window.pre_present_notify();
@@ -509,6 +655,7 @@ impl AlkahestApp {
                         // Slow the app to 10fps when it's window is out of focus
                         if !window.has_focus() {
                             std::thread::sleep(std::time::Duration::from_millis(100));
+                            renderer.skip_frame_budget();
                         }
 
                         console::process_queued_commands(resources);
@@ -540,8 +687,63 @@ impl AlkahestApp {
     }
 }
 
+/// Number of times creating a new device is retried after the device has been lost, the driver
+/// might still be resetting
+const DEVICE_RECREATE_ATTEMPTS: usize = 5;
+
+/// Recreates the GPU device after it has been removed (driver crash, driver update, GPU reset),
+/// along with everything that was created on it. The UI keeps its state and textures, while the
+/// maps are unloaded so they (and their assets) are loaded again on the new device
+fn recover_lost_device(
+    window: &winit::window::Window,
+    gctx: &mut Arc<GpuContext>,
+    gui: &mut GuiContext,
+    renderer: &mut RendererShared,
+    scratch_map: &mut Scene,
+    resources: &mut AppResources,
+) -> anyhow::Result<()> {
+    // Only one swapchain can exist for a window, everything holding on to the old one has to let
+    // go of it first
+    gui.release_renderer();
+    gctx.release_swapchain();
+
+    let mut attempt = 1;
+    let new_gctx = loop {
+        match GpuContext::create(window) {
+            Ok(new_gctx) => break Arc::new(new_gctx),
+            Err(e) if attempt < DEVICE_RECREATE_ATTEMPTS => {
+                warn!("Failed to recreate the GPU device (attempt {attempt}): {e:?}");
+                attempt += 1;
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let size = window.inner_size();
+    new_gctx.resize_swapchain(size.width, size.height);
+
+    resources.get_mut::<MapList>().unload_all();
+    *scratch_map = new_scene();
+
+    let new_renderer = Renderer::create(new_gctx.clone(), (size.width, size.height), false)?;
+    new_renderer.set_render_settings(config::with(|c| c.renderer.clone()));
+    resources.insert(new_renderer.clone());
+    *renderer = new_renderer;
+
+    gui.restore_renderer(new_gctx.clone())?;
+    *gctx = new_gctx;
+
+    info!("Recovered from GPU device loss");
+    Ok(())
+}
+
 impl Drop for AlkahestApp {
     fn drop(&mut self) {
         config::persist();
+        autosave::end_session();
+        if let Err(e) = self.resources.get::<RecentSessions>().save() {
+            error!("Failed to save recent sessions: {e:?}");
+        }
     }
 }
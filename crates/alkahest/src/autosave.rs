@@ -0,0 +1,723 @@
+//! Crash-safe autosave of user-created markup (rulers, spheres, beacons and routes).
+//!
+//! Markup is written periodically and on map switch to rotating files in the local config
+//! directory. A session marker is kept in the same directory while Alkahest is running, if it is
+//! still there on startup the previous session crashed and the user is offered to restore the
+//! most recent autosave.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use alkahest_renderer::{
+    ecs::{
+        common::{Global, Label, Mutable, RenderCommonBundle},
+        hierarchy::Children,
+        route::{Route, RouteNode, RouteNodeBundle, RouteNodeData},
+        tags::{EntityTag, NodeFilter, Tags},
+        transform::{Transform, TransformFlags},
+        utility::{Beacon, Ruler, Sphere, Utility},
+        Scene,
+    },
+    util::color::Color,
+};
+use anyhow::Context;
+use bevy_ecs::{entity::Entity, query::With, world::EntityRef};
+use destiny_pkg::TagHash;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config,
+    gui::activity_select::CurrentActivity,
+    maplist::{MapList, MapLoadState},
+    paths,
+    resources::AppResources,
+};
+
+const SESSION_MARKER: &str = "session.lock";
+/// Number of consecutive sessions that ended with a crash dump
+const CRASH_STREAK: &str = "crash_streak";
+const AUTOSAVE_PREFIX: &str = "autosave_";
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedMarkup {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub activity_hash: Option<u32>,
+    pub maps: Vec<SavedMapMarkup>,
+    /// Routes can span multiple maps, so they are not tied to the map they are stored in
+    pub routes: Vec<SavedRoute>,
+}
+
+impl SavedMarkup {
+    pub fn entity_count(&self) -> usize {
+        self.maps.iter().map(|m| m.entities.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entity_count() == 0 && self.routes.is_empty()
+    }
+
+    fn same_contents(&self, other: &SavedMarkup) -> bool {
+        self.activity_hash == other.activity_hash
+            && self.maps == other.maps
+            && self.routes == other.routes
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedMapMarkup {
+    pub map_hash: u32,
+    pub map_name: String,
+    pub entities: Vec<SavedEntity>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum SavedEntity {
+    Ruler {
+        label: Option<String>,
+        start: [f32; 3],
+        end: [f32; 3],
+        color: [f32; 4],
+        rainbow: bool,
+        scale: f32,
+        marker_interval: f32,
+        show_individual_axis: bool,
+    },
+    Sphere {
+        label: Option<String>,
+        position: [f32; 3],
+        radius: [f32; 3],
+        detail: u8,
+        color: [f32; 4],
+        rainbow: bool,
+    },
+    Beacon {
+        label: Option<String>,
+        position: [f32; 3],
+        color: [f32; 4],
+        freq: f32,
+        distance: f32,
+        travel_time: f32,
+    },
+}
+
+impl SavedEntity {
+    /// Position of the entity, the start point for rulers
+    pub fn position(&self) -> Vec3 {
+        match self {
+            SavedEntity::Ruler { start, .. } => Vec3::from_array(*start),
+            SavedEntity::Sphere { position, .. } | SavedEntity::Beacon { position, .. } => {
+                Vec3::from_array(*position)
+            }
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        let translate = |p: &mut [f32; 3]| *p = (Vec3::from_array(*p) + offset).to_array();
+        match self {
+            SavedEntity::Ruler { start, end, .. } => {
+                translate(start);
+                translate(end);
+            }
+            SavedEntity::Sphere { position, .. } | SavedEntity::Beacon { position, .. } => {
+                translate(position)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedRoute {
+    pub label: Option<String>,
+    pub color: [f32; 4],
+    pub rainbow: bool,
+    pub speed_multiplier: f32,
+    pub scale: f32,
+    pub marker_interval: f32,
+    pub show_all: bool,
+    pub activity_hash: Option<u32>,
+    pub nodes: Vec<SavedRouteNode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedRouteNode {
+    pub label: Option<String>,
+    pub position: [f32; 3],
+    pub map_hash: Option<u32>,
+    pub is_teleport: bool,
+}
+
+pub struct AutosaveFile {
+    pub path: PathBuf,
+    pub markup: SavedMarkup,
+}
+
+/// Set when the previous session did not exit cleanly
+pub struct CrashReport {
+    /// Human-readable reasons the previous session is considered crashed
+    pub evidence: Vec<String>,
+    /// Available autosaves, newest first
+    pub autosaves: Vec<AutosaveFile>,
+}
+
+pub struct Autosave {
+    directory: PathBuf,
+    next_save: Instant,
+    last_map: Option<TagHash>,
+    /// Last markup that was written, identical markup is not saved again
+    last_saved: Option<SavedMarkup>,
+    pub crash: Option<CrashReport>,
+}
+
+impl Autosave {
+    /// Checks whether the previous session crashed and marks the start of this one
+    pub fn start_session() -> Self {
+        let directory = autosave_directory();
+        if let Err(e) = std::fs::create_dir_all(&directory) {
+            error!("Failed to create autosave directory: {e}");
+        }
+
+        let marker = directory.join(SESSION_MARKER);
+        let crash = std::fs::metadata(&marker)
+            .ok()
+            .map(|m| crash_evidence(m.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            .and_then(|evidence| {
+                let autosaves = list_autosaves(&directory);
+                if autosaves.is_empty() {
+                    return None;
+                }
+
+                warn!(
+                    "Previous session did not exit cleanly, {} autosave(s) available",
+                    autosaves.len()
+                );
+                Some(CrashReport {
+                    evidence,
+                    autosaves,
+                })
+            });
+
+        if let Err(e) = std::fs::write(&marker, std::process::id().to_string()) {
+            error!("Failed to write session marker: {e}");
+        }
+
+        Self {
+            directory,
+            next_save: Instant::now() + autosave_interval(),
+            last_map: None,
+            last_saved: None,
+            crash,
+        }
+    }
+
+    /// Writes `markup` to a new autosave file on a background thread, rotating out the oldest
+    /// files. Empty markup, or markup identical to the previous save, is skipped
+    pub fn save(&mut self, markup: SavedMarkup) {
+        if markup.is_empty()
+            || self
+                .last_saved
+                .as_ref()
+                .is_some_and(|s| s.same_contents(&markup))
+        {
+            return;
+        }
+
+        let data = match serde_yaml::to_string(&markup) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to serialize autosave: {e}");
+                return;
+            }
+        };
+
+        let path = self.directory.join(format!(
+            "{AUTOSAVE_PREFIX}{}.yml",
+            markup.timestamp.format("%Y-%m-%d_%H-%M-%S")
+        ));
+        let directory = self.directory.clone();
+        let keep = config::with(|c| c.autosave.keep).max(1);
+        self.last_saved = Some(markup);
+
+        let result = std::thread::Builder::new()
+            .name("autosave".to_string())
+            .spawn(move || {
+                if let Err(e) = write_autosave(&path, &data) {
+                    error!("Failed to write autosave: {e:?}");
+                    return;
+                }
+                rotate_autosaves(&directory, keep);
+            });
+        if let Err(e) = result {
+            error!("Failed to spawn autosave thread: {e}");
+        }
+    }
+}
+
+/// Counts the consecutive sessions that ended with a crash dump, including the previous one.
+/// Has to be called before [`Autosave::start_session`] replaces the session marker
+pub fn update_crash_streak() -> u32 {
+    let directory = autosave_directory();
+    let streak_path = directory.join(CRASH_STREAK);
+    let crashed = std::fs::metadata(directory.join(SESSION_MARKER))
+        .and_then(|m| m.modified())
+        .is_ok_and(|start| crash_dumps_since(start) > 0);
+
+    let streak = if crashed {
+        std::fs::read_to_string(&streak_path)
+            .ok()
+            .and_then(|s| s.trim().parse::<u32>().ok())
+            .unwrap_or(0)
+            + 1
+    } else {
+        0
+    };
+
+    let result = std::fs::create_dir_all(&directory)
+        .and_then(|_| std::fs::write(&streak_path, streak.to_string()));
+    if let Err(e) = result {
+        error!("Failed to write crash streak: {e}");
+    }
+
+    streak
+}
+
+/// Marks the session as cleanly exited
+pub fn end_session() {
+    let marker = autosave_directory().join(SESSION_MARKER);
+    if let Err(e) = std::fs::remove_file(marker) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            error!("Failed to remove session marker: {e}");
+        }
+    }
+}
+
+/// Saves the markup of all maps when the autosave interval has passed, or when the current map
+/// has changed
+pub fn autosave_step(maps: &mut MapList, resources: &AppResources) {
+    if !config::with(|c| c.autosave.enabled) {
+        return;
+    }
+
+    let mut autosave = resources.get_mut::<Autosave>();
+    let current_map = maps.current_map().map(|m| m.hash);
+    let map_switched = autosave.last_map.is_some() && autosave.last_map != current_map;
+    autosave.last_map = current_map;
+
+    if !map_switched && Instant::now() < autosave.next_save {
+        return;
+    }
+
+    autosave.next_save = Instant::now() + autosave_interval();
+    let markup = collect_markup(maps, resources.get::<CurrentActivity>().0);
+    autosave.save(markup);
+}
+
+fn autosave_directory() -> PathBuf {
+    paths::local_config_dir().join("autosave")
+}
+
+fn autosave_interval() -> Duration {
+    Duration::from_secs(config::with(|c| c.autosave.interval_minutes).max(1) as u64 * 60)
+}
+
+/// Describes the panic log and crash dumps that were written after the session marker
+fn crash_evidence(session_start: SystemTime) -> Vec<String> {
+    let is_newer = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t >= session_start)
+    };
+
+    let mut evidence = vec!["The previous session did not exit cleanly".to_string()];
+    if is_newer(Path::new("panic.log")) {
+        evidence.push("A panic was logged to panic.log".to_string());
+    }
+
+    let dumps = crash_dumps_since(session_start);
+    if dumps > 0 {
+        evidence.push(format!("{dumps} crash dump(s) were written to crashes/"));
+    }
+
+    evidence
+}
+
+fn crash_dumps_since(session_start: SystemTime) -> usize {
+    std::fs::read_dir("crashes")
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "dmp"))
+                .filter(|e| {
+                    e.metadata()
+                        .and_then(|m| m.modified())
+                        .is_ok_and(|t| t >= session_start)
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Autosave files in `directory`, newest first
+fn autosave_paths(directory: &Path) -> Vec<PathBuf> {
+    let Ok(dir) = std::fs::read_dir(directory) else {
+        return vec![];
+    };
+
+    let mut paths: Vec<PathBuf> = dir
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension().is_some_and(|ext| ext == "yml")
+                && p.file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with(AUTOSAVE_PREFIX))
+        })
+        .collect();
+
+    // Timestamps in the file names sort chronologically
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+fn list_autosaves(directory: &Path) -> Vec<AutosaveFile> {
+    autosave_paths(directory)
+        .into_iter()
+        .filter_map(|path| {
+            let markup = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| Ok(serde_yaml::from_str::<SavedMarkup>(&data)?));
+            match markup {
+                Ok(markup) => Some(AutosaveFile { path, markup }),
+                Err(e) => {
+                    warn!("Failed to read autosave {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Writes to a temporary file first, so a crash during the write can't leave a truncated autosave
+fn write_autosave(path: &Path, data: &str) -> anyhow::Result<()> {
+    let temp_path = path.with_extension("yml.tmp");
+    std::fs::write(&temp_path, data)
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to move autosave to {}", path.display()))?;
+
+    Ok(())
+}
+
+fn rotate_autosaves(directory: &Path, keep: usize) {
+    for path in autosave_paths(directory).into_iter().skip(keep) {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("Failed to remove old autosave {}: {e}", path.display());
+        }
+    }
+}
+
+fn user_label(label: Option<&Label>) -> Option<String> {
+    label.filter(|l| !l.default).map(|l| l.label.clone())
+}
+
+/// Collects the user-created markup of every map in the list
+pub fn collect_markup(maps: &mut MapList, activity_hash: Option<TagHash>) -> SavedMarkup {
+    let mut saved_maps = vec![];
+    let mut routes = vec![];
+    for map in maps.maps.iter_mut() {
+        let scene = &mut map.scene;
+        let mut entities = vec![];
+
+        let mutable = scene
+            .query_filtered::<Entity, With<Mutable>>()
+            .iter(scene)
+            .collect::<Vec<_>>();
+        entities.extend(
+            mutable
+                .into_iter()
+                .filter_map(|e| save_entity(scene.entity(e))),
+        );
+
+        for (route, children, label) in scene
+            .query::<(&Route, &Children, Option<&Label>)>()
+            .iter(scene)
+        {
+            let nodes = children
+                .iter()
+                .filter_map(|&e| {
+                    let node = scene.get::<RouteNode>(e)?;
+                    let transform = scene.get::<Transform>(e)?;
+                    Some(SavedRouteNode {
+                        label: user_label(scene.get::<Label>(e)),
+                        position: transform.translation.to_array(),
+                        map_hash: node.map_hash.map(|h| h.0),
+                        is_teleport: node.is_teleport,
+                    })
+                })
+                .collect();
+
+            routes.push(SavedRoute {
+                label: user_label(label),
+                color: route.color.to_array(),
+                rainbow: route.rainbow,
+                speed_multiplier: route.speed_multiplier,
+                scale: route.scale,
+                marker_interval: route.marker_interval,
+                show_all: route.show_all,
+                activity_hash: route.activity_hash.map(|h| h.0),
+                nodes,
+            });
+        }
+
+        if !entities.is_empty() {
+            saved_maps.push(SavedMapMarkup {
+                map_hash: map.hash.0,
+                map_name: map.name.clone(),
+                entities,
+            });
+        }
+    }
+
+    SavedMarkup {
+        timestamp: chrono::Local::now(),
+        activity_hash: activity_hash.map(|h| h.0),
+        maps: saved_maps,
+        routes,
+    }
+}
+
+/// Saves a single ruler, sphere or beacon
+pub fn save_entity(e: EntityRef<'_>) -> Option<SavedEntity> {
+    let label = user_label(e.get::<Label>());
+    if let Some(ruler) = e.get::<Ruler>() {
+        return Some(SavedEntity::Ruler {
+            label,
+            start: ruler.start.to_array(),
+            end: ruler.end.to_array(),
+            color: ruler.color.to_array(),
+            rainbow: ruler.rainbow,
+            scale: ruler.scale,
+            marker_interval: ruler.marker_interval,
+            show_individual_axis: ruler.show_individual_axis,
+        });
+    }
+
+    let transform = e.get::<Transform>()?;
+    if let Some(sphere) = e.get::<Sphere>() {
+        Some(SavedEntity::Sphere {
+            label,
+            position: transform.translation.to_array(),
+            radius: transform.scale.to_array(),
+            detail: sphere.detail,
+            color: sphere.color.to_array(),
+            rainbow: sphere.rainbow,
+        })
+    } else {
+        e.get::<Beacon>().map(|beacon| SavedEntity::Beacon {
+            label,
+            position: transform.translation.to_array(),
+            color: beacon.color.to_array(),
+            freq: beacon.freq,
+            distance: beacon.distance,
+            travel_time: beacon.travel_time,
+        })
+    }
+}
+
+pub struct RestoreResult {
+    pub restored: usize,
+    /// Entities belonging to maps that aren't loaded
+    pub skipped: usize,
+}
+
+/// Spawns the markup into the matching maps. Routes are spawned into the current map, entities of
+/// maps that are not loaded are skipped
+pub fn restore_markup(maps: &mut MapList, markup: &SavedMarkup) -> RestoreResult {
+    let mut result = RestoreResult {
+        restored: 0,
+        skipped: 0,
+    };
+
+    for saved_map in &markup.maps {
+        let Some(map) = maps
+            .maps
+            .iter_mut()
+            .find(|m| m.hash.0 == saved_map.map_hash && m.load_state == MapLoadState::Loaded)
+        else {
+            result.skipped += saved_map.entities.len();
+            continue;
+        };
+
+        for entity in &saved_map.entities {
+            spawn_entity(&mut map.scene, entity);
+            result.restored += 1;
+        }
+    }
+
+    match maps
+        .current_map_mut()
+        .filter(|m| m.load_state == MapLoadState::Loaded)
+    {
+        Some(map) => {
+            for route in &markup.routes {
+                spawn_route(&mut map.scene, route);
+                result.restored += 1;
+            }
+            let mut routes = map.scene.query::<(Entity, &Route)>();
+            let (scene, mut commands) = map.scene_and_commands();
+            for (entity, route) in routes.iter(scene) {
+                route.fixup_visiblity(scene, &mut commands, entity);
+            }
+        }
+        None => result.skipped += markup.routes.len(),
+    }
+
+    result
+}
+
+fn color(c: [f32; 4]) -> Color {
+    Color::from_rgba_premultiplied(c[0], c[1], c[2], c[3])
+}
+
+/// Spawns a saved ruler, sphere or beacon into `scene`
+pub fn spawn_entity(scene: &mut Scene, entity: &SavedEntity) -> Entity {
+    match entity {
+        SavedEntity::Ruler {
+            label,
+            start,
+            end,
+            color: c,
+            rainbow,
+            scale,
+            marker_interval,
+            show_individual_axis,
+        } => scene
+            .spawn((
+                NodeFilter::Utility,
+                Ruler {
+                    start: Vec3::from_array(*start),
+                    end: Vec3::from_array(*end),
+                    color: color(*c),
+                    rainbow: *rainbow,
+                    scale: *scale,
+                    marker_interval: *marker_interval,
+                    show_individual_axis: *show_individual_axis,
+                },
+                Ruler::icon(),
+                label
+                    .as_deref()
+                    .map_or_else(Ruler::default_label, Ruler::label),
+                Tags::from_iter([EntityTag::Utility]),
+                Mutable,
+                RenderCommonBundle::default(),
+            ))
+            .id(),
+        SavedEntity::Sphere {
+            label,
+            position,
+            radius,
+            detail,
+            color: c,
+            rainbow,
+        } => scene
+            .spawn((
+                NodeFilter::Utility,
+                Transform {
+                    translation: Vec3::from_array(*position),
+                    scale: Vec3::from_array(*radius),
+                    flags: TransformFlags::IGNORE_ROTATION | TransformFlags::SCALE_IS_RADIUS,
+                    ..Default::default()
+                },
+                Sphere {
+                    detail: *detail,
+                    color: color(*c),
+                    rainbow: *rainbow,
+                },
+                Sphere::icon(),
+                label
+                    .as_deref()
+                    .map_or_else(Sphere::default_label, Sphere::label),
+                Tags::from_iter([EntityTag::Utility]),
+                Mutable,
+                RenderCommonBundle::default(),
+            ))
+            .id(),
+        SavedEntity::Beacon {
+            label,
+            position,
+            color: c,
+            freq,
+            distance,
+            travel_time,
+        } => scene
+            .spawn((
+                NodeFilter::Utility,
+                Transform {
+                    translation: Vec3::from_array(*position),
+                    flags: TransformFlags::IGNORE_ROTATION | TransformFlags::IGNORE_SCALE,
+                    ..Default::default()
+                },
+                Beacon {
+                    color: color(*c),
+                    freq: *freq,
+                    distance: *distance,
+                    travel_time: *travel_time,
+                },
+                Beacon::icon(),
+                label
+                    .as_deref()
+                    .map_or_else(Beacon::default_label, Beacon::label),
+                Tags::from_iter([EntityTag::Utility]),
+                Mutable,
+                RenderCommonBundle::default(),
+            ))
+            .id(),
+    }
+}
+
+fn spawn_route(scene: &mut Scene, route: &SavedRoute) {
+    let parent = scene.spawn_empty().id();
+    let children = route
+        .nodes
+        .iter()
+        .map(|node| {
+            scene
+                .spawn(RouteNodeBundle::new(
+                    parent,
+                    RouteNodeData {
+                        pos: Vec3::from_array(node.position),
+                        map_hash: node.map_hash.map(TagHash),
+                        is_teleport: node.is_teleport,
+                        label: node.label.clone(),
+                    },
+                ))
+                .id()
+        })
+        .collect::<Vec<_>>();
+
+    scene.entity_mut(parent).insert((
+        Children::from_slice(&children),
+        Route {
+            color: color(route.color),
+            rainbow: route.rainbow,
+            speed_multiplier: route.speed_multiplier,
+            scale: route.scale,
+            marker_interval: route.marker_interval,
+            show_all: route.show_all,
+            activity_hash: route.activity_hash.map(TagHash),
+            ..Default::default()
+        },
+        Route::icon(),
+        route
+            .label
+            .as_deref()
+            .map_or_else(Route::default_label, Route::label),
+        NodeFilter::Utility,
+        Tags::from_iter([EntityTag::Utility, EntityTag::Global]),
+        Mutable,
+        Global,
+        RenderCommonBundle::default(),
+    ));
+}
@@ -0,0 +1,334 @@
+//! Compositing of viewport annotations (labels, scale bar, map name) into captured images.
+//!
+//! Annotations are laid out with egui and rasterized on the CPU straight into the captured
+//! pixels, so they are drawn at the full capture resolution instead of being upscaled.
+
+use std::collections::HashMap;
+
+use alkahest_renderer::{
+    camera::Camera,
+    renderer::{ImmediateLabel, LabelAlign},
+    util::image::Png,
+};
+use egui::{
+    epaint::{Primitive, Vertex},
+    Align2, Color32, FontId, ImageData, Painter, Pos2, Rect, TextureId,
+};
+use glam::Vec3;
+use png::{BitDepth, ColorType};
+
+/// Size of the capture (in pixels) annotations are laid out for. Larger captures scale them up
+const REFERENCE_HEIGHT: f32 = 1080.0;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureAnnotation {
+    /// Text labels of rulers, routes and other utilities
+    Labels,
+    /// Ruler, sphere, beacon and route geometry
+    Utilities,
+    /// Scale bar, measured at the depth of the center of the image
+    ScaleBar,
+    /// Name and hash of the map
+    Watermark,
+}
+
+pub struct AnnotationContext<'a> {
+    pub camera: &'a Camera,
+    pub labels: Vec<ImmediateLabel>,
+    /// World position at the center of the image, if it's not sky
+    pub center_position: Option<Vec3>,
+    pub map_name: &'a str,
+    pub map_hash: String,
+}
+
+/// Draws the given annotations over an 8-bit RGBA capture
+pub fn composite_annotations(
+    image: &mut Png,
+    annotations: &[CaptureAnnotation],
+    actx: AnnotationContext,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        image.color_type == ColorType::Rgba && image.bit_depth == BitDepth::Eight,
+        "Annotations can only be drawn on 8-bit RGBA images"
+    );
+
+    let [width, height] = image.dimensions;
+    let pixels_per_point = (height as f32 / REFERENCE_HEIGHT).max(1.0);
+    let screen_size = egui::vec2(width as f32, height as f32) / pixels_per_point;
+
+    let mut input = egui::RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, screen_size)),
+        ..Default::default()
+    };
+    input
+        .viewports
+        .entry(egui::ViewportId::ROOT)
+        .or_default()
+        .native_pixels_per_point = Some(pixels_per_point);
+
+    let ctx = egui::Context::default();
+    let output = ctx.run(input, |ctx| {
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        if annotations.contains(&CaptureAnnotation::Labels) {
+            draw_labels(&painter, &actx, screen_size);
+        }
+
+        if annotations.contains(&CaptureAnnotation::ScaleBar) {
+            draw_scale_bar(&painter, &actx, screen_size);
+        }
+
+        if annotations.contains(&CaptureAnnotation::Watermark) {
+            text_with_background(
+                &painter,
+                Pos2::new(screen_size.x - 16.0, screen_size.y - 16.0),
+                Align2::RIGHT_BOTTOM,
+                format!("{} ({})", actx.map_name, actx.map_hash),
+                FontId::proportional(16.0),
+                Color32::from_white_alpha(220),
+            );
+        }
+    });
+
+    let mut textures: HashMap<TextureId, Texture> = HashMap::new();
+    for (id, delta) in &output.textures_delta.set {
+        let pixels = match &delta.image {
+            ImageData::Color(c) => c.pixels.clone(),
+            ImageData::Font(f) => f
+                .pixels
+                .iter()
+                .map(|a| Color32::from_rgba_premultiplied(255, 255, 255, (a * 255.) as u8))
+                .collect(),
+        };
+        let [delta_width, delta_height] = delta.image.size();
+
+        match delta.pos {
+            Some([x, y]) => {
+                let texture = textures
+                    .get_mut(id)
+                    .ok_or_else(|| anyhow::anyhow!("Partial update for unknown texture {id:?}"))?;
+                for row in 0..delta_height {
+                    let dst = (y + row) * texture.size[0] + x;
+                    texture.pixels[dst..dst + delta_width]
+                        .copy_from_slice(&pixels[row * delta_width..(row + 1) * delta_width]);
+                }
+            }
+            None => {
+                textures.insert(
+                    *id,
+                    Texture {
+                        size: [delta_width, delta_height],
+                        pixels,
+                    },
+                );
+            }
+        }
+    }
+
+    let mut target = image.data.to_vec();
+    for primitive in ctx.tessellate(output.shapes, output.pixels_per_point) {
+        let Primitive::Mesh(mesh) = primitive.primitive else {
+            continue;
+        };
+
+        let Some(texture) = textures.get(&mesh.texture_id) else {
+            warn!("Annotation mesh uses unknown texture {:?}", mesh.texture_id);
+            continue;
+        };
+
+        let clip = Rect::from_min_max(
+            (primitive.clip_rect.min.to_vec2() * output.pixels_per_point).to_pos2(),
+            (primitive.clip_rect.max.to_vec2() * output.pixels_per_point).to_pos2(),
+        );
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let vertices = [
+                mesh.vertices[triangle[0] as usize],
+                mesh.vertices[triangle[1] as usize],
+                mesh.vertices[triangle[2] as usize],
+            ];
+            rasterize_triangle(
+                &mut target,
+                [width, height],
+                vertices,
+                output.pixels_per_point,
+                clip,
+                texture,
+            );
+        }
+    }
+
+    image.data = target.into();
+    Ok(())
+}
+
+fn draw_labels(painter: &Painter, actx: &AnnotationContext, screen_size: egui::Vec2) {
+    for ImmediateLabel {
+        text,
+        position,
+        align,
+        color,
+    } in &actx.labels
+    {
+        if !actx.camera.is_point_visible(*position) {
+            continue;
+        }
+
+        let projected_point = actx.camera.world_to_projective.project_point3(*position);
+        let screen_point = Pos2::new(
+            ((projected_point.x + 1.0) * 0.5) * screen_size.x,
+            ((1.0 - projected_point.y) * 0.5) * screen_size.y,
+        );
+
+        let anchor = Align2(align.map(|a| match a {
+            LabelAlign::Min => egui::Align::Min,
+            LabelAlign::Center => egui::Align::Center,
+            LabelAlign::Max => egui::Align::Max,
+        }));
+
+        text_with_background(
+            painter,
+            screen_point + anchor.to_sign() * -4.,
+            anchor,
+            text.clone(),
+            FontId::monospace(12.0),
+            (*color).into(),
+        );
+    }
+}
+
+fn draw_scale_bar(painter: &Painter, actx: &AnnotationContext, screen_size: egui::Vec2) {
+    let Some(center) = actx.center_position else {
+        warn!("The center of the capture is sky, skipping scale bar");
+        return;
+    };
+
+    // Measure how many points one unit spans at the center of the image
+    let camera = actx.camera;
+    let a = camera.world_to_projective.project_point3(center);
+    let b = camera
+        .world_to_projective
+        .project_point3(center + camera.right());
+    let points_per_unit = (b.x - a.x).abs() * 0.5 * screen_size.x;
+    if !points_per_unit.is_finite() || points_per_unit <= f32::EPSILON {
+        warn!("Could not determine the capture scale, skipping scale bar");
+        return;
+    }
+
+    // Round the bar down to a 1/2/5 multiple of a power of ten, around a fifth of the image
+    let target_length = screen_size.x * 0.2 / points_per_unit;
+    let magnitude = 10f32.powf(target_length.log10().floor());
+    let length = [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|l| *l <= target_length)
+        .unwrap_or(magnitude);
+    let bar_width = length * points_per_unit;
+
+    let distance = center.distance(camera.position());
+    let origin = Pos2::new(16.0, screen_size.y - 16.0);
+    let end = origin + egui::vec2(bar_width, 0.0);
+
+    painter.rect_filled(
+        Rect::from_min_max(origin - egui::vec2(6.0, 30.0), end + egui::vec2(6.0, 6.0)),
+        2.0,
+        Color32::from_black_alpha(140),
+    );
+
+    let stroke = egui::Stroke::new(2.0, Color32::WHITE);
+    painter.line_segment([origin, end], stroke);
+    painter.line_segment([origin, origin - egui::vec2(0.0, 6.0)], stroke);
+    painter.line_segment([end, end - egui::vec2(0.0, 6.0)], stroke);
+    painter.text(
+        origin - egui::vec2(0.0, 10.0),
+        Align2::LEFT_BOTTOM,
+        format!("{length} m (at {distance:.0} m)"),
+        FontId::proportional(13.0),
+        Color32::WHITE,
+    );
+}
+
+fn text_with_background(
+    painter: &Painter,
+    pos: Pos2,
+    anchor: Align2,
+    text: String,
+    font: FontId,
+    color: Color32,
+) {
+    let galley = painter.layout_no_wrap(text, font, color);
+    let rect = anchor.anchor_rect(Rect::from_min_size(pos, galley.size()));
+    painter.rect_filled(rect.expand(2.0), 2.0, Color32::from_black_alpha(140));
+    painter.galley(rect.min, galley, color);
+}
+
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    fn sample(&self, uv: Pos2) -> Color32 {
+        let x = ((uv.x * self.size[0] as f32) as usize).min(self.size[0] - 1);
+        let y = ((uv.y * self.size[1] as f32) as usize).min(self.size[1] - 1);
+        self.pixels[y * self.size[0] + x]
+    }
+}
+
+/// Rasterizes a single egui triangle into an RGBA8 buffer, blending in gamma space like egui's
+/// GPU backends do
+fn rasterize_triangle(
+    target: &mut [u8],
+    [width, height]: [usize; 2],
+    vertices: [Vertex; 3],
+    pixels_per_point: f32,
+    clip: Rect,
+    texture: &Texture,
+) {
+    let p = vertices.map(|v| (v.pos.to_vec2() * pixels_per_point).to_pos2());
+    let edge = |a: Pos2, b: Pos2, c: Pos2| (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+
+    let area = edge(p[0], p[1], p[2]);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+
+    let bounds = Rect::from_points(&p).intersect(clip);
+    let x0 = bounds.min.x.floor().max(0.0) as usize;
+    let y0 = bounds.min.y.floor().max(0.0) as usize;
+    let x1 = (bounds.max.x.ceil().max(0.0) as usize).min(width);
+    let y1 = (bounds.max.y.ceil().max(0.0) as usize).min(height);
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(p[1], p[2], center) / area;
+            let w1 = edge(p[2], p[0], center) / area;
+            let w2 = 1.0 - w0 - w1;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+
+            let uv = (vertices[0].uv.to_vec2() * w0
+                + vertices[1].uv.to_vec2() * w1
+                + vertices[2].uv.to_vec2() * w2)
+                .to_pos2();
+            let texel = texture.sample(uv).to_array();
+            let colors = vertices.map(|v| v.color.to_array());
+
+            // Premultiplied source color
+            let src: [f32; 4] = std::array::from_fn(|c| {
+                let vertex_color =
+                    colors[0][c] as f32 * w0 + colors[1][c] as f32 * w1 + colors[2][c] as f32 * w2;
+                vertex_color * texel[c] as f32 / 255.0
+            });
+
+            let offset = (y * width + x) * 4;
+            for c in 0..3 {
+                let dst = target[offset + c] as f32;
+                target[offset + c] = (src[c] + dst * (1.0 - src[3] / 255.0))
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
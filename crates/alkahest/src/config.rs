@@ -1,19 +1,37 @@
-use alkahest_renderer::{ecs::tags::NodeFilter, renderer::RendererSettings};
-use egui::ahash::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+use alkahest_renderer::{
+    ecs::{common::ResourceOrigin, tags::NodeFilter},
+    renderer::RendererSettings,
+};
+use destiny_pkg::GameVersion;
+use egui::{ahash::HashSet, KeyboardShortcut};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use strum::IntoEnumIterator;
+use strum::{EnumIter, IntoEnumIterator};
 
-use crate::{paths, updater::UpdateChannel, util::RwLock};
+use crate::{
+    gui::hotkeys::HotkeyAction, investment::InvestmentConfig, paths,
+    presentation::PresentationTransition, safe_mode, updater::UpdateChannel, util::RwLock,
+};
 
 lazy_static! {
     pub static ref CONFIGURATION: RwLock<Config> = RwLock::new(Config::default());
 }
 
 pub fn try_persist() -> anyhow::Result<()> {
+    let data = {
+        let mut config = CONFIGURATION.write();
+        // Safe mode only lowers the settings for the current session
+        let session = safe_mode::swap_in_original_settings(&mut config);
+        let data = serde_yaml::to_string(&*config);
+        safe_mode::restore_session_settings(&mut config, session);
+        data?
+    };
+
     Ok(std::fs::write(
         paths::config_dir().join("config.yml"),
-        serde_yaml::to_string(&*CONFIGURATION.read())?,
+        data,
     )?)
 }
 
@@ -70,6 +88,57 @@ pub struct Config {
     pub visual: VisualSettings,
     pub update_channel: Option<UpdateChannel>,
     pub packages_directory: Option<String>,
+    /// Season the packages directory is from, the latest season if not set
+    pub packages_season: Option<GameSeason>,
+    /// Additional package directories that maps can be loaded from, such as archived snapshots
+    pub package_sources: Vec<PackageSourceConfig>,
+    pub console: ConsoleConfig,
+    pub autosave: AutosaveConfig,
+    pub prefetch: PrefetchConfig,
+    pub load_watchdog: LoadWatchdogConfig,
+    pub presentation: PresentationConfig,
+    /// Don't show the recent maps screen when started without a map or activity
+    pub skip_startup_screen: bool,
+    /// Tags defined by the user, which can be assigned to any entity from the inspector
+    pub custom_tags: Vec<String>,
+    /// Shortcuts that have been rebound from their defaults
+    pub keybindings: BTreeMap<HotkeyAction, KeyboardShortcut>,
+    pub activity_browser: ActivityBrowserConfig,
+    pub investment: InvestmentConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PackageSourceConfig {
+    pub name: String,
+    pub directory: String,
+    #[serde(default)]
+    pub season: GameSeason,
+}
+
+/// Seasons with a distinct package layout, used to read archived package directories
+#[derive(
+    Serialize, Deserialize, clap::ValueEnum, EnumIter, Debug, Clone, Copy, PartialEq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GameSeason {
+    Shadowkeep,
+    BeyondLight,
+    WitchQueen,
+    Lightfall,
+    #[default]
+    TheFinalShape,
+}
+
+impl GameSeason {
+    pub fn game_version(self) -> GameVersion {
+        match self {
+            GameSeason::Shadowkeep => GameVersion::Destiny2Shadowkeep,
+            GameSeason::BeyondLight => GameVersion::Destiny2BeyondLight,
+            GameSeason::WitchQueen => GameVersion::Destiny2WitchQueen,
+            GameSeason::Lightfall => GameVersion::Destiny2Lightfall,
+            GameSeason::TheFinalShape => GameVersion::Destiny2TheFinalShape,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -79,6 +148,16 @@ pub struct VisualSettings {
     pub node_nametags: bool,
     pub node_nametags_named_only: bool,
     pub node_filters: HashSet<String>,
+    /// Resource origins shown in the node overlay, nodes without an origin are always shown
+    pub node_origin_filters: HashSet<String>,
+    /// Color node icons and labels by their resource origin instead of their type
+    pub node_color_by_origin: bool,
+    /// Show a top-down overview of the current map in the corner of the viewport
+    pub minimap: bool,
+    /// Show the hash-based names of entities in the outliner instead of their resolved names
+    pub outliner_raw_labels: bool,
+    /// Group outliner entries by their resource origin and the data table they were loaded from
+    pub outliner_grouped: bool,
 }
 
 impl Default for VisualSettings {
@@ -93,6 +172,8 @@ impl Default for VisualSettings {
                         nf,
                         NodeFilter::PlayerContainmentVolume
                             | NodeFilter::Unknown80809121
+                            | NodeFilter::SlipSurfaceVolume
+                            | NodeFilter::Unknown80808246
                             | NodeFilter::InstakillBarrier
                             | NodeFilter::Cubemap
                             | NodeFilter::NamedArea
@@ -103,10 +184,22 @@ impl Default for VisualSettings {
                     }
                 })
                 .collect(),
+            node_origin_filters: ResourceOrigin::iter().map(|o| o.to_string()).collect(),
+            node_color_by_origin: false,
+            minimap: false,
+            outliner_raw_labels: false,
+            outliner_grouped: false,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ActivityBrowserConfig {
+    /// Groups in the activity browser that were expanded, restored on the next launch
+    pub expanded_groups: BTreeSet<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
 pub struct WindowConfig {
@@ -130,3 +223,117 @@ impl Default for WindowConfig {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ConsoleConfig {
+    /// Command aliases, expanded when the first word of a command matches the name
+    pub aliases: BTreeMap<String, String>,
+    /// Commands executed every time a map finishes loading
+    pub startup_commands: Vec<String>,
+    /// Log levels that are shown as notifications
+    pub toast_level: ToastLevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToastLevel {
+    Off,
+    Errors,
+    #[default]
+    Warnings,
+}
+
+impl ToastLevel {
+    pub fn shows(self, level: tracing::Level) -> bool {
+        match self {
+            ToastLevel::Off => false,
+            ToastLevel::Errors => level == tracing::Level::ERROR,
+            ToastLevel::Warnings => level <= tracing::Level::WARN,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutosaveConfig {
+    /// Periodically save user-created markup, so it can be restored after a crash
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    /// Number of autosave files kept before the oldest is removed
+    pub keep: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrefetchConfig {
+    /// Load the other maps of the activity in the background once the current map has loaded
+    pub enabled: bool,
+    /// Prefetching stops once the loaded assets use more GPU memory than this, in MiB
+    pub memory_cap_mb: u32,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            memory_cap_mb: 3072,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadWatchdogConfig {
+    /// Capture diagnostics and offer to cancel map loads that take longer than the timeout
+    pub enabled: bool,
+    pub timeout_seconds: u32,
+}
+
+impl Default for LoadWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_seconds: 180,
+        }
+    }
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: 5,
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PresentationConfig {
+    pub transition: PresentationTransition,
+    /// Length of the transition between bookmarks, in seconds
+    pub transition_seconds: f32,
+    /// Time spent at every bookmark, in seconds
+    pub hold_seconds: f32,
+    /// Orbit the selected entity instead of going through the bookmarks
+    pub orbit_selected: bool,
+    /// Orbit speed, in degrees per second
+    pub orbit_speed: f32,
+    /// Angle of the orbit above the horizon, in degrees
+    pub orbit_elevation: f32,
+}
+
+impl Default for PresentationConfig {
+    fn default() -> Self {
+        Self {
+            transition: PresentationTransition::Fade,
+            transition_seconds: 2.0,
+            hold_seconds: 8.0,
+            orbit_selected: false,
+            orbit_speed: 10.0,
+            orbit_elevation: 20.0,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Data-only map loading and export (`--data-only`), for data mining on machines without a GPU.
+//! The entity export is also used for exporting entities from the viewer itself
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alkahest_data::{occlusion::Aabb, text::StringContainer, tfx::TfxFeatureRenderer};
+use alkahest_renderer::{
+    ecs::{
+        common::{Label, ResourceOrigin},
+        hierarchy::Parent,
+        map::NodeMetadata,
+        render::{
+            decorators::DecoratorRenderer,
+            dynamic_geometry::DynamicModelComponent,
+            light::LightRenderer,
+            static_geometry::{StaticInstances, StaticModelSingle},
+            terrain::TerrainPatches,
+        },
+        tags::NodeFilter,
+        transform::Transform,
+        Scene,
+    },
+    loaders::map::load_map_data_only,
+    resources::AppResources,
+};
+use anyhow::Context;
+use bevy_ecs::{entity::Entity, world::EntityRef};
+use destiny_pkg::TagHash;
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+
+use crate::{
+    gui::entity_query::{EntityQuery, QueryComponent},
+    maplist::MapList,
+    ApplicationArgs,
+};
+
+/// Components present on entities of a data-only load
+const DATA_ONLY_COMPONENTS: &[QueryComponent] = &[
+    QueryComponent::Label,
+    QueryComponent::NodeMetadata,
+    QueryComponent::Global,
+];
+
+#[derive(Serialize)]
+struct EntityRow {
+    entity: u64,
+    parent: Option<u64>,
+    label: Option<String>,
+    origin: Option<String>,
+    world_id: Option<String>,
+    entity_tag: Option<String>,
+    source_table: Option<String>,
+    resource_type: Option<String>,
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    kind: Option<String>,
+    /// Model, terrain or decorator drawn by the entity
+    hash: Option<String>,
+    feature_renderer: Option<String>,
+    /// Size of the world space bounding box
+    aabb_size: Option<[f32; 3]>,
+}
+
+impl EntityRow {
+    const CSV_HEADER: &'static str = "entity,parent,label,origin,world_id,entity_tag,source_table,\
+                                      resource_type,x,y,z,rot_x,rot_y,rot_z,rot_w,scale_x,scale_y,\
+                                      scale_z,kind,hash,feature_renderer,aabb_x,aabb_y,aabb_z";
+
+    fn to_csv(&self) -> String {
+        let opt = |v: &Option<String>| csv_escape(v.as_deref().unwrap_or_default());
+        let mut fields = vec![
+            self.entity.to_string(),
+            self.parent.map(|p| p.to_string()).unwrap_or_default(),
+            opt(&self.label),
+            opt(&self.origin),
+            opt(&self.world_id),
+            opt(&self.entity_tag),
+            opt(&self.source_table),
+            opt(&self.resource_type),
+        ];
+        fields.extend(
+            self.translation
+                .iter()
+                .chain(&self.rotation)
+                .chain(&self.scale)
+                .map(|v| v.to_string()),
+        );
+        fields.extend([
+            opt(&self.kind),
+            opt(&self.hash),
+            opt(&self.feature_renderer),
+        ]);
+        match self.aabb_size {
+            Some(size) => fields.extend(size.map(|v| v.to_string())),
+            None => fields.extend([String::new(), String::new(), String::new()]),
+        }
+
+        fields.join(",")
+    }
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub async fn run_data_export(args: ApplicationArgs) -> anyhow::Result<()> {
+    let map_hash = args
+        .map
+        .context("--data-only requires a map to be specified with --map")?;
+
+    let query = args
+        .data_query
+        .as_deref()
+        .map(|q| EntityQuery::parse(&q.split_whitespace().collect::<Vec<_>>()))
+        .transpose()
+        .context("Invalid --data-query")?;
+    if let Some(p) = query
+        .iter()
+        .flat_map(|q| &q.predicates)
+        .find(|p| !DATA_ONLY_COMPONENTS.contains(&p.component))
+    {
+        anyhow::bail!(
+            "--data-query can't filter on {}, data-only loads only have the {} components",
+            p.component,
+            DATA_ONLY_COMPONENTS.iter().join(", ")
+        );
+    }
+
+    let stringmap = Arc::new(StringContainer::load_all_global());
+
+    let start = std::time::Instant::now();
+    let scene = load_map_data_only(map_hash, args.activity, stringmap, !args.no_ambient)
+        .await
+        .with_context(|| format!("Failed to load map {map_hash}"))?;
+    info!(
+        "Loaded map {map_hash} ({} entities) in {:.2}s without GPU resources",
+        scene.entities().len(),
+        start.elapsed().as_secs_f32()
+    );
+
+    let rows = collect_rows(&scene, |e| query.as_ref().map_or(true, |q| q.matches(e)));
+    write_rows(&args.data_output, &rows)?;
+    info!(
+        "Exported {} entities to {}",
+        rows.len(),
+        args.data_output.display()
+    );
+
+    Ok(())
+}
+
+/// Exports every entity in the scene to a JSON or CSV file (based on the extension of `path`),
+/// returning the number of entities written
+pub fn export_scene(scene: &Scene, path: &Path) -> anyhow::Result<usize> {
+    let rows = collect_rows(scene, |_| true);
+    write_rows(path, &rows)?;
+    Ok(rows.len())
+}
+
+/// `export_scene [path]`: exports every entity of the current map, asking for the destination when
+/// no path is given
+pub fn export_scene_command(args: &[&str], resources: &AppResources) {
+    let path = if args.is_empty() {
+        let map_name = resources
+            .get::<MapList>()
+            .current_map()
+            .map(|m| m.name.clone())
+            .unwrap_or_default();
+        match native_dialog::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_filename(&format!("{map_name}.csv"))
+            .show_save_single_file()
+        {
+            Ok(Some(path)) => path,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to open the file dialog: {e}");
+                return;
+            }
+        }
+    } else {
+        PathBuf::from(args.join(" "))
+    };
+
+    let maps = resources.get::<MapList>();
+    let Some(map) = maps.current_map() else {
+        error!("No map loaded");
+        return;
+    };
+
+    match export_scene(&map.scene, &path) {
+        Ok(count) => info!("Exported {count} entities to {}", path.display()),
+        Err(e) => error!("Failed to export entities: {e:?}"),
+    }
+}
+
+/// Exports the given entities to a JSON or CSV file (based on the extension of `path`)
+pub fn export_entities(scene: &Scene, entities: &[Entity], path: &Path) -> anyhow::Result<()> {
+    let entities: FxHashSet<Entity> = entities.iter().copied().collect();
+    let rows = collect_rows(scene, |e| entities.contains(&e.id()));
+    write_rows(path, &rows)
+}
+
+fn collect_rows(scene: &Scene, filter: impl Fn(EntityRef<'_>) -> bool) -> Vec<EntityRow> {
+    let mut rows = scene
+        .iter_entities()
+        .filter(|e| filter(*e))
+        .map(|e| {
+            let meta = e.get::<NodeMetadata>();
+            let transform = e.get::<Transform>().copied().unwrap_or_default();
+            let (hash, feature_renderer) = render_resource(e).unzip();
+            EntityRow {
+                entity: e.id().to_bits(),
+                parent: e.get::<Parent>().map(|p| p.0.to_bits()),
+                label: e.get::<Label>().map(Label::to_string),
+                origin: e.get::<ResourceOrigin>().map(ResourceOrigin::to_string),
+                world_id: meta
+                    .filter(|m| m.world_id != u64::MAX)
+                    .map(|m| format!("{:016X}", m.world_id)),
+                entity_tag: meta.map(|m| m.entity_tag.to_string()),
+                source_table: meta.map(|m| m.source_table.to_string()),
+                resource_type: meta.map(|m| format!("{:08X}", m.resource_type)),
+                translation: transform.translation.to_array(),
+                rotation: transform.rotation.to_array(),
+                scale: transform.scale.to_array(),
+                kind: e.get::<NodeFilter>().map(NodeFilter::to_string),
+                hash: hash.flatten().map(|h| h.to_string()),
+                feature_renderer: feature_renderer.map(|f| format!("{f:?}")),
+                aabb_size: e.get::<Aabb>().map(|aabb| {
+                    Aabb::from_obbs([(transform.local_to_world(), *aabb)])
+                        .dimensions()
+                        .to_array()
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    rows.sort_by_key(|r| r.entity);
+    rows
+}
+
+/// The resource an entity draws and the feature renderer drawing it. Entities loaded without GPU
+/// resources don't have either
+fn render_resource(e: EntityRef<'_>) -> Option<(Option<TagHash>, TfxFeatureRenderer)> {
+    if let Some(instances) = e.get::<StaticInstances>() {
+        return Some((
+            Some(instances.model.hash),
+            TfxFeatureRenderer::StaticObjects,
+        ));
+    }
+    if let Some(single) = e.get::<StaticModelSingle>() {
+        return Some((Some(single.model.hash), TfxFeatureRenderer::StaticObjects));
+    }
+    if let Some(dynamic) = e.get::<DynamicModelComponent>() {
+        return Some((Some(dynamic.model.hash), dynamic.model.feature_type));
+    }
+    if let Some(terrain) = e.get::<TerrainPatches>() {
+        return Some((Some(terrain.hash), TfxFeatureRenderer::TerrainPatch));
+    }
+    if let Some(decorator) = e.get::<DecoratorRenderer>() {
+        return Some((Some(decorator.hash), TfxFeatureRenderer::SpeedtreeTrees));
+    }
+    if e.contains::<LightRenderer>() {
+        return Some((None, TfxFeatureRenderer::DeferredLights));
+    }
+
+    None
+}
+
+fn write_rows(path: &Path, rows: &[EntityRow]) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        writeln!(writer, "{}", EntityRow::CSV_HEADER)?;
+        for row in rows {
+            writeln!(writer, "{}", row.to_csv())?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut writer, rows)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[test]
+fn test_csv_row_matches_header() {
+    let row = EntityRow {
+        entity: 1,
+        parent: None,
+        label: Some("Light, \"key\"".to_string()),
+        origin: None,
+        world_id: None,
+        entity_tag: None,
+        source_table: None,
+        resource_type: None,
+        translation: [0.0; 3],
+        rotation: [0.0, 0.0, 0.0, 1.0],
+        scale: [1.0; 3],
+        kind: Some("Light".to_string()),
+        hash: None,
+        feature_renderer: Some("DeferredLights".to_string()),
+        aabb_size: None,
+    };
+
+    let csv = row.to_csv();
+    assert!(csv.contains("\"Light, \"\"key\"\"\""));
+    let columns = csv
+        .replace("\"Light, \"\"key\"\"\"", "label")
+        .split(',')
+        .count();
+    assert_eq!(columns, EntityRow::CSV_HEADER.split(',').count());
+}
@@ -1,6 +1,10 @@
-use std::time::SystemTime;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
 
 use alkahest_pm::package_manager;
+use alkahest_renderer::util::background_errors::report_background_error;
 use discord_sdk as ds;
 use lazy_static::lazy_static;
 
@@ -15,7 +19,29 @@ lazy_static! {
     ) = crossbeam::channel::bounded(2);
 }
 
-pub async fn discord_client_loop() {
+static CLIENT_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Starts the Discord client in the background, unless it's already running
+pub fn start_discord_client() {
+    if CLIENT_RUNNING.swap(true, Ordering::Relaxed) {
+        warn!("The Discord client is already running");
+        return;
+    }
+
+    tokio::spawn(async {
+        if let Err(e) = discord_client_loop().await {
+            report_background_error(
+                "Discord",
+                format!("{e:?}"),
+                Some("discord.restart".to_string()),
+            );
+        }
+
+        CLIENT_RUNNING.store(false, Ordering::Relaxed);
+    });
+}
+
+async fn discord_client_loop() -> anyhow::Result<()> {
     let activty_rx = ACTIVITY_RX_TX.1.clone();
 
     let (wheel, handler) = ds::wheel::Wheel::new(Box::new(|err| {
@@ -29,17 +55,18 @@ pub async fn discord_client_loop() {
         ds::Subscriptions::ACTIVITY,
         Box::new(handler),
     )
-    .unwrap();
+    .map_err(|e| anyhow::anyhow!("Failed to create the Discord client: {e}"))?;
 
     tracing::info!("[discord-sdk] waiting for discord handshake...");
     if let Err(e) = user.0.changed().await {
-        error!("failed to connect to Discord: {}", e);
-        return;
+        anyhow::bail!("Failed to connect to Discord: {e}");
     }
 
     let user = match &*user.0.borrow() {
         ds::wheel::UserState::Connected(user) => user.clone(),
-        ds::wheel::UserState::Disconnected(err) => panic!("failed to connect to Discord: {}", err),
+        ds::wheel::UserState::Disconnected(err) => {
+            anyhow::bail!("Failed to connect to Discord: {err}")
+        }
     };
 
     tracing::info!(
@@ -58,6 +85,8 @@ pub async fn discord_client_loop() {
             tracing::error!(error = ?e, "[discord-sdk] failed to update activity");
         }
     }
+
+    Ok(())
 }
 
 pub fn set_activity(activity: impl Into<ds::activity::ActivityArgs>) {
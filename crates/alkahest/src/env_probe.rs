@@ -0,0 +1,224 @@
+//! Environment probe capture: renders a cubemap from an arbitrary position, to save it to disk or
+//! to preview it as the reflection cubemap of the surrounding volumes
+
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use alkahest_data::dxgi::DxgiFormat;
+use alkahest_renderer::{
+    ecs::{map::CubemapVolume, transform::Transform, Scene},
+    gpu::texture::Texture,
+    handle::Handle,
+    renderer::{CapturedCubemap, Renderer},
+    resources::AppResources,
+};
+use anyhow::Context;
+use bevy_ecs::{component::Component, entity::Entity};
+use glam::Vec3;
+
+use crate::util::{dds_cubemap::write_dds_cubemap, hdr::write_hdr};
+
+#[derive(Clone)]
+pub struct EnvProbeRequest {
+    pub position: Vec3,
+    /// Width and height of each cubemap face
+    pub size: u32,
+    pub save_dds: bool,
+    pub save_hdr: bool,
+    /// Use the capture as the specular cubemap of every cubemap volume containing the position,
+    /// until the map is reloaded or the replacement is reverted
+    pub replace_cubemaps: bool,
+}
+
+pub struct EnvProbeResult {
+    pub position: Vec3,
+    /// Folder the capture is being written to
+    pub directory: Option<PathBuf>,
+    pub replaced_volumes: usize,
+}
+
+/// Environment probe captures are rendered right before the next frame, as they need exclusive
+/// access to the renderer
+#[derive(Default)]
+pub struct EnvProbeCaptures {
+    pending: Option<EnvProbeRequest>,
+    pub last_result: Option<anyhow::Result<EnvProbeResult>>,
+}
+
+impl EnvProbeCaptures {
+    pub fn request(&mut self, request: EnvProbeRequest) {
+        self.pending = Some(request);
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+/// Original specular cubemap of a volume that has been replaced by a captured one
+#[derive(Component)]
+pub struct ReplacedCubemap {
+    pub original: Handle<Texture>,
+}
+
+/// Runs a pending environment probe capture, if any
+pub fn env_probe_capture_step(renderer: &Renderer, scene: &mut Scene, resources: &AppResources) {
+    let Some(request) = resources.get_mut::<EnvProbeCaptures>().pending.take() else {
+        return;
+    };
+
+    let result = capture_env_probe(renderer, scene, resources, &request);
+    match &result {
+        Ok(r) => info!(
+            "Captured environment probe at {} ({} volume(s) replaced)",
+            r.position, r.replaced_volumes
+        ),
+        Err(e) => error!("Failed to capture environment probe: {e:?}"),
+    }
+
+    resources.get_mut::<EnvProbeCaptures>().last_result = Some(result);
+}
+
+fn capture_env_probe(
+    renderer: &Renderer,
+    scene: &mut Scene,
+    resources: &AppResources,
+    request: &EnvProbeRequest,
+) -> anyhow::Result<EnvProbeResult> {
+    let cubemap = renderer.capture_cubemap(request.position, request.size, scene, resources)?;
+
+    let replaced_volumes = if request.replace_cubemaps {
+        let texture = cubemap.create_texture(&renderer.gpu)?;
+        let handle = renderer.data.lock().asset_manager.textures.insert(texture);
+        replace_cubemaps(scene, request.position, handle)
+    } else {
+        0
+    };
+
+    let directory = if request.save_dds || request.save_hdr {
+        let directory = PathBuf::from("cubemap_captures")
+            .join(chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string());
+        let output_directory = directory.clone();
+        let (save_dds, save_hdr) = (request.save_dds, request.save_hdr);
+        std::thread::Builder::new()
+            .name("env_probe_capture".to_string())
+            .spawn(move || {
+                match write_capture(&output_directory, &cubemap, save_dds, save_hdr) {
+                    Ok(()) => info!(
+                        "Wrote environment probe capture to {}",
+                        output_directory.display()
+                    ),
+                    Err(e) => error!("Failed to write environment probe capture: {e:?}"),
+                }
+            })?;
+        Some(directory)
+    } else {
+        None
+    };
+
+    Ok(EnvProbeResult {
+        position: request.position,
+        directory,
+        replaced_volumes,
+    })
+}
+
+fn write_capture(
+    directory: &Path,
+    cubemap: &CapturedCubemap,
+    save_dds: bool,
+    save_hdr: bool,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    if save_dds {
+        let file = std::fs::File::create(directory.join("cubemap.dds"))?;
+        write_dds_cubemap(
+            BufWriter::new(file),
+            cubemap.size,
+            DxgiFormat::R16G16B16A16_FLOAT as u32,
+            8,
+            &cubemap.faces_rgba16f(),
+        )
+        .context("Failed to write DDS")?;
+    }
+
+    if save_hdr {
+        let width = cubemap.size * 4;
+        let file = std::fs::File::create(directory.join("panorama.hdr"))?;
+        write_hdr(
+            BufWriter::new(file),
+            width,
+            width / 2,
+            &cubemap.to_equirectangular(width),
+        )
+        .context("Failed to write HDR panorama")?;
+    }
+
+    Ok(())
+}
+
+/// Whether `point` is inside the (oriented) box of a cubemap volume
+fn volume_contains(transform: &Transform, volume: &CubemapVolume, point: Vec3) -> bool {
+    let local = transform.rotation.inverse() * (point - transform.translation);
+    local.abs().cmple(volume.extents.abs()).all()
+}
+
+/// Replaces the specular cubemap of all volumes containing `position`. Returns the number of
+/// replaced volumes
+fn replace_cubemaps(scene: &mut Scene, position: Vec3, texture: Handle<Texture>) -> usize {
+    let mut newly_replaced = vec![];
+    let mut count = 0;
+    for (entity, transform, mut volume, replaced) in scene
+        .query::<(
+            Entity,
+            &Transform,
+            &mut CubemapVolume,
+            Option<&ReplacedCubemap>,
+        )>()
+        .iter_mut(scene)
+    {
+        if !volume_contains(transform, &volume, position) {
+            continue;
+        }
+
+        if replaced.is_none() {
+            newly_replaced.push((entity, volume.specular_ibl.clone()));
+        }
+        volume.specular_ibl = texture.clone();
+        count += 1;
+    }
+
+    for (entity, original) in newly_replaced {
+        scene
+            .entity_mut(entity)
+            .insert(ReplacedCubemap { original });
+    }
+
+    count
+}
+
+/// Reverts all cubemap replacements. Returns the number of restored volumes
+pub fn restore_cubemaps(scene: &mut Scene) -> usize {
+    let mut restored = vec![];
+    for (entity, mut volume, replaced) in scene
+        .query::<(Entity, &mut CubemapVolume, &ReplacedCubemap)>()
+        .iter_mut(scene)
+    {
+        volume.specular_ibl = replaced.original.clone();
+        restored.push(entity);
+    }
+
+    for &entity in &restored {
+        scene.entity_mut(entity).remove::<ReplacedCubemap>();
+    }
+
+    restored.len()
+}
+
+pub fn replaced_cubemap_count(scene: &mut Scene) -> usize {
+    scene.query::<&ReplacedCubemap>().iter(scene).count()
+}
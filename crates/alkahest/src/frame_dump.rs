@@ -0,0 +1,171 @@
+//! Frame dumps: writes every render target of the last frame to disk, for inspecting the
+//! renderer's intermediate results without attaching a graphics debugger
+
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use alkahest_renderer::{
+    camera::Camera,
+    renderer::{CapturedPixels, CapturedTarget, RendererShared},
+    resources::AppResources,
+    util::image::Png,
+};
+use anyhow::Context;
+use png::{BitDepth, ColorType};
+use serde::Serialize;
+
+use crate::{maplist::MapList, util::exr::write_exr};
+
+#[derive(Serialize)]
+struct FrameDumpManifest {
+    timestamp: String,
+    map: String,
+    map_hash: String,
+    camera_position: [f32; 3],
+    /// Pitch and yaw, in degrees
+    camera_orientation: [f32; 2],
+    debug_view: String,
+    targets: Vec<FrameDumpTarget>,
+}
+
+#[derive(Serialize)]
+struct FrameDumpTarget {
+    name: String,
+    file: String,
+    format: String,
+    width: u32,
+    height: u32,
+    /// How the pixel data is stored in the file
+    encoding: &'static str,
+}
+
+/// Reads back all render targets of the last frame and writes them to a new timestamped folder
+/// in `frame_dumps/` in the background. Returns the folder the dump is written to.
+pub fn dump_frame(resources: &AppResources) -> anyhow::Result<PathBuf> {
+    let renderer = resources.get::<RendererShared>().clone();
+    let camera = resources.get::<Camera>();
+    let mut maps = resources.get_mut::<MapList>();
+    let map = maps
+        .current_map_mut()
+        .context("No map is loaded, nothing to dump")?;
+
+    let targets = renderer.capture_render_targets(&mut map.scene)?;
+
+    let now = chrono::Local::now();
+    let directory = PathBuf::from("frame_dumps").join(now.format("%Y-%m-%d_%H-%M-%S").to_string());
+    let mut manifest = FrameDumpManifest {
+        timestamp: now.to_rfc3339(),
+        map: map.name.clone(),
+        map_hash: map.hash.to_string(),
+        camera_position: camera.position().to_array(),
+        camera_orientation: camera.orientation().to_array(),
+        debug_view: renderer.settings().debug_view.to_string(),
+        targets: vec![],
+    };
+
+    let output_directory = directory.clone();
+    std::thread::Builder::new()
+        .name("frame_dump".to_string())
+        .spawn(move || {
+            let result = (|| -> anyhow::Result<()> {
+                std::fs::create_dir_all(&output_directory)
+                    .with_context(|| format!("Failed to create {}", output_directory.display()))?;
+
+                for target in &targets {
+                    manifest
+                        .targets
+                        .push(write_target(&output_directory, target).with_context(|| {
+                            format!("Failed to write render target {}", target.name)
+                        })?);
+                }
+
+                let file = std::fs::File::create(output_directory.join("manifest.json"))?;
+                serde_json::to_writer_pretty(BufWriter::new(file), &manifest)?;
+                Ok(())
+            })();
+
+            match result {
+                Ok(()) => info!(
+                    "Dumped {} render targets to {}",
+                    targets.len(),
+                    output_directory.display()
+                ),
+                Err(e) => error!("Failed to write frame dump: {e:?}"),
+            }
+        })?;
+
+    Ok(directory)
+}
+
+fn write_target(directory: &Path, target: &CapturedTarget) -> anyhow::Result<FrameDumpTarget> {
+    let (file, encoding) = match &target.pixels {
+        CapturedPixels::Uint(ids) => {
+            let file = format!("{}.png", target.name);
+            let data = ids
+                .iter()
+                .flat_map(|id| id.to_le_bytes())
+                .collect::<Vec<_>>();
+            write_png(&directory.join(&file), target, data, ColorType::Rgba)?;
+            (file, "u32_le_rgba8")
+        }
+        CapturedPixels::Float(pixels) if target.is_8bit() => {
+            let file = format!("{}.png", target.name);
+            let channels = target.channel_count();
+            let data = pixels
+                .iter()
+                .flat_map(|p| p[..channels].iter().map(|v| (v * 255.0).round() as u8))
+                .collect::<Vec<_>>();
+            let color_type = if channels == 1 {
+                ColorType::Grayscale
+            } else {
+                ColorType::Rgba
+            };
+            write_png(&directory.join(&file), target, data, color_type)?;
+            (file, "unorm8")
+        }
+        CapturedPixels::Float(pixels) => {
+            let file = format!("{}.exr", target.name);
+            let names: &[&str] = match target.channel_count() {
+                1 => &["Y"],
+                3 => &["R", "G", "B"],
+                _ => &["R", "G", "B", "A"],
+            };
+            let channels = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (*name, pixels.iter().map(|p| p[i]).collect::<Vec<f32>>()))
+                .collect::<Vec<_>>();
+
+            let writer = BufWriter::new(std::fs::File::create(directory.join(&file))?);
+            write_exr(writer, target.width, target.height, &channels)?;
+            (file, "float32")
+        }
+    };
+
+    Ok(FrameDumpTarget {
+        name: target.name.clone(),
+        file,
+        format: format!("{:?}", target.format),
+        width: target.width,
+        height: target.height,
+        encoding,
+    })
+}
+
+fn write_png(
+    path: &Path,
+    target: &CapturedTarget,
+    data: Vec<u8>,
+    color_type: ColorType,
+) -> anyhow::Result<()> {
+    let png = Png {
+        data: data.into(),
+        dimensions: [target.width as usize, target.height as usize],
+        color_type,
+        bit_depth: BitDepth::Eight,
+    };
+
+    png.write(BufWriter::new(std::fs::File::create(path)?))
+}
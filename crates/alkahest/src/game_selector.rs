@@ -47,10 +47,10 @@ pub fn select_game_installation(
 
             match event {
                 WindowEvent::Resized(new_dims) => {
-                    let swap_chain = dcs.swap_chain.as_ref().unwrap();
+                    let swap_chain = dcs.swap_chain().unwrap();
                     let _ = gui.renderer.as_mut().map(|renderer| {
                         let _ = renderer
-                            .resize_buffers(swap_chain, || {
+                            .resize_buffers(&swap_chain, || {
                                 dcs.resize_swapchain(new_dims.width, new_dims.height);
 
                                 S_OK
@@ -129,8 +129,7 @@ pub fn select_game_installation(
 
                     unsafe {
                         if dcs
-                            .swap_chain
-                            .as_ref()
+                            .swap_chain()
                             .unwrap()
                             .Present(DXGI_SWAP_EFFECT_SEQUENTIAL.0 as _, present_parameters)
                             == DXGI_STATUS_OCCLUDED
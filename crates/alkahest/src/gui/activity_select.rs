@@ -6,21 +6,55 @@ use alkahest_data::{
 use alkahest_pm::{is_pkg_redacted, package_manager};
 use anyhow::Context as _;
 use destiny_pkg::TagHash;
-use egui::{ahash::HashMapExt, Color32, Context, RichText, TextBuffer};
+use egui::{
+    ahash::HashMapExt, collapsing_header::CollapsingState, Color32, Context, RichText, TextBuffer,
+};
 use rustc_hash::FxHashMap;
 use tiger_parse::{PackageManagerExt, TigerReadable};
 use winit::window::Window;
 
 use crate::{
-    gui::context::{GuiCtx, GuiView, ViewAction},
+    config,
+    gui::{
+        context::{GuiCtx, GuiView, ViewAction},
+        util::TagHashResponseExt,
+    },
     maplist::MapList,
     resources::AppResources,
+    thumbnails::{MapThumbnails, THUMBNAIL_SIZE_LARGE, THUMBNAIL_SIZE_SMALL},
+    util::text::fuzzy_score,
 };
 
+pub struct ActivityEntry {
+    /// Localized name of the activity, not every activity has one
+    pub name: Option<String>,
+    pub code: String,
+    pub hash: TagHash,
+}
+
+impl ActivityEntry {
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{name} ({})", self.code),
+            None => self.code.clone(),
+        }
+    }
+}
+
 pub struct ActivitiesForDestination {
-    // pub destination_name: Option<String>,
+    /// Localized name of the destination, if it differs from the name of its group
+    pub destination_name: Option<String>,
     pub destination_code: String,
-    pub activities: Vec<(String, TagHash)>,
+    pub activities: Vec<ActivityEntry>,
+}
+
+impl ActivitiesForDestination {
+    pub fn label(&self) -> String {
+        match &self.destination_name {
+            Some(name) => format!("{name} ({})", self.destination_code),
+            None => self.destination_code.clone(),
+        }
+    }
 }
 
 #[derive(PartialEq)]
@@ -36,6 +70,9 @@ pub struct ActivityBrowser {
     pub maps: Vec<(String, Vec<(String, TagHash)>)>,
     show_ambient: bool,
     panel: ActivitySelectPanel,
+    search: String,
+    /// Activity matches for `search`, as (bucket, destination, activity) indices sorted by score
+    search_results: Option<(String, Vec<(usize, usize, usize)>)>,
 }
 
 impl ActivityBrowser {
@@ -67,12 +104,10 @@ impl ActivityBrowser {
                     let mut activities = vec![];
 
                     let destination_code = destination.destination_name.to_string();
-                    let bucket_name =
-                        if let Some(name) = stringmap.get(&destination.location_name.0) {
-                            name.clone()
-                        } else {
-                            destination_code.clone()
-                        };
+                    let location_name = stringmap.get(&destination.location_name.0).cloned();
+                    let bucket_name = location_name
+                        .clone()
+                        .unwrap_or_else(|| destination_code.clone());
 
                     let bucket_name = if destination_code.starts_with("gambit_") {
                         "Gambit".to_string()
@@ -82,7 +117,7 @@ impl ActivityBrowser {
                         bucket_name
                     };
 
-                    // let destination_name = stringmap.get(&destination.location_name.0).cloned();
+                    let destination_name = location_name.filter(|name| *name != bucket_name);
 
                     for activity in &destination.activities {
                         let activity_code = activity.activity_code.to_string();
@@ -103,25 +138,22 @@ impl ActivityBrowser {
                             continue;
                         };
 
-                        let activity_name =
-                            if let Some(name) = stringmap.get(&activity.activity_name.0) {
-                                format!("{name} ({activity_code})")
-                            } else {
-                                activity_code
-                            };
-
-                        if let Some(base_name) = activity_name.strip_suffix("_freeroam") {
+                        if let Some(base_name) = activity_code.strip_suffix("_freeroam") {
                             activity_patrols
                                 .push((format!("{bucket_name} ({base_name})"), activity_hash));
                         }
 
-                        activities.push((activity_name, activity_hash));
+                        activities.push(ActivityEntry {
+                            name: stringmap.get(&activity.activity_name.0).cloned(),
+                            code: activity_code,
+                            hash: activity_hash,
+                        });
                     }
 
                     activity_buckets.entry(bucket_name).or_default().push(
                         ActivitiesForDestination {
-                            // destination_name,
-                            destination_code: destination.destination_name.to_string(),
+                            destination_name,
+                            destination_code,
                             activities,
                         },
                     );
@@ -153,17 +185,56 @@ impl ActivityBrowser {
             let name_trimmed = name.trim_start_matches("The ");
             name_trimmed.to_owned()
         });
+        for (_, destinations) in &mut activity_buckets {
+            destinations.sort_by_key(|d| d.label());
+        }
 
         Self {
-            // destinations,
             activity_buckets,
             activity_patrols,
             maps,
             show_ambient: false,
             panel: ActivitySelectPanel::Activities,
+            search: String::new(),
+            search_results: None,
         }
     }
 
+    fn is_hidden(&self, activity: &ActivityEntry) -> bool {
+        !self.show_ambient && activity.code.ends_with("_ambient")
+    }
+
+    fn update_search_results(&mut self) {
+        let query = self.search.trim();
+        if query.is_empty() {
+            self.search_results = None;
+            return;
+        }
+
+        if matches!(&self.search_results, Some((q, _)) if q == query) {
+            return;
+        }
+
+        let mut results = vec![];
+        for (bi, (bucket_name, destinations)) in self.activity_buckets.iter().enumerate() {
+            for (di, destination) in destinations.iter().enumerate() {
+                let destination_label = destination.label();
+                for (ai, activity) in destination.activities.iter().enumerate() {
+                    let text = format!("{} {destination_label} {bucket_name}", activity.label());
+                    if let Some(score) = fuzzy_score(query, &text) {
+                        results.push((score, (bi, di, ai)));
+                    }
+                }
+            }
+        }
+        results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        self.search_results = Some((
+            query.to_string(),
+            results.into_iter().map(|(_, r)| r).collect(),
+        ));
+    }
+
     fn activities_panel(
         &mut self,
         ctx: &egui::Context,
@@ -176,49 +247,44 @@ impl ActivityBrowser {
                  in-game directly, but are used as the base for other activities.",
             );
 
+        self.update_search_results();
+
         egui::ScrollArea::vertical()
             .max_height(ctx.available_rect().height() * 0.9)
             .auto_shrink([false; 2])
             .show(ui, |ui| {
+                if let Some((_, results)) = &self.search_results {
+                    for &(bi, di, ai) in results {
+                        let (bucket_name, destinations) = &self.activity_buckets[bi];
+                        let destination = &destinations[di];
+                        let activity = &destination.activities[ai];
+                        if self.is_hidden(activity) {
+                            continue;
+                        }
+
+                        ui.horizontal(|ui| {
+                            activity_entry(ui, resources, activity);
+                            ui.weak(format!("{bucket_name} › {}", destination.label()));
+                        });
+                    }
+                    return;
+                }
+
                 for (bucket_name, destinations) in &self.activity_buckets {
-                    ui.collapsing(bucket_name, |ui| {
+                    remembered_collapsing(ui, bucket_name.clone(), bucket_name, |ui| {
                         for destination in destinations {
-                            ui.collapsing(&destination.destination_code, |ui| {
-                                for (activity_name, activity_hash) in &destination.activities {
-                                    if !self.show_ambient && activity_name.ends_with("_ambient") {
-                                        continue;
-                                    }
-                                    let mut activity_name = activity_name.clone();
-                                    let is_redacted = is_pkg_redacted(*activity_hash);
-                                    if is_redacted {
-                                        activity_name.insert_text("🗝 ", 0);
-                                    }
-
-                                    if activity_name.contains("_ls_")
-                                        || activity_name.ends_with("_ls")
-                                    {
-                                        activity_name.insert_text(" ", 0);
-                                    }
-                                    let mut response = ui.selectable_label(false, &activity_name);
-                                    if is_redacted {
-                                        response = response.on_hover_text(
-                                            RichText::new(
-                                                "⚠ This activity is redacted. It will not load \
-                                                 unless you have the right decryption keys",
-                                            )
-                                            .color(Color32::YELLOW),
-                                        );
-                                    }
-                                    if response.clicked() {
-                                        if let Err(e) = set_activity(resources, *activity_hash) {
-                                            error!(
-                                                "Failed to set activity \
-                                                 {activity_name}/{activity_hash}: {e:?}"
-                                            );
+                            remembered_collapsing(
+                                ui,
+                                format!("{bucket_name}/{}", destination.destination_code),
+                                destination.label(),
+                                |ui| {
+                                    for activity in &destination.activities {
+                                        if !self.is_hidden(activity) {
+                                            activity_entry(ui, resources, activity);
                                         }
                                     }
-                                }
-                            });
+                                },
+                            );
                         }
                     });
                 }
@@ -226,11 +292,16 @@ impl ActivityBrowser {
     }
 
     fn patrols_panel(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, resources: &AppResources) {
+        let query = self.search.trim();
         egui::ScrollArea::vertical()
             .max_height(ctx.available_rect().height() * 0.9)
             .auto_shrink([false; 2])
             .show(ui, |ui| {
                 for (patrol_name, activity_hash) in &self.activity_patrols {
+                    if fuzzy_score(query, patrol_name).is_none() {
+                        continue;
+                    }
+
                     if ui.selectable_label(false, patrol_name).clicked() {
                         if let Err(e) = set_activity(resources, *activity_hash) {
                             error!(
@@ -244,38 +315,29 @@ impl ActivityBrowser {
     }
 
     fn maps_panel(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, resources: &AppResources) {
+        let query = self.search.trim();
         egui::ScrollArea::vertical()
             .max_height(ctx.available_rect().height() * 0.9)
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                for (package_name, maps) in &self.maps {
-                    ui.collapsing(package_name, |ui| {
+                if !query.is_empty() {
+                    for (package_name, maps) in &self.maps {
                         for (map_name, map_hash) in maps {
-                            let mut map_label = format!("{map_name} ({map_hash})");
-                            let is_redacted = is_pkg_redacted(*map_hash);
-                            if is_redacted {
-                                map_label.insert_text("🗝 ", 0);
+                            if fuzzy_score(query, &format!("{map_name} {map_hash}")).is_some() {
+                                ui.horizontal(|ui| {
+                                    map_entry(ui, resources, map_name, *map_hash);
+                                    ui.weak(package_name);
+                                });
                             }
+                        }
+                    }
+                    return;
+                }
 
-                            let mut response = ui.selectable_label(false, &map_label);
-                            if is_redacted {
-                                response = response.on_hover_text(
-                                    RichText::new(
-                                        "⚠ This map is redacted. It will not load unless you have \
-                                         the right decryption keys",
-                                    )
-                                    .color(Color32::YELLOW),
-                                );
-                            }
-
-                            if response.clicked() {
-                                let mut maplist = resources.get_mut::<MapList>();
-
-                                maplist.add_map(resources, map_name.clone(), *map_hash);
-
-                                let new_map = maplist.maps.len() - 1;
-                                maplist.set_current_map(new_map);
-                            }
+                for (package_name, maps) in &self.maps {
+                    remembered_collapsing(ui, format!("maps/{package_name}"), package_name, |ui| {
+                        for (map_name, map_hash) in maps {
+                            map_entry(ui, resources, map_name, *map_hash);
                         }
                     });
                 }
@@ -283,6 +345,107 @@ impl ActivityBrowser {
     }
 }
 
+/// Collapsing header that remembers whether it was expanded across restarts, keyed by `key`
+fn remembered_collapsing(
+    ui: &mut egui::Ui,
+    key: String,
+    label: impl Into<egui::WidgetText>,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) {
+    let id_source = ("activity_group", &key);
+    let default_open = config::with(|c| c.activity_browser.expanded_groups.contains(&key));
+    let response = egui::CollapsingHeader::new(label)
+        .id_source(id_source)
+        .default_open(default_open)
+        .show(ui, add_contents);
+
+    if response.header_response.clicked() {
+        let id = ui.make_persistent_id(id_source);
+        let open = CollapsingState::load(ui.ctx(), id).is_some_and(|s| s.is_open());
+        config::with_mut(|c| {
+            if open {
+                c.activity_browser.expanded_groups.insert(key);
+            } else {
+                c.activity_browser.expanded_groups.remove(&key);
+            }
+        });
+    }
+}
+
+fn activity_entry(ui: &mut egui::Ui, resources: &AppResources, activity: &ActivityEntry) {
+    let mut activity_name = activity.label();
+    let is_redacted = is_pkg_redacted(activity.hash);
+    if is_redacted {
+        activity_name.insert_text("🗝 ", 0);
+    }
+
+    if activity.code.contains("_ls_") || activity.code.ends_with("_ls") {
+        activity_name.insert_text(" ", 0);
+    }
+    let mut response = ui.selectable_label(false, &activity_name);
+    if is_redacted {
+        response = response.on_hover_text(
+            RichText::new(
+                "⚠ This activity is redacted. It will not load unless you have the right \
+                 decryption keys",
+            )
+            .color(Color32::YELLOW),
+        );
+    }
+    let mut thumbnails = resources.get_mut::<MapThumbnails>();
+    response = thumbnails.on_hover_activity(response, resources, activity.hash);
+    drop(thumbnails);
+    if response.tag_info(activity.hash).clicked() {
+        if let Err(e) = set_activity(resources, activity.hash) {
+            error!(
+                "Failed to set activity {activity_name}/{}: {e:?}",
+                activity.hash
+            );
+        }
+    }
+}
+
+fn map_entry(ui: &mut egui::Ui, resources: &AppResources, map_name: &str, map_hash: TagHash) {
+    let mut map_label = format!("{map_name} ({map_hash})");
+    let is_redacted = is_pkg_redacted(map_hash);
+    if is_redacted {
+        map_label.insert_text("🗝 ", 0);
+    }
+
+    let mut response = ui
+        .horizontal(|ui| {
+            let mut thumbnails = resources.get_mut::<MapThumbnails>();
+            thumbnails
+                .show(ui, map_hash, THUMBNAIL_SIZE_SMALL)
+                .on_hover_ui(|ui| {
+                    thumbnails.show(ui, map_hash, THUMBNAIL_SIZE_LARGE);
+                });
+            drop(thumbnails);
+
+            ui.selectable_label(false, &map_label)
+        })
+        .inner;
+    if is_redacted {
+        response = response.on_hover_text(
+            RichText::new(
+                "⚠ This map is redacted. It will not load unless you have the right decryption \
+                 keys",
+            )
+            .color(Color32::YELLOW),
+        );
+    }
+    response = response.tag_info(map_hash);
+
+    if response.clicked() {
+        let mut maplist = resources.get_mut::<MapList>();
+
+        maplist.add_map(resources, map_name.to_string(), map_hash);
+
+        let new_map = maplist.maps.len() - 1;
+        maplist.set_current_map(new_map);
+    }
+}
+
 impl GuiView for ActivityBrowser {
     fn draw(
         &mut self,
@@ -301,6 +464,11 @@ impl GuiView for ActivityBrowser {
                 ui.selectable_value(&mut self.panel, ActivitySelectPanel::Patrols, "Free Roam");
                 ui.selectable_value(&mut self.panel, ActivitySelectPanel::Maps, "Maps");
             });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Search")
+                    .desired_width(f32::INFINITY),
+            );
             ui.separator();
 
             match self.panel {
@@ -0,0 +1,126 @@
+use alkahest_renderer::icons::{ICON_ALERT, ICON_BACKUP_RESTORE};
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    autosave::{restore_markup, Autosave},
+    gui::{
+        activity_select::{set_activity, CurrentActivity},
+        context::{GuiCtx, GuiView, ViewAction},
+    },
+    maplist::MapList,
+    resources::AppResources,
+};
+
+/// Offers to restore an autosave after the previous session crashed
+pub struct AutosaveRestorePrompt;
+
+impl GuiView for AutosaveRestorePrompt {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut autosave = resources.get_mut::<Autosave>();
+        let Some(crash) = &autosave.crash else {
+            return None;
+        };
+
+        let current_activity = resources.get::<CurrentActivity>().0;
+        let maps_loading = resources.get::<MapList>().count_loading() > 0;
+
+        let mut dismiss = false;
+        let mut load_activity = None;
+        let mut restore = None;
+        egui::Window::new("Restore from autosave")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                for line in &crash.evidence {
+                    ui.label(RichText::new(format!("{ICON_ALERT} {line}")).color(Color32::YELLOW));
+                }
+                ui.label("User-created markup from the previous session can be restored.");
+                ui.separator();
+
+                egui::Grid::new("autosaves")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, file) in crash.autosaves.iter().enumerate() {
+                            let markup = &file.markup;
+                            ui.label(markup.timestamp.format("%Y-%m-%d %H:%M:%S").to_string())
+                                .on_hover_text(file.path.display().to_string());
+                            ui.label(format!(
+                                "{} entities on {} map(s), {} route(s)",
+                                markup.entity_count(),
+                                markup.maps.len(),
+                                markup.routes.len()
+                            ))
+                            .on_hover_text(
+                                markup
+                                    .maps
+                                    .iter()
+                                    .map(|m| format!("{} ({})", m.map_name, m.entities.len()))
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                            );
+
+                            let activity = markup.activity_hash.map(TagHash);
+                            if activity.is_some() && activity != current_activity {
+                                if ui
+                                    .add_enabled(!maps_loading, egui::Button::new("Load activity"))
+                                    .on_hover_text(
+                                        "Load the activity this autosave was made in, then restore",
+                                    )
+                                    .clicked()
+                                {
+                                    load_activity = activity;
+                                }
+                            } else if ui
+                                .add_enabled(
+                                    !maps_loading,
+                                    egui::Button::new(format!("{ICON_BACKUP_RESTORE} Restore")),
+                                )
+                                .on_disabled_hover_text("Wait for the maps to finish loading")
+                                .clicked()
+                            {
+                                restore = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                if ui.button("Dismiss").clicked() {
+                    dismiss = true;
+                }
+            });
+
+        if let Some(i) = restore {
+            let crash = autosave.crash.take().unwrap();
+            let result = restore_markup(
+                &mut resources.get_mut::<MapList>(),
+                &crash.autosaves[i].markup,
+            );
+            info!(
+                "Restored {} object(s) from autosave, {} skipped as their map is not loaded",
+                result.restored, result.skipped
+            );
+        } else if dismiss {
+            autosave.crash = None;
+        }
+
+        drop(autosave);
+        if let Some(activity) = load_activity {
+            if let Err(e) = set_activity(resources, activity) {
+                error!("Failed to load activity {activity}: {e:?}");
+            }
+        }
+
+        None
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
     },
     maplist::{MapList, MapLoadState},
     resources::AppResources,
+    thumbnails::{MapThumbnails, THUMBNAIL_SIZE_LARGE, THUMBNAIL_SIZE_SMALL},
 };
 
 pub struct BottomBar;
@@ -29,6 +30,8 @@ impl GuiView for BottomBar {
             if !maplist.maps.is_empty() {
                 let mut current_map = maplist.current_map_index();
 
+                let package_sources = alkahest_pm::package_sources();
+
                 let amount_loaded = maplist.count_loaded();
                 let combo_postlabel = if maplist.count_loading() != 0 {
                     format!(
@@ -41,33 +44,100 @@ impl GuiView for BottomBar {
                     "".to_string()
                 };
                 ui.horizontal(|ui| {
-                    let map_changed = egui::ComboBox::from_label("Map")
-                        .width(192.0)
-                        .show_index(ui, &mut current_map, maplist.maps.len(), |i| {
-                            let map = &maplist.maps[i];
-                            let (mut icon, color) = match map.load_state {
-                                MapLoadState::Unloaded => (ICON_CIRCLE_OUTLINE, Color32::GRAY),
-                                MapLoadState::Loading => {
-                                    (LoadingIcon::Circle.get_frame(), Color32::WHITE)
-                                }
-                                MapLoadState::Loaded => (ICON_CIRCLE, Color32::WHITE),
-                                MapLoadState::Error(_) => (ICON_ALERT_CIRCLE_OUTLINE, Color32::RED),
-                            };
-
-                            if maplist.current_map_index() == i
-                                && map.load_state == MapLoadState::Loaded
-                            {
-                                icon = ICON_CHECK_CIRCLE;
+                    let map_label = |i: usize| {
+                        let map = &maplist.maps[i];
+                        let (mut icon, color) = match map.load_state {
+                            MapLoadState::Unloaded => (ICON_CIRCLE_OUTLINE, Color32::GRAY),
+                            MapLoadState::Loading => {
+                                (LoadingIcon::Circle.get_frame(), Color32::WHITE)
+                            }
+                            MapLoadState::Loaded => (ICON_CIRCLE, Color32::WHITE),
+                            MapLoadState::Error(_) => (ICON_ALERT_CIRCLE_OUTLINE, Color32::RED),
+                        };
+
+                        if maplist.current_map_index() == i
+                            && map.load_state == MapLoadState::Loaded
+                        {
+                            icon = ICON_CHECK_CIRCLE;
+                        }
+
+                        let mut label = format!("{icon} {}", map.name);
+                        // Maps from the primary source are left unmarked
+                        if map.source != 0 {
+                            if let Some(source) = package_sources.get(map.source) {
+                                label += &format!(" [{}]", source.name);
                             }
+                        }
 
-                            RichText::new(format!("{icon} {}", maplist.maps[i].name)).color(color)
-                        })
-                        .changed();
+                        RichText::new(label).color(color)
+                    };
+
+                    let mut map_changed = false;
+                    let mut thumbnails = resources.get_mut::<MapThumbnails>();
+                    egui::ComboBox::from_label("Map")
+                        .width(192.0)
+                        .selected_text(map_label(current_map))
+                        .show_ui(ui, |ui| {
+                            for i in 0..maplist.maps.len() {
+                                ui.horizontal(|ui| {
+                                    thumbnails
+                                        .show(ui, maplist.maps[i].hash, THUMBNAIL_SIZE_SMALL)
+                                        .on_hover_ui(|ui| {
+                                            thumbnails.show(
+                                                ui,
+                                                maplist.maps[i].hash,
+                                                THUMBNAIL_SIZE_LARGE,
+                                            );
+                                        });
+                                    if ui
+                                        .selectable_label(current_map == i, map_label(i))
+                                        .clicked()
+                                    {
+                                        current_map = i;
+                                        map_changed = true;
+                                    }
+                                });
+                            }
+                        });
+                    drop(thumbnails);
 
                     ui.label(combo_postlabel);
 
                     ui.checkbox(&mut maplist.load_all_maps, "Load all maps");
 
+                    if package_sources.len() > 1 {
+                        if let Some(map) = maplist.current_map_mut() {
+                            let mut source = map.source;
+                            let source_changed = egui::ComboBox::from_label("Source")
+                                .width(128.0)
+                                .show_index(ui, &mut source, package_sources.len(), |i| {
+                                    package_sources[i].name.clone()
+                                })
+                                .on_hover_text(
+                                    package_sources
+                                        .get(map.source)
+                                        .map(|s| s.directory.display().to_string())
+                                        .unwrap_or_default(),
+                                )
+                                .changed();
+
+                            if source_changed {
+                                map.set_source(source);
+                            }
+                        }
+
+                        if maplist.is_switching_source() {
+                            ui.label(format!(
+                                "{} Switching package source",
+                                LoadingIcon::Circle.get_frame()
+                            ))
+                            .on_hover_text(
+                                "Waiting for the packages to be read and for running loads to \
+                                 finish. Only one source can be loaded at a time",
+                            );
+                        }
+                    }
+
                     if map_changed {
                         maplist.set_current_map(current_map);
                     }
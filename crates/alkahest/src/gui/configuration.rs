@@ -1,19 +1,34 @@
 use alkahest_renderer::{
-    camera::{Camera, CameraProjection},
-    ecs::tags::{NodeFilter, NodeFilterSet},
-    icons::{ICON_CLIPBOARD, ICON_CURSOR_DEFAULT, ICON_EYE},
-    renderer::{RenderDebugView, RenderFeatureVisibility, RendererShared, ShadowQuality},
+    camera::{Camera, CameraMode, CameraProjection},
+    ecs::{
+        common::ResourceOrigin,
+        tags::{NodeFilter, NodeFilterSet},
+        transform::Transform,
+        Scene,
+    },
+    icons::{ICON_CLIPBOARD, ICON_CROSSHAIRS, ICON_CURSOR_DEFAULT, ICON_EYE, ICON_IMAGE},
+    postprocess::EffectQuality,
+    renderer::{
+        AntiAliasing, OutputMode, RenderDebugView, RenderFeatureVisibility, RendererSettings,
+        RendererShared, ShadowQuality, SkyIsolation, SkyReplacement, Tonemapper,
+    },
     util::text::StringExt,
 };
 use egui::{Context, RichText, Rounding, Widget};
+use rustc_hash::FxHashMap;
 use strum::IntoEnumIterator;
 use transform_gizmo_egui::{EnumSet, GizmoMode};
 use winit::window::Window;
 
 use super::console;
 use crate::{
-    config,
-    gui::context::{GuiCtx, GuiView, ViewAction},
+    config::{self, GameSeason, PackageSourceConfig, ToastLevel},
+    gui::{
+        context::{GuiCtx, GuiView, ViewAction},
+        hotkeys::HotkeyAction,
+    },
+    investment::{self, InvestmentStatus},
+    maplist::MapList,
     resources::AppResources,
 };
 
@@ -62,6 +77,42 @@ impl GuiView for RenderSettingsPanel {
 
             ui.add_space(4.0);
 
+            let mut mode = camera.mode();
+            egui::ComboBox::from_label("Mode")
+                .selected_text(mode.to_string())
+                .show_ui(ui, |ui| {
+                    for m in CameraMode::iter() {
+                        ui.selectable_value(&mut mode, m, m.to_string());
+                    }
+                })
+                .response
+                .on_hover_text(format!(
+                    "Walk mode collides with the terrain and static geometry of the map. \
+                     Toggle with {}.\nOrbit mode circles around the selected object or the point \
+                     under the crosshair, scroll to zoom. Toggle with {}",
+                    HotkeyAction::ToggleWalkMode.shortcut_text(ctx),
+                    HotkeyAction::Orbit.shortcut_text(ctx)
+                ));
+            camera.set_mode(mode);
+
+            if mode == CameraMode::Walk {
+                ui.horizontal(|ui| {
+                    egui::DragValue::new(&mut camera.walk.eye_height)
+                        .range(0.1f32..=10.0)
+                        .speed(0.01)
+                        .suffix("m")
+                        .ui(ui);
+                    ui.label("Eye height");
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut camera.walk.gravity, "Gravity");
+                    ui.add_enabled_ui(camera.walk.gravity, |ui| {
+                        ui.checkbox(&mut camera.walk.jump, "Jump")
+                            .on_hover_text("Jump with space");
+                    });
+                });
+            }
+
             ui.horizontal(|ui| {
                 egui::DragValue::new(&mut camera.speed_mul)
                     .range(0.05f32..=25.0)
@@ -103,6 +154,122 @@ impl GuiView for RenderSettingsPanel {
                     ui.checkbox(&mut c.renderer.vsync, "VSync");
                     ui.checkbox(&mut c.renderer.matcap, "Matcap");
                     ui.checkbox(&mut c.renderer.draw_selection_outline, "Selection Outline");
+                    ui.add_enabled_ui(c.renderer.draw_selection_outline, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(&mut c.renderer.selection_outline_color);
+                            ui.label("Color");
+                            if ui.small_button("Reset").clicked() {
+                                c.renderer.selection_outline_color =
+                                    RendererSettings::default().selection_outline_color;
+                            }
+                        });
+                        ui.add_enabled_ui(!c.renderer.selection_fill_tint, |ui| {
+                            ui.add(
+                                egui::Slider::new(&mut c.renderer.selection_outline_width, 1..=8)
+                                    .text("Thickness")
+                                    .suffix(" px"),
+                            );
+                        });
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.selection_pulse_speed, 0.0..=4.0)
+                                .text("Pulse Speed")
+                                .suffix(" Hz"),
+                        )
+                        .on_hover_text("0 keeps the outline steady");
+                        ui.checkbox(&mut c.renderer.selection_fill_tint, "Fill Tint")
+                            .on_hover_text(
+                                "Tint the whole selected entity instead of drawing an outline, \
+                                 easier to spot against bright skies",
+                            );
+                    });
+                    ui.checkbox(&mut c.renderer.draw_search_highlight, "Search Highlight")
+                        .on_hover_text(
+                            "Outline entities matching the outliner search or entity query",
+                        );
+                    ui.checkbox(&mut c.renderer.draw_utilities, "Utilities");
+                    ui.checkbox(&mut c.renderer.draw_volume_bounds, "Lighting Volumes")
+                        .on_hover_text(
+                            "Draw the bounds of cubemap volumes and lights. The volumes the \
+                             camera is in are highlighted",
+                        );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut c.renderer.wireframe_overlay, "Wireframe Overlay")
+                            .on_hover_text(
+                                "Draw the wireframe of every mesh on top of the scene. Individual \
+                                 entities can be toggled from the inspector",
+                            );
+                        ui.add(
+                            egui::DragValue::new(&mut c.renderer.wireframe_overlay_bias)
+                                .range(0..=100)
+                                .prefix("Bias: "),
+                        )
+                        .on_hover_text(
+                            "Higher values keep the wireframe from being hidden by its own \
+                             surface, but let it show through nearby geometry",
+                        );
+                    });
+                    ui.checkbox(&mut c.renderer.detail_fade, "Detail Fade")
+                        .on_hover_text(
+                            "Hide decorators beyond roughly the distance at which the game \
+                             fades them out. The distance is estimated from the size of the \
+                             decorator models, not read from the game data",
+                        );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut c.renderer.auto_lod, "Automatic LOD")
+                            .on_hover_text(
+                                "Draw lower detail levels of models that cover less of the \
+                                 screen. Captures always use the highest detail",
+                            );
+                        ui.add_enabled(
+                            c.renderer.auto_lod,
+                            egui::Slider::new(&mut c.renderer.lod_bias, -2.0..=2.0)
+                                .text("Bias"),
+                        )
+                        .on_hover_text(
+                            "Positive values switch to lower detail levels sooner, negative \
+                             values later",
+                        );
+                    });
+                    ui.checkbox(&mut c.renderer.lod_debug_colors, "Color by LOD")
+                        .on_hover_text(
+                            "Color models by their selected LOD level: green (highest detail), \
+                             yellow, orange, red (lowest detail)",
+                        );
+                    ui.checkbox(
+                        &mut c.renderer.terrain_material_colors,
+                        "Color terrain by material",
+                    )
+                    .on_hover_text(
+                        "Color every terrain patch by the material it is drawn with. The terrain \
+                         inspector lists the material and textures behind each color. Blend \
+                         weights between materials are not shown",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut c.renderer.impostors, "Impostors").on_hover_text(
+                            "Draw distant groups of static instances as billboards, which are \
+                             captured once the map has finished loading. Saves a lot of draw \
+                             calls on large maps",
+                        );
+                        ui.add_enabled(
+                            c.renderer.impostors,
+                            egui::Slider::new(&mut c.renderer.impostor_distance, 100.0..=2000.0)
+                                .logarithmic(true)
+                                .text("Distance")
+                                .suffix("m"),
+                        )
+                        .on_hover_text(
+                            "A group is replaced once its closest instance is this far away",
+                        );
+                    });
+                    ui.checkbox(
+                        &mut c.renderer.light_occlusion_culling,
+                        "Light Occlusion Culling",
+                    )
+                    .on_hover_text(
+                        "Skip lights whose volume doesn't reach any visible geometry, tested \
+                         against a grid of view clusters. Speeds up maps with many hidden lights, \
+                         every remaining light is still drawn on its own",
+                    );
 
                     if egui::ComboBox::from_label("Shadows")
                         .selected_text(c.renderer.shadow_quality.to_string().split_pascalcase())
@@ -124,10 +291,49 @@ impl GuiView for RenderSettingsPanel {
                     {
                         console::queue_command("recreate_shadowmaps", &[]);
                     }
+                    ui.horizontal(|ui| {
+                        egui::DragValue::new(&mut c.renderer.texture_budget_mb)
+                            .range(0..=32768)
+                            .speed(16)
+                            .custom_formatter(|v, _| {
+                                if v == 0.0 {
+                                    "Unlimited".to_string()
+                                } else {
+                                    format!("{v} MiB")
+                                }
+                            })
+                            .ui(ui);
+                        ui.label("Texture budget");
+                    })
+                    .response
+                    .on_hover_ui(|ui| {
+                        let renderer = resources.get::<RendererShared>();
+                        let data = renderer.data.lock();
+                        ui.label(format!(
+                            "{:.1} MiB of textures loaded ({} at reduced quality)\nTextures that \
+                             haven't been used recently are unloaded when going over the budget",
+                            data.asset_manager.texture_memory() as f64 / (1024.0 * 1024.0),
+                            data.asset_manager.reduced_texture_count()
+                        ));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut c.renderer.frame_budget, "Frame Budget")
+                            .on_hover_text(
+                                "Turn down SSAO, shadow updates, decorators and cubemaps (in that \
+                                 order) when the frame rate drops below the target, and restore \
+                                 them once there's room again",
+                            );
+                        ui.add_enabled(
+                            c.renderer.frame_budget,
+                            egui::Slider::new(&mut c.renderer.frame_budget_fps, 15.0..=144.0)
+                                .text("Target")
+                                .suffix(" FPS"),
+                        );
+                    });
                     ui.checkbox(&mut c.renderer.ssao, "SSAO");
                     ui.collapsing("SSAO Settings", |ui| {
                         let renderer = resources.get::<RendererShared>();
-                        let ssao_data = renderer.ssao.scope.data();
+                        let mut ssao_data = renderer.ssao.scope.data();
                         ui.horizontal(|ui| {
                             ui.label("Radius");
                             egui::DragValue::new(&mut ssao_data.radius)
@@ -146,21 +352,66 @@ impl GuiView for RenderSettingsPanel {
                                 .ui(ui);
                         });
                     });
+                    ui.checkbox(&mut c.renderer.ssgi, "⚠ SSGI")
+                        .on_hover_text(
+                            "Experimental screen-space global illumination. Approximates bounce \
+                             lighting from what is on screen, which brightens interiors that \
+                             rely on baked lighting",
+                        );
+                    ui.add_enabled_ui(c.renderer.ssgi, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.ssgi_radius, 0.5..=16.0)
+                                .text("Radius")
+                                .suffix(" m"),
+                        )
+                        .on_hover_text("Maximum distance bounce light is gathered from");
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.ssgi_intensity, 0.0..=4.0)
+                                .text("Intensity"),
+                        );
+                    });
+                    ui.checkbox(&mut c.renderer.ssr, "Screen-Space Reflections")
+                        .on_hover_text(
+                            "Reflects what is on screen, falling back to the cubemaps where \
+                             nothing is hit",
+                        );
+                    ui.add_enabled_ui(c.renderer.ssr, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.ssr_max_distance, 1.0..=200.0)
+                                .text("Max Distance")
+                                .suffix(" m")
+                                .logarithmic(true),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.ssr_max_roughness, 0.05..=1.0)
+                                .text("Max Roughness"),
+                        )
+                        .on_hover_text("Rougher surfaces only reflect the cubemaps");
+                        quality_combo(ui, "SSR Quality", &mut c.renderer.ssr_quality);
+                    });
                     // ui.checkbox(&mut c.renderer.depth_prepass, "⚠ Depth Prepass");
 
                     render_feat_vis(ui, "Crosshair", &mut c.visual.draw_crosshair);
+                    render_feat_vis(ui, "Minimap", &mut c.visual.minimap);
                     render_feat_vis(ui, "Node Visualization", &mut c.visual.node_nametags);
                     ui.collapsing("Node filters", |ui| {
                         ui.checkbox(
                             &mut c.visual.node_nametags_named_only,
                             "Only show named nodes",
                         );
+                        let (filter_counts, origin_counts) = resources
+                            .get::<MapList>()
+                            .current_map()
+                            .map(|m| count_nodes(&m.scene))
+                            .unwrap_or_default();
+
                         let mut filters = resources.get_mut::<NodeFilterSet>();
                         for filter in NodeFilter::iter() {
                             let filter_text = RichText::new(format!(
-                                "{} {}",
+                                "{} {} ({})",
                                 filter.icon(),
-                                filter.to_string().split_pascalcase()
+                                filter.to_string().split_pascalcase(),
+                                filter_counts.get(&filter).copied().unwrap_or_default()
                             ))
                             .color(filter.color());
 
@@ -175,6 +426,29 @@ impl GuiView for RenderSettingsPanel {
                                 }
                             }
                         }
+
+                        ui.separator();
+                        ui.label(RichText::new("Origins").strong());
+                        ui.checkbox(&mut c.visual.node_color_by_origin, "Color by origin");
+                        for origin in ResourceOrigin::iter() {
+                            let origin_text = RichText::new(format!(
+                                "[{}] {} ({})",
+                                origin.short_name(),
+                                origin.to_string().split_pascalcase(),
+                                origin_counts.get(&origin).copied().unwrap_or_default()
+                            ))
+                            .color(origin.color());
+
+                            let mut checked =
+                                c.visual.node_origin_filters.contains(&origin.to_string());
+                            if ui.checkbox(&mut checked, origin_text).changed() {
+                                if checked {
+                                    c.visual.node_origin_filters.insert(origin.to_string());
+                                } else {
+                                    c.visual.node_origin_filters.remove(&origin.to_string());
+                                }
+                            }
+                        }
                     });
 
                     egui::ComboBox::from_label("Debug View")
@@ -188,6 +462,215 @@ impl GuiView for RenderSettingsPanel {
                                 );
                             }
                         });
+
+                    if c.renderer.debug_view == RenderDebugView::Overdraw {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.overdraw_max, 2..=64)
+                                .text("Overdraw Range"),
+                        )
+                        .on_hover_text(
+                            "Fragment count shown in red, from blue for a single fragment. Pixels \
+                             with more fragments are shown in white",
+                        );
+                    }
+
+                    if c.renderer.debug_view == RenderDebugView::LightVolumeCounts {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.light_volume_heatmap_max, 2..=64)
+                                .text("Light Count Range"),
+                        )
+                        .on_hover_text(
+                            "Light count shown in red, from blue for a single light. Clusters \
+                             with more lights are shown in white",
+                        );
+                        if let Some(stats) =
+                            resources.get::<RendererShared>().light_occlusion_stats()
+                        {
+                            ui.label(format!(
+                                "{} lights in view, {} outside of it, up to {} per cluster",
+                                stats.lights, stats.culled, stats.max_lights_per_cluster
+                            ));
+                        }
+                    }
+
+                    ui.add_enabled_ui(!c.renderer.debug_view.is_gamma_converter(), |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.debug_view_split, 0.0..=1.0)
+                                .text("Debug View Compare"),
+                        )
+                        .on_hover_text(
+                            "Portion of the screen (from the left) showing the debug view, the \
+                             rest shows the final image",
+                        );
+                    });
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Output").heading(), |ui| {
+                    let hdr_info = resources.get::<RendererShared>().gpu.display_hdr_info();
+                    egui::ComboBox::from_label("Output Mode")
+                        .selected_text(c.renderer.output_mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in OutputMode::iter() {
+                                ui.selectable_value(
+                                    &mut c.renderer.output_mode,
+                                    mode,
+                                    mode.to_string(),
+                                );
+                            }
+                        });
+                    match hdr_info {
+                        Some((true, peak)) => {
+                            ui.label(format!("HDR display detected ({peak:.0} nits peak)"));
+                        }
+                        Some((false, _)) => {
+                            ui.label(
+                                RichText::new(
+                                    "HDR is not enabled for this display, HDR output modes won't be \
+                                     available",
+                                )
+                                .color(egui::Color32::YELLOW),
+                            );
+                        }
+                        None => {}
+                    }
+
+                    egui::ComboBox::from_label("Tonemapper")
+                        .selected_text(c.renderer.tonemapper.to_string().split_pascalcase())
+                        .show_ui(ui, |ui| {
+                            for tonemapper in Tonemapper::iter() {
+                                ui.selectable_value(
+                                    &mut c.renderer.tonemapper,
+                                    tonemapper,
+                                    tonemapper.to_string().split_pascalcase(),
+                                );
+                            }
+                        });
+                    ui.add(
+                        egui::Slider::new(&mut c.renderer.exposure, -8.0..=8.0)
+                            .text("Exposure")
+                            .suffix(" EV"),
+                    );
+
+                    egui::ComboBox::from_label("Anti-Aliasing")
+                        .selected_text(c.renderer.anti_aliasing.to_string().to_uppercase())
+                        .show_ui(ui, |ui| {
+                            for mode in AntiAliasing::iter() {
+                                ui.selectable_value(
+                                    &mut c.renderer.anti_aliasing,
+                                    mode,
+                                    mode.to_string().to_uppercase(),
+                                );
+                            }
+                        });
+                    if c.renderer.anti_aliasing == AntiAliasing::Fxaa {
+                        ui.checkbox(&mut c.renderer.fxaa_noise, "FXAA Noise");
+                    }
+
+                    ui.add_enabled_ui(c.renderer.output_mode.is_hdr(), |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.hdr_paper_white, 80.0..=500.0)
+                                .text("Paper White")
+                                .suffix(" nits"),
+                        )
+                        .on_hover_text("Brightness of white UI elements and the SDR film curve");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut c.renderer.hdr_peak_luminance,
+                                    400.0..=10000.0,
+                                )
+                                .logarithmic(true)
+                                .text("Peak Brightness")
+                                .suffix(" nits"),
+                            );
+                            if let Some((true, peak)) = hdr_info {
+                                if ui.button("Use display peak").clicked() {
+                                    c.renderer.hdr_peak_luminance = peak;
+                                }
+                            }
+                        });
+                    });
+
+                    ui.checkbox(&mut c.renderer.stereo, "Stereo (Side by Side)")
+                        .on_hover_text(
+                            "Render the scene once for each eye, for previewing maps in VR video \
+                             players. Selecting entities by clicking is unavailable in this mode",
+                        );
+                    ui.add_enabled_ui(c.renderer.stereo, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.stereo_ipd, 0.0..=0.5)
+                                .text("Eye Distance")
+                                .suffix(" m"),
+                        )
+                        .on_hover_text(
+                            "Larger distances make the world look smaller. The average human eye \
+                             distance is 0.064m",
+                        );
+                        ui.checkbox(&mut c.renderer.stereo_swap_eyes, "Swap Eyes")
+                            .on_hover_text("Show the right eye on the left, for cross-eyed viewing");
+                    });
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Post Effects").heading(), |ui| {
+                    ui.checkbox(&mut c.renderer.dof, "Depth of Field");
+                    ui.add_enabled_ui(c.renderer.dof, |ui| {
+                        ui.checkbox(&mut c.renderer.dof_autofocus, "Autofocus")
+                            .on_hover_text("Focus on whatever is under the crosshair");
+                        ui.add_enabled_ui(!c.renderer.dof_autofocus, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::Slider::new(
+                                        &mut c.renderer.dof_focus_distance,
+                                        0.1..=1000.0,
+                                    )
+                                    .logarithmic(true)
+                                    .text("Focus Distance")
+                                    .suffix(" m"),
+                                );
+                                if ui
+                                    .button(ICON_CROSSHAIRS.to_string())
+                                    .on_hover_text("Focus on the crosshair")
+                                    .clicked()
+                                {
+                                    let (distance, _) = resources
+                                        .get::<RendererShared>()
+                                        .data
+                                        .lock()
+                                        .gbuffers
+                                        .depth_buffer_distance_pos_center(&camera);
+                                    if distance.is_finite() {
+                                        c.renderer.dof_focus_distance = distance;
+                                    }
+                                }
+                            });
+                        });
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.dof_blur_size, 0.0..=0.05)
+                                .text("Blur Size"),
+                        )
+                        .on_hover_text(
+                            "Blur radius of geometry far out of focus, relative to the image \
+                             height",
+                        );
+                        quality_combo(ui, "DoF Quality", &mut c.renderer.dof_quality);
+                    });
+
+                    ui.checkbox(&mut c.renderer.bloom, "Bloom");
+                    ui.add_enabled_ui(c.renderer.bloom, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.bloom_threshold, 0.0..=10.0)
+                                .text("Threshold"),
+                        )
+                        .on_hover_text("Brightness at which pixels start to glow");
+                        ui.add(
+                            egui::Slider::new(&mut c.renderer.bloom_intensity, 0.0..=2.0)
+                                .text("Intensity"),
+                        );
+                        quality_combo(ui, "Bloom Quality", &mut c.renderer.bloom_quality)
+                            .on_hover_text("Higher qualities spread the glow out further");
+                    });
                 });
 
                 ui.separator();
@@ -209,9 +692,74 @@ impl GuiView for RenderSettingsPanel {
                         "⚠ Global Lighting",
                         &mut c.renderer.feature_global_lighting,
                     );
-                    render_feat_vis(ui, "FXAA", &mut c.renderer.feature_fxaa);
-                    if c.renderer.feature_fxaa {
-                        render_feat_vis(ui, "FXAA Noise", &mut c.renderer.fxaa_noise);
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Sky Isolation");
+                        for isolation in SkyIsolation::iter() {
+                            ui.selectable_value(
+                                &mut c.renderer.sky_isolation,
+                                isolation,
+                                isolation.to_string(),
+                            );
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Sky Replacement");
+                        for replacement in SkyReplacement::iter() {
+                            ui.selectable_value(
+                                &mut c.renderer.sky_replacement,
+                                replacement,
+                                replacement.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Replace the background with a flat color (for chroma keying) or an \
+                         HDRI. Sky objects are hidden while the sky is replaced",
+                    );
+                    match c.renderer.sky_replacement {
+                        SkyReplacement::None => {}
+                        SkyReplacement::Color => {
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_rgb(&mut c.renderer.sky_replacement_color);
+                                ui.label("Color");
+                            });
+                        }
+                        SkyReplacement::Hdri => {
+                            ui.horizontal(|ui| {
+                                let name = c
+                                    .renderer
+                                    .sky_hdri_path
+                                    .as_deref()
+                                    .and_then(|p| std::path::Path::new(p).file_name())
+                                    .map_or("None".into(), |f| f.to_string_lossy());
+                                ui.label(format!("HDRI: {name}"));
+                                if ui.button("Browse…").clicked() {
+                                    match native_dialog::FileDialog::new()
+                                        .add_filter("Radiance HDR", &["hdr"])
+                                        .show_open_single_file()
+                                    {
+                                        Ok(Some(path)) => {
+                                            c.renderer.sky_hdri_path =
+                                                Some(path.to_string_lossy().into_owned());
+                                        }
+                                        Ok(None) => {}
+                                        Err(e) => error!("Failed to open the file dialog: {e}"),
+                                    }
+                                }
+                            });
+                            ui.add(
+                                egui::Slider::new(&mut c.renderer.sky_hdri_rotation, 0.0..=360.0)
+                                    .text("Rotation")
+                                    .suffix("°"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut c.renderer.sky_hdri_exposure, -8.0..=8.0)
+                                    .text("Exposure"),
+                            );
+                        }
                     }
                 });
 
@@ -222,6 +770,288 @@ impl GuiView for RenderSettingsPanel {
                     ui.checkbox(&mut c.renderer.stage_decals_additive, "Decals (additive)");
                 });
 
+                ui.separator();
+                ui.collapsing(RichText::new("Console").heading(), |ui| {
+                    ui.label("Startup commands, executed after a map is loaded (one per line)");
+                    let mut startup_commands = c.console.startup_commands.join("\n");
+                    if egui::TextEdit::multiline(&mut startup_commands)
+                        .code_editor()
+                        .desired_rows(4)
+                        .ui(ui)
+                        .changed()
+                    {
+                        c.console.startup_commands =
+                            startup_commands.split('\n').map(str::to_string).collect();
+                    }
+
+                    if ui.button("Run now").clicked() {
+                        console::queue_command("startup.run", &[]);
+                    }
+
+                    egui::ComboBox::from_label("Notifications")
+                        .selected_text(format!("{:?}", c.console.toast_level))
+                        .show_ui(ui, |ui| {
+                            for level in [ToastLevel::Off, ToastLevel::Errors, ToastLevel::Warnings]
+                            {
+                                ui.selectable_value(
+                                    &mut c.console.toast_level,
+                                    level,
+                                    format!("{level:?}"),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Log messages shown as notifications in the top right corner",
+                        );
+
+                    ui.label(
+                        RichText::new(format!(
+                            "{} alias(es) defined, manage them with the 'alias' and 'unalias' \
+                             commands",
+                            c.console.aliases.len()
+                        ))
+                        .weak(),
+                    );
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Map Prefetch").heading(), |ui| {
+                    ui.checkbox(&mut c.prefetch.enabled, "Prefetch other maps")
+                        .on_hover_text(
+                            "Load the other maps of the activity in the background once the \
+                             current map has loaded, so switching maps is near-instant",
+                        );
+                    ui.add_enabled_ui(c.prefetch.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            egui::DragValue::new(&mut c.prefetch.memory_cap_mb)
+                                .range(256..=32768)
+                                .speed(16)
+                                .suffix(" MiB")
+                                .ui(ui);
+                            ui.label("Memory cap");
+                        })
+                        .response
+                        .on_hover_text(
+                            "Prefetching stops once the loaded textures and geometry use more GPU \
+                             memory than this",
+                        );
+                    });
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Package Sources").heading(), |ui| {
+                    ui.label(
+                        "Additional package directories, such as an archived snapshot next to \
+                         the live install. The source of the current map can be picked in the \
+                         status bar",
+                    );
+                    if let Some(primary) = alkahest_pm::package_source(0) {
+                        ui.label(format!("Primary: {}", primary.directory.display()));
+                    }
+
+                    let mut remove = None;
+                    for (i, source) in c.package_sources.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(&source.name).strong());
+                            ui.label(format!("{:?}", source.season).split_pascalcase());
+                            ui.label(&source.directory);
+                            if ui
+                                .button("Remove")
+                                .on_hover_text("Takes effect after a restart")
+                                .clicked()
+                            {
+                                remove = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        c.package_sources.remove(i);
+                    }
+
+                    let new_source_id = ui.id().with("new_package_source");
+                    let mut new_source = ui.data_mut(|d| {
+                        d.get_temp::<PackageSourceConfig>(new_source_id)
+                            .unwrap_or_default()
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        egui::TextEdit::singleline(&mut new_source.name)
+                            .desired_width(96.0)
+                            .ui(ui);
+                        egui::ComboBox::from_id_source("new_package_source_season")
+                            .selected_text(format!("{:?}", new_source.season).split_pascalcase())
+                            .show_ui(ui, |ui| {
+                                for season in GameSeason::iter() {
+                                    ui.selectable_value(
+                                        &mut new_source.season,
+                                        season,
+                                        format!("{season:?}").split_pascalcase(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Only map data entries and cubemap volumes are read with the \
+                                 layouts of older seasons so far, other data may fail to load",
+                            );
+                        if ui.button("Browse…").clicked() {
+                            match native_dialog::FileDialog::new().show_open_single_dir() {
+                                Ok(Some(path)) => {
+                                    new_source.directory = path.to_string_lossy().into_owned();
+                                }
+                                Ok(None) => {}
+                                Err(e) => error!("Failed to open the folder dialog: {e}"),
+                            }
+                        }
+                        ui.label(&new_source.directory);
+                    });
+
+                    let can_add = !new_source.name.is_empty() && !new_source.directory.is_empty();
+                    if ui.add_enabled(can_add, egui::Button::new("Add source")).clicked() {
+                        alkahest_pm::add_package_source(
+                            new_source.name.clone(),
+                            &new_source.directory,
+                            new_source.season.game_version(),
+                        );
+                        c.package_sources.push(std::mem::take(&mut new_source));
+                    }
+                    ui.data_mut(|d| d.insert_temp(new_source_id, new_source));
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Load Watchdog").heading(), |ui| {
+                    ui.checkbox(&mut c.load_watchdog.enabled, "Detect stuck map loads")
+                        .on_hover_text(
+                            "Writes a diagnostic bundle with the backtraces of every thread when \
+                             a map takes longer than the timeout to load, and offers to cancel \
+                             or retry the load",
+                        );
+                    ui.add_enabled_ui(c.load_watchdog.enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            egui::DragValue::new(&mut c.load_watchdog.timeout_seconds)
+                                .range(10..=3600)
+                                .speed(1)
+                                .suffix(" s")
+                                .ui(ui);
+                            ui.label("Timeout");
+                        });
+                    });
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Autosave").heading(), |ui| {
+                    ui.checkbox(&mut c.autosave.enabled, "Autosave user-created markup")
+                        .on_hover_text(
+                            "Periodically saves rulers, spheres, beacons and routes, so they can \
+                             be restored if Alkahest crashes",
+                        );
+                    ui.add_enabled_ui(c.autosave.enabled, |ui| {
+                        ui.add(
+                            egui::Slider::new(&mut c.autosave.interval_minutes, 1..=60)
+                                .text("Interval (minutes)"),
+                        );
+                        ui.add(egui::Slider::new(&mut c.autosave.keep, 1..=20).text("Files kept"));
+                    });
+                });
+
+                ui.separator();
+                ui.collapsing(RichText::new("Manifest lookup").heading(), |ui| {
+                    ui.label(
+                        RichText::new(
+                            "Looks up Destiny 2 definition hashes (items, activities, \
+                             destinations and places). Map entities don't carry these hashes, \
+                             so the outliner and inspector aren't named from it",
+                        )
+                        .weak(),
+                    );
+                    ui.checkbox(&mut c.investment.enabled, "Load on startup")
+                        .on_hover_text("Loads item names from the Destiny 2 manifest");
+                    ui.horizontal(|ui| {
+                        let name = c
+                            .investment
+                            .manifest_path
+                            .as_deref()
+                            .and_then(|p| std::path::Path::new(p).file_name())
+                            .map_or("bungie.net".into(), |f| f.to_string_lossy());
+                        ui.label(format!("Source: {name}"));
+                        if ui.button("Browse…").clicked() {
+                            match native_dialog::FileDialog::new()
+                                .add_filter("Manifest JSON", &["json"])
+                                .show_open_single_file()
+                            {
+                                Ok(Some(path)) => {
+                                    c.investment.manifest_path =
+                                        Some(path.to_string_lossy().into_owned());
+                                }
+                                Ok(None) => {}
+                                Err(e) => error!("Failed to open the file dialog: {e}"),
+                            }
+                        }
+                        if c.investment.manifest_path.is_some() && ui.button("Clear").clicked() {
+                            c.investment.manifest_path = None;
+                        }
+                    });
+                    ui.add_enabled_ui(c.investment.manifest_path.is_none(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("API key");
+                            egui::TextEdit::singleline(&mut c.investment.api_key)
+                                .password(true)
+                                .ui(ui)
+                                .on_hover_text(
+                                    "Not saved to the config file. Set the BUNGIE_API_KEY \
+                                     environment variable to keep it between sessions",
+                                );
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        let status = investment::status();
+                        ui.add_enabled_ui(!matches!(status, InvestmentStatus::Loading), |ui| {
+                            if ui.button("Load").clicked() {
+                                investment::start_loading(c.investment.clone());
+                            }
+                        });
+                        ui.label(match status {
+                            InvestmentStatus::NotLoaded => "Not loaded".to_string(),
+                            InvestmentStatus::Loading => "Loading…".to_string(),
+                            InvestmentStatus::Loaded(count) => format!("{count} names loaded"),
+                            InvestmentStatus::Failed(e) => format!("Failed: {e}"),
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        let id = ui.id().with("manifest_lookup");
+                        let mut hash =
+                            ui.data_mut(|d| d.get_temp::<String>(id)).unwrap_or_default();
+                        ui.label("Look up hash");
+                        ui.add(egui::TextEdit::singleline(&mut hash).desired_width(96.0));
+                        let hash = hash.trim().to_string();
+                        match hash.parse::<u32>().ok().and_then(investment::lookup) {
+                            Some(name) => {
+                                if let Some(icon) = investment::png_icon(&name) {
+                                    let uri = format!("bytes://manifest/{hash}.png");
+                                    ui.ctx().include_bytes(uri.clone(), icon);
+                                    ui.add(
+                                        egui::Image::new(uri)
+                                            .fit_to_exact_size(egui::vec2(24.0, 24.0)),
+                                    );
+                                }
+                                ui.label(&name.name);
+                                if let Some(url) = name.icon_url() {
+                                    ui.hyperlink_to(ICON_IMAGE.to_string(), url)
+                                        .on_hover_text("Open the icon on bungie.net");
+                                }
+                            }
+                            None if !hash.is_empty() => {
+                                ui.weak("Not found");
+                            }
+                            None => {}
+                        }
+                        ui.data_mut(|d| d.insert_temp(id, hash));
+                    });
+                });
+
                 resources
                     .get::<RendererShared>()
                     .set_render_settings(c.renderer.clone());
@@ -232,6 +1062,33 @@ impl GuiView for RenderSettingsPanel {
     }
 }
 
+/// Counts the nodes shown by the node overlay per filter and per resource origin
+fn count_nodes(
+    scene: &Scene,
+) -> (
+    FxHashMap<NodeFilter, usize>,
+    FxHashMap<ResourceOrigin, usize>,
+) {
+    let mut filters = FxHashMap::default();
+    let mut origins = FxHashMap::default();
+    for e in scene.iter_entities() {
+        if !e.contains::<Transform>() {
+            continue;
+        }
+
+        let filter = e
+            .get::<NodeFilter>()
+            .copied()
+            .unwrap_or(NodeFilter::Unknown);
+        *filters.entry(filter).or_default() += 1;
+        if let Some(origin) = e.get::<ResourceOrigin>() {
+            *origins.entry(*origin).or_default() += 1;
+        }
+    }
+
+    (filters, origins)
+}
+
 #[derive(Default, PartialEq)]
 pub enum SelectionGizmoMode {
     #[default]
@@ -304,6 +1161,17 @@ fn render_feat_vis_select(ui: &mut egui::Ui, name: &str, mode: &mut RenderFeatur
     });
 }
 
+fn quality_combo(ui: &mut egui::Ui, label: &str, quality: &mut EffectQuality) -> egui::Response {
+    egui::ComboBox::from_label(label)
+        .selected_text(quality.to_string())
+        .show_ui(ui, |ui| {
+            for q in EffectQuality::iter() {
+                ui.selectable_value(quality, q, q.to_string());
+            }
+        })
+        .response
+}
+
 fn render_feat_vis(ui: &mut egui::Ui, name: &str, visible: &mut bool) {
     ui.horizontal(|ui| {
         ui.label(name);
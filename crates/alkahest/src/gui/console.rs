@@ -1,6 +1,7 @@
 use std::{
     fmt::Debug,
     io::{Cursor, Seek, SeekFrom},
+    path::Path,
     sync::Arc,
 };
 
@@ -25,7 +26,7 @@ use alkahest_renderer::{
         visibility::Visibility,
     },
     icons::ICON_CUBE,
-    renderer::{Renderer, RendererShared, Time},
+    renderer::{AntiAliasing, Renderer, RendererSettings, RendererShared, Time},
     resources::AppResources,
     tfx::bytecode::{decompiler::TfxBytecodeDecompiler, opcodes::TfxBytecodeOp},
 };
@@ -39,6 +40,7 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use ringbuffer::{AllocRingBuffer, RingBuffer};
 use rustc_hash::FxHashSet;
+use strum::IntoEnumIterator;
 use tiger_parse::{Endian, PackageManagerExt, TigerReadable};
 use tracing::{
     field::{Field, Visit},
@@ -48,11 +50,22 @@ use tracing_subscriber::Layer;
 use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
+    config,
+    data_export::export_scene_command,
+    discord::start_discord_client,
+    frame_dump::dump_frame,
     gui::{
         commands::load_pkg_entities,
         context::{GuiCtx, GuiView, ViewAction},
+        entity_query::run_query_command,
+        hex_viewer::open_in_hex_viewer,
+        hotkeys::HotkeyAction,
     },
     maplist::MapList,
+    package_check::{start_package_check, CheckDepth},
+    scripting,
+    technique_dump::dump_technique,
+    updater::{UpdateChannel, UpdateCheck},
     util::action::{ActionList, ActivitySwapAction, SpawnRouteAction},
 };
 
@@ -128,7 +141,7 @@ impl GuiView for ConsolePanel {
         resources: &AppResources,
         _gui: &GuiCtx<'_>,
     ) -> Option<ViewAction> {
-        let request_focus = if ctx.input(|i| i.key_pressed(egui::Key::F1)) {
+        let request_focus = if HotkeyAction::OpenConsole.pressed(ctx) {
             self.open = true;
             true
         } else {
@@ -188,13 +201,7 @@ impl GuiView for ConsolePanel {
                         .lost_focus()
                         && ui.input(|i| i.key_pressed(egui::Key::Enter))
                     {
-                        let cmd = self.command_buffer.split(' ').collect_vec();
-                        if !cmd.is_empty() {
-                            let command = cmd[0];
-                            let args = &cmd[1..];
-
-                            execute_command(command, args, resources);
-                        }
+                        execute_command_line(&self.command_buffer, resources);
 
                         self.command_buffer.clear();
                         ctx.memory_mut(|m| m.request_focus(egui::Id::new("console_input_line")));
@@ -237,11 +244,95 @@ pub fn queue_command(command: &str, args: &[&str]) {
     COMMAND_QUEUE.0.send(command).ok();
 }
 
+/// Queues a line of `;`-separated commands
+pub fn queue_command_line(line: &str) {
+    for command_line in line.split(';') {
+        let cmd = command_line.split_whitespace().collect_vec();
+        let Some((command, args)) = cmd.split_first() else {
+            continue;
+        };
+
+        let command = QueuedCommand {
+            command: command.to_string(),
+            args: args.iter().map(|s| s.to_string()).collect_vec(),
+        };
+
+        if COMMAND_QUEUE.0.try_send(command).is_err() {
+            error!("Command queue is full, dropping '{}'", command_line.trim());
+        }
+    }
+}
+
+/// Queues the startup commands from the config
+pub fn queue_startup_commands() {
+    let commands = config::with(|c| c.console.startup_commands.clone());
+    if !commands.is_empty() {
+        info!("Running {} startup command(s)", commands.len());
+    }
+
+    for line in commands {
+        queue_command_line(&line);
+    }
+}
+
 pub fn process_queued_commands(resources: &AppResources) {
     while let Ok(cmd) = COMMAND_QUEUE.1.try_recv() {
         let args = cmd.args.iter().map(|s| s.as_str()).collect_vec();
-        execute_command(&cmd.command, &args, resources);
+        execute_command_or_alias(&cmd.command, &args, resources, 0);
+    }
+}
+
+/// Maximum alias expansion depth, guards against aliases that (indirectly) refer to themselves
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// Executes a line of `;`-separated commands
+pub fn execute_command_line(line: &str, resources: &AppResources) {
+    execute_command_line_inner(line, resources, 0);
+}
+
+fn execute_command_line_inner(line: &str, resources: &AppResources, depth: usize) {
+    // Alias definitions can contain multiple commands, so the rest of the line belongs to the alias
+    if let Some(definition) = line.trim_start().strip_prefix("alias ") {
+        execute_command(
+            "alias",
+            &definition.split_whitespace().collect_vec(),
+            resources,
+        );
+        return;
+    }
+
+    // Same goes for script code
+    if let Some(code) = line.trim_start().strip_prefix("script.eval ") {
+        execute_command("script.eval", &[code], resources);
+        return;
+    }
+
+    for command_line in line.split(';') {
+        let cmd = command_line.split_whitespace().collect_vec();
+        if let Some((command, args)) = cmd.split_first() {
+            execute_command_or_alias(command, args, resources, depth);
+        }
+    }
+}
+
+fn execute_command_or_alias(command: &str, args: &[&str], resources: &AppResources, depth: usize) {
+    let Some(alias) = config::with(|c| c.console.aliases.get(command).cloned()) else {
+        execute_command(command, args, resources);
+        return;
+    };
+
+    if depth >= MAX_ALIAS_DEPTH {
+        error!(
+            "Alias '{command}' is nested more than {MAX_ALIAS_DEPTH} levels deep, is it recursive?"
+        );
+        return;
     }
+
+    // Arguments are appended to the last command of the alias
+    let expanded = std::iter::once(alias.as_str())
+        .chain(args.iter().copied())
+        .join(" ");
+    execute_command_line_inner(&expanded, resources, depth + 1);
 }
 
 fn execute_command(command: &str, args: &[&str], resources: &AppResources) {
@@ -377,6 +468,16 @@ fn execute_command(command: &str, args: &[&str], resources: &AppResources) {
         //         }
         //     }
         // }
+        "dump_frame" => match dump_frame(resources) {
+            Ok(directory) => info!("Writing frame dump to {}", directory.display()),
+            Err(e) => error!("Failed to dump frame: {e:?}"),
+        },
+        "query" | "find_entities" => {
+            run_query_command(args, resources);
+        }
+        "export_scene" => {
+            export_scene_command(args, resources);
+        }
         "clear_map" => {
             let mut maps = resources.get_mut::<MapList>();
             if let Some(map) = maps.current_map_mut() {
@@ -529,6 +630,36 @@ fn execute_command(command: &str, args: &[&str], resources: &AppResources) {
                 info!(" {i}: {}", o.disassemble(None));
             }
         }
+        "dump_technique" => {
+            if args.len() != 1 {
+                error!("Missing tag argument, expected 32-bit tag");
+                return;
+            }
+
+            let tag = match parse_extended_hash(args[0]) {
+                Ok(o) => o.hash32(),
+                Err(e) => {
+                    error!("Failed to parse tag: {e}");
+                    return;
+                }
+            };
+
+            match dump_technique(tag, Path::new("technique_dumps")) {
+                Ok(directory) => info!("Dumped technique {tag} to {}", directory.display()),
+                Err(e) => error!("Failed to dump technique: {e:?}"),
+            }
+        }
+        "hex" | "hex_viewer" => {
+            if args.len() != 1 {
+                error!("Missing tag argument, expected 32-bit tag");
+                return;
+            }
+
+            match parse_extended_hash(args[0]) {
+                Ok(tag) => open_in_hex_viewer(resources, tag.hash32()),
+                Err(e) => error!("Failed to parse tag: {e}"),
+            }
+        }
         "distfxt" | "disassemble_tfx_technique" => {
             // TODO(cohae): Make some abstraction for this
             if args.len() != 1 {
@@ -905,6 +1036,15 @@ fn execute_command(command: &str, args: &[&str], resources: &AppResources) {
             let renderer = resources.get_mut::<RendererShared>();
             renderer.time.store(renderer.time.load().to_instant());
         }
+        "time_speed" => {
+            let Some(Ok(speed)) = args.first().map(|s| str::parse::<f32>(s)) else {
+                error!("Usage: time_speed <multiplier>");
+                return;
+            };
+
+            let renderer = resources.get_mut::<RendererShared>();
+            renderer.time.store(renderer.time.load().with_speed(speed));
+        }
         "recreate_shadowmaps" => {
             let renderer = resources.get_mut::<RendererShared>();
             let mut maps = resources.get_mut::<MapList>();
@@ -914,13 +1054,206 @@ fn execute_command(command: &str, args: &[&str], resources: &AppResources) {
             };
 
             for mut shadowmap in scene.query::<&mut ShadowMapRenderer>().iter_mut(scene) {
-                shadowmap.resize(&renderer.gpu, renderer.settings.shadow_quality.resolution());
+                shadowmap.resize(
+                    &renderer.gpu,
+                    renderer.settings().shadow_quality.resolution(),
+                );
+            }
+        }
+        "asset.retry" => {
+            let hash = match args.first() {
+                Some(arg) => match parse_extended_hash(arg) {
+                    Ok(hash) => Some(hash.hash32()),
+                    Err(e) => {
+                        error!("{e}");
+                        return;
+                    }
+                },
+                None => None,
+            };
+
+            let renderer = resources.get::<RendererShared>();
+            let retried = renderer.data.lock().asset_manager.retry_failed(hash);
+            info!("Retrying {retried} failed asset(s)");
+        }
+        "pkg.verify" => {
+            let depth = match args.first().copied() {
+                None | Some("full") => CheckDepth::Full,
+                Some("quick") => CheckDepth::Quick,
+                Some(other) => {
+                    error!("Unknown check depth '{other}', expected 'quick' or 'full'");
+                    return;
+                }
+            };
+
+            info!("Verifying packages ({depth:?}), this may take a while");
+            start_package_check(depth);
+        }
+        "discord.restart" => start_discord_client(),
+        "update.check" => {
+            let channel = config::with(|c| c.update_channel.unwrap_or(UpdateChannel::Stable));
+            resources.get_mut::<UpdateCheck>().start(channel);
+        }
+        "alias" => match args {
+            [] => {
+                let aliases = config::with(|c| c.console.aliases.clone());
+                if aliases.is_empty() {
+                    info!("No aliases defined");
+                }
+
+                for (name, command) in aliases {
+                    info!("{name} => {command}");
+                }
+            }
+            [name] => match config::with(|c| c.console.aliases.get(*name).cloned()) {
+                Some(command) => info!("{name} => {command}"),
+                None => error!("No alias named '{name}'"),
+            },
+            [name, command @ ..] => {
+                let command = command.join(" ");
+                info!("{name} => {command}");
+                config::with_mut(|c| c.console.aliases.insert(name.to_string(), command));
+                config::persist();
+            }
+        },
+        "unalias" => {
+            let Some(name) = args.first() else {
+                error!("Missing alias name");
+                return;
+            };
+
+            if config::with_mut(|c| c.console.aliases.remove(*name)).is_some() {
+                info!("Removed alias '{name}'");
+                config::persist();
+            } else {
+                error!("No alias named '{name}'");
+            }
+        }
+        "startup" => {
+            let commands = config::with(|c| c.console.startup_commands.clone());
+            if commands.is_empty() {
+                info!("No startup commands defined");
+            }
+
+            for (i, command) in commands.iter().enumerate() {
+                info!("{i}: {command}");
+            }
+        }
+        "startup.run" => queue_startup_commands(),
+        "script.run" | "script.eval" | "script.call" | "script.hook" | "script.reload"
+        | "script.list" => scripting::run_script_command(&command.to_lowercase(), args, resources),
+        "set" => {
+            if args.len() != 2 {
+                error!(
+                    "Expected 2 arguments (set <setting> <value>), got {}",
+                    args.len()
+                );
+                return;
+            }
+
+            let result =
+                config::with_mut(|c| set_render_setting(&mut c.renderer, args[0], args[1]));
+            match result {
+                Ok(()) => {
+                    resources
+                        .get::<RendererShared>()
+                        .set_render_settings(config::with(|c| c.renderer.clone()));
+                    info!("{} = {}", args[0], args[1]);
+                }
+                Err(e) => error!("{e}"),
             }
         }
         _ => error!("Unknown command '{command}'"),
     }
 }
 
+fn set_render_setting(
+    settings: &mut RendererSettings,
+    name: &str,
+    value: &str,
+) -> anyhow::Result<()> {
+    let setting = match name {
+        "ssao" => &mut settings.ssao,
+        "matcap" => &mut settings.matcap,
+        "selection_outline" => &mut settings.draw_selection_outline,
+        "search_highlight" => &mut settings.draw_search_highlight,
+        "detail_fade" => &mut settings.detail_fade,
+        "auto_lod" => &mut settings.auto_lod,
+        "lod_colors" => &mut settings.lod_debug_colors,
+        "terrain_materials" => &mut settings.terrain_material_colors,
+        "stereo" => &mut settings.stereo,
+        "wireframe" => &mut settings.wireframe_overlay,
+        "dof" => &mut settings.dof,
+        "bloom" => &mut settings.bloom,
+        "utilities" => &mut settings.draw_utilities,
+        "atmosphere" => &mut settings.feature_atmosphere,
+        "cubemaps" => &mut settings.feature_cubemaps,
+        "global_lighting" => &mut settings.feature_global_lighting,
+        "transparents" => &mut settings.stage_transparent,
+        "decals" => &mut settings.stage_decals,
+        "decals_additive" => &mut settings.stage_decals_additive,
+        "exposure" => {
+            settings.exposure = str::parse(value).context("Invalid exposure value")?;
+            return Ok(());
+        }
+        "lod_bias" => {
+            settings.lod_bias = str::parse(value).context("Invalid LOD bias")?;
+            return Ok(());
+        }
+        // Switches to manual focus
+        "dof_focus" => {
+            settings.dof_focus_distance = str::parse(value).context("Invalid focus distance")?;
+            settings.dof_autofocus = false;
+            return Ok(());
+        }
+        "bloom_intensity" => {
+            settings.bloom_intensity = str::parse(value).context("Invalid bloom intensity")?;
+            return Ok(());
+        }
+        "stereo_ipd" => {
+            settings.stereo_ipd = str::parse(value).context("Invalid eye distance")?;
+            return Ok(());
+        }
+        "aa" => {
+            settings.anti_aliasing = AntiAliasing::iter()
+                .find(|m| m.to_string().eq_ignore_ascii_case(value))
+                .with_context(|| {
+                    format!(
+                        "Invalid anti-aliasing mode '{value}', expected one of {}",
+                        AntiAliasing::iter().join(", ").to_lowercase()
+                    )
+                })?;
+            return Ok(());
+        }
+        // Kept for existing startup commands, 'aa' selects between all modes
+        "fxaa" => {
+            settings.anti_aliasing = if parse_toggle(value)? {
+                AntiAliasing::Fxaa
+            } else {
+                AntiAliasing::None
+            };
+            return Ok(());
+        }
+        _ => anyhow::bail!(
+            "Unknown setting '{name}', expected one of ssao, matcap, selection_outline, \
+             search_highlight, detail_fade, auto_lod, lod_bias, lod_colors, terrain_materials, stereo, \
+             stereo_ipd, wireframe, dof, dof_focus, bloom, bloom_intensity, utilities, atmosphere, cubemaps, global_lighting, aa, fxaa, transparents, decals, decals_additive, exposure"
+        ),
+    };
+
+    *setting = parse_toggle(value)?;
+
+    Ok(())
+}
+
+fn parse_toggle(value: &str) -> anyhow::Result<bool> {
+    Ok(match value.to_lowercase().as_str() {
+        "1" | "true" | "on" => true,
+        "0" | "false" | "off" => false,
+        _ => anyhow::bail!("Invalid value '{value}', expected on/off"),
+    })
+}
+
 pub fn load_entity_model(
     t: WideHash,
     transform: Transform,
@@ -1003,7 +1336,7 @@ pub fn load_entity(
     Err(anyhow::anyhow!("No entitymodel found in entity"))
 }
 
-fn parse_extended_hash(s: &str) -> anyhow::Result<WideHash> {
+pub(crate) fn parse_extended_hash(s: &str) -> anyhow::Result<WideHash> {
     let tag_parsed: anyhow::Result<WideHash> = (|| {
         if s.len() > 8 {
             let h = u64::from_be(u64::from_str_radix(s, 16)?);
@@ -1,35 +1,64 @@
-use std::{any::TypeId, sync::Arc};
+use std::{any::TypeId, cell::RefCell, sync::Arc};
 
 use alkahest_renderer::{
     gpu::GpuContext,
     util::{d3d::ErrorExt, image::Png},
 };
 use anyhow::Context;
-use egui::{InputState, Key, KeyboardShortcut, Modifiers};
+use egui::InputState;
 use egui_directx11::DirectX11Renderer;
 use egui_winit::EventResponse;
 use indexmap::IndexMap;
 use smallvec::SmallVec;
+use windows::Win32::Graphics::Direct3D11::{ID3D11RenderTargetView, ID3D11ShaderResourceView};
 use winit::{event::WindowEvent, window::Window};
 
 use super::sodi::Sodi;
 use crate::{
     gui::{
+        autosave::AutosaveRestorePrompt,
         bottom_bar::BottomBar,
         configuration::RenderSettingsPanel,
         console::ConsolePanel,
         crosshair::CrosshairOverlay,
+        entity_query::EntityQueryPanel,
+        env_probe::EnvProbePanel,
+        environment::EnvironmentPanel,
+        error_cards::BackgroundErrorCards,
         fps_display::FpsDisplayOverlay,
         gizmo::GizmoSelector,
+        hex_viewer::HexViewerPanel,
+        hotkeys::HotkeyAction,
         inspector::InspectorPanel,
+        keybindings::KeybindingsPanel,
         load_indicator::ResourceLoadIndicatorOverlay,
+        load_report::{LoadStallDialog, LoadWarningsDialog, MapLoadReportPanel},
+        memory::MemoryPanel,
+        memory_diff::MemoryDiffPanel,
         menu::MenuBar,
+        mesh_stats::MeshStatisticsPanel,
+        minimap::MinimapOverlay,
         node_gizmos::NodeGizmoOverlay,
         outliner::OutlinerPanel,
+        prefabs::PrefabPanel,
+        presentation::{PresentationOverlay, PresentationPanel},
         profiler::PuffinProfiler,
+        render_stats::RenderStatsPanel,
+        respawn_points::RespawnPointsPanel,
+        scene_time::SceneTimePanel,
+        scripts::ScriptConsolePanel,
+        sky::SkyLightingPanel,
+        solo::SoloIndicatorOverlay,
+        tag_manager::TagManagerPanel,
+        tag_search::TagSearchPanel,
+        target_viewer::RenderTargetViewerPanel,
+        texture_usage::TextureUsagePanel,
         tfx::{TfxErrorViewer, TfxExternEditor},
+        toasts::ToastOverlay,
+        world_id_search::WorldIdSearchPanel,
     },
     paths,
+    presentation::Presentation,
     resources::AppResources,
     util::image::EguiPngLoader,
 };
@@ -40,6 +69,8 @@ pub struct GuiContext {
     pub renderer: Option<egui_directx11::DirectX11Renderer>,
     gctx: Arc<GpuContext>,
     resources: GuiResources,
+    /// Textures of the renderer while it's released, see [`Self::release_renderer`]
+    released_textures: Option<egui_directx11::TextureAllocator>,
 }
 
 impl GuiContext {
@@ -99,8 +130,8 @@ impl GuiContext {
         egui.set_fonts(fonts);
         egui.set_style(style::style());
 
-        let renderer = gctx.swap_chain.as_ref().map(|swap_chain| {
-            egui_directx11::DirectX11Renderer::init_from_swapchain(swap_chain)
+        let renderer = gctx.swap_chain().map(|swap_chain| {
+            egui_directx11::DirectX11Renderer::init_from_swapchain(&swap_chain)
                 .expect("Failed to initialize egui renderer")
         });
 
@@ -110,9 +141,36 @@ impl GuiContext {
             integration,
             renderer,
             gctx,
+            released_textures: None,
         }
     }
 
+    /// Drops the renderer and its references to the swapchain, keeping its textures around for
+    /// [`Self::restore_renderer`]. Used when the GPU device has been lost
+    pub fn release_renderer(&mut self) {
+        if let Some(renderer) = self.renderer.take() {
+            self.released_textures = Some(renderer.into_textures());
+        }
+    }
+
+    /// Creates a renderer for the swapchain of `gctx`, re-uploading the textures of the released
+    /// renderer
+    pub fn restore_renderer(&mut self, gctx: Arc<GpuContext>) -> anyhow::Result<()> {
+        self.gctx = gctx;
+        let Some(swap_chain) = self.gctx.swap_chain() else {
+            return Ok(());
+        };
+
+        self.renderer = Some(match self.released_textures.take() {
+            Some(textures) => {
+                egui_directx11::DirectX11Renderer::init_with_textures(&swap_chain, textures)?
+            }
+            None => egui_directx11::DirectX11Renderer::init_from_swapchain(&swap_chain)?,
+        });
+
+        Ok(())
+    }
+
     pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> EventResponse {
         self.integration.on_window_event(window, event)
     }
@@ -124,21 +182,29 @@ impl GuiContext {
         profiling::scope!("GuiContext::draw_frame");
         let input = self.integration.take_egui_input(window);
 
-        if let Some(ref swap_chain) = self.gctx.swap_chain {
+        if let Some(swap_chain) = self.gctx.swap_chain() {
             if let Some(ref mut renderer) = self.renderer {
-                let output = renderer
-                    .paint(swap_chain, input, &self.egui, |renderer, context| {
+                let output = match renderer
+                    .paint(&swap_chain, input, &self.egui, |renderer, context| {
                         paint(
                             &GuiCtx {
                                 icons: &self.resources,
-                                _integration: renderer,
+                                integration: RefCell::new(renderer),
                             },
                             context,
                         )
                     })
                     .context("Failed to paint egui frame")
                     .map_err(|e| e.with_d3d_error(&self.gctx))
-                    .unwrap();
+                {
+                    Ok(output) => output,
+                    // The device has been lost, the frame is skipped until it has been recreated
+                    Err(e) if self.gctx.device_removed_reason().is_some() => {
+                        error!("{e:?}");
+                        return;
+                    }
+                    Err(e) => panic!("{e:?}"),
+                };
 
                 self.integration
                     .handle_platform_output(window, output.platform_output)
@@ -146,6 +212,13 @@ impl GuiContext {
         }
     }
 
+    /// Draw the UI to `target` instead of the swapchain, or back to the swapchain when `None`
+    pub fn set_render_target(&mut self, target: Option<ID3D11RenderTargetView>) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_render_target(target);
+        }
+    }
+
     // pub fn input<R>(&self, reader: impl FnOnce(&InputState) -> R) -> R {
     //     self.egui.input(reader)
     // }
@@ -213,8 +286,37 @@ impl GuiViewManager {
         views.insert(ResourceLoadIndicatorOverlay);
         views.insert(GizmoSelector);
         views.insert(Sodi::default());
+        views.insert(TagSearchPanel::default());
+        views.insert(WorldIdSearchPanel::default());
+        views.insert(EntityQueryPanel::default());
+        views.insert(SoloIndicatorOverlay);
+        views.insert(RenderStatsPanel);
+        views.insert(ScriptConsolePanel::default());
+        views.insert(MemoryPanel);
+        views.insert(SkyLightingPanel);
+        views.insert(SceneTimePanel::default());
+        views.insert(TextureUsagePanel::default());
+        views.insert(MemoryDiffPanel::default());
+        views.insert(KeybindingsPanel);
+        views.insert(EnvProbePanel::default());
+        views.insert(EnvironmentPanel);
+        views.insert(MinimapOverlay::default());
+        views.insert(AutosaveRestorePrompt);
+        views.insert(TagManagerPanel::default());
+        views.insert(MeshStatisticsPanel::default());
+        views.insert(RenderTargetViewerPanel::default());
+        views.insert(MapLoadReportPanel::default());
+        views.insert(LoadWarningsDialog);
+        views.insert(LoadStallDialog);
+        views.insert(PrefabPanel::default());
+        views.insert(PresentationPanel::default());
+        views.insert(HexViewerPanel::default());
+        views.insert(RespawnPointsPanel);
 
         views.insert_overlay(FpsDisplayOverlay::default());
+        views.insert_overlay(BackgroundErrorCards::default());
+        views.insert_overlay(ToastOverlay::default());
+        views.insert_overlay(PresentationOverlay);
 
         views
     }
@@ -237,16 +339,13 @@ impl GuiViewManager {
         resources: &AppResources,
         gui: &GuiCtx<'_>,
     ) {
-        if ctx.input_mut(|input| {
-            input.consume_shortcut(&KeyboardShortcut::new(
-                Modifiers::CTRL | Modifiers::SHIFT,
-                Key::H,
-            ))
-        }) {
+        if HotkeyAction::ToggleInterface.pressed(ctx) {
             self.hide_views = !self.hide_views;
         }
 
-        if !self.hide_views {
+        // Presentation mode hides everything but its own overlay
+        let presenting = resources.get::<Presentation>().is_active();
+        if !self.hide_views && !presenting {
             let mut to_remove = SmallVec::<[TypeId; 4]>::new();
             for (tid, view) in self.views.iter_mut() {
                 if let Some(result) = view.draw(ctx, window, resources, gui) {
@@ -263,7 +362,11 @@ impl GuiViewManager {
             }
         }
 
-        for view in self.views_overlay.values_mut() {
+        for (tid, view) in self.views_overlay.iter_mut() {
+            if presenting && *tid != TypeId::of::<PresentationOverlay>() {
+                continue;
+            }
+
             view.draw(ctx, window, resources, gui);
         }
     }
@@ -271,7 +374,26 @@ impl GuiViewManager {
 
 pub struct GuiCtx<'a> {
     pub icons: &'a GuiResources,
-    pub _integration: &'a mut DirectX11Renderer,
+    integration: RefCell<&'a mut DirectX11Renderer>,
+}
+
+impl GuiCtx<'_> {
+    /// Makes a texture of the renderer drawable by egui, until it's freed with
+    /// [`Self::free_texture`]. The texture is lost along with the GPU device
+    pub fn register_texture(
+        &self,
+        view: ID3D11ShaderResourceView,
+        filter: Option<egui::TextureFilter>,
+    ) -> egui::TextureId {
+        self.integration
+            .borrow_mut()
+            .textures_mut()
+            .allocate_dx((view, filter))
+    }
+
+    pub fn free_texture(&self, id: egui::TextureId) {
+        self.integration.borrow_mut().textures_mut().free(id);
+    }
 }
 
 pub struct GuiResources {
@@ -303,6 +425,27 @@ pub struct HiddenWindows {
     pub tfx_extern_editor: bool,
     pub tfx_extern_debugger: bool,
     pub cpu_profiler: bool,
+    pub tag_search: bool,
+    pub world_id_search: bool,
+    pub entity_query: bool,
+    pub render_stats: bool,
+    pub scripts: bool,
+    pub memory: bool,
+    pub sky_lighting: bool,
+    pub scene_time: bool,
+    pub texture_usage: bool,
+    pub memory_diff: bool,
+    pub keybindings: bool,
+    pub env_probe_capture: bool,
+    pub environment: bool,
+    pub tag_manager: bool,
+    pub mesh_stats: bool,
+    pub render_targets: bool,
+    pub load_report: bool,
+    pub prefabs: bool,
+    pub presentation: bool,
+    pub hex_viewer: bool,
+    pub respawn_points: bool,
 }
 
 mod style {
@@ -0,0 +1,480 @@
+use std::str::FromStr;
+
+use alkahest_data::map::{SLight, SLightCollection, SRespawnPoint, SShadowingLight};
+use alkahest_renderer::{
+    ecs::{
+        channels::ObjectChannels,
+        common::{Global, Label, Mutable, Water},
+        map::{CubemapVolume, NodeMetadata},
+        render::{
+            decorators::DecoratorRenderer,
+            dynamic_geometry::DynamicModelComponent,
+            havok::HavokShapeRenderer,
+            light::{LightRenderer, ShadowMapRenderer},
+            static_geometry::{StaticInstance, StaticInstances, StaticModelSingle},
+            terrain::TerrainPatches,
+        },
+        resources::{SearchHighlight, SearchHighlightSource, SelectedEntity},
+        route::{Route, RouteNode},
+        utility::{Beacon, ClipPlane, Ruler, Sphere},
+        Scene,
+    },
+    icons::{ICON_CLOSE, ICON_MAGNIFY, ICON_PLUS},
+};
+use bevy_ecs::{entity::Entity, world::EntityRef};
+use destiny_pkg::TagHash;
+use egui::{Context, RichText};
+use itertools::Itertools;
+use strum::{EnumIter, IntoEnumIterator};
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::focus_selected,
+    },
+    maplist::MapList,
+    resources::AppResources,
+};
+
+/// Components that can be used in entity queries
+#[derive(Clone, Copy, PartialEq, Eq, Debug, strum::Display, strum::EnumString, EnumIter)]
+#[strum(ascii_case_insensitive)]
+pub enum QueryComponent {
+    Label,
+    NodeMetadata,
+    Global,
+    Mutable,
+    StaticInstances,
+    StaticInstance,
+    StaticModelSingle,
+    DynamicModelComponent,
+    TerrainPatches,
+    DecoratorRenderer,
+    Water,
+    LightRenderer,
+    ShadowMapRenderer,
+    #[strum(serialize = "Light")]
+    SLight,
+    #[strum(serialize = "ShadowingLight")]
+    SShadowingLight,
+    #[strum(serialize = "LightCollection")]
+    SLightCollection,
+    CubemapVolume,
+    #[strum(serialize = "RespawnPoint")]
+    SRespawnPoint,
+    HavokShapeRenderer,
+    ObjectChannels,
+    Route,
+    RouteNode,
+    Ruler,
+    Sphere,
+    Beacon,
+    ClipPlane,
+}
+
+impl QueryComponent {
+    pub fn is_present(&self, e: EntityRef<'_>) -> bool {
+        match self {
+            Self::Label => e.contains::<Label>(),
+            Self::NodeMetadata => e.contains::<NodeMetadata>(),
+            Self::Global => e.contains::<Global>(),
+            Self::Mutable => e.contains::<Mutable>(),
+            Self::StaticInstances => e.contains::<StaticInstances>(),
+            Self::StaticInstance => e.contains::<StaticInstance>(),
+            Self::StaticModelSingle => e.contains::<StaticModelSingle>(),
+            Self::DynamicModelComponent => e.contains::<DynamicModelComponent>(),
+            Self::TerrainPatches => e.contains::<TerrainPatches>(),
+            Self::DecoratorRenderer => e.contains::<DecoratorRenderer>(),
+            Self::Water => e.contains::<Water>(),
+            Self::LightRenderer => e.contains::<LightRenderer>(),
+            Self::ShadowMapRenderer => e.contains::<ShadowMapRenderer>(),
+            Self::SLight => e.contains::<SLight>(),
+            Self::SShadowingLight => e.contains::<SShadowingLight>(),
+            Self::SLightCollection => e.contains::<SLightCollection>(),
+            Self::CubemapVolume => e.contains::<CubemapVolume>(),
+            Self::SRespawnPoint => e.contains::<SRespawnPoint>(),
+            Self::HavokShapeRenderer => e.contains::<HavokShapeRenderer>(),
+            Self::ObjectChannels => e.contains::<ObjectChannels>(),
+            Self::Route => e.contains::<Route>(),
+            Self::RouteNode => e.contains::<RouteNode>(),
+            Self::Ruler => e.contains::<Ruler>(),
+            Self::Sphere => e.contains::<Sphere>(),
+            Self::Beacon => e.contains::<Beacon>(),
+            Self::ClipPlane => e.contains::<ClipPlane>(),
+        }
+    }
+
+    /// Returns the tag hash associated with the component, if it has one
+    pub fn hash(&self, e: EntityRef<'_>) -> Option<TagHash> {
+        match self {
+            Self::NodeMetadata => e.get::<NodeMetadata>().map(|m| m.entity_tag),
+            Self::StaticInstances => e.get::<StaticInstances>().map(|s| s.model.hash),
+            Self::StaticModelSingle => e.get::<StaticModelSingle>().map(|s| s.model.hash),
+            Self::DynamicModelComponent => e.get::<DynamicModelComponent>().map(|d| d.model.hash),
+            Self::TerrainPatches => e.get::<TerrainPatches>().map(|t| t.hash),
+            Self::DecoratorRenderer => e.get::<DecoratorRenderer>().map(|d| d.hash),
+            _ => None,
+        }
+    }
+
+    pub fn has_hash(&self) -> bool {
+        matches!(
+            self,
+            Self::NodeMetadata
+                | Self::StaticInstances
+                | Self::StaticModelSingle
+                | Self::DynamicModelComponent
+                | Self::TerrainPatches
+                | Self::DecoratorRenderer
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct EntityPredicate {
+    pub component: QueryComponent,
+    pub negate: bool,
+    pub hash: Option<TagHash>,
+}
+
+impl EntityPredicate {
+    pub fn matches(&self, e: EntityRef<'_>) -> bool {
+        let matches = match self.hash {
+            Some(hash) => self.component.hash(e) == Some(hash),
+            None => self.component.is_present(e),
+        };
+
+        matches != self.negate
+    }
+}
+
+impl FromStr for EntityPredicate {
+    type Err = anyhow::Error;
+
+    /// Parses predicates in the form of `Component`, `!Component` or `Component=HASH`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negate, s) = match s.strip_prefix('!') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+
+        let (component, hash) = match s.split_once('=') {
+            Some((component, hash)) => (
+                component,
+                Some(crate::parse_taghash(hash).map_err(|e| anyhow::anyhow!(e))?),
+            ),
+            None => (s, None),
+        };
+
+        let component = QueryComponent::from_str(component)
+            .map_err(|_| anyhow::anyhow!("Unknown component '{component}'"))?;
+
+        if hash.is_some() && !component.has_hash() {
+            anyhow::bail!("Component {component} does not have a hash to match against");
+        }
+
+        Ok(Self {
+            component,
+            negate,
+            hash,
+        })
+    }
+}
+
+impl std::fmt::Display for EntityPredicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.negate {
+            write!(f, "!")?;
+        }
+        write!(f, "{}", self.component)?;
+        if let Some(hash) = self.hash {
+            write!(f, "={hash}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of predicates that all have to match (AND)
+#[derive(Default, Clone)]
+pub struct EntityQuery {
+    pub predicates: Vec<EntityPredicate>,
+}
+
+impl EntityQuery {
+    pub fn parse(args: &[&str]) -> anyhow::Result<Self> {
+        let predicates = args
+            .iter()
+            .filter(|a| !a.is_empty() && !a.eq_ignore_ascii_case("and"))
+            .map(|a| EntityPredicate::from_str(a))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { predicates })
+    }
+
+    pub fn matches(&self, e: EntityRef<'_>) -> bool {
+        self.predicates.iter().all(|p| p.matches(e))
+    }
+
+    pub fn run(&self, scene: &Scene) -> Vec<Entity> {
+        if self.predicates.is_empty() {
+            return vec![];
+        }
+
+        scene
+            .iter_entities()
+            .filter(|e| self.matches(*e))
+            .map(|e| e.id())
+            .collect()
+    }
+}
+
+/// Shared between the query window and the `query` console command
+#[derive(Default)]
+pub struct EntityQueryState {
+    pub query: EntityQuery,
+    pub results: Vec<Entity>,
+    /// Text fields for predicate hashes, kept in the same order as `query.predicates`
+    hash_inputs: Vec<String>,
+}
+
+impl EntityQueryState {
+    pub fn set_query(&mut self, query: EntityQuery, results: Vec<Entity>) {
+        self.hash_inputs = query
+            .predicates
+            .iter()
+            .map(|p| p.hash.map(|h| h.to_string()).unwrap_or_default())
+            .collect();
+        self.query = query;
+        self.results = results;
+    }
+}
+
+#[derive(Default)]
+pub struct EntityQueryPanel {
+    error: Option<String>,
+}
+
+impl GuiView for EntityQueryPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        egui::Window::new("Entity Query")
+            .default_size([400., 480.])
+            .open(&mut windows.entity_query)
+            .show(ctx, |ui| {
+                let mut state = resources.get_mut::<EntityQueryState>();
+                self.predicates_ui(ui, &mut state);
+
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{ICON_PLUS} Add predicate")).clicked() {
+                        state.query.predicates.push(EntityPredicate {
+                            component: QueryComponent::LightRenderer,
+                            negate: false,
+                            hash: None,
+                        });
+                    }
+
+                    // An invalid hash would otherwise match every entity with the component
+                    if ui
+                        .add_enabled(
+                            self.error.is_none(),
+                            egui::Button::new(format!("{ICON_MAGNIFY} Run")),
+                        )
+                        .on_disabled_hover_text("Fix the invalid hash first")
+                        .clicked()
+                    {
+                        if let Some(map) = resources.get_mut::<MapList>().current_map_mut() {
+                            state.results = state.query.run(&map.scene);
+                            highlight_results(&mut map.scene, &state.results);
+                        }
+                    }
+
+                    if let Some(map) = resources.get_mut::<MapList>().current_map_mut() {
+                        let highlighted = map
+                            .scene
+                            .get_resource::<SearchHighlight>()
+                            .is_some_and(|h| h.source() == Some(SearchHighlightSource::Query));
+                        if ui
+                            .add_enabled(
+                                highlighted,
+                                egui::Button::new(format!("{ICON_CLOSE} Clear highlight")),
+                            )
+                            .clicked()
+                        {
+                            if let Some(mut highlight) =
+                                map.scene.get_resource_mut::<SearchHighlight>()
+                            {
+                                highlight.clear(SearchHighlightSource::Query);
+                            }
+                        }
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                ui.separator();
+                drop(state);
+                results_ui(ui, resources);
+            });
+
+        None
+    }
+}
+
+impl EntityQueryPanel {
+    fn predicates_ui(&mut self, ui: &mut egui::Ui, state: &mut EntityQueryState) {
+        state
+            .hash_inputs
+            .resize(state.query.predicates.len(), String::new());
+
+        self.error = None;
+        let mut remove = None;
+        for (i, (predicate, input)) in state
+            .query
+            .predicates
+            .iter_mut()
+            .zip(state.hash_inputs.iter_mut())
+            .enumerate()
+        {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut predicate.negate, "NOT");
+                egui::ComboBox::from_id_source(("entity_query_component", i))
+                    .selected_text(predicate.component.to_string())
+                    .show_ui(ui, |ui| {
+                        for c in QueryComponent::iter() {
+                            ui.selectable_value(&mut predicate.component, c, c.to_string());
+                        }
+                    });
+
+                if predicate.component.has_hash() {
+                    let response =
+                        ui.add(egui::TextEdit::singleline(input).hint_text("Hash (optional)"));
+
+                    predicate.hash = if input.trim().is_empty() {
+                        None
+                    } else {
+                        match crate::parse_taghash(input.trim()) {
+                            Ok(hash) => Some(hash),
+                            Err(e) => {
+                                ui.painter().rect_stroke(
+                                    response.rect,
+                                    2.0,
+                                    egui::Stroke::new(1.0, egui::Color32::RED),
+                                );
+                                self.error = Some(format!("Invalid hash '{input}': {e}"));
+                                None
+                            }
+                        }
+                    };
+                } else {
+                    predicate.hash = None;
+                }
+
+                if ui.button(ICON_CLOSE.to_string()).clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if let Some(i) = remove {
+            state.query.predicates.remove(i);
+            state.hash_inputs.remove(i);
+        }
+    }
+}
+
+fn results_ui(ui: &mut egui::Ui, resources: &AppResources) {
+    let results = resources.get::<EntityQueryState>().results.clone();
+    ui.label(RichText::new(format!("{} results", results.len())).strong());
+
+    let maps = resources.get::<MapList>();
+    let Some(map) = maps.current_map() else {
+        return;
+    };
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, true])
+        .show_rows(
+            ui,
+            ui.text_style_height(&egui::TextStyle::Button),
+            results.len(),
+            |ui, range| {
+                let selected = resources.get::<SelectedEntity>().selected();
+                for &e in &results[range] {
+                    let label = map
+                        .scene
+                        .get::<Label>(e)
+                        .map(|l| format!("{l} (id {e})"))
+                        .unwrap_or_else(|| format!("Entity {e}"));
+
+                    if ui.selectable_label(selected == Some(e), label).clicked() {
+                        clicked = Some(e);
+                    }
+                }
+            },
+        );
+    drop(maps);
+
+    if let Some(e) = clicked {
+        resources.get_mut::<SelectedEntity>().select(e);
+        focus_selected(resources);
+    }
+}
+
+/// Highlights the results in the viewport, replacing the outliner search highlight (if any)
+fn highlight_results(scene: &mut Scene, results: &[Entity]) {
+    scene
+        .get_resource_or_insert_with(SearchHighlight::default)
+        .set(SearchHighlightSource::Query, results.to_vec());
+}
+
+/// Runs an entity query from console arguments on the current map and shows the results window
+pub fn run_query_command(args: &[&str], resources: &AppResources) {
+    if args.is_empty() {
+        info!(
+            "Usage: query <predicates...> (eg. `query LightRenderer !ShadowingLight` or `query \
+             DynamicModelComponent=0x80a0b0c0`)"
+        );
+        info!(
+            "Available components: {}",
+            QueryComponent::iter().map(|c| c.to_string()).join(", ")
+        );
+        return;
+    }
+
+    let query = match EntityQuery::parse(args) {
+        Ok(q) => q,
+        Err(e) => {
+            error!("Invalid query: {e}");
+            return;
+        }
+    };
+
+    let mut maps = resources.get_mut::<MapList>();
+    let Some(map) = maps.current_map_mut() else {
+        error!("No map loaded");
+        return;
+    };
+
+    let results = query.run(&map.scene);
+    highlight_results(&mut map.scene, &results);
+    info!(
+        "Query '{}' matched {} entities",
+        query.predicates.iter().join(" "),
+        results.len()
+    );
+
+    resources
+        .get_mut::<EntityQueryState>()
+        .set_query(query, results);
+    resources.get_mut::<HiddenWindows>().entity_query = true;
+}
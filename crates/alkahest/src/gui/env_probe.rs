@@ -0,0 +1,162 @@
+use alkahest_renderer::{
+    camera::Camera,
+    icons::{ICON_CAMERA, ICON_CROSSHAIRS_GPS, ICON_RESTORE},
+};
+use egui::{Color32, Context, RichText};
+use glam::Vec3;
+use winit::window::Window;
+
+use crate::{
+    env_probe::{replaced_cubemap_count, restore_cubemaps, EnvProbeCaptures, EnvProbeRequest},
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    resources::AppResources,
+};
+
+const FACE_SIZES: [u32; 4] = [128, 256, 512, 1024];
+
+pub struct EnvProbePanel {
+    request: EnvProbeRequest,
+}
+
+impl Default for EnvProbePanel {
+    fn default() -> Self {
+        Self {
+            request: EnvProbeRequest {
+                position: Vec3::ZERO,
+                size: 256,
+                save_dds: true,
+                save_hdr: true,
+                replace_cubemaps: false,
+            },
+        }
+    }
+}
+
+impl GuiView for EnvProbePanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        egui::Window::new("Environment Probe Capture")
+            .open(&mut windows.env_probe_capture)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("env_probe_settings")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Position");
+                        ui.horizontal(|ui| {
+                            let p = &mut self.request.position;
+                            ui.add(egui::DragValue::new(&mut p.x).speed(0.1).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut p.y).speed(0.1).prefix("y: "));
+                            ui.add(egui::DragValue::new(&mut p.z).speed(0.1).prefix("z: "));
+                            if ui
+                                .button(ICON_CROSSHAIRS_GPS.to_string())
+                                .on_hover_text("Use the camera position")
+                                .clicked()
+                            {
+                                *p = resources.get::<Camera>().position();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Face size");
+                        egui::ComboBox::from_id_source("env_probe_face_size")
+                            .selected_text(format!("{0}x{0}", self.request.size))
+                            .show_ui(ui, |ui| {
+                                for size in FACE_SIZES {
+                                    ui.selectable_value(
+                                        &mut self.request.size,
+                                        size,
+                                        format!("{size}x{size}"),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Save");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.request.save_dds, "DDS cubemap");
+                            ui.checkbox(&mut self.request.save_hdr, "HDR panorama");
+                        });
+                        ui.end_row();
+                    });
+
+                ui.checkbox(
+                    &mut self.request.replace_cubemaps,
+                    "Replace the cubemap of the surrounding volumes",
+                )
+                .on_hover_text(
+                    "Uses the capture as the reflection cubemap of every cubemap volume \
+                     containing the position, for this session only",
+                );
+
+                let mut captures = resources.get_mut::<EnvProbeCaptures>();
+                let mut maps = resources.get_mut::<MapList>();
+                let Some(map) = maps.current_map_mut() else {
+                    ui.label("No map loaded");
+                    return;
+                };
+
+                let nothing_to_do = !self.request.save_dds
+                    && !self.request.save_hdr
+                    && !self.request.replace_cubemaps;
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !captures.is_pending() && !nothing_to_do,
+                            egui::Button::new(format!("{ICON_CAMERA} Capture")),
+                        )
+                        .clicked()
+                    {
+                        captures.request(self.request.clone());
+                    }
+
+                    let replaced = replaced_cubemap_count(&mut map.scene);
+                    if ui
+                        .add_enabled(
+                            replaced > 0,
+                            egui::Button::new(format!(
+                                "{ICON_RESTORE} Restore original cubemaps ({replaced})"
+                            )),
+                        )
+                        .clicked()
+                    {
+                        let restored = restore_cubemaps(&mut map.scene);
+                        info!("Restored {restored} cubemap volume(s)");
+                    }
+                });
+
+                match &captures.last_result {
+                    Some(Ok(result)) => {
+                        ui.separator();
+                        ui.label(format!(
+                            "Last capture at ({:.2}, {:.2}, {:.2})",
+                            result.position.x, result.position.y, result.position.z
+                        ));
+                        if self.request.replace_cubemaps || result.replaced_volumes > 0 {
+                            ui.label(format!(
+                                "Replaced {} cubemap volume(s)",
+                                result.replaced_volumes
+                            ));
+                        }
+                        if let Some(directory) = &result.directory {
+                            ui.label(format!("Saved to {}", directory.display()));
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.separator();
+                        ui.label(RichText::new(format!("Capture failed: {e}")).color(Color32::RED));
+                    }
+                    None => {}
+                }
+            });
+
+        None
+    }
+}
@@ -0,0 +1,127 @@
+use alkahest_renderer::renderer::RendererShared;
+use egui::{Color32, Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    resources::AppResources,
+};
+
+/// Volumetric fog and light shaft settings
+pub struct EnvironmentPanel;
+
+impl GuiView for EnvironmentPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.environment {
+            return None;
+        }
+
+        egui::Window::new("Environment")
+            .open(&mut windows.environment)
+            .show(ctx, |ui| {
+                config::with_mut(|c| {
+                    let r = &mut c.renderer;
+                    let mut changed = ui
+                        .checkbox(&mut r.volumetrics, "Volumetric Fog")
+                        .on_hover_text(
+                            "Raymarched depth fog, with light shafts from the shadowing lights \
+                             closest to the camera",
+                        )
+                        .changed();
+
+                    ui.add_enabled_ui(r.volumetrics, |ui| {
+                        ui.heading("Fog");
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut r.volumetrics_density, 0.0..=0.2)
+                                    .text("Density")
+                                    .logarithmic(true),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut r.volumetrics_height_falloff, 0.0..=0.5)
+                                    .text("Height Falloff")
+                                    .logarithmic(true),
+                            )
+                            .on_hover_text("How quickly the fog thins out above the base height")
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut r.volumetrics_base_height)
+                                    .speed(0.5)
+                                    .prefix("Base Height: ")
+                                    .suffix(" m"),
+                            )
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut r.volumetrics_max_distance, 10.0..=2000.0)
+                                    .text("Max Distance")
+                                    .suffix(" m")
+                                    .logarithmic(true),
+                            )
+                            .changed();
+                        ui.horizontal(|ui| {
+                            changed |= ui
+                                .color_edit_button_rgb(&mut r.volumetrics_fog_color)
+                                .changed();
+                            ui.label("Fog Color");
+                        });
+
+                        ui.heading("Light Shafts");
+                        if !r.shadows_enabled() {
+                            ui.label(
+                                RichText::new("Light shafts need shadows to be enabled")
+                                    .color(Color32::YELLOW),
+                            );
+                        }
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut r.volumetrics_anisotropy, -0.9..=0.9)
+                                    .text("Anisotropy"),
+                            )
+                            .on_hover_text(
+                                "Positive values scatter light forwards, making the shafts \
+                                 brighter when looking towards the light",
+                            )
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut r.volumetrics_light_intensity, 0.0..=32.0)
+                                    .text("Intensity")
+                                    .logarithmic(true),
+                            )
+                            .changed();
+                        ui.horizontal(|ui| {
+                            changed |= ui
+                                .color_edit_button_rgb(&mut r.volumetrics_light_color)
+                                .changed();
+                            ui.label("Light Color");
+                        })
+                        .response
+                        .on_hover_text(
+                            "Light colors are computed by the light techniques, so every light \
+                             shaft shares this color",
+                        );
+                    });
+
+                    if changed {
+                        resources
+                            .get::<RendererShared>()
+                            .set_render_settings(c.renderer.clone());
+                    }
+                });
+            });
+
+        None
+    }
+}
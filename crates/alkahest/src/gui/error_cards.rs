@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+
+use alkahest_renderer::{
+    resources::AppResources,
+    util::background_errors::{drain_background_errors, BackgroundError},
+};
+use egui::{Color32, Context, RichText, Stroke};
+use winit::window::Window;
+
+use crate::gui::{
+    console,
+    context::{GuiCtx, GuiView, ViewAction},
+    icons::{ICON_ALERT_CIRCLE_OUTLINE, ICON_CLOSE, ICON_CONTENT_COPY, ICON_REFRESH},
+};
+
+const ERROR_COLOR: Color32 = Color32::from_rgb(213, 86, 86);
+/// Number of messages kept per card, older ones are only counted
+const MAX_MESSAGES_PER_CARD: usize = 32;
+const MAX_VISIBLE_CARDS: usize = 4;
+
+/// Errors reported by background threads, shown as dismissible cards grouped by source
+#[derive(Default)]
+pub struct BackgroundErrorCards {
+    cards: Vec<ErrorCard>,
+}
+
+struct ErrorCard {
+    source: &'static str,
+    messages: VecDeque<String>,
+    count: usize,
+    retry_command: Option<String>,
+}
+
+impl ErrorCard {
+    fn new(error: BackgroundError) -> Self {
+        let mut card = Self {
+            source: error.source,
+            messages: VecDeque::new(),
+            count: 0,
+            retry_command: None,
+        };
+        card.push(error);
+        card
+    }
+
+    fn push(&mut self, error: BackgroundError) {
+        if self.messages.len() == MAX_MESSAGES_PER_CARD {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(error.message);
+        self.count += 1;
+        if error.retry_command.is_some() {
+            self.retry_command = error.retry_command;
+        }
+    }
+
+    /// Returns true if the card should be removed
+    fn show(&self, ui: &mut egui::Ui) -> bool {
+        let mut close = false;
+        egui::Frame::window(ui.style())
+            .stroke(Stroke::new(1.0, ERROR_COLOR))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("{ICON_ALERT_CIRCLE_OUTLINE} {}", self.source))
+                            .strong()
+                            .color(ERROR_COLOR),
+                    );
+                    if self.count > 1 {
+                        ui.label(format!("({} errors)", self.count));
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .small_button(ICON_CLOSE.to_string())
+                            .on_hover_text("Dismiss")
+                            .clicked()
+                        {
+                            close = true;
+                        }
+                    });
+                });
+
+                let latest = self.messages.back().map(String::as_str).unwrap_or_default();
+                ui.label(latest.lines().next().unwrap_or_default())
+                    .on_hover_text(latest);
+
+                if self.messages.len() > 1 {
+                    ui.collapsing("Previous errors", |ui| {
+                        egui::ScrollArea::vertical()
+                            .max_height(160.0)
+                            .show(ui, |ui| {
+                                for message in self.messages.iter().rev().skip(1) {
+                                    ui.label(message.lines().next().unwrap_or_default())
+                                        .on_hover_text(message);
+                                }
+                            });
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if let Some(command) = &self.retry_command {
+                        if ui
+                            .button(format!("{ICON_REFRESH} Retry"))
+                            .on_hover_text(command)
+                            .clicked()
+                        {
+                            console::queue_command_line(command);
+                            close = true;
+                        }
+                    }
+
+                    if ui.button(format!("{ICON_CONTENT_COPY} Copy")).clicked() {
+                        let text = self.messages.iter().cloned().collect::<Vec<_>>().join("\n");
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                });
+            });
+
+        close
+    }
+}
+
+impl GuiView for BackgroundErrorCards {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        _resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        for error in drain_background_errors() {
+            match self.cards.iter_mut().find(|c| c.source == error.source) {
+                Some(card) => card.push(error),
+                None => self.cards.push(ErrorCard::new(error)),
+            }
+        }
+
+        if self.cards.is_empty() {
+            return None;
+        }
+
+        egui::Area::new(egui::Id::new("background_error_cards"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -40.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(420.0);
+
+                let hidden = self.cards.len().saturating_sub(MAX_VISIBLE_CARDS);
+                if hidden > 0 {
+                    ui.label(
+                        RichText::new(format!("{hidden} more error source(s)"))
+                            .color(Color32::WHITE),
+                    );
+                }
+
+                let mut closed = vec![];
+                for (i, card) in self.cards.iter().enumerate().skip(hidden) {
+                    if card.show(ui) {
+                        closed.push(i);
+                    }
+                }
+
+                if self.cards.len() > 1 && ui.button("Dismiss all").clicked() {
+                    self.cards.clear();
+                }
+
+                for i in closed.into_iter().rev() {
+                    if i < self.cards.len() {
+                        self.cards.remove(i);
+                    }
+                }
+            });
+
+        None
+    }
+}
@@ -1,6 +1,8 @@
 use std::time::Instant;
 
+use alkahest_renderer::renderer::RendererShared;
 use egui::Color32;
+use itertools::Itertools;
 use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 use winit::window::Window;
 
@@ -31,7 +33,7 @@ impl GuiView for FpsDisplayOverlay {
         &mut self,
         ctx: &egui::Context,
         _window: &Window,
-        _resources: &AppResources,
+        resources: &AppResources,
         _gui: &GuiCtx<'_>,
     ) -> Option<ViewAction> {
         let average_delta = self.deltas.iter().sum::<f32>() / self.deltas.len() as f32;
@@ -64,6 +66,23 @@ impl GuiView for FpsDisplayOverlay {
             color,
         );
 
+        let degraded = resources
+            .get::<RendererShared>()
+            .frame_budget_degradations();
+        if !degraded.is_empty() {
+            painter.text_with_shadow(
+                [
+                    ctx.input(|i| i.screen_rect.right()) - 20.0,
+                    22.0 + (14.0 + 1.0) * 2.0,
+                ]
+                .into(),
+                egui::Align2::RIGHT_TOP,
+                format!("Reduced: {}", degraded.iter().join(", ")),
+                egui::FontId::proportional(12.0),
+                Color32::GOLD,
+            );
+        }
+
         let now = Instant::now();
         let delta = self.last_frame.elapsed().as_secs_f32();
         self.deltas.push(delta);
@@ -2,7 +2,7 @@ use alkahest_renderer::{
     camera::Camera,
     ecs::{resources::SelectedEntity, transform::Transform},
     icons::{ICON_AXIS_ARROW, ICON_CURSOR_DEFAULT, ICON_RESIZE, ICON_ROTATE_ORBIT},
-    renderer::Renderer,
+    renderer::{PickLayer, Renderer, RendererShared},
     resources::AppResources,
 };
 use egui::{
@@ -10,15 +10,18 @@ use egui::{
     UiStackInfo,
 };
 use glam::{DQuat, DVec3};
+use strum::IntoEnumIterator;
 use transform_gizmo_egui::{
     math::Transform as GTransform, Gizmo, GizmoConfig, GizmoInteraction, GizmoResult,
 };
 use winit::window::Window;
 
 use crate::{
+    config,
     gui::{
         configuration::SelectionGizmoMode,
         context::{GuiCtx, GuiView, ViewAction},
+        hotkeys::HotkeyAction,
     },
     maplist::MapList,
 };
@@ -75,7 +78,10 @@ impl GuiView for GizmoSelector {
                 SelectionGizmoMode::Select,
                 RichText::new(ICON_CURSOR_DEFAULT.to_string()).size(16.0),
             )
-            .on_hover_text("Hotkey: 1");
+            .on_hover_text(format!(
+                "Hotkey: {}",
+                HotkeyAction::ToolSelect.shortcut_text(ctx)
+            ));
 
             ui.style_mut().visuals.widgets.active.rounding = rounding_m;
             ui.style_mut().visuals.widgets.hovered.rounding = rounding_m;
@@ -86,14 +92,20 @@ impl GuiView for GizmoSelector {
                 SelectionGizmoMode::Translate,
                 RichText::new(ICON_AXIS_ARROW.to_string()).size(16.0),
             )
-            .on_hover_text("Hotkey: 2");
+            .on_hover_text(format!(
+                "Hotkey: {}",
+                HotkeyAction::ToolTranslate.shortcut_text(ctx)
+            ));
 
             ui.selectable_value(
                 &mut *gizmo_mode,
                 SelectionGizmoMode::Rotate,
                 RichText::new(ICON_ROTATE_ORBIT.to_string()).size(16.0),
             )
-            .on_hover_text("Hotkey: 3");
+            .on_hover_text(format!(
+                "Hotkey: {}",
+                HotkeyAction::ToolRotate.shortcut_text(ctx)
+            ));
 
             ui.style_mut().visuals.widgets.active.rounding = rounding_r;
             ui.style_mut().visuals.widgets.hovered.rounding = rounding_r;
@@ -104,7 +116,42 @@ impl GuiView for GizmoSelector {
                 SelectionGizmoMode::Scale,
                 RichText::new(ICON_RESIZE.to_string()).size(16.0),
             )
-            .on_hover_text("Hotkey: 4");
+            .on_hover_text(format!(
+                "Hotkey: {}",
+                HotkeyAction::ToolScale.shortcut_text(ctx)
+            ));
+
+            ui.add_space(12.0);
+
+            // Pick layers
+            let renderer = resources.get::<RendererShared>();
+            let settings = renderer.settings();
+            let layer_count = PickLayer::iter().count();
+            for (i, layer) in PickLayer::iter().enumerate() {
+                let rounding = if i == 0 {
+                    rounding_l
+                } else if i == layer_count - 1 {
+                    rounding_r
+                } else {
+                    rounding_m
+                };
+                ui.style_mut().visuals.widgets.active.rounding = rounding;
+                ui.style_mut().visuals.widgets.hovered.rounding = rounding;
+                ui.style_mut().visuals.widgets.inactive.rounding = rounding;
+
+                let pickable = settings.is_pickable(layer);
+                let response = ui
+                    .selectable_label(pickable, RichText::new(layer.icon().to_string()).size(16.0))
+                    .on_hover_text(if pickable {
+                        format!("{layer} can be picked in the viewport")
+                    } else {
+                        format!("{layer} can't be picked in the viewport")
+                    });
+                if response.clicked() {
+                    config::with_mut(|c| c.renderer.set_pickable(layer, !pickable));
+                    renderer.set_render_settings(config::with(|c| c.renderer.clone()));
+                }
+            }
         });
 
         None
@@ -117,13 +164,13 @@ pub fn draw_transform_gizmos(renderer: &Renderer, ctx: &egui::Context, resources
     };
 
     let mut gizmo_mode = resources.get_mut::<SelectionGizmoMode>();
-    if ctx.input(|i| i.key_pressed(egui::Key::Num1)) {
+    if HotkeyAction::ToolSelect.pressed(ctx) {
         *gizmo_mode = SelectionGizmoMode::Select;
-    } else if ctx.input(|i| i.key_pressed(egui::Key::Num2)) {
+    } else if HotkeyAction::ToolTranslate.pressed(ctx) {
         *gizmo_mode = SelectionGizmoMode::Translate;
-    } else if ctx.input(|i| i.key_pressed(egui::Key::Num3)) {
+    } else if HotkeyAction::ToolRotate.pressed(ctx) {
         *gizmo_mode = SelectionGizmoMode::Rotate;
-    } else if ctx.input(|i| i.key_pressed(egui::Key::Num4)) {
+    } else if HotkeyAction::ToolScale.pressed(ctx) {
         *gizmo_mode = SelectionGizmoMode::Scale;
     }
 
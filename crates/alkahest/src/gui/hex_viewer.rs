@@ -0,0 +1,430 @@
+use alkahest_pm::package_manager;
+use alkahest_renderer::icons::{ICON_ARROW_LEFT, ICON_HEXADECIMAL, ICON_MAGNIFY_SCAN};
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, RichText, Sense};
+use itertools::Itertools;
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        util::{tag_hash_label, TagHashResponseExt},
+    },
+    resources::AppResources,
+    util::{
+        tag_info,
+        tag_layout::{
+            annotate, find_root_layout, scan_unknown_references, Annotation, AnnotationKind,
+            StructLayout, ROOT_LAYOUTS,
+        },
+    },
+};
+
+const BYTES_PER_ROW: usize = 16;
+const NO_ANNOTATION: u32 = u32::MAX;
+
+const FIELD_COLORS: [Color32; 4] = [
+    Color32::from_rgb(0xE0, 0xB0, 0x60),
+    Color32::from_rgb(0x90, 0xD0, 0x80),
+    Color32::from_rgb(0xD0, 0x90, 0xD0),
+    Color32::from_rgb(0x80, 0xC0, 0xD0),
+];
+const REFERENCE_COLOR: Color32 = Color32::from_rgb(0x60, 0xA0, 0xFF);
+const HEADER_COLOR: Color32 = Color32::GRAY;
+
+/// Tag to show in the hex viewer, picked up (and the window opened) when the viewer is drawn
+#[derive(Default)]
+pub struct HexViewerTarget(pub Option<TagHash>);
+
+/// Opens `tag` in the hex viewer window. Safe to call while other windows are being drawn
+pub fn open_in_hex_viewer(resources: &AppResources, tag: TagHash) {
+    resources.get_mut::<HexViewerTarget>().0 = Some(tag);
+}
+
+struct LoadedTag {
+    hash: TagHash,
+    data: Vec<u8>,
+    class: Option<u32>,
+    layout: Option<&'static StructLayout>,
+    annotations: Vec<Annotation>,
+    /// Set when the data doesn't fit the layout
+    annotation_error: Option<String>,
+    /// Index of the annotation covering every byte, `NO_ANNOTATION` if there is none
+    byte_annotations: Vec<u32>,
+}
+
+impl LoadedTag {
+    fn load(hash: TagHash, layout_override: Option<&'static StructLayout>) -> anyhow::Result<Self> {
+        let data = package_manager().read_tag(hash)?;
+        let class = tag_info::resolve(hash).reference;
+        let layout = layout_override.or_else(|| class.and_then(find_root_layout));
+
+        let (annotations, annotation_error) = match layout.map(|l| annotate(&data, l)) {
+            Some(Ok(annotations)) => (annotations, None),
+            Some(Err(e)) => (vec![], Some(format!("Failed to annotate tag: {e}"))),
+            None => (vec![], None),
+        };
+
+        let mut tag = Self {
+            hash,
+            data,
+            class,
+            layout,
+            annotations,
+            annotation_error,
+            byte_annotations: vec![],
+        };
+        tag.update_byte_annotations();
+        Ok(tag)
+    }
+
+    fn scan_unknown(&mut self) {
+        let found = scan_unknown_references(&self.data, &self.annotations);
+        info!(
+            "Found {} plausible tag references in unannotated regions of {}",
+            found.len(),
+            self.hash
+        );
+        self.annotations.extend(found);
+        self.annotations.sort_by_key(|a| a.range.start);
+        self.update_byte_annotations();
+    }
+
+    fn update_byte_annotations(&mut self) {
+        self.byte_annotations = vec![NO_ANNOTATION; self.data.len()];
+        for (i, a) in self.annotations.iter().enumerate() {
+            let end = a.range.end.min(self.data.len());
+            for b in &mut self.byte_annotations[a.range.start.min(end)..end] {
+                *b = i as u32;
+            }
+        }
+    }
+
+    fn annotation_at(&self, offset: usize) -> Option<(usize, &Annotation)> {
+        let index = *self.byte_annotations.get(offset)?;
+        (index != NO_ANNOTATION).then(|| (index as usize, &self.annotations[index as usize]))
+    }
+}
+
+/// Raw hex view of a tag, annotated with the fields of known structures
+#[derive(Default)]
+pub struct HexViewerPanel {
+    input: String,
+    error: Option<String>,
+    tag: Option<LoadedTag>,
+    /// Previously viewed tags, for going back after following a reference
+    history: Vec<TagHash>,
+    layout_override: Option<&'static StructLayout>,
+    /// Annotation to scroll to on the next frame
+    scroll_to: Option<usize>,
+}
+
+impl HexViewerPanel {
+    fn open(&mut self, hash: TagHash, push_history: bool) {
+        match LoadedTag::load(hash, self.layout_override) {
+            Ok(tag) => {
+                if push_history {
+                    if let Some(current) = &self.tag {
+                        self.history.push(current.hash);
+                    }
+                }
+                self.input = hash.to_string();
+                self.error = None;
+                self.tag = Some(tag);
+            }
+            Err(e) => self.error = Some(format!("Failed to read tag {hash}: {e}")),
+        }
+    }
+}
+
+impl GuiView for HexViewerPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if let Some(hash) = resources.get_mut::<HexViewerTarget>().0.take() {
+            self.open(hash, true);
+            windows.hex_viewer = true;
+        }
+
+        if !windows.hex_viewer {
+            return None;
+        }
+
+        let mut follow = None;
+        egui::Window::new(format!("{ICON_HEXADECIMAL} Hex Viewer"))
+            .default_size([900., 600.])
+            .open(&mut windows.hex_viewer)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.history.is_empty(),
+                            egui::Button::new(ICON_ARROW_LEFT.to_string()),
+                        )
+                        .on_hover_text("Back")
+                        .clicked()
+                    {
+                        if let Some(previous) = self.history.pop() {
+                            self.open(previous, false);
+                        }
+                    }
+
+                    let response = ui.text_edit_singleline(&mut self.input);
+                    if (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                        || ui.button("Open").clicked()
+                    {
+                        match crate::parse_taghash(self.input.trim()) {
+                            Ok(hash) => self.open(hash, true),
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+
+                    let selected = self.layout_override.map_or("Automatic", |l| l.name);
+                    let mut layout_override = self.layout_override;
+                    egui::ComboBox::from_id_source("hex_viewer_layout")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            if ui
+                                .selectable_label(layout_override.is_none(), "Automatic")
+                                .clicked()
+                            {
+                                layout_override = None;
+                            }
+                            for &layout in ROOT_LAYOUTS {
+                                let checked =
+                                    layout_override.is_some_and(|l| std::ptr::eq(l, layout));
+                                if ui.selectable_label(checked, layout.name).clicked() {
+                                    layout_override = Some(layout);
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(format!(
+                            "Structure used to annotate the tag. Only {} root structures have \
+                             known layouts",
+                            ROOT_LAYOUTS.len()
+                        ));
+                    if !same_layout(layout_override, self.layout_override) {
+                        self.layout_override = layout_override;
+                        if let Some(hash) = self.tag.as_ref().map(|t| t.hash) {
+                            self.open(hash, false);
+                        }
+                    }
+
+                    if let Some(tag) = &mut self.tag {
+                        if ui
+                            .button(format!("{ICON_MAGNIFY_SCAN} Scan Unknown"))
+                            .on_hover_text(
+                                "Find values in unannotated regions that resolve to existing tags",
+                            )
+                            .clicked()
+                        {
+                            tag.scan_unknown();
+                        }
+                    }
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if let Some(error) = self.tag.as_ref().and_then(|t| t.annotation_error.as_ref()) {
+                    ui.colored_label(Color32::RED, error);
+                }
+
+                let Some(tag) = &self.tag else {
+                    ui.label("Enter a tag hash to view its contents");
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    tag_hash_label(ui, tag.hash);
+                    ui.label(format!(
+                        "{} bytes, class {}, {}",
+                        tag.data.len(),
+                        tag.class
+                            .map_or("unknown".to_string(), |c| format!("{c:08X}")),
+                        tag.layout
+                            .map_or("no known layout".to_string(), |l| l.name.to_string())
+                    ));
+                });
+                ui.separator();
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(620.0);
+                        follow = follow.or(self.hex_ui(ui));
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        follow = follow.or(self.annotations_ui(ui));
+                    });
+                });
+            });
+
+        if let Some(hash) = follow {
+            self.open(hash, true);
+        }
+
+        None
+    }
+}
+
+impl HexViewerPanel {
+    /// Draws the hex dump, returns a tag reference that was clicked
+    fn hex_ui(&mut self, ui: &mut egui::Ui) -> Option<TagHash> {
+        let tag = self.tag.as_ref()?;
+        let mut follow = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let rows = tag.data.len().div_ceil(BYTES_PER_ROW);
+
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_source("hex_viewer_bytes")
+            .auto_shrink([false, false]);
+        if let Some(index) = self.scroll_to.take() {
+            let row = tag.annotations[index].range.start / BYTES_PER_ROW;
+            scroll_area = scroll_area
+                .vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+
+        scroll_area.show_rows(ui, row_height, rows, |ui, row_range| {
+            for row in row_range {
+                let start = row * BYTES_PER_ROW;
+                let end = (start + BYTES_PER_ROW).min(tag.data.len());
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.label(RichText::new(format!("{start:08X}")).monospace().weak());
+                    ui.add_space(8.0);
+
+                    for offset in start..start + BYTES_PER_ROW {
+                        let Some(&byte) = tag.data.get(offset) else {
+                            ui.label(RichText::new("  ").monospace());
+                            continue;
+                        };
+
+                        let annotation = tag.annotation_at(offset);
+                        let color = match annotation {
+                            Some((_, a)) if matches!(a.kind, AnnotationKind::TagReference(_)) => {
+                                REFERENCE_COLOR
+                            }
+                            Some((_, a)) if a.kind == AnnotationKind::ArrayHeader => HEADER_COLOR,
+                            Some((i, _)) => FIELD_COLORS[i % FIELD_COLORS.len()],
+                            None => ui.visuals().weak_text_color(),
+                        };
+
+                        let response = ui.add(
+                            egui::Label::new(
+                                RichText::new(format!("{byte:02X}"))
+                                    .monospace()
+                                    .color(color),
+                            )
+                            .sense(Sense::click()),
+                        );
+                        if let Some((_, a)) = annotation {
+                            let response = response.on_hover_text(format!(
+                                "0x{:X}: {} = {}",
+                                a.range.start, a.label, a.value
+                            ));
+                            if let AnnotationKind::TagReference(hash) = a.kind {
+                                if response.clicked() {
+                                    follow = Some(hash);
+                                }
+                            }
+                        }
+
+                        if offset % 8 == 7 {
+                            ui.add_space(6.0);
+                        }
+                    }
+
+                    let ascii = tag.data[start..end]
+                        .iter()
+                        .map(|&b| {
+                            if b.is_ascii_graphic() || b == b' ' {
+                                b as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect::<String>();
+                    ui.label(RichText::new(ascii).monospace().weak());
+                });
+            }
+        });
+
+        follow
+    }
+
+    /// Lists the annotated fields, returns a tag reference that was opened
+    fn annotations_ui(&mut self, ui: &mut egui::Ui) -> Option<TagHash> {
+        let tag = self.tag.as_ref()?;
+        let mut follow = None;
+        if tag.annotations.is_empty() {
+            ui.label(
+                "This tag has no known layout. Pick a structure above, or scan for tag references",
+            );
+            ui.label(
+                RichText::new(format!(
+                    "Layouts are only known for {}",
+                    ROOT_LAYOUTS.iter().map(|l| l.name).join(", ")
+                ))
+                .weak(),
+            );
+            return None;
+        }
+
+        egui::ScrollArea::vertical()
+            .id_source("hex_viewer_annotations")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                egui::Grid::new("hex_viewer_annotation_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, a) in tag.annotations.iter().enumerate() {
+                            if ui
+                                .selectable_label(
+                                    false,
+                                    RichText::new(format!("{:X}", a.range.start)).monospace(),
+                                )
+                                .on_hover_text("Scroll to offset")
+                                .clicked()
+                            {
+                                self.scroll_to = Some(i);
+                            }
+                            ui.label(&a.label);
+
+                            match a.kind {
+                                AnnotationKind::TagReference(hash) => {
+                                    if ui
+                                        .link(RichText::new(&a.value).monospace())
+                                        .tag_info(hash)
+                                        .clicked()
+                                    {
+                                        follow = Some(hash);
+                                    }
+                                }
+                                _ => {
+                                    ui.label(RichText::new(&a.value).monospace());
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        follow
+    }
+}
+
+fn same_layout(a: Option<&StructLayout>, b: Option<&StructLayout>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
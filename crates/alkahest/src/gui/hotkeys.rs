@@ -2,7 +2,7 @@ use alkahest_data::occlusion::Aabb;
 use alkahest_renderer::{
     camera::{
         tween::{ease_out_exponential, Tween},
-        Camera,
+        Camera, CameraMode,
     },
     ecs::{
         hierarchy::{Children, Parent},
@@ -15,63 +15,214 @@ use alkahest_renderer::{
     util::scene::SceneExt,
 };
 use bevy_ecs::entity::Entity;
+use egui::{Event, Key, KeyboardShortcut, Modifiers};
+use glam::Vec3;
 use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
 
 use crate::{
+    config,
+    gui::{
+        keybindings,
+        solo::{self, SoloState},
+    },
     maplist::MapList,
+    presentation::Presentation,
     resources::AppResources,
     util::action::{ActionList, TweenAction},
 };
 
-pub const SHORTCUT_DELETE: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::Delete);
-
-pub const SHORTCUT_HIDE: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::H);
-
-pub const SHORTCUT_UNHIDE_ALL: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::H);
-
-pub const SHORTCUT_HIDE_UNSELECTED: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::SHIFT, egui::Key::H);
-
-pub const SHORTCUT_DESELECT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
-    egui::Modifiers::CTRL.plus(egui::Modifiers::SHIFT),
-    egui::Key::A,
-);
-
-pub const SHORTCUT_FOCUS: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F);
-
-pub const SHORTCUT_GAZE: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::G);
+/// Every action that can be bound to a keyboard shortcut
+#[derive(
+    Serialize, Deserialize, EnumIter, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum HotkeyAction {
+    Focus,
+    Gaze,
+    ToggleWalkMode,
+    Orbit,
+
+    Deselect,
+    SelectParent,
+    SelectChild,
+    SelectNextChild,
+    SelectPrevChild,
+    Delete,
+    Duplicate,
+
+    Hide,
+    HideUnselected,
+    UnhideAll,
+    Solo,
+    SoloRestore,
+
+    ToolSelect,
+    ToolTranslate,
+    ToolRotate,
+    ToolScale,
+
+    MapSwap,
+    MapPrev,
+    MapNext,
+
+    AddRouteNodeNext,
+    AddRouteNodePrev,
+
+    OpenConsole,
+    ToggleInterface,
+}
 
-pub const SHORTCUT_MAP_SWAP: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::I);
+impl HotkeyAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HotkeyAction::Focus => "Focus on Selected Object",
+            HotkeyAction::Gaze => "Move Camera to Position of Gaze",
+            HotkeyAction::ToggleWalkMode => "Toggle Walk Mode",
+            HotkeyAction::Orbit => "Orbit Selected Object or Point Under Crosshair",
+            HotkeyAction::Deselect => "Deselect All Objects",
+            HotkeyAction::SelectParent => "Select Parent",
+            HotkeyAction::SelectChild => "Select First Child",
+            HotkeyAction::SelectNextChild => "Select Next Child",
+            HotkeyAction::SelectPrevChild => "Select Previous Child",
+            HotkeyAction::Delete => "Delete Selected Object",
+            HotkeyAction::Duplicate => "Duplicate Selected Object",
+            HotkeyAction::Hide => "Toggle Hide Selected Object",
+            HotkeyAction::HideUnselected => "Hide All Unselected Objects",
+            HotkeyAction::UnhideAll => "Unhide All Objects",
+            HotkeyAction::Solo => "Solo Selected Object",
+            HotkeyAction::SoloRestore => "Restore Solo",
+            HotkeyAction::ToolSelect => "Selection Tool",
+            HotkeyAction::ToolTranslate => "Translation Tool",
+            HotkeyAction::ToolRotate => "Rotation Tool",
+            HotkeyAction::ToolScale => "Scale Tool",
+            HotkeyAction::MapSwap => "Swap to Previous Map",
+            HotkeyAction::MapPrev => "Swap to Previous Map in List",
+            HotkeyAction::MapNext => "Swap to Next Map in List",
+            HotkeyAction::AddRouteNodeNext => "Add Node After Selected Node",
+            HotkeyAction::AddRouteNodePrev => "Add Node Before Selected Node",
+            HotkeyAction::OpenConsole => "Open Console",
+            HotkeyAction::ToggleInterface => "Toggle Interface",
+        }
+    }
 
-pub const SHORTCUT_MAP_PREV: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::PageUp);
+    pub fn category(&self) -> &'static str {
+        match self {
+            HotkeyAction::Focus
+            | HotkeyAction::Gaze
+            | HotkeyAction::ToggleWalkMode
+            | HotkeyAction::Orbit => "Camera",
+            HotkeyAction::Deselect
+            | HotkeyAction::SelectParent
+            | HotkeyAction::SelectChild
+            | HotkeyAction::SelectNextChild
+            | HotkeyAction::SelectPrevChild
+            | HotkeyAction::Delete
+            | HotkeyAction::Duplicate => "Selection",
+            HotkeyAction::Hide
+            | HotkeyAction::HideUnselected
+            | HotkeyAction::UnhideAll
+            | HotkeyAction::Solo
+            | HotkeyAction::SoloRestore => "Visibility",
+            HotkeyAction::ToolSelect
+            | HotkeyAction::ToolTranslate
+            | HotkeyAction::ToolRotate
+            | HotkeyAction::ToolScale => "Tools",
+            HotkeyAction::MapSwap | HotkeyAction::MapPrev | HotkeyAction::MapNext => "Maps",
+            HotkeyAction::AddRouteNodeNext | HotkeyAction::AddRouteNodePrev => "Route Editing",
+            HotkeyAction::OpenConsole | HotkeyAction::ToggleInterface => "Interface",
+        }
+    }
 
-pub const SHORTCUT_MAP_NEXT: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::PageDown);
+    pub fn default_shortcut(&self) -> KeyboardShortcut {
+        let (modifiers, key) = match self {
+            HotkeyAction::Focus => (Modifiers::NONE, Key::F),
+            HotkeyAction::Gaze => (Modifiers::NONE, Key::G),
+            HotkeyAction::ToggleWalkMode => (Modifiers::NONE, Key::V),
+            HotkeyAction::Orbit => (Modifiers::NONE, Key::O),
+            HotkeyAction::Deselect => (Modifiers::CTRL.plus(Modifiers::SHIFT), Key::A),
+            HotkeyAction::SelectParent => (Modifiers::NONE, Key::ArrowUp),
+            HotkeyAction::SelectChild => (Modifiers::NONE, Key::ArrowDown),
+            HotkeyAction::SelectNextChild => (Modifiers::NONE, Key::ArrowRight),
+            HotkeyAction::SelectPrevChild => (Modifiers::NONE, Key::ArrowLeft),
+            HotkeyAction::Delete => (Modifiers::SHIFT, Key::Delete),
+            HotkeyAction::Duplicate => (Modifiers::CTRL, Key::D),
+            HotkeyAction::Hide => (Modifiers::NONE, Key::H),
+            HotkeyAction::HideUnselected => (Modifiers::SHIFT, Key::H),
+            HotkeyAction::UnhideAll => (Modifiers::ALT, Key::H),
+            HotkeyAction::Solo => (Modifiers::CTRL, Key::H),
+            HotkeyAction::SoloRestore => (Modifiers::NONE, Key::Escape),
+            HotkeyAction::ToolSelect => (Modifiers::NONE, Key::Num1),
+            HotkeyAction::ToolTranslate => (Modifiers::NONE, Key::Num2),
+            HotkeyAction::ToolRotate => (Modifiers::NONE, Key::Num3),
+            HotkeyAction::ToolScale => (Modifiers::NONE, Key::Num4),
+            HotkeyAction::MapSwap => (Modifiers::NONE, Key::I),
+            HotkeyAction::MapPrev => (Modifiers::NONE, Key::PageUp),
+            HotkeyAction::MapNext => (Modifiers::NONE, Key::PageDown),
+            HotkeyAction::AddRouteNodeNext => (Modifiers::NONE, Key::Plus),
+            HotkeyAction::AddRouteNodePrev => (Modifiers::NONE, Key::Minus),
+            HotkeyAction::OpenConsole => (Modifiers::NONE, Key::F1),
+            HotkeyAction::ToggleInterface => (Modifiers::CTRL.plus(Modifiers::SHIFT), Key::H),
+        };
+
+        KeyboardShortcut::new(modifiers, key)
+    }
 
-pub const SHORTCUT_ADD_ROUTE_NODE_NEXT: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Plus);
+    /// The shortcut currently bound to this action
+    pub fn shortcut(&self) -> KeyboardShortcut {
+        config::with(|c| c.keybindings.get(self).copied())
+            .unwrap_or_else(|| self.default_shortcut())
+    }
 
-pub const SHORTCUT_ADD_ROUTE_NODE_PREV: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Minus);
+    /// Consumes the shortcut bound to this action, returns true if it was pressed. Modifiers have
+    /// to match exactly, so Ctrl+H doesn't also trigger on Ctrl+Shift+H
+    pub fn pressed(&self, ctx: &egui::Context) -> bool {
+        if keybindings::is_capturing(ctx) {
+            return false;
+        }
 
-pub const SHORTCUT_SELECT_PARENT: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::ArrowUp);
+        let shortcut = self.shortcut();
+        ctx.input_mut(|i| {
+            let mut pressed = false;
+            i.events.retain(|e| {
+                let matches = matches!(
+                    e,
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } if *key == shortcut.logical_key
+                        && modifiers_match_exactly(*modifiers, shortcut.modifiers)
+                );
+                pressed |= matches;
+                !matches
+            });
+            pressed
+        })
+    }
 
-pub const SHORTCUT_SELECT_CHILD: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::ArrowDown);
+    /// Human readable form of the shortcut bound to this action
+    pub fn shortcut_text(&self, ctx: &egui::Context) -> String {
+        ctx.format_shortcut(&self.shortcut())
+    }
 
-pub const SHORTCUT_SELECT_NEXT_CHILD: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::ArrowRight);
+    /// Other actions that are bound to the same shortcut as this one
+    pub fn conflicts(&self) -> Vec<HotkeyAction> {
+        let shortcut = self.shortcut();
+        HotkeyAction::iter()
+            .filter(|a| a != self && a.shortcut() == shortcut)
+            .collect()
+    }
+}
 
-pub const SHORTCUT_SELECT_PREV_CHILD: egui::KeyboardShortcut =
-    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::ArrowLeft);
+/// Whether `pressed` holds exactly the modifiers of `bound`. `command` is an alias for ctrl on this
+/// platform
+fn modifiers_match_exactly(pressed: Modifiers, bound: Modifiers) -> bool {
+    pressed.alt == bound.alt
+        && pressed.shift == bound.shift
+        && (pressed.ctrl || pressed.command) == (bound.ctrl || bound.command)
+}
 
 pub fn process_hotkeys(ctx: &egui::Context, resources: &mut AppResources) {
     // We're in a text input field, don't process hotkeys
@@ -79,63 +230,87 @@ pub fn process_hotkeys(ctx: &egui::Context, resources: &mut AppResources) {
         return;
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_UNHIDE_ALL)) {
+    if HotkeyAction::UnhideAll.pressed(ctx) {
         unhide_all(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_HIDE_UNSELECTED)) {
+    if HotkeyAction::Solo.pressed(ctx) {
+        solo_selected(resources);
+    }
+
+    // Escape also stops presentations, only take it while something is soloed
+    if solo_active(resources) && HotkeyAction::SoloRestore.pressed(ctx) {
+        if let Some(map) = resources.get_mut::<MapList>().current_map_mut() {
+            solo::restore_solo(&mut map.scene);
+        }
+    }
+
+    if HotkeyAction::HideUnselected.pressed(ctx) {
         hide_unselected(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_HIDE)) {
+    if HotkeyAction::Hide.pressed(ctx) {
         hide_selected(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_DESELECT)) {
+    if HotkeyAction::Deselect.pressed(ctx) {
         resources.get_mut::<SelectedEntity>().deselect();
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_MAP_SWAP)) {
+    if HotkeyAction::MapSwap.pressed(ctx) {
         let mut maplist = resources.get_mut::<MapList>();
         if let Some(prev) = maplist.previous_map {
             maplist.set_current_map(prev);
         }
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_MAP_PREV)) {
+    if HotkeyAction::MapPrev.pressed(ctx) {
         resources.get_mut::<MapList>().set_current_map_prev();
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_MAP_NEXT)) {
+    if HotkeyAction::MapNext.pressed(ctx) {
         resources.get_mut::<MapList>().set_current_map_next();
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_GAZE)) {
+    if HotkeyAction::Gaze.pressed(ctx) {
         goto_gaze(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_FOCUS)) {
+    if HotkeyAction::Focus.pressed(ctx) {
         focus_selected(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_SELECT_PARENT)) {
+    if HotkeyAction::ToggleWalkMode.pressed(ctx) {
+        let mut camera = resources.get_mut::<Camera>();
+        let mode = match camera.mode() {
+            CameraMode::Fly => CameraMode::Walk,
+            CameraMode::Walk | CameraMode::Orbit => CameraMode::Fly,
+        };
+        camera.set_mode(mode);
+    }
+
+    if HotkeyAction::Orbit.pressed(ctx) {
+        toggle_orbit(resources);
+    }
+
+    if HotkeyAction::SelectParent.pressed(ctx) {
         select_parent(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_SELECT_CHILD)) {
+    if HotkeyAction::SelectChild.pressed(ctx) {
         select_child(resources);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_SELECT_NEXT_CHILD)) {
+    if HotkeyAction::SelectNextChild.pressed(ctx) {
         select_child_offset(resources, true);
     }
 
-    if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_SELECT_PREV_CHILD)) {
+    if HotkeyAction::SelectPrevChild.pressed(ctx) {
         select_child_offset(resources, false);
     }
 }
 
-fn focus_selected(resources: &mut AppResources) {
+pub(crate) fn focus_selected(resources: &AppResources) {
     let mut maps = resources.get_mut::<MapList>();
     let Some(map) = maps.current_map_mut() else {
         return;
@@ -147,33 +322,19 @@ fn focus_selected(resources: &mut AppResources) {
     };
 
     let mut cam = resources.get_mut::<Camera>();
-    let bounds = map.scene.get::<Aabb>(selected_entity).cloned();
-
-    let (center, radius) = if let Some(transform) = map.scene.get::<Transform>(selected_entity) {
-        if let Some(bounds) = bounds {
-            (
-                transform.local_to_world().transform_point3(bounds.center()),
-                bounds.radius(),
-            )
-        } else {
-            (transform.translation, 1.0)
-        }
-    } else {
-        if let Some(bounds) = bounds {
-            (bounds.center(), bounds.radius())
-        } else {
-            return;
-        }
+    let Some((center, radius)) = entity_bounds(&map.scene, selected_entity) else {
+        return;
     };
 
-    // Calculate the vertical field of view in radians
-    let half_fov_y = (cam.fov() * 0.5).to_radians();
-
-    // Calculate the distance required to fit the sphere in the frustum vertically
-    let distance = radius / half_fov_y.tan();
+    // Orbiting cameras keep looking at their pivot, so move that instead
+    if cam.mode() == CameraMode::Orbit {
+        let distance = framing_distance(&cam, radius);
+        cam.orbit_around(center, distance);
+        return;
+    }
 
     // Adjust the camera's position to ensure the sphere fits in the frustum
-    let target_position = center - cam.forward().normalize() * (distance * 1.75);
+    let target_position = center - cam.forward().normalize() * framing_distance(&cam, radius);
     cam.tween = Some(Tween::new(
         ease_out_exponential,
         Some((cam.position(), target_position)),
@@ -182,6 +343,90 @@ fn focus_selected(resources: &mut AppResources) {
     ));
 }
 
+/// Distance from which a sphere with the given radius fills most of the view
+fn framing_distance(camera: &Camera, radius: f32) -> f32 {
+    // Calculate the vertical field of view in radians
+    let half_fov_y = (camera.fov() * 0.5).to_radians();
+
+    // Calculate the distance required to fit the sphere in the frustum vertically
+    let distance = radius / half_fov_y.tan();
+
+    distance * 1.75
+}
+
+/// Orbits around the selected entity, or the point under the crosshair if nothing is selected.
+/// Goes back to flying if the camera is already orbiting
+fn toggle_orbit(resources: &AppResources) {
+    let mut camera = resources.get_mut::<Camera>();
+    if camera.mode() == CameraMode::Orbit {
+        camera.set_mode(CameraMode::Fly);
+        return;
+    }
+
+    let selected_bounds = resources.get::<SelectedEntity>().selected().and_then(|e| {
+        resources
+            .get::<MapList>()
+            .current_map()
+            .and_then(|map| entity_bounds(&map.scene, e))
+    });
+
+    if let Some((center, radius)) = selected_bounds {
+        let distance = framing_distance(&camera, radius);
+        camera.orbit_around(center, distance);
+        return;
+    }
+
+    let (d, pos) = resources
+        .get::<RendererShared>()
+        .data
+        .lock()
+        .gbuffers
+        .depth_buffer_distance_pos_center(&camera);
+    if d.is_finite() {
+        camera.orbit_around(pos, d);
+    }
+}
+
+/// World space center and radius of an entity, for framing it with the camera
+pub(crate) fn entity_bounds(scene: &Scene, entity: Entity) -> Option<(Vec3, f32)> {
+    let bounds = scene.get::<Aabb>(entity).cloned();
+
+    if let Some(transform) = scene.get::<Transform>(entity) {
+        if let Some(bounds) = bounds {
+            Some((
+                transform.local_to_world().transform_point3(bounds.center()),
+                bounds.radius(),
+            ))
+        } else {
+            Some((transform.translation, 1.0))
+        }
+    } else {
+        bounds.map(|bounds| (bounds.center(), bounds.radius()))
+    }
+}
+
+fn solo_active(resources: &AppResources) -> bool {
+    if resources.get::<Presentation>().is_active() {
+        return false;
+    }
+
+    resources
+        .get::<MapList>()
+        .current_map()
+        .is_some_and(|map| map.scene.contains_resource::<SoloState>())
+}
+
+fn solo_selected(resources: &mut AppResources) {
+    let mut maps = resources.get_mut::<MapList>();
+    let Some(map) = maps.current_map_mut() else {
+        return;
+    };
+
+    if let Some(selected) = resources.get::<SelectedEntity>().selected() {
+        solo::solo_entity(&mut map.scene, selected);
+    }
+}
+
 fn hide_unselected(resources: &mut AppResources) {
     let mut maps = resources.get_mut::<MapList>();
     let Some(map) = maps.current_map_mut() else {
@@ -196,9 +441,10 @@ fn hide_unselected(resources: &mut AppResources) {
     let entity_parents: FxHashSet<Entity> = get_ancestors(&map.scene, selected_entity)
         .into_iter()
         .collect();
-    for e in map.scene.iter_entities() {
+    let (scene, mut commands) = map.scene_and_commands();
+    for e in scene.iter_entities() {
         if e.id() != selected_entity && !entity_parents.contains(&e.id()) {
-            map.commands().entity(e.id()).insert((Visibility::Hidden,));
+            commands.entity(e.id()).insert((Visibility::Hidden,));
         }
     }
 }
@@ -231,7 +477,7 @@ fn hide_selected(resources: &mut AppResources) {
     }
 }
 
-fn get_ancestors(scene: &Scene, entity: Entity) -> Vec<Entity> {
+pub(crate) fn get_ancestors(scene: &Scene, entity: Entity) -> Vec<Entity> {
     if let Some(parent) = scene.get::<Parent>(entity) {
         let mut parents = vec![parent.0];
         parents.append(&mut get_ancestors(scene, parent.0));
@@ -244,6 +490,8 @@ fn get_ancestors(scene: &Scene, entity: Entity) -> Vec<Entity> {
 fn unhide_all(resources: &mut AppResources) {
     let mut maps = resources.get_mut::<MapList>();
     if let Some(map) = maps.current_map_mut() {
+        // Everything is visible again, there's nothing left for solo mode to restore
+        map.scene.remove_resource::<SoloState>();
         map.scene
             .query::<&mut Visibility>()
             .iter_mut(&mut map.scene)
@@ -319,3 +567,17 @@ fn select_child_offset(resources: &mut AppResources, add: bool) {
         }
     }
 }
+
+#[test]
+fn test_exact_modifiers() {
+    let ctrl_shift = Modifiers::CTRL.plus(Modifiers::SHIFT);
+    assert!(modifiers_match_exactly(Modifiers::CTRL, Modifiers::CTRL));
+    assert!(!modifiers_match_exactly(ctrl_shift, Modifiers::CTRL));
+    assert!(!modifiers_match_exactly(Modifiers::CTRL, ctrl_shift));
+    assert!(!modifiers_match_exactly(Modifiers::SHIFT, Modifiers::NONE));
+    // Key events on Windows set both `ctrl` and `command`
+    assert!(modifiers_match_exactly(
+        Modifiers::CTRL.plus(Modifiers::COMMAND),
+        Modifiers::CTRL
+    ));
+}
@@ -0,0 +1,213 @@
+use alkahest_renderer::{
+    ecs::{
+        animation::{AnimationLoopMode, TransformAnimation},
+        transform::Transform,
+        Scene,
+    },
+    icons::{
+        ICON_ANIMATION_PLAY, ICON_DELETE, ICON_PAUSE, ICON_PLAY, ICON_RECORD, ICON_SKIP_BACKWARD,
+        ICON_SKIP_FORWARD,
+    },
+};
+use bevy_ecs::{prelude::EntityRef, system::Commands};
+use egui::{RichText, Widget};
+use strum::IntoEnumIterator;
+
+use crate::{gui::inspector::ComponentPanel, resources::AppResources};
+
+/// Spacing of keyframes added after the last one, in seconds
+const APPEND_INTERVAL: f32 = 1.0;
+
+impl ComponentPanel for TransformAnimation {
+    fn inspector_name() -> &'static str {
+        "Transform Animation"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_ANIMATION_PLAY
+    }
+
+    fn show_inspector_ui(
+        &mut self,
+        _: &Scene,
+        cmd: &mut Commands<'_, '_>,
+        e: EntityRef<'_>,
+        ui: &mut egui::Ui,
+        _: &AppResources,
+    ) {
+        let Some(transform) = e.get::<Transform>().copied() else {
+            return;
+        };
+
+        ui.label(
+            RichText::new("Keyframes are recorded by hand, mover paths aren't read from the game")
+                .weak(),
+        );
+
+        // Set when the transform has to follow a change made while paused
+        let mut seek = false;
+        let duration = self.duration();
+        ui.horizontal(|ui| {
+            if ui
+                .button(ICON_SKIP_BACKWARD.to_string())
+                .on_hover_text("Rest position")
+                .clicked()
+            {
+                self.time = 0.0;
+                self.playing = false;
+                seek = true;
+            }
+
+            if self.playing {
+                if ui.button(format!("{ICON_PAUSE} Pause")).clicked() {
+                    self.playing = false;
+                }
+            } else if ui
+                .add_enabled(
+                    self.keyframes().len() > 1,
+                    egui::Button::new(format!("{ICON_PLAY} Play")),
+                )
+                .clicked()
+            {
+                if self.loop_mode == AnimationLoopMode::Once && self.time >= duration {
+                    self.time = 0.0;
+                }
+                self.playing = true;
+            }
+
+            if ui
+                .button(ICON_SKIP_FORWARD.to_string())
+                .on_hover_text("End position")
+                .clicked()
+            {
+                self.time = duration;
+                self.playing = false;
+                seek = true;
+            }
+
+            let mut local_time = self.local_time();
+            if egui::Slider::new(&mut local_time, 0.0..=duration.max(0.001))
+                .suffix(" s")
+                .ui(ui)
+                .changed()
+            {
+                self.time = local_time;
+                self.playing = false;
+                seek = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            egui::DragValue::new(&mut self.speed)
+                .range(0.01..=10.0)
+                .speed(0.01)
+                .prefix("Speed ")
+                .suffix("x")
+                .ui(ui);
+
+            egui::ComboBox::from_id_source("transform_animation_loop")
+                .selected_text(self.loop_mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in AnimationLoopMode::iter() {
+                        ui.selectable_value(&mut self.loop_mode, mode, mode.to_string());
+                    }
+                });
+        });
+
+        ui.separator();
+        ui.label(
+            "Move the entity and record its transform as a keyframe. The first keyframe is the \
+             rest position",
+        );
+        ui.horizontal(|ui| {
+            let local_time = self.local_time();
+            if ui
+                .button(format!("{ICON_RECORD} Record at {local_time:.2}s"))
+                .on_hover_text("Replaces the keyframe at this time, if there is one")
+                .clicked()
+            {
+                self.record(local_time, &transform);
+            }
+
+            let append_time = duration + APPEND_INTERVAL;
+            if ui
+                .button(format!("{ICON_RECORD} Append at {append_time:.2}s"))
+                .clicked()
+            {
+                self.record(append_time, &transform);
+                self.time = append_time;
+            }
+        });
+
+        let mut remove = None;
+        let mut retime = None;
+        let mut go_to = None;
+        egui::Grid::new("transform_animation_keyframes")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, keyframe) in self.keyframes().iter().enumerate() {
+                    let mut time = keyframe.time;
+                    if egui::DragValue::new(&mut time)
+                        .range(0.0..=f32::INFINITY)
+                        .speed(0.05)
+                        .suffix(" s")
+                        .ui(ui)
+                        .changed()
+                    {
+                        retime = Some((i, time));
+                    }
+
+                    ui.label(format!(
+                        "{:.2}, {:.2}, {:.2}",
+                        keyframe.translation.x, keyframe.translation.y, keyframe.translation.z
+                    ));
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Go to").clicked() {
+                            go_to = Some(keyframe.time);
+                        }
+                        if self.keyframes().len() > 1
+                            && ui.button(ICON_DELETE.to_string()).clicked()
+                        {
+                            remove = Some(i);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        if let Some(time) = go_to {
+            self.time = time;
+            self.playing = false;
+            seek = true;
+        }
+        if let Some((i, time)) = retime {
+            self.set_keyframe_time(i, time);
+        }
+        if let Some(i) = remove {
+            self.remove_keyframe(i);
+            seek = true;
+        }
+
+        ui.separator();
+        if ui
+            .button("Remove animation")
+            .on_hover_text("Moves the entity back to its rest position")
+            .clicked()
+        {
+            self.time = 0.0;
+            let mut rest = transform;
+            self.apply(&mut rest);
+            cmd.entity(e.id()).insert(rest);
+            cmd.entity(e.id()).remove::<TransformAnimation>();
+            return;
+        }
+
+        if seek {
+            let mut new_transform = transform;
+            self.apply(&mut new_transform);
+            cmd.entity(e.id()).insert(new_transform);
+        }
+    }
+}
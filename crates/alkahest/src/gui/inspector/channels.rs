@@ -25,7 +25,7 @@ impl ComponentPanel for ObjectChannels {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut alkahest_renderer::ecs::Scene,
+        _: &'s alkahest_renderer::ecs::Scene,
         _: &mut Commands<'_, '_>,
         _: bevy_ecs::world::EntityRef<'s>,
         ui: &mut egui::Ui,
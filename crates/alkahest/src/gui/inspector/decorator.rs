@@ -19,7 +19,7 @@ impl ComponentPanel for DecoratorRenderer {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'s>,
         ui: &mut Ui,
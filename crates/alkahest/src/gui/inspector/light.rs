@@ -1,10 +1,14 @@
 use alkahest_data::map::{SLight, SLightCollection, SShadowingLight};
 use alkahest_renderer::{
+    camera::CameraProjection,
     ecs::{
-        hierarchy::Children, map::CubemapVolume, render::light::LightRenderer,
-        transform::Transform, Scene,
+        hierarchy::Children,
+        map::CubemapVolume,
+        render::light::{LightRenderer, ShadowMapRenderer},
+        transform::Transform,
+        Scene,
     },
-    icons::{ICON_LIGHTBULB_GROUP, ICON_LIGHTBULB_ON},
+    icons::{ICON_BOX_SHADOW, ICON_LIGHTBULB_GROUP, ICON_LIGHTBULB_ON, ICON_RESTORE},
     renderer::RendererShared,
     util::color::Color,
 };
@@ -24,7 +28,7 @@ impl ComponentPanel for SLightCollection {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        scene: &'s mut Scene,
+        scene: &'s Scene,
         _: &mut Commands<'_, '_>,
         e: EntityRef<'s>,
         _ui: &mut Ui,
@@ -36,10 +40,10 @@ impl ComponentPanel for SLightCollection {
         };
 
         for child in &children.0 {
-            if let Ok((light, transform)) = scene
-                .query::<(&LightRenderer, &Transform)>()
-                .get(scene, *child)
-            {
+            if let (Some(light), Some(transform)) = (
+                scene.get::<LightRenderer>(*child),
+                scene.get::<Transform>(*child),
+            ) {
                 renderer.immediate.cube_outline(
                     transform.local_to_world() * light.projection_matrix,
                     Color::from_rgb(1.0, 1.0, 0.0),
@@ -66,7 +70,7 @@ impl ComponentPanel for LightRenderer {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         e: EntityRef<'s>,
         ui: &mut Ui,
@@ -141,7 +145,7 @@ impl ComponentPanel for CubemapVolume {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         e: EntityRef<'s>,
         _: &mut Ui,
@@ -158,3 +162,213 @@ impl ComponentPanel for CubemapVolume {
         );
     }
 }
+
+impl ComponentPanel for ShadowMapRenderer {
+    fn inspector_name() -> &'static str {
+        "Shadow Map"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_BOX_SHADOW
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        scene: &'s Scene,
+        _: &mut Commands<'_, '_>,
+        e: EntityRef<'s>,
+        ui: &mut Ui,
+        resources: &AppResources,
+    ) {
+        let renderer = resources.get::<RendererShared>();
+        let default_resolution = renderer.settings().shadow_quality.resolution();
+        ui.horizontal(|ui| {
+            ui.strong("Resolution:");
+            let mut resolution = self.resolution_override();
+            egui::ComboBox::from_id_source("shadow_resolution_override")
+                .selected_text(match resolution {
+                    Some(r) => format!("{r}x{r}"),
+                    None => format!("Quality preset ({0}x{0})", default_resolution),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut resolution, None, "Quality preset");
+                    for r in SHADOW_RESOLUTIONS {
+                        ui.selectable_value(&mut resolution, Some(r), format!("{r}x{r}"));
+                    }
+                });
+
+            if resolution != self.resolution_override() {
+                self.set_resolution_override(&renderer.gpu, resolution, default_resolution);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.strong("Depth Bias:");
+            let mut overridden = self.depth_bias_override().is_some();
+            let mut bias = self.depth_bias_override().unwrap_or(5);
+            ui.checkbox(&mut overridden, "Override").on_hover_text(
+                "Replace the depth bias of the shadow casters. Raise it to get rid of shadow acne, \
+                 lower it when shadows detach from their casters",
+            );
+            ui.add_enabled(
+                overridden,
+                egui::DragValue::new(&mut bias).range(-100..=1000),
+            );
+
+            if let Err(e) = self.set_depth_bias_override(&renderer.gpu, overridden.then_some(bias))
+            {
+                error!("Failed to override shadow depth bias: {e:?}");
+            }
+        });
+
+        let other_usage: usize = scene
+            .iter_entities()
+            .filter(|other| other.id() != e.id())
+            .filter_map(|other| other.get::<ShadowMapRenderer>())
+            .map(ShadowMapRenderer::memory_usage)
+            .sum();
+        ui.horizontal(|ui| {
+            ui.strong("Memory:");
+            ui.label(format!(
+                "{:.1} MiB ({:.1} MiB for all shadow maps)",
+                self.memory_usage() as f64 / (1024.0 * 1024.0),
+                (self.memory_usage() + other_usage) as f64 / (1024.0 * 1024.0)
+            ));
+        });
+
+        ui.horizontal(|ui| {
+            ui.strong("Frustum:");
+            if self.is_projection_overridden() {
+                ui.label(RichText::new("Manual").color(Color32::GOLD));
+                if ui
+                    .button(format!("{ICON_RESTORE} Reset to automatic"))
+                    .clicked()
+                {
+                    self.reset_projection();
+                }
+            } else {
+                ui.label("Automatic");
+            }
+        });
+
+        let mut projection = self.projection().clone();
+        let changed = egui::Grid::new("shadow_frustum_grid")
+            .num_columns(2)
+            .show(ui, |ui| projection_ui(ui, &mut projection))
+            .inner;
+
+        if changed {
+            self.set_projection(projection);
+        }
+
+        if let Some(transform) = e.get::<Transform>() {
+            let color = if self.is_projection_overridden() {
+                Color::from_rgb(1.0, 0.5, 0.0)
+            } else {
+                Color::from_rgb(0.6, 0.6, 1.0)
+            };
+
+            draw_frustum(resources, &self.frustum_corners(transform), color);
+        }
+    }
+}
+
+const SHADOW_RESOLUTIONS: [u32; 6] = [256, 512, 1024, 2048, 4096, 8192];
+
+/// Returns true if the projection was changed
+fn projection_ui(ui: &mut Ui, projection: &mut CameraProjection) -> bool {
+    let mut changed = false;
+    match projection {
+        CameraProjection::Perspective { fov, near } => {
+            ui.strong("FoV:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(fov)
+                        .speed(0.25)
+                        .range(1.0..=179.0)
+                        .suffix("°"),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.strong("Near Plane:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(near)
+                        .speed(0.05)
+                        .range(0.01..=f32::MAX),
+                )
+                .changed();
+            ui.end_row();
+        }
+        CameraProjection::PerspectiveBounded { fov, near, far } => {
+            ui.strong("FoV:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(fov)
+                        .speed(0.25)
+                        .range(1.0..=179.0)
+                        .suffix("°"),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.strong("Near Plane:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(near)
+                        .speed(0.05)
+                        .range(0.01..=*far - 0.01),
+                )
+                .changed();
+            ui.end_row();
+
+            ui.strong("Far Plane:");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(far)
+                        .speed(0.25)
+                        .range(*near + 0.01..=f32::MAX),
+                )
+                .changed();
+            ui.end_row();
+        }
+        CameraProjection::Orthographic { extents } => {
+            ui.strong("Extents:");
+            ui.horizontal(|ui| {
+                for c in extents.as_mut() {
+                    changed |= ui
+                        .add(egui::DragValue::new(c).speed(0.25).range(0.01..=f32::MAX))
+                        .changed();
+                }
+            });
+            ui.end_row();
+        }
+    }
+
+    changed
+}
+
+fn draw_frustum(resources: &AppResources, corners: &[glam::Vec3; 8], color: Color) {
+    let renderer = resources.get::<RendererShared>();
+
+    // Corners are ordered by bits: x = 1, y = 2, z (near/far) = 4
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (2, 3),
+        (0, 2),
+        (1, 3),
+        (4, 5),
+        (6, 7),
+        (4, 6),
+        (5, 7),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+
+    for (a, b) in EDGES {
+        renderer.immediate.line(corners[a], corners[b], color, 1.5);
+    }
+}
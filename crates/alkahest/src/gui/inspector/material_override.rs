@@ -0,0 +1,309 @@
+use alkahest_data::tfx::TfxRenderStage;
+use alkahest_renderer::{
+    ecs::render::TechniqueOverride,
+    handle::Handle,
+    icons::{ICON_DELETE, ICON_FOLDER_OPEN, ICON_SWAP_HORIZONTAL, ICON_TEXTURE, ICON_UNDO},
+    loaders::TextureSlot,
+    renderer::RendererShared,
+    tfx::{technique::Technique, view::RenderStageSubscriptions},
+};
+use destiny_pkg::TagHash;
+use egui::{Color32, RichText, Ui};
+use itertools::Itertools;
+
+use crate::{
+    gui::{console::parse_extended_hash, util::TagHashResponseExt},
+    resources::AppResources,
+};
+
+/// Maximum number of loaded techniques listed in the technique dropdown
+const MAX_LISTED_TECHNIQUES: usize = 256;
+
+#[derive(Clone, Default)]
+struct OverrideUiState {
+    stage: Option<TfxRenderStage>,
+    hash_input: String,
+    filter: String,
+}
+
+/// Lets the user replace the techniques of a model in one of its render stages
+pub(super) fn technique_override_ui(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    technique_override: &mut Option<TechniqueOverride>,
+    original_techniques: &[Handle<Technique>],
+    subscribed_stages: RenderStageSubscriptions,
+    resources: &AppResources,
+) {
+    let id = ui.make_persistent_id(("technique_override", id_source));
+    let mut state = ui.data_mut(|d| d.get_temp::<OverrideUiState>(id).unwrap_or_default());
+
+    let stages = TfxRenderStage::VARIANTS
+        .into_iter()
+        .filter(|s| subscribed_stages.is_subscribed(*s))
+        .collect_vec();
+    let stage = *state.stage.get_or_insert_with(|| {
+        if stages.contains(&TfxRenderStage::GenerateGbuffer) {
+            TfxRenderStage::GenerateGbuffer
+        } else {
+            stages
+                .first()
+                .copied()
+                .unwrap_or(TfxRenderStage::GenerateGbuffer)
+        }
+    });
+
+    let mut selected_hash = None;
+    egui::CollapsingHeader::new(
+        RichText::new(format!("{ICON_SWAP_HORIZONTAL} Material Override")).strong(),
+    )
+    .id_source(id.with("header"))
+    .show(ui, |ui| {
+        if let Some(current) = technique_override.as_ref() {
+            let text = format!(
+                "Drawing {} in {:?}",
+                current
+                    .technique
+                    .id()
+                    .tiger_taghash()
+                    .unwrap_or(TagHash::NONE),
+                current.stage
+            );
+            let reset = ui
+                .horizontal(|ui| {
+                    ui.label(RichText::new(text).color(Color32::GOLD));
+                    ui.button("Reset").clicked()
+                })
+                .inner;
+            if reset {
+                *technique_override = None;
+            }
+        }
+
+        egui::ComboBox::from_label("Stage")
+            .selected_text(format!("{stage:?}"))
+            .show_ui(ui, |ui| {
+                for s in &stages {
+                    ui.selectable_value(&mut state.stage, Some(*s), format!("{s:?}"));
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut state.hash_input)
+                    .hint_text("Technique hash")
+                    .desired_width(96.0),
+            );
+            if ui.button("Apply").clicked() {
+                match parse_extended_hash(state.hash_input.trim()) {
+                    Ok(hash) => selected_hash = Some(hash.hash32()),
+                    Err(e) => error!("Invalid technique hash '{}': {e}", state.hash_input),
+                }
+            }
+        });
+
+        let renderer = resources.get::<RendererShared>();
+        ui.label("Techniques of this model");
+        ui.horizontal_wrapped(|ui| {
+            for hash in original_techniques
+                .iter()
+                .filter_map(|h| h.id().tiger_taghash())
+                .unique()
+            {
+                if ui.small_button(hash.to_string()).clicked() {
+                    selected_hash = Some(hash);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Loaded techniques");
+            ui.add(
+                egui::TextEdit::singleline(&mut state.filter)
+                    .hint_text("Filter")
+                    .desired_width(96.0),
+            );
+        });
+        let filter = state.filter.to_lowercase();
+        let loaded = renderer
+            .data
+            .lock()
+            .asset_manager
+            .techniques
+            .iter_loaded()
+            .filter_map(|(id, _)| id.tiger_taghash())
+            .filter(|h| h.to_string().to_lowercase().contains(&filter))
+            .sorted_by_key(|h| h.0)
+            .collect_vec();
+
+        egui::ScrollArea::vertical()
+            .id_source(id.with("loaded"))
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for hash in loaded.iter().take(MAX_LISTED_TECHNIQUES) {
+                    if ui
+                        .selectable_label(false, hash.to_string())
+                        .tag_info(*hash)
+                        .clicked()
+                    {
+                        selected_hash = Some(*hash);
+                    }
+                }
+
+                if loaded.len() > MAX_LISTED_TECHNIQUES {
+                    ui.label(
+                        RichText::new(format!(
+                            "...and {} more",
+                            loaded.len() - MAX_LISTED_TECHNIQUES
+                        ))
+                        .weak(),
+                    );
+                }
+            });
+
+        if let Some(hash) = selected_hash {
+            *technique_override = Some(TechniqueOverride {
+                technique: renderer
+                    .data
+                    .lock()
+                    .asset_manager
+                    .get_or_load_technique(hash),
+                stage: state.stage.unwrap_or(stage),
+            });
+        }
+    });
+
+    ui.data_mut(|d| d.insert_temp(id, state));
+
+    let techniques = original_techniques
+        .iter()
+        .chain(technique_override.as_ref().map(|o| &o.technique))
+        .unique_by(|h| h.id())
+        .collect_vec();
+    texture_override_ui(ui, id, &techniques, resources);
+}
+
+/// Lets the user replace the textures of techniques with PNG or DDS files from disk
+fn texture_override_ui(
+    ui: &mut Ui,
+    id: egui::Id,
+    techniques: &[&Handle<Technique>],
+    resources: &AppResources,
+) {
+    egui::CollapsingHeader::new(
+        RichText::new(format!("{ICON_TEXTURE} Texture Overrides")).strong(),
+    )
+    .id_source(id.with("textures"))
+    .show(ui, |ui| {
+        ui.label(RichText::new("Overrides apply to every model using the technique").weak());
+
+        let renderer = resources.get::<RendererShared>();
+        // Slot to load a texture into, and whether it should be loaded as sRGB
+        let mut load = None;
+        let mut reset = None;
+        let mut clear = false;
+        {
+            let data = renderer.data.lock();
+            let am = &data.asset_manager;
+            for handle in techniques {
+                let Some(technique) = am.techniques.get(handle) else {
+                    continue;
+                };
+
+                ui.label(RichText::new(format!("Technique {}", technique.hash)).strong());
+                for (_, stage) in technique.all_stages() {
+                    let Some(stage) = stage else {
+                        continue;
+                    };
+
+                    for (slot, texture) in stage.textures.iter() {
+                        if texture.is_none() {
+                            continue;
+                        }
+
+                        let slot = TextureSlot {
+                            technique: technique.hash,
+                            stage: stage.stage,
+                            slot: *slot,
+                        };
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} t{}", stage.stage.short_name(), slot.slot));
+                            match am.texture_override(slot) {
+                                Some(texture_override) => {
+                                    let name = am
+                                        .texture_file(texture_override.id())
+                                        .and_then(|p| {
+                                            p.file_name().map(|n| n.to_string_lossy().to_string())
+                                        })
+                                        .unwrap_or_default();
+                                    ui.label(RichText::new(name).color(Color32::GOLD));
+                                    if ui
+                                        .small_button(ICON_UNDO.to_string())
+                                        .on_hover_text("Restore the original texture")
+                                        .clicked()
+                                    {
+                                        reset = Some(slot);
+                                    }
+                                }
+                                None => {
+                                    let hash =
+                                        texture.id().tiger_taghash().unwrap_or(TagHash::NONE);
+                                    ui.label(hash.to_string()).tag_info(hash);
+                                }
+                            }
+
+                            if ui
+                                .small_button(ICON_FOLDER_OPEN.to_string())
+                                .on_hover_text("Replace with a PNG or DDS file")
+                                .clicked()
+                            {
+                                let srgb = am
+                                    .textures
+                                    .get(texture)
+                                    .map_or(false, |t| t.format.is_srgb());
+                                load = Some((slot, srgb));
+                            }
+                        });
+                    }
+                }
+            }
+
+            if am.texture_override_count() > 0 {
+                clear = ui
+                    .button(format!(
+                        "{ICON_DELETE} Clear all {} overrides",
+                        am.texture_override_count()
+                    ))
+                    .clicked();
+            }
+        }
+
+        if clear {
+            renderer.data.lock().asset_manager.clear_texture_overrides();
+        }
+
+        if let Some(slot) = reset {
+            renderer
+                .data
+                .lock()
+                .asset_manager
+                .set_texture_override(slot, None);
+        }
+
+        if let Some((slot, srgb)) = load {
+            // The dialog blocks until it's closed, so the renderer can't be locked here
+            match native_dialog::FileDialog::new()
+                .add_filter("Textures", &["png", "dds"])
+                .show_open_single_file()
+            {
+                Ok(Some(path)) => {
+                    let mut data = renderer.data.lock();
+                    let texture = data.asset_manager.load_texture_file(path, srgb);
+                    data.asset_manager.set_texture_override(slot, Some(texture));
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to open the file dialog: {e}"),
+            }
+        }
+    });
+}
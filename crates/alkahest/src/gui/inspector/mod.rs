@@ -1,54 +1,79 @@
+mod animation;
 mod channels;
 mod decorator;
 mod light;
+mod material_override;
+mod prefab;
+mod render_stages;
+mod source_data;
+mod statics;
+mod terrain;
 mod util;
 use alkahest_data::map::{SLightCollection, SRespawnPoint};
 use alkahest_renderer::{
     camera::Camera,
     ecs::{
+        animation::TransformAnimation,
         channels::ObjectChannels,
         common::{Global, Label, Mutable},
         hierarchy::{Children, Parent},
         map::{CubemapVolume, NodeMetadata},
         render::{
-            decorators::DecoratorRenderer, dynamic_geometry::DynamicModelComponent,
-            light::LightRenderer,
+            decorators::DecoratorRenderer,
+            dynamic_geometry::DynamicModelComponent,
+            has_geometry,
+            light::{LightRenderer, ShadowMapRenderer},
+            stages::entity_render_stages,
+            static_geometry::{StaticInstances, StaticModelSingle},
+            terrain::TerrainPatches,
+            ShowWireframe,
         },
         resources::SelectedEntity,
         route::{Route, RouteNode},
+        skeleton::Skeleton,
         tags::{insert_tag, remove_tag, EntityTag, Tags},
         transform::{OriginalTransform, Transform, TransformFlags},
-        utility::{Beacon, Ruler, Sphere},
+        utility::{Beacon, ClipPlane, Ruler, Sphere},
         visibility::{Visibility, VisibilityHelper},
         Scene,
     },
     icons::{
-        ICON_ACCOUNT_CONVERT, ICON_EYE_ARROW_RIGHT_OUTLINE, ICON_HUMAN_MALE,
-        ICON_HUMAN_MALE_FEMALE_CHILD, ICON_POKEBALL,
+        ICON_ACCOUNT_CONVERT, ICON_ANIMATION_PLAY, ICON_CODE_BRACES, ICON_CONTENT_DUPLICATE,
+        ICON_EXPORT, ICON_EYE_ARROW_RIGHT_OUTLINE, ICON_HUMAN_MALE, ICON_HUMAN_MALE_FEMALE_CHILD,
+        ICON_LAYERS, ICON_MIRROR, ICON_POKEBALL, ICON_SPOTLIGHT_BEAM, ICON_TAG_PLUS,
+        ICON_VECTOR_TRIANGLE,
     },
     renderer::RendererShared,
-    shader::shader_ball::ShaderBallComponent,
-    util::{black_magic::EntityRefDarkMagic, Hocus},
+    shader::shader_ball::{LightProbeMaterial, LightProbeSphere, ShaderBallComponent},
+    util::black_magic::EntityRefDarkMagic,
 };
-use bevy_ecs::{entity::Entity, prelude::EntityRef, system::Commands};
+use anyhow::Context;
+use bevy_ecs::{entity::Entity, prelude::EntityRef, system::Commands, world::CommandQueue};
 pub use channels::FnvWordlist;
 use egui::{Align2, Color32, FontId, Key, RichText, Ui, Widget};
-use glam::{Quat, Vec3};
+use glam::{Mat4, Quat, Vec3};
+use strum::IntoEnumIterator;
 use winit::window::Window;
 
 use crate::{
+    config,
     gui::{
         chip::EcsTagsExt,
-        context::{GuiCtx, GuiView, ViewAction},
-        hotkeys::SHORTCUT_DELETE,
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::HotkeyAction,
         icons::{
             ICON_AXIS_ARROW, ICON_CAMERA_CONTROL, ICON_CUBE_OUTLINE, ICON_DELETE, ICON_EYE,
             ICON_EYE_OFF, ICON_RADIUS_OUTLINE, ICON_RESIZE, ICON_ROTATE_ORBIT, ICON_TAG,
         },
+        solo,
+        util::{entity_copy_menu, tag_hash_label},
     },
     input_float3,
     maplist::MapList,
+    model_export::export_dynamic_model_fbx,
+    prefab::Prefab,
     resources::AppResources,
+    util::error::ErrorAlert,
 };
 
 pub struct InspectorPanel;
@@ -69,8 +94,8 @@ impl GuiView for InspectorPanel {
                 if let Some(ent) = selected {
                     show_inspector_panel(
                         ui,
-                        &mut map.pocus().scene,
-                        map.commands(),
+                        &mut map.scene,
+                        &mut map.command_queue,
                         ent,
                         resources,
                     );
@@ -109,14 +134,17 @@ impl GuiView for InspectorPanel {
 pub fn show_inspector_panel(
     ui: &mut egui::Ui,
     scene: &mut Scene,
-    mut cmd: Commands<'_, '_>,
+    command_queue: &mut CommandQueue,
     ent: Entity,
     resources: &AppResources,
 ) {
+    let mut cmd = Commands::new(command_queue, scene);
     let Some(e) = scene.get_entity(ent) else {
         return;
     };
 
+    let mut toggle_solo = false;
+    let mut duplicate = false;
     ui.horizontal(|ui| {
         let visible = e.get::<Visibility>().is_visible(0);
 
@@ -124,7 +152,7 @@ pub fn show_inspector_panel(
             && (ui
                 .button(RichText::new(ICON_DELETE).size(24.0).strong())
                 .clicked()
-                || ui.input_mut(|i| i.consume_shortcut(&SHORTCUT_DELETE)))
+                || HotkeyAction::Delete.pressed(ui.ctx()))
         {
             // Remove all children
             if let Some(children) = e.get::<Children>() {
@@ -142,6 +170,21 @@ pub fn show_inspector_panel(
             cmd.entity(ent).despawn();
         }
 
+        if crate::prefab::can_duplicate(scene, ent) {
+            duplicate = ui
+                .button(
+                    RichText::new(ICON_CONTENT_DUPLICATE.to_string())
+                        .size(24.0)
+                        .strong(),
+                )
+                .on_hover_text(format!(
+                    "Duplicate ({})",
+                    HotkeyAction::Duplicate.shortcut_text(ui.ctx())
+                ))
+                .clicked()
+                || HotkeyAction::Duplicate.pressed(ui.ctx());
+        }
+
         if e.contains::<RouteNode>() {
             ui.label(
                 RichText::new(if visible { ICON_EYE } else { ICON_EYE_OFF })
@@ -163,6 +206,43 @@ pub fn show_inspector_panel(
             }
         }
 
+        let soloed = solo::is_soloed(scene, ent);
+        let mut solo_icon = RichText::new(ICON_SPOTLIGHT_BEAM).size(24.0).strong();
+        if soloed {
+            solo_icon = solo_icon.color(Color32::GOLD);
+        }
+        toggle_solo = ui
+            .button(solo_icon)
+            .on_hover_text(if soloed {
+                "Restore solo (Esc)"
+            } else {
+                "Solo (Ctrl+H)"
+            })
+            .clicked();
+
+        if has_geometry(e) {
+            let wireframe = e.contains::<ShowWireframe>();
+            let mut wireframe_icon = RichText::new(ICON_VECTOR_TRIANGLE).size(24.0).strong();
+            if wireframe {
+                wireframe_icon = wireframe_icon.color(Color32::LIGHT_BLUE);
+            }
+            if ui
+                .button(wireframe_icon)
+                .on_hover_text(if wireframe {
+                    "Hide wireframe"
+                } else {
+                    "Show wireframe"
+                })
+                .clicked()
+            {
+                if wireframe {
+                    cmd.entity(ent).remove::<ShowWireframe>();
+                } else {
+                    cmd.entity(ent).insert((ShowWireframe,));
+                }
+            }
+        }
+
         let title = if let Some(label) = e.get::<Label>() {
             format!("{label} (id {})", ent)
         } else {
@@ -173,12 +253,21 @@ pub fn show_inspector_panel(
                 let some_label = e.get_mut::<Label>();
                 if some_label.as_ref().is_some_and(|l| !l.default) {
                     if let Some(mut label) = some_label {
-                        egui::TextEdit::singleline(&mut label.label)
+                        let response = egui::TextEdit::singleline(&mut label.label)
                             .font(FontId::proportional(22.0))
                             .ui(ui);
+                        // A user-edited name no longer corresponds to the raw one
+                        if response.changed() {
+                            label.raw = None;
+                        }
+                        response.context_menu(|ui| entity_copy_menu(ui, e));
                     }
                 } else {
-                    ui.label(RichText::new(title).size(24.0).strong());
+                    ui.add(
+                        egui::Label::new(RichText::new(title).size(24.0).strong())
+                            .sense(egui::Sense::click()),
+                    )
+                    .context_menu(|ui| entity_copy_menu(ui, e));
                     if ui
                         .button(RichText::new(ICON_TAG.to_string()).size(24.0).strong())
                         .on_hover_text("Add label")
@@ -193,7 +282,11 @@ pub fn show_inspector_panel(
                     }
                 }
             } else {
-                ui.label(RichText::new(title).size(24.0).strong());
+                ui.add(
+                    egui::Label::new(RichText::new(title).size(24.0).strong())
+                        .sense(egui::Sense::click()),
+                )
+                .context_menu(|ui| entity_copy_menu(ui, e));
             }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Max), |ui| {
                 if let Some(parent) = e.get::<Parent>() {
@@ -236,13 +329,38 @@ pub fn show_inspector_panel(
     });
     ui.separator();
 
-    if let Some(tags) = e.get::<Tags>() {
-        ui.horizontal(|ui| {
-            ui.label(RichText::new("Tags: ").color(Color32::WHITE).strong());
+    let mut tag_toggle = None;
+    ui.horizontal(|ui| {
+        ui.label(RichText::new("Tags: ").color(Color32::WHITE).strong());
+        let tags = e.get::<Tags>();
+        if let Some(tags) = tags {
             tags.ui_chips(ui);
-        });
-        ui.separator();
-    }
+        }
+
+        ui.menu_button(ICON_TAG_PLUS.to_string(), |ui| {
+            let custom_tags = config::with(|c| c.custom_tags.clone());
+            if custom_tags.is_empty() {
+                ui.label("No custom tags have been created yet");
+            }
+
+            for name in custom_tags {
+                let tag = EntityTag::Custom(name);
+                let mut assigned = tags.is_some_and(|t| t.contains(&tag));
+                if ui.checkbox(&mut assigned, tag.to_string()).changed() {
+                    tag_toggle = Some((tag, assigned));
+                }
+            }
+
+            ui.separator();
+            if ui.button("Manage tags").clicked() {
+                resources.get_mut::<HiddenWindows>().tag_manager = true;
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_hover_text("Assign custom tags");
+    });
+    ui.separator();
 
     let mut global = e.contains::<Global>();
     let mut global_changed = false;
@@ -257,7 +375,7 @@ pub fn show_inspector_panel(
         };
         ui.separator();
     }
-    show_inspector_components(ui, scene.pocus(), &mut cmd, e, resources);
+    show_inspector_components(ui, scene, &mut cmd, e, resources);
 
     if global_changed {
         if global {
@@ -266,11 +384,29 @@ pub fn show_inspector_panel(
             remove_tag(scene, ent, EntityTag::Global);
         }
     }
+
+    if let Some((tag, assigned)) = tag_toggle {
+        if assigned {
+            insert_tag(scene, ent, tag);
+        } else {
+            remove_tag(scene, ent, tag);
+        }
+    }
+
+    if toggle_solo {
+        solo::toggle_solo(scene, ent);
+    }
+
+    if duplicate {
+        if let Some(copy) = crate::prefab::duplicate(scene, ent) {
+            resources.get_mut::<SelectedEntity>().select(copy);
+        }
+    }
 }
 
 fn show_inspector_components(
     ui: &mut egui::Ui,
-    scene: &mut Scene,
+    scene: &Scene,
     cmd: &mut Commands<'_, '_>,
     e: EntityRef<'_>,
     resources: &AppResources,
@@ -281,6 +417,12 @@ fn show_inspector_components(
         });
     }
 
+    if let Some((feature, stages)) = entity_render_stages(e) {
+        inspector_component_frame(ui, "Render Stages", ICON_LAYERS, |ui| {
+            render_stages::render_stages_ui(ui, cmd, e, feature, stages, resources);
+        });
+    }
+
     macro_rules! component_views {
 		($($component:ty),+) => {
 			$(
@@ -300,18 +442,33 @@ fn show_inspector_components(
         Ruler,
         Sphere,
         Beacon,
+        ClipPlane,
+        TransformAnimation,
         Route,
         RouteNode,
         DynamicModelComponent,
+        StaticInstances,
+        StaticModelSingle,
         LightRenderer,
+        ShadowMapRenderer,
         SLightCollection,
         CubemapVolume,
         ShaderBallComponent,
+        LightProbeSphere,
         DecoratorRenderer,
+        TerrainPatches,
         SRespawnPoint,
         ObjectChannels,
+        Prefab,
         NodeMetadata
     );
+
+    if let Some(metadata) = e.get::<NodeMetadata>().filter(|m| m.source_table.is_some()) {
+        let metadata = metadata.clone();
+        inspector_component_frame(ui, "Source Data", ICON_CODE_BRACES, |ui| {
+            source_data::source_data_ui(ui, e, &metadata, resources);
+        });
+    }
 }
 
 fn inspector_component_frame(
@@ -339,7 +496,7 @@ pub(super) trait ComponentPanel {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'s>,
         _: &mut egui::Ui,
@@ -359,8 +516,8 @@ impl ComponentPanel for Transform {
 
     fn show_inspector_ui(
         &mut self,
-        _scene: &mut Scene,
-        _: &mut Commands<'_, '_>,
+        _scene: &Scene,
+        cmd: &mut Commands<'_, '_>,
         e: EntityRef<'_>,
         ui: &mut egui::Ui,
         resources: &AppResources,
@@ -496,6 +653,18 @@ impl ComponentPanel for Transform {
                 }
             });
         }
+
+        if !e.contains::<TransformAnimation>()
+            && ui
+                .button(format!("{ICON_ANIMATION_PLAY} Animate"))
+                .on_hover_text(
+                    "Records keyframes of the transform, to preview the movement of eg. moving \
+                     platforms",
+                )
+                .clicked()
+        {
+            cmd.entity(e.id()).insert(TransformAnimation::new(self));
+        }
     }
 }
 
@@ -510,16 +679,31 @@ impl ComponentPanel for DynamicModelComponent {
 
     fn show_inspector_ui(
         &mut self,
-        _: &mut Scene,
+        _: &Scene,
         _: &mut Commands<'_, '_>,
-        _: EntityRef<'_>,
+        e: EntityRef<'_>,
         ui: &mut egui::Ui,
-        _: &AppResources,
+        resources: &AppResources,
     ) {
         ui.horizontal(|ui| {
             ui.strong("Hash:");
-            ui.label(self.model.hash.to_string());
+            tag_hash_label(ui, self.model.hash);
+        });
+        let skeleton = e.get::<Skeleton>();
+        ui.horizontal(|ui| {
+            ui.strong("Skeleton:");
+            ui.label(skeleton.map_or("None".to_string(), |s| format!("{} bones", s.bones.len())));
         });
+        if ui
+            .button(format!("{ICON_EXPORT} Export FBX"))
+            .on_hover_text("Export the highest detail LOD with its skeleton and skin weights")
+            .clicked()
+        {
+            export_dynamic_model_fbx(&self.model, skeleton)
+                .context("Failed to export model")
+                .err_alert()
+                .ok();
+        }
         ui.separator();
 
         let mesh_count = self.model.mesh_count();
@@ -574,6 +758,16 @@ impl ComponentPanel for DynamicModelComponent {
                 .text("Material Variant")
                 .ui(ui);
         }
+
+        let techniques = self.model.techniques();
+        material_override::technique_override_ui(
+            ui,
+            ("dynamic", self.model.hash),
+            &mut self.model.technique_override,
+            &techniques,
+            self.model.subscribed_stages,
+            resources,
+        );
     }
 }
 
@@ -588,7 +782,7 @@ impl ComponentPanel for ShaderBallComponent {
 
     fn show_inspector_ui(
         &mut self,
-        _: &mut Scene,
+        _: &Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'_>,
         ui: &mut egui::Ui,
@@ -621,6 +815,66 @@ impl ComponentPanel for ShaderBallComponent {
     }
 }
 
+impl ComponentPanel for LightProbeSphere {
+    fn inspector_name() -> &'static str {
+        "Light Probe"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_MIRROR
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        scene: &'s Scene,
+        _: &mut Commands<'_, '_>,
+        e: EntityRef<'s>,
+        ui: &mut egui::Ui,
+        _: &AppResources,
+    ) {
+        egui::ComboBox::from_label("Material")
+            .selected_text(self.material.to_string())
+            .show_ui(ui, |ui| {
+                for material in LightProbeMaterial::iter() {
+                    ui.selectable_value(&mut self.material, material, material.to_string());
+                }
+            });
+        ui.add(egui::Slider::new(&mut self.radius, 0.05..=2.0).text("Radius"));
+
+        let Some(position) = e.get::<Transform>().map(|t| t.translation) else {
+            return;
+        };
+
+        // Smallest volumes first, as those take priority where volumes overlap
+        let mut volumes = scene
+            .iter_entities()
+            .filter_map(|v| Some((v.get::<Transform>()?, v.get::<CubemapVolume>()?)))
+            .filter(|(transform, cubemap)| {
+                let local = Mat4::from_scale_rotation_translation(
+                    cubemap.extents,
+                    transform.rotation,
+                    transform.translation,
+                )
+                .inverse()
+                .transform_point3(position);
+                local.abs().max_element() <= 1.0
+            })
+            .map(|(_, cubemap)| cubemap)
+            .collect::<Vec<_>>();
+        volumes.sort_by(|a, b| a.volume().total_cmp(&b.volume()));
+
+        ui.separator();
+        if volumes.is_empty() {
+            ui.label("Not inside any cubemap volume");
+        } else {
+            ui.strong("Inside cubemap volumes:");
+            for cubemap in volumes {
+                ui.label(&cubemap.name);
+            }
+        }
+    }
+}
+
 // impl ComponentPanel for EntityWorldId {
 //     fn inspector_name() -> &'static str {
 //         "World ID"
@@ -646,7 +900,7 @@ impl ComponentPanel for NodeMetadata {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'s>,
         ui: &mut Ui,
@@ -654,7 +908,7 @@ impl ComponentPanel for NodeMetadata {
     ) {
         ui.horizontal(|ui| {
             ui.strong("Entity:");
-            ui.label(self.entity_tag.to_string());
+            tag_hash_label(ui, self.entity_tag);
         });
 
         ui.horizontal(|ui| {
@@ -666,7 +920,7 @@ impl ComponentPanel for NodeMetadata {
 
         ui.horizontal(|ui| {
             ui.strong("Source Table:");
-            ui.label(self.source_table.to_string());
+            tag_hash_label(ui, self.source_table);
         });
 
         ui.horizontal(|ui| {
@@ -690,7 +944,7 @@ impl ComponentPanel for SRespawnPoint {
 
     fn show_inspector_ui<'s>(
         &mut self,
-        _: &'s mut Scene,
+        _: &'s Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'s>,
         ui: &mut Ui,
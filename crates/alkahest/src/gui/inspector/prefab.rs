@@ -0,0 +1,62 @@
+use alkahest_renderer::{
+    ecs::{
+        hierarchy::{Children, Parent},
+        Scene,
+    },
+    icons::{ICON_CONTENT_SAVE, ICON_PACKAGE_VARIANT, ICON_UNGROUP},
+};
+use bevy_ecs::{prelude::EntityRef, system::Commands};
+use egui::Ui;
+
+use crate::{
+    gui::inspector::ComponentPanel,
+    prefab::{Prefab, SavedPrefab},
+    resources::AppResources,
+};
+
+impl ComponentPanel for Prefab {
+    fn inspector_name() -> &'static str {
+        "Prefab"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_PACKAGE_VARIANT
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        scene: &'s Scene,
+        cmd: &mut Commands<'_, '_>,
+        e: EntityRef<'s>,
+        ui: &mut Ui,
+        _: &AppResources,
+    ) {
+        let children = e.get::<Children>().map_or(&[][..], |c| &c[..]);
+        ui.label(format!("{} entities", children.len()));
+
+        ui.horizontal(|ui| {
+            if ui
+                .button(format!("{ICON_CONTENT_SAVE} Save Prefab"))
+                .on_hover_text("Saves the group so it can be spawned into other maps")
+                .clicked()
+            {
+                match SavedPrefab::from_group(scene, e.id()).map(|p| p.save()) {
+                    Some(Ok(path)) => info!("Saved prefab to {}", path.display()),
+                    Some(Err(e)) => error!("Failed to save prefab: {e:?}"),
+                    None => warn!("Prefab has no entities that can be saved"),
+                }
+            }
+
+            if ui
+                .button(format!("{ICON_UNGROUP} Ungroup"))
+                .on_hover_text("Removes the group, keeping its entities")
+                .clicked()
+            {
+                for &child in children {
+                    cmd.entity(child).remove::<Parent>();
+                }
+                cmd.entity(e.id()).despawn();
+            }
+        });
+    }
+}
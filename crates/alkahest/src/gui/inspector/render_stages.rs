@@ -0,0 +1,97 @@
+use alkahest_data::tfx::{TfxFeatureRenderer, TfxRenderStage};
+use alkahest_renderer::{
+    ecs::render::stages::RenderStageOverride, renderer::RendererShared,
+    tfx::view::RenderStageSubscriptions,
+};
+use bevy_ecs::{prelude::EntityRef, system::Commands};
+use egui::{Color32, RichText, Ui};
+
+use crate::{gui::UiExt, resources::AppResources};
+
+#[derive(PartialEq, Clone, Copy)]
+enum StageMode {
+    Default,
+    Forced,
+    Excluded,
+}
+
+/// Shows the feature renderer and render stages an entity is drawn by, and lets the user force
+/// the entity in or out of each stage
+pub(super) fn render_stages_ui(
+    ui: &mut Ui,
+    cmd: &mut Commands<'_, '_>,
+    e: EntityRef<'_>,
+    feature: TfxFeatureRenderer,
+    subscribed_stages: RenderStageSubscriptions,
+    resources: &AppResources,
+) {
+    let renderer = resources.get::<RendererShared>();
+    let original = e.get::<RenderStageOverride>().copied().unwrap_or_default();
+    let mut stage_override = original;
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new("Feature: ").color(Color32::WHITE).strong());
+        ui.chip(feature.short());
+    });
+
+    if subscribed_stages.is_empty() {
+        ui.label("Not subscribed to any render stage");
+        return;
+    }
+
+    egui::Grid::new(("render_stages", e.id()))
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for stage in TfxRenderStage::VARIANTS
+                .into_iter()
+                .filter(|s| subscribed_stages.is_subscribed(*s))
+            {
+                let flag = RenderStageSubscriptions::from(stage);
+                let mut mode = if stage_override.excluded.contains(flag) {
+                    StageMode::Excluded
+                } else if stage_override.forced.contains(flag) {
+                    StageMode::Forced
+                } else {
+                    StageMode::Default
+                };
+
+                match mode {
+                    StageMode::Default => ui.chip(stage.as_str()),
+                    StageMode::Forced => ui.chip_with_color(stage.as_str(), Color32::DARK_GREEN),
+                    StageMode::Excluded => ui.chip_with_color(stage.as_str(), Color32::DARK_GRAY),
+                };
+
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut mode, StageMode::Default, "Default");
+                    ui.selectable_value(&mut mode, StageMode::Forced, "Force")
+                        .on_hover_text("Draw in this stage even if it is disabled in the settings");
+                    ui.selectable_value(&mut mode, StageMode::Excluded, "Exclude")
+                        .on_hover_text("Never draw in this stage");
+                });
+
+                // Shadow generation also depends on the shadow map being rendered, so it's left out
+                if stage != TfxRenderStage::ShadowGenerate
+                    && !renderer.should_render(Some(stage), Some(feature))
+                {
+                    ui.label(RichText::new("Disabled in settings").color(Color32::GRAY));
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+
+                stage_override.forced.set(flag, mode == StageMode::Forced);
+                stage_override
+                    .excluded
+                    .set(flag, mode == StageMode::Excluded);
+            }
+        });
+
+    if stage_override != original {
+        if stage_override.is_empty() {
+            cmd.entity(e.id()).remove::<RenderStageOverride>();
+        } else {
+            cmd.entity(e.id()).insert(stage_override);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use alkahest_data::map::{SMapDataTable, SUnk80809885};
+use alkahest_pm::package_manager;
+use alkahest_renderer::{
+    ecs::{
+        map::NodeMetadata,
+        transform::{OriginalTransform, Transform},
+    },
+    icons::{ICON_HEXADECIMAL, ICON_RELOAD},
+    util::black_magic::EntityRefDarkMagic,
+};
+use anyhow::Context;
+use bevy_ecs::world::EntityRef;
+use egui::{Color32, RichText};
+use glam::{Vec3, Vec4Swizzles};
+use tiger_parse::PackageManagerExt;
+
+use crate::{gui::hex_viewer::open_in_hex_viewer, resources::AppResources};
+
+type SourceRow = Arc<anyhow::Result<SUnk80809885>>;
+
+/// Reads the data table row an entity was placed by
+fn read_source_row(metadata: &NodeMetadata) -> anyhow::Result<SUnk80809885> {
+    let table: SMapDataTable = package_manager()
+        .read_tag_struct(metadata.source_table)
+        .with_context(|| format!("Failed to read data table {}", metadata.source_table))?;
+
+    // World IDs aren't always unique within a table, the resource offset tells rows apart
+    let rows = table
+        .data_entries
+        .iter()
+        .filter(|row| row.world_id == metadata.world_id)
+        .collect::<Vec<_>>();
+    rows.iter()
+        .find(|row| row.data_resource.offset == metadata.source_table_resource_offset)
+        .or(rows.first())
+        .map(|row| (*row).clone())
+        .with_context(|| {
+            format!(
+                "No row with world ID 0x{:016X} in data table {}",
+                metadata.world_id, metadata.source_table
+            )
+        })
+}
+
+/// Writes the placement of `row` back to the entity
+fn apply_source_row(e: EntityRef<'_>, row: &SUnk80809885) {
+    let placement = Transform {
+        translation: row.translation.xyz(),
+        rotation: row.rotation,
+        scale: Vec3::splat(row.translation.w),
+        ..Default::default()
+    };
+
+    if let Some(mut transform) = e.get_mut::<Transform>() {
+        transform.translation = placement.translation;
+        transform.rotation = placement.rotation;
+        transform.scale = placement.scale;
+    }
+
+    if let Some(mut original) = e.get_mut::<OriginalTransform>() {
+        original.0 = placement;
+    }
+
+    if let Some(mut metadata) = e.get_mut::<NodeMetadata>() {
+        metadata.entity_tag = row.entity_hash();
+        metadata.world_id = row.world_id;
+        metadata.resource_type = row.data_resource.resource_type;
+        metadata.source_table_resource_offset = row.data_resource.offset;
+    }
+}
+
+/// Shows the parsed data table row the entity came from. The row is read once and cached until
+/// it is reloaded from the package, which also re-applies its placement to the entity
+pub(super) fn source_data_ui(
+    ui: &mut egui::Ui,
+    e: EntityRef<'_>,
+    metadata: &NodeMetadata,
+    resources: &AppResources,
+) {
+    let id = ui.id().with(("source_row", e.id()));
+    let mut reload = false;
+    ui.horizontal(|ui| {
+        reload = ui
+            .button(format!("{ICON_RELOAD} Reload from package"))
+            .on_hover_text(
+                "Reads the row from the package again and applies its transform and metadata to \
+                 this entity",
+            )
+            .clicked();
+
+        if ui
+            .button(format!("{ICON_HEXADECIMAL} Open table"))
+            .on_hover_text(format!(
+                "Open data table {} in the hex viewer",
+                metadata.source_table
+            ))
+            .clicked()
+        {
+            open_in_hex_viewer(resources, metadata.source_table);
+        }
+    });
+
+    let cached = ui.data(|d| d.get_temp::<SourceRow>(id));
+    let row = match cached {
+        Some(row) if !reload => row,
+        _ => {
+            let row: SourceRow = Arc::new(read_source_row(metadata));
+            if reload {
+                match row.as_ref() {
+                    Ok(r) => apply_source_row(e, r),
+                    Err(err) => error!("Failed to reload entity source data: {err:?}"),
+                }
+            }
+            ui.data_mut(|d| d.insert_temp(id, row.clone()));
+            row
+        }
+    };
+
+    match row.as_ref() {
+        Ok(row) => {
+            egui::ScrollArea::vertical()
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    ui.monospace(format!("{row:#X?}"));
+                });
+        }
+        Err(err) => {
+            ui.label(RichText::new(format!("{err:?}")).color(Color32::RED));
+        }
+    }
+}
@@ -0,0 +1,361 @@
+use std::path::Path;
+
+use alkahest_renderer::{
+    ecs::{
+        hierarchy::Children,
+        map::{MapStaticAO, NodeMetadata},
+        render::static_geometry::{
+            StaticInstanceGroup, StaticInstanceParameter, StaticInstances, StaticModel,
+            StaticModelSingle,
+        },
+        transform::Transform,
+        Scene,
+    },
+    icons::{ICON_CUBE_OUTLINE, ICON_EXPORT, ICON_PALETTE_SWATCH_OUTLINE, ICON_RESTORE, ICON_TUNE},
+    renderer::RendererShared,
+    tfx::{bytecode::opcodes::TfxBytecodeOp, technique::TechniqueStage},
+};
+use anyhow::Context;
+use bevy_ecs::{entity::Entity, prelude::EntityRef, system::Commands};
+use egui::{Color32, RichText, Ui};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+
+use crate::{
+    gui::{
+        inspector::{material_override::technique_override_ui, ComponentPanel},
+        util::tag_hash_label,
+    },
+    model_export::{export_static_model_fbx, StaticInstanceAo},
+    resources::AppResources,
+    technique_dump::dump_technique,
+    util::error::ErrorAlert,
+};
+
+impl ComponentPanel for StaticInstances {
+    fn inspector_name() -> &'static str {
+        "Static Instances"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_CUBE_OUTLINE
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        scene: &'s Scene,
+        cmd: &mut Commands<'_, '_>,
+        e: EntityRef<'s>,
+        ui: &mut Ui,
+        resources: &AppResources,
+    ) {
+        ui.horizontal(|ui| {
+            ui.strong("Hash:");
+            tag_hash_label(ui, self.model.hash);
+        });
+        ui.horizontal(|ui| {
+            ui.strong("Instances:");
+            ui.label(self.instance_count.to_string());
+        });
+        if export_button(ui) {
+            let transforms = e.get::<Children>().map_or(vec![], |children| {
+                children
+                    .iter()
+                    .filter_map(|&c| scene.get::<Transform>(c).copied())
+                    .collect()
+            });
+            export_static(scene, e, &self.model, &transforms);
+        }
+        ui.separator();
+
+        ui.label(RichText::new("The material override applies to every instance").weak());
+        technique_override_ui(
+            ui,
+            ("static_instances", self.model.hash),
+            &mut self.model.technique_override,
+            &self.model.materials,
+            self.model.subscribed_stages,
+            resources,
+        );
+        let instances = e.get::<Children>().map_or(&[][..], |c| &c[..]);
+        instance_parameters_ui(ui, scene, cmd, &self.model, instances);
+        material_parameters_ui(ui, &self.model, resources);
+    }
+}
+
+impl ComponentPanel for StaticModelSingle {
+    fn inspector_name() -> &'static str {
+        "Static Model"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_CUBE_OUTLINE
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        scene: &'s Scene,
+        cmd: &mut Commands<'_, '_>,
+        e: EntityRef<'s>,
+        ui: &mut Ui,
+        resources: &AppResources,
+    ) {
+        ui.horizontal(|ui| {
+            ui.strong("Hash:");
+            tag_hash_label(ui, self.model.hash);
+        });
+        if export_button(ui) {
+            let transform = e.get::<Transform>().copied().unwrap_or_default();
+            export_static(scene, e, &self.model, &[transform]);
+        }
+        ui.separator();
+
+        technique_override_ui(
+            ui,
+            ("static_single", self.model.hash),
+            &mut self.model.technique_override,
+            &self.model.materials,
+            self.model.subscribed_stages,
+            resources,
+        );
+        instance_parameters_ui(ui, scene, cmd, &self.model, &[e.id()]);
+        material_parameters_ui(ui, &self.model, resources);
+    }
+}
+
+fn export_button(ui: &mut Ui) -> bool {
+    ui.button(format!("{ICON_EXPORT} Export FBX"))
+        .on_hover_text(
+            "Export the highest detail LOD of every instance, with the vertex colors and baked AO \
+             combined into the exported vertex colors",
+        )
+        .clicked()
+}
+
+fn export_static(scene: &Scene, e: EntityRef<'_>, model: &StaticModel, instances: &[Transform]) {
+    let ao = scene
+        .get_resource::<MapStaticAO>()
+        .zip(e.get::<NodeMetadata>())
+        .and_then(|(map_ao, metadata)| {
+            StaticInstanceAo::read(map_ao, metadata.world_id, e.get::<StaticInstanceGroup>())
+                .context("Failed to read static AO")
+                .err_alert()
+                .ok()
+                .flatten()
+        });
+
+    export_static_model_fbx(model, instances, ao.as_ref())
+        .context("Failed to export static model")
+        .err_alert()
+        .ok();
+}
+
+/// Edits the per-instance constants in the instance scope, which every mesh part of the model is
+/// drawn with. Edits are stored on the instance entities, so they only affect this placement
+fn instance_parameters_ui(
+    ui: &mut Ui,
+    scene: &Scene,
+    cmd: &mut Commands<'_, '_>,
+    model: &StaticModel,
+    instances: &[Entity],
+) {
+    egui::CollapsingHeader::new(RichText::new(format!("{ICON_TUNE} Instance Parameters")).strong())
+        .id_source(("static_instance_parameters", model.hash))
+        .show(ui, |ui| {
+            let parts = model
+                .model
+                .opaque_meshes
+                .mesh_groups
+                .iter()
+                .filter(|g| {
+                    model.model.opaque_meshes.parts[g.part_index as usize]
+                        .lod_category
+                        .is_highest_detail()
+                })
+                .map(|g| format!("{} ({:?})", g.part_index, g.render_stage))
+                .join(", ");
+            ui.label(
+                RichText::new(format!(
+                    "Passed to the shaders of every mesh part (parts {parts}). What the values \
+                     control isn't known, the map data's values aren't read yet"
+                ))
+                .weak(),
+            );
+
+            egui::Grid::new(("static_instance_parameter_grid", model.hash))
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, &instance) in instances.iter().enumerate() {
+                        let original = scene
+                            .get::<StaticInstanceParameter>(instance)
+                            .copied()
+                            .unwrap_or_default();
+
+                        ui.label(RichText::new(format!("#{i}")).monospace());
+                        let mut value = original;
+                        for c in &mut value.0.as_mut()[..3] {
+                            ui.add(egui::DragValue::new(c).speed(0.01));
+                        }
+                        let mut bits = value.0.w.to_bits();
+                        if ui
+                            .add(egui::DragValue::new(&mut bits).hexadecimal(8, false, true))
+                            .on_hover_text("Raw bits of the last component")
+                            .changed()
+                        {
+                            value.0.w = f32::from_bits(bits);
+                        }
+
+                        let default = StaticInstanceParameter::default();
+                        if ui
+                            .add_enabled(
+                                value != default,
+                                egui::Button::new(ICON_RESTORE.to_string()),
+                            )
+                            .on_hover_text("Reset to the default")
+                            .clicked()
+                        {
+                            value = default;
+                        }
+
+                        if value != original {
+                            cmd.entity(instance).insert(value);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+/// Lists the material parameter blocks (technique constant buffers) of every mesh part. The
+/// blocks are shared by every model using the same technique, so they're shown read-only
+fn material_parameters_ui(ui: &mut Ui, model: &StaticModel, resources: &AppResources) {
+    let renderer = resources.get::<RendererShared>();
+
+    egui::CollapsingHeader::new(
+        RichText::new(format!("{ICON_PALETTE_SWATCH_OUTLINE} Material Parameters")).strong(),
+    )
+    .id_source(("static_material_parameters", model.hash))
+    .show(ui, |ui| {
+        ui.label(
+            RichText::new(
+                "Parameters are shared by every model using the same technique. Use the instance \
+                 parameters to vary individual instances",
+            )
+            .italics()
+            .weak(),
+        );
+
+        for (i, group) in model.model.opaque_meshes.mesh_groups.iter().enumerate() {
+            let part = &model.model.opaque_meshes.parts[group.part_index as usize];
+            if !part.lod_category.is_highest_detail() {
+                continue;
+            }
+
+            let Some(handle) = model.materials.get(i) else {
+                continue;
+            };
+
+            let Some(technique) = renderer.get_technique_shared(handle) else {
+                ui.label(format!("Part {} - technique not loaded", group.part_index));
+                continue;
+            };
+
+            egui::CollapsingHeader::new(format!(
+                "Part {} ({:?}) - {}",
+                group.part_index, group.render_stage, technique.hash
+            ))
+            .id_source(("static_material_part", model.hash, i))
+            .show(ui, |ui| {
+                if ui
+                    .button(format!("{ICON_EXPORT} Dump Technique"))
+                    .on_hover_text(
+                        "Writes the DXBC disassembly, resource bindings and decompiled TFX \
+                         bytecode of every stage to technique_dumps/",
+                    )
+                    .clicked()
+                {
+                    if let Ok(directory) =
+                        dump_technique(technique.hash, Path::new("technique_dumps"))
+                            .context("Failed to dump technique")
+                            .err_alert()
+                    {
+                        info!(
+                            "Dumped technique {} to {}",
+                            technique.hash,
+                            directory.display()
+                        );
+                    }
+                }
+
+                let mut any_parameters = false;
+                for stage in technique.all_stages().into_iter().filter_map(|(_, s)| s) {
+                    any_parameters |= stage_parameters_ui(ui, stage);
+                }
+
+                if !any_parameters {
+                    ui.label(RichText::new("No parameters").weak());
+                }
+            });
+        }
+    });
+}
+
+/// Returns false if the stage doesn't have a parameter block
+fn stage_parameters_ui(ui: &mut Ui, stage: &TechniqueStage) -> bool {
+    let Some(cbuffer) = &stage.cbuffer else {
+        return false;
+    };
+
+    // Elements written by TFX bytecode are overwritten every time the technique is bound
+    let bytecode_outputs: FxHashSet<usize> = stage
+        .bytecode
+        .as_ref()
+        .map(|b| {
+            b.opcodes
+                .iter()
+                .flat_map(|op| match op {
+                    TfxBytecodeOp::PopOutput { element } => {
+                        *element as usize..*element as usize + 1
+                    }
+                    TfxBytecodeOp::PopOutputMat4 { element } => {
+                        *element as usize..*element as usize + 4
+                    }
+                    _ => 0..0,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ui.label(RichText::new(format!("{:?} stage", stage.stage)).strong());
+    egui::Grid::new(("stage_parameters", stage.shader.shader))
+        .striped(true)
+        .show(ui, |ui| {
+            for (element, value) in cbuffer.data_slice().iter().enumerate() {
+                ui.label(RichText::new(format!("c{element}")).monospace());
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!(
+                            "{:.3} {:.3} {:.3} {:.3}",
+                            value.x, value.y, value.z, value.w
+                        ))
+                        .monospace(),
+                    );
+                    egui::color_picker::show_color(
+                        ui,
+                        egui::Rgba::from_rgba_unmultiplied(value.x, value.y, value.z, value.w),
+                        egui::vec2(16.0, 16.0),
+                    );
+                });
+
+                if bytecode_outputs.contains(&element) {
+                    ui.label(RichText::new("bytecode").color(Color32::LIGHT_BLUE))
+                        .on_hover_text(
+                            "This element is written by the technique's TFX bytecode every frame",
+                        );
+                }
+                ui.end_row();
+            }
+        });
+
+    true
+}
@@ -0,0 +1,145 @@
+use alkahest_renderer::{
+    ecs::{
+        render::terrain::{terrain_material_color, TerrainPatches},
+        Scene,
+    },
+    icons::ICON_TERRAIN,
+    renderer::RendererShared,
+};
+use bevy_ecs::{prelude::EntityRef, system::Commands};
+use egui::{Color32, RichText, Ui};
+use itertools::Itertools;
+
+use crate::{
+    config,
+    gui::{inspector::ComponentPanel, util::tag_hash_label},
+    resources::AppResources,
+};
+
+impl ComponentPanel for TerrainPatches {
+    fn inspector_name() -> &'static str {
+        "Terrain"
+    }
+
+    fn inspector_icon() -> char {
+        ICON_TERRAIN
+    }
+
+    fn show_inspector_ui<'s>(
+        &mut self,
+        _: &'s Scene,
+        _: &mut Commands<'_, '_>,
+        _: EntityRef<'s>,
+        ui: &mut Ui,
+        resources: &AppResources,
+    ) {
+        let renderer = resources.get::<RendererShared>();
+        renderer
+            .immediate
+            .cube_outline_aabb(&self.terrain.bounds, Color32::from_rgb(210, 160, 80));
+
+        ui.horizontal(|ui| {
+            ui.strong("Hash:");
+            tag_hash_label(ui, self.hash);
+        });
+
+        let mut material_colors = renderer.settings().terrain_material_colors;
+        if ui
+            .checkbox(&mut material_colors, "Color terrain by material")
+            .changed()
+        {
+            config::with_mut(|c| c.renderer.terrain_material_colors = material_colors);
+            renderer.set_render_settings(config::with(|c| c.renderer.clone()));
+        }
+
+        ui.separator();
+        ui.strong(format!("Groups ({})", self.terrain.mesh_groups.len()));
+        egui::Grid::new(("terrain_groups", self.hash))
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, group) in self.terrain.mesh_groups.iter().enumerate() {
+                    ui.label(format!("Group {i}"));
+                    ui.label("Dye map");
+                    tag_hash_label(ui, group.dyemap);
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        let parts = self.highest_detail_parts().collect_vec();
+        ui.strong(format!("Parts ({})", parts.len()));
+        ui.label(
+            RichText::new(
+                "Colors match the terrain material overlay. Blend weights from the dye maps \
+                 aren't shown, their channel layout is unknown",
+            )
+            .italics()
+            .weak(),
+        );
+
+        for (i, part) in parts {
+            let c = terrain_material_color(part.technique);
+            let color: Color32 = egui::Rgba::from_rgb(c.x, c.y, c.z).into();
+
+            let header = egui::CollapsingHeader::new(
+                RichText::new(format!(
+                    "⏹ Part {i} (group {}) - {}",
+                    part.group_index, part.technique
+                ))
+                .color(color),
+            )
+            .id_source(("terrain_part", self.hash, i));
+
+            header.show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Technique:");
+                    tag_hash_label(ui, part.technique);
+                });
+                ui.label(format!("{} indices", part.index_count));
+
+                let Some(technique) = self
+                    .techniques()
+                    .get(i)
+                    .and_then(|h| renderer.get_technique_shared(h))
+                else {
+                    ui.label(RichText::new("Technique not loaded").weak());
+                    return;
+                };
+
+                let textures = technique
+                    .all_stages()
+                    .into_iter()
+                    .filter_map(|(_, stage)| stage)
+                    .flat_map(|stage| {
+                        stage
+                            .textures
+                            .iter()
+                            .map(move |(slot, texture)| (stage.stage, *slot, texture.id()))
+                    })
+                    .collect_vec();
+
+                if textures.is_empty() {
+                    ui.label(RichText::new("No textures").weak());
+                    return;
+                }
+
+                egui::Grid::new(("terrain_part_textures", self.hash, i))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (stage, slot, id) in textures {
+                            ui.label(format!("{stage:?} t{slot}"));
+                            match id.tiger_taghash() {
+                                Some(hash) => {
+                                    tag_hash_label(ui, hash);
+                                }
+                                None => {
+                                    ui.label(RichText::new("generated").weak());
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        }
+    }
+}
@@ -1,19 +1,22 @@
+use std::f32::consts::PI;
+
 use alkahest_renderer::{
     camera::{tween::Tween, Camera},
     ecs::{
         hierarchy::{Children, Parent},
         resources::SelectedEntity,
-        route::{Route, RouteNode, RouteNodeBundle, RouteNodeData},
+        route::{Route, RouteNode, RouteNodeBundle, RouteNodeData, RouteValidation},
         transform::Transform,
-        utility::{Beacon, Ruler, Sphere, Utility},
+        utility::{Beacon, ClipPlane, Ruler, Sphere, Utility},
         Scene, SceneInfo,
     },
     icons::{
         ICON_ALERT, ICON_ALPHA_A_BOX, ICON_ALPHA_B_BOX, ICON_ARROW_LEFT, ICON_ARROW_RIGHT,
         ICON_CAMERA, ICON_CAMERA_CONTROL, ICON_CLIPBOARD, ICON_EYE_ARROW_RIGHT_OUTLINE,
-        ICON_EYE_OFF_OUTLINE, ICON_MAP_MARKER, ICON_MAP_MARKER_PATH, ICON_MAP_MARKER_PLUS,
+        ICON_EYE_OFF_OUTLINE, ICON_MAP_MARKER, ICON_MAP_MARKER_ALERT, ICON_MAP_MARKER_CHECK,
+        ICON_MAP_MARKER_PATH, ICON_MAP_MARKER_PLUS,
     },
-    renderer::RendererShared,
+    renderer::{RendererShared, MAX_CLIP_PLANES},
     util::{black_magic::EntityRefDarkMagic, text::prettify_distance},
 };
 use bevy_ecs::{prelude::EntityRef, system::Commands};
@@ -21,12 +24,10 @@ use egui::{
     color_picker::{color_edit_button_rgba, Alpha},
     Button,
 };
+use glam::Quat;
 
 use crate::{
-    gui::{
-        hotkeys::{SHORTCUT_ADD_ROUTE_NODE_NEXT, SHORTCUT_ADD_ROUTE_NODE_PREV},
-        inspector::ComponentPanel,
-    },
+    gui::{hotkeys::HotkeyAction, inspector::ComponentPanel},
     input_float3,
     resources::AppResources,
     util::action::{ActionList, FollowAction},
@@ -43,7 +44,7 @@ impl ComponentPanel for Ruler {
 
     fn show_inspector_ui(
         &mut self,
-        _: &mut Scene,
+        _: &Scene,
         _: &mut Commands<'_, '_>,
         _: EntityRef<'_>,
         ui: &mut egui::Ui,
@@ -176,7 +177,7 @@ impl ComponentPanel for Sphere {
 
     fn show_inspector_ui(
         &mut self,
-        _: &mut Scene,
+        _: &Scene,
         _: &mut Commands<'_, '_>,
         e: EntityRef<'_>,
         ui: &mut egui::Ui,
@@ -219,7 +220,7 @@ impl ComponentPanel for Beacon {
 
     fn show_inspector_ui(
         &mut self,
-        _: &mut Scene,
+        _: &Scene,
         _: &mut Commands<'_, '_>,
         e: EntityRef<'_>,
         ui: &mut egui::Ui,
@@ -314,6 +315,70 @@ impl ComponentPanel for Beacon {
     }
 }
 
+impl ComponentPanel for ClipPlane {
+    fn inspector_name() -> &'static str {
+        "Clip Plane"
+    }
+
+    fn inspector_icon() -> char {
+        ClipPlane::icon().char()
+    }
+
+    fn show_inspector_ui(
+        &mut self,
+        scene: &Scene,
+        cmd: &mut Commands<'_, '_>,
+        e: EntityRef<'_>,
+        ui: &mut egui::Ui,
+        _resources: &AppResources,
+    ) {
+        let Some(transform) = e.get::<Transform>() else {
+            ui.label(format!(
+                "{} This entity has no transform component",
+                ICON_ALERT
+            ));
+            return;
+        };
+
+        let plane_count = scene
+            .iter_entities()
+            .filter(|e| e.contains::<ClipPlane>())
+            .count();
+        if plane_count > MAX_CLIP_PLANES {
+            ui.label(format!(
+                "{} Only {MAX_CLIP_PLANES} clip planes can be active at once, hide the ones you \
+                 aren't using",
+                ICON_ALERT
+            ));
+        }
+
+        ui.label("Geometry on the side the arrow is pointing towards is kept");
+        if ui.button("Flip").clicked() {
+            let mut transform = *transform;
+            transform.rotation *= Quat::from_rotation_x(PI);
+            cmd.entity(e.id()).insert(transform);
+        }
+
+        ui.horizontal(|ui| {
+            ui.strong("Size");
+            ui.add(
+                egui::DragValue::new(&mut self.size)
+                    .speed(0.1)
+                    .range(0.1..=1000.0)
+                    .suffix(" m"),
+            )
+        })
+        .response
+        .on_hover_text("Size of the outline, the plane itself is infinite");
+
+        ui.horizontal(|ui| {
+            color_edit_button_rgba(ui, &mut self.color, Alpha::Opaque);
+
+            ui.label("Color");
+        });
+    }
+}
+
 impl ComponentPanel for Route {
     fn inspector_name() -> &'static str {
         "Route"
@@ -325,13 +390,13 @@ impl ComponentPanel for Route {
 
     fn show_inspector_ui(
         &mut self,
-        scene: &mut Scene,
+        scene: &Scene,
         cmd: &mut Commands<'_, '_>,
         e: EntityRef<'_>,
         ui: &mut egui::Ui,
         resources: &AppResources,
     ) {
-        let camera = resources.get_mut::<Camera>();
+        let mut camera = resources.get_mut::<Camera>();
         ui.horizontal(|ui| {
             color_edit_button_rgba(ui, &mut self.color, Alpha::Opaque);
 
@@ -383,9 +448,12 @@ impl ComponentPanel for Route {
         ui.horizontal(|ui| {
             if ui
                 .button(format!("{}", ICON_MAP_MARKER_PLUS))
-                .on_hover_text("Hotkey: +")
+                .on_hover_text(format!(
+                    "Hotkey: {}",
+                    HotkeyAction::AddRouteNodeNext.shortcut_text(ui.ctx())
+                ))
                 .clicked()
-                || ui.input_mut(|i| i.consume_shortcut(&SHORTCUT_ADD_ROUTE_NODE_NEXT))
+                || HotkeyAction::AddRouteNodeNext.pressed(ui.ctx())
             {
                 let node = cmd
                     .spawn(RouteNodeBundle::new(
@@ -427,6 +495,93 @@ impl ComponentPanel for Route {
         }
 
         ui.separator();
+
+        egui::CollapsingHeader::new("Validation").show(ui, |ui| {
+            let settings = &mut self.validation_settings;
+            egui::Grid::new("route_validation_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.strong("Max Slope");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.max_slope)
+                            .speed(0.5)
+                            .range(0f32..=90f32)
+                            .suffix("\u{b0}"),
+                    );
+                    ui.end_row();
+
+                    ui.strong("Max Jump Height");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.max_jump_height)
+                            .speed(0.1)
+                            .range(0f32..=100f32)
+                            .min_decimals(2)
+                            .max_decimals(2)
+                            .suffix(" m"),
+                    );
+                    ui.end_row();
+
+                    ui.strong("Clearance");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.clearance)
+                            .speed(0.1)
+                            .range(0f32..=10f32)
+                            .min_decimals(2)
+                            .max_decimals(2)
+                            .suffix(" m"),
+                    )
+                    .on_hover_text("Height above the nodes at which segments are tested");
+                    ui.end_row();
+                });
+
+            if ui
+                .button(format!("{} Validate Route", ICON_MAP_MARKER_CHECK))
+                .on_hover_text(
+                    "Checks every segment on the current map against scene geometry, kill \
+                     barriers and the slope limits",
+                )
+                .clicked()
+            {
+                cmd.entity(e.id()).insert(RouteValidation::default());
+            }
+
+            let Some(validation) = e.get::<RouteValidation>() else {
+                return;
+            };
+
+            if !validation.is_finished() {
+                ui.add(
+                    egui::ProgressBar::new(validation.progress())
+                        .text("Probing segments")
+                        .animate(true),
+                );
+            } else if validation.issues.is_empty() {
+                ui.label(format!("{} No issues found", ICON_MAP_MARKER_CHECK));
+            } else {
+                ui.label(format!(
+                    "{} {} issue(s) found",
+                    ICON_MAP_MARKER_ALERT,
+                    validation.issues.len()
+                ));
+                for issue in &validation.issues {
+                    if ui
+                        .selectable_label(
+                            false,
+                            format!("Segment {}: {}", issue.segment + 1, issue.kind),
+                        )
+                        .on_hover_text("Click to go to the issue")
+                        .clicked()
+                    {
+                        camera.tween = Some(Tween::new(
+                            |x| x,
+                            Some((camera.position(), issue.position - camera.forward() * 5.0)),
+                            None,
+                            1.0,
+                        ));
+                    }
+                }
+            }
+        });
     }
 }
 
@@ -441,7 +596,7 @@ impl ComponentPanel for RouteNode {
 
     fn show_inspector_ui(
         &mut self,
-        scene: &mut Scene,
+        scene: &Scene,
         cmd: &mut Commands<'_, '_>,
         e: EntityRef<'_>,
         ui: &mut egui::Ui,
@@ -473,9 +628,12 @@ impl ComponentPanel for RouteNode {
         ui.horizontal(|ui| {
             if ui
                 .button(format!("{}{}", ICON_ARROW_LEFT, ICON_MAP_MARKER_PLUS))
-                .on_hover_text("Hotkey: -")
+                .on_hover_text(format!(
+                    "Hotkey: {}",
+                    HotkeyAction::AddRouteNodePrev.shortcut_text(ui.ctx())
+                ))
                 .clicked()
-                || ui.input_mut(|i| i.consume_shortcut(&SHORTCUT_ADD_ROUTE_NODE_PREV))
+                || HotkeyAction::AddRouteNodePrev.pressed(ui.ctx())
             {
                 if let Some(mut children) = scene.entity(parent.0).get_mut::<Children>() {
                     let index = children
@@ -503,9 +661,12 @@ impl ComponentPanel for RouteNode {
         ui.horizontal(|ui| {
             if ui
                 .button(format!("{}{}", ICON_MAP_MARKER_PLUS, ICON_ARROW_RIGHT))
-                .on_hover_text("Hotkey: +")
+                .on_hover_text(format!(
+                    "Hotkey: {}",
+                    HotkeyAction::AddRouteNodeNext.shortcut_text(ui.ctx())
+                ))
                 .clicked()
-                || ui.input_mut(|i| i.consume_shortcut(&SHORTCUT_ADD_ROUTE_NODE_NEXT))
+                || HotkeyAction::AddRouteNodeNext.pressed(ui.ctx())
             {
                 if let Some(mut children) = scene.entity(parent.0).get_mut::<Children>() {
                     let index = children
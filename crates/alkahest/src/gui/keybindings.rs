@@ -0,0 +1,171 @@
+use alkahest_renderer::icons::{ICON_ALERT, ICON_KEYBOARD, ICON_RESTORE};
+use egui::{Color32, Context, Event, Id, Key, KeyboardShortcut, Modifiers, RichText};
+use itertools::Itertools;
+use strum::IntoEnumIterator;
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::HotkeyAction,
+    },
+    resources::AppResources,
+};
+
+/// Temporary egui data, set to the action that is waiting for a new shortcut
+const CAPTURE_ID: &str = "keybinding_capture";
+
+/// Whether the keybinding editor is waiting for a key press. Hotkeys should be ignored while this
+/// is the case
+pub fn is_capturing(ctx: &Context) -> bool {
+    ctx.data(|d| d.get_temp::<HotkeyAction>(Id::new(CAPTURE_ID)))
+        .is_some()
+}
+
+pub struct KeybindingsPanel;
+
+impl GuiView for KeybindingsPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.keybindings {
+            ctx.data_mut(|d| d.remove::<HotkeyAction>(Id::new(CAPTURE_ID)));
+            return None;
+        }
+
+        let capturing = ctx.data(|d| d.get_temp::<HotkeyAction>(Id::new(CAPTURE_ID)));
+        if let Some(action) = capturing {
+            capture_shortcut(ctx, action);
+        }
+
+        egui::Window::new("Keybindings")
+            .open(&mut windows.keybindings)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Click a shortcut to rebind it, Escape cancels.");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button(format!("{ICON_RESTORE} Reset all")).clicked() {
+                            config::with_mut(|c| c.keybindings.clear());
+                            config::persist();
+                        }
+                    });
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (category, actions) in &HotkeyAction::iter().chunk_by(|a| a.category()) {
+                        ui.strong(category);
+                        egui::Grid::new(("keybindings", category))
+                            .num_columns(3)
+                            .striped(true)
+                            .min_col_width(32.0)
+                            .show(ui, |ui| {
+                                for action in actions {
+                                    keybinding_row(ui, action, capturing == Some(action));
+                                    ui.end_row();
+                                }
+                            });
+                        ui.add_space(8.0);
+                    }
+                });
+            });
+
+        None
+    }
+}
+
+fn keybinding_row(ui: &mut egui::Ui, action: HotkeyAction, capturing: bool) {
+    let conflicts = action.conflicts();
+    ui.horizontal(|ui| {
+        if !conflicts.is_empty() {
+            ui.label(RichText::new(ICON_ALERT.to_string()).color(Color32::YELLOW))
+                .on_hover_text(format!(
+                    "Also bound to {}",
+                    conflicts.iter().map(|a| a.name()).join(", ")
+                ));
+        }
+        ui.label(action.name());
+    });
+
+    let text = if capturing {
+        RichText::new(format!("{ICON_KEYBOARD} Press a key...")).italics()
+    } else {
+        RichText::new(action.shortcut_text(ui.ctx()))
+    };
+    let text = if conflicts.is_empty() {
+        text
+    } else {
+        text.color(Color32::YELLOW)
+    };
+
+    if ui
+        .add(egui::Button::new(text).min_size([140.0, 0.0].into()))
+        .clicked()
+    {
+        ui.ctx()
+            .data_mut(|d| d.insert_temp(Id::new(CAPTURE_ID), action));
+    }
+
+    let is_default = action.shortcut() == action.default_shortcut();
+    if ui
+        .add_enabled(!is_default, egui::Button::new(ICON_RESTORE.to_string()))
+        .on_hover_text(format!(
+            "Reset to {}",
+            ui.ctx().format_shortcut(&action.default_shortcut())
+        ))
+        .clicked()
+    {
+        config::with_mut(|c| c.keybindings.remove(&action));
+        config::persist();
+    }
+}
+
+/// Binds the first key pressed this frame to `action`
+fn capture_shortcut(ctx: &Context, action: HotkeyAction) {
+    let Some((key, modifiers)) = ctx.input(|i| {
+        i.events.iter().find_map(|e| match e {
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } => Some((*key, *modifiers)),
+            _ => None,
+        })
+    }) else {
+        return;
+    };
+
+    // Make sure the key doesn't trigger anything else this frame
+    ctx.input_mut(|i| i.consume_key(modifiers, key));
+    ctx.data_mut(|d| d.remove::<HotkeyAction>(Id::new(CAPTURE_ID)));
+
+    if key == Key::Escape && modifiers.is_none() {
+        return;
+    }
+
+    // Only keep the modifiers that can be bound, `command` is an alias for ctrl on this platform
+    let modifiers = Modifiers {
+        alt: modifiers.alt,
+        ctrl: modifiers.ctrl || modifiers.command,
+        shift: modifiers.shift,
+        ..Modifiers::NONE
+    };
+    let shortcut = KeyboardShortcut::new(modifiers, key);
+
+    config::with_mut(|c| {
+        if shortcut == action.default_shortcut() {
+            c.keybindings.remove(&action);
+        } else {
+            c.keybindings.insert(action, shortcut);
+        }
+    });
+    config::persist();
+}
@@ -0,0 +1,374 @@
+use alkahest_renderer::{
+    icons::{ICON_ALERT, ICON_CODE_JSON, ICON_CONTENT_COPY, ICON_TIMER_SAND},
+    loaders::map_report::{LoadWarnings, MapLoadReport, SkippedResource},
+};
+use egui::{Color32, Context, RichText};
+use strum::IntoEnumIterator;
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    resources::AppResources,
+    util::{export::save_file_dialog, text::format_bytes},
+};
+
+/// Shows how long each map took to load, what it contains and what failed while loading it
+#[derive(Default)]
+pub struct MapLoadReportPanel {
+    /// Index of the map to show the report of, the current map if `None`
+    selected_map: Option<usize>,
+}
+
+impl GuiView for MapLoadReportPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.load_report {
+            return None;
+        }
+
+        let maps = resources.get::<MapList>();
+        egui::Window::new("Map Load Report")
+            .default_size([420., 480.])
+            .open(&mut windows.load_report)
+            .show(ctx, |ui| {
+                let selected = self
+                    .selected_map
+                    .filter(|&i| i < maps.maps.len())
+                    .unwrap_or(maps.current_map_index());
+
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("load_report_map")
+                        .selected_text(
+                            maps.maps
+                                .get(selected)
+                                .map(|m| m.name.as_str())
+                                .unwrap_or("No maps"),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (i, map) in maps.maps.iter().enumerate() {
+                                let label = match &map.load_report {
+                                    Some(r) if !r.streaming => {
+                                        format!("{} ({:.2}s)", map.name, r.total_seconds())
+                                    }
+                                    _ => map.name.clone(),
+                                };
+                                if ui.selectable_label(i == selected, label).clicked() {
+                                    self.selected_map = Some(i);
+                                }
+                            }
+                        });
+
+                    let reports = maps
+                        .maps
+                        .iter()
+                        .filter_map(|m| m.load_report.as_ref())
+                        .collect::<Vec<_>>();
+                    if ui
+                        .add_enabled(
+                            !reports.is_empty(),
+                            egui::Button::new(format!("{ICON_CODE_JSON} Export all")),
+                        )
+                        .clicked()
+                    {
+                        match serde_json::to_string_pretty(&reports) {
+                            Ok(json) => save_file_dialog(
+                                json.into_bytes(),
+                                "JSON",
+                                "json",
+                                "map_load_reports".to_string(),
+                            ),
+                            Err(e) => error!("Failed to serialize map load reports: {e}"),
+                        }
+                    }
+                });
+                ui.separator();
+
+                let Some(report) = maps.maps.get(selected).and_then(|m| m.load_report.as_ref())
+                else {
+                    ui.label("This map hasn't been loaded yet");
+                    return;
+                };
+
+                report_ui(ui, report);
+            });
+
+        None
+    }
+}
+
+/// Lists the resources that were skipped after a map loads partially
+pub struct LoadWarningsDialog;
+
+impl GuiView for LoadWarningsDialog {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut maps = resources.get_mut::<MapList>();
+        let map = maps.current_map_mut()?;
+        if !map.show_load_warnings {
+            return None;
+        }
+        let Some(warnings) = map.scene.get_resource::<LoadWarnings>() else {
+            map.show_load_warnings = false;
+            return None;
+        };
+
+        let mut close = false;
+        let mut open_report = false;
+        egui::Window::new("Map loaded with warnings")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "{ICON_ALERT} {} was only partially loaded, the following resources \
+                         failed to load and were skipped:",
+                        map.name
+                    ))
+                    .color(Color32::YELLOW),
+                );
+
+                for kind in SkippedResource::iter() {
+                    let count = warnings.count(kind);
+                    if count != 0 {
+                        ui.label(format!("{count} × {kind}"));
+                    }
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("load_warnings")
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for warning in &warnings.0 {
+                                    ui.label(format!("{} {}", warning.kind, warning.tag));
+                                    ui.label(
+                                        RichText::new(&warning.error).color(Color32::LIGHT_RED),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    open_report = ui.button("Open load report").clicked();
+                    close = ui.button("Close").clicked();
+                });
+            });
+
+        if open_report {
+            resources.get_mut::<HiddenWindows>().load_report = true;
+        }
+        if close || open_report {
+            map.show_load_warnings = false;
+        }
+
+        None
+    }
+}
+
+/// Offers to cancel or retry map loads that the load watchdog considers stuck
+pub struct LoadStallDialog;
+
+enum StallChoice {
+    KeepWaiting,
+    Retry,
+    Cancel,
+}
+
+impl GuiView for LoadStallDialog {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut maps = resources.get_mut::<MapList>();
+        let map = maps
+            .maps
+            .iter_mut()
+            .find(|m| m.load_watch.as_ref().is_some_and(|w| w.stall.is_some()))?;
+        let watch = map.load_watch.as_ref()?;
+        let stall = watch.stall.as_ref()?;
+
+        let mut choice = None;
+        egui::Window::new("Map load is taking a long time")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "{ICON_TIMER_SAND} {} has been loading for {:.0}s, it might be stuck on a \
+                         malformed package",
+                        map.name,
+                        watch.elapsed().as_secs_f32()
+                    ))
+                    .color(Color32::YELLOW),
+                );
+
+                match stall.bundle.ready() {
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Writing diagnostics...");
+                        });
+                    }
+                    Some(Ok(path)) => {
+                        let path = path.display().to_string();
+                        ui.label("The backtraces of every thread and the log were written to");
+                        ui.horizontal(|ui| {
+                            ui.monospace(&path);
+                            if ui
+                                .button(ICON_CONTENT_COPY.to_string())
+                                .on_hover_text("Copy path")
+                                .clicked()
+                            {
+                                ui.output_mut(|o| o.copied_text = path.clone());
+                            }
+                        });
+                        ui.label("Please include this folder when reporting the issue");
+                    }
+                    Some(Err(e)) => {
+                        ui.label(
+                            RichText::new(format!("Failed to write diagnostics: {e}"))
+                                .color(Color32::LIGHT_RED),
+                        );
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Keep waiting")
+                        .on_hover_text("Check again once the timeout has passed another time")
+                        .clicked()
+                    {
+                        choice = Some(StallChoice::KeepWaiting);
+                    }
+                    if ui
+                        .button("Retry")
+                        .on_hover_text(
+                            "Start the load over. The stuck load keeps running in the background",
+                        )
+                        .clicked()
+                    {
+                        choice = Some(StallChoice::Retry);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(StallChoice::Cancel);
+                    }
+                });
+            });
+
+        match choice {
+            Some(StallChoice::KeepWaiting) => {
+                if let Some(watch) = &mut map.load_watch {
+                    watch.keep_waiting();
+                }
+            }
+            Some(StallChoice::Retry) => map.retry_load(),
+            Some(StallChoice::Cancel) => map.cancel_load(),
+            None => {}
+        }
+
+        None
+    }
+}
+
+fn report_ui(ui: &mut egui::Ui, report: &MapLoadReport) {
+    ui.horizontal(|ui| {
+        ui.strong(format!("{} ({})", report.map_name, report.map_hash));
+        if ui
+            .button(format!("{ICON_CODE_JSON} Export"))
+            .on_hover_text("Save this report as JSON")
+            .clicked()
+        {
+            match serde_json::to_string_pretty(report) {
+                Ok(json) => save_file_dialog(
+                    json.into_bytes(),
+                    "JSON",
+                    "json",
+                    format!("load_report_{}", report.map_hash),
+                ),
+                Err(e) => error!("Failed to serialize map load report: {e}"),
+            }
+        }
+    });
+
+    if report.streaming {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(format!("{ICON_TIMER_SAND} Assets are still streaming in"));
+        });
+    }
+
+    ui.strong("Phases");
+    let total = report.total_seconds();
+    egui::Grid::new("load_report_phases")
+        .striped(true)
+        .show(ui, |ui| {
+            for phase in &report.phases {
+                ui.label(&phase.name);
+                ui.label(format!("{:.2}s", phase.seconds));
+                ui.add(
+                    egui::ProgressBar::new((phase.seconds / total.max(f64::EPSILON)) as f32)
+                        .desired_width(160.0),
+                );
+                ui.end_row();
+            }
+            ui.strong("Total");
+            ui.strong(format!("{total:.2}s"));
+            ui.end_row();
+        });
+
+    ui.strong("Contents");
+    egui::Grid::new("load_report_counts")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Entities");
+            ui.label(report.entity_count.to_string());
+            ui.end_row();
+            for (filter, count) in &report.counts {
+                ui.label(filter);
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+            ui.label("Texture data uploaded");
+            ui.label(format_bytes(report.texture_bytes_uploaded as usize));
+            ui.end_row();
+        });
+
+    ui.strong(format!(
+        "Failures ({} load errors, {} failed assets)",
+        report.failures.len(),
+        report.asset_failures
+    ));
+    if report.failures.is_empty() {
+        ui.label(RichText::new("None").weak());
+    } else {
+        egui::ScrollArea::vertical()
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for failure in &report.failures {
+                    ui.label(RichText::new(failure).color(Color32::LIGHT_RED));
+                }
+            });
+    }
+}
@@ -0,0 +1,160 @@
+use alkahest_renderer::{icons::ICON_DELETE, renderer::RendererShared};
+use egui::{Color32, Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    resources::AppResources,
+    util::text::format_bytes,
+};
+
+/// Memory of the process as tracked by mimalloc, in bytes
+#[derive(Default, Clone, Copy)]
+struct ProcessMemory {
+    current_rss: usize,
+    peak_rss: usize,
+    current_commit: usize,
+    peak_commit: usize,
+}
+
+impl ProcessMemory {
+    fn query() -> Self {
+        let mut memory = Self::default();
+        let mut unused = 0;
+        unsafe {
+            libmimalloc_sys::mi_process_info(
+                &mut unused,
+                &mut unused,
+                &mut unused,
+                &mut memory.current_rss,
+                &mut memory.peak_rss,
+                &mut memory.current_commit,
+                &mut memory.peak_commit,
+                &mut unused,
+            );
+        }
+
+        memory
+    }
+}
+
+/// Shows where memory is going: process memory, GPU memory and the contents of every asset
+/// registry
+pub struct MemoryPanel;
+
+impl GuiView for MemoryPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.memory {
+            return None;
+        }
+
+        let renderer = resources.get::<RendererShared>();
+        let process = ProcessMemory::query();
+        let video_memory = renderer.gpu.video_memory_info();
+
+        egui::Window::new("Memory")
+            .open(&mut windows.memory)
+            .show(ctx, |ui| {
+                ui.heading("Process");
+                egui::Grid::new("memory_process_grid")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        ui.label("");
+                        ui.label(RichText::new("Current").strong());
+                        ui.label(RichText::new("Peak").strong());
+                        ui.end_row();
+
+                        ui.label("Resident (RSS)");
+                        ui.label(format_bytes(process.current_rss));
+                        ui.label(format_bytes(process.peak_rss));
+                        ui.end_row();
+
+                        ui.label("Committed");
+                        ui.label(format_bytes(process.current_commit));
+                        ui.label(format_bytes(process.peak_commit));
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.heading("GPU");
+                let mut data = renderer.data.lock();
+                let am = &mut data.asset_manager;
+                match video_memory {
+                    Some((usage, budget)) => {
+                        ui.label(format!(
+                            "Video memory: {} of {} budget",
+                            format_bytes(usage as usize),
+                            format_bytes(budget as usize)
+                        ))
+                        .on_hover_text(
+                            "Reported by the driver, includes render targets and everything \
+                             else the application has allocated",
+                        );
+                    }
+                    None => {
+                        ui.label(
+                            RichText::new("Video memory usage is not available on this adapter")
+                                .color(Color32::GRAY),
+                        );
+                    }
+                }
+                ui.label(format!(
+                    "Loaded assets: {} (estimated)",
+                    format_bytes(am.loaded_memory())
+                ));
+                if am.texture_budget() != 0 {
+                    ui.label(format!(
+                        "Texture budget: {} of {}",
+                        format_bytes(am.texture_memory()),
+                        format_bytes(am.texture_budget())
+                    ));
+                }
+
+                ui.separator();
+                ui.heading("Asset registries");
+                egui::Grid::new("memory_registry_grid")
+                    .striped(true)
+                    .num_columns(5)
+                    .show(ui, |ui| {
+                        for header in ["Registry", "Handles", "Loaded", "Dead", "Memory"] {
+                            ui.label(RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for (kind, stats) in am.registry_stats() {
+                            ui.label(kind.to_string());
+                            ui.label(stats.handles.to_string());
+                            ui.label(stats.loaded.to_string());
+                            ui.label(stats.dead.to_string());
+                            if stats.size == 0 {
+                                ui.label("-");
+                            } else {
+                                ui.label(format_bytes(stats.size));
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if ui
+                    .button(format!("{ICON_DELETE} Drop dead assets"))
+                    .on_hover_text(
+                        "Removes assets that are no longer used by anything, instead of waiting \
+                         for the asset manager to get to them",
+                    )
+                    .clicked()
+                {
+                    let removed = am.remove_all_dead();
+                    info!("Dropped {removed} dead assets");
+                }
+            });
+
+        None
+    }
+}
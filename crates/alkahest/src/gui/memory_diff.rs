@@ -0,0 +1,327 @@
+use std::fmt::Write;
+
+use alkahest_renderer::{
+    handle::AssetId,
+    icons::{ICON_CAMERA, ICON_CONTENT_COPY, ICON_PLAY},
+    loaders::snapshot::{AssetSnapshot, AssetSnapshotDiff, AssetSnapshotEntry},
+    renderer::RendererShared,
+};
+use egui::{Color32, Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::{MapList, MapLoadState},
+    resources::AppResources,
+    util::text::format_bytes,
+};
+
+/// Number of consecutive frames the map has to be loaded and the asset manager idle before the
+/// second snapshot is taken, so assets requested while rendering the new map are included
+const SETTLE_FRAMES: usize = 60;
+/// Maximum number of assets listed per diff category
+const MAX_LISTED_ASSETS: usize = 500;
+
+/// Compares the loaded assets before and after switching maps
+#[derive(Default)]
+pub struct MemoryDiffPanel {
+    before: Option<AssetSnapshot>,
+    after: Option<AssetSnapshot>,
+    diff: Option<AssetSnapshotDiff>,
+
+    target_map: usize,
+    /// Unload the map that was switched away from, instead of keeping it in memory
+    unload_previous: bool,
+    /// Set while waiting for the target map to finish loading
+    pending: Option<PendingSnapshot>,
+}
+
+struct PendingSnapshot {
+    settled_frames: usize,
+}
+
+impl MemoryDiffPanel {
+    fn take_snapshot(resources: &AppResources) -> AssetSnapshot {
+        let label = resources
+            .get::<MapList>()
+            .current_map()
+            .map(|m| format!("{} ({})", m.name, m.hash))
+            .unwrap_or_else(|| "No map".to_string());
+
+        resources
+            .get::<RendererShared>()
+            .data
+            .lock()
+            .asset_manager
+            .snapshot(label)
+    }
+
+    fn set_after(&mut self, after: AssetSnapshot) {
+        self.diff = self.before.as_ref().map(|before| before.diff(&after));
+        self.after = Some(after);
+    }
+
+    fn start_automated(&mut self, resources: &AppResources) {
+        self.before = Some(Self::take_snapshot(resources));
+        self.after = None;
+        self.diff = None;
+
+        let mut maps = resources.get_mut::<MapList>();
+        let previous = maps.current_map_index();
+        maps.set_current_map(self.target_map);
+        if self.unload_previous && previous != self.target_map {
+            maps.maps[previous].unload();
+        }
+
+        self.pending = Some(PendingSnapshot { settled_frames: 0 });
+    }
+
+    fn update_pending(&mut self, resources: &AppResources) {
+        if self.pending.is_none() {
+            return;
+        }
+
+        let settled = match resources
+            .get::<MapList>()
+            .current_map()
+            .map(|m| &m.load_state)
+        {
+            Some(MapLoadState::Error(e)) => {
+                error!("Memory snapshot diff cancelled, the map failed to load: {e}");
+                self.pending = None;
+                return;
+            }
+            Some(MapLoadState::Loaded) => resources
+                .get::<RendererShared>()
+                .data
+                .lock()
+                .asset_manager
+                .is_idle(),
+            _ => false,
+        };
+
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+
+        pending.settled_frames = if settled {
+            pending.settled_frames + 1
+        } else {
+            0
+        };
+
+        if pending.settled_frames >= SETTLE_FRAMES {
+            self.pending = None;
+            self.set_after(Self::take_snapshot(resources));
+        }
+    }
+
+    fn report(&self) -> String {
+        let (Some(before), Some(after), Some(diff)) = (&self.before, &self.after, &self.diff)
+        else {
+            return String::new();
+        };
+
+        let mut report = String::new();
+        writeln!(report, "Before: {}", before.label).ok();
+        writeln!(report, "After: {}", after.label).ok();
+        for (name, assets) in [
+            ("Freed", &diff.freed),
+            ("Persisted", &diff.persisted),
+            ("New", &diff.new),
+        ] {
+            writeln!(report, "\n{name}:").ok();
+            for (kind, totals) in AssetSnapshotDiff::totals(assets) {
+                writeln!(
+                    report,
+                    "  {kind}: {} ({})",
+                    totals.count,
+                    format_bytes(totals.size)
+                )
+                .ok();
+            }
+            for (id, entry) in assets {
+                writeln!(
+                    report,
+                    "    {} {id:?} {}",
+                    entry.kind,
+                    format_bytes(entry.size)
+                )
+                .ok();
+            }
+        }
+
+        report
+    }
+}
+
+impl GuiView for MemoryDiffPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        // Keep waiting for the map to load, even if the window is closed in the meantime
+        self.update_pending(resources);
+
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.memory_diff {
+            return None;
+        }
+
+        egui::Window::new("Memory Snapshot Diff")
+            .default_size([480., 560.])
+            .open(&mut windows.memory_diff)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{ICON_CAMERA} Snapshot before"))
+                        .clicked()
+                    {
+                        self.before = Some(Self::take_snapshot(resources));
+                        self.after = None;
+                        self.diff = None;
+                    }
+
+                    if ui
+                        .add_enabled(
+                            self.before.is_some(),
+                            egui::Button::new(format!("{ICON_CAMERA} Snapshot after")),
+                        )
+                        .clicked()
+                    {
+                        self.set_after(Self::take_snapshot(resources));
+                    }
+                });
+
+                ui.separator();
+                ui.label("Snapshot, switch to another map and snapshot again once it has loaded");
+                {
+                    let maps = resources.get::<MapList>();
+                    self.target_map = self.target_map.min(maps.maps.len().saturating_sub(1));
+                    ui.horizontal(|ui| {
+                        ui.label("Switch to");
+                        egui::ComboBox::from_id_source("memory_diff_target_map")
+                            .selected_text(
+                                maps.maps
+                                    .get(self.target_map)
+                                    .map(|m| m.name.as_str())
+                                    .unwrap_or("No maps"),
+                            )
+                            .show_ui(ui, |ui| {
+                                for (i, map) in maps.maps.iter().enumerate() {
+                                    ui.selectable_value(&mut self.target_map, i, map.name.as_str());
+                                }
+                            });
+                    });
+                }
+                ui.checkbox(&mut self.unload_previous, "Unload the current map");
+
+                if let Some(pending) = &self.pending {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Waiting for the map to settle ({}/{SETTLE_FRAMES} frames)",
+                            pending.settled_frames
+                        ));
+                    });
+                } else if ui.button(format!("{ICON_PLAY} Run")).clicked() {
+                    self.start_automated(resources);
+                }
+
+                ui.separator();
+                self.draw_results(ui);
+            });
+
+        None
+    }
+}
+
+impl MemoryDiffPanel {
+    fn draw_results(&self, ui: &mut egui::Ui) {
+        if let Some(before) = &self.before {
+            ui.label(format!(
+                "Before: {} - {} assets, {}",
+                before.label,
+                before.assets.len(),
+                format_bytes(before.total_size())
+            ));
+        }
+        if let Some(after) = &self.after {
+            ui.label(format!(
+                "After: {} - {} assets, {}",
+                after.label,
+                after.assets.len(),
+                format_bytes(after.total_size())
+            ));
+        }
+
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        if ui
+            .button(format!("{ICON_CONTENT_COPY} Copy report"))
+            .clicked()
+        {
+            let report = self.report();
+            ui.output_mut(|o| o.copied_text = report);
+        }
+
+        let categories = [
+            ("Freed", &diff.freed, Color32::LIGHT_GREEN),
+            ("Persisted", &diff.persisted, Color32::LIGHT_GRAY),
+            ("New", &diff.new, Color32::LIGHT_BLUE),
+        ];
+
+        egui::Grid::new("memory_diff_totals")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                for (kind, _) in AssetSnapshotDiff::totals(&[]) {
+                    ui.strong(kind.to_string());
+                }
+                ui.end_row();
+
+                for (name, assets, color) in categories {
+                    ui.label(RichText::new(name).color(color));
+                    for (_, totals) in AssetSnapshotDiff::totals(assets) {
+                        ui.label(format!("{} ({})", totals.count, format_bytes(totals.size)));
+                    }
+                    ui.end_row();
+                }
+            });
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (name, assets, color) in categories {
+                egui::CollapsingHeader::new(
+                    RichText::new(format!("{name} ({})", assets.len())).color(color),
+                )
+                .id_source(name)
+                .show(ui, |ui| asset_list(ui, assets));
+            }
+        });
+    }
+}
+
+fn asset_list(ui: &mut egui::Ui, assets: &[(AssetId, AssetSnapshotEntry)]) {
+    egui::Grid::new(ui.next_auto_id())
+        .striped(true)
+        .show(ui, |ui| {
+            for (id, entry) in assets.iter().take(MAX_LISTED_ASSETS) {
+                ui.label(entry.kind.to_string());
+                match id.tiger_taghash() {
+                    Some(hash) => ui.monospace(hash.to_string()),
+                    None => ui.monospace(format!("{id:?}")),
+                };
+                ui.label(format_bytes(entry.size));
+                ui.end_row();
+            }
+        });
+
+    if assets.len() > MAX_LISTED_ASSETS {
+        ui.label(format!("...and {} more", assets.len() - MAX_LISTED_ASSETS));
+    }
+}
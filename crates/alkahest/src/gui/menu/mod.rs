@@ -1,20 +1,24 @@
-use alkahest_renderer::icons::{
-    ICON_ALPHA_A_BOX_OUTLINE, ICON_ALPHA_D_BOX_OUTLINE, ICON_ALPHA_E_BOX_OUTLINE,
-    ICON_ALPHA_G_BOX_OUTLINE, ICON_ALPHA_H_BOX_OUTLINE, ICON_ALPHA_I_BOX_OUTLINE,
-    ICON_ALPHA_Q_BOX_OUTLINE, ICON_ALPHA_S_BOX_OUTLINE, ICON_ALPHA_W_BOX_OUTLINE,
-    ICON_APPLE_KEYBOARD_SHIFT, ICON_ARROW_ALL, ICON_ARROW_DOWN_BOLD_BOX_OUTLINE,
-    ICON_ARROW_LEFT_BOLD_BOX_OUTLINE, ICON_ARROW_RIGHT_BOLD_BOX_OUTLINE,
-    ICON_ARROW_UP_BOLD_BOX_OUTLINE, ICON_KEYBOARD_SPACE, ICON_MINUS_BOX_OUTLINE,
-    ICON_MOUSE_LEFT_CLICK_OUTLINE, ICON_MOUSE_RIGHT_CLICK_OUTLINE, ICON_NUMERIC_1_BOX_OUTLINE,
-    ICON_NUMERIC_2_BOX_OUTLINE, ICON_NUMERIC_3_BOX_OUTLINE, ICON_NUMERIC_4_BOX_OUTLINE,
-    ICON_PLUS_BOX_OUTLINE,
+use alkahest_renderer::{
+    icons::{
+        ICON_ALPHA_A_BOX_OUTLINE, ICON_ALPHA_D_BOX_OUTLINE, ICON_ALPHA_E_BOX_OUTLINE,
+        ICON_ALPHA_Q_BOX_OUTLINE, ICON_ALPHA_S_BOX_OUTLINE, ICON_ALPHA_W_BOX_OUTLINE,
+        ICON_APPLE_KEYBOARD_SHIFT, ICON_ARROW_ALL, ICON_KEYBOARD_SPACE,
+        ICON_MOUSE_LEFT_CLICK_OUTLINE, ICON_MOUSE_RIGHT_CLICK_OUTLINE, ICON_SPHERE,
+    },
+    renderer::{RenderDebugView, ViewportShading},
+    util::text::StringExt,
 };
 use egui::{vec2, Color32, RichText, Vec2};
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use strum::IntoEnumIterator;
 use winit::window::Window;
 
 use crate::{
-    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    config,
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::HotkeyAction,
+    },
     resources::AppResources,
     util::{consts, consts::CHANGELOG_MD},
 };
@@ -72,6 +76,62 @@ impl GuiView for MenuBar {
                     windows.tfx_extern_editor ^= ui
                         .selectable_label(windows.tfx_extern_editor, "TFX Extern Editor")
                         .clicked();
+                    windows.tag_search ^= ui
+                        .selectable_label(windows.tag_search, "Tag Search")
+                        .clicked();
+                    windows.hex_viewer ^= ui
+                        .selectable_label(windows.hex_viewer, "Hex Viewer")
+                        .clicked();
+                    windows.world_id_search ^= ui
+                        .selectable_label(windows.world_id_search, "World ID Search")
+                        .clicked();
+                    windows.entity_query ^= ui
+                        .selectable_label(windows.entity_query, "Entity Query")
+                        .clicked();
+                    windows.respawn_points ^= ui
+                        .selectable_label(windows.respawn_points, "Respawn Points")
+                        .clicked();
+                    windows.tag_manager ^=
+                        ui.selectable_label(windows.tag_manager, "Tags").clicked();
+                    windows.render_stats ^= ui
+                        .selectable_label(windows.render_stats, "Render Statistics")
+                        .clicked();
+                    windows.mesh_stats ^= ui
+                        .selectable_label(windows.mesh_stats, "Mesh Statistics")
+                        .clicked();
+                    windows.render_targets ^= ui
+                        .selectable_label(windows.render_targets, "Render Targets")
+                        .clicked();
+                    windows.load_report ^= ui
+                        .selectable_label(windows.load_report, "Map Load Reports")
+                        .clicked();
+                    windows.prefabs ^= ui.selectable_label(windows.prefabs, "Prefabs").clicked();
+                    windows.presentation ^= ui
+                        .selectable_label(windows.presentation, "Presentation")
+                        .clicked();
+                    windows.memory ^= ui.selectable_label(windows.memory, "Memory").clicked();
+                    windows.sky_lighting ^= ui
+                        .selectable_label(windows.sky_lighting, "Sky & Global Lighting")
+                        .clicked();
+                    windows.scene_time ^= ui
+                        .selectable_label(windows.scene_time, "Scene Time")
+                        .clicked();
+                    windows.texture_usage ^= ui
+                        .selectable_label(windows.texture_usage, "Texture Usage")
+                        .clicked();
+                    windows.memory_diff ^= ui
+                        .selectable_label(windows.memory_diff, "Memory Snapshot Diff")
+                        .clicked();
+                    windows.env_probe_capture ^= ui
+                        .selectable_label(windows.env_probe_capture, "Environment Probe Capture")
+                        .clicked();
+                    windows.environment ^= ui
+                        .selectable_label(windows.environment, "Environment")
+                        .clicked();
+                    windows.scripts ^= ui.selectable_label(windows.scripts, "Scripts").clicked();
+                    windows.keybindings ^= ui
+                        .selectable_label(windows.keybindings, "Keybindings")
+                        .clicked();
 
                     if cfg!(feature = "profiler") {
                         windows.cpu_profiler ^= ui
@@ -85,6 +145,52 @@ impl GuiView for MenuBar {
                 ui.menu_button("Help", |ui| {
                     self.help_menu(ui, resources);
                 });
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    config::with_mut(|c| {
+                        let shading = &mut c.renderer.shading;
+                        egui::ComboBox::from_id_source("viewport_shading")
+                            .selected_text(format!(
+                                "{ICON_SPHERE} {}",
+                                shading.to_string().split_pascalcase()
+                            ))
+                            .show_ui(ui, |ui| {
+                                for mode in ViewportShading::iter() {
+                                    ui.selectable_value(
+                                        shading,
+                                        mode,
+                                        mode.to_string().split_pascalcase(),
+                                    );
+                                }
+
+                                if *shading == ViewportShading::LightingOnly {
+                                    ui.separator();
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut c.renderer.lighting_only_albedo,
+                                            0.02..=0.8,
+                                        )
+                                        .text("Albedo"),
+                                    )
+                                    .on_hover_text("Linear grey value, 0.18 being middle grey");
+                                    ui.checkbox(&mut c.renderer.lighting_only_shadows, "Shadows");
+                                    ui.checkbox(&mut c.renderer.lighting_only_cubemaps, "Cubemaps")
+                                        .on_hover_text(
+                                            "Only applies when cubemaps are enabled in the \
+                                             render settings",
+                                        );
+                                    ui.checkbox(&mut c.renderer.lighting_only_emission, "Emission");
+                                }
+                            })
+                            .response
+                            .on_hover_text(if c.renderer.debug_view == RenderDebugView::None {
+                                "Viewport shading"
+                            } else {
+                                "Viewport shading (overridden by the debug view in the render \
+                                 settings)"
+                            });
+                    });
+                });
             });
         });
 
@@ -152,6 +258,7 @@ impl MenuBar {
                         .min_row_height(30.0)
                         .min_col_width(200.0)
                         .show(ui, |ui| {
+                            let ctx = ui.ctx().clone();
                             control_section_title!(ui, "Movement");
 
                             control_description!(
@@ -227,7 +334,7 @@ impl MenuBar {
 
                             control_description!(
                                 ui,
-                                ICON_ALPHA_G_BOX_OUTLINE,
+                                HotkeyAction::Gaze.shortcut_text(&ctx),
                                 "Move Camera to Position of Gaze"
                             );
 
@@ -241,25 +348,25 @@ impl MenuBar {
 
                             control_description!(
                                 ui,
-                                format!("{}", ICON_NUMERIC_1_BOX_OUTLINE),
+                                HotkeyAction::ToolSelect.shortcut_text(&ctx),
                                 "Selection Tool"
                             );
 
                             control_description!(
                                 ui,
-                                format!("{}", ICON_NUMERIC_2_BOX_OUTLINE),
+                                HotkeyAction::ToolTranslate.shortcut_text(&ctx),
                                 "Translation Tool"
                             );
 
                             control_description!(
                                 ui,
-                                format!("{}", ICON_NUMERIC_3_BOX_OUTLINE),
+                                HotkeyAction::ToolRotate.shortcut_text(&ctx),
                                 "Rotation Tool"
                             );
 
                             control_description!(
                                 ui,
-                                format!("{}", ICON_NUMERIC_4_BOX_OUTLINE),
+                                HotkeyAction::ToolScale.shortcut_text(&ctx),
                                 "Scale Tool"
                             );
 
@@ -272,55 +379,49 @@ impl MenuBar {
 
                             control_description!(
                                 ui,
-                                ICON_ALPHA_H_BOX_OUTLINE,
+                                HotkeyAction::Hide.shortcut_text(&ctx),
                                 "Toggle Hide Selected Object"
                             );
 
                             control_description!(
                                 ui,
-                                format!("Alt + {}", ICON_ALPHA_H_BOX_OUTLINE),
+                                HotkeyAction::UnhideAll.shortcut_text(&ctx),
                                 "Unhide All Objects"
                             );
 
                             control_description!(
                                 ui,
-                                format!(
-                                    "{} Shift + {}",
-                                    ICON_APPLE_KEYBOARD_SHIFT, ICON_ALPHA_H_BOX_OUTLINE
-                                ),
+                                HotkeyAction::HideUnselected.shortcut_text(&ctx),
                                 "Hide All Unselected Objects"
                             );
 
                             control_description!(
                                 ui,
-                                format!(
-                                    "Ctrl + {} Shift + {}",
-                                    ICON_APPLE_KEYBOARD_SHIFT, ICON_ALPHA_H_BOX_OUTLINE
-                                ),
+                                HotkeyAction::Deselect.shortcut_text(&ctx),
                                 "Deselect All Objects"
                             );
 
                             control_description!(
                                 ui,
-                                ICON_ARROW_UP_BOLD_BOX_OUTLINE,
+                                HotkeyAction::SelectParent.shortcut_text(&ctx),
                                 "Select Parent"
                             );
 
                             control_description!(
                                 ui,
-                                ICON_ARROW_DOWN_BOLD_BOX_OUTLINE,
+                                HotkeyAction::SelectChild.shortcut_text(&ctx),
                                 "Select First Child"
                             );
 
                             control_description!(
                                 ui,
-                                ICON_ARROW_LEFT_BOLD_BOX_OUTLINE,
+                                HotkeyAction::SelectPrevChild.shortcut_text(&ctx),
                                 "Select Previous Child"
                             );
 
                             control_description!(
                                 ui,
-                                ICON_ARROW_RIGHT_BOLD_BOX_OUTLINE,
+                                HotkeyAction::SelectNextChild.shortcut_text(&ctx),
                                 "Select Next Child"
                             );
                             // control_description!(
@@ -345,25 +446,33 @@ impl MenuBar {
 
                             control_description!(
                                 ui,
-                                ICON_ALPHA_I_BOX_OUTLINE,
+                                HotkeyAction::MapSwap.shortcut_text(&ctx),
                                 "Swap to Previous Map"
                             );
 
-                            control_description!(ui, "Page Up", "Swap to Previous Map in List");
+                            control_description!(
+                                ui,
+                                HotkeyAction::MapPrev.shortcut_text(&ctx),
+                                "Swap to Previous Map in List"
+                            );
 
-                            control_description!(ui, "Page Down", "Swap to Next Map in List");
+                            control_description!(
+                                ui,
+                                HotkeyAction::MapNext.shortcut_text(&ctx),
+                                "Swap to Next Map in List"
+                            );
 
                             control_section_title!(ui, "Route Editing");
 
                             control_description!(
                                 ui,
-                                ICON_PLUS_BOX_OUTLINE,
+                                HotkeyAction::AddRouteNodeNext.shortcut_text(&ctx),
                                 "Add node at end of route, or after selected node"
                             );
 
                             control_description!(
                                 ui,
-                                ICON_MINUS_BOX_OUTLINE,
+                                HotkeyAction::AddRouteNodePrev.shortcut_text(&ctx),
                                 "Add node before selected node"
                             );
                         });
@@ -2,23 +2,31 @@ use alkahest_renderer::{
     camera::Camera,
     ecs::{
         common::{Global, Icon, Label, Mutable, RenderCommonBundle},
-        hierarchy::Children,
+        hierarchy::{Children, Parent},
         resources::SelectedEntity,
         route::{Route, RouteNodeBundle, RouteNodeData},
         tags::{EntityTag, NodeFilter, Tags},
         transform::{Transform, TransformFlags},
-        utility::{Beacon, Ruler, Sphere, Utility},
-        SceneInfo,
+        utility::{Beacon, ClipPlane, Ruler, Sphere, Utility, UtilityCommonBundle},
+        Scene, SceneInfo,
+    },
+    icons::{
+        ICON_BOX_CUTTER, ICON_CAMERA_BURST, ICON_DOTS_GRID, ICON_EXPORT, ICON_MAP_MARKER_PATH,
+        ICON_MIRROR, ICON_POKEBALL, ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE,
     },
-    icons::{ICON_MAP_MARKER_PATH, ICON_POKEBALL, ICON_RULER_SQUARE, ICON_SIGN_POLE, ICON_SPHERE},
     renderer::RendererShared,
     resources::AppResources,
-    shader::shader_ball::ShaderBallComponent,
+    shader::shader_ball::{LightProbeMaterial, LightProbeSphere, ShaderBallComponent},
 };
+use bevy_ecs::entity::Entity;
 use egui::Ui;
-use glam::Vec3;
+use glam::{Quat, Vec3};
 
-use crate::{gui::menu::MenuBar, maplist::MapList};
+use crate::{
+    gui::{console, menu::MenuBar},
+    maplist::MapList,
+    model_export::{navmesh::export_navmesh_obj, usd::export_map_usd},
+};
 
 impl MenuBar {
     pub(super) fn utility_menu(&self, ui: &mut Ui, resources: &AppResources) {
@@ -130,6 +138,47 @@ impl MenuBar {
                 ui.close_menu();
             }
         }
+        if ui
+            .button(format!("{} Clip Plane", ICON_BOX_CUTTER))
+            .on_hover_text("Cuts away everything between the camera and the plane")
+            .clicked()
+        {
+            let mut maps = resources.get_mut::<MapList>();
+            let renderer = resources.get::<RendererShared>();
+            let camera = resources.get::<Camera>();
+            let (distance, pos) = renderer
+                .data
+                .lock()
+                .gbuffers
+                .depth_buffer_distance_pos_center(&camera);
+
+            if let Some(map) = maps.current_map_mut() {
+                let e = map.scene.spawn((
+                    NodeFilter::Utility,
+                    Transform {
+                        translation: if distance > 24.0 {
+                            camera.position() + camera.forward() * 8.0
+                        } else {
+                            pos
+                        },
+                        // Keep the side facing away from the camera
+                        rotation: Quat::from_rotation_arc(Vec3::Z, camera.forward()),
+                        flags: TransformFlags::IGNORE_SCALE,
+                        ..Default::default()
+                    },
+                    ClipPlane::default(),
+                    ClipPlane::icon(),
+                    ClipPlane::default_label(),
+                    Tags::from_iter([EntityTag::Utility]),
+                    Mutable,
+                    RenderCommonBundle::default(),
+                ));
+
+                resources.get_mut::<SelectedEntity>().select(e.id());
+
+                ui.close_menu();
+            }
+        }
         if ui
             .button(format!("{} Route", ICON_MAP_MARKER_PATH))
             .clicked()
@@ -201,5 +250,153 @@ impl MenuBar {
                 ui.close_menu();
             }
         }
+
+        ui.menu_button(format!("{} Light Probes", ICON_MIRROR), |ui| {
+            let mut maps = resources.get_mut::<MapList>();
+            let Some(map) = maps.current_map_mut() else {
+                return;
+            };
+            let camera = resources.get::<Camera>();
+
+            if ui
+                .button(format!("{} Probe at Camera", ICON_MIRROR))
+                .clicked()
+            {
+                let e = map.scene.spawn((
+                    Transform::from_translation(camera.position() + camera.forward() * 2.0),
+                    LightProbeSphere::default(),
+                    light_probe_common("Light Probe"),
+                ));
+
+                resources.get_mut::<SelectedEntity>().select(e.id());
+                ui.close_menu();
+            }
+
+            if ui
+                .button(format!("{} Probe Grid", ICON_DOTS_GRID))
+                .on_hover_text(
+                    "Spawns a grid of mirror and diffuse spheres around the camera, showing the \
+                     cubemap and ambient lighting at every point",
+                )
+                .clicked()
+            {
+                let grid = spawn_light_probe_grid(&mut map.scene, camera.position());
+                resources.get_mut::<SelectedEntity>().select(grid);
+                ui.close_menu();
+            }
+        });
+
+        ui.separator();
+
+        if ui
+            .button(format!("{} Dump Frame", ICON_CAMERA_BURST))
+            .on_hover_text("Writes every render target of the current frame to frame_dumps/")
+            .clicked()
+        {
+            console::queue_command("dump_frame", &[]);
+            ui.close_menu();
+        }
+
+        if ui
+            .button(format!("{} Export Map to USD", ICON_EXPORT))
+            .on_hover_text(
+                "Writes the statics, lights and cubemap volumes of the current map as a layered \
+                 USDA scene",
+            )
+            .clicked()
+        {
+            let mut maps = resources.get_mut::<MapList>();
+            if let Some(map) = maps.current_map_mut() {
+                export_map_usd(&mut map.scene, &map.name);
+            }
+            ui.close_menu();
+        }
+
+        if ui
+            .button(format!("{} Export Scene Statistics", ICON_EXPORT))
+            .on_hover_text(
+                "Writes every entity of the current map to a CSV or JSON file, with its type, \
+                 resource hash, world position, bounding box size, feature renderer, origin and \
+                 label",
+            )
+            .clicked()
+        {
+            console::queue_command("export_scene", &[]);
+            ui.close_menu();
+        }
+
+        if ui
+            .button(format!("{} Export Navmesh Geometry", ICON_EXPORT))
+            .on_hover_text(
+                "Writes the terrain and collision shapes of the current map as a single Y-up OBJ \
+                 for navmesh tools, split into walkable and unwalkable materials. Static models \
+                 are not included",
+            )
+            .clicked()
+        {
+            let mut maps = resources.get_mut::<MapList>();
+            if let Some(map) = maps.current_map_mut() {
+                export_navmesh_obj(&mut map.scene, &map.name);
+            }
+            ui.close_menu();
+        }
+    }
+}
+
+const LIGHT_PROBE_GRID_SIZE: i32 = 7;
+const LIGHT_PROBE_GRID_SPACING: f32 = 4.0;
+
+fn light_probe_common(label: &str) -> UtilityCommonBundle {
+    UtilityCommonBundle {
+        label: Label::from(label),
+        icon: Icon::Unicode(ICON_MIRROR),
+        filter: NodeFilter::Utility,
+        tags: Tags::from_iter([EntityTag::Utility]),
+        mutable: Mutable,
+        render_common: RenderCommonBundle::default(),
     }
 }
+
+/// Spawns a horizontal grid of light probes centered on `center`, alternating between mirror and
+/// diffuse spheres. Returns the parent entity of the grid
+fn spawn_light_probe_grid(scene: &mut Scene, center: Vec3) -> Entity {
+    let grid = scene
+        .spawn((
+            Transform::from_translation(center),
+            UtilityCommonBundle {
+                icon: Icon::Unicode(ICON_DOTS_GRID),
+                ..light_probe_common("Light Probe Grid")
+            },
+        ))
+        .id();
+
+    let half = LIGHT_PROBE_GRID_SIZE / 2;
+    let mut probes = vec![];
+    for y in -half..=half {
+        for x in -half..=half {
+            let material = if (x + y) % 2 == 0 {
+                LightProbeMaterial::Mirror
+            } else {
+                LightProbeMaterial::Diffuse
+            };
+
+            let offset = Vec3::new(x as f32, y as f32, 0.0) * LIGHT_PROBE_GRID_SPACING;
+            probes.push(
+                scene
+                    .spawn((
+                        Transform::from_translation(center + offset),
+                        LightProbeSphere {
+                            material,
+                            ..Default::default()
+                        },
+                        Parent(grid),
+                        light_probe_common(&format!("Light Probe ({x}, {y})")),
+                    ))
+                    .id(),
+            );
+        }
+    }
+
+    scene.entity_mut(grid).insert(Children::from_slice(&probes));
+    grid
+}
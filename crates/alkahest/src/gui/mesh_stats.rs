@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use alkahest_renderer::{
+    ecs::{
+        hierarchy::Parent,
+        render::{
+            dynamic_geometry::DynamicModelComponent,
+            mesh_info::{dynamic_mesh_info, static_mesh_info, MeshInfo, UvLayout},
+            static_geometry::{StaticInstances, StaticModelSingle},
+        },
+        resources::SelectedEntity,
+        Scene,
+    },
+    util::dxbc::SignatureElement,
+};
+use bevy_ecs::entity::Entity;
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, RichText, TextureHandle};
+use glam::Vec2;
+use rustc_hash::FxHashMap;
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    resources::AppResources,
+};
+
+const UV_PREVIEW_SIZE: usize = 256;
+
+/// Shows the vertex layout, LOD levels and texture coordinates of the selected entity's model
+#[derive(Default)]
+pub struct MeshStatisticsPanel {
+    /// Model the cached meshes belong to
+    model: Option<TagHash>,
+    meshes: Vec<MeshInfo>,
+    /// Vertex shader input signatures, by mesh index
+    signatures: Vec<VertexSignature>,
+    /// UV layout previews, by mesh index
+    uv_previews: Vec<Option<Result<TextureHandle, String>>>,
+}
+
+type VertexSignature = Result<Arc<Vec<SignatureElement>>, String>;
+
+impl GuiView for MeshStatisticsPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.mesh_stats {
+            return None;
+        }
+
+        let selected = resources.get::<SelectedEntity>().selected();
+        let maps = resources.get::<MapList>();
+        let model = maps
+            .current_map()
+            .zip(selected)
+            .and_then(|(map, e)| selected_model(&map.scene, e));
+
+        if model.as_ref().map(|(hash, _)| *hash) != self.model {
+            self.model = model.as_ref().map(|(hash, _)| *hash);
+            self.meshes = model.map(|(_, get_info)| get_info()).unwrap_or_default();
+            self.signatures = read_vertex_signatures(&self.meshes);
+            self.uv_previews = self.meshes.iter().map(|_| None).collect();
+        }
+
+        egui::Window::new("Mesh Statistics")
+            .default_size([420., 500.])
+            .open(&mut windows.mesh_stats)
+            .show(ctx, |ui| {
+                let Some(model) = self.model else {
+                    ui.label("Select an entity with a static or dynamic model");
+                    return;
+                };
+
+                ui.label(format!("Model {model}"));
+                ui.label(format!(
+                    "{} mesh(es), {} vertices, {} indices",
+                    self.meshes.len(),
+                    self.meshes.iter().map(|m| m.vertex_count()).sum::<u32>(),
+                    self.meshes.iter().map(|m| m.index_count).sum::<u32>(),
+                ));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, mesh) in self.meshes.iter().enumerate() {
+                        egui::CollapsingHeader::new(format!(
+                            "Mesh {i} ({} vertices)",
+                            mesh.vertex_count()
+                        ))
+                        .id_source(("mesh_stats", model, i))
+                        .default_open(self.meshes.len() == 1)
+                        .show(ui, |ui| {
+                            mesh_details_ui(
+                                ui,
+                                ctx,
+                                model,
+                                i,
+                                mesh,
+                                &self.signatures[i],
+                                &mut self.uv_previews[i],
+                            );
+                        });
+                    }
+                });
+            });
+
+        None
+    }
+}
+
+/// Finds the model of an entity, along with a function that collects its mesh info. Static
+/// instances resolve to the model of the instance group they belong to
+fn selected_model(
+    scene: &Scene,
+    entity: Entity,
+) -> Option<(TagHash, Box<dyn FnOnce() -> Vec<MeshInfo> + '_>)> {
+    let e = scene.get_entity(entity)?;
+    if let Some(instances) = e.get::<StaticInstances>() {
+        return Some((
+            instances.model.hash,
+            Box::new(|| static_mesh_info(&instances.model.model)),
+        ));
+    }
+
+    if let Some(single) = e.get::<StaticModelSingle>() {
+        return Some((
+            single.model.hash,
+            Box::new(|| static_mesh_info(&single.model.model)),
+        ));
+    }
+
+    if let Some(dynamic) = e.get::<DynamicModelComponent>() {
+        return Some((
+            dynamic.model.hash,
+            Box::new(|| dynamic_mesh_info(&dynamic.model.model)),
+        ));
+    }
+
+    let parent = e.get::<Parent>()?;
+    let instances = scene.get::<StaticInstances>(parent.0)?;
+    Some((
+        instances.model.hash,
+        Box::new(|| static_mesh_info(&instances.model.model)),
+    ))
+}
+
+/// Reads the vertex shader signature of every mesh, once per technique
+fn read_vertex_signatures(meshes: &[MeshInfo]) -> Vec<VertexSignature> {
+    let mut by_technique: FxHashMap<Option<TagHash>, VertexSignature> = FxHashMap::default();
+    meshes
+        .iter()
+        .map(|mesh| {
+            by_technique
+                .entry(mesh.technique)
+                .or_insert_with(|| {
+                    mesh.vertex_shader_signature()
+                        .map(Arc::new)
+                        .map_err(|e| e.to_string())
+                })
+                .clone()
+        })
+        .collect()
+}
+
+fn mesh_details_ui(
+    ui: &mut egui::Ui,
+    ctx: &Context,
+    model: TagHash,
+    index: usize,
+    mesh: &MeshInfo,
+    signature: &VertexSignature,
+    uv_preview: &mut Option<Result<TextureHandle, String>>,
+) {
+    ui.label(format!(
+        "{} vertices, {} indices ({})",
+        mesh.vertex_count(),
+        mesh.index_count,
+        mesh.index_buffer
+    ));
+
+    ui.strong("Vertex streams");
+    egui::Grid::new(("mesh_streams", index))
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Slot");
+            ui.label("Buffer");
+            ui.label("Stride");
+            ui.label("Vertices");
+            ui.end_row();
+
+            for stream in &mesh.vertex_streams {
+                ui.label(stream.slot.to_string());
+                ui.label(stream.hash.to_string());
+                ui.label(stream.stride.to_string());
+                ui.label(stream.vertex_count.to_string());
+                ui.end_row();
+            }
+        });
+
+    ui.strong("Vertex layout");
+    if let Some(layout) = mesh.input_layout {
+        ui.label(format!("Input layout #{layout}"));
+    }
+    // Elements the vertex shader doesn't read are greyed out
+    if let Err(e) = signature {
+        ui.label(RichText::new(format!("No vertex shader signature: {e}")).weak());
+    }
+    egui::Grid::new(("mesh_layout", index))
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Semantic");
+            ui.label("Format");
+            ui.label("Slot");
+            ui.label("Offset");
+            ui.label("Shader input");
+            ui.end_row();
+
+            for element in mesh.layout_elements() {
+                let input = signature.as_ref().ok().and_then(|s| {
+                    s.iter().find(|s| {
+                        s.semantic_name == element.semantic_name
+                            && s.semantic_index == element.semantic_index
+                    })
+                });
+                let color = match input {
+                    Some(input) if input.is_used() => Color32::WHITE,
+                    Some(_) => Color32::GRAY,
+                    None if signature.is_err() => Color32::WHITE,
+                    None => Color32::DARK_GRAY,
+                };
+
+                let instance = if element.is_instance_data {
+                    " (instance)"
+                } else {
+                    ""
+                };
+                ui.colored_label(
+                    color,
+                    format!(
+                        "{}{}{instance}",
+                        element.semantic_name, element.semantic_index
+                    ),
+                );
+                ui.colored_label(color, format!("{:?}", element.format));
+                ui.colored_label(color, element.buffer_index.to_string());
+                ui.colored_label(color, element.offset.to_string());
+                ui.colored_label(
+                    color,
+                    input.map_or_else(|| "-".to_string(), |i| i.to_string()),
+                );
+                ui.end_row();
+            }
+        });
+
+    ui.strong("LOD levels");
+    egui::Grid::new(("mesh_lods", index))
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Category");
+            ui.label("Parts");
+            ui.label("Indices");
+            ui.end_row();
+
+            for lod in &mesh.lods {
+                ui.label(format!("{:?}", lod.category));
+                ui.label(lod.part_count.to_string());
+                ui.label(lod.index_count.to_string());
+                ui.end_row();
+            }
+        });
+
+    ui.strong("UV layout");
+    match uv_preview {
+        None => {
+            if ui.button("Generate preview").clicked() {
+                *uv_preview = Some(
+                    mesh.read_uv_layout()
+                        .map(|layout| {
+                            ctx.load_texture(
+                                format!("uv_layout_{model}_{index}"),
+                                rasterize_uv_layout(&layout),
+                                egui::TextureOptions::LINEAR,
+                            )
+                        })
+                        .map_err(|e| format!("{e:?}")),
+                );
+            }
+        }
+        Some(Ok(texture)) => {
+            ui.image((texture.id(), egui::vec2(256., 256.)));
+        }
+        Some(Err(e)) => {
+            ui.colored_label(Color32::RED, e.as_str());
+        }
+    }
+}
+
+/// Draws the edges of all triangles into an image, wrapping texture coordinates outside of the
+/// 0-1 range. Triangles spanning a wrap are drawn in the unwrapped space of their first vertex
+fn rasterize_uv_layout(layout: &UvLayout) -> egui::ColorImage {
+    let size = UV_PREVIEW_SIZE;
+    let mut image = egui::ColorImage::new([size, size], Color32::from_gray(24));
+
+    // Light grid lines every 1/4th
+    for i in (0..size).step_by(size / 4) {
+        for j in 0..size {
+            image[(i, j)] = Color32::from_gray(48);
+            image[(j, i)] = Color32::from_gray(48);
+        }
+    }
+
+    let to_pixel = |uv: Vec2, origin: Vec2| {
+        let p = (uv - origin) * size as f32;
+        (p.x as i32, p.y as i32)
+    };
+
+    for triangle in &layout.triangles {
+        let uvs = triangle.map(|i| layout.texcoords[i as usize]);
+        let origin = uvs[0].floor();
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            draw_line(
+                &mut image,
+                to_pixel(uvs[a], origin),
+                to_pixel(uvs[b], origin),
+                Color32::from_rgb(0x33, 0x96, 0xda),
+            );
+        }
+    }
+
+    image
+}
+
+fn draw_line(image: &mut egui::ColorImage, from: (i32, i32), to: (i32, i32), color: Color32) {
+    let [width, height] = image.size;
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let sx = if x < to.0 { 1 } else { -1 };
+    let sy = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    // Lines this long are degenerate texture coordinates, don't bother walking them
+    if dx > 4 * width as i32 || -dy > 4 * height as i32 {
+        return;
+    }
+
+    loop {
+        if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+            image[(x as usize, y as usize)] = color;
+        }
+
+        if (x, y) == to {
+            break;
+        }
+
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use alkahest_data::occlusion::Aabb;
+use alkahest_renderer::{
+    camera::{
+        fps::FpsCamera,
+        tween::{ease_out_exponential, Tween},
+        Camera, CameraProjection, Viewport,
+    },
+    ecs::{
+        hierarchy::Children,
+        resources::SelectedEntity,
+        route::{Route, RouteNode},
+        transform::Transform,
+    },
+    icons::ICON_REFRESH,
+    renderer::Renderer,
+    resources::AppResources,
+    util::image::Png,
+};
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, Pos2, Rect, Sense, Stroke};
+use glam::{Mat4, UVec2, Vec2, Vec3};
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::context::{GuiCtx, GuiView, ViewAction},
+    maplist::{Map, MapList, MapLoadState},
+    util::text::alk_color_to_egui,
+};
+
+/// Resolution of the top-down render
+const MINIMAP_RESOLUTION: u32 = 512;
+/// Size of the widget on screen, in points
+const MINIMAP_SIZE: f32 = 220.0;
+/// Number of consecutive frames the asset manager has to be idle before the map is rendered, so
+/// textures streamed in after loading are included
+const SETTLE_FRAMES: usize = 30;
+
+/// Cached top-down render of the current map, shared between the capture step and the overlay
+#[derive(Default)]
+pub struct Minimap {
+    /// Map the image was rendered for
+    map: Option<TagHash>,
+    /// Rendered image that hasn't been uploaded to egui yet
+    image: Option<Png>,
+    /// World space XY bounds covered by the image
+    bounds: (Vec2, Vec2),
+    refresh_requested: bool,
+    settled_frames: usize,
+}
+
+impl Minimap {
+    pub fn request_refresh(&mut self) {
+        self.refresh_requested = true;
+    }
+}
+
+/// Renders the minimap of the current map once it has finished loading
+pub fn minimap_capture_step(renderer: &Renderer, map: &mut Map, resources: &AppResources) {
+    if !config::with(|c| c.visual.minimap) {
+        return;
+    }
+
+    {
+        let mut minimap = resources.get_mut::<Minimap>();
+        if minimap.map == Some(map.hash) && !minimap.refresh_requested {
+            return;
+        }
+
+        let settled =
+            map.load_state == MapLoadState::Loaded && renderer.data.lock().asset_manager.is_idle();
+        minimap.settled_frames = if settled {
+            minimap.settled_frames + 1
+        } else {
+            0
+        };
+
+        if minimap.settled_frames < SETTLE_FRAMES {
+            return;
+        }
+    }
+
+    let (min, max) = map_bounds(&mut map.scene);
+    let center = (min + max) / 2.0;
+    let half_size = (max.truncate() - min.truncate()).max_element() / 2.0 * 1.05;
+    // Depth range covering everything from slightly above the highest to below the lowest point
+    let height = max.z - min.z + 20.0;
+    let eye = Vec3::new(center.x, center.y, max.z + 10.0);
+
+    let mut camera = Camera::new(
+        Viewport {
+            origin: UVec2::ZERO,
+            size: UVec2::splat(MINIMAP_RESOLUTION),
+        },
+        CameraProjection::orthographic(Vec3::new(half_size, half_size, height)),
+        Box::<FpsCamera>::default(),
+    );
+    camera.set_position(eye);
+    camera.update_matrices();
+    // Looking straight down, with +Y pointing up in the image
+    camera.set_view_matrix(Mat4::look_at_rh(eye, eye - Vec3::Z, Vec3::Y));
+
+    let result = renderer.capture_view(&camera, &mut map.scene, resources);
+
+    let mut minimap = resources.get_mut::<Minimap>();
+    minimap.map = Some(map.hash);
+    minimap.refresh_requested = false;
+    minimap.settled_frames = 0;
+    match result {
+        Ok(image) => {
+            minimap.image = Some(image);
+            minimap.bounds = (
+                center.truncate() - Vec2::splat(half_size),
+                center.truncate() + Vec2::splat(half_size),
+            );
+        }
+        Err(e) => error!("Failed to render minimap: {e:?}"),
+    }
+}
+
+/// Bounds of the map's geometry, ignoring the 1% most extreme objects on each side (skyboxes,
+/// far away backdrops)
+pub fn map_bounds(scene: &mut alkahest_renderer::ecs::Scene) -> (Vec3, Vec3) {
+    let (mut xs, mut ys, mut zs) = (vec![], vec![], vec![]);
+    for (transform, bounds) in scene.query::<(&Transform, &Aabb)>().iter(scene) {
+        let center = transform.local_to_world().transform_point3(bounds.center());
+        xs.push(center.x);
+        ys.push(center.y);
+        zs.push(center.z);
+    }
+
+    if xs.is_empty() {
+        return (Vec3::splat(-100.0), Vec3::splat(100.0));
+    }
+
+    let percentiles = |values: &mut Vec<f32>| {
+        values.sort_by(f32::total_cmp);
+        let outliers = values.len() / 100;
+        (values[outliers], values[values.len() - 1 - outliers])
+    };
+    let (x, y, z) = (
+        percentiles(&mut xs),
+        percentiles(&mut ys),
+        percentiles(&mut zs),
+    );
+
+    (Vec3::new(x.0, y.0, z.0), Vec3::new(x.1, y.1, z.1))
+}
+
+#[derive(Default)]
+pub struct MinimapOverlay {
+    texture: Option<egui::TextureHandle>,
+}
+
+impl GuiView for MinimapOverlay {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        if !config::with(|c| c.visual.minimap) {
+            return None;
+        }
+
+        let mut maps = resources.get_mut::<MapList>();
+        let Some(map) = maps.current_map_mut() else {
+            return None;
+        };
+        let map_hash = map.hash;
+
+        let mut minimap = resources.get_mut::<Minimap>();
+        if let Some(image) = minimap.image.take() {
+            let texture = ctx.load_texture(
+                "minimap",
+                egui::ColorImage::from_rgba_unmultiplied(image.dimensions, &image.data),
+                egui::TextureOptions::LINEAR,
+            );
+            self.texture = Some(texture);
+        }
+
+        if minimap.map != Some(map_hash) {
+            return None;
+        }
+        let Some(texture) = &self.texture else {
+            return None;
+        };
+
+        let (bounds_min, bounds_max) = minimap.bounds;
+        let to_screen = |rect: Rect, p: Vec3| -> Pos2 {
+            let uv = (p.truncate() - bounds_min) / (bounds_max - bounds_min);
+            rect.lerp_inside([uv.x, 1.0 - uv.y].into())
+        };
+        let to_world = |rect: Rect, p: Pos2| -> Vec2 {
+            let uv = (p - rect.min) / rect.size();
+            bounds_min + (bounds_max - bounds_min) * Vec2::new(uv.x, 1.0 - uv.y)
+        };
+
+        let mut refresh = false;
+        let mut teleport = None;
+        egui::Area::new(egui::Id::new("minimap"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -40.0])
+            .show(ctx, |ui| {
+                let (rect, response) =
+                    ui.allocate_exact_size(egui::vec2(MINIMAP_SIZE, MINIMAP_SIZE), Sense::click());
+                let painter = ui.painter_at(rect);
+                painter.image(
+                    texture.id(),
+                    rect,
+                    Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+                painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::from_gray(96)));
+
+                // Routes
+                let mut q_routes = map.scene.query::<(&Route, &Children)>();
+                let scene = &map.scene;
+                for (route, children) in q_routes.iter(scene) {
+                    let color = alk_color_to_egui(route.color);
+                    let points = children
+                        .iter()
+                        .filter_map(|&e| {
+                            let node = scene.get::<RouteNode>(e)?;
+                            if node.map_hash.is_some_and(|h| h != map_hash) {
+                                return None;
+                            }
+                            scene
+                                .get::<Transform>(e)
+                                .map(|t| to_screen(rect, t.translation))
+                        })
+                        .collect::<Vec<_>>();
+
+                    for segment in points.windows(2) {
+                        painter.line_segment([segment[0], segment[1]], Stroke::new(1.5, color));
+                    }
+                    for &p in &points {
+                        painter.circle_filled(p, 2.5, color);
+                    }
+                }
+
+                // Selected entity
+                if let Some(transform) = resources
+                    .get::<SelectedEntity>()
+                    .selected()
+                    .and_then(|e| scene.get::<Transform>(e))
+                {
+                    painter.circle_stroke(
+                        to_screen(rect, transform.translation),
+                        5.0,
+                        Stroke::new(2.0, Color32::from_rgb(255, 140, 30)),
+                    );
+                }
+
+                // Camera and its (horizontal) field of view
+                let camera = resources.get::<Camera>();
+                let position = to_screen(rect, camera.position());
+                let forward = camera.forward().truncate().normalize_or_zero();
+                if forward != Vec2::ZERO {
+                    let half_fov = ((camera.fov() * 0.5).to_radians().tan()
+                        * camera.viewport().aspect_ratio())
+                    .atan();
+                    let length = MINIMAP_SIZE * 0.12;
+                    let edge = |angle: f32| {
+                        let dir = Vec2::from_angle(angle).rotate(forward) * length;
+                        position + egui::vec2(dir.x, -dir.y)
+                    };
+                    let color = Color32::from_rgba_unmultiplied(255, 255, 255, 48);
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![position, edge(half_fov), edge(-half_fov)],
+                        color,
+                        Stroke::new(1.0, Color32::WHITE),
+                    ));
+                }
+                painter.circle_filled(position, 3.0, Color32::WHITE);
+
+                if let Some(hover) = response.hover_pos() {
+                    let p = to_world(rect, hover);
+                    response
+                        .clone()
+                        .on_hover_text(format!("Click to teleport to ({:.1}, {:.1})", p.x, p.y));
+                }
+                if response.clicked() {
+                    teleport = response.interact_pointer_pos().map(|p| to_world(rect, p));
+                }
+
+                let refresh_rect = Rect::from_min_size(
+                    rect.right_top() + egui::vec2(-22.0, 2.0),
+                    egui::vec2(20.0, 20.0),
+                );
+                refresh = ui
+                    .put(
+                        refresh_rect,
+                        egui::Button::new(ICON_REFRESH.to_string()).small(),
+                    )
+                    .on_hover_text("Render the minimap again")
+                    .clicked();
+            });
+
+        if refresh {
+            minimap.request_refresh();
+        }
+
+        if let Some(target) = teleport {
+            let mut camera = resources.get_mut::<Camera>();
+            let position = camera.position();
+            camera.tween = Some(Tween::new(
+                ease_out_exponential,
+                Some((position, target.extend(position.z))),
+                None,
+                0.5,
+            ));
+        }
+
+        None
+    }
+}
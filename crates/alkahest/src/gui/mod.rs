@@ -3,29 +3,56 @@ use egui::Response;
 pub mod activity_select;
 mod configuration;
 pub mod context;
+pub mod entity_query;
 mod fps_display;
+pub mod hex_viewer;
 pub mod hotkeys;
 pub use alkahest_renderer::icons;
 mod input;
 pub mod inspector;
+mod keybindings;
 mod sodi;
+pub mod solo;
+pub mod startup;
+mod tag_manager;
+mod tag_search;
+mod target_viewer;
+mod texture_usage;
 mod tfx;
+pub mod toasts;
 
 // Custom widgets
+mod autosave;
 pub mod big_button;
 mod bottom_bar;
 pub mod chip;
 mod commands;
 pub mod console;
 mod crosshair;
+mod env_probe;
+mod environment;
+mod error_cards;
 pub mod gizmo;
 mod load_indicator;
+mod load_report;
+mod memory;
+mod memory_diff;
 mod menu;
+mod mesh_stats;
+pub mod minimap;
 mod node_gizmos;
 mod outliner;
+mod prefabs;
+mod presentation;
 mod profiler;
+mod render_stats;
+mod respawn_points;
+mod scene_time;
+mod scripts;
+mod sky;
 pub(crate) mod updater;
 mod util;
+mod world_id_search;
 
 pub use configuration::SelectionGizmoMode;
 
@@ -9,7 +9,7 @@ use alkahest_renderer::{
         visibility::{Visibility, VisibilityHelper as _},
     },
     icons::ICON_HELP,
-    renderer::{ImmediateLabel, LabelAlign, RendererShared},
+    renderer::{ImmediateLabel, LabelAlign, PickLayer, RendererShared},
     resources::AppResources,
     ColorExt,
 };
@@ -17,6 +17,8 @@ use alkahest_renderer::{
 use bevy_ecs::entity::Entity;
 use egui::{Color32, Context, Pos2, Rect, Sense, Ui, UiStackInfo};
 use glam::{Vec2, Vec3};
+use itertools::Itertools;
+use strum::IntoEnumIterator;
 use winit::window::Window;
 
 use crate::{
@@ -93,6 +95,13 @@ impl GuiView for NodeGizmoOverlay {
         // if self.debug_overlay.borrow().show_map_resources {
         if config::with(|c| c.visual.node_nametags) {
             let named_nodes_only = config::with(|c| c.visual.node_nametags_named_only);
+            let color_by_origin = config::with(|c| c.visual.node_color_by_origin);
+            let origin_filters = config::with(|c| {
+                ResourceOrigin::iter()
+                    .filter(|o| c.visual.node_origin_filters.contains(&o.to_string()))
+                    .collect_vec()
+            });
+            let render_settings = resources.get::<RendererShared>().settings();
             let mut maps = resources.get_mut::<MapList>();
             if let Some(map) = maps.current_map_mut() {
                 struct NodeDisplayPoint {
@@ -100,6 +109,8 @@ impl GuiView for NodeGizmoOverlay {
                     origin: Option<ResourceOrigin>,
                     label: String,
                     icon: Option<Icon>,
+                    /// Whether clicking the nametag selects the node
+                    pickable: bool,
                 }
 
                 let filters = resources.get::<NodeFilterSet>();
@@ -127,6 +138,10 @@ impl GuiView for NodeGizmoOverlay {
                         continue;
                     }
 
+                    if origin.is_some_and(|o| !origin_filters.contains(o)) {
+                        continue;
+                    }
+
                     if let Some(filter) = filter {
                         if !filters.contains(filter) {
                             continue;
@@ -200,6 +215,9 @@ impl GuiView for NodeGizmoOverlay {
                             origin: origin.cloned(),
                             label: label.map(|v| v.label.clone()).unwrap_or_default(),
                             icon: icon.cloned(),
+                            pickable: filter
+                                .and_then(|f| PickLayer::from_node_filter(*f))
+                                .map_or(true, |l| render_settings.is_pickable(l)),
                         },
                     ))
                 }
@@ -219,7 +237,10 @@ impl GuiView for NodeGizmoOverlay {
                     let icon = node.icon.clone().unwrap_or(Icon::Unicode(ICON_HELP));
                     // let c = res.resource.debug_color();
                     // let color = egui::Color32::from_rgb(c[0], c[1], c[2]);
-                    let color = icon.color();
+                    let color = match node.origin {
+                        Some(origin) if color_by_origin => origin.color(),
+                        _ => icon.color(),
+                    };
                     // if self.debug_overlay.borrow().show_map_resource_label
                     //     || selected_entity == Some(e)
                     if true {
@@ -252,7 +273,7 @@ impl GuiView for NodeGizmoOverlay {
                             );
                         }
 
-                        if response.hovered() {
+                        if response.hovered() && node.pickable {
                             if let Some(mouse_pos) = ctx.input(|i| i.pointer.latest_pos()) {
                                 if debug_string_rect.expand(4.0).contains(mouse_pos) {
                                     top_hovered = Some((i, debug_string_rect));
@@ -320,19 +341,9 @@ impl GuiView for NodeGizmoOverlay {
                             painter.text(
                                 egui::Pos2::from(screen_point.to_array()) + egui::vec2(5.5, 5.5),
                                 egui::Align2::CENTER_CENTER,
-                                match origin {
-                                    ResourceOrigin::Map => "M",
-                                    ResourceOrigin::Activity => "A",
-                                    ResourceOrigin::ActivityBruteforce => "Ab",
-                                    ResourceOrigin::Ambient => "AM",
-                                },
+                                origin.short_name(),
                                 egui::FontId::monospace(12.0),
-                                match origin {
-                                    ResourceOrigin::Map => Color32::LIGHT_RED,
-                                    ResourceOrigin::Activity => Color32::GREEN,
-                                    ResourceOrigin::ActivityBruteforce => Color32::RED,
-                                    ResourceOrigin::Ambient => Color32::from_rgb(0, 255, 255),
-                                },
+                                origin.color(),
                             );
                         }
                     }
@@ -1,29 +1,51 @@
+use std::{
+    collections::BTreeSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use alkahest_renderer::{
     camera::Camera,
     ecs::{
-        common::{Icon, Label, Mutable},
+        common::{Icon, Label, Mutable, ResourceOrigin},
         hierarchy::{Children, Parent},
-        resources::SelectedEntity,
+        map::NodeMetadata,
+        resources::{SearchHighlight, SearchHighlightSource, SelectedEntity},
+        route::RouteNode,
         tags::{EntityTag, Tags},
         transform::Transform,
         visibility::{Visibility, VisibilityHelper},
         Scene,
     },
     resources::AppResources,
-    util::{color::ColorExt, text::prettify_distance},
+    util::{
+        color::ColorExt,
+        text::{prettify_distance, StringExt},
+    },
 };
-use bevy_ecs::{entity::Entity, query::Without, system::Commands, world::EntityRef};
+use bevy_ecs::{
+    entity::Entity,
+    prelude::{RemovedComponents, Resource},
+    query::{Added, Changed, Or, Without},
+    system::{Commands, Query, SystemId},
+    world::EntityRef,
+};
+use destiny_pkg::TagHash;
 use egui::{collapsing_header::CollapsingState, Color32, RichText};
+use glam::Vec3;
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
 use strum::IntoEnumIterator;
 use winit::window::Window;
 
 use crate::{
+    config,
     gui::{
         chip::EcsTagsExt,
         context::{GuiCtx, GuiView, ViewAction},
-        icons::{ICON_DELETE, ICON_EYE_OFF},
+        hotkeys::get_ancestors,
+        icons::{ICON_CONTENT_COPY, ICON_DELETE, ICON_EYE, ICON_EYE_OFF, ICON_TABLE},
+        util::{entity_copy_menu, tag_info_tooltip},
     },
     maplist::{Map, MapList},
     util::text::alk_color_to_egui,
@@ -35,6 +57,84 @@ pub struct OutlinerPanel {
     filters: FxHashMap<EntityTag, bool>,
 
     search: String,
+
+    index: Option<OutlinerIndex>,
+    /// Roots in the order they are shown
+    sorted: Option<SortedRoots>,
+    /// Groups shown when grouping is enabled, along with the sorted roots they were built from
+    groups: Option<(Arc<[(Entity, f32)]>, Vec<OriginGroup>)>,
+    /// Set when the visibility of any entity changed since the group visibility was last updated
+    group_visibility_dirty: bool,
+    /// Map and index generation last sent to the scene's [`SearchHighlight`]
+    highlighted: Option<(TagHash, u64)>,
+}
+
+/// Root entities matching the current search and filters, maintained incrementally from scene changes
+struct OutlinerIndex {
+    map: TagHash,
+    search: String,
+    filters: Vec<EntityTag>,
+    entity_count: u32,
+    /// Bumped every time `roots` changes
+    generation: u64,
+    roots: BTreeSet<Entity>,
+}
+
+/// Minimum time between re-sorts by distance while the camera or the roots keep moving. The
+/// distances shown next to the entries are always up to date, only their order lags behind
+const DISTANCE_SORT_INTERVAL: Duration = Duration::from_millis(250);
+
+struct SortedRoots {
+    /// Index generation the roots were sorted for
+    generation: u64,
+    /// Camera position the roots were sorted by, `None` when they're not sorted by distance
+    position: Option<Vec3>,
+    /// Set when a root moved since the roots were sorted
+    roots_moved: bool,
+    sorted_at: Instant,
+    entities: Arc<[(Entity, f32)]>,
+}
+
+/// Per-scene handle to the registered [`outliner_changes_system`]
+#[derive(Resource)]
+struct OutlinerChangeTracker(SystemId<(), OutlinerChanges>);
+
+#[derive(Default)]
+struct OutlinerChanges {
+    /// Entities that were spawned, despawned, (un)parented, relabeled or retagged
+    entities: Vec<Entity>,
+    /// Whether any root entity moved, which invalidates the distance sort
+    roots_moved: bool,
+    /// Whether the visibility of any entity changed, which invalidates the group visibility
+    visibility_changed: bool,
+}
+
+/// Returns the changes relevant to the outliner since the last run
+fn outliner_changes_system(
+    q_changed: Query<
+        Entity,
+        Or<(
+            Added<Transform>,
+            Added<Parent>,
+            Changed<Label>,
+            Changed<Tags>,
+        )>,
+    >,
+    q_moved: Query<(), (Changed<Transform>, Without<Parent>)>,
+    q_visibility: Query<(), Changed<Visibility>>,
+    mut removed_parents: RemovedComponents<Parent>,
+    mut removed_transforms: RemovedComponents<Transform>,
+    mut removed_visibility: RemovedComponents<Visibility>,
+) -> OutlinerChanges {
+    OutlinerChanges {
+        entities: q_changed
+            .iter()
+            .chain(removed_parents.read())
+            .chain(removed_transforms.read())
+            .collect(),
+        roots_moved: !q_moved.is_empty(),
+        visibility_changed: !q_visibility.is_empty() || removed_visibility.read().next().is_some(),
+    }
 }
 
 impl Default for OutlinerPanel {
@@ -45,6 +145,11 @@ impl Default for OutlinerPanel {
                 .map(|tag| (tag, false))
                 .collect::<FxHashMap<_, _>>(),
             search: "".to_string(),
+            index: None,
+            sorted: None,
+            groups: None,
+            group_visibility_dirty: false,
+            highlighted: None,
         }
     }
 }
@@ -59,72 +164,13 @@ impl GuiView for OutlinerPanel {
     ) -> Option<ViewAction> {
         let mut maps = resources.get_mut::<MapList>();
         if let Some(map) = maps.current_map_mut() {
-            let scene = &mut map.scene;
-
-            let camera = resources.get::<Camera>();
-
-            fn search(entity: Entity, s: &str, sce: &Scene) -> bool {
-                let e = sce.entity(entity);
-                let Some(label) = e.get::<Label>().map(Label::to_string) else {
-                    return false;
-                };
-
-                if let Some(children) = e.get::<Children>() {
-                    for child in children.iter() {
-                        if search(*child, s, sce) {
-                            return true;
-                        }
-                    }
-                }
-
-                if !label.to_lowercase().contains(s) {
-                    return false;
-                }
-
-                true
-            }
-
+            profiling::scope!("OutlinerPanel::draw");
             let enabled_filters = self.filters.iter().filter(|(_, v)| **v).count();
-            let mut entities = scene
-                .query_filtered::<(Entity, Option<&Transform>, Option<&Tags>), Without<Parent>>()
-                .iter(scene)
-                .filter(|(e, _, tags)| {
-                    // Match search string
-                    if !self.search.is_empty() {
-                        let s = self.search.to_lowercase();
-                        if !search(*e, &s, scene) {
-                            return false;
-                        }
-                    }
-
-                    if enabled_filters == 0 {
-                        return true;
-                    }
-
-                    // Check if the entity has all the tags that are enabled
-                    tags.map_or(false, |tags| {
-                        self.filters
-                            .iter()
-                            .filter(|(_, enabled)| **enabled)
-                            .all(|(tag, _)| tags.0.contains(tag))
-                    })
-                })
-                .map(|(e, transform, _tags)| {
-                    let distance = if let Some(transform) = transform {
-                        (transform.translation - camera.position()).length()
-                    } else {
-                        f32::INFINITY
-                    };
+            self.update_index(map.hash, &mut map.scene);
+            self.update_search_highlight(map);
 
-                    (e, distance)
-                })
-                .collect_vec();
-
-            entities.sort_by_key(|(e, _)| *e);
-
-            if self.sort_by_distance {
-                entities.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
-            }
+            let camera = resources.get::<Camera>();
+            let entities = self.sorted_roots(&map.scene, camera.position());
 
             // let mut selected_entity = resources.get_mut::<SelectedEntity>();
             // let mut delete_entity = None;
@@ -137,6 +183,12 @@ impl GuiView for OutlinerPanel {
 
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.sort_by_distance, "Sort by distance");
+                    config::with_mut(|c| {
+                        ui.checkbox(&mut c.visual.outliner_raw_labels, "Raw names")
+                            .on_hover_text("Show hash-based names instead of resolved names");
+                        ui.checkbox(&mut c.visual.outliner_grouped, "Group")
+                            .on_hover_text("Group entities by resource origin and data table");
+                    });
 
                     let filter_count = if enabled_filters > 0 {
                         format!(" ({})", enabled_filters)
@@ -167,8 +219,12 @@ impl GuiView for OutlinerPanel {
                         // ui.spacing().interact_size.y,
                         // entities.len(),
                         |ui| {
-                            for &(ent, _distance) in &entities {
-                                self.entity_entry(ui, ent, map, resources);
+                            if config::with(|c| c.visual.outliner_grouped) {
+                                self.grouped_entries(ui, &entities, map, resources);
+                            } else {
+                                for &(ent, _distance) in entities.iter() {
+                                    self.entity_entry(ui, ent, map, resources);
+                                }
                             }
                         },
                     );
@@ -179,7 +235,393 @@ impl GuiView for OutlinerPanel {
     }
 }
 
+fn root_matches(scene: &Scene, root: Entity, search: &str, filters: &[EntityTag]) -> bool {
+    fn search_recursive(entity: Entity, s: &str, scene: &Scene) -> bool {
+        let e = scene.entity(entity);
+        let Some(label) = e.get::<Label>() else {
+            return false;
+        };
+
+        if let Some(children) = e.get::<Children>() {
+            for child in children.iter() {
+                if search_recursive(*child, s, scene) {
+                    return true;
+                }
+            }
+        }
+
+        label.text(false).to_lowercase().contains(s) || label.text(true).to_lowercase().contains(s)
+    }
+
+    // Match search string
+    if !search.is_empty() && !search_recursive(root, search, scene) {
+        return false;
+    }
+
+    if filters.is_empty() {
+        return true;
+    }
+
+    // Check if the entity has all the tags that are enabled
+    scene
+        .get::<Tags>(root)
+        .map_or(false, |tags| filters.iter().all(|tag| tags.0.contains(tag)))
+}
+
+/// Root entities loaded with the same resource origin, split up by the data table they came from
+struct OriginGroup {
+    /// `None` for entities that weren't loaded from map data, such as utility entities
+    origin: Option<ResourceOrigin>,
+    tables: Vec<TableGroup>,
+    /// Whether any of the entities is visible
+    visible: bool,
+}
+
+struct TableGroup {
+    table: Option<TagHash>,
+    entities: Vec<Entity>,
+    /// Whether any of the entities is visible
+    visible: bool,
+}
+
+impl OriginGroup {
+    fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.tables.iter().flat_map(|t| t.entities.iter().copied())
+    }
+
+    fn len(&self) -> usize {
+        self.tables.iter().map(|t| t.entities.len()).sum()
+    }
+
+    fn update_visibility(&mut self, scene: &Scene) {
+        for table in &mut self.tables {
+            table.visible = any_visible(scene, table.entities.iter().copied());
+        }
+        self.visible = self.tables.iter().any(|t| t.visible);
+    }
+}
+
+/// Groups roots by origin and source table, keeping the order they are shown in within each table
+fn group_roots(scene: &Scene, roots: &[(Entity, f32)]) -> Vec<OriginGroup> {
+    let mut groups = ResourceOrigin::iter()
+        .map(Some)
+        .chain([None])
+        .map(|origin| OriginGroup {
+            origin,
+            tables: vec![],
+            visible: false,
+        })
+        .collect_vec();
+    let mut table_indices: FxHashMap<(Option<ResourceOrigin>, Option<TagHash>), usize> =
+        FxHashMap::default();
+
+    for &(e, _) in roots {
+        let origin = scene.get::<ResourceOrigin>(e).copied();
+        let table = scene
+            .get::<NodeMetadata>(e)
+            .map(|m| m.source_table)
+            .filter(|t| t.is_some());
+
+        let group = groups.iter_mut().find(|g| g.origin == origin).unwrap();
+        let index = *table_indices.entry((origin, table)).or_insert_with(|| {
+            group.tables.push(TableGroup {
+                table,
+                entities: vec![],
+                visible: false,
+            });
+            group.tables.len() - 1
+        });
+        group.tables[index].entities.push(e);
+    }
+
+    groups.retain(|g| !g.tables.is_empty());
+    groups
+}
+
+/// Shows or hides all of the given entities at once
+fn set_group_visibility(map: &mut Map, entities: impl Iterator<Item = Entity>, visible: bool) {
+    let visibility = if visible {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+
+    let (scene, mut commands) = map.scene_and_commands();
+    // Route node visibility is managed by their route
+    for e in entities.filter(|&e| {
+        scene
+            .get_entity(e)
+            .is_some_and(|e| !e.contains::<RouteNode>())
+    }) {
+        commands.entity(e).insert((visibility,));
+    }
+}
+
+fn any_visible(scene: &Scene, mut entities: impl Iterator<Item = Entity>) -> bool {
+    entities.any(|e| scene.get::<Visibility>(e).is_visible(0))
+}
+
+/// Draws the visibility toggle of a group, a group is visible as long as any of its entities are.
+/// Returns the new visibility when it was clicked
+fn group_visibility_button(ui: &mut egui::Ui, visible: bool) -> Option<bool> {
+    ui.add(egui::Button::new(if visible { ICON_EYE } else { ICON_EYE_OFF }).frame(false))
+        .on_hover_text(if visible { "Hide group" } else { "Show group" })
+        .clicked()
+        .then_some(!visible)
+}
+
 impl OutlinerPanel {
+    fn enabled_filters(&self) -> Vec<EntityTag> {
+        EntityTag::iter()
+            .filter(|tag| self.filters.get(tag).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Brings the root index up to date, only rebuilding it completely when the map, search or filters changed
+    fn update_index(&mut self, map_hash: TagHash, scene: &mut Scene) {
+        profiling::scope!("OutlinerPanel::update_index");
+        let tracker = if let Some(tracker) = scene.get_resource::<OutlinerChangeTracker>() {
+            tracker.0
+        } else {
+            let id = scene.register_system(outliner_changes_system);
+            scene.insert_resource(OutlinerChangeTracker(id));
+            id
+        };
+        let changes = scene.run_system(tracker).unwrap_or_default();
+        if changes.roots_moved {
+            if let Some(sorted) = &mut self.sorted {
+                sorted.roots_moved = true;
+            }
+        }
+        self.group_visibility_dirty |= changes.visibility_changed;
+
+        let filters = self.enabled_filters();
+        let search = self.search.to_lowercase();
+        let entity_count = scene.entities().len();
+
+        let index = match &mut self.index {
+            Some(index)
+                if index.map == map_hash && index.search == search && index.filters == filters =>
+            {
+                index
+            }
+            _ => {
+                let roots = scene
+                    .query_filtered::<Entity, Without<Parent>>()
+                    .iter(scene)
+                    .filter(|e| root_matches(scene, *e, &search, &filters))
+                    .collect();
+
+                let generation = self.index.as_ref().map_or(0, |i| i.generation + 1);
+                self.index = Some(OutlinerIndex {
+                    map: map_hash,
+                    search,
+                    filters,
+                    entity_count,
+                    generation,
+                    roots,
+                });
+                return;
+            }
+        };
+
+        let mut modified = false;
+        for e in changes.entities {
+            if scene.get_entity(e).is_none() {
+                // Despawned
+                modified |= index.roots.remove(&e);
+                continue;
+            }
+
+            if scene.get::<Parent>(e).is_some() {
+                modified |= index.roots.remove(&e);
+            }
+
+            // Changes to children can affect whether their root matches the search
+            let root = get_ancestors(scene, e).last().copied().unwrap_or(e);
+            if root_matches(scene, root, &index.search, &index.filters) {
+                modified |= index.roots.insert(root);
+            } else {
+                modified |= index.roots.remove(&root);
+            }
+        }
+
+        // Catches despawned roots without a transform, `entity_entry` skips any that slip through
+        // when a spawn and despawn cancel each other out
+        if index.entity_count != entity_count {
+            let count = index.roots.len();
+            index.roots.retain(|e| scene.get_entity(*e).is_some());
+            modified |= index.roots.len() != count;
+            index.entity_count = entity_count;
+        }
+
+        if modified {
+            index.generation += 1;
+        }
+    }
+
+    /// Highlights the matching roots in the viewport while a search or filter is active
+    fn update_search_highlight(&mut self, map: &mut Map) {
+        let Some(index) = &self.index else {
+            return;
+        };
+
+        let key = (map.hash, index.generation);
+        if self.highlighted == Some(key) {
+            return;
+        }
+
+        let scene = &mut map.scene;
+        if index.search.is_empty() && index.filters.is_empty() {
+            if let Some(mut highlight) = scene.get_resource_mut::<SearchHighlight>() {
+                highlight.clear(SearchHighlightSource::Outliner);
+            }
+        } else {
+            scene
+                .get_resource_or_insert_with(SearchHighlight::default)
+                .set(
+                    SearchHighlightSource::Outliner,
+                    index.roots.iter().copied().collect(),
+                );
+        }
+
+        self.highlighted = Some(key);
+    }
+
+    /// Returns the roots in the order they are shown. Re-sorted right away when the index or the
+    /// sort mode changes. When sorting by distance and the camera or a root moved, re-sorted at
+    /// most once every [`DISTANCE_SORT_INTERVAL`]
+    fn sorted_roots(&mut self, scene: &Scene, camera_position: Vec3) -> Arc<[(Entity, f32)]> {
+        let Some(index) = &self.index else {
+            return Arc::new([]);
+        };
+
+        let sort_position = self.sort_by_distance.then_some(camera_position);
+        if let Some(sorted) = &self.sorted {
+            let moved =
+                sorted.position != sort_position || (sort_position.is_some() && sorted.roots_moved);
+            if sorted.generation == index.generation
+                && sorted.position.is_some() == sort_position.is_some()
+                && (!moved || sorted.sorted_at.elapsed() < DISTANCE_SORT_INTERVAL)
+            {
+                return sorted.entities.clone();
+            }
+        }
+
+        let entities: Arc<[(Entity, f32)]> = match sort_position {
+            None => index.roots.iter().map(|e| (*e, f32::INFINITY)).collect(),
+            Some(camera_position) => {
+                profiling::scope!("OutlinerPanel::sort_by_distance");
+                let mut entities = index
+                    .roots
+                    .iter()
+                    .map(|e| {
+                        let distance = scene.get::<Transform>(*e).map_or(f32::INFINITY, |t| {
+                            (t.translation - camera_position).length()
+                        });
+
+                        (*e, distance)
+                    })
+                    .collect_vec();
+                entities.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+                entities.into()
+            }
+        };
+
+        self.sorted = Some(SortedRoots {
+            generation: index.generation,
+            position: sort_position,
+            roots_moved: false,
+            sorted_at: Instant::now(),
+            entities: entities.clone(),
+        });
+        entities
+    }
+
+    /// Draws the roots grouped by origin and data table. The groups are only rebuilt when the
+    /// sorted roots change, and their visibility when the visibility of an entity changed
+    fn grouped_entries(
+        &mut self,
+        ui: &mut egui::Ui,
+        entities: &Arc<[(Entity, f32)]>,
+        map: &mut Map,
+        resources: &AppResources,
+    ) {
+        let (entities, mut groups) = match self.groups.take() {
+            Some((grouped, groups)) if Arc::ptr_eq(&grouped, entities) => (grouped, groups),
+            _ => {
+                profiling::scope!("OutlinerPanel::group_roots");
+                self.group_visibility_dirty = true;
+                (entities.clone(), group_roots(&map.scene, entities))
+            }
+        };
+
+        if self.group_visibility_dirty {
+            for group in &mut groups {
+                group.update_visibility(&map.scene);
+            }
+            self.group_visibility_dirty = false;
+        }
+
+        for group in &groups {
+            let (name, color) = match group.origin {
+                Some(origin) => (
+                    format!(
+                        "[{}] {}",
+                        origin.short_name(),
+                        origin.to_string().split_pascalcase()
+                    ),
+                    origin.color(),
+                ),
+                None => ("No origin".to_string(), Color32::GRAY),
+            };
+
+            CollapsingState::load_with_default_open(
+                ui.ctx(),
+                egui::Id::new(("outliner_origin", group.origin)),
+                false,
+            )
+            .show_header(ui, |ui| {
+                if let Some(visible) = group_visibility_button(ui, group.visible) {
+                    set_group_visibility(map, group.entities(), visible);
+                }
+                ui.label(RichText::new(format!("{name} ({})", group.len())).color(color));
+            })
+            .body(|ui| {
+                for table in &group.tables {
+                    CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        egui::Id::new(("outliner_table", group.origin, table.table)),
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        if let Some(visible) = group_visibility_button(ui, table.visible) {
+                            set_group_visibility(map, table.entities.iter().copied(), visible);
+                        }
+
+                        let count = table.entities.len();
+                        match table.table {
+                            Some(table) => {
+                                ui.label(format!("{ICON_TABLE} Table {table} ({count})"))
+                                    .on_hover_ui(|ui| tag_info_tooltip(ui, table));
+                            }
+                            None => {
+                                ui.label(format!("{ICON_TABLE} No data table ({count})"));
+                            }
+                        }
+                    })
+                    .body(|ui| {
+                        for &ent in &table.entities {
+                            self.entity_entry(ui, ent, map, resources);
+                        }
+                    });
+                }
+            });
+        }
+
+        self.groups = Some((entities, groups));
+    }
+
     fn entity_entry(
         &mut self,
         ui: &mut egui::Ui,
@@ -187,8 +629,10 @@ impl OutlinerPanel {
         map: &mut Map,
         resources: &AppResources,
     ) {
-        let mut commands = map.commands();
-        let e = map.scene.entity(ent);
+        let (scene, mut commands) = map.scene_and_commands();
+        let Some(e) = scene.get_entity(ent) else {
+            return;
+        };
 
         let children = e.get::<Children>().cloned();
 
@@ -247,7 +691,8 @@ impl OutlinerPanel {
         };
 
         let label = if let Some(label) = e.get::<Label>() {
-            format!("{label} (id {})", e.id())
+            let raw = config::with(|c| c.visual.outliner_raw_labels);
+            format!("{} (id {})", label.text(raw), e.id())
         } else {
             format!("Entity {}", e.id())
         };
@@ -272,6 +717,18 @@ impl OutlinerPanel {
                 }),
             );
 
+            let entity_tag = e
+                .get::<NodeMetadata>()
+                .map(|m| m.entity_tag)
+                .filter(|t| t.is_some());
+            let response = match entity_tag {
+                Some(tag) => response.on_hover_ui(|ui| {
+                    ui.strong(format!("Entity {tag}"));
+                    tag_info_tooltip(ui, tag);
+                }),
+                None => response,
+            };
+
             response.context_menu(|ui| {
                 ui.add_enabled_ui(e.contains::<Mutable>(), |ui| {
                     // Delete button
@@ -280,6 +737,10 @@ impl OutlinerPanel {
                         cmd.entity(e.id()).despawn();
                     }
                 });
+
+                ui.menu_button(format!("{ICON_CONTENT_COPY} Copy"), |ui| {
+                    entity_copy_menu(ui, e);
+                });
             });
 
             if response.clicked() {
@@ -292,3 +753,59 @@ impl OutlinerPanel {
         });
     }
 }
+
+/// Times the outliner over a synthetic scene of 50k roots, while the camera and every root move
+/// each frame. Run with `cargo test -p alkahest --release bench_outliner -- --ignored --nocapture`
+#[test]
+#[ignore = "benchmark"]
+fn bench_outliner_50k_entities() {
+    const ENTITY_COUNT: usize = 50_000;
+    const FRAMES: usize = 100;
+
+    let mut scene = Scene::new();
+    for i in 0..ENTITY_COUNT {
+        let position = Vec3::new((i % 250) as f32, (i / 250) as f32, 0.0) * 4.0;
+        scene.spawn((
+            Transform::from_translation(position),
+            Label::new_default(format!("Entity {i}")),
+            Visibility::default(),
+        ));
+    }
+
+    let map_hash = TagHash(0x80800000);
+    let mut outliner = OutlinerPanel {
+        sort_by_distance: true,
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    outliner.update_index(map_hash, &mut scene);
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    outliner.sorted_roots(&scene, Vec3::ZERO);
+    let sort = start.elapsed();
+
+    let mut total = Duration::ZERO;
+    let mut slowest = Duration::ZERO;
+    for frame in 0..FRAMES {
+        for mut transform in scene.query::<&mut Transform>().iter_mut(&mut scene) {
+            transform.translation.z += 0.01;
+        }
+
+        let frame_start = Instant::now();
+        outliner.update_index(map_hash, &mut scene);
+        let roots = outliner.sorted_roots(&scene, Vec3::splat(frame as f32));
+        assert_eq!(roots.len(), ENTITY_COUNT);
+        let elapsed = frame_start.elapsed();
+        total += elapsed;
+        slowest = slowest.max(elapsed);
+    }
+
+    println!("Index build: {build:?}");
+    println!("Distance sort: {sort:?}");
+    println!(
+        "Moving frames: {:?} average, {slowest:?} slowest",
+        total / FRAMES as u32
+    );
+}
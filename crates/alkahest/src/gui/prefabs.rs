@@ -0,0 +1,180 @@
+use alkahest_renderer::{
+    camera::Camera,
+    ecs::{
+        common::{Label, Mutable},
+        hierarchy::Parent,
+        resources::SelectedEntity,
+    },
+    icons::{ICON_DELETE, ICON_GROUP, ICON_PLUS, ICON_REFRESH},
+};
+use bevy_ecs::{
+    entity::Entity,
+    query::{With, Without},
+};
+use egui::{Context, RichText};
+use rustc_hash::FxHashSet;
+use winit::window::Window;
+
+use crate::{
+    autosave::save_entity,
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    prefab::{group_entities, list_prefabs, prefab_directory, PrefabFile},
+    resources::AppResources,
+};
+
+/// Groups user entities into prefabs, and spawns saved prefabs at the camera position
+pub struct PrefabPanel {
+    name: String,
+    /// Entities to group into a new prefab
+    selection: FxHashSet<Entity>,
+    /// Saved prefabs, read when the panel is first opened or refreshed
+    prefabs: Option<Vec<PrefabFile>>,
+}
+
+impl Default for PrefabPanel {
+    fn default() -> Self {
+        Self {
+            name: "New Prefab".to_string(),
+            selection: FxHashSet::default(),
+            prefabs: None,
+        }
+    }
+}
+
+impl GuiView for PrefabPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.prefabs {
+            return None;
+        }
+
+        let mut maps = resources.get_mut::<MapList>();
+        let Some(map) = maps.current_map_mut() else {
+            return None;
+        };
+
+        egui::Window::new("Prefabs")
+            .default_size([320., 400.])
+            .open(&mut windows.prefabs)
+            .show(ctx, |ui| {
+                ui.heading("Group entities");
+                // Only top-level rulers, spheres and beacons can be grouped
+                let candidates = map
+                    .scene
+                    .query_filtered::<Entity, (With<Mutable>, Without<Parent>)>()
+                    .iter(&map.scene)
+                    .filter(|&e| save_entity(map.scene.entity(e)).is_some())
+                    .collect::<Vec<_>>();
+                self.selection.retain(|e| candidates.contains(e));
+
+                if candidates.is_empty() {
+                    ui.label("There are no rulers, spheres or beacons in this map to group");
+                } else {
+                    egui::ScrollArea::vertical()
+                        .id_source("prefab_candidates")
+                        .max_height(160.0)
+                        .show(ui, |ui| {
+                            for &e in &candidates {
+                                let label = map
+                                    .scene
+                                    .get::<Label>(e)
+                                    .map_or_else(|| format!("Entity {e}"), |l| l.to_string());
+                                let mut selected = self.selection.contains(&e);
+                                if ui.checkbox(&mut selected, label).changed() {
+                                    if selected {
+                                        self.selection.insert(e);
+                                    } else {
+                                        self.selection.remove(&e);
+                                    }
+                                }
+                            }
+                        });
+                }
+
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.name);
+                    if ui
+                        .add_enabled(
+                            !self.selection.is_empty() && !self.name.is_empty(),
+                            egui::Button::new(format!("{ICON_GROUP} Group")),
+                        )
+                        .clicked()
+                    {
+                        let entities = self.selection.drain().collect::<Vec<_>>();
+                        let group = group_entities(&mut map.scene, &self.name, &entities);
+                        resources.get_mut::<SelectedEntity>().select(group);
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.heading("Saved prefabs");
+                    if ui
+                        .button(ICON_REFRESH.to_string())
+                        .on_hover_text(format!(
+                            "Reload prefabs from {}",
+                            prefab_directory().display()
+                        ))
+                        .clicked()
+                    {
+                        self.prefabs = None;
+                    }
+                });
+
+                let prefabs = self.prefabs.get_or_insert_with(list_prefabs);
+                if prefabs.is_empty() {
+                    ui.label(
+                        "No prefabs have been saved yet. Select a group and press 'Save Prefab' \
+                         to save it",
+                    );
+                }
+
+                let mut removed = None;
+                for (i, file) in prefabs.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(RichText::new(ICON_PLUS.to_string()).strong())
+                            .on_hover_text("Spawn at the camera position")
+                            .clicked()
+                        {
+                            let position = resources.get::<Camera>().position();
+                            let group = file.prefab.instantiate(&mut map.scene, position);
+                            resources.get_mut::<SelectedEntity>().select(group);
+                        }
+
+                        if ui
+                            .button(ICON_DELETE.to_string())
+                            .on_hover_text("Delete the saved prefab")
+                            .clicked()
+                        {
+                            match std::fs::remove_file(&file.path) {
+                                Ok(()) => removed = Some(i),
+                                Err(e) => {
+                                    error!("Failed to delete prefab {}: {e}", file.path.display())
+                                }
+                            }
+                        }
+
+                        ui.label(format!(
+                            "{} ({} entities)",
+                            file.prefab.name,
+                            file.prefab.entities.len()
+                        ));
+                    });
+                }
+
+                if let Some(i) = removed {
+                    prefabs.remove(i);
+                }
+            });
+
+        None
+    }
+}
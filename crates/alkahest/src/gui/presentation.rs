@@ -0,0 +1,224 @@
+use alkahest_renderer::{
+    camera::Camera,
+    icons::{ICON_BOOKMARK_PLUS, ICON_CROSSHAIRS_GPS, ICON_DELETE, ICON_PRESENTATION_PLAY},
+    renderer::RendererShared,
+};
+use egui::{Color32, Context, RichText};
+use strum::IntoEnumIterator;
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    presentation::{CameraBookmark, CameraBookmarks, Presentation, PresentationTransition},
+    resources::AppResources,
+};
+
+/// Manages the camera bookmarks of the current map and starts the presentation
+pub struct PresentationPanel {
+    name: String,
+}
+
+impl Default for PresentationPanel {
+    fn default() -> Self {
+        Self {
+            name: "View 1".to_string(),
+        }
+    }
+}
+
+impl GuiView for PresentationPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.presentation {
+            return None;
+        }
+
+        let maps = resources.get::<MapList>();
+        let Some(map) = maps.current_map() else {
+            return None;
+        };
+
+        let mut bookmarks = resources.get_mut::<CameraBookmarks>();
+        bookmarks.switch_map(map.hash);
+
+        egui::Window::new("Presentation")
+            .default_size([320., 400.])
+            .open(&mut windows.presentation)
+            .show(ctx, |ui| {
+                ui.heading("Bookmarks");
+                let mut changed = false;
+                let mut removed = None;
+                egui::ScrollArea::vertical()
+                    .id_source("presentation_bookmarks")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for (i, bookmark) in bookmarks.bookmarks.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button(ICON_CROSSHAIRS_GPS.to_string())
+                                    .on_hover_text("Go to bookmark")
+                                    .clicked()
+                                {
+                                    bookmark.apply(&mut resources.get_mut::<Camera>());
+                                }
+
+                                if ui
+                                    .button(ICON_DELETE.to_string())
+                                    .on_hover_text("Delete bookmark")
+                                    .clicked()
+                                {
+                                    removed = Some(i);
+                                }
+
+                                ui.label(&bookmark.name);
+                            });
+                        }
+                    });
+
+                if let Some(i) = removed {
+                    bookmarks.bookmarks.remove(i);
+                    changed = true;
+                }
+
+                if bookmarks.bookmarks.is_empty() {
+                    ui.label("This map has no bookmarks yet");
+                }
+
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.name);
+                    if ui
+                        .add_enabled(
+                            !self.name.is_empty(),
+                            egui::Button::new(format!("{ICON_BOOKMARK_PLUS} Add Current View")),
+                        )
+                        .clicked()
+                    {
+                        let bookmark =
+                            CameraBookmark::from_camera(&self.name, &resources.get::<Camera>());
+                        bookmarks.bookmarks.push(bookmark);
+                        self.name = format!("View {}", bookmarks.bookmarks.len() + 1);
+                        changed = true;
+                    }
+                });
+
+                if changed {
+                    if let Err(e) = bookmarks.save() {
+                        error!("Failed to save camera bookmarks: {e:?}");
+                    }
+                }
+
+                ui.separator();
+                ui.heading("Settings");
+                config::with_mut(|c| {
+                    let p = &mut c.presentation;
+                    ui.checkbox(&mut p.orbit_selected, "Orbit selected entity")
+                        .on_hover_text(
+                            "Circle around the selected entity instead of going through the \
+                             bookmarks",
+                        );
+
+                    if p.orbit_selected {
+                        ui.add(
+                            egui::Slider::new(&mut p.orbit_speed, 1.0..=90.0)
+                                .text("Orbit speed")
+                                .suffix("°/s"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut p.orbit_elevation, -60.0..=80.0)
+                                .text("Orbit elevation")
+                                .suffix("°"),
+                        );
+                    } else {
+                        egui::ComboBox::from_label("Transition")
+                            .selected_text(p.transition.to_string())
+                            .show_ui(ui, |ui| {
+                                for transition in PresentationTransition::iter() {
+                                    ui.selectable_value(
+                                        &mut p.transition,
+                                        transition,
+                                        transition.to_string(),
+                                    );
+                                }
+                            });
+                        if p.transition != PresentationTransition::Cut {
+                            ui.add(
+                                egui::Slider::new(&mut p.transition_seconds, 0.25..=10.0)
+                                    .text("Transition time")
+                                    .suffix("s"),
+                            );
+                        }
+                        ui.add(
+                            egui::Slider::new(&mut p.hold_seconds, 1.0..=60.0)
+                                .text("Hold time")
+                                .suffix("s"),
+                        );
+                    }
+                });
+
+                ui.separator();
+                let can_start = config::with(|c| c.presentation.orbit_selected)
+                    || !bookmarks.bookmarks.is_empty();
+                if ui
+                    .add_enabled(
+                        can_start,
+                        egui::Button::new(
+                            RichText::new(format!("{ICON_PRESENTATION_PLAY} Start Presentation"))
+                                .strong(),
+                        ),
+                    )
+                    .on_hover_text("Hides the interface. Press Escape to stop")
+                    .clicked()
+                {
+                    resources.get_mut::<Presentation>().start(
+                        &resources.get::<Camera>(),
+                        &resources.get::<RendererShared>(),
+                    );
+                }
+            });
+
+        None
+    }
+}
+
+/// Draws the fade between bookmarks, and stops the presentation when Escape is pressed
+pub struct PresentationOverlay;
+
+impl GuiView for PresentationOverlay {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut presentation = resources.get_mut::<Presentation>();
+        if !presentation.is_active() {
+            return None;
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            presentation.stop(&resources.get::<RendererShared>());
+            return None;
+        }
+
+        let fade = presentation.fade();
+        if fade > 0.0 {
+            let layer = egui::LayerId::new(egui::Order::Foreground, "presentation_fade".into());
+            ctx.layer_painter(layer).rect_filled(
+                ctx.screen_rect(),
+                0.0,
+                Color32::from_black_alpha((fade * 255.0) as u8),
+            );
+        }
+
+        None
+    }
+}
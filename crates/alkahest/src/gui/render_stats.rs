@@ -0,0 +1,197 @@
+use alkahest_data::tfx::TfxFeatureRenderer;
+use alkahest_renderer::{
+    ecs::{
+        common::ResourceOrigin,
+        render::{
+            decorators::DecoratorRenderer,
+            dynamic_geometry::DynamicModelComponent,
+            static_geometry::{StaticInstance, StaticInstances, StaticModelSingle},
+            terrain::TerrainPatches,
+        },
+        Scene,
+    },
+    renderer::{FeatureStats, RendererShared, StatsCategory},
+};
+use egui::{Context, RichText};
+use itertools::Itertools;
+use strum::{EnumCount, IntoEnumIterator};
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    maplist::MapList,
+    resources::AppResources,
+    util::text::format_bytes,
+};
+
+pub struct RenderStatsPanel;
+
+impl GuiView for RenderStatsPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        let renderer = resources.get::<RendererShared>();
+        renderer.stats.set_enabled(windows.render_stats);
+        if !windows.render_stats {
+            return None;
+        }
+
+        let stats = renderer.stats.last_frame();
+        let entity_counts = resources
+            .get::<MapList>()
+            .current_map()
+            .map(|m| count_entities(&m.scene))
+            .unwrap_or_default();
+
+        egui::Window::new("Render Statistics")
+            .open(&mut windows.render_stats)
+            .show(ctx, |ui| {
+                egui::Grid::new("render_stats_grid")
+                    .striped(true)
+                    .num_columns(7)
+                    .show(ui, |ui| {
+                        for header in [
+                            "Feature",
+                            "Entities",
+                            "Draw calls",
+                            "Triangles",
+                            "Instances",
+                            "Textures",
+                            "Texture memory",
+                        ] {
+                            ui.label(RichText::new(header).strong());
+                        }
+                        ui.end_row();
+
+                        for category in StatsCategory::iter() {
+                            ui.label(category.to_string());
+                            ui.label(entity_counts[category as usize].to_string());
+                            stats_row(ui, stats.get(category));
+                            ui.end_row();
+                        }
+
+                        ui.label(RichText::new("Total").strong());
+                        ui.label(entity_counts.iter().sum::<usize>().to_string());
+                        stats_row(ui, &stats.total());
+                        ui.end_row();
+                    });
+
+                ui.collapsing("By resource origin", |ui| {
+                    stats_grid(
+                        ui,
+                        "render_stats_origin_grid",
+                        "Origin",
+                        ResourceOrigin::iter()
+                            .map(Some)
+                            .chain([None])
+                            .filter_map(|origin| {
+                                let stats = stats.origins.get(&origin)?;
+                                let name = origin.map_or("Other".to_string(), |o| o.to_string());
+                                Some((name, stats))
+                            }),
+                    );
+                });
+
+                ui.collapsing("By activity group", |ui| {
+                    if stats.activity_groups.is_empty() {
+                        ui.label("No activity entities were drawn last frame");
+                        return;
+                    }
+
+                    stats_grid(
+                        ui,
+                        "render_stats_activity_grid",
+                        "Activity phase",
+                        stats
+                            .activity_groups
+                            .iter()
+                            .sorted_by_key(|(_, stats)| std::cmp::Reverse(stats.triangles))
+                            .map(|(phase, stats)| (format!("0x{phase:08X}"), stats)),
+                    );
+                });
+
+                ui.label(
+                    RichText::new(
+                        "Includes every pass rendered last frame (shadows, pickbuffer, etc.). \
+                         Texture counts are per row, textures shared between features, origins \
+                         or activity groups are counted once for each.",
+                    )
+                    .italics()
+                    .weak(),
+                );
+            });
+
+        None
+    }
+}
+
+fn stats_grid<'a>(
+    ui: &mut egui::Ui,
+    id: &str,
+    label: &str,
+    rows: impl Iterator<Item = (String, &'a FeatureStats)>,
+) {
+    egui::Grid::new(id)
+        .striped(true)
+        .num_columns(6)
+        .show(ui, |ui| {
+            for header in [
+                label,
+                "Draw calls",
+                "Triangles",
+                "Instances",
+                "Textures",
+                "Texture memory",
+            ] {
+                ui.label(RichText::new(header).strong());
+            }
+            ui.end_row();
+
+            for (name, stats) in rows {
+                ui.label(name);
+                stats_row(ui, stats);
+                ui.end_row();
+            }
+        });
+}
+
+fn stats_row(ui: &mut egui::Ui, stats: &FeatureStats) {
+    ui.label(stats.draw_calls.to_string());
+    ui.label(stats.triangles.to_string());
+    ui.label(stats.instances.to_string());
+    ui.label(stats.textures.to_string());
+    ui.label(format_bytes(stats.texture_memory));
+}
+
+fn count_entities(scene: &Scene) -> [usize; StatsCategory::COUNT] {
+    let mut counts = [0; StatsCategory::COUNT];
+    for e in scene.iter_entities() {
+        let category = if e.contains::<StaticInstances>()
+            || e.contains::<StaticInstance>()
+            || e.contains::<StaticModelSingle>()
+        {
+            StatsCategory::Statics
+        } else if e.contains::<TerrainPatches>() {
+            StatsCategory::Terrain
+        } else if e.contains::<DecoratorRenderer>() {
+            StatsCategory::Decorators
+        } else if let Some(dynamic) = e.get::<DynamicModelComponent>() {
+            if dynamic.model.feature_type == TfxFeatureRenderer::Water {
+                StatsCategory::Water
+            } else {
+                StatsCategory::Dynamics
+            }
+        } else {
+            continue;
+        };
+
+        counts[category as usize] += 1;
+    }
+
+    counts
+}
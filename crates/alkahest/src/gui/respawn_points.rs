@@ -0,0 +1,167 @@
+use alkahest_data::map::SRespawnPoint;
+use alkahest_renderer::{
+    ecs::{
+        common::Label,
+        map::NodeMetadata,
+        resources::SelectedEntity,
+        respawn::{respawn_set_color, respawn_set_key},
+        tags::{NodeFilter, NodeFilterSet},
+        transform::Transform,
+    },
+    icons::{ICON_ACCOUNT_CONVERT, ICON_TARGET},
+    renderer::RendererShared,
+};
+use bevy_ecs::{entity::Entity, query::With};
+use egui::{Color32, Context, RichText};
+use glam::Vec3;
+use itertools::Itertools;
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::focus_selected,
+    },
+    maplist::MapList,
+    resources::AppResources,
+};
+
+struct RespawnSet {
+    key: (u32, u64),
+    color: Color32,
+    points: Vec<(Entity, String, Vec3)>,
+}
+
+/// Lists the respawn points of the current map by the set they were loaded from
+pub struct RespawnPointsPanel;
+
+impl GuiView for RespawnPointsPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.respawn_points {
+            return None;
+        }
+
+        // Collected up front, focusing an entity needs the map list
+        let sets = {
+            let mut maps = resources.get_mut::<MapList>();
+            let Some(map) = maps.current_map_mut() else {
+                return None;
+            };
+
+            map.scene
+                .query_filtered::<
+                    (Entity, &Transform, Option<&NodeMetadata>, Option<&Label>),
+                    With<SRespawnPoint>,
+                >()
+                .iter(&map.scene)
+                .map(|(e, transform, metadata, label)| {
+                    let key = respawn_set_key(metadata);
+                    let label = label.map_or_else(|| format!("Entity {e}"), |l| l.to_string());
+                    (key, (e, label, transform.translation))
+                })
+                .into_group_map()
+                .into_iter()
+                .map(|(key, points)| RespawnSet {
+                    key,
+                    color: respawn_set_color(key).into(),
+                    points,
+                })
+                .sorted_by_key(|s| s.key)
+                .collect_vec()
+        };
+
+        let mut focus = None;
+        egui::Window::new("Respawn Points")
+            .default_size([320., 480.])
+            .open(&mut windows.respawn_points)
+            .show(ctx, |ui| {
+                if !resources
+                    .get::<NodeFilterSet>()
+                    .contains(&NodeFilter::RespawnPoint)
+                {
+                    ui.label(
+                        RichText::new(
+                            "Respawn points are hidden, enable them in the node filters to see \
+                             their markers",
+                        )
+                        .color(Color32::YELLOW),
+                    );
+                }
+
+                let changed = config::with_mut(|c| {
+                    ui.add(
+                        egui::Slider::new(&mut c.renderer.respawn_influence_radius, 0.0..=50.0)
+                            .text("Influence radius")
+                            .suffix("m"),
+                    )
+                    .on_hover_text("Draws a circle around every respawn point, 0 to disable")
+                    .changed()
+                });
+                if changed {
+                    resources
+                        .get::<RendererShared>()
+                        .set_render_settings(config::with(|c| c.renderer.clone()));
+                }
+
+                ui.separator();
+                if sets.is_empty() {
+                    ui.label("This map has no respawn points");
+                    return;
+                }
+
+                ui.label(format!(
+                    "{} respawn points in {} sets. Sets are colored by the table they were \
+                     loaded from",
+                    sets.iter().map(|s| s.points.len()).sum::<usize>(),
+                    sets.len()
+                ));
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for set in &sets {
+                            let title = RichText::new(format!(
+                                "{ICON_ACCOUNT_CONVERT} Set {:08X}+{:X} ({})",
+                                set.key.0,
+                                set.key.1,
+                                set.points.len()
+                            ))
+                            .color(set.color);
+
+                            egui::CollapsingHeader::new(title)
+                                .id_source(set.key)
+                                .default_open(sets.len() <= 4)
+                                .show(ui, |ui| {
+                                    for (e, label, position) in &set.points {
+                                        if ui
+                                            .button(format!("{ICON_TARGET} {label}"))
+                                            .on_hover_text(format!(
+                                                "{:.1}, {:.1}, {:.1}",
+                                                position.x, position.y, position.z
+                                            ))
+                                            .clicked()
+                                        {
+                                            focus = Some(*e);
+                                        }
+                                    }
+                                });
+                        }
+                    });
+            });
+
+        if let Some(e) = focus {
+            resources.get_mut::<SelectedEntity>().select(e);
+            focus_selected(resources);
+        }
+
+        None
+    }
+}
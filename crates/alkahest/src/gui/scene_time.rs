@@ -0,0 +1,135 @@
+use alkahest_renderer::{
+    icons::{ICON_PAUSE, ICON_PLAY, ICON_SKIP_BACKWARD},
+    renderer::{RendererShared, Time},
+};
+use egui::{Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    resources::AppResources,
+};
+
+const SPEED_PRESETS: [f32; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+/// The scrub slider covers the current time rounded up to a multiple of this many seconds
+const SCRUB_STEP: f32 = 600.0;
+
+/// Controls the scene time that drives animated materials, blinking lights and decorator sway
+pub struct SceneTimePanel {
+    /// Speed to continue at when unpausing
+    speed: f32,
+    /// End of the scrub slider, only moved while the slider isn't being dragged
+    scrub_end: f32,
+}
+
+impl Default for SceneTimePanel {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            scrub_end: SCRUB_STEP,
+        }
+    }
+}
+
+impl GuiView for SceneTimePanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.scene_time {
+            return None;
+        }
+
+        let renderer = resources.get::<RendererShared>();
+        let time = renderer.time.load();
+        if !time.is_paused() {
+            self.speed = time.speed();
+        }
+
+        egui::Window::new("Scene Time")
+            .open(&mut windows.scene_time)
+            .show(ctx, |ui| {
+                let mut new_time = None;
+                ui.horizontal(|ui| {
+                    if time.is_paused() {
+                        if ui
+                            .button(format!("{ICON_PLAY} Play"))
+                            .on_hover_text("Continue at the selected speed")
+                            .clicked()
+                        {
+                            new_time = Some(time.with_speed(self.speed));
+                        }
+                    } else if ui.button(format!("{ICON_PAUSE} Pause")).clicked() {
+                        new_time = Some(time.to_fixed());
+                    }
+
+                    if ui
+                        .button(ICON_SKIP_BACKWARD.to_string())
+                        .on_hover_text("Restart from 0 seconds")
+                        .clicked()
+                    {
+                        new_time = Some(time.seek(0.0));
+                    }
+
+                    ui.label(RichText::new(format!("{:.2}s", time.elapsed())).monospace());
+                });
+
+                ui.horizontal(|ui| {
+                    let mut speed = self.speed;
+                    ui.add(
+                        egui::Slider::new(&mut speed, 0.05..=8.0)
+                            .logarithmic(true)
+                            .text("Speed")
+                            .suffix("x"),
+                    );
+                    for preset in SPEED_PRESETS {
+                        if ui
+                            .selectable_label(speed == preset, format!("{preset}x"))
+                            .clicked()
+                        {
+                            speed = preset;
+                        }
+                    }
+
+                    if speed != self.speed {
+                        self.speed = speed;
+                        if !time.is_paused() {
+                            new_time = Some(time.with_speed(speed));
+                        }
+                    }
+                });
+
+                // Scrubbing pauses the time, so the frame under the cursor stays put
+                let mut scrub = time.elapsed();
+                let response = ui.add(
+                    egui::Slider::new(&mut scrub, 0.0..=self.scrub_end)
+                        .text("Time")
+                        .suffix("s"),
+                );
+                if response.changed() {
+                    new_time = Some(Time::fixed(scrub));
+                }
+                if !response.dragged() {
+                    self.scrub_end = ((scrub / SCRUB_STEP).floor() + 1.0) * SCRUB_STEP;
+                }
+
+                ui.label(
+                    RichText::new(
+                        "Affects animated materials, blinking lights and decorator sway. The \
+                         time can also be locked with the lock_time console command",
+                    )
+                    .weak(),
+                );
+
+                if let Some(time) = new_time {
+                    renderer.time.store(time);
+                }
+            });
+
+        None
+    }
+}
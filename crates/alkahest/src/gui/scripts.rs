@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use alkahest_renderer::icons::{ICON_PLAY, ICON_REFRESH};
+use egui::{Context, RichText};
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        console,
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    },
+    resources::AppResources,
+    scripting::{self, ScriptHost, SCRIPT_DIRECTORY},
+};
+
+/// Editor for running script snippets, and a list of the scripts in the script directory
+pub struct ScriptConsolePanel {
+    code: String,
+    scripts: Option<Vec<PathBuf>>,
+}
+
+impl Default for ScriptConsolePanel {
+    fn default() -> Self {
+        Self {
+            code: "for e in query(\"Beacon\") {\n    print(`${e.label}: ${e.position}`);\n}"
+                .to_string(),
+            scripts: None,
+        }
+    }
+}
+
+impl GuiView for ScriptConsolePanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.scripts {
+            return None;
+        }
+
+        let scripts = self.scripts.get_or_insert_with(|| {
+            scripting::list_scripts().unwrap_or_else(|e| {
+                error!("Failed to list scripts: {e:?}");
+                vec![]
+            })
+        });
+
+        let mut refresh = false;
+        egui::Window::new("Scripts")
+            .open(&mut windows.scripts)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                let editor = ui.add(
+                    egui::TextEdit::multiline(&mut self.code)
+                        .code_editor()
+                        .desired_rows(8)
+                        .desired_width(f32::INFINITY),
+                );
+                let run_shortcut = editor.has_focus()
+                    && ui.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Enter));
+
+                ui.horizontal(|ui| {
+                    if ui.button(format!("{ICON_PLAY} Run")).clicked() || run_shortcut {
+                        console::queue_command("script.eval", &[&self.code]);
+                    }
+                    ui.label(RichText::new("Ctrl+Enter, output is shown in the console").weak());
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.strong(format!("{SCRIPT_DIRECTORY}/"));
+                    if ui.button(format!("{ICON_REFRESH} Reload all")).clicked() {
+                        console::queue_command("script.reload", &[]);
+                        refresh = true;
+                    }
+                });
+
+                if scripts.is_empty() {
+                    ui.label(
+                        RichText::new(format!(
+                            "No scripts found, .rhai files in {SCRIPT_DIRECTORY}/ are loaded on \
+                             startup"
+                        ))
+                        .weak(),
+                    );
+                }
+
+                for path in scripts.iter() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button(ICON_PLAY.to_string())
+                            .on_hover_text("Run")
+                            .clicked()
+                        {
+                            console::queue_command(
+                                "script.run",
+                                &[path.to_string_lossy().as_ref()],
+                            );
+                        }
+                        ui.label(
+                            path.file_name()
+                                .map(|n| n.to_string_lossy())
+                                .unwrap_or_default(),
+                        );
+                    });
+                }
+
+                let functions = resources.get::<ScriptHost>().functions();
+                if !functions.is_empty() {
+                    ui.collapsing(format!("Functions ({})", functions.len()), |ui| {
+                        for function in functions {
+                            ui.monospace(function);
+                        }
+                    });
+                }
+            });
+
+        if refresh {
+            self.scripts = None;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,150 @@
+use alkahest_renderer::{
+    ecs::map::MapAtmosphere, icons::ICON_EXPORT, loaders::texture::texture_to_dds,
+    renderer::RendererShared,
+};
+use egui::{Color32, Context, RichText};
+use glam::Vec4;
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        util::tag_hash_label,
+    },
+    maplist::MapList,
+    resources::AppResources,
+    util::{error::ErrorAlert, export::save_file_dialog},
+};
+
+/// Shows the atmosphere textures of the current map, and the global lighting values the renderer
+/// is using. Names are the raw field names, as the roles of most of them are unknown
+pub struct SkyLightingPanel;
+
+impl GuiView for SkyLightingPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.sky_lighting {
+            return None;
+        }
+
+        let renderer = resources.get::<RendererShared>();
+        let maps = resources.get::<MapList>();
+        let map = maps.current_map()?;
+        let atmosphere = map.scene.get_resource::<MapAtmosphere>();
+        // Map names aren't necessarily valid file names
+        let file_prefix = map
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        egui::Window::new("Sky & Global Lighting")
+            .open(&mut windows.sky_lighting)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(
+                        "The sky hemisphere is always the built-in placeholder, none of the \
+                         atmosphere textures have been identified as one yet",
+                    )
+                    .color(Color32::GRAY),
+                );
+
+                ui.separator();
+                ui.heading("Atmosphere Textures");
+                let Some(atmosphere) = atmosphere else {
+                    ui.label(
+                        RichText::new("The current map has no atmosphere").color(Color32::GRAY),
+                    );
+                    return;
+                };
+
+                egui::Grid::new("sky_textures_grid")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        for (name, hash, texture) in atmosphere.textures() {
+                            ui.label(name);
+                            tag_hash_label(ui, hash);
+                            ui.label(format!("{:?}", texture.format));
+                            if ui
+                                .button(format!("{ICON_EXPORT} DDS"))
+                                .on_hover_text("Export the first mip level as a DDS file")
+                                .clicked()
+                            {
+                                if let Ok(data) = texture_to_dds(hash).err_alert() {
+                                    save_file_dialog(
+                                        data,
+                                        "DirectDraw Surface",
+                                        "dds",
+                                        format!("{file_prefix}_{name}_{hash}"),
+                                    );
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.heading("Global Lighting");
+                let global_lighting = renderer.data.lock().externs.global_lighting.clone();
+                match global_lighting {
+                    Some(global_lighting) => {
+                        egui::Grid::new("sky_global_lighting_grid")
+                            .num_columns(2)
+                            .show(ui, |ui| {
+                                let vec4 = |v: Vec4| {
+                                    format!("{:.3} {:.3} {:.3} {:.3}", v.x, v.y, v.z, v.w)
+                                };
+                                for (name, value) in [
+                                    ("unk10", vec4(global_lighting.unk10)),
+                                    ("unk30", vec4(global_lighting.unk30)),
+                                    ("unk50", vec4(global_lighting.unk50)),
+                                    ("unk70", vec4(global_lighting.unk70)),
+                                    ("unk80", vec4(global_lighting.unk80)),
+                                ] {
+                                    ui.label(name);
+                                    ui.monospace(value);
+                                    ui.end_row();
+                                }
+                            });
+                    }
+                    None => {
+                        ui.label(
+                            RichText::new("Global lighting has not been rendered yet")
+                                .color(Color32::GRAY),
+                        );
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{ICON_EXPORT} Export lighting data"))
+                        .on_hover_text(
+                            "Save the raw atmosphere tag and global lighting values as text",
+                        )
+                        .clicked()
+                    {
+                        let data = format!(
+                            "{:#X?}\n\n{:#?}\n",
+                            atmosphere.data(),
+                            renderer.data.lock().externs.global_lighting
+                        );
+                        save_file_dialog(
+                            data.into_bytes(),
+                            "Text file",
+                            "txt",
+                            format!("{file_prefix}_lighting"),
+                        );
+                    }
+                });
+            });
+
+        None
+    }
+}
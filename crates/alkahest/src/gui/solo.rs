@@ -0,0 +1,215 @@
+//! Solo mode hides everything except a set of entities and their ancestors, remembering the
+//! previous visibility of every affected entity so it can be restored in one go.
+
+use alkahest_renderer::{
+    ecs::{
+        common::Label,
+        hierarchy::Children,
+        resources::SelectedEntity,
+        visibility::Visibility,
+        Scene,
+    },
+    icons::{ICON_CHEVRON_RIGHT, ICON_RESTORE, ICON_SPOTLIGHT_BEAM},
+};
+use bevy_ecs::{entity::Entity, prelude::Resource};
+use egui::{Color32, Context, RichText};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, ViewAction},
+        hotkeys::get_ancestors,
+    },
+    maplist::MapList,
+    resources::AppResources,
+};
+
+/// Per-scene solo state
+#[derive(Resource, Default)]
+pub struct SoloState {
+    entities: Vec<Entity>,
+    /// Visibility of every entity changed by solo mode, before it was changed
+    previous: FxHashMap<Entity, Option<Visibility>>,
+}
+
+impl SoloState {
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+pub fn is_soloed(scene: &Scene, entity: Entity) -> bool {
+    scene
+        .get_resource::<SoloState>()
+        .is_some_and(|s| s.contains(entity))
+}
+
+/// Adds `entity` to the solo set, hiding every entity that isn't soloed, an ancestor of a soloed entity or a descendant of one
+pub fn solo_entity(scene: &mut Scene, entity: Entity) {
+    let mut state = scene.remove_resource::<SoloState>().unwrap_or_default();
+    if !state.entities.contains(&entity) {
+        state.entities.push(entity);
+    }
+
+    let mut shown = FxHashSet::default();
+    let mut descendants = FxHashSet::default();
+    for &e in &state.entities {
+        shown.insert(e);
+        shown.extend(get_ancestors(scene, e));
+        collect_descendants(scene, e, &mut descendants);
+    }
+
+    let entities = scene.iter_entities().map(|e| e.id()).collect_vec();
+    for e in entities {
+        if descendants.contains(&e) && !shown.contains(&e) {
+            // Descendants inherit the visibility of their soloed ancestor, undo any changes made by an earlier solo
+            if let Some(previous) = state.previous.remove(&e) {
+                set_visibility(scene, e, previous);
+            }
+            continue;
+        }
+
+        let target = if shown.contains(&e) {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+
+        let current = scene.get::<Visibility>(e).copied();
+        if current != Some(target) {
+            state.previous.entry(e).or_insert(current);
+            scene.entity_mut(e).insert((target,));
+        }
+    }
+
+    scene.insert_resource(state);
+}
+
+/// Restores the visibility of all entities changed by solo mode. Returns false if solo mode wasn't active
+pub fn restore_solo(scene: &mut Scene) -> bool {
+    let Some(state) = scene.remove_resource::<SoloState>() else {
+        return false;
+    };
+
+    for (e, previous) in state.previous {
+        if scene.get_entity(e).is_some() {
+            set_visibility(scene, e, previous);
+        }
+    }
+
+    true
+}
+
+/// Solos the entity, or restores the scene if it was already soloed
+pub fn toggle_solo(scene: &mut Scene, entity: Entity) {
+    if is_soloed(scene, entity) {
+        restore_solo(scene);
+    } else {
+        solo_entity(scene, entity);
+    }
+}
+
+fn set_visibility(scene: &mut Scene, entity: Entity, visibility: Option<Visibility>) {
+    if let Some(visibility) = visibility {
+        scene.entity_mut(entity).insert((visibility,));
+    } else {
+        scene.entity_mut(entity).remove::<Visibility>();
+    }
+}
+
+fn collect_descendants(scene: &Scene, entity: Entity, out: &mut FxHashSet<Entity>) {
+    if let Some(children) = scene.get::<Children>(entity) {
+        for &child in &children.0 {
+            if out.insert(child) {
+                collect_descendants(scene, child, out);
+            }
+        }
+    }
+}
+
+fn entity_name(scene: &Scene, entity: Entity) -> String {
+    scene
+        .get::<Label>(entity)
+        .map(|l| l.to_string())
+        .unwrap_or_else(|| format!("Entity {entity}"))
+}
+
+/// Breadcrumb bar shown at the top of the viewport while solo mode is active
+pub struct SoloIndicatorOverlay;
+
+impl GuiView for SoloIndicatorOverlay {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut maps = resources.get_mut::<MapList>();
+        let map = maps.current_map_mut()?;
+        let soloed = map
+            .scene
+            .get_resource::<SoloState>()
+            .map(|s| s.entities().to_vec())?;
+        let first = *soloed.first()?;
+
+        let mut restore = false;
+        egui::Window::new("Solo")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 32.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("{ICON_SPOTLIGHT_BEAM} Solo"))
+                            .color(Color32::GOLD)
+                            .strong(),
+                    );
+
+                    let mut path = get_ancestors(&map.scene, first);
+                    path.reverse();
+                    path.push(first);
+                    for (i, e) in path.iter().enumerate() {
+                        if i != 0 {
+                            ui.label(RichText::new(ICON_CHEVRON_RIGHT.to_string()).weak());
+                        }
+
+                        if ui
+                            .selectable_label(*e == first, entity_name(&map.scene, *e))
+                            .clicked()
+                        {
+                            resources.get_mut::<SelectedEntity>().select(*e);
+                        }
+                    }
+
+                    if soloed.len() > 1 {
+                        ui.label(format!("(+{} more)", soloed.len() - 1))
+                            .on_hover_text(
+                                soloed[1..]
+                                    .iter()
+                                    .map(|e| entity_name(&map.scene, *e))
+                                    .join("\n"),
+                            );
+                    }
+
+                    ui.separator();
+                    restore = ui
+                        .button(format!("{ICON_RESTORE} Restore"))
+                        .on_hover_text("Esc")
+                        .clicked();
+                });
+            });
+
+        if restore {
+            restore_solo(&mut map.scene);
+        }
+
+        None
+    }
+}
@@ -0,0 +1,233 @@
+use alkahest_renderer::{
+    camera::Camera,
+    icons::{ICON_HISTORY, ICON_MAP, ICON_PLAY},
+    util::image::Png,
+};
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, RichText, Sense, Vec2};
+use rustc_hash::FxHashMap;
+use winit::window::Window;
+
+use crate::{
+    config,
+    gui::{
+        activity_select::{set_activity, CurrentActivity},
+        context::{GuiCtx, GuiView, ViewAction},
+    },
+    maplist::MapList,
+    recent::{thumbnail_path, RecentSession, RecentSessions},
+    resources::AppResources,
+};
+
+const THUMBNAIL_SIZE: Vec2 = Vec2::new(192.0, 108.0);
+const COLUMNS: usize = 3;
+
+/// Shown when the application is started without a map or activity, lists recently opened maps
+/// and resumes the last session
+#[derive(Default)]
+pub struct StartupScreen {
+    /// Thumbnails are loaded on first use, `None` if the map doesn't have one
+    thumbnails: FxHashMap<TagHash, Option<egui::TextureHandle>>,
+}
+
+impl StartupScreen {
+    fn thumbnail(&mut self, ctx: &Context, map: TagHash) -> Option<egui::TextureHandle> {
+        self.thumbnails
+            .entry(map)
+            .or_insert_with(|| {
+                let data = std::fs::read(thumbnail_path(map)).ok()?;
+                let image = Png::from_bytes(&data)
+                    .and_then(Png::into_rgba)
+                    .map_err(|e| warn!("Failed to read thumbnail for map {map}: {e:?}"))
+                    .ok()?;
+                Some(ctx.load_texture(
+                    format!("thumbnail_{map}"),
+                    egui::ColorImage::from_rgba_unmultiplied(image.dimensions, &image.data),
+                    egui::TextureOptions::LINEAR,
+                ))
+            })
+            .clone()
+    }
+}
+
+impl GuiView for StartupScreen {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        // Anything loaded in the meantime (console, scripts, activity browser) replaces this screen
+        if resources
+            .get::<MapList>()
+            .maps
+            .iter()
+            .any(|m| !m.hash.is_none())
+        {
+            return Some(ViewAction::Close);
+        }
+
+        let sessions = resources.get::<RecentSessions>().sessions.clone();
+        let mut open = true;
+        let mut resume = None;
+        egui::Window::new("Welcome")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Game installation:").strong());
+                    match config::with(|c| c.packages_directory.clone()) {
+                        Some(directory) => ui.label(directory),
+                        None => ui.label(RichText::new("None").color(Color32::YELLOW)),
+                    };
+                });
+                ui.separator();
+
+                if let Some(last) = sessions.first() {
+                    if ui
+                        .button(
+                            RichText::new(format!(
+                                "{ICON_PLAY} Resume {} ({})",
+                                last.map_name,
+                                last.map()
+                            ))
+                            .size(18.0)
+                            .strong(),
+                        )
+                        .on_hover_text("Load the last opened map and return to where you left off")
+                        .clicked()
+                    {
+                        resume = Some(last.clone());
+                    }
+                    ui.add_space(8.0);
+                }
+
+                ui.heading(format!("{ICON_HISTORY} Recent Maps"));
+                if sessions.is_empty() {
+                    ui.label(
+                        "Maps you open will show up here. Pick an activity or map from the \
+                         activity browser to get started",
+                    );
+                }
+
+                egui::Grid::new("startup_recent_maps")
+                    .spacing([8.0, 8.0])
+                    .show(ui, |ui| {
+                        for (i, session) in sessions.iter().enumerate() {
+                            if self.session_card(ui, session).clicked() {
+                                resume = Some(session.clone());
+                            }
+
+                            if i % COLUMNS == COLUMNS - 1 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let mut skip = config::with(|c| c.skip_startup_screen);
+                    if ui
+                        .checkbox(&mut skip, "Don't show this on startup")
+                        .changed()
+                    {
+                        config::with_mut(|c| c.skip_startup_screen = skip);
+                    }
+                });
+            });
+
+        if let Some(session) = resume {
+            resume_session(resources, &session);
+            return Some(ViewAction::Close);
+        }
+
+        if !open {
+            return Some(ViewAction::Close);
+        }
+
+        None
+    }
+}
+
+impl StartupScreen {
+    fn session_card(&mut self, ui: &mut egui::Ui, session: &RecentSession) -> egui::Response {
+        let thumbnail = self.thumbnail(ui.ctx(), session.map());
+        let response = ui
+            .vertical(|ui| {
+                ui.set_width(THUMBNAIL_SIZE.x);
+                let (rect, _) = ui.allocate_exact_size(THUMBNAIL_SIZE, Sense::hover());
+                match &thumbnail {
+                    Some(texture) => {
+                        ui.painter().image(
+                            texture.id(),
+                            rect,
+                            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                            Color32::WHITE,
+                        );
+                    }
+                    None => {
+                        ui.painter().rect_filled(rect, 4.0, Color32::from_gray(32));
+                        ui.painter().text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            ICON_MAP,
+                            egui::FontId::proportional(32.0),
+                            Color32::from_gray(96),
+                        );
+                    }
+                }
+
+                ui.label(RichText::new(&session.map_name).strong());
+                let opened = chrono::DateTime::from_timestamp(session.last_opened as i64, 0)
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                ui.label(RichText::new(opened).weak());
+            })
+            .response;
+
+        let response = ui.interact(response.rect, response.id, Sense::click());
+        if response.hovered() {
+            ui.painter()
+                .rect_stroke(response.rect.expand(2.0), 4.0, (1.0, Color32::WHITE));
+        }
+
+        response.on_hover_text(match session.activity() {
+            Some(activity) => format!("Map {} from activity {activity}", session.map()),
+            None => format!("Map {}", session.map()),
+        })
+    }
+}
+
+/// Loads the map of `session` (with its activity, if it was opened from one) and moves the camera
+/// to where it was left
+pub fn resume_session(resources: &AppResources, session: &RecentSession) {
+    let loaded_activity =
+        session
+            .activity()
+            .is_some_and(|activity| match set_activity(resources, activity) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Failed to load activity {activity}, loading the map by itself: {e:?}");
+                    false
+                }
+            });
+
+    let mut maps = resources.get_mut::<MapList>();
+    if loaded_activity {
+        if let Some(index) = maps.maps.iter().position(|m| m.hash == session.map()) {
+            maps.set_current_map(index);
+        }
+    } else {
+        resources.get_mut::<CurrentActivity>().0 = None;
+        maps.set_maps(resources, &[(session.map(), session.map_name.clone())]);
+    }
+
+    session.camera.apply(&mut resources.get_mut::<Camera>());
+}
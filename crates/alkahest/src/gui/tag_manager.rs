@@ -0,0 +1,316 @@
+use alkahest_renderer::{
+    ecs::{
+        common::Mutable,
+        hierarchy::{Children, Parent},
+        resources::SelectedEntity,
+        route::RouteNode,
+        tags::{entities_with_tag, remove_tag, EntityTag, Tags},
+        visibility::Visibility,
+        Scene,
+    },
+    icons::{ICON_DELETE, ICON_EXPORT, ICON_EYE, ICON_EYE_OFF, ICON_TAG_PLUS, ICON_TAG_REMOVE},
+};
+use bevy_ecs::entity::Entity;
+use egui::{Context, Key};
+use itertools::Itertools;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::IntoEnumIterator;
+use winit::window::Window;
+
+use crate::{
+    config, data_export,
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        UiExt,
+    },
+    maplist::MapList,
+    resources::AppResources,
+    util::text::alk_color_to_egui,
+};
+
+#[derive(Clone, Copy)]
+enum TagAction {
+    Show,
+    Hide,
+    Export,
+    Delete,
+    /// Removes the tag from all entities, and deletes the custom tag definition
+    Undefine,
+}
+
+/// Lists all tags and runs bulk operations on the entities that have them
+#[derive(Default)]
+pub struct TagManagerPanel {
+    new_tag: String,
+}
+
+impl GuiView for TagManagerPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        let mut action = None;
+        egui::Window::new("Tags")
+            .default_size([360., 400.])
+            .open(&mut windows.tag_manager)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.new_tag).hint_text("New tag name"),
+                    );
+                    let name = self.new_tag.trim().to_string();
+                    let valid = !name.is_empty() && !is_tag_defined(&name);
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    if ui
+                        .add_enabled(valid, egui::Button::new(format!("{ICON_TAG_PLUS} Create")))
+                        .clicked()
+                        || (valid && submitted)
+                    {
+                        config::with_mut(|c| c.custom_tags.push(name));
+                        config::persist();
+                        self.new_tag.clear();
+                    }
+                });
+                ui.separator();
+
+                let maps = resources.get::<MapList>();
+                let Some(map) = maps.current_map() else {
+                    ui.label("No map loaded");
+                    return;
+                };
+
+                // Number of entities with each tag, and how many of those can be deleted
+                let mut counts: FxHashMap<EntityTag, (usize, usize)> = FxHashMap::default();
+                for e in map.scene.iter_entities() {
+                    if let Some(tags) = e.get::<Tags>() {
+                        for tag in tags.0.iter() {
+                            let count = counts.entry(tag.clone()).or_default();
+                            count.0 += 1;
+                            if e.contains::<Mutable>() {
+                                count.1 += 1;
+                            }
+                        }
+                    }
+                }
+
+                let custom_tags = config::with(|c| c.custom_tags.clone());
+                let tags = EntityTag::iter()
+                    .chain(custom_tags.into_iter().map(EntityTag::Custom))
+                    .chain(
+                        counts
+                            .keys()
+                            .filter(|t| matches!(t, EntityTag::Custom(_)))
+                            .sorted_by_key(|t| t.to_string())
+                            .cloned(),
+                    )
+                    .unique()
+                    .collect_vec();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("tag_manager")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for tag in tags {
+                                let (count, mutable) =
+                                    counts.get(&tag).copied().unwrap_or_default();
+                                ui.chip_with_color(tag.to_string(), alk_color_to_egui(tag.color()));
+                                ui.label(format!("{count} entities"));
+                                ui.horizontal(|ui| {
+                                    ui.add_enabled_ui(count > 0, |ui| {
+                                        if ui
+                                            .button(ICON_EYE.to_string())
+                                            .on_hover_text("Show all")
+                                            .clicked()
+                                        {
+                                            action = Some((tag.clone(), TagAction::Show));
+                                        }
+                                        if ui
+                                            .button(ICON_EYE_OFF.to_string())
+                                            .on_hover_text("Hide all")
+                                            .clicked()
+                                        {
+                                            action = Some((tag.clone(), TagAction::Hide));
+                                        }
+                                        if ui
+                                            .button(ICON_EXPORT.to_string())
+                                            .on_hover_text("Export all to JSON or CSV")
+                                            .clicked()
+                                        {
+                                            action = Some((tag.clone(), TagAction::Export));
+                                        }
+                                    });
+
+                                    if ui
+                                        .add_enabled(
+                                            mutable > 0,
+                                            egui::Button::new(ICON_DELETE.to_string()),
+                                        )
+                                        .on_hover_text(format!(
+                                            "Delete {mutable} user-created entities"
+                                        ))
+                                        .on_disabled_hover_text(
+                                            "Only user-created entities can be deleted",
+                                        )
+                                        .clicked()
+                                    {
+                                        action = Some((tag.clone(), TagAction::Delete));
+                                    }
+
+                                    if matches!(tag, EntityTag::Custom(_))
+                                        && ui
+                                            .button(ICON_TAG_REMOVE.to_string())
+                                            .on_hover_text(
+                                                "Remove this tag from all entities and delete it",
+                                            )
+                                            .clicked()
+                                    {
+                                        action = Some((tag.clone(), TagAction::Undefine));
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        drop(windows);
+        if let Some((tag, action)) = action {
+            apply_tag_action(resources, &tag, action);
+        }
+
+        None
+    }
+}
+
+fn is_tag_defined(name: &str) -> bool {
+    EntityTag::iter().any(|t| t.to_string().eq_ignore_ascii_case(name))
+        || config::with(|c| c.custom_tags.iter().any(|t| t.eq_ignore_ascii_case(name)))
+}
+
+fn apply_tag_action(resources: &AppResources, tag: &EntityTag, action: TagAction) {
+    if let TagAction::Undefine = action {
+        let EntityTag::Custom(name) = tag else {
+            return;
+        };
+
+        for map in resources.get_mut::<MapList>().maps.iter_mut() {
+            for e in entities_with_tag(&map.scene, tag) {
+                remove_tag(&mut map.scene, e, tag.clone());
+            }
+        }
+        config::with_mut(|c| c.custom_tags.retain(|t| t != name));
+        config::persist();
+        return;
+    }
+
+    // Ask for the destination before borrowing the map list, the dialog blocks until it's closed
+    let export_path = if let TagAction::Export = action {
+        match native_dialog::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("CSV", &["csv"])
+            .set_filename(&format!("{tag}.json"))
+            .show_save_single_file()
+        {
+            Ok(Some(path)) => Some(path),
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to open the file dialog: {e}");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut maps = resources.get_mut::<MapList>();
+    let Some(map) = maps.current_map_mut() else {
+        return;
+    };
+
+    let entities = entities_with_tag(&map.scene, tag);
+    match action {
+        TagAction::Show | TagAction::Hide => {
+            let visibility = if let TagAction::Show = action {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+
+            let (scene, mut commands) = map.scene_and_commands();
+            // Route node visibility is managed by their route
+            for &e in entities
+                .iter()
+                .filter(|&&e| !scene.entity(e).contains::<RouteNode>())
+            {
+                commands.entity(e).insert((visibility,));
+            }
+        }
+        TagAction::Export => {
+            let path = export_path.unwrap();
+            match data_export::export_entities(&map.scene, &entities, &path) {
+                Ok(()) => info!(
+                    "Exported {} entities tagged '{tag}' to {}",
+                    entities.len(),
+                    path.display()
+                ),
+                Err(e) => error!("Failed to export entities tagged '{tag}': {e:?}"),
+            }
+        }
+        TagAction::Delete => {
+            let entities = entities
+                .into_iter()
+                .filter(|&e| map.scene.entity(e).contains::<Mutable>())
+                .collect_vec();
+            let deleted = delete_entities(&mut map.scene, &entities);
+
+            let mut selected = resources.get_mut::<SelectedEntity>();
+            if selected
+                .selected()
+                .is_some_and(|e| map.scene.get_entity(e).is_none())
+            {
+                selected.deselect();
+            }
+            info!("Deleted {deleted} entities tagged '{tag}'");
+        }
+        TagAction::Undefine => unreachable!(),
+    }
+}
+
+/// Despawns the given entities along with all of their descendants, returning the total number of
+/// entities despawned
+fn delete_entities(scene: &mut Scene, entities: &[Entity]) -> usize {
+    let mut to_delete = FxHashSet::default();
+    let mut stack = entities.to_vec();
+    while let Some(e) = stack.pop() {
+        if to_delete.insert(e) {
+            if let Some(children) = scene.get::<Children>(e) {
+                stack.extend(children.0.iter().copied());
+            }
+        }
+    }
+
+    // Detach the entities from any parents that are staying around
+    for &e in &to_delete {
+        let Some(parent) = scene.get::<Parent>(e).map(|p| p.0) else {
+            continue;
+        };
+
+        if !to_delete.contains(&parent) {
+            if let Some(mut children) = scene.get_mut::<Children>(parent) {
+                children.0.retain(|c| *c != e);
+            }
+        }
+    }
+
+    for &e in &to_delete {
+        scene.despawn(e);
+    }
+
+    to_delete.len()
+}
@@ -0,0 +1,467 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use alkahest_pm::package_manager;
+use alkahest_renderer::{
+    ecs::{
+        common::Label,
+        map::NodeMetadata,
+        render::{
+            dynamic_geometry::DynamicModelComponent,
+            static_geometry::{StaticInstances, StaticModelSingle},
+        },
+        resources::SelectedEntity,
+        Scene,
+    },
+    icons::{ICON_CLOSE, ICON_HEXADECIMAL, ICON_MAGNIFY, ICON_TARGET},
+};
+use bevy_ecs::{
+    entity::Entity,
+    query::{Changed, Or},
+    system::{Query, Resource, SystemId},
+};
+use destiny_pkg::TagHash;
+use egui::{Context, RichText};
+use itertools::Itertools;
+use poll_promise::Promise;
+use rayon::prelude::*;
+use rustc_hash::FxHashSet;
+use strum::{EnumIter, IntoEnumIterator};
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hex_viewer::open_in_hex_viewer,
+        hotkeys::focus_selected,
+        util::TagHashResponseExt,
+    },
+    maplist::{Map, MapList},
+    resources::AppResources,
+    util::fnv1,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display, EnumIter)]
+enum SearchMode {
+    TagHash,
+    #[strum(to_string = "String (FNV1)")]
+    String,
+}
+
+struct TagSearchResult {
+    tag: TagHash,
+    offsets: Vec<usize>,
+}
+
+struct TagSearchJob {
+    needle: u32,
+    progress: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    promise: Promise<Vec<TagSearchResult>>,
+}
+
+pub struct TagSearchPanel {
+    input: String,
+    mode: SearchMode,
+    error: Option<String>,
+
+    job: Option<TagSearchJob>,
+    /// Needle and results of the last finished search
+    results: Option<(u32, Vec<TagSearchResult>)>,
+    /// Entities in the current map referencing the results, cleared when the results change
+    entity_matches: Option<EntityMatches>,
+}
+
+/// Entities referencing the search results, along with their labels
+struct EntityMatches {
+    map: TagHash,
+    entity_count: usize,
+    entities: Vec<(Entity, String)>,
+}
+
+/// Per-scene handle to the registered [`entity_match_changes_system`]
+#[derive(Resource)]
+struct EntityMatchChangeTracker(SystemId<(), bool>);
+
+/// Returns whether any component that [`find_matching_entities`] looks at changed since the last
+/// run
+fn entity_match_changes_system(
+    q_changed: Query<
+        (),
+        Or<(
+            Changed<NodeMetadata>,
+            Changed<Label>,
+            Changed<DynamicModelComponent>,
+            Changed<StaticInstances>,
+            Changed<StaticModelSingle>,
+        )>,
+    >,
+) -> bool {
+    !q_changed.is_empty()
+}
+
+impl Default for TagSearchPanel {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            mode: SearchMode::TagHash,
+            error: None,
+            job: None,
+            results: None,
+            entity_matches: None,
+        }
+    }
+}
+
+impl GuiView for TagSearchPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        egui::Window::new("Tag Search")
+            .default_size([480., 560.])
+            .open(&mut windows.tag_search)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("tag_search_mode")
+                        .selected_text(self.mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in SearchMode::iter() {
+                                ui.selectable_value(&mut self.mode, mode, mode.to_string());
+                            }
+                        });
+
+                    let response = ui.text_edit_singleline(&mut self.input);
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    ui.add_enabled_ui(self.job.is_none(), |ui| {
+                        if ui.button(format!("{ICON_MAGNIFY} Search")).clicked() || submitted {
+                            self.start_search();
+                        }
+                    });
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                if let Some(job) = &self.job {
+                    let progress = job.progress.load(Ordering::Relaxed);
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::ProgressBar::new(progress as f32 / job.total.max(1) as f32)
+                                .text(format!("{progress}/{} tags", job.total))
+                                .desired_width(320.0),
+                        );
+                        if ui.button(format!("{ICON_CLOSE} Cancel")).clicked() {
+                            job.cancel.store(true, Ordering::Relaxed);
+                        }
+                    });
+                    ctx.request_repaint();
+                }
+
+                self.poll_job();
+
+                ui.separator();
+                self.results_ui(ui, resources);
+            });
+
+        None
+    }
+}
+
+impl TagSearchPanel {
+    fn start_search(&mut self) {
+        let needle = match self.mode {
+            SearchMode::TagHash => match crate::parse_taghash(self.input.trim()) {
+                Ok(tag) => tag.0,
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            },
+            SearchMode::String => fnv1(self.input.as_bytes()),
+        };
+
+        if needle == u32::MAX || needle == 0 {
+            self.error = Some("Refusing to search for a null hash".to_string());
+            return;
+        }
+
+        self.error = None;
+        self.results = None;
+        self.entity_matches = None;
+
+        // Only regular tags (type 8) contain references we can scan for
+        let tags = package_manager()
+            .lookup
+            .tag32_entries_by_pkg
+            .iter()
+            .flat_map(|(pkg_id, entries)| {
+                entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| e.file_type == 8)
+                    .map(|(i, _)| TagHash::new(*pkg_id, i as u16))
+            })
+            .collect_vec();
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = tags.len();
+
+        let promise = {
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+            Promise::spawn_thread("tag_search", move || {
+                scan_tags(&tags, needle, &progress, &cancel)
+            })
+        };
+
+        self.job = Some(TagSearchJob {
+            needle,
+            progress,
+            total,
+            cancel,
+            promise,
+        });
+    }
+
+    fn poll_job(&mut self) {
+        let Some(job) = self.job.take() else {
+            return;
+        };
+
+        match job.promise.try_take() {
+            Ok(results) => {
+                info!(
+                    "Tag search for {:08X} finished with {} results{}",
+                    job.needle,
+                    results.len(),
+                    if job.cancel.load(Ordering::Relaxed) {
+                        " (cancelled)"
+                    } else {
+                        ""
+                    }
+                );
+                self.results = Some((job.needle, results));
+                self.entity_matches = None;
+            }
+            Err(promise) => {
+                self.job = Some(TagSearchJob { promise, ..job });
+            }
+        }
+    }
+
+    fn results_ui(&mut self, ui: &mut egui::Ui, resources: &AppResources) {
+        let Some((needle, results)) = &self.results else {
+            ui.label("No search results");
+            return;
+        };
+
+        let mut maps = resources.get_mut::<MapList>();
+        let matching_entities = match maps.current_map_mut() {
+            Some(map) => update_entity_matches(&mut self.entity_matches, map, *needle, results),
+            None => &[],
+        };
+
+        let mut clicked = None;
+        if !matching_entities.is_empty() {
+            ui.collapsing(
+                format!("Entities in current map ({})", matching_entities.len()),
+                |ui| {
+                    egui::ScrollArea::vertical()
+                        .id_source("tag_search_entities")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            for (entity, label) in matching_entities {
+                                if ui.button(format!("{ICON_TARGET} {label}")).clicked() {
+                                    clicked = Some(*entity);
+                                }
+                            }
+                        });
+                },
+            );
+        }
+
+        // Focusing needs the map list, which is borrowed by the matches until here
+        drop(maps);
+        if let Some(entity) = clicked {
+            resources.get_mut::<SelectedEntity>().select(entity);
+            focus_selected(resources);
+        }
+
+        ui.label(RichText::new(format!("{} referencing tags", results.len())).strong());
+        egui::ScrollArea::vertical()
+            .id_source("tag_search_results")
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for result in results {
+                    let package_name = package_manager()
+                        .package_paths
+                        .get(&result.tag.pkg_id())
+                        .map(|p| p.name.clone())
+                        .unwrap_or_default();
+
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(
+                                false,
+                                RichText::new(result.tag.to_string()).monospace(),
+                            )
+                            .tag_info(result.tag)
+                            .clicked()
+                        {
+                            ui.output_mut(|o| o.copied_text = result.tag.to_string());
+                        }
+                        if ui
+                            .button(ICON_HEXADECIMAL.to_string())
+                            .on_hover_text("Open in the hex viewer")
+                            .clicked()
+                        {
+                            open_in_hex_viewer(resources, result.tag);
+                        }
+                        ui.label(RichText::new(package_name).weak());
+                        ui.label(result.offsets.iter().map(|o| format!("0x{o:X}")).join(", "));
+                    });
+                }
+            });
+    }
+}
+
+fn scan_tags(
+    tags: &[TagHash],
+    needle: u32,
+    progress: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Vec<TagSearchResult> {
+    let needle_bytes = needle.to_le_bytes();
+    let mut results = tags
+        .par_iter()
+        .filter_map(|&tag| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            progress.fetch_add(1, Ordering::Relaxed);
+            let data = package_manager().read_tag(tag).ok()?;
+
+            // References are always 4-byte aligned
+            let offsets = data
+                .chunks_exact(4)
+                .enumerate()
+                .filter(|(_, c)| *c == needle_bytes)
+                .map(|(i, _)| i * 4)
+                .collect_vec();
+
+            (!offsets.is_empty()).then_some(TagSearchResult { tag, offsets })
+        })
+        .collect::<Vec<_>>();
+
+    results.sort_by_key(|r| r.tag.0);
+    results
+}
+
+/// Returns the entities of `map` referencing the search results, only searching the scene again
+/// when the map changed or entities referencing tags were spawned, despawned or modified
+fn update_entity_matches<'a>(
+    cache: &'a mut Option<EntityMatches>,
+    map: &mut Map,
+    needle: u32,
+    results: &[TagSearchResult],
+) -> &'a [(Entity, String)] {
+    profiling::scope!("update_entity_matches");
+    let scene = &mut map.scene;
+    let tracker = if let Some(tracker) = scene.get_resource::<EntityMatchChangeTracker>() {
+        tracker.0
+    } else {
+        let id = scene.register_system(entity_match_changes_system);
+        scene.insert_resource(EntityMatchChangeTracker(id));
+        id
+    };
+    let changed = scene.run_system(tracker).unwrap_or(true);
+    let entity_count = scene.entities().len();
+
+    let cache = match cache {
+        Some(cache) if cache.map == map.hash => {
+            if changed || cache.entity_count != entity_count {
+                cache.entities = find_matching_entities(scene, needle, results);
+                cache.entity_count = entity_count;
+            }
+            cache
+        }
+        _ => cache.insert(EntityMatches {
+            map: map.hash,
+            entity_count,
+            entities: find_matching_entities(scene, needle, results),
+        }),
+    };
+
+    &cache.entities
+}
+
+fn find_matching_entities(
+    scene: &Scene,
+    needle: u32,
+    results: &[TagSearchResult],
+) -> Vec<(Entity, String)> {
+    let mut hashes: FxHashSet<u32> = results.iter().map(|r| r.tag.0).collect();
+    hashes.insert(needle);
+
+    let mut entities = vec![];
+    for e in scene.iter_entities() {
+        let mut matched = vec![];
+        if let Some(meta) = e.get::<NodeMetadata>() {
+            if hashes.contains(&meta.entity_tag.0) {
+                matched.push(meta.entity_tag);
+            }
+            if hashes.contains(&meta.source_table.0) {
+                matched.push(meta.source_table);
+            }
+        }
+
+        if let Some(dynamic) = e.get::<DynamicModelComponent>() {
+            if hashes.contains(&dynamic.model.hash.0) {
+                matched.push(dynamic.model.hash);
+            }
+        }
+
+        if let Some(statics) = e.get::<StaticInstances>() {
+            if hashes.contains(&statics.model.hash.0) {
+                matched.push(statics.model.hash);
+            }
+        }
+
+        if let Some(single) = e.get::<StaticModelSingle>() {
+            if hashes.contains(&single.model.hash.0) {
+                matched.push(single.model.hash);
+            }
+        }
+
+        if matched.is_empty() {
+            continue;
+        }
+
+        let name = e
+            .get::<Label>()
+            .map(Label::to_string)
+            .unwrap_or_else(|| format!("Entity {}", e.id()));
+        entities.push((
+            e.id(),
+            format!(
+                "{name} ({})",
+                matched.iter().map(|h| h.to_string()).join(", ")
+            ),
+        ));
+    }
+
+    entities
+}
@@ -0,0 +1,172 @@
+use alkahest_renderer::renderer::{
+    gbuffer::InspectableKind, RendererShared, TargetChannels, TargetViewRequest,
+};
+use egui::{vec2, Context, RichText, TextureId};
+use strum::IntoEnumIterator;
+use windows::Win32::Graphics::Direct3D11::ID3D11ShaderResourceView;
+use winit::window::Window;
+
+use crate::{
+    gui::context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+    resources::AppResources,
+};
+
+const ZOOM_LEVELS: [f32; 4] = [1.0, 2.0, 4.0, 8.0];
+
+/// Shows any intermediate render target of the last frame, updated live
+pub struct RenderTargetViewerPanel {
+    request: TargetViewRequest,
+    /// Texels per screen pixel, or fit to the window if not set
+    zoom: Option<f32>,
+    /// Output of the target viewer as registered with egui, along with the view it was registered
+    /// for
+    texture: Option<(ID3D11ShaderResourceView, TextureId)>,
+}
+
+impl Default for RenderTargetViewerPanel {
+    fn default() -> Self {
+        Self {
+            request: TargetViewRequest {
+                target: "RT0 (albedo)".to_string(),
+                channels: TargetChannels::Rgb,
+                range: (0.0, 1.0),
+                layer: 0,
+            },
+            zoom: None,
+            texture: None,
+        }
+    }
+}
+
+impl RenderTargetViewerPanel {
+    fn update_texture(
+        &mut self,
+        gui: &GuiCtx<'_>,
+        output: Option<ID3D11ShaderResourceView>,
+    ) -> Option<TextureId> {
+        if self.texture.as_ref().map(|(view, _)| view) != output.as_ref() {
+            if let Some((_, id)) = self.texture.take() {
+                gui.free_texture(id);
+            }
+
+            self.texture = output.map(|view| {
+                let id = gui.register_texture(view.clone(), Some(egui::TextureFilter::Nearest));
+                (view, id)
+            });
+        }
+
+        self.texture.as_ref().map(|(_, id)| *id)
+    }
+}
+
+impl GuiView for RenderTargetViewerPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.render_targets {
+            self.update_texture(gui, None);
+            return None;
+        }
+
+        let renderer = resources.get::<RendererShared>();
+        let targets = renderer.inspectable_targets();
+        let texture = self.update_texture(gui, renderer.target_view_output());
+
+        egui::Window::new("Render Targets")
+            .default_size([640., 480.])
+            .open(&mut windows.render_targets)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("render_target_viewer_target")
+                        .selected_text(&self.request.target)
+                        .width(240.0)
+                        .show_ui(ui, |ui| {
+                            for target in &targets {
+                                ui.selectable_value(
+                                    &mut self.request.target,
+                                    target.name.clone(),
+                                    &target.name,
+                                );
+                            }
+                        });
+
+                    ui.separator();
+                    for channels in TargetChannels::iter() {
+                        ui.selectable_value(
+                            &mut self.request.channels,
+                            channels,
+                            channels.to_string(),
+                        );
+                    }
+                });
+
+                let source = targets.iter().find(|t| t.name == self.request.target);
+                ui.horizontal(|ui| {
+                    ui.label("Range");
+                    ui.add(egui::DragValue::new(&mut self.request.range.0).speed(0.005));
+                    ui.add(egui::DragValue::new(&mut self.request.range.1).speed(0.005));
+                    if ui.button("Reset").clicked() {
+                        self.request.range = (0.0, 1.0);
+                    }
+
+                    if let Some(InspectableKind::DepthArray { layers }) = source.map(|s| s.kind) {
+                        self.request.layer = self.request.layer.min(layers.saturating_sub(1));
+                        ui.separator();
+                        ui.add(
+                            egui::Slider::new(
+                                &mut self.request.layer,
+                                0..=layers.saturating_sub(1),
+                            )
+                            .text("Layer"),
+                        );
+                    }
+
+                    ui.separator();
+                    ui.selectable_value(&mut self.zoom, None, "Fit");
+                    for zoom in ZOOM_LEVELS {
+                        ui.selectable_value(&mut self.zoom, Some(zoom), format!("{zoom}x"));
+                    }
+                });
+
+                let Some(source) = source else {
+                    ui.label("The selected target isn't available");
+                    return;
+                };
+                ui.label(
+                    RichText::new(format!(
+                        "{}x{} {:?}",
+                        source.size.0, source.size.1, source.format
+                    ))
+                    .weak(),
+                );
+                ui.separator();
+
+                let Some(texture) = texture else {
+                    return;
+                };
+
+                let size = vec2(source.size.0 as f32, source.size.1 as f32);
+                match self.zoom {
+                    None => {
+                        let available = ui.available_size();
+                        let scale = (available.x / size.x).min(available.y / size.y);
+                        ui.image((texture, size * scale.max(0.0)));
+                    }
+                    Some(zoom) => {
+                        egui::ScrollArea::both().show(ui, |ui| {
+                            ui.image((texture, size * zoom / ctx.pixels_per_point()));
+                        });
+                    }
+                }
+            });
+
+        renderer.request_target_view(self.request.clone());
+
+        None
+    }
+}
@@ -0,0 +1,258 @@
+use alkahest_renderer::{
+    ecs::{common::Label, render::entity_techniques, resources::SelectedEntity, Scene},
+    icons::{ICON_RELOAD, ICON_SELECT},
+    loaders::texture_usage::TextureUsage,
+    renderer::RendererShared,
+};
+use bevy_ecs::entity::Entity;
+use destiny_pkg::TagHash;
+use egui::{Color32, Context, RichText};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::focus_selected,
+        util::tag_hash_label,
+    },
+    maplist::MapList,
+    resources::AppResources,
+    util::text::format_bytes,
+};
+
+struct TextureRow {
+    usage: TextureUsage,
+    /// Entities drawn with one of the techniques referencing the texture
+    entities: Vec<(Entity, String)>,
+}
+
+/// Lists the textures loaded for the current map, along with the techniques and entities using
+/// them
+#[derive(Default)]
+pub struct TextureUsagePanel {
+    /// Map the rows were collected for
+    map: Option<TagHash>,
+    rows: Vec<TextureRow>,
+    filter: String,
+    unreferenced_only: bool,
+}
+
+impl TextureUsagePanel {
+    fn refresh(&mut self, resources: &AppResources) {
+        let maps = resources.get::<MapList>();
+        let Some(map) = maps.current_map() else {
+            self.map = None;
+            self.rows.clear();
+            return;
+        };
+
+        let usage = resources
+            .get::<RendererShared>()
+            .data
+            .lock()
+            .asset_manager
+            .texture_usage();
+        let technique_entities = technique_entities(&map.scene);
+
+        self.map = Some(map.hash);
+        self.rows = usage
+            .into_iter()
+            .map(|usage| {
+                let entities = usage
+                    .techniques
+                    .iter()
+                    .filter_map(|t| technique_entities.get(t))
+                    .flatten()
+                    .unique_by(|(e, _)| *e)
+                    .cloned()
+                    .collect();
+
+                TextureRow { usage, entities }
+            })
+            .collect();
+    }
+}
+
+impl GuiView for TextureUsagePanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        if !windows.texture_usage {
+            return None;
+        }
+
+        let current_map = resources.get::<MapList>().current_map().map(|m| m.hash);
+        if current_map != self.map {
+            self.refresh(resources);
+        }
+
+        let mut select = None;
+        egui::Window::new("Texture Usage")
+            .default_size([640., 480.])
+            .open(&mut windows.texture_usage)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("{ICON_RELOAD} Refresh"))
+                        .on_hover_text("Textures finish streaming in after the map is loaded")
+                        .clicked()
+                    {
+                        self.refresh(resources);
+                    }
+
+                    ui.label(format!(
+                        "{} textures, {}",
+                        self.rows.len(),
+                        format_bytes(self.rows.iter().map(|r| r.usage.size).sum())
+                    ));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Filter");
+                    ui.text_edit_singleline(&mut self.filter)
+                        .on_hover_text("Matches the texture hash and format");
+                    ui.checkbox(&mut self.unreferenced_only, "Unreferenced only")
+                        .on_hover_text("Textures that no loaded technique binds");
+                });
+                ui.separator();
+
+                let filter = self.filter.to_lowercase();
+                let rows = self
+                    .rows
+                    .iter()
+                    .filter(|r| !self.unreferenced_only || r.usage.techniques.is_empty())
+                    .filter(|r| {
+                        filter.is_empty()
+                            || format!("{:?} {:?}", r.usage.id, r.usage.format)
+                                .to_lowercase()
+                                .contains(&filter)
+                    })
+                    .collect_vec();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("texture_usage_grid")
+                        .striped(true)
+                        .num_columns(6)
+                        .show(ui, |ui| {
+                            for header in [
+                                "Texture",
+                                "Resolution",
+                                "Format",
+                                "Memory",
+                                "Techniques",
+                                "Entities",
+                            ] {
+                                ui.strong(header);
+                            }
+                            ui.end_row();
+
+                            for row in rows {
+                                texture_row_ui(ui, row, &mut select);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        if let Some(entity) = select {
+            resources.get_mut::<SelectedEntity>().select(entity);
+            focus_selected(resources);
+        }
+
+        None
+    }
+}
+
+fn texture_row_ui(ui: &mut egui::Ui, row: &TextureRow, select: &mut Option<Entity>) {
+    let usage = &row.usage;
+    match usage.id.tiger_taghash() {
+        Some(hash) => {
+            tag_hash_label(ui, hash);
+        }
+        None => {
+            ui.monospace(format!("{:?}", usage.id));
+        }
+    }
+
+    let resolution = if usage.depth > 1 {
+        format!("{}x{}x{}", usage.width, usage.height, usage.depth)
+    } else {
+        format!("{}x{}", usage.width, usage.height)
+    };
+    if usage.skipped_mips > 0 {
+        ui.label(RichText::new(resolution).color(Color32::YELLOW))
+            .on_hover_text(format!(
+                "Streamed in at reduced quality, {} mip(s) skipped",
+                usage.skipped_mips
+            ));
+    } else {
+        ui.label(resolution);
+    }
+    ui.label(format!("{:?}", usage.format));
+    ui.label(format_bytes(usage.size));
+
+    if usage.techniques.is_empty() {
+        ui.label(RichText::new("None").color(Color32::GRAY));
+    } else {
+        egui::CollapsingHeader::new(usage.techniques.len().to_string())
+            .id_source(("texture_techniques", usage.id))
+            .show(ui, |ui| {
+                for &technique in &usage.techniques {
+                    tag_hash_label(ui, technique);
+                }
+            });
+    }
+
+    if row.entities.is_empty() {
+        ui.label(RichText::new("None").color(Color32::GRAY));
+    } else {
+        egui::CollapsingHeader::new(row.entities.len().to_string())
+            .id_source(("texture_entities", usage.id))
+            .show(ui, |ui| {
+                for (entity, name) in &row.entities {
+                    if ui
+                        .selectable_label(false, format!("{ICON_SELECT} {name}"))
+                        .on_hover_text("Select and focus the entity")
+                        .clicked()
+                    {
+                        *select = Some(*entity);
+                    }
+                }
+            });
+    }
+}
+
+/// Entities drawn with each technique, along with their names
+fn technique_entities(scene: &Scene) -> FxHashMap<TagHash, Vec<(Entity, String)>> {
+    let mut entities: FxHashMap<TagHash, Vec<(Entity, String)>> = FxHashMap::default();
+    for e in scene.iter_entities() {
+        let techniques = entity_techniques(e);
+        if techniques.is_empty() {
+            continue;
+        }
+
+        let name = e
+            .get::<Label>()
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| format!("Entity {}", e.id()));
+        for technique in techniques
+            .iter()
+            .filter_map(|t| t.id().tiger_taghash())
+            .unique()
+        {
+            entities
+                .entry(technique)
+                .or_default()
+                .push((e.id(), name.clone()));
+        }
+    }
+
+    entities
+}
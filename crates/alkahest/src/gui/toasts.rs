@@ -0,0 +1,230 @@
+//! Warnings and errors logged while the app is running, shown as notifications that disappear
+//! on their own. Errors reported through
+//! [`report_background_error`](alkahest_renderer::util::background_errors::report_background_error)
+//! already get an error card, and are left out.
+
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use alkahest_renderer::resources::AppResources;
+use crossbeam::channel::{Receiver, Sender};
+use egui::{Color32, Context, RichText, Stroke};
+use lazy_static::lazy_static;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::Layer;
+use winit::window::Window;
+
+use crate::{
+    config::{self, ToastLevel},
+    gui::{
+        context::{GuiCtx, GuiView, ViewAction},
+        icons::{ICON_ALERT, ICON_ALERT_CIRCLE_OUTLINE, ICON_CLOSE},
+    },
+};
+
+/// Maximum number of events waiting to be picked up by the UI, events past this are dropped
+const MAX_PENDING_TOASTS: usize = 64;
+const MAX_VISIBLE_TOASTS: usize = 5;
+const WARN_COLOR: Color32 = Color32::from_rgb(226, 192, 80);
+const ERROR_COLOR: Color32 = Color32::from_rgb(213, 86, 86);
+/// Target of the events logged by `report_background_error`
+const BACKGROUND_ERROR_TARGET: &str = "alkahest_renderer::util::background_errors";
+
+lazy_static! {
+    static ref TOAST_EVENTS: (Sender<ToastEvent>, Receiver<ToastEvent>) =
+        crossbeam::channel::bounded(MAX_PENDING_TOASTS);
+}
+
+struct ToastEvent {
+    level: Level,
+    target: String,
+    message: String,
+}
+
+/// Tracing layer that queues warnings and errors to be shown as toasts
+pub struct ToastLayer;
+
+struct MessageVisitor {
+    message: Option<String>,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{value:?}"));
+        }
+    }
+}
+
+impl<S> Layer<S> for ToastLayer
+where
+    S: Subscriber,
+{
+    fn on_event(&self, event: &Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::WARN || metadata.target() == BACKGROUND_ERROR_TARGET {
+            return;
+        }
+
+        let mut visitor = MessageVisitor { message: None };
+        event.record(&mut visitor);
+        if let Some(message) = visitor.message {
+            TOAST_EVENTS
+                .0
+                .try_send(ToastEvent {
+                    level: *metadata.level(),
+                    target: metadata.target().to_string(),
+                    message,
+                })
+                .ok();
+        }
+    }
+}
+
+struct Toast {
+    event: ToastEvent,
+    /// Number of times the same message was logged while the toast was shown
+    count: usize,
+    /// Last time the toast was shown or hovered, it expires a while after this
+    last_active: Instant,
+}
+
+impl Toast {
+    fn lifetime(&self) -> Duration {
+        if self.event.level == Level::ERROR {
+            Duration::from_secs(10)
+        } else {
+            Duration::from_secs(6)
+        }
+    }
+
+    /// Returns true if the toast should be removed
+    fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let (icon, color) = if self.event.level == Level::ERROR {
+            (ICON_ALERT_CIRCLE_OUTLINE, ERROR_COLOR)
+        } else {
+            (ICON_ALERT, WARN_COLOR)
+        };
+
+        let mut close = false;
+        let response = egui::Frame::window(ui.style())
+            .stroke(Stroke::new(1.0, color))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(icon.to_string()).color(color));
+                    ui.label(RichText::new(&self.event.target).weak().small());
+                    if self.count > 1 {
+                        ui.label(RichText::new(format!("x{}", self.count)).strong());
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        close = ui
+                            .small_button(ICON_CLOSE.to_string())
+                            .on_hover_text("Dismiss")
+                            .clicked();
+                    });
+                });
+
+                ui.label(self.event.message.lines().next().unwrap_or_default())
+                    .on_hover_text(&self.event.message);
+            })
+            .response;
+
+        // Keep the toast around while the user is reading it
+        if response.hovered() {
+            self.last_active = Instant::now();
+        }
+
+        close || self.last_active.elapsed() > self.lifetime()
+    }
+}
+
+/// Shows logged warnings and errors in the top right corner of the viewport
+#[derive(Default)]
+pub struct ToastOverlay {
+    toasts: Vec<Toast>,
+}
+
+impl GuiView for ToastOverlay {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        _resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        let level = config::with(|c| c.console.toast_level);
+        for event in TOAST_EVENTS.1.try_iter() {
+            if !level.shows(event.level) {
+                continue;
+            }
+
+            match self
+                .toasts
+                .iter_mut()
+                .find(|t| t.event.target == event.target && t.event.message == event.message)
+            {
+                Some(toast) => {
+                    toast.count += 1;
+                    toast.last_active = Instant::now();
+                }
+                None => self.toasts.push(Toast {
+                    event,
+                    count: 1,
+                    last_active: Instant::now(),
+                }),
+            }
+        }
+
+        if self.toasts.is_empty() {
+            return None;
+        }
+
+        // Only the newest toasts are shown, the others don't expire until they are visible
+        let hidden = self.toasts.len().saturating_sub(MAX_VISIBLE_TOASTS);
+        for toast in &mut self.toasts[..hidden] {
+            toast.last_active = Instant::now();
+        }
+
+        egui::Area::new(egui::Id::new("toast_notifications"))
+            .anchor(egui::Align2::RIGHT_TOP, [-12.0, 40.0])
+            .order(egui::Order::Foreground)
+            .interactable(true)
+            .show(ctx, |ui| {
+                ui.set_max_width(380.0);
+
+                let mut closed = vec![];
+                for (i, toast) in self.toasts.iter_mut().enumerate().skip(hidden) {
+                    if toast.show(ui) {
+                        closed.push(i);
+                    }
+                }
+
+                if hidden > 0 {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(format!("{hidden} more notification(s)"))
+                                .color(Color32::WHITE),
+                        );
+                        if ui.small_button("Dismiss all").clicked() {
+                            closed = (0..self.toasts.len()).collect();
+                        }
+                    });
+                }
+
+                for i in closed.into_iter().rev() {
+                    self.toasts.remove(i);
+                }
+            });
+
+        // Toasts expire without any input, keep repainting until they are gone
+        ctx.request_repaint_after(Duration::from_millis(500));
+
+        None
+    }
+}
@@ -1,4 +1,13 @@
-use egui::{vec2, Align2, Color32, FontId, Pos2, Rect};
+use alkahest_renderer::{
+    ecs::{map::NodeMetadata, transform::Transform},
+    icons::ICON_ALERT,
+    util::packages::TagHashExt,
+};
+use bevy_ecs::world::EntityRef;
+use destiny_pkg::TagHash;
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, RichText, Sense};
+
+use crate::util::tag_info;
 
 pub trait PainterExt {
     fn text_with_shadow(
@@ -32,6 +41,144 @@ impl PainterExt for egui::Painter {
     }
 }
 
+pub trait TagHashResponseExt {
+    /// Shows the provenance of `hash` on hover, and options to copy it on right click
+    fn tag_info(self, hash: TagHash) -> Self;
+}
+
+impl TagHashResponseExt for egui::Response {
+    fn tag_info(self, hash: TagHash) -> Self {
+        let response = self.on_hover_ui(|ui| tag_info_tooltip(ui, hash));
+        response.context_menu(|ui| tag_copy_menu(ui, hash));
+        response
+    }
+}
+
+/// Monospace label showing a tag hash, with its provenance on hover and copy options on right
+/// click
+pub fn tag_hash_label(ui: &mut egui::Ui, hash: TagHash) -> egui::Response {
+    ui.add(egui::Label::new(RichText::new(hash.to_string()).monospace()).sense(Sense::click()))
+        .tag_info(hash)
+}
+
+pub fn tag_info_tooltip(ui: &mut egui::Ui, hash: TagHash) {
+    if hash.is_none() {
+        ui.label("Null tag hash");
+        return;
+    }
+
+    let info = tag_info::resolve(hash);
+    egui::Grid::new("tag_info_tooltip")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.strong("Package");
+            ui.label(info.package_name.as_deref().unwrap_or("Unknown"));
+            ui.end_row();
+
+            ui.strong("Entry");
+            ui.label(format!(
+                "{} (package {:04X})",
+                info.entry_index, info.package_id
+            ));
+            ui.end_row();
+
+            ui.strong("Reference");
+            ui.label(
+                info.reference
+                    .map_or("Entry not found".to_string(), |r| format!("{r:08X}")),
+            );
+            ui.end_row();
+
+            if let Some((file_type, file_subtype)) = info.file_type {
+                ui.strong("Type");
+                ui.label(format!("{file_type}/{file_subtype}"));
+                ui.end_row();
+            }
+
+            if let Some(hash64) = info.hash64 {
+                ui.strong("64-bit hash");
+                ui.label(format!("{:016X}", hash64.0));
+                ui.end_row();
+            }
+        });
+
+    if info.redacted {
+        ui.label(
+            RichText::new(format!(
+                "{ICON_ALERT} The package is redacted, its contents can't be read"
+            ))
+            .color(Color32::YELLOW),
+        );
+    }
+    ui.label(RichText::new("Right-click to copy").weak());
+}
+
+/// Buttons copying every representation of `hash`, for use in context menus
+pub fn tag_copy_menu(ui: &mut egui::Ui, hash: TagHash) {
+    let copy_button = |ui: &mut egui::Ui, name: &str, text: String| {
+        if ui.button(format!("Copy {name} ({text})")).clicked() {
+            ui.output_mut(|o| o.copied_text = text);
+            ui.close_menu();
+        }
+    };
+
+    copy_button(ui, "hex", format!("{:08X}", hash.0));
+    copy_button(ui, "u32", hash.0.to_string());
+    match tag_info::resolve(hash).hash64 {
+        Some(hash64) => copy_button(ui, "64-bit", format!("{:016X}", hash64.0)),
+        None => {
+            ui.add_enabled(false, egui::Button::new("Copy 64-bit (none)"));
+        }
+    }
+    copy_button(ui, "package path", hash.prepend_package_name());
+}
+
+/// Buttons copying the identifiers and transform of an entity, for use in context menus
+pub fn entity_copy_menu(ui: &mut egui::Ui, e: EntityRef<'_>) {
+    let copy_button = |ui: &mut egui::Ui, name: &str, text: String| {
+        if ui
+            .button(format!("Copy {name}"))
+            .on_hover_text(&text)
+            .clicked()
+        {
+            ui.output_mut(|o| o.copied_text = text);
+            ui.close_menu();
+        }
+    };
+
+    let metadata = e.get::<NodeMetadata>();
+    match metadata.map(|m| m.entity_tag).filter(|t| t.is_some()) {
+        Some(tag) => {
+            ui.menu_button("Copy entity tag", |ui| tag_copy_menu(ui, tag));
+        }
+        None => {
+            ui.add_enabled(false, egui::Button::new("Copy entity tag (none)"));
+        }
+    }
+
+    match metadata.map(|m| m.world_id) {
+        Some(world_id) => copy_button(ui, "world ID", format!("{world_id:016X}")),
+        None => {
+            ui.add_enabled(false, egui::Button::new("Copy world ID (none)"));
+        }
+    }
+
+    if let Some(transform) = e.get::<Transform>() {
+        let t = transform.translation;
+        let json = serde_json::json!({
+            "translation": t.to_array(),
+            "rotation": transform.rotation.to_array(),
+            "scale": transform.scale.to_array(),
+        });
+        copy_button(ui, "transform as JSON", json.to_string());
+        copy_button(
+            ui,
+            "goto command",
+            format!("goto {:.3} {:.3} {:.3}", t.x, t.y, t.z),
+        );
+    }
+}
+
 #[macro_export]
 macro_rules! input_float3 {
     ($ui:expr, $label:expr, $v:expr) => {{
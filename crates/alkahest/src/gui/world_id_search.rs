@@ -0,0 +1,371 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+use alkahest_data::{
+    map::{SBubbleDefinition, SBubbleParentShallow, SMapDataTable},
+    text::StringContainerShared,
+};
+use alkahest_pm::package_manager;
+use alkahest_renderer::{
+    ecs::{map::NodeMetadata, resources::SelectedEntity},
+    icons::{ICON_CLOSE, ICON_MAGNIFY, ICON_MAP_MARKER},
+};
+use destiny_pkg::TagHash;
+use egui::{Context, RichText};
+use glam::Vec3;
+use itertools::Itertools;
+use poll_promise::Promise;
+use rayon::prelude::*;
+use tiger_parse::{PackageManagerExt, TigerReadable};
+use winit::window::Window;
+
+use crate::{
+    gui::{
+        activity_select::get_map_name,
+        context::{GuiCtx, GuiView, HiddenWindows, ViewAction},
+        hotkeys::focus_selected,
+    },
+    maplist::{MapList, MapLoadState},
+    resources::AppResources,
+};
+
+struct WorldIdMatch {
+    map: TagHash,
+    map_name: String,
+    table: TagHash,
+    entity: TagHash,
+    translation: Vec3,
+}
+
+struct WorldIdSearchJob {
+    world_id: u64,
+    progress: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    promise: Promise<Vec<WorldIdMatch>>,
+}
+
+/// Searches the data tables of every map for entities with a given world ID
+#[derive(Default)]
+pub struct WorldIdSearchPanel {
+    input: String,
+    error: Option<String>,
+
+    job: Option<WorldIdSearchJob>,
+    /// World ID and results of the last finished search
+    results: Option<(u64, Vec<WorldIdMatch>)>,
+
+    /// Entity to select once the map it's in has finished loading
+    pending_focus: Option<(TagHash, u64)>,
+}
+
+impl GuiView for WorldIdSearchPanel {
+    fn draw(
+        &mut self,
+        ctx: &Context,
+        _window: &Window,
+        resources: &AppResources,
+        _gui: &GuiCtx<'_>,
+    ) -> Option<ViewAction> {
+        self.update_pending_focus(resources);
+
+        let mut windows = resources.get_mut::<HiddenWindows>();
+        egui::Window::new("World ID Search")
+            .default_size([480., 400.])
+            .open(&mut windows.world_id_search)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("World ID:");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.input)
+                            .hint_text("0x0123456789ABCDEF or decimal"),
+                    );
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    ui.add_enabled_ui(self.job.is_none(), |ui| {
+                        if ui.button(format!("{ICON_MAGNIFY} Search")).clicked() || submitted {
+                            self.start_search(resources);
+                        }
+                    });
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+
+                if let Some(job) = &self.job {
+                    let progress = job.progress.load(Ordering::Relaxed);
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::ProgressBar::new(progress as f32 / job.total.max(1) as f32)
+                                .text(format!("{progress}/{} maps", job.total))
+                                .desired_width(320.0),
+                        );
+                        if ui.button(format!("{ICON_CLOSE} Cancel")).clicked() {
+                            job.cancel.store(true, Ordering::Relaxed);
+                        }
+                    });
+                    ctx.request_repaint();
+                }
+
+                self.poll_job();
+
+                ui.separator();
+                self.results_ui(ui, resources);
+            });
+
+        None
+    }
+}
+
+impl WorldIdSearchPanel {
+    fn start_search(&mut self, resources: &AppResources) {
+        let world_id = match parse_world_id(&self.input) {
+            Some(id) if id != u64::MAX => id,
+            _ => {
+                self.error = Some(format!("Invalid world ID '{}'", self.input.trim()));
+                return;
+            }
+        };
+
+        self.error = None;
+        self.results = None;
+
+        let maps = package_manager()
+            .get_all_by_reference(SBubbleParentShallow::ID.unwrap())
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .collect_vec();
+
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = maps.len();
+        let stringmap = resources.get::<StringContainerShared>().clone();
+
+        let promise = {
+            let progress = progress.clone();
+            let cancel = cancel.clone();
+            Promise::spawn_thread("world_id_search", move || {
+                scan_maps(&maps, world_id, &stringmap, &progress, &cancel)
+            })
+        };
+
+        self.job = Some(WorldIdSearchJob {
+            world_id,
+            progress,
+            total,
+            cancel,
+            promise,
+        });
+    }
+
+    fn poll_job(&mut self) {
+        let Some(job) = self.job.take() else {
+            return;
+        };
+
+        match job.promise.try_take() {
+            Ok(results) => {
+                info!(
+                    "World ID search for {:016X} finished with {} results{}",
+                    job.world_id,
+                    results.len(),
+                    if job.cancel.load(Ordering::Relaxed) {
+                        " (cancelled)"
+                    } else {
+                        ""
+                    }
+                );
+                self.results = Some((job.world_id, results));
+            }
+            Err(promise) => {
+                self.job = Some(WorldIdSearchJob { promise, ..job });
+            }
+        }
+    }
+
+    fn results_ui(&mut self, ui: &mut egui::Ui, resources: &AppResources) {
+        let Some((world_id, results)) = &self.results else {
+            ui.label("No search results");
+            return;
+        };
+
+        if results.is_empty() {
+            ui.label(format!("No maps contain world ID {world_id:016X}"));
+            return;
+        }
+
+        let mut load = None;
+        ui.label(
+            RichText::new(format!(
+                "Found in {} map(s)",
+                results.iter().map(|r| r.map).unique().count()
+            ))
+            .strong(),
+        );
+        egui::ScrollArea::vertical()
+            .id_source("world_id_search_results")
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for result in results {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(format!("{ICON_MAP_MARKER} {}", result.map_name))
+                            .on_hover_text("Load map and select entity")
+                            .clicked()
+                        {
+                            load = Some((result.map, result.map_name.clone()));
+                        }
+                        ui.label(RichText::new(result.map.to_string()).monospace().weak());
+                    })
+                    .response
+                    .on_hover_text(format!(
+                        "Entity: {}\nData table: {}\nPosition: [{:.2}, {:.2}, {:.2}]",
+                        result.entity,
+                        result.table,
+                        result.translation.x,
+                        result.translation.y,
+                        result.translation.z
+                    ));
+                }
+            });
+
+        if let Some((map_hash, map_name)) = load {
+            let world_id = *world_id;
+            let mut maplist = resources.get_mut::<MapList>();
+            let index = if let Some(index) = maplist.maps.iter().position(|m| m.hash == map_hash) {
+                index
+            } else {
+                maplist.add_map(resources, map_name, map_hash);
+                maplist.maps.len() - 1
+            };
+            maplist.set_current_map(index);
+
+            self.pending_focus = Some((map_hash, world_id));
+        }
+    }
+
+    fn update_pending_focus(&mut self, resources: &AppResources) {
+        let Some((map_hash, world_id)) = self.pending_focus else {
+            return;
+        };
+
+        let entity = {
+            let maps = resources.get::<MapList>();
+            let Some(map) = maps.current_map().filter(|m| m.hash == map_hash) else {
+                // The user switched to another map in the meantime
+                self.pending_focus = None;
+                return;
+            };
+
+            match &map.load_state {
+                MapLoadState::Loaded => {}
+                MapLoadState::Error(_) => {
+                    self.pending_focus = None;
+                    return;
+                }
+                _ => return,
+            }
+
+            self.pending_focus = None;
+            map.scene
+                .iter_entities()
+                .find(|e| {
+                    e.get::<NodeMetadata>()
+                        .is_some_and(|m| m.world_id == world_id)
+                })
+                .map(|e| e.id())
+        };
+
+        if let Some(entity) = entity {
+            resources.get_mut::<SelectedEntity>().select(entity);
+            focus_selected(resources);
+        } else {
+            warn!("Could not find an entity with world ID {world_id:016X} in map {map_hash}");
+        }
+    }
+}
+
+fn parse_world_id(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok().or_else(|| u64::from_str_radix(s, 16).ok())
+    }
+}
+
+fn scan_maps(
+    maps: &[TagHash],
+    world_id: u64,
+    stringmap: &StringContainerShared,
+    progress: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Vec<WorldIdMatch> {
+    let mut results = maps
+        .par_iter()
+        .flat_map_iter(|&map| {
+            if cancel.load(Ordering::Relaxed) {
+                return vec![];
+            }
+
+            progress.fetch_add(1, Ordering::Relaxed);
+            match scan_map(map, world_id) {
+                Ok(matches) if !matches.is_empty() => {
+                    let map_name = get_map_name(map, stringmap).unwrap_or_else(|_| map.to_string());
+                    matches
+                        .into_iter()
+                        .map(|(table, entity, translation)| WorldIdMatch {
+                            map,
+                            map_name: map_name.clone(),
+                            table,
+                            entity,
+                            translation,
+                        })
+                        .collect_vec()
+                }
+                Ok(_) => vec![],
+                Err(e) => {
+                    debug!("Skipping map {map} in world ID search: {e:?}");
+                    vec![]
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    results.sort_by(|a, b| a.map_name.cmp(&b.map_name).then(a.map.0.cmp(&b.map.0)));
+    results
+}
+
+/// Returns the data table, entity and position of every entry in the map's data tables with the given world ID
+fn scan_map(map: TagHash, world_id: u64) -> anyhow::Result<Vec<(TagHash, TagHash, Vec3)>> {
+    let bubble_parent: SBubbleParentShallow = package_manager().read_tag_struct(map)?;
+    if bubble_parent.child_map.is_none() {
+        return Ok(vec![]);
+    }
+
+    let bubble_definition: SBubbleDefinition =
+        package_manager().read_tag_struct(bubble_parent.child_map)?;
+
+    let mut matches = vec![];
+    for table_hash in bubble_definition
+        .map_resources
+        .iter()
+        .flat_map(|c| c.data_tables.iter())
+        .unique()
+    {
+        let table: SMapDataTable = package_manager().read_tag_struct(*table_hash)?;
+        for entry in table.data_entries.iter().filter(|e| e.world_id == world_id) {
+            matches.push((
+                *table_hash,
+                entry.entity_hash(),
+                entry.translation.truncate(),
+            ));
+        }
+    }
+
+    Ok(matches)
+}
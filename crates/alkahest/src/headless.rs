@@ -0,0 +1,224 @@
+//! Windowless capture mode, used for automated/bulk screenshots (`--headless-capture`)
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use alkahest_data::text::{StringContainer, StringContainerShared};
+use alkahest_renderer::{
+    camera::{Camera, Viewport},
+    ecs::{
+        resources::SelectedEntity,
+        tags::{NodeFilter, NodeFilterSet},
+    },
+    gpu::{texture::LOW_RES, GpuContext},
+    renderer::{OutputMode, Renderer, RendererShared, Time},
+};
+use anyhow::Context;
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+use glam::{Vec2, Vec3};
+use strum::IntoEnumIterator;
+
+use crate::{
+    capture_annotations::{composite_annotations, AnnotationContext, CaptureAnnotation},
+    config,
+    gui::activity_select::{get_map_name, CurrentActivity},
+    maplist::{MapList, MapLoadState},
+    presentation::CameraBookmark,
+    resources::AppResources,
+    util::text::sanitize_file_name,
+    ApplicationArgs,
+};
+
+pub fn run_headless_capture(args: ApplicationArgs) -> anyhow::Result<()> {
+    let map_hash = args
+        .map
+        .context("--headless-capture requires a map to be specified with --map")?;
+
+    let bookmarks = if let Some(path) = &args.capture_bookmarks {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bookmarks file {}", path.display()))?;
+        let bookmarks: Vec<CameraBookmark> =
+            serde_json::from_str(&data).context("Failed to parse bookmarks file")?;
+        anyhow::ensure!(!bookmarks.is_empty(), "Bookmarks file is empty");
+        bookmarks
+    } else {
+        vec![CameraBookmark {
+            name: "capture".to_string(),
+            position: args.camera_position.unwrap_or_default().to_array(),
+            orientation: args.camera_orientation.unwrap_or_default().to_array(),
+        }]
+    };
+
+    std::fs::create_dir_all(&args.capture_output).with_context(|| {
+        format!(
+            "Failed to create output directory {}",
+            args.capture_output.display()
+        )
+    })?;
+
+    let (width, height) = args.capture_resolution;
+    LOW_RES.store(args.low_res, std::sync::atomic::Ordering::Relaxed);
+
+    let gctx = Arc::new(GpuContext::create_headless().context("Failed to create GPU context")?);
+    gctx.swapchain_resolution.store((width, height));
+    let renderer = Renderer::create(gctx.clone(), (width, height), false)?;
+    let mut settings = config::with(|c| c.renderer.clone());
+    // Captures are always 8-bit SDR images
+    settings.output_mode = OutputMode::Sdr;
+    if let Some(annotations) = &args.capture_annotations {
+        settings.draw_utilities = annotations.contains(&CaptureAnnotation::Utilities);
+    }
+    renderer.set_render_settings(settings);
+    if let Some(time) = args.capture_time {
+        renderer.time.store(Time::fixed(time));
+    }
+
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    let mut resources = AppResources::default();
+    resources.insert(CurrentActivity(args.activity));
+    resources.insert(SelectedEntity::default());
+    resources.insert(renderer.clone());
+    resources.insert(Arc::new(StringContainer::load_all_global()));
+
+    let mut node_filter_set = NodeFilterSet::default();
+    config::with(|c| {
+        for nf in NodeFilter::iter() {
+            if c.visual.node_filters.contains(&nf.to_string()) {
+                node_filter_set.insert(nf);
+            }
+        }
+    });
+    resources.insert(node_filter_set);
+
+    let map_name = get_map_name(map_hash, &resources.get::<StringContainerShared>())
+        .unwrap_or_else(|_| format!("Unknown map {map_hash}"));
+
+    let frame_count = args.capture_frames.max(1);
+    let output_dir = args.capture_output.clone();
+    let annotations = args.capture_annotations.clone().unwrap_or_default();
+    resources.insert(args);
+
+    let mut maps = MapList::default();
+    maps.set_maps(&resources, &[(map_hash, map_name.clone())]);
+    resources.insert(maps);
+
+    // Wait for the map to finish loading
+    loop {
+        let mut maps = resources.get_mut::<MapList>();
+        maps.update_maps(&resources);
+        match &maps.current_map().unwrap().load_state {
+            MapLoadState::Loaded => break,
+            MapLoadState::Error(e) => anyhow::bail!("Failed to load map {map_hash}: {e}"),
+            _ => {}
+        }
+        drop(maps);
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let mut camera = Camera::new_fps(Viewport {
+        size: glam::UVec2::new(width, height),
+        origin: glam::UVec2::ZERO,
+    });
+
+    for (index, bookmark) in bookmarks.iter().enumerate() {
+        camera.set_position(Vec3::from_array(bookmark.position));
+        camera.set_orientation(Vec2::from_array(bookmark.orientation));
+        camera.update_matrices();
+
+        let mut labels = vec![];
+        for _ in 0..frame_count {
+            // Make sure every asset requested by the previous frame is available
+            renderer.wait_for_assets();
+            render_frame(&renderer, &camera, &resources);
+            // Only the labels of the frame that ends up in the capture are drawn
+            labels = renderer.immediate.drain_labels();
+        }
+
+        let mut image = renderer
+            .capture_final_image()
+            .context("Failed to read back frame")?;
+
+        if !annotations.is_empty() {
+            let (_, center_position) = renderer
+                .data
+                .lock()
+                .gbuffers
+                .depth_buffer_distance_pos_center(&camera);
+
+            composite_annotations(
+                &mut image,
+                &annotations,
+                AnnotationContext {
+                    camera: &camera,
+                    labels,
+                    center_position: center_position.is_finite().then_some(center_position),
+                    map_name: &map_name,
+                    map_hash: map_hash.to_string(),
+                },
+            )
+            .context("Failed to draw capture annotations")?;
+        }
+
+        // Bookmark names come from the config, keep them from escaping the output directory
+        let name = if bookmark.name.trim().is_empty() {
+            index.to_string()
+        } else {
+            sanitize_file_name(bookmark.name.trim())
+        };
+        let path = output_dir.join(format!("{map_hash}_{name}.png"));
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        image.write(std::io::BufWriter::new(file))?;
+
+        info!("Saved capture '{}' to {}", bookmark.name, path.display());
+    }
+
+    Ok(())
+}
+
+fn render_frame(renderer: &RendererShared, camera: &Camera, resources: &AppResources) {
+    renderer.gpu.begin_frame();
+
+    let mut maps = resources.get_mut::<MapList>();
+    let Some(map) = maps.current_map_mut() else {
+        return;
+    };
+
+    map.update(renderer);
+
+    renderer.render_world(camera, &mut map.scene, resources);
+}
+
+pub fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (w, h) = s
+        .split_once('x')
+        .ok_or_else(|| "Expected a resolution in the format WIDTHxHEIGHT".to_string())?;
+
+    let w = w.trim().parse::<u32>().map_err(|e| e.to_string())?;
+    let h = h.trim().parse::<u32>().map_err(|e| e.to_string())?;
+    if w == 0 || h == 0 {
+        return Err("Resolution must be non-zero".to_string());
+    }
+
+    Ok((w, h))
+}
+
+fn parse_floats<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<f32>, String>>()?;
+
+    values
+        .try_into()
+        .map_err(|v: Vec<f32>| format!("Expected {N} comma-separated values, got {}", v.len()))
+}
+
+pub fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    parse_floats::<3>(s).map(Vec3::from_array)
+}
+
+pub fn parse_vec2(s: &str) -> Result<Vec2, String> {
+    parse_floats::<2>(s).map(Vec2::from_array)
+}
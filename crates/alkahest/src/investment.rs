@@ -0,0 +1,265 @@
+//! Standalone manifest lookup tool: display names and icons from the Destiny 2 manifest, loaded
+//! from an offline JSON dump or from bungie.net, looked up by definition hash.
+//!
+//! This does not name entities in the outliner or inspector. Manifest hashes don't share a
+//! namespace with package tags, and no map component carries a definition hash (`ActivityGroup`
+//! is a phase name hash), so there is nothing to look up for them.
+
+use std::sync::Arc;
+
+use alkahest_renderer::util::background_errors::report_background_error;
+use anyhow::Context;
+use lazy_static::lazy_static;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::util::RwLock;
+
+const BUNGIE_NET: &str = "https://www.bungie.net";
+const ERROR_SOURCE: &str = "Manifest";
+/// Environment variable the API key is read from when it isn't entered in the settings
+const API_KEY_VARIABLE: &str = "BUNGIE_API_KEY";
+/// Definition tables that are read from bungie.net. Offline dumps can contain any table
+const REMOTE_TABLES: &[&str] = &[
+    "DestinyInventoryItemDefinition",
+    "DestinyActivityDefinition",
+    "DestinyDestinationDefinition",
+    "DestinyPlaceDefinition",
+];
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct InvestmentConfig {
+    /// Load the manifest when alkahest is started
+    pub enabled: bool,
+    /// JSON dump of the manifest, either a single definition table or the full world content
+    /// file. The manifest is downloaded from bungie.net if not set
+    pub manifest_path: Option<String>,
+    /// bungie.net API key, required to download the manifest. Not saved to the config file, falls
+    /// back to the `BUNGIE_API_KEY` environment variable
+    #[serde(skip)]
+    pub api_key: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct InvestmentName {
+    pub name: String,
+    /// Icon path on bungie.net
+    pub icon: Option<String>,
+}
+
+impl InvestmentName {
+    pub fn icon_url(&self) -> Option<String> {
+        self.icon.as_ref().map(|icon| format!("{BUNGIE_NET}{icon}"))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum InvestmentStatus {
+    #[default]
+    NotLoaded,
+    Loading,
+    Loaded(usize),
+    Failed(String),
+}
+
+/// Icons that have been or are being downloaded, `None` while the download is in progress or if
+/// it failed
+type IconCache = FxHashMap<String, Option<Arc<[u8]>>>;
+
+lazy_static! {
+    static ref NAMES: RwLock<FxHashMap<u32, InvestmentName>> = RwLock::new(FxHashMap::default());
+    static ref STATUS: RwLock<InvestmentStatus> = RwLock::new(InvestmentStatus::NotLoaded);
+    static ref ICONS: RwLock<IconCache> = RwLock::new(FxHashMap::default());
+}
+
+pub fn status() -> InvestmentStatus {
+    STATUS.read().clone()
+}
+
+/// Name of the definition with the given hash
+pub fn lookup(hash: u32) -> Option<InvestmentName> {
+    NAMES.read().get(&hash).cloned()
+}
+
+/// (Re)loads the manifest in the background
+pub fn start_loading(config: InvestmentConfig) {
+    *STATUS.write() = InvestmentStatus::Loading;
+    tokio::spawn(async move {
+        match load_names(&config).await {
+            Ok(names) => {
+                info!("Loaded {} names from the manifest", names.len());
+                *STATUS.write() = InvestmentStatus::Loaded(names.len());
+                *NAMES.write() = names;
+            }
+            Err(e) => {
+                report_background_error(
+                    ERROR_SOURCE,
+                    format!("Failed to load the manifest: {e:?}"),
+                    None,
+                );
+                *STATUS.write() = InvestmentStatus::Failed(e.to_string());
+            }
+        }
+    });
+}
+
+/// Bytes of a PNG icon, starting a download if it hasn't been downloaded yet. Other formats
+/// can't be displayed, and always return `None`
+pub fn png_icon(name: &InvestmentName) -> Option<Arc<[u8]>> {
+    let url = name.icon_url().filter(|url| url.ends_with(".png"))?;
+    if let Some(icon) = ICONS.read().get(&url) {
+        return icon.clone();
+    }
+
+    ICONS.write().insert(url.clone(), None);
+    tokio::spawn(async move {
+        let bytes = async {
+            let response = reqwest::get(&url).await?.error_for_status()?;
+            anyhow::Ok(response.bytes().await?)
+        };
+        match bytes.await {
+            Ok(bytes) => {
+                ICONS.write().insert(url, Some(bytes.to_vec().into()));
+            }
+            Err(e) => warn!("Failed to download icon {url}: {e}"),
+        }
+    });
+
+    None
+}
+
+async fn load_names(config: &InvestmentConfig) -> anyhow::Result<FxHashMap<u32, InvestmentName>> {
+    let mut names = FxHashMap::default();
+    if let Some(path) = &config.manifest_path {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+        let json: serde_json::Value =
+            serde_json::from_slice(&data).context("Failed to parse the manifest dump")?;
+        collect_names(&json, &mut names);
+    } else {
+        let api_key = Some(config.api_key.clone())
+            .filter(|k| !k.is_empty())
+            .or_else(|| std::env::var(API_KEY_VARIABLE).ok())
+            .context("An API key is required to download the manifest")?;
+
+        for table in download_tables(&api_key).await? {
+            collect_names(&table, &mut names);
+        }
+    }
+
+    Ok(names)
+}
+
+async fn download_tables(api_key: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct ManifestResponse {
+        response: Manifest,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Manifest {
+        json_world_component_content_paths: FxHashMap<String, FxHashMap<String, String>>,
+    }
+
+    let client = reqwest::Client::builder().build()?;
+    let manifest: ManifestResponse = client
+        .get(format!("{BUNGIE_NET}/Platform/Destiny2/Manifest/"))
+        .header("User-Agent", "alkahest")
+        .header("X-API-Key", api_key)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to parse the manifest")?;
+
+    let paths = manifest
+        .response
+        .json_world_component_content_paths
+        .get("en")
+        .context("The manifest has no English content")?;
+
+    let mut tables = vec![];
+    for table in REMOTE_TABLES {
+        let Some(path) = paths.get(*table) else {
+            warn!("The manifest has no {table} table");
+            continue;
+        };
+
+        info!("Downloading {table}");
+        let table = client
+            .get(format!("{BUNGIE_NET}{path}"))
+            .header("User-Agent", "alkahest")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {table}"))?;
+        tables.push(table);
+    }
+
+    Ok(tables)
+}
+
+/// Collects the names of all definitions in `json`, which is either a single table keyed by
+/// hash, or an object containing any number of tables
+fn collect_names(json: &serde_json::Value, names: &mut FxHashMap<u32, InvestmentName>) {
+    let Some(object) = json.as_object() else {
+        return;
+    };
+
+    for (key, value) in object {
+        let Ok(hash) = key.parse::<u32>() else {
+            collect_names(value, names);
+            continue;
+        };
+
+        let display = &value["displayProperties"];
+        let Some(name) = display["name"].as_str().filter(|n| !n.is_empty()) else {
+            continue;
+        };
+
+        names.insert(
+            hash,
+            InvestmentName {
+                name: name.to_string(),
+                icon: display["icon"]
+                    .as_str()
+                    .filter(|i| !i.is_empty())
+                    .map(str::to_string),
+            },
+        );
+    }
+}
+
+#[test]
+fn test_collect_names() {
+    let json = serde_json::json!({
+        "DestinyInventoryItemDefinition": {
+            "1363886209": {
+                "displayProperties": { "name": "Gjallarhorn", "icon": "/common/gjally.jpg" }
+            },
+            "12345": { "displayProperties": { "name": "" } }
+        }
+    });
+
+    let mut names = FxHashMap::default();
+    collect_names(&json, &mut names);
+    assert_eq!(names.len(), 1);
+    assert_eq!(names[&1363886209].name, "Gjallarhorn");
+    assert_eq!(
+        names[&1363886209].icon_url().as_deref(),
+        Some("https://www.bungie.net/common/gjally.jpg")
+    );
+
+    // A single table dump, keyed by hash directly
+    let json = serde_json::json!({
+        "1363886209": { "displayProperties": { "name": "Gjallarhorn" } }
+    });
+    let mut names = FxHashMap::default();
+    collect_names(&json, &mut names);
+    assert_eq!(names[&1363886209].icon, None);
+}
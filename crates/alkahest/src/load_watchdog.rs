@@ -0,0 +1,353 @@
+//! Detects map loads that take longer than the configured timeout. When a load stalls, the
+//! backtraces of every thread are captured and written to a diagnostic bundle along with the log,
+//! so hangs on malformed packages can be reported.
+
+use std::{
+    ffi::c_void,
+    fmt::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use poll_promise::Promise;
+use windows::Win32::{
+    Foundation::{CloseHandle, LocalFree, HANDLE, HLOCAL},
+    System::{
+        Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+        },
+        Threading::{
+            GetCurrentProcessId, GetCurrentThreadId, GetThreadDescription, OpenThread,
+            ResumeThread, SuspendThread, THREAD_GET_CONTEXT, THREAD_QUERY_LIMITED_INFORMATION,
+            THREAD_SUSPEND_RESUME,
+        },
+    },
+};
+
+use crate::{config, util::consts};
+
+/// Frames captured per thread, anything deeper is cut off
+const MAX_FRAMES: usize = 96;
+
+/// Tracks a single map load
+pub struct LoadWatch {
+    started: Instant,
+    /// Time added to the timeout every time the user chooses to keep waiting
+    extension: Duration,
+    /// Native id of the thread running the loader, 0 until the loader has started
+    loader_thread: Arc<AtomicU32>,
+    pub stall: Option<LoadStall>,
+}
+
+pub struct LoadStall {
+    /// How long the load had been running when it was considered stalled
+    pub elapsed: Duration,
+    /// Folder the diagnostic bundle was written to, resolves once the symbols have been resolved
+    /// and the bundle has been written
+    pub bundle: Promise<anyhow::Result<PathBuf>>,
+}
+
+impl Default for LoadWatch {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            extension: Duration::ZERO,
+            loader_thread: Arc::new(AtomicU32::new(0)),
+            stall: None,
+        }
+    }
+}
+
+impl LoadWatch {
+    /// Handle for the loader to record the thread it runs on
+    pub fn loader_thread(&self) -> Arc<AtomicU32> {
+        self.loader_thread.clone()
+    }
+
+    /// Records the calling thread as the loader thread
+    pub fn mark_loader_thread(loader_thread: &AtomicU32) {
+        loader_thread.store(unsafe { GetCurrentThreadId() }, Ordering::Relaxed);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Gives the load another full timeout before it is considered stalled again
+    pub fn keep_waiting(&mut self) {
+        self.extension = self.elapsed();
+        self.stall = None;
+    }
+
+    /// Captures diagnostics once the load exceeds the timeout. Returns true when the load has
+    /// just stalled
+    pub fn check(&mut self, map_name: &str, map_hash: TagHash) -> bool {
+        let (enabled, timeout_seconds) =
+            config::with(|c| (c.load_watchdog.enabled, c.load_watchdog.timeout_seconds));
+        let timeout = Duration::from_secs(timeout_seconds as u64) + self.extension;
+        if !enabled || self.stall.is_some() || self.elapsed() < timeout {
+            return false;
+        }
+
+        let elapsed = self.elapsed();
+        error!(
+            "Loading map {map_hash} '{map_name}' has been running for {:.0}s, it might be stuck. \
+             Capturing diagnostics",
+            elapsed.as_secs_f32()
+        );
+
+        let threads = capture_thread_stacks();
+        let loader_thread = self.loader_thread.load(Ordering::Relaxed);
+        let map_name = map_name.to_string();
+        self.stall = Some(LoadStall {
+            elapsed,
+            bundle: Promise::spawn_thread("load_diagnostics", move || {
+                let bundle =
+                    write_diagnostic_bundle(&map_name, map_hash, elapsed, loader_thread, &threads);
+                match &bundle {
+                    Ok(path) => info!("Wrote load diagnostics to {}", path.display()),
+                    Err(e) => error!("Failed to write load diagnostics: {e:?}"),
+                }
+                bundle
+            }),
+        });
+
+        true
+    }
+}
+
+/// Return addresses of a thread at the time it was captured, innermost first
+pub struct ThreadStack {
+    pub id: u32,
+    pub name: String,
+    pub frames: Vec<u64>,
+}
+
+/// Captures the stacks of every thread in the process except the calling one. Every thread is
+/// suspended while its stack is walked
+pub fn capture_thread_stacks() -> Vec<ThreadStack> {
+    let mut stacks = vec![];
+    for id in process_thread_ids() {
+        unsafe {
+            let Ok(thread) = OpenThread(
+                THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_QUERY_LIMITED_INFORMATION,
+                false,
+                id,
+            ) else {
+                continue;
+            };
+
+            let name = thread_name(thread);
+            // Nothing may be allocated while the thread is suspended, it could be holding the
+            // allocator lock
+            let mut frames = Vec::with_capacity(MAX_FRAMES);
+            if SuspendThread(thread) != u32::MAX {
+                walk_stack(thread, &mut frames);
+                ResumeThread(thread);
+            }
+
+            CloseHandle(thread).ok();
+            stacks.push(ThreadStack { id, name, frames });
+        }
+    }
+
+    stacks
+}
+
+fn process_thread_ids() -> Vec<u32> {
+    let mut ids = vec![];
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) else {
+            return ids;
+        };
+
+        let process_id = GetCurrentProcessId();
+        let current_thread = GetCurrentThreadId();
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        let mut result = Thread32First(snapshot, &mut entry);
+        while result.is_ok() {
+            if entry.th32OwnerProcessID == process_id && entry.th32ThreadID != current_thread {
+                ids.push(entry.th32ThreadID);
+            }
+            result = Thread32Next(snapshot, &mut entry);
+        }
+
+        CloseHandle(snapshot).ok();
+    }
+
+    ids
+}
+
+unsafe fn thread_name(thread: HANDLE) -> String {
+    let Ok(description) = GetThreadDescription(thread) else {
+        return String::new();
+    };
+
+    let name = description.to_string().unwrap_or_default();
+    let _ = LocalFree(HLOCAL(description.0 as *mut c_void));
+    name
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn walk_stack(thread: HANDLE, frames: &mut Vec<u64>) {
+    use windows::Win32::System::Diagnostics::Debug::{
+        GetThreadContext, RtlLookupFunctionEntry, RtlVirtualUnwind, CONTEXT, CONTEXT_FULL_AMD64,
+        UNW_FLAG_NHANDLER,
+    };
+
+    let mut context = CONTEXT {
+        ContextFlags: CONTEXT_FULL_AMD64,
+        ..Default::default()
+    };
+    if GetThreadContext(thread, &mut context).is_err() {
+        return;
+    }
+
+    while frames.len() < frames.capacity() && context.Rip != 0 {
+        let pc = context.Rip;
+        frames.push(pc);
+
+        let mut image_base = 0;
+        let function = RtlLookupFunctionEntry(pc, &mut image_base, None);
+        if function.is_null() {
+            // Leaf functions don't have unwind info, their return address is on top of the stack
+            if context.Rsp == 0 {
+                break;
+            }
+            context.Rip = *(context.Rsp as *const u64);
+            context.Rsp += 8;
+        } else {
+            let mut handler_data = std::ptr::null_mut();
+            let mut establisher_frame = 0;
+            RtlVirtualUnwind(
+                UNW_FLAG_NHANDLER,
+                image_base,
+                pc,
+                function,
+                &mut context,
+                &mut handler_data,
+                &mut establisher_frame,
+                None,
+            );
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn walk_stack(_thread: HANDLE, _frames: &mut Vec<u64>) {}
+
+/// Resolves the function name and source location of a captured frame
+fn symbolize(address: u64, is_return_address: bool) -> String {
+    // Return addresses point at the instruction after the call
+    let lookup = if is_return_address {
+        address - 1
+    } else {
+        address
+    };
+
+    let mut resolved = None;
+    backtrace::resolve(lookup as *mut c_void, |symbol| {
+        if resolved.is_some() {
+            return;
+        }
+
+        let mut line = symbol
+            .name()
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "<unknown>".to_string());
+        if let (Some(file), Some(lineno)) = (symbol.filename(), symbol.lineno()) {
+            write!(
+                line,
+                "\n                       at {}:{lineno}",
+                file.display()
+            )
+            .ok();
+        }
+        resolved = Some(line);
+    });
+
+    format!(
+        "{address:#018x}  {}",
+        resolved.unwrap_or_else(|| "<unknown>".to_string())
+    )
+}
+
+/// Writes a summary of the load, the symbolized thread stacks and the log to a new folder in
+/// `load_diagnostics/`
+fn write_diagnostic_bundle(
+    map_name: &str,
+    map_hash: TagHash,
+    elapsed: Duration,
+    loader_thread: u32,
+    threads: &[ThreadStack],
+) -> anyhow::Result<PathBuf> {
+    let now = chrono::Local::now();
+    let directory = PathBuf::from("load_diagnostics").join(format!(
+        "{}_{}",
+        now.format("%Y-%m-%d_%H-%M-%S"),
+        map_hash
+    ));
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    let mut summary = String::new();
+    writeln!(summary, "Alkahest v{}", consts::VERSION)?;
+    writeln!(summary, "Built from commit {}", consts::GIT_HASH)?;
+    writeln!(summary, "Captured at {}", now.to_rfc3339())?;
+    writeln!(summary)?;
+    writeln!(summary, "Map: {map_name} ({map_hash})")?;
+    writeln!(summary, "Loading for {:.1}s", elapsed.as_secs_f32())?;
+    if loader_thread != 0 {
+        writeln!(summary, "Loader thread: {loader_thread}")?;
+    } else {
+        writeln!(summary, "The loader never started running")?;
+    }
+    writeln!(summary, "{} threads captured", threads.len())?;
+    config::with(|c| {
+        writeln!(
+            summary,
+            "Packages directory: {}",
+            c.packages_directory.as_deref().unwrap_or("<not set>")
+        )
+    })?;
+    std::fs::write(directory.join("summary.txt"), summary)?;
+
+    // Put the loader first, that's the one most likely to be stuck
+    let mut threads = threads.iter().collect::<Vec<_>>();
+    threads.sort_by_key(|t| t.id != loader_thread);
+    let mut stacks = String::new();
+    for thread in threads {
+        write!(stacks, "Thread {}", thread.id)?;
+        if !thread.name.is_empty() {
+            write!(stacks, " '{}'", thread.name)?;
+        }
+        if thread.id == loader_thread {
+            write!(stacks, " (map loader)")?;
+        }
+        writeln!(stacks)?;
+
+        if thread.frames.is_empty() {
+            writeln!(stacks, "    <no frames captured>")?;
+        }
+        for (i, &frame) in thread.frames.iter().enumerate() {
+            writeln!(stacks, "  {i:>3}: {}", symbolize(frame, i != 0))?;
+        }
+        writeln!(stacks)?;
+    }
+    std::fs::write(directory.join("threads.txt"), stacks)?;
+
+    if let Err(e) = std::fs::copy("alkahest.log", directory.join("alkahest.log")) {
+        warn!("Failed to copy the log to the load diagnostics: {e}");
+    }
+
+    Ok(directory)
+}
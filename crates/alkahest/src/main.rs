@@ -7,12 +7,12 @@ extern crate tracing;
 
 use std::{fmt::Write, path::PathBuf, process::exit, str::FromStr, sync::Arc};
 
-use alkahest_pm::PACKAGE_MANAGER;
 use alkahest_renderer::util::image::Png;
 use anyhow::Context;
 use app::AlkahestApp;
 use clap::Parser;
-use destiny_pkg::{GameVersion, PackageManager, TagHash};
+use config::GameSeason;
+use destiny_pkg::{PackageManager, TagHash};
 use mimalloc::MiMalloc;
 use tracing::level_filters::LevelFilter;
 use tracing_log::LogTracer;
@@ -20,18 +20,35 @@ use tracing_subscriber::{layer::SubscriberExt, EnvFilter};
 use util::consts;
 use winit::event_loop::EventLoop;
 
-use crate::gui::console::ConsoleLogLayer;
+use crate::gui::{console::ConsoleLogLayer, toasts::ToastLayer};
 
 mod app;
+mod autosave;
+mod capture_annotations;
 mod config;
+mod data_export;
+mod env_probe;
+mod frame_dump;
 mod game_selector;
 mod gui;
+mod headless;
+mod investment;
+mod load_watchdog;
 mod maplist;
+mod model_export;
+mod package_check;
 mod resources {
     pub use alkahest_renderer::resources::*;
 }
 mod discord;
 mod paths;
+mod prefab;
+mod presentation;
+mod recent;
+mod safe_mode;
+mod scripting;
+mod technique_dump;
+mod thumbnails;
 mod updater;
 mod util;
 
@@ -44,6 +61,12 @@ struct ApplicationArgs {
     /// Packages directory
     package_dir: Option<String>,
 
+    /// Season the packages directory is from, for archived packages of older seasons.
+    /// Remembered together with the packages directory. Only map data entries and cubemap volumes
+    /// are read with older layouts so far, other data from older seasons may fail to load
+    #[arg(long, value_enum)]
+    game_version: Option<GameSeason>,
+
     // TODO(cohae): Reimplement
     // /// Package prefix to load maps from, ignores package argument.
     // /// For example: `throneworld`, `edz`
@@ -64,6 +87,72 @@ struct ApplicationArgs {
 
     #[arg(long)]
     fullscreen: bool,
+
+    /// Start with shadows, atmosphere, decorators, SSAO and cubemaps disabled and the smallest
+    /// window size, for when Alkahest keeps crashing on startup
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Render the map given by --map without opening a window, save screenshots and exit
+    #[arg(long)]
+    headless_capture: bool,
+
+    /// Output resolution for headless captures
+    #[arg(long, default_value = "1920x1080", value_parser = headless::parse_resolution)]
+    capture_resolution: (u32, u32),
+
+    /// Number of frames to render before each capture is taken
+    #[arg(long, default_value_t = 8)]
+    capture_frames: usize,
+
+    /// Directory to write headless captures to
+    #[arg(long, default_value = "captures")]
+    capture_output: PathBuf,
+
+    /// Fixes the game time for headless captures, for deterministic output
+    #[arg(long)]
+    capture_time: Option<f32>,
+
+    /// JSON file containing a list of camera bookmarks to capture
+    /// (`[{ "name": "...", "position": [x, y, z], "orientation": [pitch, yaw] }]`)
+    #[arg(long)]
+    capture_bookmarks: Option<PathBuf>,
+
+    /// Camera position for headless captures, as `x,y,z`. Ignored when bookmarks are given
+    #[arg(long, value_parser = headless::parse_vec3, allow_hyphen_values = true)]
+    camera_position: Option<glam::Vec3>,
+
+    /// Camera orientation for headless captures, as `pitch,yaw` in degrees
+    #[arg(long, value_parser = headless::parse_vec2, allow_hyphen_values = true)]
+    camera_orientation: Option<glam::Vec2>,
+
+    /// Comma-separated annotations to draw over headless captures at full resolution.
+    /// When given, utilities are only drawn if `utilities` is one of them
+    #[arg(long, value_enum, value_delimiter = ',')]
+    capture_annotations: Option<Vec<capture_annotations::CaptureAnnotation>>,
+
+    /// Load the map given by --map without creating any GPU resources, export its entities and exit
+    #[arg(long)]
+    data_only: bool,
+
+    /// File to write the entities of a data-only load to. Written as CSV for `.csv` files, JSON otherwise
+    #[arg(long, default_value = "map_data.json")]
+    data_output: PathBuf,
+
+    /// Only export entities matching these query predicates (eg. `"NodeMetadata=0x80AB12CD"`).
+    /// Data-only loads don't create models or lights, so only `NodeMetadata`, `Label` and `Global`
+    /// can match
+    #[arg(long)]
+    data_query: Option<String>,
+
+    /// Comma-separated technique hashes to dump (DXBC disassembly, bindings and decompiled TFX)
+    /// without opening a window
+    #[arg(long, value_delimiter = ',', value_parser = parse_taghash)]
+    dump_technique: Vec<TagHash>,
+
+    /// Directory to write technique dumps to
+    #[arg(long, default_value = "technique_dumps")]
+    dump_output: PathBuf,
 }
 
 #[tokio::main]
@@ -76,6 +165,8 @@ async fn main() -> anyhow::Result<()> {
     writeln!(&mut panic_header, "Built on {}", consts::BUILD_TIMESTAMP).unwrap();
 
     alkahest_panic_handler::install_hook(Some(panic_header));
+    alkahest_panic_handler::add_report_file(paths::config_dir().join("config.yml"));
+    alkahest_panic_handler::add_report_log("alkahest.log");
 
     consts::print_banner();
 
@@ -107,7 +198,7 @@ async fn main() -> anyhow::Result<()> {
         });
     } // only for #[cfg]
 
-    let args = ApplicationArgs::parse();
+    let mut args = ApplicationArgs::parse();
     config::with_mut(|c| {
         c.window.fullscreen = args.fullscreen;
     });
@@ -126,6 +217,7 @@ async fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(
         tracing_subscriber::registry()
             .with(ConsoleLogLayer)
+            .with(ToastLayer)
             .with(
                 tracing_subscriber::fmt::layer()
                     .without_time()
@@ -154,7 +246,31 @@ async fn main() -> anyhow::Result<()> {
 
     // extract_tfx_externs()?;
 
-    tokio::spawn(discord::discord_client_loop());
+    if !args.dump_technique.is_empty() {
+        return technique_dump::run_technique_dump(&args.dump_technique, &args.dump_output);
+    }
+
+    if args.data_only {
+        return data_export::run_data_export(args).await;
+    }
+
+    if args.headless_capture {
+        return headless::run_headless_capture(args);
+    }
+
+    let crash_streak = autosave::update_crash_streak();
+    if !args.safe_mode && crash_streak >= safe_mode::CRASH_STREAK_PROMPT {
+        warn!("Alkahest crashed {crash_streak} times in a row, offering safe mode");
+        args.safe_mode = safe_mode::prompt(crash_streak);
+    }
+
+    if args.safe_mode {
+        info!("Starting in safe mode");
+        config::with_mut(safe_mode::apply);
+    }
+
+    package_check::start_package_check(package_check::CheckDepth::Quick);
+    discord::start_discord_client();
 
     let mut app = AlkahestApp::new(event_loop, &icon, args);
 
@@ -170,6 +286,17 @@ fn initialize_package_manager(
     event_loop: &mut EventLoop<()>,
     icon: &winit::window::Icon,
 ) -> anyhow::Result<()> {
+    let season = args
+        .game_version
+        .or_else(|| {
+            // A saved season only applies to the saved packages directory
+            args.package_dir
+                .is_none()
+                .then(|| config::with(|c| c.packages_directory.as_ref().and(c.packages_season)))
+                .flatten()
+        })
+        .unwrap_or_default();
+
     let package_dir = if let Some(p) = &args.package_dir {
         if p.ends_with(".pkg") {
             warn!(
@@ -207,14 +334,56 @@ fn initialize_package_manager(
         );
     }
 
-    let pm = info_span!("Initializing package manager").in_scope(|| {
-        PackageManager::new(package_dir, GameVersion::Destiny2TheFinalShape, None).unwrap()
+    let has_packages = std::fs::read_dir(&package_dir).is_ok_and(|mut dir| {
+        dir.any(|e| {
+            e.is_ok_and(|e| {
+                e.path()
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("pkg"))
+            })
+        })
     });
+    if !has_packages {
+        config::with_mut(|c| c.packages_directory = None);
+        config::persist();
 
-    config::with_mut(|c| c.packages_directory = Some(pm.package_dir.to_string_lossy().to_string()));
+        panic!(
+            "The specified package directory does not contain any packages! ({})\nRelaunch \
+             alkahest and select the 'packages' directory of your Destiny 2 installation.",
+            package_dir.display()
+        );
+    }
+
+    let pm = info_span!("Initializing package manager").in_scope(|| {
+        PackageManager::new(&package_dir, season.game_version(), None).with_context(|| {
+            format!(
+                "Failed to read the packages in {} as {season:?}. The game files may be corrupt, \
+                 verify them through your launcher",
+                package_dir.display()
+            )
+        })
+    })?;
+    if season != GameSeason::default() {
+        warn!(
+            "Reading packages with the {season:?} layout. Only map data entries and cubemap \
+             volumes are read with older layouts so far, other data may fail to load"
+        );
+    }
+
+    config::with_mut(|c| {
+        c.packages_directory = Some(pm.package_dir.to_string_lossy().to_string());
+        c.packages_season = (season != GameSeason::default()).then_some(season);
+    });
     config::persist();
 
-    *PACKAGE_MANAGER.write() = Some(Arc::new(pm));
+    alkahest_pm::set_primary_package_source("Default", Arc::new(pm));
+    for source in config::with(|c| c.package_sources.clone()) {
+        alkahest_pm::add_package_source(
+            source.name,
+            source.directory,
+            source.season.game_version(),
+        );
+    }
 
     Ok(())
 }
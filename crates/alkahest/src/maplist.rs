@@ -1,38 +1,42 @@
-use alkahest_data::text::StringContainerShared;
+use std::{sync::Arc, time::Instant};
+
+use alkahest_data::text::{StringContainer, StringContainerShared};
 use alkahest_renderer::{
     ecs::{
         common::Global,
         hierarchy::{Children, Parent},
-        render::{
-            dynamic_geometry::update_dynamic_model_system, light::update_shadowrenderer_system,
-            static_geometry::update_static_instances_system,
-        },
         resources::SelectedEntity,
         route::Route,
-        visibility::propagate_entity_visibility_system,
+        schedule::{run_frame_schedule, PreUpdate},
         Scene, SceneInfo,
     },
-    loaders::map::load_map,
-    renderer::RendererShared,
-    util::{
-        scene::{EntityWorldMutExt, SceneExt},
-        Hocus,
+    loaders::{
+        map::load_map,
+        map_report::{LoadWarnings, MapLoadReport},
+        AssetManager,
     },
+    renderer::RendererShared,
+    util::scene::{EntityWorldMutExt, SceneExt},
 };
 use bevy_ecs::{
     entity::Entity,
     query::{With, Without},
-    schedule::{ExecutorKind, Schedule, ScheduleLabel},
     system::Commands,
     world::CommandQueue,
 };
-use destiny_pkg::TagHash;
+use destiny_pkg::{PackageManager, TagHash};
 use itertools::Itertools;
 use poll_promise::Promise;
 use smallvec::SmallVec;
 
 use crate::{
-    discord, gui::activity_select::CurrentActivity, resources::AppResources, ApplicationArgs,
+    config, discord,
+    gui::{activity_select::CurrentActivity, console},
+    load_watchdog::LoadWatch,
+    package_check,
+    resources::AppResources,
+    util::tag_info,
+    ApplicationArgs,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -48,49 +52,32 @@ pub struct Map {
     pub hash: TagHash,
     pub name: String,
     pub load_promise: Option<Box<Promise<anyhow::Result<Scene>>>>,
+    /// Loads that were given up on but are still running, their results are discarded
+    abandoned_loads: Vec<Box<Promise<anyhow::Result<Scene>>>>,
     pub load_state: MapLoadState,
 
     pub command_queue: CommandQueue,
     pub scene: Scene,
-
-    systems: Systems,
+    /// Report of the last load, final once the map's assets have finished streaming in
+    pub load_report: Option<MapLoadReport>,
+    /// Set when the last load skipped resources, until the summary of them has been dismissed
+    pub show_load_warnings: bool,
+    load_tracking: Option<LoadTracking>,
+    /// Watches the load in progress for stalls
+    pub load_watch: Option<LoadWatch>,
+    /// Index of the package source the map is loaded from
+    pub source: usize,
+    /// Set when a load finishes, until the startup commands and `on_map_loaded` hook have been
+    /// queued. Maps that are prefetched in the background only run them once they become current
+    run_load_hooks: bool,
 }
 
-#[derive(ScheduleLabel, Debug, Hash, PartialEq, Eq, Clone)]
-struct PreUpdate;
-
-// TODO: Trash, fix and move to alkahest_renderer
-struct Systems {
-    /// Schedule ran before the main update
-    pub(crate) schedule_pre: Schedule,
-    pub(crate) schedule_pre_threadsafe: Schedule,
-}
-
-impl Systems {
-    fn create(world: &mut Scene) -> Self {
-        let mut schedule_pre = Schedule::new(PreUpdate);
-
-        schedule_pre
-            .add_systems((update_static_instances_system, update_dynamic_model_system))
-            .set_executor_kind(ExecutorKind::SingleThreaded)
-            .initialize(world)
-            .unwrap();
-
-        let mut schedule_pre_threadsafe = Schedule::new(PreUpdate);
-        schedule_pre_threadsafe
-            .add_systems((
-                update_shadowrenderer_system,
-                propagate_entity_visibility_system,
-            ))
-            .set_executor_kind(ExecutorKind::MultiThreaded)
-            .initialize(world)
-            .unwrap();
-
-        Self {
-            schedule_pre,
-            schedule_pre_threadsafe,
-        }
-    }
+/// Asset manager totals at the start of a load, to attribute the difference to the map
+struct LoadTracking {
+    texture_bytes_uploaded: u64,
+    failed_assets: usize,
+    /// When the loader finished building the scene
+    scene_ready: Option<Instant>,
 }
 
 impl Map {
@@ -102,28 +89,110 @@ impl Map {
     }
 
     pub fn create(name: impl AsRef<str>, hash: TagHash, activity_hash: Option<TagHash>) -> Self {
-        let mut scene = Scene::new_with_info(activity_hash, hash);
+        let scene = Scene::new_with_info(activity_hash, hash);
 
         Self {
             hash,
             name: name.as_ref().to_string(),
             load_promise: Default::default(),
+            abandoned_loads: vec![],
             load_state: Default::default(),
 
-            systems: Systems::create(&mut scene),
             scene,
             command_queue: Default::default(),
+            load_report: None,
+            show_load_warnings: false,
+            load_tracking: None,
+            load_watch: None,
+            source: alkahest_pm::active_package_source(),
+            run_load_hooks: false,
+        }
+    }
+
+    /// Changes the package source the map is loaded from. The map is unloaded, global entities
+    /// are kept
+    pub fn set_source(&mut self, source: usize) {
+        if self.source == source {
+            return;
+        }
+
+        self.source = source;
+        if self.hash.is_none() {
+            return;
+        }
+
+        let mut previous = std::mem::take(&mut self.scene);
+        self.unload();
+        self.take_globals(&mut previous);
+        self.fixup_route_visibility();
+    }
+
+    /// Drops the scene of the map, releasing the assets that only it references. The map is
+    /// loaded again once it becomes the current map.
+    pub fn unload(&mut self) {
+        let activity_hash = self.scene.get_activity_hash();
+        self.scene = Scene::new_with_info(activity_hash, self.hash);
+        self.command_queue = Default::default();
+        self.abandon_load();
+        self.load_tracking = None;
+        self.load_watch = None;
+        self.run_load_hooks = false;
+        self.load_state = MapLoadState::Unloaded;
+    }
+
+    /// Gives up on a load that is in progress. The loader keeps running in the background until
+    /// it finishes, but its result is discarded
+    pub fn cancel_load(&mut self) {
+        let elapsed = self.load_watch.as_ref().map(|w| w.elapsed().as_secs_f32());
+        warn!("Cancelled loading map {} '{}'", self.hash, self.name);
+        self.abandon_load();
+        self.load_tracking = None;
+        self.load_watch = None;
+        self.load_state = MapLoadState::Error(format!(
+            "Loading was cancelled after {:.0}s",
+            elapsed.unwrap_or_default()
+        ));
+    }
+
+    fn abandon_load(&mut self) {
+        if let Some(promise) = self.load_promise.take() {
+            self.abandoned_loads.push(promise);
+        }
+    }
+
+    /// Whether a loader of this map is still reading from the package manager, including loads
+    /// that were cancelled
+    pub fn is_loader_running(&self) -> bool {
+        self.load_promise.is_some() || !self.abandoned_loads.is_empty()
+    }
+
+    /// Cancels the load that is in progress and starts over. The map is loaded again once it is
+    /// the current map
+    pub fn retry_load(&mut self) {
+        warn!("Retrying load of map {} '{}'", self.hash, self.name);
+        self.unload();
+    }
+
+    fn update_watchdog(&mut self) {
+        if self.load_state != MapLoadState::Loading {
+            self.load_watch = None;
+            return;
+        }
+
+        if let Some(watch) = &mut self.load_watch {
+            watch.check(&self.name, self.hash);
         }
     }
 
     pub(super) fn update_load(&mut self) {
+        self.abandoned_loads.retain(|p| p.ready().is_none());
+
         if let Some(promise) = self.load_promise.take() {
             if promise.ready().is_some() {
                 match promise.block_and_take() {
                     Ok(mut scene) => {
                         // Move all globals to a temporary scene
                         std::mem::swap(&mut self.scene, &mut scene);
-                        self.systems = Systems::create(&mut self.scene);
                         self.take_globals(&mut scene);
 
                         info!(
@@ -132,11 +201,36 @@ impl Map {
                             self.scene.entities().len()
                         );
 
+                        let mut report = self
+                            .scene
+                            .remove_resource::<MapLoadReport>()
+                            .unwrap_or_default();
+                        report.map_name = self.name.clone();
+                        report.streaming = true;
+                        self.load_report = Some(report);
+                        self.show_load_warnings = self.scene.contains_resource::<LoadWarnings>();
+                        if let Some(tracking) = &mut self.load_tracking {
+                            tracking.scene_ready = Some(Instant::now());
+                        }
+
+                        self.run_load_hooks = true;
                         self.load_state = MapLoadState::Loaded;
                     }
                     Err(e) => {
                         error!("Failed to load map {} '{}': {:?}", self.hash, self.name, e);
-                        self.load_state = MapLoadState::Error(format!("{:?}", e));
+                        let mut message = format!("{:?}", e);
+                        if let Some(hint) = package_check::load_failure_hint(self.hash) {
+                            error!("{hint}");
+                            message = format!("{hint}\n\n{message}");
+                        }
+                        self.load_report = Some(MapLoadReport {
+                            map_hash: self.hash.to_string(),
+                            map_name: self.name.clone(),
+                            failures: vec![message.clone()],
+                            ..Default::default()
+                        });
+                        self.load_tracking = None;
+                        self.load_state = MapLoadState::Error(message);
                     }
                 }
             } else {
@@ -146,13 +240,44 @@ impl Map {
         }
     }
 
-    pub fn update(&mut self) {
+    /// Completes the load report once the assets requested by the map have finished loading
+    fn update_load_report(&mut self, asset_manager: &AssetManager) {
+        let (Some(tracking), Some(report)) = (&self.load_tracking, &mut self.load_report) else {
+            return;
+        };
+        let Some(scene_ready) = tracking.scene_ready else {
+            return;
+        };
+        if !asset_manager.is_idle() {
+            return;
+        }
+
+        report.record_phase("Asset streaming", scene_ready);
+        report.texture_bytes_uploaded = asset_manager
+            .texture_bytes_uploaded()
+            .saturating_sub(tracking.texture_bytes_uploaded);
+        report.asset_failures = asset_manager
+            .failed_count()
+            .saturating_sub(tracking.failed_assets);
+        report.streaming = false;
+        info!(
+            "Map {} '{}' finished loading in {:.2}s ({} failures, {} failed assets)",
+            self.hash,
+            self.name,
+            report.total_seconds(),
+            report.failures.len(),
+            report.asset_failures
+        );
+        self.load_tracking = None;
+    }
+
+    pub fn update(&mut self, renderer: &RendererShared) {
         self.command_queue.apply(&mut self.scene);
         self.scene.clear_trackers();
         self.scene.check_change_ticks();
 
-        self.systems.schedule_pre.run(&mut self.scene);
-        self.systems.schedule_pre_threadsafe.run(&mut self.scene);
+        self.scene.insert_resource(renderer.clone());
+        run_frame_schedule(&mut self.scene, PreUpdate);
     }
 
     /// Remove global entities from the scene and store them in this one
@@ -225,8 +350,10 @@ impl Map {
     }
 
     fn fixup_route_visibility(&mut self) {
-        for (e, r) in self.scene.query::<(Entity, &Route)>().iter(&self.scene) {
-            r.fixup_visiblity(&self.scene, &mut self.commands(), e);
+        let mut routes = self.scene.query::<(Entity, &Route)>();
+        let (scene, mut commands) = self.scene_and_commands();
+        for (e, r) in routes.iter(scene) {
+            r.fixup_visiblity(scene, &mut commands, e);
         }
     }
 
@@ -244,20 +371,47 @@ impl Map {
         let activity_hash = resources.get_mut::<CurrentActivity>().0;
         let global_strings = resources.get::<StringContainerShared>().clone();
 
+        {
+            let data = renderer.data.lock();
+            self.load_tracking = Some(LoadTracking {
+                texture_bytes_uploaded: data.asset_manager.texture_bytes_uploaded(),
+                failed_assets: data.asset_manager.failed_count(),
+                scene_ready: None,
+            });
+        }
+        self.load_report = None;
+
         info!("Loading map {} '{}'", self.hash, self.name);
-        self.load_promise = Some(Box::new(Promise::spawn_async(load_map(
-            renderer,
-            self.hash,
-            activity_hash,
-            global_strings,
-            !cli_args.no_ambient,
-        ))));
+        let load_watch = LoadWatch::default();
+        let loader_thread = load_watch.loader_thread();
+        let map_hash = self.hash;
+        let load_ambient = !cli_args.no_ambient;
+        self.load_promise = Some(Box::new(Promise::spawn_async(async move {
+            LoadWatch::mark_loader_thread(&loader_thread);
+            load_map(
+                renderer,
+                map_hash,
+                activity_hash,
+                global_strings,
+                load_ambient,
+            )
+            .await
+        })));
+        self.load_watch = Some(load_watch);
 
         self.load_state = MapLoadState::Loading;
     }
 
-    pub fn commands(&self) -> Commands<'_, '_> {
-        Commands::new(&mut self.pocus().command_queue, &self.scene)
+    pub fn commands(&mut self) -> Commands<'_, '_> {
+        Commands::new(&mut self.command_queue, &self.scene)
+    }
+
+    /// Commands for the scene, along with read access to the scene itself
+    pub fn scene_and_commands(&mut self) -> (&Scene, Commands<'_, '_>) {
+        (
+            &self.scene,
+            Commands::new(&mut self.command_queue, &self.scene),
+        )
     }
 }
 
@@ -269,6 +423,15 @@ pub struct MapList {
     pub load_all_maps: bool,
 
     pub maps: Vec<Map>,
+
+    /// Package source that is being switched to, no loads are started until the switch is done
+    pending_source: Option<PendingSource>,
+}
+
+struct PendingSource {
+    index: usize,
+    /// Reading the packages of a source takes a while, so it's done on a separate thread
+    package_manager: Promise<anyhow::Result<Arc<PackageManager>>>,
 }
 
 impl MapList {
@@ -288,6 +451,11 @@ impl MapList {
     //     self.maps.get_mut(index)
     // }
 
+    /// Whether the package source of the current map is being switched to, new loads wait for it
+    pub fn is_switching_source(&self) -> bool {
+        self.pending_source.is_some()
+    }
+
     pub fn count_loading(&self) -> usize {
         self.maps
             .iter()
@@ -305,13 +473,40 @@ impl MapList {
 
 impl MapList {
     pub fn update_maps(&mut self, resources: &AppResources) {
+        self.update_package_source(resources);
+
+        let active_source = alkahest_pm::active_package_source();
+        let switching_source = self.pending_source.is_some();
         for (i, map) in self.maps.iter_mut().enumerate() {
             map.update_load();
-            if i == self.current_map && map.load_state == MapLoadState::Unloaded {
-                map.start_load(resources);
+            map.update_watchdog();
+            if i == self.current_map {
+                if map.load_state == MapLoadState::Unloaded
+                    && map.source == active_source
+                    && !switching_source
+                {
+                    map.start_load(resources);
+                } else if map.load_state == MapLoadState::Loaded && map.run_load_hooks {
+                    // Also covers prefetched maps, which finished loading before becoming current
+                    map.run_load_hooks = false;
+                    console::queue_startup_commands();
+                    console::queue_command("script.hook", &["on_map_loaded"]);
+                }
+            }
+        }
+
+        {
+            let renderer = resources.get::<RendererShared>();
+            let data = renderer.data.lock();
+            for map in self.maps.iter_mut() {
+                map.update_load_report(&data.asset_manager);
             }
         }
 
+        if switching_source {
+            return;
+        }
+
         if self.load_all_maps {
             const LOAD_MAX_PARALLEL: usize = 4;
             let mut loaded = 0;
@@ -324,11 +519,172 @@ impl MapList {
                     loaded += 1;
                 }
 
-                if map.load_state == MapLoadState::Unloaded {
+                if map.load_state == MapLoadState::Unloaded && map.source == active_source {
                     map.start_load(resources);
                     loaded += 1;
                 }
             }
+        } else {
+            self.update_prefetch(resources);
+        }
+    }
+
+    /// Loads the other maps in the background once the current map has loaded, closest to the
+    /// current map in the list first, so switching maps doesn't have to wait for a full load.
+    /// Maps are prefetched one at a time, after the assets of the previous map have finished
+    /// loading, until the loaded assets exceed the memory cap
+    fn update_prefetch(&mut self, resources: &AppResources) {
+        let (enabled, memory_cap_mb) =
+            config::with(|c| (c.prefetch.enabled, c.prefetch.memory_cap_mb));
+        if !enabled
+            || self.count_loading() != 0
+            || self.current_map().map(|m| &m.load_state) != Some(&MapLoadState::Loaded)
+        {
+            return;
+        }
+
+        {
+            let renderer = resources.get::<RendererShared>();
+            let data = renderer.data.lock();
+            if !data.asset_manager.is_idle()
+                || data.asset_manager.loaded_memory() >= memory_cap_mb as usize * 1024 * 1024
+            {
+                return;
+            }
+        }
+
+        let active_source = alkahest_pm::active_package_source();
+        if let Some(index) = prefetch_order(self.current_map, self.maps.len()).find(|&i| {
+            self.maps[i].load_state == MapLoadState::Unloaded
+                && self.maps[i].source == active_source
+        }) {
+            info!(
+                "Prefetching map {} '{}'",
+                self.maps[index].hash, self.maps[index].name
+            );
+            self.maps[index].start_load(resources);
+        }
+    }
+
+    /// Switches the global package manager to the source of the current map. Only one source can
+    /// be active at a time, so maps loaded from any other source are unloaded along with their
+    /// assets.
+    ///
+    /// The packages of the new source are read on a separate thread, and the switch waits for
+    /// every map load and asset load to finish so nothing is read from the wrong source
+    fn update_package_source(&mut self, resources: &AppResources) {
+        let active_source = alkahest_pm::active_package_source();
+
+        if let Some(pending) = &self.pending_source {
+            let Some(result) = pending.package_manager.ready() else {
+                return;
+            };
+            let index = pending.index;
+
+            if let Err(e) = result {
+                let name = alkahest_pm::package_source(index)
+                    .map(|s| s.name.clone())
+                    .unwrap_or_default();
+                error!("Failed to switch to package source '{name}': {e:?}");
+                let message = format!("Failed to read package source '{name}': {e}");
+                self.pending_source = None;
+                if let Some(map) = self.current_map_mut().filter(|m| m.source == index) {
+                    map.source = active_source;
+                    map.load_state = MapLoadState::Error(message);
+                }
+                return;
+            }
+
+            // The current map was moved back to the active source in the meantime, the package
+            // manager stays with the source for the next time it's used
+            if self.current_map().map(|m| m.source) != Some(index) {
+                self.pending_source = None;
+                return;
+            }
+
+            if self.maps.iter().any(Map::is_loader_running)
+                || !resources
+                    .get::<RendererShared>()
+                    .data
+                    .lock()
+                    .asset_manager
+                    .is_idle()
+            {
+                return;
+            }
+
+            self.pending_source = None;
+            self.activate_package_source(index, resources);
+            return;
+        }
+
+        let Some(map) = self.maps.get_mut(self.current_map) else {
+            return;
+        };
+        if map.source == active_source {
+            return;
+        }
+
+        let Some(source) = alkahest_pm::package_source(map.source) else {
+            warn!(
+                "Package source {} of map {} does not exist, using the active source",
+                map.source, map.hash
+            );
+            map.source = active_source;
+            return;
+        };
+
+        info!(
+            "Switching to package source '{}' ({})",
+            source.name,
+            source.directory.display()
+        );
+        self.pending_source = Some(PendingSource {
+            index: map.source,
+            package_manager: Promise::spawn_thread("package_source", move || {
+                source.package_manager()
+            }),
+        });
+    }
+
+    fn activate_package_source(&mut self, index: usize, resources: &AppResources) {
+        if let Err(e) = alkahest_pm::set_active_package_source(index) {
+            error!("Failed to switch to package source {index}: {e:?}");
+            return;
+        }
+
+        for map in self.maps.iter_mut() {
+            if map.source != index
+                && map.load_state != MapLoadState::Unloaded
+                && !map.hash.is_none()
+            {
+                map.unload();
+            }
+        }
+
+        // Everything cached by tag describes the packages of the previous source
+        tag_info::clear_cache();
+        package_check::clear_issues();
+        let removed = resources
+            .get::<RendererShared>()
+            .data
+            .lock()
+            .asset_manager
+            .remove_all_dead();
+        debug!("Released {removed} assets of the previous package source");
+
+        *resources.get_mut::<StringContainerShared>() =
+            Arc::new(StringContainer::load_all_global());
+    }
+
+    /// Unloads every map, the current map is loaded again on the next update. Maps that aren't
+    /// loaded from a tag (eg. the empty map) are only cleared
+    pub fn unload_all(&mut self) {
+        for map in self.maps.iter_mut() {
+            map.unload();
+            if map.hash.is_none() {
+                map.load_state = MapLoadState::Loaded;
+            }
         }
     }
 
@@ -411,3 +767,18 @@ impl MapList {
         }
     }
 }
+
+/// Indices of all maps other than `current`, ordered by their distance to it (next map first)
+fn prefetch_order(current: usize, count: usize) -> impl Iterator<Item = usize> {
+    (1..count)
+        .flat_map(move |distance| [current.checked_add(distance), current.checked_sub(distance)])
+        .flatten()
+        .filter(move |&i| i < count)
+}
+
+#[test]
+fn test_prefetch_order() {
+    assert_eq!(prefetch_order(2, 5).collect_vec(), vec![3, 1, 4, 0]);
+    assert_eq!(prefetch_order(0, 3).collect_vec(), vec![1, 2]);
+    assert_eq!(prefetch_order(0, 1).count(), 0);
+}
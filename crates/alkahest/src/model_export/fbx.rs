@@ -0,0 +1,364 @@
+//! ASCII FBX 7.4 writer, for meshes with an optional skeleton and skin weights
+
+use std::fmt::Write;
+
+use alkahest_renderer::ecs::skeleton::Skeleton;
+use glam::{EulerRot, Mat4};
+
+use super::ExportMesh;
+
+/// Writes `meshes` and `skeleton` as an ASCII FBX document. Vertices of skinned meshes are
+/// expected to be in the same (model) space as the bind pose of the skeleton
+pub fn write_fbx(name: &str, meshes: &[ExportMesh], skeleton: Option<&Skeleton>) -> String {
+    let mut w = FbxWriter::default();
+
+    let bone_ids = skeleton.map_or(vec![], |s| {
+        (0..s.bones.len()).map(|_| w.next_id()).collect()
+    });
+
+    w.line("; FBX 7.4.0 project file");
+    w.line("; Exported by Alkahest");
+    w.begin("FBXHeaderExtension:  {");
+    w.line("FBXHeaderVersion: 1003");
+    w.line("FBXVersion: 7400");
+    w.line("Creator: \"Alkahest\"");
+    w.end();
+
+    // Destiny is Z-up and measured in meters
+    w.begin("GlobalSettings:  {");
+    w.line("Version: 1000");
+    w.begin("Properties70:  {");
+    for (name, value) in [
+        ("UpAxis", 2),
+        ("UpAxisSign", 1),
+        ("FrontAxis", 1),
+        ("FrontAxisSign", -1),
+        ("CoordAxis", 0),
+        ("CoordAxisSign", 1),
+    ] {
+        w.line(&format!(
+            "P: \"{name}\", \"int\", \"Integer\", \"\",{value}"
+        ));
+    }
+    w.line("P: \"UnitScaleFactor\", \"double\", \"Number\", \"\",100");
+    w.end();
+    w.end();
+
+    let mut connections = vec![];
+    w.begin("Objects:  {");
+
+    if let Some(skeleton) = skeleton {
+        for (i, bone) in skeleton.bones.iter().enumerate() {
+            let attribute_id = w.next_id();
+            w.begin(&format!(
+                "NodeAttribute: {attribute_id}, \"NodeAttribute::{}\", \"LimbNode\" {{",
+                bone.name
+            ));
+            w.line("TypeFlags: \"Skeleton\"");
+            w.end();
+
+            w.begin(&format!(
+                "Model: {}, \"Model::{}\", \"LimbNode\" {{",
+                bone_ids[i], bone.name
+            ));
+            w.line("Version: 232");
+            w.transform_properties(skeleton.local_transform(i));
+            w.end();
+
+            connections.push((attribute_id, bone_ids[i]));
+            connections.push((bone_ids[i], bone.parent.map_or(0, |p| bone_ids[p])));
+        }
+    }
+
+    for mesh in meshes {
+        let geometry_id = w.next_id();
+        let model_id = w.next_id();
+        let geometry = &mesh.geometry;
+
+        w.begin(&format!(
+            "Geometry: {geometry_id}, \"Geometry::{}\", \"Mesh\" {{",
+            mesh.name
+        ));
+        w.array(
+            "Vertices",
+            geometry.positions.iter().flat_map(|p| p.to_array()),
+        );
+        w.array(
+            "PolygonVertexIndex",
+            geometry
+                .triangles
+                .iter()
+                .flat_map(|&[a, b, c]| [a as i64, b as i64, -(c as i64) - 1]),
+        );
+        w.line("GeometryVersion: 124");
+
+        let mut layer_elements = vec![];
+        if let Some(normals) = &geometry.normals {
+            w.begin("LayerElementNormal: 0 {");
+            w.layer_element_header("");
+            w.array("Normals", normals.iter().flat_map(|n| n.to_array()));
+            w.end();
+            layer_elements.push("LayerElementNormal");
+        }
+        if let Some(colors) = &geometry.colors {
+            w.begin("LayerElementColor: 0 {");
+            w.layer_element_header("Color");
+            w.array("Colors", colors.iter().flat_map(|c| c.to_array()));
+            w.end();
+            layer_elements.push("LayerElementColor");
+        }
+        if let Some(texcoords) = &geometry.texcoords {
+            w.begin("LayerElementUV: 0 {");
+            w.layer_element_header("UVMap");
+            // FBX texture coordinates start at the bottom left
+            w.array("UV", texcoords.iter().flat_map(|uv| [uv.x, 1.0 - uv.y]));
+            w.end();
+            layer_elements.push("LayerElementUV");
+        }
+        w.begin("Layer: 0 {");
+        w.line("Version: 100");
+        for element in layer_elements {
+            w.begin("LayerElement:  {");
+            w.line(&format!("Type: \"{element}\""));
+            w.line("TypedIndex: 0");
+            w.end();
+        }
+        w.end();
+        w.end();
+
+        w.begin(&format!(
+            "Model: {model_id}, \"Model::{}\", \"Mesh\" {{",
+            mesh.name
+        ));
+        w.line("Version: 232");
+        w.transform_properties(mesh.transform);
+        w.line("Shading: T");
+        w.line("Culling: \"CullingOff\"");
+        w.end();
+
+        connections.push((model_id, 0));
+        connections.push((geometry_id, model_id));
+
+        if let (Some(skeleton), Some(skin_weights)) = (skeleton, &geometry.skin_weights) {
+            let skin_id = w.next_id();
+            w.begin(&format!(
+                "Deformer: {skin_id}, \"Deformer::{}_skin\", \"Skin\" {{",
+                mesh.name
+            ));
+            w.line("Version: 101");
+            w.line("Link_DeformAcuracy: 50");
+            w.end();
+            connections.push((skin_id, geometry_id));
+
+            // One cluster per bone, listing the vertices it influences
+            let mut clusters = vec![(vec![], vec![]); skeleton.bones.len()];
+            for (vertex, weights) in skin_weights.iter().enumerate() {
+                for &(bone, weight) in weights {
+                    if let Some((indices, weights)) = clusters.get_mut(bone as usize) {
+                        indices.push(vertex as i64);
+                        weights.push(weight);
+                    }
+                }
+            }
+
+            for (bone, (indices, weights)) in clusters.into_iter().enumerate() {
+                if indices.is_empty() {
+                    continue;
+                }
+
+                let bind_pose = skeleton.bones[bone].object_transform;
+                let cluster_id = w.next_id();
+                w.begin(&format!(
+                    "Deformer: {cluster_id}, \"SubDeformer::{}_{}\", \"Cluster\" {{",
+                    mesh.name, skeleton.bones[bone].name
+                ));
+                w.line("Version: 100");
+                w.line("UserData: \"\", \"\"");
+                w.array("Indexes", indices);
+                w.array("Weights", weights);
+                w.array("Transform", bind_pose.inverse().to_cols_array());
+                w.array("TransformLink", bind_pose.to_cols_array());
+                w.end();
+
+                connections.push((cluster_id, skin_id));
+                connections.push((bone_ids[bone], cluster_id));
+            }
+        }
+    }
+
+    if let Some(skeleton) = skeleton {
+        let pose_id = w.next_id();
+        w.begin(&format!(
+            "Pose: {pose_id}, \"Pose::{name}_bind_pose\", \"BindPose\" {{"
+        ));
+        w.line("Type: \"BindPose\"");
+        w.line("Version: 100");
+        w.line(&format!("NbPoseNodes: {}", skeleton.bones.len()));
+        for (bone, id) in skeleton.bones.iter().zip(&bone_ids) {
+            w.begin("PoseNode:  {");
+            w.line(&format!("Node: {id}"));
+            w.array("Matrix", bone.object_transform.to_cols_array());
+            w.end();
+        }
+        w.end();
+    }
+
+    w.end();
+
+    w.begin("Connections:  {");
+    for (child, parent) in connections {
+        w.line(&format!("C: \"OO\",{child},{parent}"));
+    }
+    w.end();
+
+    w.out
+}
+
+struct FbxWriter {
+    out: String,
+    indent: usize,
+    next_id: i64,
+}
+
+impl Default for FbxWriter {
+    fn default() -> Self {
+        Self {
+            out: String::new(),
+            indent: 0,
+            // Object IDs just need to be unique, 0 is the scene root
+            next_id: 1_000_000,
+        }
+    }
+}
+
+impl FbxWriter {
+    fn next_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.out.push('\t');
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn begin(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn end(&mut self) {
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn array<T: FbxValue>(&mut self, name: &str, values: impl IntoIterator<Item = T>) {
+        let mut count = 0;
+        let mut data = String::new();
+        for v in values {
+            if count != 0 {
+                data.push(',');
+            }
+            v.write(&mut data);
+            count += 1;
+        }
+
+        self.begin(&format!("{name}: *{count} {{"));
+        self.line(&format!("a: {data}"));
+        self.end();
+    }
+
+    fn layer_element_header(&mut self, name: &str) {
+        self.line("Version: 101");
+        self.line(&format!("Name: \"{name}\""));
+        self.line("MappingInformationType: \"ByVertice\"");
+        self.line("ReferenceInformationType: \"Direct\"");
+    }
+
+    /// Writes the local translation, rotation (XYZ euler angles in degrees) and scale of a model
+    fn transform_properties(&mut self, transform: Mat4) {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let (z, y, x) = rotation.to_euler(EulerRot::ZYX);
+
+        self.begin("Properties70:  {");
+        for (name, [x, y, z]) in [
+            ("Lcl Translation", translation.to_array()),
+            ("Lcl Rotation", [x, y, z].map(f32::to_degrees)),
+            ("Lcl Scaling", scale.to_array()),
+        ] {
+            let mut values = String::new();
+            for v in [x, y, z] {
+                values.push(',');
+                v.write(&mut values);
+            }
+            self.line(&format!("P: \"{name}\", \"{name}\", \"\", \"A\"{values}"));
+        }
+        self.end();
+    }
+}
+
+trait FbxValue {
+    fn write(&self, out: &mut String);
+}
+
+impl FbxValue for f32 {
+    fn write(&self, out: &mut String) {
+        // NaN and infinity aren't valid FBX numbers
+        let v = if self.is_finite() { *self } else { 0.0 };
+        write!(out, "{v}").ok();
+    }
+}
+
+impl FbxValue for i64 {
+    fn write(&self, out: &mut String) {
+        write!(out, "{self}").ok();
+    }
+}
+
+#[test]
+fn test_write_fbx() {
+    use alkahest_renderer::ecs::{render::mesh_info::MeshGeometry, skeleton::Bone};
+    use glam::{Vec2, Vec3, Vec4};
+
+    let skeleton = Skeleton {
+        bones: vec![
+            Bone {
+                name: "root".to_string(),
+                parent: None,
+                object_transform: Mat4::IDENTITY,
+            },
+            Bone {
+                name: "child".to_string(),
+                parent: Some(0),
+                object_transform: Mat4::from_translation(Vec3::Z),
+            },
+        ],
+    };
+    let mesh = ExportMesh {
+        name: "mesh_0".to_string(),
+        transform: Mat4::IDENTITY,
+        geometry: MeshGeometry {
+            positions: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            normals: Some(vec![Vec3::Z; 3]),
+            texcoords: Some(vec![Vec2::ZERO; 3]),
+            colors: Some(vec![Vec4::ONE; 3]),
+            skin_weights: Some(vec![vec![(1, 1.0)], vec![(1, 0.5), (0, 0.5)], vec![]]),
+            triangles: vec![[0, 1, 2]],
+        },
+    };
+
+    let fbx = write_fbx("test", &[mesh], Some(&skeleton));
+    assert!(fbx.contains("Vertices: *9 {"));
+    assert!(fbx.contains("a: 0,1,-3"));
+    assert!(fbx.contains("UV: *6 {"));
+    assert!(fbx.contains("Colors: *12 {"));
+    assert_eq!(fbx.matches("\"LimbNode\" {").count(), 4);
+    assert_eq!(fbx.matches("\"Cluster\" {").count(), 2);
+    // The child bone sits 1 unit above its parent
+    assert!(fbx.contains("P: \"Lcl Translation\", \"Lcl Translation\", \"\", \"A\",0,0,1"));
+    // Every opened block is closed again
+    assert_eq!(fbx.matches('{').count(), fbx.matches('}').count());
+}
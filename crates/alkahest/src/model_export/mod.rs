@@ -0,0 +1,192 @@
+//! Exporting models to formats used by external art tools
+
+pub mod fbx;
+pub mod navmesh;
+pub mod usd;
+
+use alkahest_data::statics::SStaticMesh;
+use alkahest_pm::package_manager;
+use alkahest_renderer::ecs::{
+    map::MapStaticAO,
+    render::{
+        dynamic_geometry::DynamicModel,
+        mesh_info::{dynamic_mesh_info, static_mesh_info, MeshGeometry, MeshInfo},
+        static_geometry::{StaticInstanceGroup, StaticModel},
+    },
+    skeleton::Skeleton,
+    transform::Transform,
+};
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Mat4, Vec4};
+use tiger_parse::PackageManagerExt;
+
+use crate::util::export::save_file_dialog;
+
+pub struct ExportMesh {
+    pub name: String,
+    /// Placement of the mesh in the exported scene
+    pub transform: Mat4,
+    pub geometry: MeshGeometry,
+}
+
+/// Reads the geometry of every mesh of a model. Meshes that fail to read are skipped, unless none
+/// of them can be read
+pub fn read_export_meshes(meshes: &[MeshInfo]) -> anyhow::Result<Vec<ExportMesh>> {
+    let mut last_error = None;
+    let meshes = meshes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, mesh)| match mesh.read_geometry() {
+            Ok(geometry) => Some(ExportMesh {
+                name: format!("mesh_{i}"),
+                transform: Mat4::IDENTITY,
+                geometry,
+            }),
+            Err(e) => {
+                warn!("Failed to read geometry of mesh {i}: {e:?}");
+                last_error = Some(e);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if meshes.is_empty() {
+        return match last_error {
+            Some(e) => Err(e).context("Model has no readable meshes"),
+            None => Err(anyhow::anyhow!("Model has no meshes")),
+        };
+    }
+
+    Ok(meshes)
+}
+
+/// Exports the highest detail LOD of a dynamic model to FBX, asking the user where to save it
+pub fn export_dynamic_model_fbx(
+    model: &DynamicModel,
+    skeleton: Option<&Skeleton>,
+) -> anyhow::Result<()> {
+    let meshes = read_export_meshes(&dynamic_mesh_info(&model.model))?;
+    let name = format!("model_{}", model.hash);
+    let fbx = fbx::write_fbx(&name, &meshes, skeleton);
+    save_file_dialog(fbx.into_bytes(), "FBX", "fbx", name);
+    Ok(())
+}
+
+/// Static AO of the instances of a single instance group
+pub struct StaticInstanceAo {
+    values: Vec<f32>,
+    offset: usize,
+}
+
+impl StaticInstanceAo {
+    /// Looks up the AO of an instance group of the placement with the given identifier. The
+    /// groups of a placement are stored one after another, and so are the instances within a
+    /// group, with a value for every vertex of every mesh
+    pub fn read(
+        map_ao: &MapStaticAO,
+        identifier: u64,
+        group: Option<&StaticInstanceGroup>,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(&offset) = map_ao.offset_map.get(&identifier) else {
+            return Ok(None);
+        };
+
+        let mut offset = offset as usize;
+        if let Some(group) = group {
+            for &(hash, instance_count) in &group.groups[..group.index] {
+                offset += instance_count * static_vertex_count(hash)?;
+            }
+        }
+
+        Ok(Some(Self {
+            values: map_ao.read_ao()?,
+            offset,
+        }))
+    }
+
+    /// AO values of the vertices of an instance, starting `vertex_offset` vertices into it
+    fn instance_values(
+        &self,
+        instance: usize,
+        instance_vertex_count: usize,
+        vertex_offset: usize,
+        count: usize,
+    ) -> Option<&[f32]> {
+        let start = self.offset + instance * instance_vertex_count + vertex_offset;
+        self.values.get(start..start + count)
+    }
+}
+
+/// Exports the highest detail LOD of a static model to FBX, once for every instance. Vertex
+/// colors and the map's baked AO (if available) are combined into the exported vertex colors, so
+/// external renders match how the model looks in the viewer
+pub fn export_static_model_fbx(
+    model: &StaticModel,
+    instances: &[Transform],
+    ao: Option<&StaticInstanceAo>,
+) -> anyhow::Result<()> {
+    let meshes = read_export_meshes(&static_mesh_info(&model.model))?;
+    let instance_vertex_count = meshes
+        .iter()
+        .map(|m| m.geometry.positions.len())
+        .sum::<usize>();
+
+    let mut missing_ao = false;
+    let mut exported = vec![];
+    for (i, transform) in instances.iter().enumerate() {
+        let mut vertex_offset = 0;
+        for mesh in &meshes {
+            let vertex_count = mesh.geometry.positions.len();
+            let mut geometry = mesh.geometry.clone();
+            let colors = geometry
+                .colors
+                .get_or_insert_with(|| vec![Vec4::ONE; vertex_count]);
+
+            if let Some(ao) = ao {
+                match ao.instance_values(i, instance_vertex_count, vertex_offset, vertex_count) {
+                    Some(values) => bake_ao(colors, values),
+                    None => missing_ao = true,
+                }
+            }
+
+            exported.push(ExportMesh {
+                name: format!("instance_{i}_{}", mesh.name),
+                transform: transform.local_to_world(),
+                geometry,
+            });
+            vertex_offset += vertex_count;
+        }
+    }
+
+    if missing_ao {
+        warn!(
+            "Static AO buffer is smaller than expected for model {}, some instances were exported \
+             without AO",
+            model.hash
+        );
+    }
+
+    let name = format!("static_{}", model.hash);
+    let fbx = fbx::write_fbx(&name, &exported, None);
+    save_file_dialog(fbx.into_bytes(), "FBX", "fbx", name);
+    Ok(())
+}
+
+/// Number of vertices of a single instance of a static model
+fn static_vertex_count(hash: TagHash) -> anyhow::Result<usize> {
+    let model: SStaticMesh = package_manager()
+        .read_tag_struct(hash)
+        .with_context(|| format!("Failed to read static model {hash}"))?;
+
+    Ok(static_mesh_info(&model)
+        .iter()
+        .map(|m| m.vertex_count() as usize)
+        .sum())
+}
+
+fn bake_ao(colors: &mut [Vec4], ao: &[f32]) {
+    for (color, &ao) in colors.iter_mut().zip(ao) {
+        *color = (color.truncate() * ao).extend(color.w);
+    }
+}
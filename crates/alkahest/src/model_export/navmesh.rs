@@ -0,0 +1,256 @@
+//! Collision geometry for navmesh generation. Terrain and Havok shapes are merged into a single
+//! OBJ with shared vertices, split into walkable and unwalkable materials, which navmesh tools
+//! like Recast can consume directly
+
+use std::fmt::Write;
+
+use alkahest_renderer::ecs::{
+    render::{
+        havok::HavokShapeRenderer,
+        mesh_info::{terrain_mesh_info, MeshInfo},
+        terrain::TerrainPatches,
+    },
+    tags::NodeFilter,
+    transform::Transform,
+    Scene,
+};
+use glam::{IVec3, Vec3};
+use rustc_hash::FxHashMap;
+
+use crate::util::export::save_file_dialog;
+
+/// Steepest slope that can still be walked on
+const MAX_WALKABLE_SLOPE_DEGREES: f32 = 45.0;
+/// Vertices closer together than this are merged, closing the seams between terrain patches and
+/// shapes
+const WELD_DISTANCE: f32 = 0.01;
+
+struct NavmeshExport {
+    name: String,
+    terrain: Vec<MeshInfo>,
+    /// World space triangles of every collision shape, and whether the shape can never be walked
+    /// on (eg. kill barriers)
+    shapes: Vec<(Vec<[Vec3; 3]>, bool)>,
+}
+
+impl NavmeshExport {
+    fn collect(scene: &mut Scene, name: &str) -> Self {
+        let terrain = scene
+            .query::<&TerrainPatches>()
+            .iter(scene)
+            .map(|patches| terrain_mesh_info(&patches.terrain))
+            .collect();
+
+        let shapes = scene
+            .query::<(&HavokShapeRenderer, &Transform, Option<&NodeFilter>)>()
+            .iter(scene)
+            .filter_map(|(shape, transform, filter)| {
+                let unwalkable = match filter {
+                    // Trigger volumes, these don't block anything
+                    Some(NodeFilter::NamedArea | NodeFilter::PlayerContainmentVolume) => {
+                        return None
+                    }
+                    Some(NodeFilter::InstakillBarrier | NodeFilter::TurnbackBarrier) => true,
+                    _ => false,
+                };
+
+                let local_to_world = transform.local_to_world();
+                let triangles = shape
+                    .triangles()
+                    .iter()
+                    .map(|t| t.map(|v| local_to_world.transform_point3(v)))
+                    .collect();
+
+                Some((triangles, unwalkable))
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            terrain,
+            shapes,
+        }
+    }
+
+    /// Reads the terrain geometry and merges everything into one mesh
+    fn build(&self) -> WeldedMesh {
+        let mut mesh = WeldedMesh::default();
+        for (i, terrain) in self.terrain.iter().enumerate() {
+            let geometry = match terrain.read_geometry() {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    warn!("Failed to read terrain patches {i}, skipping them: {e:?}");
+                    continue;
+                }
+            };
+
+            for t in &geometry.triangles {
+                mesh.add_triangle(t.map(|i| geometry.positions[i as usize]), false);
+            }
+        }
+
+        for (triangles, unwalkable) in &self.shapes {
+            for t in triangles {
+                mesh.add_triangle(*t, *unwalkable);
+            }
+        }
+
+        mesh
+    }
+}
+
+#[derive(Default)]
+struct WeldedMesh {
+    vertices: Vec<Vec3>,
+    /// Vertices bucketed into cells of `WELD_DISTANCE`
+    vertex_lookup: FxHashMap<IVec3, Vec<u32>>,
+    walkable: Vec<[u32; 3]>,
+    unwalkable: Vec<[u32; 3]>,
+}
+
+impl WeldedMesh {
+    fn vertex(&mut self, v: Vec3) -> u32 {
+        let cell = (v / WELD_DISTANCE).floor().as_ivec3();
+
+        // Vertices within the weld distance can be in any of the neighbouring cells
+        let mut closest: Option<(u32, f32)> = None;
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(indices) = self.vertex_lookup.get(&(cell + IVec3::new(x, y, z)))
+                    else {
+                        continue;
+                    };
+
+                    for &i in indices {
+                        let distance = self.vertices[i as usize].distance(v);
+                        if distance <= WELD_DISTANCE && closest.map_or(true, |(_, d)| distance < d)
+                        {
+                            closest = Some((i, distance));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((i, _)) = closest {
+            return i;
+        }
+
+        self.vertices.push(v);
+        let i = self.vertices.len() as u32 - 1;
+        self.vertex_lookup.entry(cell).or_default().push(i);
+        i
+    }
+
+    fn add_triangle(&mut self, t: [Vec3; 3], unwalkable: bool) {
+        let mut indices = t.map(|v| self.vertex(v));
+        if indices[0] == indices[1] || indices[1] == indices[2] || indices[0] == indices[2] {
+            return;
+        }
+
+        let normal = (t[1] - t[0]).cross(t[2] - t[0]).normalize_or_zero();
+        let max_slope = MAX_WALKABLE_SLOPE_DEGREES.to_radians().cos();
+        if !unwalkable && normal.z.abs() >= max_slope {
+            // Navmesh tools only walk on triangles facing up
+            if normal.z < 0.0 {
+                indices.swap(1, 2);
+            }
+            self.walkable.push(indices);
+        } else {
+            self.unwalkable.push(indices);
+        }
+    }
+
+    /// Writes the mesh as a Y-up OBJ, with a group for both walkable and unwalkable triangles
+    fn to_obj(&self, name: &str) -> String {
+        let mut obj = String::new();
+        writeln!(obj, "# Navmesh geometry for {name}, exported by Alkahest").ok();
+        writeln!(obj, "o {name}").ok();
+        for v in &self.vertices {
+            writeln!(obj, "v {} {} {}", v.x, v.z, -v.y).ok();
+        }
+
+        for (material, triangles) in [
+            ("walkable", &self.walkable),
+            ("unwalkable", &self.unwalkable),
+        ] {
+            if triangles.is_empty() {
+                continue;
+            }
+
+            writeln!(obj, "g {material}\nusemtl {material}").ok();
+            for t in triangles {
+                writeln!(obj, "f {} {} {}", t[0] + 1, t[1] + 1, t[2] + 1).ok();
+            }
+        }
+
+        obj
+    }
+}
+
+/// Collects the terrain and collision shapes of the current map and asks where to save them.
+/// Terrain geometry is read on a separate thread
+pub fn export_navmesh_obj(scene: &mut Scene, name: &str) {
+    // Map names aren't necessarily valid file names
+    let name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let export = NavmeshExport::collect(scene, &name);
+    std::thread::spawn(move || {
+        let mesh = export.build();
+        if mesh.walkable.is_empty() && mesh.unwalkable.is_empty() {
+            error!("Map {} has no collision geometry to export", export.name);
+            return;
+        }
+
+        info!(
+            "Exporting navmesh geometry: {} vertices, {} walkable and {} unwalkable triangles",
+            mesh.vertices.len(),
+            mesh.walkable.len(),
+            mesh.unwalkable.len()
+        );
+        save_file_dialog(
+            mesh.to_obj(&export.name).into_bytes(),
+            "Wavefront OBJ",
+            "obj",
+            format!("{}_navmesh", export.name),
+        );
+    });
+}
+
+#[test]
+fn test_navmesh_welding() {
+    let mut mesh = WeldedMesh::default();
+    // Floor, wound facing down
+    mesh.add_triangle([Vec3::ZERO, Vec3::Y, Vec3::X], false);
+    // Wall sharing an edge with the floor, with a tiny gap
+    mesh.add_triangle(
+        [
+            Vec3::new(0.001, 0.0, 0.0),
+            Vec3::X,
+            Vec3::new(0.0, 0.0, 1.0),
+        ],
+        false,
+    );
+    // Floor of a kill barrier
+    mesh.add_triangle([Vec3::ZERO, Vec3::X, Vec3::Y], true);
+
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.walkable, vec![[0, 2, 1]]);
+    assert_eq!(mesh.unwalkable.len(), 2);
+
+    let obj = mesh.to_obj("test");
+    assert!(obj.contains("usemtl walkable\nf 1 3 2\n"));
+    // Z-up to Y-up
+    assert!(obj.contains("v 0 1 -0\n"));
+
+    // Vertices on either side of a cell boundary are still welded
+    for (a, b) in [(0.0049, 0.0051), (0.0099, 0.0101)] {
+        let mut mesh = WeldedMesh::default();
+        let a = mesh.vertex(Vec3::new(a, 5.0, 5.0));
+        assert_eq!(a, mesh.vertex(Vec3::new(b, 5.0, 5.0)));
+        assert_ne!(a, mesh.vertex(Vec3::new(0.03, 5.0, 5.0)));
+    }
+}
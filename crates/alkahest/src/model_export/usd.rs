@@ -0,0 +1,457 @@
+//! USDA scene export. A map is written as a root layer with sublayers for the static model
+//! prototypes, the static placements, lights and cubemap volumes, so each part can be swapped or
+//! muted individually in tools like Houdini or Omniverse
+
+use std::{fmt::Write, path::Path};
+
+use alkahest_data::map::SShadowingLight;
+use alkahest_renderer::ecs::{
+    hierarchy::Children,
+    map::CubemapVolume,
+    render::{
+        light::{LightRenderer, LightShape},
+        mesh_info::{static_mesh_info, MeshInfo},
+        static_geometry::{StaticInstances, StaticModelSingle},
+    },
+    transform::Transform,
+    Scene,
+};
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Mat4, Vec3};
+use rustc_hash::FxHashMap;
+
+use super::{read_export_meshes, ExportMesh};
+
+struct StaticPlacement {
+    model: TagHash,
+    transform: Mat4,
+}
+
+struct ExportLight {
+    shape: LightShape,
+    transform: Mat4,
+    /// Extents of the light volume
+    range: Vec3,
+    /// Half of the cone angle of shadowing spotlights, in radians
+    half_fov: Option<f32>,
+}
+
+struct ExportCubemapVolume {
+    name: String,
+    transform: Mat4,
+    extents: Vec3,
+    specular_ibl: Option<TagHash>,
+}
+
+/// Everything needed to export a map, collected from the scene up front so the (slow) geometry
+/// export can run without holding on to the scene
+pub struct UsdMapExport {
+    name: String,
+    models: FxHashMap<TagHash, Vec<MeshInfo>>,
+    statics: Vec<StaticPlacement>,
+    lights: Vec<ExportLight>,
+    cubemap_volumes: Vec<ExportCubemapVolume>,
+}
+
+impl UsdMapExport {
+    pub fn collect(scene: &mut Scene, name: &str) -> Self {
+        let mut models = FxHashMap::default();
+        let mut statics = vec![];
+
+        for (instances, children) in scene.query::<(&StaticInstances, &Children)>().iter(scene) {
+            let model = &instances.model;
+            models
+                .entry(model.hash)
+                .or_insert_with(|| static_mesh_info(&model.model));
+            for &child in children.iter() {
+                if let Some(transform) = scene.get::<Transform>(child) {
+                    statics.push(StaticPlacement {
+                        model: model.hash,
+                        transform: transform.local_to_world(),
+                    });
+                }
+            }
+        }
+
+        for (single, transform) in scene
+            .query::<(&StaticModelSingle, &Transform)>()
+            .iter(scene)
+        {
+            let model = &single.model;
+            models
+                .entry(model.hash)
+                .or_insert_with(|| static_mesh_info(&model.model));
+            statics.push(StaticPlacement {
+                model: model.hash,
+                transform: transform.local_to_world(),
+            });
+        }
+
+        let lights = scene
+            .query::<(&LightRenderer, &Transform, Option<&SShadowingLight>)>()
+            .iter(scene)
+            .map(|(light, transform, shadowing)| ExportLight {
+                shape: LightShape::from_volume_matrix(light.projection_matrix),
+                transform: transform.local_to_world(),
+                range: Vec3::new(
+                    light.projection_matrix.x_axis.truncate().length(),
+                    light.projection_matrix.y_axis.truncate().length(),
+                    light.projection_matrix.z_axis.truncate().length(),
+                ),
+                half_fov: shadowing.map(|s| s.half_fov),
+            })
+            .collect();
+
+        let cubemap_volumes = scene
+            .query::<(&CubemapVolume, &Transform)>()
+            .iter(scene)
+            .map(|(volume, transform)| ExportCubemapVolume {
+                name: volume.name.clone(),
+                transform: transform.local_to_world(),
+                extents: volume.extents,
+                specular_ibl: volume.specular_ibl.id().tiger_taghash(),
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            models,
+            statics,
+            lights,
+            cubemap_volumes,
+        }
+    }
+
+    /// Writes the root layer and its sublayers to `directory`
+    pub fn write(&self, directory: &Path) -> anyhow::Result<()> {
+        let layers = [
+            ("prototypes", self.prototypes_layer()),
+            ("statics", self.statics_layer()),
+            ("lights", self.lights_layer()),
+            ("cubemap_volumes", self.cubemap_volumes_layer()),
+        ];
+
+        let mut root = layer_header(&self.name);
+        root.insert_str(
+            root.rfind(')').unwrap(),
+            &format!(
+                "    subLayers = [\n{}\n    ]\n",
+                layers
+                    .iter()
+                    .map(|(layer, _)| format!("        @./{}_{layer}.usda@", self.name))
+                    .collect::<Vec<_>>()
+                    .join(",\n")
+            ),
+        );
+        root.push_str("def Xform \"Map\"\n{\n}\n");
+
+        fs_err::create_dir_all(directory)?;
+        fs_err::write(directory.join(format!("{}.usda", self.name)), root)?;
+        for (layer, data) in layers {
+            fs_err::write(directory.join(format!("{}_{layer}.usda", self.name)), data)?;
+        }
+
+        info!(
+            "Exported {} statics ({} models), {} lights and {} cubemap volumes to {}",
+            self.statics.len(),
+            self.models.len(),
+            self.lights.len(),
+            self.cubemap_volumes.len(),
+            directory.display()
+        );
+
+        Ok(())
+    }
+
+    /// Static models are defined once as abstract prototypes, placements reference them
+    fn prototypes_layer(&self) -> String {
+        let mut out = layer_header(&self.name);
+        out.push_str("class \"Prototypes\"\n{\n");
+        for (hash, meshes) in &self.models {
+            let meshes = match read_export_meshes(meshes) {
+                Ok(meshes) => meshes,
+                Err(e) => {
+                    warn!("Failed to export static model {hash}: {e:?}");
+                    vec![]
+                }
+            };
+
+            writeln!(out, "    def Xform \"{}\"\n    {{", prototype_name(*hash)).ok();
+            for mesh in &meshes {
+                write_mesh(&mut out, mesh);
+            }
+            out.push_str("    }\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn statics_layer(&self) -> String {
+        let mut out = layer_header(&self.name);
+        out.push_str("over \"Map\"\n{\n    def Xform \"Statics\"\n    {\n");
+        for (i, placement) in self.statics.iter().enumerate() {
+            writeln!(
+                out,
+                "        def Xform \"static_{i}\" (\n            instanceable = true\n            \
+                 prepend references = </Prototypes/{}>\n        )\n        {{",
+                prototype_name(placement.model)
+            )
+            .ok();
+            write_transform(&mut out, placement.transform, 3);
+            out.push_str("        }\n");
+        }
+        out.push_str("    }\n}\n");
+        out
+    }
+
+    /// Light colors and intensities are computed by the light techniques, only the placement,
+    /// shape and range of lights are exported. Spotlights without a shadowmap get a 90 degree cone
+    fn lights_layer(&self) -> String {
+        let mut out = layer_header(&self.name);
+        out.push_str("over \"Map\"\n{\n    def Xform \"Lights\"\n    {\n");
+        for (i, light) in self.lights.iter().enumerate() {
+            let (prim_type, shape_attributes) = match light.shape {
+                LightShape::Omni => ("SphereLight", "bool treatAsPoint = 1".to_string()),
+                LightShape::Spot => (
+                    "SphereLight",
+                    format!(
+                        "bool treatAsPoint = 1\n            float inputs:shaping:cone:angle = {}",
+                        light.half_fov.map_or(45.0, f32::to_degrees)
+                    ),
+                ),
+                LightShape::Line => (
+                    "CylinderLight",
+                    format!("float inputs:length = {}", light.range.x * 2.0),
+                ),
+            };
+
+            let schemas = if matches!(light.shape, LightShape::Spot) {
+                " (\n            prepend apiSchemas = [\"ShapingAPI\"]\n        )"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "        def {prim_type} \"light_{i}\"{schemas}\n        {{"
+            )
+            .ok();
+            writeln!(out, "            {shape_attributes}").ok();
+            writeln!(
+                out,
+                "            custom float3 alkahest:range = ({}, {}, {})",
+                light.range.x, light.range.y, light.range.z
+            )
+            .ok();
+            write_transform(&mut out, light.transform, 3);
+            out.push_str("        }\n");
+        }
+        out.push_str("    }\n}\n");
+        out
+    }
+
+    fn cubemap_volumes_layer(&self) -> String {
+        let mut out = layer_header(&self.name);
+        out.push_str("over \"Map\"\n{\n    def Xform \"CubemapVolumes\"\n    {\n");
+        for (i, volume) in self.cubemap_volumes.iter().enumerate() {
+            let specular_ibl = volume
+                .specular_ibl
+                .map(|h| h.to_string())
+                .unwrap_or_default();
+            writeln!(out, "        def Xform \"cubemap_volume_{i}\" (").ok();
+            out.push_str("            customData = {\n");
+            writeln!(
+                out,
+                "                string name = \"{}\"",
+                volume.name.replace('"', "'")
+            )
+            .ok();
+            writeln!(
+                out,
+                "                float3 extents = ({}, {}, {})",
+                volume.extents.x, volume.extents.y, volume.extents.z
+            )
+            .ok();
+            writeln!(
+                out,
+                "                string specularIbl = \"{specular_ibl}\""
+            )
+            .ok();
+            out.push_str("            }\n        )\n        {\n");
+            write_transform(&mut out, volume.transform, 3);
+            out.push_str("        }\n");
+        }
+        out.push_str("    }\n}\n");
+        out
+    }
+}
+
+fn prototype_name(model: TagHash) -> String {
+    format!("static_{:08X}", model.0)
+}
+
+/// Destiny is Z-up and measured in meters
+fn layer_header(name: &str) -> String {
+    format!(
+        "#usda 1.0\n(\n    defaultPrim = \"Map\"\n    doc = \"Exported from map '{}' by \
+         Alkahest\"\n    metersPerUnit = 1\n    upAxis = \"Z\"\n)\n\n",
+        name.replace('"', "'")
+    )
+}
+
+fn write_transform(out: &mut String, transform: Mat4, indent: usize) {
+    let indent = "    ".repeat(indent);
+    // USD matrices are row-major with row vectors, so the columns of a glam matrix become the rows
+    let rows = transform
+        .to_cols_array_2d()
+        .map(|c| format!("({}, {}, {}, {})", c[0], c[1], c[2], c[3]));
+    writeln!(
+        out,
+        "{indent}matrix4d xformOp:transform = ({})\n{indent}uniform token[] xformOpOrder = \
+         [\"xformOp:transform\"]",
+        rows.join(", ")
+    )
+    .ok();
+}
+
+fn write_mesh(out: &mut String, mesh: &ExportMesh) {
+    let geometry = &mesh.geometry;
+    let join = |values: Vec<String>| values.join(", ");
+
+    writeln!(out, "        def Mesh \"{}\"\n        {{", mesh.name).ok();
+    out.push_str("            uniform bool doubleSided = 1\n");
+    out.push_str("            uniform token subdivisionScheme = \"none\"\n");
+    writeln!(
+        out,
+        "            int[] faceVertexCounts = [{}]",
+        join(vec!["3".to_string(); geometry.triangles.len()])
+    )
+    .ok();
+    writeln!(
+        out,
+        "            int[] faceVertexIndices = [{}]",
+        join(
+            geometry
+                .triangles
+                .iter()
+                .flat_map(|t| t.map(|i| i.to_string()))
+                .collect()
+        )
+    )
+    .ok();
+    writeln!(
+        out,
+        "            point3f[] points = [{}]",
+        join(
+            geometry
+                .positions
+                .iter()
+                .map(|p| format!("({}, {}, {})", p.x, p.y, p.z))
+                .collect()
+        )
+    )
+    .ok();
+
+    if let Some(normals) = &geometry.normals {
+        write_vertex_attribute(
+            out,
+            "normal3f[] normals",
+            normals
+                .iter()
+                .map(|n| format!("({}, {}, {})", n.x, n.y, n.z)),
+        );
+    }
+
+    if let Some(texcoords) = &geometry.texcoords {
+        // USD texture coordinates start at the bottom left
+        write_vertex_attribute(
+            out,
+            "texCoord2f[] primvars:st",
+            texcoords
+                .iter()
+                .map(|uv| format!("({}, {})", uv.x, 1.0 - uv.y)),
+        );
+    }
+
+    if let Some(colors) = &geometry.colors {
+        write_vertex_attribute(
+            out,
+            "color3f[] primvars:displayColor",
+            colors
+                .iter()
+                .map(|c| format!("({}, {}, {})", c.x, c.y, c.z)),
+        );
+    }
+
+    out.push_str("        }\n");
+}
+
+fn write_vertex_attribute(out: &mut String, name: &str, values: impl Iterator<Item = String>) {
+    writeln!(
+        out,
+        "            {name} = [{}] (\n                interpolation = \"vertex\"\n            )",
+        values.collect::<Vec<_>>().join(", ")
+    )
+    .ok();
+}
+
+/// Collects the current map and asks for a directory to write it to. The geometry is read and
+/// written on a separate thread, large maps can take a while
+pub fn export_map_usd(scene: &mut Scene, name: &str) {
+    // Map names aren't necessarily valid file names
+    let name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let export = UsdMapExport::collect(scene, &name);
+    std::thread::spawn(move || {
+        let directory = match native_dialog::FileDialog::new().show_open_single_dir() {
+            Ok(Some(directory)) => directory,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to open the directory dialog: {e}");
+                return;
+            }
+        };
+
+        if let Err(e) = export
+            .write(&directory)
+            .context("Failed to export map to USD")
+        {
+            error!("{e:?}");
+        }
+    });
+}
+
+#[test]
+fn test_usd_layers() {
+    let model = TagHash(0x80a0_1234);
+    let export = UsdMapExport {
+        name: "test".to_string(),
+        models: FxHashMap::default(),
+        statics: vec![StaticPlacement {
+            model,
+            transform: Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+        }],
+        lights: vec![ExportLight {
+            shape: LightShape::Spot,
+            transform: Mat4::IDENTITY,
+            range: Vec3::splat(4.0),
+            half_fov: Some(std::f32::consts::FRAC_PI_4),
+        }],
+        cubemap_volumes: vec![],
+    };
+
+    let statics = export.statics_layer();
+    assert!(statics.contains("prepend references = </Prototypes/static_80A01234>"));
+    // Translation ends up in the last row
+    assert!(statics.contains("(1, 2, 3, 1))"));
+
+    let lights = export.lights_layer();
+    assert!(lights.contains("def SphereLight \"light_0\""));
+    assert!(lights.contains("float inputs:shaping:cone:angle = 45"));
+    for layer in [statics, lights, export.cubemap_volumes_layer()] {
+        assert!(layer.starts_with("#usda 1.0"));
+        assert_eq!(layer.matches('{').count(), layer.matches('}').count());
+    }
+}
@@ -0,0 +1,214 @@
+//! Verification of the package directory, so corrupt or missing packages are reported up front
+//! instead of surfacing as errors deep inside map loading
+
+use std::path::Path;
+
+use alkahest_pm::package_manager;
+use alkahest_renderer::util::background_errors::report_background_error;
+use destiny_pkg::TagHash;
+use lazy_static::lazy_static;
+use rustc_hash::FxHashMap;
+
+use crate::util::RwLock;
+
+/// Files smaller than this can't contain a complete package header
+const MIN_PACKAGE_SIZE: u64 = 0x200;
+const ERROR_SOURCE: &str = "Package check";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckDepth {
+    /// Checks that all packages referenced by the tag index exist and aren't truncated
+    Quick,
+    /// Also reads the first and last entry of every package, verifying the block data and that
+    /// the package can be decrypted
+    Full,
+}
+
+#[derive(Clone, Debug)]
+pub struct PackageIssue {
+    pub package: String,
+    pub message: String,
+}
+
+lazy_static! {
+    /// Issues found by the last check, by package ID
+    static ref ISSUES: RwLock<FxHashMap<u16, PackageIssue>> = RwLock::new(FxHashMap::default());
+}
+
+/// Runs the check on a background thread. Issues are logged and shown as error cards
+pub fn start_package_check(depth: CheckDepth) {
+    std::thread::Builder::new()
+        .name("Package check".into())
+        .spawn(move || {
+            let start = std::time::Instant::now();
+            let issues = check_packages(depth);
+            info!(
+                "{depth:?} package check finished in {:.1}s, {} issue(s) found",
+                start.elapsed().as_secs_f32(),
+                issues.len()
+            );
+
+            for issue in issues.values() {
+                report_background_error(
+                    ERROR_SOURCE,
+                    format!("{}: {}", issue.package, issue.message),
+                    Some("pkg.verify full".to_string()),
+                );
+            }
+            *ISSUES.write() = issues;
+        })
+        .expect("Failed to spawn the package check thread");
+}
+
+/// Forgets the issues found by the last check, for when the package manager has been replaced
+pub fn clear_issues() {
+    ISSUES.write().clear();
+}
+
+/// Explains a map load failure if the package check found problems that could have caused it
+pub fn load_failure_hint(map: TagHash) -> Option<String> {
+    let issues = ISSUES.read();
+    if let Some(issue) = issues.get(&map.pkg_id()) {
+        return Some(format!(
+            "There is a problem with the package containing this map ({}): {}",
+            issue.package, issue.message
+        ));
+    }
+
+    (!issues.is_empty()).then(|| {
+        format!(
+            "{} package(s) failed the integrity check, which may have caused this error. Run \
+             `pkg.verify full` for details",
+            issues.len()
+        )
+    })
+}
+
+fn check_packages(depth: CheckDepth) -> FxHashMap<u16, PackageIssue> {
+    let pm = package_manager();
+    let mut issues = FxHashMap::default();
+
+    // Packages the tag index knows about, but that aren't in the package directory (anymore)
+    for &pkg_id in pm.lookup.tag32_entries_by_pkg.keys() {
+        if !pm.package_paths.contains_key(&pkg_id) {
+            issues.insert(
+                pkg_id,
+                PackageIssue {
+                    package: format!("{pkg_id:04x}"),
+                    message: "Package is missing from the package directory. Verify the game \
+                              files through your launcher"
+                        .to_string(),
+                },
+            );
+        }
+    }
+
+    match std::fs::read_dir(&pm.package_dir) {
+        Ok(dir) => {
+            for entry in dir.flatten() {
+                let path = entry.path();
+                if !path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("pkg"))
+                {
+                    continue;
+                }
+
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                if size < MIN_PACKAGE_SIZE {
+                    // Issues are looked up by package ID, files without one can't be attributed
+                    let Some(pkg_id) = package_id_from_path(&path) else {
+                        warn!(
+                            "Skipping truncated file {} ({size} bytes), its name doesn't contain \
+                             a package ID",
+                            path.display()
+                        );
+                        continue;
+                    };
+                    issues.insert(
+                        pkg_id,
+                        PackageIssue {
+                            package: path.file_name().unwrap().to_string_lossy().to_string(),
+                            message: format!(
+                                "File is truncated ({size} bytes). Verify the game files through \
+                                 your launcher"
+                            ),
+                        },
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            error!(
+                "Failed to read package directory {}: {e}",
+                pm.package_dir.display()
+            );
+        }
+    }
+
+    if depth == CheckDepth::Full {
+        let mut pkg_ids = pm.package_paths.keys().copied().collect::<Vec<_>>();
+        pkg_ids.sort_unstable();
+        for pkg_id in pkg_ids {
+            let path = &pm.package_paths[&pkg_id];
+            // Redacted packages are expected to be unreadable until their keys are released
+            if issues.contains_key(&pkg_id) || path.name.ends_with("redacted") {
+                continue;
+            }
+
+            let entry_count = pm
+                .lookup
+                .tag32_entries_by_pkg
+                .get(&pkg_id)
+                .map_or(0, |e| e.len());
+            if entry_count == 0 {
+                continue;
+            }
+
+            for index in [0, entry_count - 1] {
+                if let Err(e) = pm.read_tag(TagHash::new(pkg_id, index as u16)) {
+                    issues.insert(
+                        pkg_id,
+                        PackageIssue {
+                            package: format!("{}_{}", path.name, path.id),
+                            message: describe_read_error(&e),
+                        },
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Turns package read errors into something the user can act on
+fn describe_read_error(e: &anyhow::Error) -> String {
+    let error = format!("{e:#}");
+    if error.contains("key") {
+        format!(
+            "Package can't be decrypted ({error}). It may be from a newer game version than this \
+             version of Alkahest supports"
+        )
+    } else if e
+        .chain()
+        .filter_map(|e| e.downcast_ref::<std::io::Error>())
+        .any(|e| e.kind() == std::io::ErrorKind::NotFound)
+    {
+        format!(
+            "A patch file containing part of this package is missing ({error}). Verify the game \
+             files through your launcher"
+        )
+    } else {
+        format!("Package data is corrupt ({error}). Verify the game files through your launcher")
+    }
+}
+
+/// Extracts the package ID from a package filename (eg. `w64_sr_audio_063c_0.pkg`)
+fn package_id_from_path(path: &Path) -> Option<u16> {
+    let stem = path.file_stem()?.to_str()?;
+    stem.rsplit('_')
+        .find(|part| part.len() == 4)
+        .and_then(|part| u16::from_str_radix(part, 16).ok())
+}
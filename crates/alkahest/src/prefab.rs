@@ -0,0 +1,268 @@
+//! Prefabs: named groups of user entities (rulers, spheres and beacons) that can be saved to disk
+//! and spawned into any map.
+//!
+//! A group is a parent entity with a [`Prefab`] component and the grouped entities as children.
+//! Saved prefabs store their entities relative to the center of the group, so they can be placed
+//! anywhere.
+
+use std::path::{Path, PathBuf};
+
+use alkahest_renderer::{
+    ecs::{
+        common::{Icon, Label, Mutable, RenderCommonBundle},
+        hierarchy::{Children, Parent},
+        tags::{EntityTag, NodeFilter, Tags},
+        Scene,
+    },
+    icons::ICON_PACKAGE_VARIANT,
+};
+use anyhow::Context;
+use bevy_ecs::{component::Component, entity::Entity};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    autosave::{save_entity, spawn_entity, SavedEntity},
+    paths,
+    util::text::sanitize_file_name,
+};
+
+/// Marks the parent entity of a prefab group
+#[derive(Component)]
+pub struct Prefab;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedPrefab {
+    pub name: String,
+    /// Entities relative to the center of the group
+    pub entities: Vec<SavedEntity>,
+}
+
+impl SavedPrefab {
+    /// Saves the given entities, skipping the ones that can't be part of a prefab. Returns `None`
+    /// if none of them can
+    pub fn from_entities(scene: &Scene, name: &str, entities: &[Entity]) -> Option<Self> {
+        let mut saved = entities
+            .iter()
+            .filter_map(|&e| scene.get_entity(e).and_then(save_entity))
+            .collect::<Vec<_>>();
+        if saved.is_empty() {
+            return None;
+        }
+
+        let center = saved.iter().map(SavedEntity::position).sum::<Vec3>() / saved.len() as f32;
+        for entity in &mut saved {
+            entity.translate(-center);
+        }
+
+        Some(Self {
+            name: name.to_string(),
+            entities: saved,
+        })
+    }
+
+    /// Saves the prefab group `group`
+    pub fn from_group(scene: &Scene, group: Entity) -> Option<Self> {
+        let name = scene
+            .get::<Label>(group)
+            .map_or("Prefab".to_string(), |l| l.label.clone());
+        let children = scene.get::<Children>(group)?;
+        Self::from_entities(scene, &name, children)
+    }
+
+    /// Spawns the prefab as a new group centered on `position`
+    pub fn instantiate(&self, scene: &mut Scene, position: Vec3) -> Entity {
+        let children = self
+            .entities
+            .iter()
+            .map(|entity| {
+                let mut entity = entity.clone();
+                entity.translate(position);
+                spawn_entity(scene, &entity)
+            })
+            .collect::<Vec<_>>();
+
+        group_entities(scene, &self.name, &children)
+    }
+
+    /// Writes the prefab to the prefab directory, replacing any prefab with the same name
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let directory = prefab_directory();
+        std::fs::create_dir_all(&directory)
+            .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+        let path = directory.join(format!("{}.yml", sanitize_file_name(&self.name)));
+        let data = serde_yaml::to_string(self)?;
+        std::fs::write(&path, data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+pub struct PrefabFile {
+    pub path: PathBuf,
+    pub prefab: SavedPrefab,
+}
+
+pub fn prefab_directory() -> PathBuf {
+    paths::config_dir().join("prefabs")
+}
+
+/// All prefabs in the prefab directory, sorted by name
+pub fn list_prefabs() -> Vec<PrefabFile> {
+    let Ok(dir) = std::fs::read_dir(prefab_directory()) else {
+        return vec![];
+    };
+
+    let mut prefabs = dir
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "yml"))
+        .filter_map(|path| match read_prefab(&path) {
+            Ok(prefab) => Some(PrefabFile { path, prefab }),
+            Err(e) => {
+                warn!("Failed to read prefab {}: {e:?}", path.display());
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    prefabs.sort_by(|a, b| a.prefab.name.cmp(&b.prefab.name));
+    prefabs
+}
+
+fn read_prefab(path: &Path) -> anyhow::Result<SavedPrefab> {
+    let data = std::fs::read_to_string(path)?;
+    Ok(serde_yaml::from_str(&data)?)
+}
+
+/// Groups `entities` under a new prefab parent. Entities that already have a parent are moved
+pub fn group_entities(scene: &mut Scene, name: &str, entities: &[Entity]) -> Entity {
+    for &e in entities {
+        ungroup_entity(scene, e);
+    }
+
+    let parent = scene
+        .spawn((
+            Prefab,
+            Icon::Unicode(ICON_PACKAGE_VARIANT),
+            Label::from(name),
+            NodeFilter::Utility,
+            Tags::from_iter([EntityTag::Utility]),
+            Mutable,
+            Children::from_slice(entities),
+            RenderCommonBundle::default(),
+        ))
+        .id();
+
+    for &e in entities {
+        scene.entity_mut(e).insert(Parent(parent));
+    }
+
+    parent
+}
+
+/// Removes an entity from its parent
+fn ungroup_entity(scene: &mut Scene, entity: Entity) {
+    let Some(Parent(parent)) = scene.entity_mut(entity).take::<Parent>() else {
+        return;
+    };
+
+    if let Some(mut children) = scene.get_mut::<Children>(parent) {
+        children.0.retain(|&c| c != entity);
+    }
+}
+
+/// Dissolves a prefab group, keeping its entities
+pub fn ungroup(scene: &mut Scene, group: Entity) {
+    if let Some(children) = scene.entity_mut(group).take::<Children>() {
+        for &child in children.iter() {
+            scene.entity_mut(child).remove::<Parent>();
+        }
+    }
+
+    scene.despawn(group);
+}
+
+/// Whether an entity can be duplicated with [`duplicate`]
+pub fn can_duplicate(scene: &Scene, entity: Entity) -> bool {
+    scene.get_entity(entity).is_some_and(|e| {
+        e.contains::<Mutable>() && (e.contains::<Prefab>() || save_entity(e).is_some())
+    })
+}
+
+/// Spawns a copy of a user entity or prefab group in the same place. Duplicated entities stay in
+/// the same group as the original
+pub fn duplicate(scene: &mut Scene, entity: Entity) -> Option<Entity> {
+    if scene.get::<Prefab>(entity).is_some() {
+        let name = scene.get::<Label>(entity)?.label.clone();
+        let saved = scene
+            .get::<Children>(entity)?
+            .iter()
+            .filter_map(|&e| scene.get_entity(e).and_then(save_entity))
+            .collect::<Vec<_>>();
+        let copies = saved
+            .iter()
+            .map(|e| spawn_entity(scene, e))
+            .collect::<Vec<_>>();
+        return Some(group_entities(scene, &name, &copies));
+    }
+
+    let saved = save_entity(scene.get_entity(entity)?)?;
+    let copy = spawn_entity(scene, &saved);
+    if let Some(&Parent(parent)) = scene.get::<Parent>(entity) {
+        scene.entity_mut(copy).insert(Parent(parent));
+        if let Some(mut children) = scene.get_mut::<Children>(parent) {
+            children.0.push(copy);
+        }
+    }
+
+    Some(copy)
+}
+
+#[test]
+fn test_prefab_roundtrip() {
+    let mut scene = Scene::new();
+    let beacon = spawn_entity(
+        &mut scene,
+        &SavedEntity::Beacon {
+            label: Some("Beacon".to_string()),
+            position: [2.0, 0.0, 0.0],
+            color: [1.0; 4],
+            freq: 1.0,
+            distance: 10.0,
+            travel_time: 1.0,
+        },
+    );
+    let ruler = spawn_entity(
+        &mut scene,
+        &SavedEntity::Ruler {
+            label: None,
+            start: [4.0, 0.0, 0.0],
+            end: [4.0, 0.0, 2.0],
+            color: [1.0; 4],
+            rainbow: false,
+            scale: 1.0,
+            marker_interval: 0.0,
+            show_individual_axis: false,
+        },
+    );
+
+    let group = group_entities(&mut scene, "Markers", &[beacon, ruler]);
+    assert_eq!(scene.get::<Parent>(beacon).map(|p| p.0), Some(group));
+
+    let prefab = SavedPrefab::from_group(&scene, group).unwrap();
+    assert_eq!(prefab.name, "Markers");
+    // Entities are stored relative to the center of the group
+    assert_eq!(prefab.entities[0].position(), Vec3::new(-1.0, 0.0, 0.0));
+    assert_eq!(prefab.entities[1].position(), Vec3::new(1.0, 0.0, 0.0));
+
+    let copy = prefab.instantiate(&mut scene, Vec3::new(0.0, 10.0, 0.0));
+    let copied = SavedPrefab::from_group(&scene, copy).unwrap();
+    assert!(copied.entities == prefab.entities);
+
+    ungroup(&mut scene, group);
+    assert!(scene.get::<Parent>(beacon).is_none());
+    assert!(scene.get_entity(group).is_none());
+}
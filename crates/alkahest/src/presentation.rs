@@ -0,0 +1,308 @@
+//! Presentation mode: hides the interface and moves the camera between bookmarked views on a
+//! timer, or orbits the selected entity. Meant for streaming and recording footage without any
+//! manual camera work.
+//!
+//! Bookmarks are stored per map in the config directory, in the same format as the
+//! `--capture-bookmarks` files used by headless captures.
+
+use std::path::PathBuf;
+
+use alkahest_renderer::{
+    camera::Camera,
+    ecs::resources::SelectedEntity,
+    renderer::{RendererSettings, RendererShared},
+};
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+use crate::{
+    config, gui::hotkeys::entity_bounds, maplist::MapList, paths, resources::AppResources,
+};
+
+/// A named camera position
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub position: [f32; 3],
+    /// Pitch and yaw, in degrees
+    #[serde(default)]
+    pub orientation: [f32; 2],
+}
+
+impl CameraBookmark {
+    pub fn from_camera(name: &str, camera: &Camera) -> Self {
+        Self {
+            name: name.to_string(),
+            position: camera.position().to_array(),
+            orientation: camera.orientation().to_array(),
+        }
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.tween = None;
+        camera.set_position(Vec3::from_array(self.position));
+        camera.set_orientation(Vec2::from_array(self.orientation));
+        camera.update_matrices();
+    }
+}
+
+/// Camera bookmarks of the current map
+#[derive(Default)]
+pub struct CameraBookmarks {
+    /// Map the bookmarks belong to
+    map: Option<TagHash>,
+    pub bookmarks: Vec<CameraBookmark>,
+}
+
+impl CameraBookmarks {
+    /// Loads the bookmarks of `map`, if they aren't loaded already
+    pub fn switch_map(&mut self, map: TagHash) {
+        if self.map == Some(map) {
+            return;
+        }
+
+        self.map = Some(map);
+        self.bookmarks = match std::fs::read_to_string(bookmark_path(map)) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse camera bookmarks for map {map}: {e}");
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+    }
+
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let map = self.map.context("No map to save bookmarks for")?;
+        let path = bookmark_path(map);
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .with_context(|| format!("Failed to create {}", directory.display()))?;
+        }
+
+        std::fs::write(&path, serde_json::to_string_pretty(&self.bookmarks)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+fn bookmark_path(map: TagHash) -> PathBuf {
+    paths::config_dir()
+        .join("bookmarks")
+        .join(format!("{map}.json"))
+}
+
+#[derive(
+    Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, EnumIter, strum::Display,
+)]
+pub enum PresentationTransition {
+    /// Fade to black, move the camera and fade back in
+    #[default]
+    Fade,
+    /// Fly the camera from one bookmark to the next
+    Glide,
+    /// Jump to the next bookmark
+    Cut,
+}
+
+struct PresentationState {
+    /// Bookmark that is being shown, or transitioned to
+    index: usize,
+    /// Time since the transition to the current bookmark started
+    elapsed: f32,
+    /// Camera position and orientation at the start of the transition
+    from: (Vec3, Vec2),
+    /// Whether the camera has jumped to the current bookmark yet (fade and cut transitions)
+    arrived: bool,
+    /// Angle around the orbited entity, in radians. Starts from the camera position on the first
+    /// orbit frame
+    orbit_angle: Option<f32>,
+    /// Renderer overlays that were enabled before presenting, restored when stopping
+    draw_selection_outline: bool,
+    draw_search_highlight: bool,
+}
+
+#[derive(Default)]
+pub struct Presentation {
+    state: Option<PresentationState>,
+}
+
+impl Presentation {
+    pub fn is_active(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Starts presenting, hiding the selection and search outlines
+    pub fn start(&mut self, camera: &Camera, renderer: &RendererShared) {
+        if self.is_active() {
+            return;
+        }
+
+        let (draw_selection_outline, draw_search_highlight) = config::with_mut(|c| {
+            let previous = (
+                c.renderer.draw_selection_outline,
+                c.renderer.draw_search_highlight,
+            );
+            c.renderer.draw_selection_outline = false;
+            c.renderer.draw_search_highlight = false;
+            previous
+        });
+        apply_render_settings(renderer);
+
+        self.state = Some(PresentationState {
+            index: 0,
+            elapsed: 0.0,
+            from: (camera.position(), camera.orientation()),
+            arrived: false,
+            orbit_angle: None,
+            draw_selection_outline,
+            draw_search_highlight,
+        });
+    }
+
+    pub fn stop(&mut self, renderer: &RendererShared) {
+        let Some(state) = self.state.take() else {
+            return;
+        };
+
+        config::with_mut(|c| {
+            c.renderer.draw_selection_outline = state.draw_selection_outline;
+            c.renderer.draw_search_highlight = state.draw_search_highlight;
+        });
+        apply_render_settings(renderer);
+    }
+
+    /// Opacity of the black fade overlay
+    pub fn fade(&self) -> f32 {
+        let Some(state) = &self.state else {
+            return 0.0;
+        };
+
+        let settings = config::with(|c| c.presentation.clone());
+        if settings.orbit_selected || settings.transition != PresentationTransition::Fade {
+            return 0.0;
+        }
+
+        let t = transition_progress(state.elapsed, settings.transition_seconds);
+        if t >= 1.0 {
+            0.0
+        } else {
+            1.0 - (t * 2.0 - 1.0).abs()
+        }
+    }
+}
+
+fn apply_render_settings(renderer: &RendererShared) {
+    let settings: RendererSettings = config::with(|c| c.renderer.clone());
+    renderer.set_render_settings(settings);
+}
+
+fn transition_progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        1.0
+    } else {
+        (elapsed / duration).clamp(0.0, 1.0)
+    }
+}
+
+/// Moves the camera for the current presentation frame. Runs after the regular camera update,
+/// overriding any user input
+pub fn presentation_step(resources: &AppResources, delta_time: f32) {
+    let mut presentation = resources.get_mut::<Presentation>();
+    let Some(state) = &mut presentation.state else {
+        return;
+    };
+
+    let settings = config::with(|c| c.presentation.clone());
+    let mut camera = resources.get_mut::<Camera>();
+    camera.tween = None;
+
+    if settings.orbit_selected {
+        let maps = resources.get::<MapList>();
+        let target = resources
+            .get::<SelectedEntity>()
+            .selected()
+            .zip(maps.current_map())
+            .and_then(|(e, map)| entity_bounds(&map.scene, e));
+
+        if let Some((center, radius)) = target {
+            let orbit_angle = state.orbit_angle.get_or_insert_with(|| {
+                let offset = camera.position() - center;
+                offset.y.atan2(offset.x)
+            });
+            *orbit_angle += settings.orbit_speed.to_radians() * delta_time;
+            let half_fov = (camera.fov() * 0.5).to_radians();
+            let distance = (radius.max(0.5) / half_fov.tan()) * 1.75;
+            let elevation = settings.orbit_elevation.to_radians();
+
+            let offset = Vec3::new(
+                orbit_angle.cos() * elevation.cos(),
+                orbit_angle.sin() * elevation.cos(),
+                elevation.sin(),
+            ) * distance;
+            camera.set_position(center + offset);
+            let angle = camera.get_look_angle(center);
+            camera.set_orientation(angle);
+            camera.update_matrices();
+            return;
+        }
+    }
+
+    let mut bookmarks = resources.get_mut::<CameraBookmarks>();
+    if let Some(map) = resources.get::<MapList>().current_map() {
+        bookmarks.switch_map(map.hash);
+    }
+    let bookmarks = &bookmarks.bookmarks;
+    if bookmarks.is_empty() {
+        return;
+    }
+
+    state.index %= bookmarks.len();
+    state.elapsed += delta_time;
+
+    let target = &bookmarks[state.index];
+    let to = (
+        Vec3::from_array(target.position),
+        Vec2::from_array(target.orientation),
+    );
+    let t = transition_progress(state.elapsed, settings.transition_seconds);
+    match settings.transition {
+        PresentationTransition::Fade => {
+            // Switch views while the screen is fully black
+            if t >= 0.5 && !state.arrived {
+                target.apply(&mut camera);
+                state.arrived = true;
+            }
+        }
+        PresentationTransition::Glide => {
+            let s = t * t * (3.0 - 2.0 * t);
+            // Take the shortest way around
+            let mut yaw_delta = (to.1.y - state.from.1.y).rem_euclid(360.0);
+            if yaw_delta > 180.0 {
+                yaw_delta -= 360.0;
+            }
+
+            camera.set_position(state.from.0.lerp(to.0, s));
+            camera.set_orientation(Vec2::new(
+                state.from.1.x + (to.1.x - state.from.1.x) * s,
+                state.from.1.y + yaw_delta * s,
+            ));
+            camera.update_matrices();
+        }
+        PresentationTransition::Cut => {
+            if !state.arrived {
+                target.apply(&mut camera);
+                state.arrived = true;
+            }
+        }
+    }
+
+    if state.elapsed >= settings.transition_seconds + settings.hold_seconds {
+        state.index = (state.index + 1) % bookmarks.len();
+        state.elapsed = 0.0;
+        state.arrived = false;
+        state.from = (camera.position(), camera.orientation());
+    }
+}
@@ -0,0 +1,200 @@
+//! Recently opened maps, listed on the startup screen so a previous session can be resumed
+//! without going through the activity browser.
+//!
+//! The list is stored in the config directory, together with a thumbnail of every map. The
+//! thumbnail is taken once per session, the first time the map has finished loading.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use alkahest_renderer::{
+    camera::{Camera, Viewport},
+    renderer::Renderer,
+    resources::AppResources,
+};
+use anyhow::Context;
+use destiny_pkg::TagHash;
+use glam::UVec2;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gui::activity_select::CurrentActivity,
+    maplist::{Map, MapLoadState},
+    paths,
+    presentation::CameraBookmark,
+};
+
+const MAX_SESSIONS: usize = 12;
+const THUMBNAIL_SIZE: UVec2 = UVec2::new(320, 180);
+/// How often the camera position of the current map is recorded
+const RECORD_INTERVAL: Duration = Duration::from_secs(2);
+/// Number of consecutive frames the asset manager has to be idle before a thumbnail is taken
+const SETTLE_FRAMES: usize = 30;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecentSession {
+    pub map_hash: u32,
+    pub map_name: String,
+    pub activity_hash: Option<u32>,
+    /// Last camera position on the map
+    pub camera: CameraBookmark,
+    /// Unix timestamp of the last time the map was open
+    pub last_opened: u64,
+}
+
+impl RecentSession {
+    pub fn map(&self) -> TagHash {
+        TagHash(self.map_hash)
+    }
+
+    pub fn activity(&self) -> Option<TagHash> {
+        self.activity_hash.map(TagHash)
+    }
+}
+
+pub struct RecentSessions {
+    /// Most recently opened first
+    pub sessions: Vec<RecentSession>,
+    last_recorded: Option<Instant>,
+    /// Maps a thumbnail has been taken of this session. Taking one resizes the render targets,
+    /// which causes a hitch
+    thumbnails_taken: FxHashSet<TagHash>,
+    settled_frames: usize,
+}
+
+impl RecentSessions {
+    pub fn load() -> Self {
+        let sessions = match std::fs::read_to_string(sessions_path()) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                warn!("Failed to parse recent sessions: {e}");
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+
+        Self {
+            sessions,
+            last_recorded: None,
+            thumbnails_taken: FxHashSet::default(),
+            settled_frames: 0,
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = sessions_path();
+        std::fs::write(&path, serde_json::to_string_pretty(&self.sessions)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Moves the session of `map` to the front of the list, and updates its camera position
+    pub fn record(&mut self, map: &Map, activity: Option<TagHash>, camera: &Camera) {
+        let existing = self.sessions.iter().position(|s| s.map_hash == map.hash.0);
+        let mut session = match existing {
+            Some(i) => self.sessions.remove(i),
+            None => RecentSession {
+                map_hash: map.hash.0,
+                map_name: map.name.clone(),
+                activity_hash: None,
+                camera: CameraBookmark::from_camera("", camera),
+                last_opened: 0,
+            },
+        };
+
+        session.activity_hash = activity.map(|h| h.0);
+        session.camera = CameraBookmark::from_camera(&map.name, camera);
+        session.last_opened = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        self.sessions.insert(0, session);
+        self.sessions.truncate(MAX_SESSIONS);
+        self.last_recorded = Some(Instant::now());
+    }
+
+    pub fn last_session(&self) -> Option<&RecentSession> {
+        self.sessions.first()
+    }
+}
+
+fn sessions_path() -> PathBuf {
+    paths::config_dir().join("recent.json")
+}
+
+pub fn thumbnail_path(map: TagHash) -> PathBuf {
+    paths::config_dir()
+        .join("thumbnails")
+        .join(format!("{map}.png"))
+}
+
+/// Keeps the recent session of the current map up to date, and renders its thumbnail once the
+/// map has finished loading
+pub fn recent_sessions_step(renderer: &Renderer, map: &mut Map, resources: &AppResources) {
+    if map.hash.is_none() || map.load_state != MapLoadState::Loaded {
+        return;
+    }
+
+    {
+        let mut recent = resources.get_mut::<RecentSessions>();
+        let camera = resources.get::<Camera>();
+        let is_new = recent
+            .last_session()
+            .map_or(true, |s| s.map_hash != map.hash.0);
+        let due = recent
+            .last_recorded
+            .map_or(true, |t| t.elapsed() >= RECORD_INTERVAL);
+        if is_new || due {
+            recent.record(map, resources.get::<CurrentActivity>().0, &camera);
+        }
+        if is_new {
+            if let Err(e) = recent.save() {
+                error!("Failed to save recent sessions: {e:?}");
+            }
+        }
+
+        if recent.thumbnails_taken.contains(&map.hash) {
+            return;
+        }
+
+        let settled = renderer.data.lock().asset_manager.is_idle();
+        recent.settled_frames = if settled {
+            recent.settled_frames + 1
+        } else {
+            0
+        };
+        if recent.settled_frames < SETTLE_FRAMES {
+            return;
+        }
+
+        recent.settled_frames = 0;
+        recent.thumbnails_taken.insert(map.hash);
+    }
+
+    let result = {
+        let view_camera = resources.get::<Camera>();
+        let mut camera = Camera::new_fps(Viewport {
+            origin: UVec2::ZERO,
+            size: THUMBNAIL_SIZE,
+        });
+        camera.set_projection(view_camera.projection.clone());
+        camera.set_position(view_camera.position());
+        camera.set_orientation(view_camera.orientation());
+        camera.update_matrices();
+        drop(view_camera);
+
+        renderer.capture_view(&camera, &mut map.scene, resources)
+    };
+
+    let path = thumbnail_path(map.hash);
+    let result = result.and_then(|image| {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        image.write(std::fs::File::create(&path)?)
+    });
+    if let Err(e) = result {
+        error!("Failed to save thumbnail for map {}: {e:?}", map.hash);
+    }
+}
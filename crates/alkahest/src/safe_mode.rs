@@ -0,0 +1,172 @@
+//! Safe mode starts Alkahest with the rendering features that are hardest on the GPU disabled and
+//! the window at its minimum size, so users with problematic drivers can still get to the
+//! settings screen.
+//!
+//! Safe mode is enabled with `--safe-mode`, or offered on startup when the last sessions crashed
+//! in a row. The reduced settings only apply to the session: features can be turned back on one
+//! at a time from the configuration window, and settings that still have their safe mode value
+//! are saved with the value they had before.
+
+use alkahest_renderer::renderer::{RenderFeatureVisibility, ShadowQuality};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use winit::dpi::PhysicalSize;
+
+use crate::config::Config;
+
+/// Consecutive crashed sessions after which safe mode is offered
+pub const CRASH_STREAK_PROMPT: u32 = 2;
+
+/// Smallest window size, and with that the smallest render targets
+pub const MINIMUM_WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+lazy_static! {
+    /// Settings from before safe mode was applied
+    static ref ORIGINAL_SETTINGS: Mutex<Option<SafeModeSettings>> = Mutex::new(None);
+}
+
+/// The settings safe mode changes
+#[derive(Clone, Copy)]
+pub struct SafeModeSettings {
+    shadow_quality: ShadowQuality,
+    feature_atmosphere: bool,
+    feature_decorators: u8,
+    feature_cubemaps: bool,
+    ssao: bool,
+    ssgi: bool,
+    ssr: bool,
+    volumetrics: bool,
+    window_size: (u32, u32),
+    maximised: bool,
+    fullscreen: bool,
+}
+
+impl SafeModeSettings {
+    fn safe() -> Self {
+        Self {
+            shadow_quality: ShadowQuality::Off,
+            feature_atmosphere: false,
+            feature_decorators: RenderFeatureVisibility::empty().bits(),
+            feature_cubemaps: false,
+            ssao: false,
+            ssgi: false,
+            ssr: false,
+            volumetrics: false,
+            window_size: (MINIMUM_WINDOW_SIZE.width, MINIMUM_WINDOW_SIZE.height),
+            maximised: false,
+            fullscreen: false,
+        }
+    }
+
+    fn read(config: &Config) -> Self {
+        let renderer = &config.renderer;
+        let window = &config.window;
+        Self {
+            shadow_quality: renderer.shadow_quality,
+            feature_atmosphere: renderer.feature_atmosphere,
+            feature_decorators: renderer.feature_decorators.bits(),
+            feature_cubemaps: renderer.feature_cubemaps,
+            ssao: renderer.ssao,
+            ssgi: renderer.ssgi,
+            ssr: renderer.ssr,
+            volumetrics: renderer.volumetrics,
+            window_size: (window.width, window.height),
+            maximised: window.maximised,
+            fullscreen: window.fullscreen,
+        }
+    }
+
+    fn write(&self, config: &mut Config) {
+        let renderer = &mut config.renderer;
+        renderer.shadow_quality = self.shadow_quality;
+        renderer.feature_atmosphere = self.feature_atmosphere;
+        renderer.feature_decorators =
+            RenderFeatureVisibility::from_bits_truncate(self.feature_decorators);
+        renderer.feature_cubemaps = self.feature_cubemaps;
+        renderer.ssao = self.ssao;
+        renderer.ssgi = self.ssgi;
+        renderer.ssr = self.ssr;
+        renderer.volumetrics = self.volumetrics;
+
+        let window = &mut config.window;
+        (window.width, window.height) = self.window_size;
+        window.maximised = self.maximised;
+        window.fullscreen = self.fullscreen;
+    }
+
+    /// Settings that still have their safe mode value are replaced with their original value
+    fn unchanged_to_original(&self, original: &Self) -> Self {
+        fn pick<T: PartialEq + Copy>(current: T, safe: T, original: T) -> T {
+            if current == safe {
+                original
+            } else {
+                current
+            }
+        }
+
+        let safe = Self::safe();
+        Self {
+            shadow_quality: pick(
+                self.shadow_quality,
+                safe.shadow_quality,
+                original.shadow_quality,
+            ),
+            feature_atmosphere: pick(
+                self.feature_atmosphere,
+                safe.feature_atmosphere,
+                original.feature_atmosphere,
+            ),
+            feature_decorators: pick(
+                self.feature_decorators,
+                safe.feature_decorators,
+                original.feature_decorators,
+            ),
+            feature_cubemaps: pick(
+                self.feature_cubemaps,
+                safe.feature_cubemaps,
+                original.feature_cubemaps,
+            ),
+            ssao: pick(self.ssao, safe.ssao, original.ssao),
+            ssgi: pick(self.ssgi, safe.ssgi, original.ssgi),
+            ssr: pick(self.ssr, safe.ssr, original.ssr),
+            volumetrics: pick(self.volumetrics, safe.volumetrics, original.volumetrics),
+            window_size: pick(self.window_size, safe.window_size, original.window_size),
+            maximised: pick(self.maximised, safe.maximised, original.maximised),
+            fullscreen: pick(self.fullscreen, safe.fullscreen, original.fullscreen),
+        }
+    }
+}
+
+pub fn apply(config: &mut Config) {
+    *ORIGINAL_SETTINGS.lock() = Some(SafeModeSettings::read(config));
+    SafeModeSettings::safe().write(config);
+}
+
+/// Puts back the settings from before safe mode, for saving the config. Returns the settings of
+/// the session, which have to be put back with [`restore_session_settings`] afterwards
+pub fn swap_in_original_settings(config: &mut Config) -> Option<SafeModeSettings> {
+    let original = (*ORIGINAL_SETTINGS.lock())?;
+    let session = SafeModeSettings::read(config);
+    session.unchanged_to_original(&original).write(config);
+    Some(session)
+}
+
+pub fn restore_session_settings(config: &mut Config, session: Option<SafeModeSettings>) {
+    if let Some(session) = session {
+        session.write(config);
+    }
+}
+
+/// Asks whether to start in safe mode, after `crash_streak` sessions crashed in a row
+pub fn prompt(crash_streak: u32) -> bool {
+    native_dialog::MessageDialog::new()
+        .set_title("Alkahest crashed")
+        .set_text(&format!(
+            "Alkahest crashed the last {crash_streak} times it was started.\n\nDo you want to \
+             start in safe mode? Shadows, atmosphere, decorators, SSAO and cubemaps will be \
+             disabled, they can be turned back on from the configuration window."
+        ))
+        .set_type(native_dialog::MessageType::Warning)
+        .show_confirm()
+        .unwrap_or(false)
+}
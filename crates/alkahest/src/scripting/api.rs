@@ -0,0 +1,408 @@
+//! Functions and types exposed to scripts
+
+use alkahest_data::text::StringContainerShared;
+use alkahest_renderer::{
+    camera::Camera,
+    ecs::{
+        common::{Label, Mutable, RenderCommonBundle},
+        hierarchy::{Children, Parent},
+        resources::SelectedEntity,
+        tags::{EntityTag, NodeFilter, Tags},
+        transform::{Transform, TransformFlags},
+        utility::{Beacon, Ruler, Sphere, Utility},
+        visibility::{Visibility, VisibilityHelper},
+        Scene,
+    },
+    resources::AppResources,
+};
+use bevy_ecs::{bundle::Bundle, entity::Entity, world::EntityWorldMut};
+use glam::{EulerRot, Quat, Vec3};
+use itertools::Itertools;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, FLOAT, INT};
+
+use super::with_resources;
+use crate::{
+    gui::{
+        activity_select::get_map_name, console, entity_query::EntityQuery, hotkeys::focus_selected,
+    },
+    maplist::MapList,
+};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct ScriptEntity(pub Entity);
+
+pub fn register(engine: &mut Engine) {
+    register_vec3(engine);
+    register_entity(engine);
+    register_scene(engine);
+    register_camera(engine);
+    register_maps(engine);
+}
+
+fn register_vec3(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Vec3>("Vec3")
+        .register_fn(
+            "vec3",
+            |x: Dynamic, y: Dynamic, z: Dynamic| -> ScriptResult<Vec3> {
+                Ok(Vec3::new(number(&x)?, number(&y)?, number(&z)?))
+            },
+        )
+        .register_get_set(
+            "x",
+            |v: &mut Vec3| v.x as FLOAT,
+            |v: &mut Vec3, x: FLOAT| v.x = x as f32,
+        )
+        .register_get_set(
+            "y",
+            |v: &mut Vec3| v.y as FLOAT,
+            |v: &mut Vec3, y: FLOAT| v.y = y as f32,
+        )
+        .register_get_set(
+            "z",
+            |v: &mut Vec3| v.z as FLOAT,
+            |v: &mut Vec3, z: FLOAT| v.z = z as f32,
+        )
+        .register_fn("+", |a: Vec3, b: Vec3| a + b)
+        .register_fn("-", |a: Vec3, b: Vec3| a - b)
+        .register_fn("-", |v: Vec3| -v)
+        .register_fn("*", |v: Vec3, s: Dynamic| -> ScriptResult<Vec3> {
+            Ok(v * number(&s)?)
+        })
+        .register_fn("/", |v: Vec3, s: Dynamic| -> ScriptResult<Vec3> {
+            Ok(v / number(&s)?)
+        })
+        .register_fn("==", |a: Vec3, b: Vec3| a == b)
+        .register_fn("length", |v: &mut Vec3| v.length() as FLOAT)
+        .register_fn("distance", |a: &mut Vec3, b: Vec3| a.distance(b) as FLOAT)
+        .register_fn("normalize", |v: &mut Vec3| v.normalize_or_zero())
+        .register_fn("dot", |a: &mut Vec3, b: Vec3| a.dot(b) as FLOAT)
+        .register_fn("cross", |a: &mut Vec3, b: Vec3| a.cross(b))
+        .register_fn("to_string", |v: &mut Vec3| format_vec3(*v))
+        .register_fn("to_debug", |v: &mut Vec3| format_vec3(*v));
+}
+
+fn register_entity(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptEntity>("Entity")
+        .register_fn("==", |a: ScriptEntity, b: ScriptEntity| a == b)
+        .register_fn("to_string", |e: &mut ScriptEntity| format!("{:?}", e.0))
+        .register_fn("to_debug", |e: &mut ScriptEntity| format!("{:?}", e.0))
+        .register_get("id", |e: &mut ScriptEntity| e.0.index() as INT)
+        .register_get("exists", |e: &mut ScriptEntity| {
+            with_scene(|scene| Ok(scene.get_entity(e.0).is_some()))
+        })
+        .register_get_set(
+            "label",
+            |e: &mut ScriptEntity| {
+                with_scene(|scene| {
+                    Ok(scene
+                        .get::<Label>(e.0)
+                        .map(|l| l.to_string())
+                        .unwrap_or_default())
+                })
+            },
+            |e: &mut ScriptEntity, label: ImmutableString| {
+                with_scene(|scene| {
+                    entity_mut(scene, e.0)?.insert(Label::from(label.as_str()));
+                    Ok(())
+                })
+            },
+        )
+        .register_get_set(
+            "visible",
+            |e: &mut ScriptEntity| {
+                with_scene(|scene| Ok(scene.get::<Visibility>(e.0).is_visible(0)))
+            },
+            |e: &mut ScriptEntity, visible: bool| {
+                with_scene(|scene| {
+                    entity_mut(scene, e.0)?.insert(if visible {
+                        Visibility::Visible
+                    } else {
+                        Visibility::Hidden
+                    });
+                    Ok(())
+                })
+            },
+        )
+        .register_get_set(
+            "position",
+            |e: &mut ScriptEntity| -> ScriptResult<Vec3> { Ok(transform(e.0)?.translation) },
+            |e: &mut ScriptEntity, position: Vec3| {
+                modify_transform(e.0, |t| t.translation = position)
+            },
+        )
+        .register_get_set(
+            "rotation",
+            |e: &mut ScriptEntity| -> ScriptResult<Vec3> {
+                let (x, y, z) = transform(e.0)?.rotation.to_euler(EulerRot::XYZ);
+                Ok(Vec3::new(x, y, z) * 180.0 / std::f32::consts::PI)
+            },
+            |e: &mut ScriptEntity, degrees: Vec3| {
+                let radians = degrees * std::f32::consts::PI / 180.0;
+                modify_transform(e.0, |t| {
+                    t.rotation = Quat::from_euler(EulerRot::XYZ, radians.x, radians.y, radians.z)
+                })
+            },
+        )
+        .register_get_set(
+            "scale",
+            |e: &mut ScriptEntity| -> ScriptResult<Vec3> { Ok(transform(e.0)?.scale) },
+            |e: &mut ScriptEntity, scale: Vec3| modify_transform(e.0, |t| t.scale = scale),
+        )
+        .register_fn("despawn", |e: &mut ScriptEntity| {
+            with_scene(|scene| despawn(scene, e.0))
+        });
+}
+
+fn register_scene(engine: &mut Engine) {
+    engine
+        .register_fn("entities", || {
+            with_scene(|scene| {
+                Ok(scene
+                    .iter_entities()
+                    .map(|e| Dynamic::from(ScriptEntity(e.id())))
+                    .collect::<Array>())
+            })
+        })
+        .register_fn("query", |query: &str| -> ScriptResult<Array> {
+            let query = EntityQuery::parse(&query.split_whitespace().collect_vec())
+                .map_err(|e| e.to_string())?;
+            with_scene(|scene| {
+                Ok(query
+                    .run(scene)
+                    .into_iter()
+                    .map(|e| Dynamic::from(ScriptEntity(e)))
+                    .collect::<Array>())
+            })
+        })
+        .register_fn("selected", || {
+            with_resources(|resources| {
+                Ok(resources
+                    .get::<SelectedEntity>()
+                    .selected()
+                    .map(|e| Dynamic::from(ScriptEntity(e)))
+                    .unwrap_or(Dynamic::UNIT))
+            })
+        })
+        .register_fn("select", |e: ScriptEntity| {
+            with_resources(|resources| {
+                resources.get_mut::<SelectedEntity>().select(e.0);
+                Ok(())
+            })
+        })
+        .register_fn("deselect", || {
+            with_resources(|resources| {
+                resources.get_mut::<SelectedEntity>().deselect();
+                Ok(())
+            })
+        })
+        .register_fn("spawn_ruler", |start: Vec3, end: Vec3| {
+            spawn_utility((
+                Ruler {
+                    start,
+                    end,
+                    ..Default::default()
+                },
+                Ruler::icon(),
+                Ruler::default_label(),
+            ))
+        })
+        .register_fn(
+            "spawn_sphere",
+            |center: Vec3, radius: Dynamic| -> ScriptResult<ScriptEntity> {
+                spawn_utility((
+                    Transform {
+                        translation: center,
+                        scale: Vec3::splat(number(&radius)?),
+                        flags: TransformFlags::IGNORE_ROTATION | TransformFlags::SCALE_IS_RADIUS,
+                        ..Default::default()
+                    },
+                    Sphere::default(),
+                    Sphere::icon(),
+                    Sphere::default_label(),
+                ))
+            },
+        )
+        .register_fn("spawn_beacon", |position: Vec3| {
+            spawn_utility((
+                Transform {
+                    translation: position,
+                    flags: TransformFlags::IGNORE_ROTATION | TransformFlags::IGNORE_SCALE,
+                    ..Default::default()
+                },
+                Beacon::default(),
+                Beacon::icon(),
+                Beacon::default_label(),
+            ))
+        })
+        .register_fn("command", |line: &str| console::queue_command_line(line));
+}
+
+fn register_camera(engine: &mut Engine) {
+    engine
+        .register_fn("camera_position", || {
+            with_resources(|resources| Ok(resources.get::<Camera>().position()))
+        })
+        .register_fn("set_camera_position", |position: Vec3| {
+            with_resources(|resources| {
+                resources.get_mut::<Camera>().set_position(position);
+                Ok(())
+            })
+        })
+        .register_fn("camera_forward", || {
+            with_resources(|resources| Ok(resources.get::<Camera>().forward()))
+        })
+        .register_fn("look_at", |target: Vec3| {
+            with_resources(|resources| {
+                let mut camera = resources.get_mut::<Camera>();
+                let direction = (target - camera.position()).normalize_or_zero();
+                if direction != Vec3::ZERO {
+                    camera.set_forward(direction);
+                }
+                Ok(())
+            })
+        })
+        .register_fn("focus", |e: ScriptEntity| {
+            with_resources(|resources| {
+                resources.get_mut::<SelectedEntity>().select(e.0);
+                focus_selected(resources);
+                Ok(())
+            })
+        });
+}
+
+fn register_maps(engine: &mut Engine) {
+    engine
+        .register_fn("map_name", || {
+            with_resources(|resources| {
+                Ok(resources
+                    .get::<MapList>()
+                    .current_map()
+                    .map(|m| m.name.clone())
+                    .unwrap_or_default())
+            })
+        })
+        .register_fn("map_hash", || {
+            with_resources(|resources| {
+                Ok(resources
+                    .get::<MapList>()
+                    .current_map()
+                    .map(|m| m.hash.to_string())
+                    .unwrap_or_default())
+            })
+        })
+        .register_fn("maps", || {
+            with_resources(|resources| {
+                Ok(resources
+                    .get::<MapList>()
+                    .maps
+                    .iter()
+                    .map(|m| Dynamic::from(m.name.clone()))
+                    .collect::<Array>())
+            })
+        })
+        .register_fn("load_map", |hash: &str| -> ScriptResult<()> {
+            let map_hash = console::parse_extended_hash(hash)
+                .map_err(|e| e.to_string())?
+                .hash32();
+            with_resources(|resources| {
+                let map_name = get_map_name(map_hash, &resources.get::<StringContainerShared>())
+                    .unwrap_or_else(|_| format!("Unknown map {map_hash}"));
+
+                let mut maplist = resources.get_mut::<MapList>();
+                let index = match maplist.maps.iter().position(|m| m.hash == map_hash) {
+                    Some(index) => index,
+                    None => {
+                        maplist.add_map(resources, map_name, map_hash);
+                        maplist.maps.len() - 1
+                    }
+                };
+                maplist.set_current_map(index);
+                Ok(())
+            })
+        });
+}
+
+/// Scripts use 64-bit numbers, integers are accepted wherever a float is expected
+fn number(value: &Dynamic) -> ScriptResult<f32> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|i| i as FLOAT))
+        .map(|v| v as f32)
+        .map_err(|ty| format!("Expected a number, got {ty}").into())
+}
+
+fn format_vec3(v: Vec3) -> String {
+    format!("vec3({}, {}, {})", v.x, v.y, v.z)
+}
+
+fn with_scene<R>(f: impl FnOnce(&mut Scene) -> ScriptResult<R>) -> ScriptResult<R> {
+    with_resources(|resources| {
+        let mut maps = resources.get_mut::<MapList>();
+        let map = maps.current_map_mut().ok_or("No map is loaded")?;
+        f(&mut map.scene)
+    })
+}
+
+fn entity_mut(scene: &mut Scene, e: Entity) -> ScriptResult<EntityWorldMut<'_>> {
+    scene
+        .get_entity_mut(e)
+        .ok_or_else(|| format!("Entity {e:?} does not exist").into())
+}
+
+fn transform(e: Entity) -> ScriptResult<Transform> {
+    with_scene(|scene| {
+        scene
+            .get::<Transform>(e)
+            .copied()
+            .ok_or_else(|| format!("Entity {e:?} has no transform").into())
+    })
+}
+
+fn modify_transform(e: Entity, f: impl FnOnce(&mut Transform)) -> ScriptResult<()> {
+    with_scene(|scene| {
+        let mut transform = scene
+            .get_mut::<Transform>(e)
+            .ok_or_else(|| format!("Entity {e:?} has no transform"))?;
+        f(&mut transform);
+        Ok(())
+    })
+}
+
+fn spawn_utility(bundle: impl Bundle) -> ScriptResult<ScriptEntity> {
+    with_scene(|scene| {
+        let e = scene.spawn((
+            NodeFilter::Utility,
+            bundle,
+            Tags::from_iter([EntityTag::Utility]),
+            Mutable,
+            RenderCommonBundle::default(),
+        ));
+        Ok(ScriptEntity(e.id()))
+    })
+}
+
+/// Despawns a utility entity and its children. Map entities can't be despawned
+fn despawn(scene: &mut Scene, e: Entity) -> ScriptResult<()> {
+    if scene.get::<Mutable>(e).is_none() {
+        return Err(format!("Entity {e:?} is not a utility and can't be despawned").into());
+    }
+
+    if let Some(children) = scene.get::<Children>(e).cloned() {
+        for child in children.0 {
+            scene.despawn(child);
+        }
+    }
+
+    if let Some(parent) = scene.get::<Parent>(e).map(|p| p.0) {
+        if let Some(mut children) = scene.get_mut::<Children>(parent) {
+            children.0.retain(|child| *child != e);
+        }
+    }
+
+    scene.despawn(e);
+    Ok(())
+}
@@ -0,0 +1,269 @@
+//! Rhai scripting. Scripts can query and modify entities in the current map, spawn utilities,
+//! move the camera and load maps.
+//!
+//! Scripts in `scripts/` are loaded at startup. Functions they define stay available to the
+//! script console and to later scripts, and a function named `on_map_loaded` is called every
+//! time the current map finishes loading.
+
+mod api;
+
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    ptr::NonNull,
+    time::{Duration, Instant},
+};
+
+use alkahest_renderer::resources::AppResources;
+use anyhow::Context;
+use itertools::Itertools;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+/// Directory scripts are autoloaded from
+pub const SCRIPT_DIRECTORY: &str = "scripts";
+
+/// Scripts running longer than this are aborted, so an infinite loop doesn't lock up the UI
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+thread_local! {
+    static RESOURCES: Cell<Option<NonNull<AppResources>>> = const { Cell::new(None) };
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Runs `f` with the resources exposed to script functions through [`with_resources`]
+fn with_resources_scope<R>(resources: &AppResources, f: impl FnOnce() -> R) -> R {
+    let previous_resources = RESOURCES.replace(Some(NonNull::from(resources)));
+    let previous_deadline = DEADLINE.replace(Some(Instant::now() + SCRIPT_TIMEOUT));
+    let result = f();
+    DEADLINE.set(previous_deadline);
+    RESOURCES.set(previous_resources);
+    result
+}
+
+/// Gives script functions access to the resources of the script that is currently running
+pub(crate) fn with_resources<R>(
+    f: impl FnOnce(&AppResources) -> Result<R, Box<EvalAltResult>>,
+) -> Result<R, Box<EvalAltResult>> {
+    match RESOURCES.get() {
+        // SAFETY: The pointer is only set by `with_resources_scope`, which borrows the resources
+        // for as long as the pointer is set
+        Some(resources) => f(unsafe { resources.as_ref() }),
+        None => Err("Scripts can only access the application while they are running".into()),
+    }
+}
+
+pub struct ScriptHost {
+    engine: Engine,
+    scope: Scope<'static>,
+    /// Functions defined by all scripts that have been run so far
+    library: AST,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.on_print(|s| info!(target: "script", "{s}"));
+        engine.on_debug(|s, source, pos| match source {
+            Some(source) => debug!(target: "script", "{source} @ {pos:?} > {s}"),
+            None => debug!(target: "script", "{pos:?} > {s}"),
+        });
+        engine.on_progress(|operations| {
+            let timed_out = operations % 1024 == 0
+                && DEADLINE
+                    .get()
+                    .is_some_and(|deadline| Instant::now() > deadline);
+
+            timed_out.then(|| Dynamic::from(format!("Timed out after {SCRIPT_TIMEOUT:?}")))
+        });
+        api::register(&mut engine);
+
+        Self {
+            engine,
+            scope: Scope::new(),
+            library: AST::empty(),
+        }
+    }
+}
+
+impl ScriptHost {
+    /// Runs a script. Functions defined by the script are kept for later scripts
+    pub fn eval(
+        &mut self,
+        resources: &AppResources,
+        source_name: Option<&str>,
+        code: &str,
+    ) -> anyhow::Result<Dynamic> {
+        let mut ast = self
+            .engine
+            .compile(code)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        if let Some(source_name) = source_name {
+            ast.set_source(source_name);
+        }
+
+        // Only the functions of previous scripts are merged in, their statements have already run
+        let combined = self.library.clone_functions_only().merge(&ast);
+        let result = with_resources_scope(resources, || {
+            self.engine
+                .eval_ast_with_scope::<Dynamic>(&mut self.scope, &combined)
+        })
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+
+        self.library.combine(ast.clone_functions_only());
+        Ok(result)
+    }
+
+    pub fn run_file(&mut self, resources: &AppResources, path: &Path) -> anyhow::Result<Dynamic> {
+        let code = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script {}", path.display()))?;
+
+        self.eval(resources, Some(&path.display().to_string()), &code)
+            .with_context(|| format!("Failed to run script {}", path.display()))
+    }
+
+    /// Calls a function defined by a script
+    pub fn call(
+        &mut self,
+        resources: &AppResources,
+        function: &str,
+        args: Vec<Dynamic>,
+    ) -> anyhow::Result<Dynamic> {
+        with_resources_scope(resources, || {
+            self.engine
+                .call_fn::<Dynamic>(&mut self.scope, &self.library, function, args)
+        })
+        .map_err(|e| anyhow::anyhow!("{e}"))
+    }
+
+    pub fn has_function(&self, function: &str) -> bool {
+        self.library.iter_functions().any(|f| f.name == function)
+    }
+
+    /// Names of the functions defined by scripts
+    pub fn functions(&self) -> Vec<String> {
+        let mut functions = self
+            .library
+            .iter_functions()
+            .filter(|f| !f.name.starts_with('_'))
+            .map(|f| format!("{}({})", f.name, f.params.join(", ")))
+            .collect::<Vec<_>>();
+        functions.sort();
+        functions
+    }
+
+    /// Forgets all script functions and variables and runs the autoload scripts again
+    pub fn reload(&mut self, resources: &AppResources) {
+        self.scope.clear();
+        self.library.clear_functions();
+        self.run_autoload_scripts(resources);
+    }
+
+    pub fn run_autoload_scripts(&mut self, resources: &AppResources) {
+        let scripts = match list_scripts() {
+            Ok(scripts) => scripts,
+            Err(e) => {
+                error!("Failed to list scripts: {e:?}");
+                return;
+            }
+        };
+
+        if !scripts.is_empty() {
+            info!(
+                "Loading {} script(s) from {SCRIPT_DIRECTORY}/",
+                scripts.len()
+            );
+        }
+
+        for path in scripts {
+            if let Err(e) = self.run_file(resources, &path) {
+                error!("{e:?}");
+            }
+        }
+    }
+}
+
+/// Lists the `.rhai` scripts in the script directory, sorted by name
+pub fn list_scripts() -> anyhow::Result<Vec<PathBuf>> {
+    let directory = Path::new(SCRIPT_DIRECTORY);
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut scripts = vec![];
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "rhai") {
+            scripts.push(path);
+        }
+    }
+
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// Resolves a script name given on the console, relative to the script directory
+pub fn resolve_script_path(name: &str) -> PathBuf {
+    let path = Path::new(name);
+    if path.exists() {
+        return path.to_path_buf();
+    }
+
+    let path = Path::new(SCRIPT_DIRECTORY).join(name);
+    if path.extension().is_none() {
+        path.with_extension("rhai")
+    } else {
+        path
+    }
+}
+
+/// Handles the `script.*` console commands
+pub fn run_script_command(command: &str, args: &[&str], resources: &AppResources) {
+    let mut host = resources.get_mut::<ScriptHost>();
+    let result = match command {
+        "script.run" => {
+            if args.is_empty() {
+                error!("Usage: script.run <file>");
+                return;
+            }
+
+            host.run_file(resources, &resolve_script_path(&args.join(" ")))
+        }
+        "script.eval" => host.eval(resources, None, &args.join(" ")),
+        "script.call" => {
+            let Some((function, args)) = args.split_first() else {
+                error!("Usage: script.call <function> [args...]");
+                return;
+            };
+
+            let args = args.iter().map(|a| Dynamic::from(a.to_string())).collect();
+            host.call(resources, function, args)
+        }
+        // Calls a function only if a script defines it, used for the `on_*` hooks
+        "script.hook" => match args.first() {
+            Some(function) if host.has_function(function) => host.call(resources, function, vec![]),
+            _ => return,
+        },
+        "script.reload" => {
+            host.reload(resources);
+            return;
+        }
+        "script.list" => {
+            match list_scripts() {
+                Ok(scripts) => info!(
+                    "Scripts: {}",
+                    scripts.iter().map(|p| p.display().to_string()).join(", ")
+                ),
+                Err(e) => error!("Failed to list scripts: {e:?}"),
+            }
+            info!("Script functions: {}", host.functions().join(", "));
+            return;
+        }
+        _ => unreachable!("Unknown script command '{command}'"),
+    };
+
+    match result {
+        Ok(value) if !value.is_unit() => info!("{value}"),
+        Ok(_) => {}
+        Err(e) => error!("{e:?}"),
+    }
+}
@@ -0,0 +1,369 @@
+//! Technique dumps: writes everything needed to reverse engineer a technique to a folder, so
+//! shaders can be read and diffed without a graphics debugger.
+//!
+//! Every shader stage gets its raw DXBC, the DXBC disassembly, the TFX bytecode disassembly and
+//! an HLSL-like listing combining the resource bindings with the decompiled bytecode.
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use alkahest_data::{
+    technique::{STechnique, STechniqueShader},
+    tfx::TfxShaderStage,
+};
+use alkahest_pm::package_manager;
+use alkahest_renderer::{
+    tfx::bytecode::{decompiler::TfxBytecodeDecompiler, opcodes::TfxBytecodeOp},
+    util::dxbc::{parse_input_signature, read_shader_bytecode},
+};
+use anyhow::{ensure, Context};
+use destiny_pkg::TagHash;
+use glam::Vec4;
+use serde::Serialize;
+use tiger_parse::PackageManagerExt;
+use windows::{
+    core::PCSTR,
+    Win32::Graphics::{Direct3D::Fxc::D3DDisassemble, Direct3D11::D3D11_SAMPLER_DESC},
+};
+
+#[derive(Serialize)]
+struct TechniqueManifest {
+    hash: String,
+    states: TechniqueStates,
+    used_scopes: Vec<String>,
+    compatible_scopes: Vec<String>,
+    stages: Vec<StageManifest>,
+}
+
+#[derive(Serialize)]
+struct TechniqueStates {
+    raw: u32,
+    blend: Option<usize>,
+    depth_stencil: Option<usize>,
+    rasterizer: Option<usize>,
+    depth_bias: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct StageManifest {
+    stage: String,
+    shader: String,
+    /// Files written for this stage, relative to the dump folder
+    files: Vec<String>,
+    textures: Vec<TextureBinding>,
+    samplers: Vec<SamplerBinding>,
+    constant_buffer_slot: i32,
+    constant_buffer: Option<String>,
+    /// Number of float4 elements in the constant buffer
+    constant_buffer_size: usize,
+}
+
+#[derive(Serialize)]
+struct TextureBinding {
+    slot: u32,
+    texture: String,
+}
+
+#[derive(Serialize)]
+struct SamplerBinding {
+    /// Index in the technique's sampler table, as referenced by the TFX bytecode
+    index: usize,
+    hash: String,
+    desc: Option<SamplerDesc>,
+}
+
+/// Plain copy of a `D3D11_SAMPLER_DESC`
+#[derive(Serialize)]
+struct SamplerDesc {
+    filter: i32,
+    address_u: i32,
+    address_v: i32,
+    address_w: i32,
+    mip_lod_bias: f32,
+    max_anisotropy: u32,
+    comparison_func: i32,
+    border_color: [f32; 4],
+    min_lod: f32,
+    max_lod: f32,
+}
+
+impl SamplerDesc {
+    fn read(hash: TagHash) -> anyhow::Result<Self> {
+        let entry = package_manager()
+            .get_entry(hash)
+            .context("Sampler entry not found")?;
+        ensure!(
+            entry.file_type == 34 && entry.file_subtype == 1,
+            "Sampler header type mismatch"
+        );
+        let data = package_manager()
+            .read_tag(entry.reference)
+            .context("Failed to read sampler data")?;
+        ensure!(
+            data.len() >= size_of::<D3D11_SAMPLER_DESC>(),
+            "Sampler data is too small ({} bytes)",
+            data.len()
+        );
+
+        let desc: D3D11_SAMPLER_DESC =
+            unsafe { std::ptr::read_unaligned(data.as_ptr() as *const D3D11_SAMPLER_DESC) };
+        Ok(Self {
+            filter: desc.Filter.0,
+            address_u: desc.AddressU.0,
+            address_v: desc.AddressV.0,
+            address_w: desc.AddressW.0,
+            mip_lod_bias: desc.MipLODBias,
+            max_anisotropy: desc.MaxAnisotropy,
+            comparison_func: desc.ComparisonFunc.0,
+            border_color: desc.BorderColor,
+            min_lod: desc.MinLOD,
+            max_lod: desc.MaxLOD,
+        })
+    }
+}
+
+/// Dumps a technique to `<root>/<hash>/`. Returns the folder the dump was written to.
+///
+/// Stages that fail to disassemble or decompile are still dumped, with the error written in place
+/// of the listing.
+pub fn dump_technique(hash: TagHash, root: &Path) -> anyhow::Result<PathBuf> {
+    let technique: STechnique = package_manager()
+        .read_tag_struct(hash)
+        .with_context(|| format!("Failed to read technique {hash}"))?;
+
+    let directory = root.join(hash.to_string());
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("Failed to create {}", directory.display()))?;
+
+    let mut manifest = TechniqueManifest {
+        hash: hash.to_string(),
+        states: TechniqueStates {
+            raw: technique.states.raw(),
+            blend: technique.states.blend_state(),
+            depth_stencil: technique.states.depth_stencil_state(),
+            rasterizer: technique.states.rasterizer_state(),
+            depth_bias: technique.states.depth_bias_state(),
+        },
+        used_scopes: technique
+            .used_scopes
+            .iter_names()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        compatible_scopes: technique
+            .compatible_scopes
+            .iter_names()
+            .map(|(name, _)| name.to_string())
+            .collect(),
+        stages: vec![],
+    };
+
+    for (stage, shader) in technique.all_valid_shaders() {
+        manifest.stages.push(
+            dump_stage(&directory, hash, stage, shader)
+                .with_context(|| format!("Failed to dump {} stage", stage.short_name()))?,
+        );
+    }
+
+    let manifest_path = directory.join("technique.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    Ok(directory)
+}
+
+fn dump_stage(
+    directory: &Path,
+    technique: TagHash,
+    stage: TfxShaderStage,
+    shader: &STechniqueShader,
+) -> anyhow::Result<StageManifest> {
+    let name = stage.short_name().to_lowercase();
+    let mut files = vec![];
+    let mut write_file = |extension: &str, data: &[u8]| -> anyhow::Result<()> {
+        let file = format!("{name}.{extension}");
+        let path = directory.join(&file);
+        std::fs::write(&path, data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        files.push(file);
+        Ok(())
+    };
+
+    let dxbc = read_shader_bytecode(shader.shader)?;
+    write_file("dxbc", &dxbc)?;
+
+    let disassembly = disassemble_dxbc(&dxbc).unwrap_or_else(|e| format!("// {e:?}\n"));
+    write_file("asm", disassembly.as_bytes())?;
+
+    let constants = if shader.constants.bytecode_constants.is_empty() {
+        &[]
+    } else {
+        bytemuck::cast_slice(&shader.constants.bytecode_constants)
+    };
+
+    let (tfx_disassembly, tfx_decompiled) =
+        match TfxBytecodeOp::parse_all(&shader.constants.bytecode, binrw::Endian::Little) {
+            Ok(opcodes) => (
+                opcodes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, o)| format!("{i}: {}\n", o.disassemble(Some(constants))))
+                    .collect::<String>(),
+                TfxBytecodeDecompiler::decompile(&opcodes, constants)
+                    .map(|e| e.pretty_print())
+                    .unwrap_or_else(|e| format!("// Failed to decompile bytecode: {e}\n")),
+            ),
+            Err(e) => {
+                let error = format!("// Failed to decode TFX bytecode: {e}\n");
+                (error.clone(), error)
+            }
+        };
+    write_file("tfx.txt", tfx_disassembly.as_bytes())?;
+
+    let textures = shader
+        .textures
+        .iter()
+        .map(|t| TextureBinding {
+            slot: t.slot,
+            texture: t.texture.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    let samplers = shader
+        .constants
+        .samplers
+        .iter()
+        .enumerate()
+        .map(|(index, hash)| SamplerBinding {
+            index,
+            hash: hash.to_string(),
+            desc: SamplerDesc::read(hash.hash32())
+                .map_err(|e| warn!("Failed to read sampler {hash}: {e:?}"))
+                .ok(),
+        })
+        .collect::<Vec<_>>();
+
+    let constant_buffer = shader
+        .constants
+        .constant_buffer
+        .is_some()
+        .then_some(shader.constants.constant_buffer);
+    let constant_buffer_size = match constant_buffer {
+        Some(cb) => package_manager()
+            .get_entry(cb)
+            .and_then(|e| package_manager().read_tag(e.reference).ok())
+            .map_or(0, |data| data.len() / size_of::<Vec4>()),
+        None => shader.constants.unk38.len(),
+    };
+
+    let mut listing = String::new();
+    writeln!(
+        &mut listing,
+        "// Technique {technique}, {} shader {}",
+        stage.short_name(),
+        shader.shader
+    )?;
+    writeln!(
+        &mut listing,
+        "// Resource bindings are taken from the technique, expressions from its TFX bytecode\n"
+    )?;
+
+    if let Ok(inputs) = parse_input_signature(&dxbc) {
+        writeln!(&mut listing, "struct {}Input\n{{", stage.short_name())?;
+        for input in inputs {
+            writeln!(
+                &mut listing,
+                "    {} {}{} : {}{};{}",
+                input.hlsl_type(),
+                input.semantic_name.to_lowercase(),
+                input.semantic_index,
+                input.semantic_name,
+                input.semantic_index,
+                if input.is_used() { "" } else { " // unused" }
+            )?;
+        }
+        writeln!(&mut listing, "}};\n")?;
+    }
+
+    if !textures.is_empty() {
+        writeln!(&mut listing, "// Material textures")?;
+        for t in &textures {
+            writeln!(
+                &mut listing,
+                "Texture2D<float4> t{0} : register(t{0}); // {1}",
+                t.slot, t.texture
+            )?;
+        }
+        writeln!(&mut listing)?;
+    }
+
+    if !samplers.is_empty() {
+        writeln!(&mut listing, "// Sampler table")?;
+        for s in &samplers {
+            match &s.desc {
+                Some(d) => writeln!(
+                    &mut listing,
+                    "// [{}] {}: filter={:#x} address={}/{}/{} anisotropy={} lod={}..{}",
+                    s.index,
+                    s.hash,
+                    d.filter,
+                    d.address_u,
+                    d.address_v,
+                    d.address_w,
+                    d.max_anisotropy,
+                    d.min_lod,
+                    d.max_lod
+                )?,
+                None => writeln!(&mut listing, "// [{}] {}: unreadable", s.index, s.hash)?,
+            }
+        }
+        writeln!(&mut listing)?;
+    }
+
+    if constant_buffer_size > 0 {
+        let slot = shader.constants.constant_buffer_slot;
+        let source = constant_buffer.map_or("inline data".to_string(), |cb| cb.to_string());
+        writeln!(
+            &mut listing,
+            "cbuffer cb{slot} : register(b{slot}) // {source}\n{{\n    float4 \
+             cb{slot}[{constant_buffer_size}];\n}};\n"
+        )?;
+    }
+
+    listing.push_str(&tfx_decompiled);
+    write_file("hlsl", listing.as_bytes())?;
+
+    Ok(StageManifest {
+        stage: format!("{stage:?}"),
+        shader: shader.shader.to_string(),
+        files,
+        textures,
+        samplers,
+        constant_buffer_slot: shader.constants.constant_buffer_slot,
+        constant_buffer: constant_buffer.map(|cb| cb.to_string()),
+        constant_buffer_size,
+    })
+}
+
+fn disassemble_dxbc(dxbc: &[u8]) -> anyhow::Result<String> {
+    let blob = unsafe { D3DDisassemble(dxbc.as_ptr() as _, dxbc.len(), 0, PCSTR::null()) }
+        .context("Failed to disassemble DXBC")?;
+    let text = unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    };
+
+    Ok(String::from_utf8_lossy(text)
+        .trim_end_matches('\0')
+        .to_string())
+}
+
+/// Dumps the techniques given with `--dump-technique` and exits
+pub fn run_technique_dump(hashes: &[TagHash], root: &Path) -> anyhow::Result<()> {
+    for &hash in hashes {
+        let directory = dump_technique(hash, root)?;
+        info!("Dumped technique {hash} to {}", directory.display());
+    }
+
+    Ok(())
+}
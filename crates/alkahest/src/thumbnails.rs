@@ -0,0 +1,254 @@
+//! Overview thumbnails of maps, shown in the map list and the activity browser to tell bubbles
+//! apart without loading them.
+//!
+//! A thumbnail is rendered the first time a map finishes loading and cached on disk, unlike the
+//! thumbnails of recent sessions it always shows the whole map from the same angle.
+
+use std::path::PathBuf;
+
+use alkahest_data::text::StringContainerShared;
+use alkahest_renderer::{
+    camera::{Camera, CameraProjection, Viewport},
+    icons::ICON_MAP,
+    renderer::Renderer,
+    resources::AppResources,
+    util::image::Png,
+};
+use destiny_pkg::TagHash;
+use egui::{Color32, Sense, Vec2};
+use glam::{Mat4, UVec2, Vec3};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    gui::{activity_select::query_activity_maps, minimap::map_bounds},
+    maplist::{Map, MapLoadState},
+    paths,
+};
+
+const THUMBNAIL_RESOLUTION: UVec2 = UVec2::new(256, 144);
+/// Number of consecutive frames the asset manager has to be idle before the map is rendered, so
+/// textures streamed in after loading are included
+const SETTLE_FRAMES: usize = 30;
+/// Thumbnails read from disk per frame, so opening a long list doesn't stall the UI
+const LOADS_PER_FRAME: usize = 4;
+/// Vertical field of view of the overview shot, in degrees
+const OVERVIEW_FOV: f32 = 60.0;
+/// Maximum number of map thumbnails shown when hovering an activity
+const MAX_ACTIVITY_THUMBNAILS: usize = 6;
+
+pub const THUMBNAIL_SIZE_SMALL: Vec2 = Vec2::new(48.0, 27.0);
+pub const THUMBNAIL_SIZE_LARGE: Vec2 = Vec2::new(256.0, 144.0);
+
+pub fn overview_thumbnail_path(map: TagHash) -> PathBuf {
+    paths::local_config_dir()
+        .join("thumbnails")
+        .join(format!("{map}.png"))
+}
+
+#[derive(Default)]
+pub struct MapThumbnails {
+    /// Loaded on first use, `None` if the map doesn't have a thumbnail
+    textures: FxHashMap<TagHash, Option<egui::TextureHandle>>,
+    /// Maps that have a thumbnail on disk, or that failed to render one this session
+    rendered: FxHashSet<TagHash>,
+    /// Maps of every activity that was hovered in the activity browser
+    activity_maps: FxHashMap<TagHash, Vec<TagHash>>,
+    /// Frame number and the number of thumbnails loaded during it
+    loads: (u64, usize),
+    settled_frames: usize,
+}
+
+impl MapThumbnails {
+    /// Returns the thumbnail of `map`, reading it from disk if it hasn't been loaded yet
+    pub fn get(&mut self, ctx: &egui::Context, map: TagHash) -> Option<egui::TextureHandle> {
+        if let Some(texture) = self.textures.get(&map) {
+            return texture.clone();
+        }
+
+        let frame = ctx.frame_nr();
+        if self.loads.0 != frame {
+            self.loads = (frame, 0);
+        }
+        if self.loads.1 >= LOADS_PER_FRAME {
+            ctx.request_repaint();
+            return None;
+        }
+        self.loads.1 += 1;
+
+        let texture = std::fs::read(overview_thumbnail_path(map))
+            .ok()
+            .and_then(|data| {
+                Png::from_bytes(&data)
+                    .and_then(Png::into_rgba)
+                    .map_err(|e| warn!("Failed to read thumbnail for map {map}: {e:?}"))
+                    .ok()
+            })
+            .map(|image| {
+                ctx.load_texture(
+                    format!("overview_thumbnail_{map}"),
+                    egui::ColorImage::from_rgba_unmultiplied(image.dimensions, &image.data),
+                    egui::TextureOptions::LINEAR,
+                )
+            });
+
+        self.textures.insert(map, texture.clone());
+        texture
+    }
+
+    /// Draws the thumbnail of `map`, or a placeholder if it doesn't have one
+    pub fn show(&mut self, ui: &mut egui::Ui, map: TagHash, size: Vec2) -> egui::Response {
+        let texture = self.get(ui.ctx(), map);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        match texture {
+            Some(texture) => {
+                ui.painter().image(
+                    texture.id(),
+                    rect,
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    Color32::WHITE,
+                );
+            }
+            None => {
+                ui.painter().rect_filled(rect, 2.0, Color32::from_gray(32));
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    ICON_MAP,
+                    egui::FontId::proportional(size.y * 0.6),
+                    Color32::from_gray(96),
+                );
+            }
+        }
+
+        response
+    }
+
+    /// Shows the thumbnails of the maps in `activity` when `response` is hovered. Activities
+    /// without any thumbnails show nothing
+    pub fn on_hover_activity(
+        &mut self,
+        response: egui::Response,
+        resources: &AppResources,
+        activity: TagHash,
+    ) -> egui::Response {
+        if !response.hovered() {
+            return response;
+        }
+
+        let maps = self
+            .activity_maps
+            .entry(activity)
+            .or_insert_with(|| {
+                let stringmap = resources.get::<StringContainerShared>();
+                query_activity_maps(activity, &stringmap)
+                    .map(|maps| maps.into_iter().map(|(hash, _)| hash).collect())
+                    .unwrap_or_default()
+            })
+            .clone();
+
+        let ctx = response.ctx.clone();
+        let thumbnails = maps
+            .iter()
+            .filter_map(|&map| self.get(&ctx, map))
+            .take(MAX_ACTIVITY_THUMBNAILS)
+            .collect::<Vec<_>>();
+        if thumbnails.is_empty() {
+            return response;
+        }
+
+        response.on_hover_ui(|ui| {
+            egui::Grid::new("activity_thumbnails").show(ui, |ui| {
+                for (i, texture) in thumbnails.iter().enumerate() {
+                    ui.image((texture.id(), THUMBNAIL_SIZE_LARGE * 0.5));
+                    if i % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+        })
+    }
+
+    fn has_thumbnail(&mut self, map: TagHash) -> bool {
+        if self.rendered.contains(&map) {
+            return true;
+        }
+
+        let exists = overview_thumbnail_path(map).exists();
+        if exists {
+            self.rendered.insert(map);
+        }
+        exists
+    }
+}
+
+/// Renders an overview thumbnail of the current map the first time it finishes loading
+pub fn thumbnail_capture_step(renderer: &Renderer, map: &mut Map, resources: &AppResources) {
+    if map.hash.is_none() || map.load_state != MapLoadState::Loaded {
+        return;
+    }
+
+    {
+        let mut thumbnails = resources.get_mut::<MapThumbnails>();
+        if thumbnails.has_thumbnail(map.hash) {
+            return;
+        }
+
+        let settled = renderer.data.lock().asset_manager.is_idle();
+        thumbnails.settled_frames = if settled {
+            thumbnails.settled_frames + 1
+        } else {
+            0
+        };
+        if thumbnails.settled_frames < SETTLE_FRAMES {
+            return;
+        }
+
+        thumbnails.settled_frames = 0;
+        // Don't retry maps that fail to render every frame
+        thumbnails.rendered.insert(map.hash);
+    }
+
+    // Looking at the center of the map from above at an angle, far enough away to fit all of it
+    let (min, max) = map_bounds(&mut map.scene);
+    let center = (min + max) / 2.0;
+    let radius = (max - min).length() / 2.0;
+    let distance = radius / (OVERVIEW_FOV.to_radians() / 2.0).tan();
+    let eye = center + Vec3::new(-0.5, -0.6, 0.6).normalize() * distance;
+
+    let mut camera = Camera::new_fps(Viewport {
+        origin: UVec2::ZERO,
+        size: THUMBNAIL_RESOLUTION,
+    });
+    camera.set_projection(CameraProjection::Perspective {
+        fov: OVERVIEW_FOV,
+        near: 0.01,
+    });
+    camera.set_position(eye);
+    camera.update_matrices();
+    camera.set_view_matrix(Mat4::look_at_rh(eye, center, Vec3::Z));
+
+    let path = overview_thumbnail_path(map.hash);
+    let result = renderer
+        .capture_view(&camera, &mut map.scene, resources)
+        .and_then(|image| {
+            if let Some(directory) = path.parent() {
+                std::fs::create_dir_all(directory)?;
+            }
+            image.write(std::fs::File::create(&path)?)
+        });
+
+    match result {
+        Ok(()) => {
+            info!("Saved overview thumbnail for map {}", map.hash);
+            // Reload the texture if the map was shown without a thumbnail before
+            resources
+                .get_mut::<MapThumbnails>()
+                .textures
+                .remove(&map.hash);
+        }
+        Err(e) => error!(
+            "Failed to save overview thumbnail for map {}: {e:?}",
+            map.hash
+        ),
+    }
+}
@@ -1,5 +1,6 @@
 use std::io::Cursor;
 
+use alkahest_renderer::util::background_errors::report_background_error;
 use anyhow::Context;
 use poll_promise::Promise;
 use serde::{Deserialize, Serialize};
@@ -193,7 +194,11 @@ impl UpdateCheck {
     pub fn start(&mut self, channel: UpdateChannel) {
         self.0 = Some(Promise::spawn_async(async move {
             channel.check_for_updates().await.unwrap_or_else(|e| {
-                error!("Failed to check for updates: {:?}", e);
+                report_background_error(
+                    "Updater",
+                    format!("Failed to check for updates: {e:?}"),
+                    Some("update.check".to_string()),
+                );
                 None
             })
         }));
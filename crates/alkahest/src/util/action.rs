@@ -229,8 +229,9 @@ impl Action for SpawnRouteAction {
                     RenderCommonBundle::default(),
                 ));
                 resources.get_mut::<SelectedEntity>().select(parent);
-                if let Some(route) = map.scene.get::<Route>(parent) {
-                    route.fixup_visiblity(&map.scene, &mut map.commands(), parent);
+                let (scene, mut commands) = map.scene_and_commands();
+                if let Some(route) = scene.get::<Route>(parent) {
+                    route.fixup_visiblity(scene, &mut commands, parent);
                 }
             }
         }
@@ -0,0 +1,82 @@
+//! Minimal DDS writer for uncompressed cubemaps (DX10 header, single mip level)
+
+use std::io::Write;
+
+const DDS_MAGIC: u32 = 0x2053_4444;
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xfe00;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D11_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Writes a cubemap from 6 faces in D3D order (+X, -X, +Y, -Y, +Z, -Z).
+///
+/// `format` is the raw DXGI format of the face data, `bytes_per_pixel` its size.
+pub fn write_dds_cubemap<W: Write>(
+    mut writer: W,
+    size: u32,
+    format: u32,
+    bytes_per_pixel: u32,
+    faces: &[Vec<u8>; 6],
+) -> anyhow::Result<()> {
+    let face_size = (size * size * bytes_per_pixel) as usize;
+    for (i, face) in faces.iter().enumerate() {
+        anyhow::ensure!(
+            face.len() == face_size,
+            "Face {i} is {} bytes, expected {face_size}",
+            face.len()
+        );
+    }
+
+    let mut header = Vec::with_capacity(4 + 124 + 20);
+    let mut put = |v: u32| header.extend_from_slice(&v.to_le_bytes());
+    put(DDS_MAGIC);
+    put(124);
+    put(DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT);
+    put(size);
+    put(size);
+    put(size * bytes_per_pixel);
+    // Depth, mip count
+    put(0);
+    put(1);
+    // Reserved
+    for _ in 0..11 {
+        put(0);
+    }
+
+    // Pixel format, the actual format is in the DX10 header
+    put(32);
+    put(DDPF_FOURCC);
+    put(u32::from_le_bytes(*b"DX10"));
+    for _ in 0..5 {
+        put(0);
+    }
+
+    put(DDSCAPS_COMPLEX | DDSCAPS_TEXTURE);
+    put(DDSCAPS2_CUBEMAP_ALL_FACES);
+    // Caps3, caps4, reserved
+    for _ in 0..3 {
+        put(0);
+    }
+
+    // DX10 header
+    put(format);
+    put(D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+    put(D3D11_RESOURCE_MISC_TEXTURECUBE);
+    // Array size (number of cubes), alpha mode
+    put(1);
+    put(0);
+
+    writer.write_all(&header)?;
+    for face in faces {
+        writer.write_all(face)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -1,25 +1,27 @@
-use std::io::Write;
-
 use anyhow::Context;
-use fs_err::File;
 
 use super::error::ErrorAlert;
 
-pub fn save_dds_dialog(data: &[u8], filename: String) {
-    let data = data.to_vec();
+/// Asks where to save `data` and writes it there. The dialog runs on its own thread, so it
+/// doesn't block the UI
+pub fn save_file_dialog(
+    data: Vec<u8>,
+    filter_name: &'static str,
+    extension: &'static str,
+    filename: String,
+) {
     std::thread::spawn(move || {
         let dialog_result = native_dialog::FileDialog::new()
-            .add_filter("DirectX Texture", &["dds"])
-            .set_filename(&format!("{filename}.dds"))
+            .add_filter(filter_name, &[extension])
+            .set_filename(&format!("{filename}.{extension}"))
             .show_save_single_file()
             .unwrap();
 
         if let Some(path) = dialog_result {
-            let mut f = File::create(path)
-                .context("Failed to create DDS file")
+            fs_err::write(path, data)
+                .with_context(|| format!("Failed to write {filter_name} file"))
                 .err_alert()
-                .unwrap();
-            f.write_all(&data).unwrap();
+                .ok();
         }
     });
 }
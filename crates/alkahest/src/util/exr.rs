@@ -0,0 +1,106 @@
+//! Minimal OpenEXR writer (single part, scanline, uncompressed, 32-bit float channels)
+
+use std::io::Write;
+
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+/// Writes an EXR image from planar channel data.
+///
+/// Each channel is a `(name, samples)` pair with `width * height` samples in row-major order.
+pub fn write_exr<W: Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    channels: &[(&str, Vec<f32>)],
+) -> anyhow::Result<()> {
+    anyhow::ensure!(!channels.is_empty(), "EXR images need at least one channel");
+    let pixel_count = width as usize * height as usize;
+    for (name, samples) in channels {
+        anyhow::ensure!(
+            samples.len() == pixel_count,
+            "Channel {name} has {} samples, expected {pixel_count}",
+            samples.len()
+        );
+    }
+
+    // Channels have to be stored in alphabetical order
+    let mut channels = channels.iter().collect::<Vec<_>>();
+    channels.sort_by_key(|(name, _)| *name);
+
+    let mut header = vec![];
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut chlist = vec![];
+    for (name, _) in &channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        // pLinear + reserved
+        chlist.extend_from_slice(&[0; 4]);
+        // x/y sampling
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+
+    let window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    write_attribute(&mut header, "channels", "chlist", &chlist);
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    writer.write_all(&header)?;
+
+    // Offset table, one uncompressed scanline per block
+    let line_size = width as usize * 4 * channels.len();
+    let block_size = 8 + line_size;
+    let first_block = header.len() + height as usize * 8;
+    for y in 0..height as usize {
+        writer.write_all(&((first_block + y * block_size) as u64).to_le_bytes())?;
+    }
+
+    let mut line = Vec::with_capacity(line_size);
+    for y in 0..height as usize {
+        line.clear();
+        for (_, samples) in &channels {
+            let row = &samples[y * width as usize..(y + 1) * width as usize];
+            line.extend(row.iter().flat_map(|v| v.to_le_bytes()));
+        }
+
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line_size as i32).to_le_bytes())?;
+        writer.write_all(&line)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(ty.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
@@ -0,0 +1,48 @@
+//! Minimal Radiance HDR (.hdr) writer (RGBE, uncompressed scanlines)
+
+use std::io::Write;
+
+/// Writes an RGBE image from linear RGB pixels in row-major order, top row first
+pub fn write_hdr<W: Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 4]],
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        pixels.len() == width as usize * height as usize,
+        "Image has {} pixels, expected {}",
+        pixels.len(),
+        width as usize * height as usize
+    );
+
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n"
+    )?;
+
+    let data = pixels.iter().flat_map(|p| to_rgbe(p)).collect::<Vec<u8>>();
+    writer.write_all(&data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Shared exponent encoding, as described in Graphics Gems II
+fn to_rgbe(pixel: &[f32; 4]) -> [u8; 4] {
+    let [r, g, b] =
+        [pixel[0], pixel[1], pixel[2]].map(|c| if c.is_finite() { c.max(0.0) } else { 0.0 });
+    let max = r.max(g).max(b);
+    if max < 1e-32 {
+        return [0; 4];
+    }
+
+    // frexp: max = mantissa * 2^exponent, with mantissa in [0.5, 1)
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2f32.powi(exponent);
+    [
+        (r * scale).min(255.0) as u8,
+        (g * scale).min(255.0) as u8,
+        (b * scale).min(255.0) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
@@ -1,12 +1,18 @@
 pub mod changelog_diff;
 pub mod consts;
 // pub mod dds;
-pub mod error;
-// pub mod export;
 pub mod action;
+pub mod dds_cubemap;
+pub mod error;
+pub mod export;
+pub mod exr;
+pub mod hdr;
 pub mod image;
 pub mod iron;
+pub mod tag_info;
+pub mod tag_layout;
 pub mod text;
+pub mod window_placement;
 
 pub use parking_lot::RwLock;
 use tiger_parse::FnvHash;
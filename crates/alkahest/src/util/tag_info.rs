@@ -0,0 +1,102 @@
+//! Central resolver for the provenance of tag hashes (package, entry, reference type), shared by
+//! every part of the UI that displays a tag hash
+
+use std::sync::Arc;
+
+use alkahest_pm::{is_pkg_redacted, package_manager};
+use destiny_pkg::{TagHash, TagHash64};
+use lazy_static::lazy_static;
+use rustc_hash::FxHashMap;
+
+use crate::util::RwLock;
+
+pub struct TagInfo {
+    pub hash: TagHash,
+    /// 64-bit hash that resolves to this tag, if any
+    pub hash64: Option<TagHash64>,
+    pub package_name: Option<String>,
+    pub package_id: u16,
+    pub entry_index: u16,
+    /// Reference (class) hash of the entry, None if the entry doesn't exist
+    pub reference: Option<u32>,
+    pub file_type: Option<(u8, u8)>,
+    pub redacted: bool,
+}
+
+impl TagInfo {
+    pub fn exists(&self) -> bool {
+        self.reference.is_some()
+    }
+}
+
+lazy_static! {
+    static ref CACHE: RwLock<FxHashMap<TagHash, Arc<TagInfo>>> = RwLock::new(FxHashMap::default());
+    /// Reverse lookup of the package manager's 64-bit hash table, built on first use
+    static ref HASH64_LOOKUP: RwLock<Option<Arc<FxHashMap<TagHash, u64>>>> = RwLock::new(None);
+}
+
+/// Forgets everything resolved so far, for when the package manager has been replaced
+pub fn clear_cache() {
+    CACHE.write().clear();
+    *HASH64_LOOKUP.write() = None;
+}
+
+fn hash64_lookup() -> Arc<FxHashMap<TagHash, u64>> {
+    if let Some(lookup) = HASH64_LOOKUP.read().as_ref() {
+        return lookup.clone();
+    }
+
+    let lookup: Arc<FxHashMap<TagHash, u64>> = Arc::new(
+        package_manager()
+            .lookup
+            .tag64_entries
+            .iter()
+            .map(|(hash64, entry)| (entry.hash32, *hash64))
+            .collect(),
+    );
+    *HASH64_LOOKUP.write() = Some(lookup.clone());
+    lookup
+}
+
+/// Resolves (and caches) the provenance of a tag hash
+pub fn resolve(hash: TagHash) -> Arc<TagInfo> {
+    if let Some(info) = CACHE.read().get(&hash) {
+        return info.clone();
+    }
+
+    let info = Arc::new(resolve_uncached(hash));
+    CACHE.write().insert(hash, info.clone());
+    info
+}
+
+fn resolve_uncached(hash: TagHash) -> TagInfo {
+    // Hashes below this aren't package references, and would underflow the package ID
+    if hash.is_none() || hash.0 < 0x80800000 {
+        return TagInfo {
+            hash,
+            hash64: None,
+            package_name: None,
+            package_id: 0,
+            entry_index: 0,
+            reference: None,
+            file_type: None,
+            redacted: false,
+        };
+    }
+
+    let pm = package_manager();
+    let entry = pm.get_entry(hash);
+    TagInfo {
+        hash,
+        hash64: hash64_lookup().get(&hash).map(|&h| TagHash64(h)),
+        package_name: pm
+            .package_paths
+            .get(&hash.pkg_id())
+            .map(|p| format!("{}_{}", p.name, p.id)),
+        package_id: hash.pkg_id(),
+        entry_index: hash.entry_index(),
+        reference: entry.as_ref().map(|e| e.reference),
+        file_type: entry.as_ref().map(|e| (e.file_type, e.file_subtype)),
+        redacted: is_pkg_redacted(hash),
+    }
+}
@@ -0,0 +1,472 @@
+//! Byte layouts of known tag structures, used by the hex viewer to annotate raw tag data.
+//!
+//! tiger-parse doesn't expose field offsets at runtime, so the layouts here mirror the structures
+//! in `alkahest-data` by hand. Keep them in sync when a structure changes.
+
+use std::ops::Range;
+
+use alkahest_pm::package_manager;
+use anyhow::Context;
+use destiny_pkg::TagHash;
+
+/// Maximum number of array elements that get annotated, so huge arrays don't flood the viewer
+const MAX_ANNOTATED_ELEMENTS: usize = 1024;
+
+#[derive(Clone, Copy)]
+pub enum FieldKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    /// Multiple consecutive floats (vectors, quaternions)
+    Floats(usize),
+    TagHash,
+    /// `Vec<T>`: a 64-bit element count followed by a 64-bit offset to the array. The offset is
+    /// relative to the offset field itself, and points at a 16-byte header preceding the elements
+    Array(Option<&'static StructLayout>),
+    Bytes(usize),
+}
+
+impl FieldKind {
+    pub const fn size(&self) -> usize {
+        match self {
+            FieldKind::U8 => 1,
+            FieldKind::U16 => 2,
+            FieldKind::U32 | FieldKind::F32 | FieldKind::TagHash => 4,
+            FieldKind::U64 => 8,
+            FieldKind::Floats(n) => n * 4,
+            FieldKind::Array(_) => 16,
+            FieldKind::Bytes(n) => *n,
+        }
+    }
+}
+
+pub struct FieldLayout {
+    pub offset: usize,
+    pub name: &'static str,
+    pub kind: FieldKind,
+}
+
+const fn field(offset: usize, name: &'static str, kind: FieldKind) -> FieldLayout {
+    FieldLayout { offset, name, kind }
+}
+
+pub struct StructLayout {
+    pub name: &'static str,
+    /// Reference (class) hash of tags containing this structure
+    pub class: u32,
+    /// Size of a single element, used as the stride when the structure is in an array
+    pub size: usize,
+    pub fields: &'static [FieldLayout],
+}
+
+static TAG_HASH: StructLayout = StructLayout {
+    name: "TagHash",
+    class: 0xFFFFFFFF,
+    size: 4,
+    fields: &[field(0, "", FieldKind::TagHash)],
+};
+
+static S_STATIC_MESH: StructLayout = StructLayout {
+    name: "SStaticMesh",
+    class: 0x80806D44,
+    size: 0x70,
+    fields: &[
+        field(0x0, "file_size", FieldKind::U64),
+        field(0x8, "opaque_meshes", FieldKind::TagHash),
+        field(0xC, "unkc", FieldKind::U32),
+        field(0x10, "techniques", FieldKind::Array(Some(&TAG_HASH))),
+        field(
+            0x20,
+            "special_meshes",
+            FieldKind::Array(Some(&S_STATIC_SPECIAL_MESH)),
+        ),
+        field(0x30, "unk30", FieldKind::Bytes(8)),
+        field(0x38, "unk38", FieldKind::Floats(6)),
+        field(0x50, "unk50", FieldKind::Bytes(16)),
+        field(0x60, "unk60", FieldKind::Bytes(16)),
+    ],
+};
+
+static S_STATIC_MESH_DATA: StructLayout = StructLayout {
+    name: "SStaticMeshData",
+    class: 0x80806D30,
+    size: 0x60,
+    fields: &[
+        field(0x0, "file_size", FieldKind::U64),
+        field(
+            0x8,
+            "mesh_groups",
+            FieldKind::Array(Some(&S_STATIC_MESH_GROUP)),
+        ),
+        field(0x18, "parts", FieldKind::Array(Some(&S_STATIC_MESH_PART))),
+        field(
+            0x28,
+            "buffers",
+            FieldKind::Array(Some(&STATIC_MESH_BUFFERS)),
+        ),
+        field(0x38, "unk38", FieldKind::U32),
+        field(0x40, "mesh_offset", FieldKind::Floats(3)),
+        field(0x4C, "mesh_scale", FieldKind::F32),
+        field(0x50, "texture_coordinate_scale", FieldKind::F32),
+        field(0x54, "texture_coordinate_offset", FieldKind::Floats(2)),
+        field(0x5C, "max_color_index", FieldKind::U32),
+    ],
+};
+
+static STATIC_MESH_BUFFERS: StructLayout = StructLayout {
+    name: "(TagHash, TagHash, TagHash, TagHash)",
+    class: 0xFFFFFFFF,
+    size: 0x10,
+    fields: &[
+        field(0x0, "index_buffer", FieldKind::TagHash),
+        field(0x4, "vertex0_buffer", FieldKind::TagHash),
+        field(0x8, "vertex1_buffer", FieldKind::TagHash),
+        field(0xC, "color_buffer", FieldKind::TagHash),
+    ],
+};
+
+static S_STATIC_MESH_PART: StructLayout = StructLayout {
+    name: "SStaticMeshPart",
+    class: 0x80806D37,
+    size: 0xC,
+    fields: &[
+        field(0x0, "index_start", FieldKind::U32),
+        field(0x4, "index_count", FieldKind::U32),
+        field(0x8, "buffer_index", FieldKind::U8),
+        field(0x9, "unk9", FieldKind::U8),
+        field(0xA, "lod_category", FieldKind::U8),
+        field(0xB, "primitive_type", FieldKind::U8),
+    ],
+};
+
+static S_STATIC_MESH_GROUP: StructLayout = StructLayout {
+    name: "SStaticMeshGroup",
+    class: 0x80806D38,
+    size: 0x6,
+    fields: &[
+        field(0x0, "part_index", FieldKind::U16),
+        field(0x2, "render_stage", FieldKind::U8),
+        field(0x3, "input_layout_index", FieldKind::U8),
+        field(0x4, "unk5", FieldKind::U8),
+        field(0x5, "unk6", FieldKind::U8),
+    ],
+};
+
+static S_STATIC_SPECIAL_MESH: StructLayout = StructLayout {
+    name: "SStaticSpecialMesh",
+    class: 0x80806D2F,
+    size: 0x24,
+    fields: &[
+        field(0x0, "render_stage", FieldKind::U8),
+        field(0x1, "input_layout_index", FieldKind::U8),
+        field(0x2, "lod", FieldKind::U8),
+        field(0x3, "unk3", FieldKind::U8),
+        field(0x4, "primitive_type", FieldKind::U8),
+        field(0x5, "unk5", FieldKind::U8),
+        field(0x6, "unk6", FieldKind::U16),
+        field(0x8, "index_buffer", FieldKind::TagHash),
+        field(0xC, "vertex0_buffer", FieldKind::TagHash),
+        field(0x10, "vertex1_buffer", FieldKind::TagHash),
+        field(0x14, "color_buffer", FieldKind::TagHash),
+        field(0x18, "index_start", FieldKind::U32),
+        field(0x1C, "index_count", FieldKind::U32),
+        field(0x20, "technique", FieldKind::TagHash),
+    ],
+};
+
+static S_STATIC_MESH_INSTANCES: StructLayout = StructLayout {
+    name: "SStaticMeshInstances",
+    class: 0x808093AD,
+    size: 0x98,
+    fields: &[
+        field(0x0, "file_size", FieldKind::U64),
+        field(0x18, "occlusion_bounds", FieldKind::TagHash),
+        field(
+            0x40,
+            "transforms",
+            FieldKind::Array(Some(&S_STATIC_INSTANCE_TRANSFORM)),
+        ),
+        field(0x50, "unk50", FieldKind::U64),
+        field(0x58, "unk58", FieldKind::Bytes(0x20)),
+        field(0x78, "statics", FieldKind::Array(Some(&TAG_HASH))),
+        field(
+            0x88,
+            "instance_groups",
+            FieldKind::Array(Some(&S_STATIC_MESH_INSTANCE_GROUP)),
+        ),
+    ],
+};
+
+static S_STATIC_MESH_INSTANCE_GROUP: StructLayout = StructLayout {
+    name: "SStaticMeshInstanceGroup",
+    class: 0x80806D28,
+    size: 0x8,
+    fields: &[
+        field(0x0, "instance_count", FieldKind::U16),
+        field(0x2, "instance_start", FieldKind::U16),
+        field(0x4, "static_index", FieldKind::U16),
+        field(0x6, "unk6", FieldKind::U16),
+    ],
+};
+
+static S_STATIC_INSTANCE_TRANSFORM: StructLayout = StructLayout {
+    name: "SStaticInstanceTransform",
+    class: 0x80806D40,
+    size: 0x40,
+    fields: &[
+        field(0x0, "rotation", FieldKind::Floats(4)),
+        field(0x10, "translation", FieldKind::Floats(3)),
+        field(0x1C, "scale", FieldKind::Floats(3)),
+        field(0x28, "unk28", FieldKind::U32),
+        field(0x2C, "unk2c", FieldKind::U32),
+        field(0x30, "unk30", FieldKind::Bytes(16)),
+    ],
+};
+
+static S_OCCLUSION_BOUNDS: StructLayout = StructLayout {
+    name: "SOcclusionBounds",
+    class: 0x808093B1,
+    size: 0x18,
+    fields: &[
+        field(0x0, "file_size", FieldKind::U64),
+        field(
+            0x8,
+            "bounds",
+            FieldKind::Array(Some(&S_OBJECT_OCCLUSION_BOUNDS)),
+        ),
+    ],
+};
+
+static S_OBJECT_OCCLUSION_BOUNDS: StructLayout = StructLayout {
+    name: "SObjectOcclusionBounds",
+    class: 0x808093B3,
+    size: 0x30,
+    fields: &[
+        field(0x0, "bb", FieldKind::Bytes(0x20)),
+        field(0x20, "unk20", FieldKind::Bytes(16)),
+    ],
+};
+
+/// Structures that can be the root of a tag
+pub static ROOT_LAYOUTS: &[&StructLayout] = &[
+    &S_STATIC_MESH,
+    &S_STATIC_MESH_DATA,
+    &S_STATIC_MESH_INSTANCES,
+    &S_OCCLUSION_BOUNDS,
+];
+
+pub fn find_root_layout(class: u32) -> Option<&'static StructLayout> {
+    ROOT_LAYOUTS.iter().copied().find(|l| l.class == class)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnnotationKind {
+    Field,
+    ArrayHeader,
+    /// A hash that resolves to an existing tag
+    TagReference(TagHash),
+}
+
+pub struct Annotation {
+    pub range: Range<usize>,
+    /// Field path, eg. `parts[3].index_count`
+    pub label: String,
+    pub value: String,
+    pub kind: AnnotationKind,
+}
+
+/// Annotates `data` as an instance of `layout`, following arrays into their elements.
+/// Annotations are sorted by offset
+///
+/// Fails if an array points outside of the addressable range, which usually means `layout` is
+/// the wrong structure for the data
+pub fn annotate(data: &[u8], layout: &StructLayout) -> anyhow::Result<Vec<Annotation>> {
+    let mut annotations = vec![];
+    annotate_struct(data, layout, 0, "", &mut annotations)?;
+    annotations.sort_by_key(|a| a.range.start);
+    Ok(annotations)
+}
+
+fn annotate_struct(
+    data: &[u8],
+    layout: &StructLayout,
+    base: usize,
+    prefix: &str,
+    out: &mut Vec<Annotation>,
+) -> anyhow::Result<()> {
+    for f in layout.fields {
+        let offset = checked_offset(base, f.offset)?;
+        let range = offset..checked_offset(offset, f.kind.size())?;
+        let Some(bytes) = data.get(range.clone()) else {
+            continue;
+        };
+
+        let label = match (prefix.is_empty(), f.name.is_empty()) {
+            (true, _) => f.name.to_string(),
+            (false, true) => prefix.to_string(),
+            (false, false) => format!("{prefix}.{}", f.name),
+        };
+
+        let kind = match f.kind {
+            FieldKind::TagHash => {
+                let hash = TagHash(read_u32(bytes, 0));
+                if is_tag_reference(hash) {
+                    AnnotationKind::TagReference(hash)
+                } else {
+                    AnnotationKind::Field
+                }
+            }
+            _ => AnnotationKind::Field,
+        };
+
+        if let FieldKind::Array(element) = f.kind {
+            annotate_array(data, offset, &label, element, out)?;
+        }
+
+        out.push(Annotation {
+            range,
+            value: format_value(bytes, f.kind),
+            label,
+            kind,
+        });
+    }
+
+    Ok(())
+}
+
+fn annotate_array(
+    data: &[u8],
+    offset: usize,
+    label: &str,
+    element: Option<&StructLayout>,
+    out: &mut Vec<Annotation>,
+) -> anyhow::Result<()> {
+    let count = read_u64(data, offset) as usize;
+    let offset_field = checked_offset(offset, 8)?;
+    let relative_offset = read_u64(data, offset_field) as usize;
+    let header = checked_offset(offset_field, relative_offset)
+        .with_context(|| format!("Array {label} points outside of the tag"))?;
+    let start = checked_offset(header, 16)?;
+    if count == 0 || start > data.len() {
+        return Ok(());
+    }
+
+    out.push(Annotation {
+        range: header..start,
+        label: format!("{label} (array header)"),
+        value: format!("class {:08X}", read_u32(data, checked_offset(header, 8)?)),
+        kind: AnnotationKind::ArrayHeader,
+    });
+
+    let Some(element) = element else {
+        return Ok(());
+    };
+
+    for i in 0..count.min(MAX_ANNOTATED_ELEMENTS) {
+        let element_offset = checked_offset(start, i * element.size)?;
+        if checked_offset(element_offset, element.size)? > data.len() {
+            break;
+        }
+
+        annotate_struct(data, element, element_offset, &format!("{label}[{i}]"), out)?;
+    }
+
+    Ok(())
+}
+
+fn checked_offset(offset: usize, add: usize) -> anyhow::Result<usize> {
+    offset
+        .checked_add(add)
+        .with_context(|| format!("Offset 0x{offset:X} + 0x{add:X} overflows"))
+}
+
+fn format_value(bytes: &[u8], kind: FieldKind) -> String {
+    match kind {
+        FieldKind::U8 => bytes[0].to_string(),
+        FieldKind::U16 => u16::from_le_bytes([bytes[0], bytes[1]]).to_string(),
+        FieldKind::U32 => read_u32(bytes, 0).to_string(),
+        FieldKind::U64 => read_u64(bytes, 0).to_string(),
+        FieldKind::F32 => f32::from_le_bytes(bytes.try_into().unwrap()).to_string(),
+        FieldKind::Floats(_) => format!(
+            "[{}]",
+            bytes
+                .chunks_exact(4)
+                .map(|c| format!("{:.3}", f32::from_le_bytes(c.try_into().unwrap())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        FieldKind::TagHash => TagHash(read_u32(bytes, 0)).to_string(),
+        FieldKind::Array(_) => format!("{} elements", read_u64(bytes, 0)),
+        FieldKind::Bytes(_) => bytes.iter().map(|b| format!("{b:02X}")).collect(),
+    }
+}
+
+/// Whether `hash` points at an existing entry
+pub fn is_tag_reference(hash: TagHash) -> bool {
+    hash.is_pkg_file() && package_manager().get_entry(hash).is_some()
+}
+
+/// Finds 4-byte aligned values outside of `annotations` that resolve to existing tags
+pub fn scan_unknown_references(data: &[u8], annotations: &[Annotation]) -> Vec<Annotation> {
+    data.chunks_exact(4)
+        .enumerate()
+        .map(|(i, c)| (i * 4, TagHash(u32::from_le_bytes(c.try_into().unwrap()))))
+        .filter(|(offset, hash)| {
+            !annotations
+                .iter()
+                .any(|a| a.range.start < offset + 4 && *offset < a.range.end)
+                && is_tag_reference(*hash)
+        })
+        .map(|(offset, hash)| Annotation {
+            range: offset..offset + 4,
+            label: format!("unk{offset:x}"),
+            value: hash.to_string(),
+            kind: AnnotationKind::TagReference(hash),
+        })
+        .collect()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset.saturating_add(4))
+        .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    data.get(offset..offset.saturating_add(8))
+        .map_or(0, |b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+#[test]
+fn test_annotate_array_elements() {
+    // SOcclusionBounds with 2 bounds. The array header is right after the root structure
+    let mut data = vec![0u8; 0x18 + 0x10 + 2 * 0x30];
+    data[0x8..0x10].copy_from_slice(&2u64.to_le_bytes());
+    // Relative to the offset field at 0x10, pointing at the header at 0x18
+    data[0x10..0x18].copy_from_slice(&8u64.to_le_bytes());
+    data[0x20..0x24].copy_from_slice(&0x808093B3u32.to_le_bytes());
+
+    let annotations = annotate(&data, &S_OCCLUSION_BOUNDS).unwrap();
+    let header = annotations
+        .iter()
+        .find(|a| a.kind == AnnotationKind::ArrayHeader)
+        .unwrap();
+    assert_eq!(header.range, 0x18..0x28);
+    assert_eq!(header.value, "class 808093B3");
+
+    let second = annotations
+        .iter()
+        .find(|a| a.label == "bounds[1].unk20")
+        .unwrap();
+    assert_eq!(second.range.start, 0x28 + 0x30 + 0x20);
+}
+
+#[test]
+fn test_annotate_array_offset_overflow() {
+    let mut data = vec![0u8; 0x18];
+    data[0x8..0x10].copy_from_slice(&1u64.to_le_bytes());
+    data[0x10..0x18].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    assert!(annotate(&data, &S_OCCLUSION_BOUNDS).is_err());
+}
@@ -19,6 +19,70 @@ pub fn alk_color_to_egui(c: Color) -> Color32 {
     )
 }
 
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// `name` with anything that isn't safe in a file name replaced, including path separators and dots
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Scores how well `text` matches the search `query`, or `None` if it doesn't match at all.
+///
+/// Every whitespace-separated word of the query has to appear in `text` as a (case-insensitive)
+/// subsequence. Consecutive characters and characters at the start of a word score higher
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut total = 0;
+    for word in query.split_whitespace() {
+        let mut score = 0;
+        let mut position = 0;
+        let mut previous_match = None;
+        for q in word.chars().flat_map(char::to_lowercase) {
+            let offset = text[position..].iter().position(|&c| c == q)?;
+            let index = position + offset;
+
+            score += 1;
+            if previous_match == Some(index.wrapping_sub(1)) {
+                score += 5;
+            } else if previous_match.is_some() {
+                // Penalize gaps, but don't let long names bury a match
+                score -= offset.min(3) as i32;
+            }
+            if index == 0 || !text[index - 1].is_alphanumeric() {
+                score += 3;
+            }
+
+            previous_match = Some(index);
+            position = index + 1;
+        }
+        total += score;
+    }
+
+    Some(total)
+}
+
 // pub fn split_pascal_case(s: &str) -> String {
 //     let mut result = String::new();
 //     let mut last_upper = false;
@@ -35,3 +99,24 @@ pub fn alk_color_to_egui(c: Color) -> Color32 {
 //     }
 //     result
 // }
+
+#[test]
+fn test_fuzzy_score() {
+    assert!(fuzzy_score("", "anything").is_some());
+    assert!(fuzzy_score("eur", "Europa").is_some());
+    assert!(fuzzy_score("EUR", "europa").is_some());
+    assert!(fuzzy_score("europa ls", "Europa (europa_ls_eventide)").is_some());
+    assert!(fuzzy_score("xyz", "Europa").is_none());
+    assert!(fuzzy_score("ape", "Europa").is_none());
+
+    // Consecutive matches and word starts rank higher than scattered ones
+    assert!(fuzzy_score("dsc", "Deep Stone Crypt") > fuzzy_score("dsc", "dusk"));
+    assert!(fuzzy_score("throne", "The Throne World") > fuzzy_score("throne", "the hero nest"));
+}
+
+#[test]
+fn test_sanitize_file_name() {
+    assert_eq!(sanitize_file_name("Tower - top"), "Tower - top");
+    assert_eq!(sanitize_file_name("../up"), "___up");
+    assert_eq!(sanitize_file_name(r"a/b\c:d"), "a_b_c_d");
+}
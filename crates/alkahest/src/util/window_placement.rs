@@ -0,0 +1,157 @@
+//! Validation of the saved window placement against the monitors that are currently connected
+
+use crate::config::WindowConfig;
+
+/// Height of the area at the top of the window that has to be on a monitor for the window to be
+/// considered reachable (roughly the title bar)
+const TITLE_BAR_HEIGHT: i32 = 32;
+/// Minimum width of the title bar that has to be on a monitor for it to be grabbable
+const MIN_VISIBLE_WIDTH: i32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_monitor(monitor: &winit::monitor::MonitorHandle) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+        Self::new(position.x, position.y, size.width, size.height)
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    /// Size of the overlap between this monitor and the given rectangle, if any
+    fn intersection(&self, x: i32, y: i32, width: i32, height: i32) -> Option<(i32, i32)> {
+        let w = self.right().min(x + width) - self.x.max(x);
+        let h = self.bottom().min(y + height) - self.y.max(y);
+        (w > 0 && h > 0).then_some((w, h))
+    }
+
+    /// Squared distance from a point to the closest point on the monitor
+    fn distance_squared(&self, x: i32, y: i32) -> i64 {
+        let dx = (self.x - x).max(x - self.right()).max(0) as i64;
+        let dy = (self.y - y).max(y - self.bottom()).max(0) as i64;
+        dx * dx + dy * dy
+    }
+}
+
+/// Makes sure the saved window position is reachable with the current monitor layout, moving the
+/// window onto the nearest monitor when it isn't. Returns true if the config was changed
+pub fn validate_window_placement(window: &mut WindowConfig, monitors: &[MonitorRect]) -> bool {
+    // Without any monitor information there's nothing to validate against
+    if monitors.is_empty() {
+        return false;
+    }
+
+    let title_bar_visible = monitors.iter().any(|m| {
+        m.intersection(
+            window.pos_x,
+            window.pos_y,
+            window.width as i32,
+            TITLE_BAR_HEIGHT,
+        )
+        .is_some_and(|(w, _)| w >= MIN_VISIBLE_WIDTH.min(window.width as i32))
+    });
+
+    if title_bar_visible {
+        return false;
+    }
+
+    let center_x = window.pos_x + window.width as i32 / 2;
+    let center_y = window.pos_y + window.height as i32 / 2;
+    let nearest = monitors
+        .iter()
+        .min_by_key(|m| m.distance_squared(center_x, center_y))
+        .unwrap();
+
+    window.width = window.width.min(nearest.width);
+    window.height = window.height.min(nearest.height);
+    window.pos_x = window
+        .pos_x
+        .clamp(nearest.x, nearest.right() - window.width as i32);
+    window.pos_y = window
+        .pos_y
+        .clamp(nearest.y, nearest.bottom() - window.height as i32);
+    // The monitor the window was maximised on is gone
+    window.maximised = false;
+
+    true
+}
+
+#[test]
+fn test_window_placement() {
+    let window = |pos_x, pos_y, width, height| WindowConfig {
+        width,
+        height,
+        pos_x,
+        pos_y,
+        maximised: true,
+        fullscreen: false,
+    };
+
+    let single = [MonitorRect::new(0, 0, 1920, 1080)];
+    // Secondary monitor to the left of the primary one
+    let dual = [
+        MonitorRect::new(0, 0, 2560, 1440),
+        MonitorRect::new(-1920, 200, 1920, 1080),
+    ];
+
+    // Fully on screen, nothing changes
+    let mut w = window(100, 100, 1600, 900);
+    assert!(!validate_window_placement(&mut w, &single));
+    assert!(w.maximised);
+
+    // Partially off screen, but the title bar can still be grabbed
+    let mut w = window(1700, 500, 1600, 900);
+    assert!(!validate_window_placement(&mut w, &single));
+
+    // No monitor information
+    let mut w = window(-5000, -5000, 1600, 900);
+    assert!(!validate_window_placement(&mut w, &[]));
+
+    // Window was on the secondary monitor, which has been unplugged
+    let mut w = window(-1800, 300, 1600, 900);
+    assert!(!validate_window_placement(&mut w, &dual));
+    assert!(validate_window_placement(&mut w, &single));
+    assert_eq!((w.pos_x, w.pos_y, w.width, w.height), (0, 180, 1600, 900));
+    assert!(!w.maximised);
+
+    // Title bar above the top of the screen
+    let mut w = window(200, -500, 1280, 720);
+    assert!(validate_window_placement(&mut w, &single));
+    assert_eq!((w.pos_x, w.pos_y), (200, 0));
+
+    // Window larger than the only remaining monitor gets shrunk to fit
+    let mut w = window(3000, 0, 2560, 1440);
+    assert!(validate_window_placement(&mut w, &single));
+    assert_eq!((w.pos_x, w.pos_y, w.width, w.height), (0, 0, 1920, 1080));
+
+    // Monitors rearranged, the window is moved to the closest one
+    let rearranged = [
+        MonitorRect::new(0, 0, 1920, 1080),
+        MonitorRect::new(0, 1080, 1920, 1080),
+    ];
+    let mut w = window(2200, 1500, 1280, 720);
+    assert!(validate_window_placement(&mut w, &rearranged));
+    assert_eq!((w.pos_x, w.pos_y), (640, 1440));
+}
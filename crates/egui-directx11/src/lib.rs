@@ -7,6 +7,7 @@ mod shader;
 mod texture;
 
 pub use painter::*;
+pub use texture::TextureAllocator;
 
 #[derive(Debug, thiserror::Error)]
 pub enum RenderError {
@@ -43,6 +43,8 @@ use crate::{
 /// * [`Self::wnd_proc`] - Should be called on each `WndProc`.
 pub struct DirectX11Renderer {
     render_view: Option<ID3D11RenderTargetView>,
+    /// Render target to draw to instead of the swapchain backbuffer
+    target_override: Option<ID3D11RenderTargetView>,
     tex_alloc: TextureAllocator,
     input_layout: ID3D11InputLayout,
     shaders: CompiledShaders,
@@ -152,6 +154,7 @@ impl DirectX11Renderer {
                 backup: BackupState::default(),
                 input_layout,
                 render_view,
+                target_override: None,
                 shaders,
                 hwnd,
                 samplers,
@@ -205,7 +208,13 @@ impl DirectX11Renderer {
             self.set_raster_options(dev, ctx)?;
 
             ctx.RSSetViewports(Some(&[self.get_viewport()]));
-            ctx.OMSetRenderTargets(Some(&[self.render_view.clone()]), None);
+            ctx.OMSetRenderTargets(
+                Some(&[self
+                    .target_override
+                    .clone()
+                    .or_else(|| self.render_view.clone())]),
+                None,
+            );
             ctx.IASetPrimitiveTopology(D3D11_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
             ctx.IASetInputLayout(&self.input_layout);
 
@@ -273,6 +282,30 @@ impl DirectX11Renderer {
         }
     }
 
+    /// Draw to `target` instead of the swapchain backbuffer. The target must be the same size as the backbuffer
+    pub fn set_render_target(&mut self, target: Option<ID3D11RenderTargetView>) {
+        self.target_override = target;
+    }
+
+    /// Takes the textures out of the renderer, so they can be moved to a renderer for a new
+    /// swapchain with [`Self::init_with_textures`]
+    pub fn into_textures(self) -> TextureAllocator {
+        self.tex_alloc
+    }
+
+    /// Create a new renderer from a swapchain, keeping the textures of a previous renderer.
+    /// Textures are recreated on the device of the new swapchain
+    pub fn init_with_textures(
+        swapchain: &IDXGISwapChain,
+        mut textures: TextureAllocator,
+    ) -> Result<Self, RenderError> {
+        let mut renderer = Self::init_from_swapchain(swapchain)?;
+        let dev: ID3D11Device = unsafe { swapchain.GetDevice()? };
+        textures.recreate(&dev)?;
+        renderer.tex_alloc = textures;
+        Ok(renderer)
+    }
+
     pub fn textures(&self) -> &TextureAllocator {
         &self.tex_alloc
     }
@@ -1,6 +1,6 @@
-use std::{mem::size_of, slice::from_raw_parts_mut};
+use std::{mem::size_of, slice::from_raw_parts_mut, sync::Arc};
 
-use egui::{epaint::ahash::HashMap, Color32, ImageData, TextureId, TexturesDelta};
+use egui::{epaint::ahash::HashMap, Color32, ColorImage, ImageData, TextureId, TexturesDelta};
 use windows::Win32::Graphics::{
     Direct3D::D3D11_SRV_DIMENSION_TEXTURE2D,
     Direct3D11::{
@@ -80,6 +80,21 @@ impl TextureAllocator {
         }
     }
 
+    /// Recreates every managed texture on `dev` from its CPU copy, after the device they were
+    /// created on has been lost. User textures can't be recreated and are dropped
+    pub fn recreate(&mut self, dev: &ID3D11Device) -> Result<(), RenderError> {
+        self.allocated_unmanaged.clear();
+        for texture in self.allocated.values_mut() {
+            let image = ImageData::Color(Arc::new(ColorImage {
+                size: [texture.width, texture.pixels.len() / texture.width],
+                pixels: std::mem::take(&mut texture.pixels),
+            }));
+            *texture = Self::allocate_texture(dev, &image)?;
+        }
+
+        Ok(())
+    }
+
     pub fn free(&mut self, tid: TextureId) -> bool {
         self.allocated
             .remove(&tid)